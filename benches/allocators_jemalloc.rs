@@ -0,0 +1,22 @@
+//! Parsing throughput and allocations per stage with jemalloc, see `common/allocators.rs`.
+use bgpkit_parser::alloc_stats::CountingAllocator;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[path = "common/allocators.rs"]
+mod allocators;
+mod data_source;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator<tikv_jemallocator::Jemalloc> =
+    CountingAllocator::new(tikv_jemallocator::Jemalloc);
+
+fn criterion_benchmark(c: &mut Criterion) {
+    allocators::criterion_benchmark(c, "jemalloc");
+}
+
+criterion_group! {
+    name = benches;
+    config = allocators::config();
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! Parsing throughput and allocations per stage, shared by the `allocators*` benchmarks.
+//!
+//! Each benchmark installs a different global allocator wrapped in a [CountingAllocator]:
+//!
+//! ```text
+//! cargo bench --bench allocators --features alloc-stats
+//! cargo bench --bench allocators_mimalloc --features alloc-stats
+//! cargo bench --bench allocators_jemalloc --features alloc-stats
+//! ```
+//!
+//! [CountingAllocator]: bgpkit_parser::alloc_stats::CountingAllocator
+use bgpkit_parser::alloc_stats::{self, AllocStage};
+use bgpkit_parser::BgpkitParser;
+use bzip2::bufread::BzDecoder;
+use criterion::{black_box, Criterion};
+use flate2::bufread::GzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::time::Duration;
+
+/// Limit on the number of records, as the input data is decompressed into memory.
+const RECORD_LIMIT: usize = 100_000;
+
+fn load<R: Read>(mut reader: R) -> Vec<u8> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data).unwrap();
+    data
+}
+
+/// Parse the data once and print the allocations of each stage.
+fn print_alloc_stats(name: &str, allocator: &str, data: &[u8]) {
+    alloc_stats::reset();
+    let elems = BgpkitParser::from_reader(data)
+        .into_elem_iter()
+        .take(RECORD_LIMIT)
+        .count();
+    let stats = alloc_stats::snapshot();
+    println!("{} ({}, {} elems):", name, allocator, elems);
+    for stage in AllocStage::ALL {
+        let s = stats.get(stage);
+        println!(
+            "  {:?}: {} allocations, {} bytes, {:.1} allocations per elem",
+            stage,
+            s.count,
+            s.bytes,
+            s.count as f64 / elems.max(1) as f64
+        );
+    }
+}
+
+/// Print the allocations per stage and measure the parsing throughput with the global allocator
+/// of the benchmark, named `allocator` in the output.
+pub fn criterion_benchmark(c: &mut Criterion, allocator: &str) {
+    let update_data = crate::data_source::test_data_file("update-example.gz");
+    let rib_data = crate::data_source::test_data_file("rib-example-small.bz2");
+
+    let updates = load(GzDecoder::new(BufReader::new(
+        File::open(update_data).unwrap(),
+    )));
+    let rib_dump = load(BzDecoder::new(BufReader::new(
+        File::open(rib_data).unwrap(),
+    )));
+
+    print_alloc_stats("updates", allocator, &updates);
+    print_alloc_stats("rib", allocator, &rib_dump);
+
+    c.bench_function(&format!("updates into_elem_iter ({})", allocator), |b| {
+        b.iter(|| {
+            BgpkitParser::from_reader(black_box(&updates[..]))
+                .into_elem_iter()
+                .take(RECORD_LIMIT)
+                .for_each(|x| {
+                    black_box(x);
+                });
+        })
+    });
+
+    c.bench_function(&format!("rib into_elem_iter ({})", allocator), |b| {
+        b.iter(|| {
+            BgpkitParser::from_reader(black_box(&rib_dump[..]))
+                .into_elem_iter()
+                .take(RECORD_LIMIT)
+                .for_each(|x| {
+                    black_box(x);
+                });
+        })
+    });
+}
+
+/// Criterion configuration of the `allocators*` benchmarks.
+pub fn config() -> Criterion {
+    Criterion::default().measurement_time(Duration::from_secs(20))
+}
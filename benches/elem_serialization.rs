@@ -0,0 +1,73 @@
+//! Serialization of elems to JSON and to MessagePack with `BgpElem::to_bytes`.
+//!
+//! ```text
+//! cargo bench --bench elem_serialization --features msgpack
+//! ```
+use bgpkit_parser::{BgpElem, BgpkitParser};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::time::Duration;
+
+mod data_source;
+
+/// Number of elems serialized in each iteration.
+const ELEM_LIMIT: usize = 10_000;
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let update_data = data_source::test_data_file("update-example.gz");
+    let elems: Vec<BgpElem> = BgpkitParser::new(update_data.to_str().unwrap())
+        .unwrap()
+        .into_elem_iter()
+        .take(ELEM_LIMIT)
+        .collect();
+
+    let json: Vec<Vec<u8>> = elems
+        .iter()
+        .map(|e| serde_json::to_vec(e).unwrap())
+        .collect();
+    let msgpack: Vec<Vec<u8>> = elems.iter().map(|e| e.to_bytes().unwrap()).collect();
+    println!(
+        "{} elems: {} bytes as JSON, {} bytes as MessagePack",
+        elems.len(),
+        json.iter().map(|b| b.len()).sum::<usize>(),
+        msgpack.iter().map(|b| b.len()).sum::<usize>()
+    );
+
+    c.bench_function("elems to JSON", |b| {
+        b.iter(|| {
+            for elem in &elems {
+                black_box(serde_json::to_vec(black_box(elem)).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("elems to MessagePack", |b| {
+        b.iter(|| {
+            for elem in &elems {
+                black_box(black_box(elem).to_bytes().unwrap());
+            }
+        })
+    });
+
+    c.bench_function("elems from JSON", |b| {
+        b.iter(|| {
+            for bytes in &json {
+                black_box(serde_json::from_slice::<BgpElem>(black_box(bytes)).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("elems from MessagePack", |b| {
+        b.iter(|| {
+            for bytes in &msgpack {
+                black_box(BgpElem::from_bytes(black_box(bytes)).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(10));
+    targets = criterion_benchmark
+}
+criterion_main!(benches);
@@ -1,4 +1,5 @@
-use bgpkit_parser::BgpkitParser;
+use bgpkit_parser::{BgpkitParser, ParseContext};
+use bytes::Bytes;
 use bzip2::bufread::BzDecoder;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use flate2::bufread::GzDecoder;
@@ -90,6 +91,47 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    let updates_bytes = Bytes::from(updates.clone());
+    c.bench_function("updates from_bytes into_record_iter", |b| {
+        b.iter(|| {
+            BgpkitParser::from_bytes(black_box(updates_bytes.clone()))
+                .into_record_iter()
+                .take(RECORD_LIMIT)
+                .for_each(|x| {
+                    black_box(x);
+                });
+        })
+    });
+
+    let rib_bytes = Bytes::from(rib_dump.clone());
+    c.bench_function("rib from_bytes into_record_iter", |b| {
+        b.iter(|| {
+            BgpkitParser::from_bytes(black_box(rib_bytes.clone()))
+                .into_record_iter()
+                .take(RECORD_LIMIT)
+                .for_each(|x| {
+                    black_box(x);
+                });
+        })
+    });
+
+    c.bench_function("rib next_record_with_context", |b| {
+        b.iter(|| {
+            let mut reader = black_box(&rib_dump[..]);
+            let mut parser = BgpkitParser::from_reader(&mut reader);
+            let mut context = ParseContext::new();
+
+            for _ in 0..RECORD_LIMIT {
+                match parser.next_record_with_context(&mut context) {
+                    Ok(record) => {
+                        context.recycle(black_box(record));
+                    }
+                    Err(_) => break,
+                }
+            }
+        })
+    });
+
     c.bench_function("rib into_elem_iter", |b| {
         b.iter(|| {
             let mut reader = black_box(&rib_dump[..]);
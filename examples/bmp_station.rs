@@ -0,0 +1,17 @@
+//! Run a BMP station on port 11019 and print the elems received from connected routers.
+//!
+//! Run with `cargo run --example bmp_station --features bmp-station`.
+use bgpkit_parser::bmp::station::run_bmp_station;
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    run_bmp_station("0.0.0.0:11019", |session, elem| {
+        let router = session
+            .sys_name
+            .clone()
+            .unwrap_or_else(|| session.router_addr.to_string());
+        println!("{} {}", router, elem);
+    })
+    .unwrap();
+}
@@ -1,25 +1,62 @@
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::io::Write;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
 use std::net::IpAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use bgpkit_parser::{BgpElem, BgpkitParser, Elementor};
-use clap::Parser;
+use bgpkit_parser::analysis::Summary;
+use bgpkit_parser::encoder::{MrtAfiSplitEncoder, MrtRibEncoder, MrtUpdatesEncoder};
+use bgpkit_parser::export::JsonlWriter;
+use bgpkit_parser::models::{
+    attr_type_info, AsPath, Asn, Bgp4MpEnum, BgpMessage, EntryType, MrtMessage, OutputCompat,
+    TimestampFormat,
+};
+use bgpkit_parser::{
+    merge_mrt_files, ApproxElemStats, BgpElem, BgpkitParser, Elementor, Filter, Filterable,
+    MrtCensus, MrtIndex, MrtRecord, Pipeline, PipelineConfig,
+};
+use clap::{CommandFactory, Parser, Subcommand};
 use ipnet::IpNet;
 
 /// bgpkit-parser-cli is a simple cli tool that allow parsing of individual MRT files.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Opts {
+    #[clap(subcommand)]
+    command: Option<Commands>,
+
     /// File path to a MRT file, local or remote.
-    #[clap(name = "FILE")]
-    file_path: PathBuf,
+    #[clap(name = "FILE", required = true)]
+    file_path: Option<PathBuf>,
+
+    /// Path to the config file. Defaults to `$XDG_CONFIG_HOME/bgpkit/parser.toml` or `~/.config/bgpkit/parser.toml`.
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Print only errors to stderr, without the summary of parsing warnings
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print warnings as they occur instead of a summary at the end; repeat for debug (-vv) and trace (-vvv) messages
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
 
     /// Set the cache directory for caching remote files. Default behavior does not enable caching.
     #[clap(short, long)]
     cache_dir: Option<PathBuf>,
 
+    /// Print the shell completion script to stdout
+    #[clap(long, value_enum, value_name = "SHELL", exclusive = true)]
+    completions: Option<clap_complete::Shell>,
+
+    /// Print the man page in roff format to stdout
+    #[clap(long, exclusive = true)]
+    man: bool,
+
     /// Output as JSON objects
     #[clap(long)]
     json: bool,
@@ -32,6 +69,18 @@ struct Opts {
     #[clap(long)]
     pretty: bool,
 
+    /// Timestamp format of JSON and PSV output: float, micros (seconds and microseconds as separate fields), or rfc3339
+    #[clap(long)]
+    timestamp_format: Option<TimestampFormat>,
+
+    /// Keep the JSON fields of an older release: 0.10 or latest
+    #[clap(long)]
+    compat: Option<OutputCompat>,
+
+    /// Write elems as JSON Lines to a file instead of stdout, compressed according to its extension, e.g. `elems.jsonl.gz`. Cannot be combined with `--psv` or `--pretty`
+    #[clap(long, conflicts_with_all = ["psv", "pretty"])]
+    output: Option<PathBuf>,
+
     /// Count BGP elems
     #[clap(short, long)]
     elems_count: bool,
@@ -40,20 +89,292 @@ struct Opts {
     #[clap(short, long)]
     records_count: bool,
 
+    /// Print BGP NOTIFICATION messages with decoded error codes instead of elems
+    #[clap(long)]
+    notifications: bool,
+
+    /// Path to a YAML pipeline file with stages applied to the elems after the filters
+    #[clap(long)]
+    pipeline: Option<PathBuf>,
+
+    /// Write matching elems as MRT files split by address family, to `<SPLIT_AFI>.ipv4.mrt` and `<SPLIT_AFI>.ipv6.mrt`; no file is written for a family without elems
+    #[clap(long)]
+    split_afi: Option<PathBuf>,
+
+    /// Start parsing at the record with this index (from 0), using the index of the file built with the `index` subcommand
+    #[clap(long, conflicts_with = "start_timestamp")]
+    start_record: Option<u64>,
+
+    /// Start parsing at the first record at or after this Unix timestamp, using the index of the file built with the `index` subcommand
+    #[clap(long)]
+    start_timestamp: Option<u32>,
+
+    /// Path to the index of the file for --start-record and --start-timestamp. Defaults to `<FILE>.idx`
+    #[clap(long)]
+    index: Option<PathBuf>,
+
     #[clap(flatten)]
     filters: Filters,
 }
 
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Print the config file path and the effective configuration
+    Config,
+
+    /// Count elems and unique prefixes, origin ASNs, and AS paths across MRT files
+    Stats {
+        /// File paths to MRT files, local or remote
+        #[clap(name = "FILES", required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// Use bounded-memory approximate counting (HyperLogLog) for unique values
+        #[clap(long)]
+        approx: bool,
+
+        /// Set the cache directory for caching remote files. Default behavior does not enable caching.
+        #[clap(short, long)]
+        cache_dir: Option<PathBuf>,
+    },
+
+    /// Summarize elems per peer, prefix length and elem type, with announcement and withdrawal rates
+    Summary {
+        /// File paths to MRT files, local or remote
+        #[clap(name = "FILES", required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// Output as a JSON object
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Report MRT type/subtype and BGP attribute type distributions across MRT files
+    Census {
+        /// File paths to MRT files, local or remote
+        #[clap(name = "FILES", required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// Output as a JSON object
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Check MRT files for truncated records, invalid lengths, and other integrity problems
+    Validate {
+        /// File paths to MRT files, local or remote
+        #[clap(name = "FILES", required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// Output the reports as a JSON object keyed by file path
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Build the index of an MRT file, to start parsing it at a record or a timestamp
+    Index {
+        /// File path to a MRT file, local or remote
+        #[clap(name = "FILE")]
+        file_path: PathBuf,
+
+        /// Number of records between two entries of the index
+        #[clap(long, default_value_t = 1000)]
+        interval: u64,
+
+        /// Path of the index file. Defaults to `<FILE>.idx`, in the current directory for remote files
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Search the BGPKIT Broker for MRT files and print their URLs, one per line
+    Search {
+        /// Start of the time range, as a Unix timestamp or RFC 3339 time
+        #[clap(long)]
+        start_ts: String,
+
+        /// End of the time range, as a Unix timestamp or RFC 3339 time
+        #[clap(long)]
+        end_ts: String,
+
+        /// Collector ID, e.g. `rrc00` or `route-views2`
+        #[clap(long)]
+        collector: Option<String>,
+
+        /// Data type: rib or updates
+        #[clap(long)]
+        data_type: Option<String>,
+    },
+
+    /// Merge MRT update files into one timestamp-ordered MRT file
+    Merge {
+        /// File paths to MRT update files, local or remote
+        #[clap(name = "FILES", required = true)]
+        file_paths: Vec<PathBuf>,
+
+        /// Path of the merged file, compressed according to its extension
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+}
+
+/// Output format of the parsed BGP elems.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Plain,
+    Json,
+    Psv,
+}
+
+/// Configuration loaded from `parser.toml`.
+///
+/// Values set here act as defaults; command-line flags always take precedence.
+///
+/// ```toml
+/// cache_dir = "/tmp/bgpkit-cache"
+/// output_format = "json"
+/// timestamp_format = "rfc3339"
+/// compat = "0.10"
+/// broker_url = "https://api.bgpkit.com/v3/broker"
+///
+/// [filters]
+/// peer_asn = "13335"
+/// ip_version = "ipv4"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    /// Cache directory for remote files
+    cache_dir: Option<PathBuf>,
+    /// Default output format
+    output_format: Option<OutputFormat>,
+    /// Default timestamp format of JSON and PSV output
+    timestamp_format: Option<TimestampFormat>,
+    /// Default output compatibility version of JSON output
+    compat: Option<OutputCompat>,
+    /// BGPKIT Broker API URL used by the `search` subcommand
+    broker_url: Option<String>,
+    /// Default filters, keyed by filter type as accepted by `BgpkitParser::add_filter`
+    filters: BTreeMap<String, String>,
+}
+
+impl Config {
+    fn default_path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("bgpkit").join("parser.toml"))
+    }
+
+    /// Load the config file. A missing file at the default location yields the default config,
+    /// while a missing file given explicitly with `--config` is an error.
+    fn load(path: Option<&PathBuf>) -> Result<Config, String> {
+        let (path, explicit) = match path {
+            Some(p) => (p.clone(), true),
+            None => match Self::default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => {
+                return Ok(Config::default())
+            }
+            Err(e) => return Err(format!("cannot read config file {}: {}", path.display(), e)),
+        };
+        toml::from_str(&content)
+            .map_err(|e| format!("invalid config file {}: {}", path.display(), e))
+    }
+}
+
+/// BGPKIT Broker API URL used unless `broker_url` is set in the config file.
+const DEFAULT_BROKER_URL: &str = "https://api.bgpkit.com/v3/broker";
+
+/// Number of files requested per page of broker search results.
+const BROKER_PAGE_SIZE: usize = 1000;
+
+/// Page of BGPKIT Broker search results.
+#[derive(Deserialize, Debug)]
+struct BrokerSearchResult {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    data: Vec<BrokerItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BrokerItem {
+    url: String,
+}
+
+/// Percent-encode a query parameter value.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Print the URLs of the files matching the search, following the pages of results.
+fn run_search(broker_url: &str, params: &[(&str, &str)]) -> CliResult {
+    let query = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, encode_query_value(value)))
+        .join("&");
+    let mut page = 1;
+    loop {
+        let url = format!(
+            "{}/search?{}&page={}&page_size={}",
+            broker_url.trim_end_matches('/'),
+            query,
+            page,
+            BROKER_PAGE_SIZE
+        );
+        let result = oneio::get_reader(&url)
+            .map_err(|e| e.to_string())
+            .and_then(|reader| {
+                serde_json::from_reader::<_, BrokerSearchResult>(reader).map_err(|e| e.to_string())
+            });
+        let result = match result {
+            Ok(BrokerSearchResult { error: Some(e), .. }) | Err(e) => {
+                return Err(format!("{}: {}", broker_url, e).into());
+            }
+            Ok(result) => result,
+        };
+        for item in &result.data {
+            println!("{}", item.url);
+        }
+        if result.data.len() < BROKER_PAGE_SIZE {
+            return Ok(());
+        }
+        page += 1;
+    }
+}
+
 #[derive(Parser, Debug)]
 struct Filters {
     /// Filter by origin AS Number
     #[clap(short = 'o', long)]
     origin_asn: Option<u32>,
 
+    /// Filter by a file of origin AS numbers, one per line, matching any of them
+    #[clap(long)]
+    origin_asn_list: Option<PathBuf>,
+
     /// Filter by network prefix
     #[clap(short = 'p', long)]
     prefix: Option<IpNet>,
 
+    /// Filter by a file of network prefixes, one per line
+    #[clap(long)]
+    prefix_list: Option<PathBuf>,
+
     /// Include super-prefix when filtering
     #[clap(short = 's', long)]
     include_super: bool,
@@ -70,6 +391,18 @@ struct Filters {
     #[clap(short = '6', long)]
     ipv6_only: bool,
 
+    /// Filter by SAFI: unicast or multicast
+    #[clap(long)]
+    safi: Option<String>,
+
+    /// Filter BMP routes by RIB: adj-rib-in, adj-rib-out or loc-rib; MRT elems have no RIB side
+    #[clap(long)]
+    rib_side: Option<String>,
+
+    /// Filter BMP routes by policy stage: pre or post; MRT elems have no policy stage
+    #[clap(long)]
+    policy: Option<String>,
+
     /// Filter by peer IP address
     #[clap(short = 'j', long)]
     peer_ip: Vec<IpAddr>,
@@ -78,57 +411,642 @@ struct Filters {
     #[clap(short = 'J', long)]
     peer_asn: Option<u32>,
 
+    /// Filter by next hop IP address or prefix
+    #[clap(long)]
+    next_hop: Option<String>,
+
     /// Filter by elem type: announce (a) or withdraw (w)
     #[clap(short = 'm', long)]
     elem_type: Option<String>,
 
-    /// Filter by start unix timestamp inclusive
+    /// Filter by start unix timestamp or RFC 3339 time inclusive
     #[clap(short = 't', long)]
-    start_ts: Option<f64>,
+    start_ts: Option<String>,
 
-    /// Filter by end unix timestamp inclusive
+    /// Filter by end unix timestamp or RFC 3339 time inclusive
     #[clap(short = 'T', long)]
-    end_ts: Option<f64>,
+    end_ts: Option<String>,
 
     /// Filter by AS path regex string
     #[clap(short = 'a', long)]
     as_path: Option<String>,
 
-    /// Filter by AS path regex string
+    /// Filter by community, e.g. `13335:100` or `13335:*`, or by community regex string
     #[clap(short = 'C', long)]
     community: Option<String>,
 }
 
-fn main() {
-    let opts: Opts = Opts::parse();
+/// Replace the floating-point `timestamp` of a JSON elem according to the timestamp format.
+fn format_json_timestamp(val: &mut serde_json::Value, elem: &BgpElem, format: TimestampFormat) {
+    match format {
+        TimestampFormat::Float => {}
+        TimestampFormat::Micros => {
+            let (seconds, micros) = elem.timestamp_parts();
+            val["timestamp"] = json!(seconds);
+            val["timestamp_us"] = json!(micros);
+        }
+        TimestampFormat::Rfc3339 => {
+            val["timestamp"] = json!(elem.timestamp_rfc3339());
+        }
+    }
+}
 
-    env_logger::init();
+/// Write elems as JSON Lines to a file.
+fn run_output<I: Iterator<Item = BgpElem>>(
+    elems: I,
+    path: &Path,
+    compat: OutputCompat,
+    timestamp_format: TimestampFormat,
+) -> CliResult {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| format!("cannot write {}: path is not valid UTF-8", path.display()))?;
+    let result = JsonlWriter::create(path_str).and_then(|mut writer| {
+        for elem in elems {
+            let mut val = elem.to_json_compat(compat);
+            format_json_timestamp(&mut val, &elem, timestamp_format);
+            writer.write_json(&val)?;
+        }
+        writer.finish()
+    });
+    result.map_err(|e| CliError::from(format!("cannot write {}: {}", path.display(), e)))
+}
 
-    let file_path = opts.file_path.to_str().unwrap();
+fn load_pipeline(path: &Path) -> Result<Pipeline, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read pipeline file {}: {}", path.display(), e))?;
+    let config: PipelineConfig = serde_yaml::from_str(&content)
+        .map_err(|e| format!("invalid pipeline file {}: {}", path.display(), e))?;
+    Pipeline::from_config(&config)
+        .map_err(|e| format!("invalid pipeline file {}: {}", path.display(), e))
+}
 
-    let parser_opt = match opts.cache_dir {
+/// Add the filters to the parser, failing on invalid filters.
+fn add_filters<R>(
+    mut parser: BgpkitParser<R>,
+    filters: &[(String, String)],
+) -> CliResult<BgpkitParser<R>> {
+    for (filter_type, value) in filters {
+        parser = parser
+            .add_filter(filter_type, value)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(parser)
+}
+
+fn new_parser(
+    file_path: &str,
+    cache_dir: Option<&PathBuf>,
+) -> CliResult<BgpkitParser<Box<dyn Read + Send>>> {
+    let parser_opt = match cache_dir {
         None => BgpkitParser::new(file_path),
         Some(c) => BgpkitParser::new_cached(file_path, c.to_str().unwrap()),
     };
+    parser_opt.map_err(|e| CliError::from(e.to_string()))
+}
 
-    let mut parser = match parser_opt {
-        Ok(p) => p,
-        Err(err) => {
-            eprintln!("{}", err);
-            std::process::exit(1);
+/// Index path of a file: `<FILE>.idx`, in the current directory for remote files.
+fn default_index_path(file_path: &str) -> String {
+    match file_path.split_once("://") {
+        Some((_, rest)) => format!("{}.idx", rest.rsplit('/').next().unwrap_or(rest)),
+        None => format!("{}.idx", file_path),
+    }
+}
+
+fn new_indexed_parser(
+    file_path: &str,
+    index_path: Option<&PathBuf>,
+    record: Option<u64>,
+    timestamp: Option<u32>,
+) -> CliResult<BgpkitParser<Box<dyn Read + Send>>> {
+    let index_path = match index_path {
+        Some(p) => p.to_str().unwrap().to_string(),
+        None => default_index_path(file_path),
+    };
+    let index =
+        MrtIndex::read_from_path(&index_path).map_err(|e| format!("{}: {}", index_path, e))?;
+    let parser_opt = match (record, timestamp) {
+        (Some(record), _) => BgpkitParser::new_at_record(file_path, &index, record),
+        (None, timestamp) => {
+            BgpkitParser::new_at_timestamp(file_path, &index, timestamp.unwrap_or_default())
         }
     };
+    parser_opt.map_err(|e| CliError::from(e.to_string()))
+}
+
+fn run_index(file_path: &str, interval: u64, output: Option<&PathBuf>) -> CliResult {
+    let output = match output {
+        Some(p) => p.to_str().unwrap().to_string(),
+        None => default_index_path(file_path),
+    };
+    let result = oneio::get_reader(file_path)
+        .map_err(|e| e.to_string())
+        .and_then(|reader| MrtIndex::build(reader, interval).map_err(|e| e.to_string()))
+        .and_then(|index| {
+            index
+                .write_to_path(&output)
+                .map(|_| index)
+                .map_err(|e| format!("{}: {}", output, e))
+        });
+    let index = result.map_err(|e| format!("{}: {}", file_path, e))?;
+    println!(
+        "indexed {} records with {} entries to {}",
+        index.records,
+        index.entries.len(),
+        output
+    );
+    Ok(())
+}
+
+fn run_merge(file_paths: &[PathBuf], output: &Path) -> CliResult {
+    let inputs: Vec<&str> = file_paths.iter().map(|p| p.to_str().unwrap()).collect();
+    let output = output.to_str().unwrap();
+    let records = merge_mrt_files(&inputs, output).map_err(|e| format!("{}: {}", output, e))?;
+    println!("merged {} records to {}", records, output);
+    Ok(())
+}
+
+/// Count elems and unique values of the files, applying the default filters of the config file.
+fn run_stats(
+    file_paths: &[PathBuf],
+    approx: bool,
+    cache_dir: Option<&PathBuf>,
+    filters: &[(String, String)],
+) -> CliResult {
+    let parser = |file_path: &PathBuf| {
+        add_filters(new_parser(file_path.to_str().unwrap(), cache_dir)?, filters)
+    };
+    let (elems, prefixes, origins, as_paths) = if approx {
+        let mut stats = ApproxElemStats::new();
+        for file_path in file_paths {
+            stats.extend(parser(file_path)?);
+        }
+        (
+            stats.elems(),
+            stats.prefixes(),
+            stats.origins(),
+            stats.as_paths(),
+        )
+    } else {
+        let mut elems = 0;
+        let mut prefixes: HashSet<IpNet> = HashSet::new();
+        let mut origins: HashSet<Asn> = HashSet::new();
+        let mut as_paths: HashSet<AsPath> = HashSet::new();
+        for file_path in file_paths {
+            for elem in parser(file_path)? {
+                elems += 1;
+                prefixes.insert(elem.prefix.prefix);
+                origins.extend(elem.origin_asns.unwrap_or_default());
+                if let Some(path) = elem.as_path {
+                    as_paths.insert(path);
+                }
+            }
+        }
+        (
+            elems,
+            prefixes.len() as u64,
+            origins.len() as u64,
+            as_paths.len() as u64,
+        )
+    };
+    let approx_mark = if approx { "~" } else { "" };
+    println!("total elems:       {}", elems);
+    println!("unique prefixes:   {}{}", approx_mark, prefixes);
+    println!("unique origins:    {}{}", approx_mark, origins);
+    println!("unique AS paths:   {}{}", approx_mark, as_paths);
+    Ok(())
+}
+
+fn run_summary(file_paths: &[PathBuf], json: bool, cache_dir: Option<&PathBuf>) -> CliResult {
+    let mut summary = Summary::new();
+    for file_path in file_paths {
+        summary.extend(new_parser(file_path.to_str().unwrap(), cache_dir)?);
+    }
+    let report = summary.report();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return Ok(());
+    }
+
+    let rate = |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.2}/s", r));
+    println!("total elems:       {}", report.elems);
+    println!(
+        "announcements:     {} ({})",
+        report.announcements,
+        rate(report.announcement_rate)
+    );
+    println!(
+        "withdrawals:       {} ({})",
+        report.withdrawals,
+        rate(report.withdrawal_rate)
+    );
+    println!("unique prefixes:   {}", report.unique_prefixes);
+    println!("unique origins:    {}", report.unique_origins);
+    println!("peers:");
+    for (peer_ip, peer) in &report.peers {
+        println!(
+            "  {} AS{}: {} announcements, {} withdrawals",
+            peer_ip, peer.asn, peer.announcements, peer.withdrawals
+        );
+    }
+    for (family, lengths) in [
+        ("IPv4", &report.ipv4_prefix_lengths),
+        ("IPv6", &report.ipv6_prefix_lengths),
+    ] {
+        println!("{} prefix lengths:", family);
+        for (len, count) in lengths {
+            println!("  /{}: {}", len, count);
+        }
+    }
+    Ok(())
+}
 
+fn run_census(file_paths: &[PathBuf], json: bool) {
+    let mut census = MrtCensus::new();
+    for file_path in file_paths {
+        let file_path = file_path.to_str().unwrap();
+        let result = oneio::get_reader(file_path)
+            .map_err(|e| e.to_string())
+            .and_then(|mut reader| census.scan(&mut reader).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("{}: {}", file_path, e);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&census).unwrap());
+        return;
+    }
+
+    let type_name = |entry_type: u16| match EntryType::try_from(entry_type) {
+        Ok(t) => format!("{:?}({})", t, entry_type),
+        Err(_) => entry_type.to_string(),
+    };
+    println!("total records: {}", census.records);
+    println!("MRT types:");
+    for (entry_type, subtypes) in &census.record_types {
+        for (subtype, count) in subtypes {
+            println!("  {}/{}: {}", type_name(*entry_type), subtype, count);
+        }
+    }
+    println!("failed MRT types:");
+    for (entry_type, subtypes) in &census.failed_record_types {
+        for (subtype, count) in subtypes {
+            println!("  {}/{}: {}", type_name(*entry_type), subtype, count);
+        }
+    }
+    println!("attribute types:");
+    for (attr_type, count) in &census.attr_types {
+        let name = attr_type_info(*attr_type).map_or("UNASSIGNED", |info| info.name);
+        println!("  {}({}): {}", name, attr_type, count);
+    }
+    println!("undecoded attribute lengths:");
+    for (attr_type, lengths) in &census.raw_attr_lengths {
+        let histogram = lengths
+            .iter()
+            .map(|(len, count)| format!("{}B x {}", len, count))
+            .join(", ");
+        println!("  {}: {}", attr_type, histogram);
+    }
+}
+
+fn run_validate(file_paths: &[PathBuf], json: bool) -> CliResult {
+    let mut reports = BTreeMap::new();
+    let mut all_valid = true;
+    for file_path in file_paths {
+        let file_path = file_path.to_str().unwrap();
+        match bgpkit_parser::validate::check_file(file_path) {
+            Ok(report) => {
+                all_valid &= report.is_valid();
+                reports.insert(file_path, report);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file_path, e);
+                all_valid = false;
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for (file_path, report) in &reports {
+            for issue in &report.issues {
+                println!("{}: {}", file_path, issue);
+            }
+            println!("{}: {}", file_path, report);
+        }
+    }
+    match all_valid {
+        true => Ok(()),
+        // the issues are reported above
+        false => Err(CliError(None)),
+    }
+}
+
+/// Encode the elems of all records into per-AFI MRT files in a single pass.
+fn run_split_afi<R: Read>(
+    parser: BgpkitParser<R>,
+    filters: &[Filter],
+    output_prefix: &Path,
+) -> CliResult {
+    let mut rib_encoder = MrtAfiSplitEncoder::<MrtRibEncoder>::new();
+    let mut updates_encoder = MrtAfiSplitEncoder::<MrtUpdatesEncoder>::new();
+    let (mut has_rib, mut has_updates) = (false, false);
+    let mut elementor = Elementor::new();
+    for record in parser.into_record_iter() {
+        let is_rib = is_rib_record(&record);
+        for elem in elementor.record_to_elems(record) {
+            if !elem.match_filters(filters) {
+                continue;
+            }
+            if is_rib {
+                has_rib = true;
+                rib_encoder.process_elem(&elem);
+            } else {
+                has_updates = true;
+                updates_encoder.process_elem(&elem);
+            }
+        }
+    }
+
+    let mut ipv4_bytes = vec![];
+    let mut ipv6_bytes = vec![];
+    if has_rib {
+        let (ipv4, ipv6) = rib_encoder.export_bytes();
+        ipv4_bytes.extend(ipv4);
+        ipv6_bytes.extend(ipv6);
+    }
+    if has_updates {
+        let (ipv4, ipv6) = updates_encoder.export_bytes();
+        ipv4_bytes.extend(ipv4);
+        ipv6_bytes.extend(ipv6);
+    }
+
+    for (suffix, bytes) in [("ipv4.mrt", ipv4_bytes), ("ipv6.mrt", ipv6_bytes)] {
+        let mut path = output_prefix.as_os_str().to_owned();
+        path.push(".");
+        path.push(suffix);
+        let path = PathBuf::from(path);
+        if bytes.is_empty() {
+            // no elems of this address family
+            println!("{}: skipped, no matching elems", path.display());
+            continue;
+        }
+        std::fs::write(&path, &bytes)
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        println!("{}: {} bytes", path.display(), bytes.len());
+    }
+    Ok(())
+}
+
+/// Print BGP NOTIFICATION messages as `timestamp|peer_ip|peer_asn|error`.
+fn run_notifications<R: Read>(parser: BgpkitParser<R>) {
+    for record in parser.into_record_iter() {
+        if let MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) = &record.message {
+            if let BgpMessage::Notification(notification) = &msg.bgp_message {
+                let timestamp = match record.common_header.microsecond_timestamp {
+                    Some(micro) => {
+                        record.common_header.timestamp as f64 + micro as f64 / 1_000_000.0
+                    }
+                    None => record.common_header.timestamp as f64,
+                };
+                println!(
+                    "{}|{}|{}|{}",
+                    timestamp, msg.peer_ip, msg.peer_asn, notification.error
+                );
+            }
+        }
+    }
+}
+
+fn is_rib_record(record: &MrtRecord) -> bool {
+    matches!(
+        record.message,
+        MrtMessage::TableDumpMessage(_) | MrtMessage::TableDumpV2Message(_)
+    )
+}
+
+/// Logger printing log messages to stderr, optionally holding back warnings to print a summary of
+/// them at the end, so that warnings of malformed records do not interleave with the output.
+struct CliLogger {
+    inner: env_logger::Logger,
+    summarize_warnings: bool,
+    /// number of warnings and the first warning message per log target
+    warnings: Mutex<BTreeMap<String, (usize, String)>>,
+}
+
+impl CliLogger {
+    /// Set up the logger for the verbosity options. `RUST_LOG` takes precedence over the level
+    /// selected by the options.
+    fn init(quiet: bool, verbose: u8) -> &'static CliLogger {
+        let level = match (quiet, verbose) {
+            (true, _) => log::LevelFilter::Error,
+            (false, 0) => log::LevelFilter::Warn,
+            (false, 1) => log::LevelFilter::Info,
+            (false, 2) => log::LevelFilter::Debug,
+            (false, _) => log::LevelFilter::Trace,
+        };
+        let inner = env_logger::Builder::new()
+            .filter_level(level)
+            .parse_default_env()
+            .build();
+        let logger: &'static CliLogger = Box::leak(Box::new(CliLogger {
+            inner,
+            summarize_warnings: !quiet && verbose == 0,
+            warnings: Mutex::new(BTreeMap::new()),
+        }));
+        log::set_max_level(logger.inner.filter());
+        if log::set_logger(logger).is_err() {
+            eprintln!("Error: cannot set up logging");
+        }
+        logger
+    }
+
+    /// Print the summary of the held back warnings, if any.
+    fn print_summary(&self) {
+        let warnings = self.warnings.lock().unwrap();
+        if warnings.is_empty() {
+            return;
+        }
+        let total: usize = warnings.values().map(|(count, _)| count).sum();
+        eprintln!(
+            "{} warning(s) while parsing, rerun with -v to print them as they occur:",
+            total
+        );
+        for (target, (count, first)) in warnings.iter() {
+            eprintln!("  {} from {}, first: {}", count, target, first);
+        }
+    }
+}
+
+impl log::Log for CliLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        if self.summarize_warnings && record.level() == log::Level::Warn {
+            let mut warnings = self.warnings.lock().unwrap();
+            warnings
+                .entry(record.target().to_string())
+                .or_insert_with(|| (0, record.args().to_string()))
+                .0 += 1;
+            return;
+        }
+        self.inner.log(record)
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+/// Error ending the program with exit status 1. The message is printed to stderr; errors without
+/// message have been reported already, e.g. the issues of invalid files.
+struct CliError(Option<String>);
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError(Some(message))
+    }
+}
+
+type CliResult<T = ()> = Result<T, CliError>;
+
+fn main() {
+    let opts: Opts = Opts::parse();
+
+    let logger = CliLogger::init(opts.quiet, opts.verbose);
+    let result = run(opts);
+    if let Err(CliError(Some(message))) = &result {
+        eprintln!("{}", message);
+    }
+    // the summary of warnings is also printed for failed runs, as the warnings may explain them
+    logger.print_summary();
+    if result.is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// Load the config file. Only commands using the config load it, so that e.g. `--completions`
+/// still works with an invalid config file.
+fn load_config(path: Option<&PathBuf>) -> CliResult<Config> {
+    Config::load(path).map_err(|e| CliError::from(format!("Error: {}", e)))
+}
+
+fn run(opts: Opts) -> CliResult {
+    if let Some(shell) = opts.completions {
+        clap_complete::generate(
+            shell,
+            &mut Opts::command(),
+            "bgpkit-parser",
+            &mut std::io::stdout(),
+        );
+        return Ok(());
+    }
+    if opts.man {
+        let man = clap_mangen::Man::new(Opts::command());
+        return man
+            .render(&mut std::io::stdout())
+            .map_err(|e| CliError::from(e.to_string()));
+    }
+
+    match opts.command {
+        Some(Commands::Config) => {
+            let config = load_config(opts.config.as_ref())?;
+            let path = opts.config.clone().or_else(Config::default_path);
+            match path {
+                Some(p) => println!("# {}", p.display()),
+                None => println!("# no config file location available"),
+            }
+            print!("{}", toml::to_string_pretty(&config).unwrap());
+            return Ok(());
+        }
+        Some(Commands::Stats {
+            file_paths,
+            approx,
+            cache_dir,
+        }) => {
+            let config = load_config(opts.config.as_ref())?;
+            let cache_dir = cache_dir.or(config.cache_dir);
+            let filters: Vec<(String, String)> = config.filters.into_iter().collect();
+            return run_stats(&file_paths, approx, cache_dir.as_ref(), &filters);
+        }
+        Some(Commands::Summary { file_paths, json }) => {
+            let config = load_config(opts.config.as_ref())?;
+            return run_summary(&file_paths, json, config.cache_dir.as_ref());
+        }
+        Some(Commands::Census { file_paths, json }) => {
+            run_census(&file_paths, json);
+            return Ok(());
+        }
+        Some(Commands::Validate { file_paths, json }) => {
+            return run_validate(&file_paths, json);
+        }
+        Some(Commands::Index {
+            file_path,
+            interval,
+            output,
+        }) => {
+            return run_index(file_path.to_str().unwrap(), interval, output.as_ref());
+        }
+        Some(Commands::Search {
+            start_ts,
+            end_ts,
+            collector,
+            data_type,
+        }) => {
+            let config = load_config(opts.config.as_ref())?;
+            let broker_url = config.broker_url.as_deref().unwrap_or(DEFAULT_BROKER_URL);
+            let mut params = vec![("ts_start", start_ts.as_str()), ("ts_end", end_ts.as_str())];
+            if let Some(collector) = &collector {
+                params.push(("collector_id", collector));
+            }
+            if let Some(data_type) = &data_type {
+                params.push(("data_type", data_type));
+            }
+            return run_search(broker_url, &params);
+        }
+        Some(Commands::Merge { file_paths, output }) => {
+            return run_merge(&file_paths, &output);
+        }
+        None => {}
+    }
+
+    let config = load_config(opts.config.as_ref())?;
+
+    // FILE is required when no subcommand is given, enforced by clap
+    let file_path = opts.file_path.unwrap();
+    let file_path = file_path.to_str().unwrap();
+
+    let cache_dir = opts.cache_dir.or(config.cache_dir);
+    let mut parser = match (opts.start_record, opts.start_timestamp) {
+        (None, None) => new_parser(file_path, cache_dir.as_ref())?,
+        (record, timestamp) => {
+            new_indexed_parser(file_path, opts.index.as_ref(), record, timestamp)?
+        }
+    };
+
+    let mut cli_filters: Vec<(String, String)> = vec![];
     if let Some(v) = opts.filters.as_path {
-        parser = parser.add_filter("as_path", v.as_str()).unwrap();
+        cli_filters.push(("as_path".to_string(), v));
     }
     if let Some(v) = opts.filters.community {
-        parser = parser.add_filter("community", v.as_str()).unwrap();
+        cli_filters.push(("community".to_string(), v));
     }
     if let Some(v) = opts.filters.origin_asn {
-        parser = parser
-            .add_filter("origin_asn", v.to_string().as_str())
-            .unwrap();
+        cli_filters.push(("origin_asn".to_string(), v.to_string()));
+    }
+    if let Some(v) = opts.filters.origin_asn_list {
+        cli_filters.push((
+            "origin_asn_list".to_string(),
+            v.to_string_lossy().to_string(),
+        ));
     }
     if let Some(v) = opts.filters.prefix {
         let filter_type = match (opts.filters.include_super, opts.filters.include_sub) {
@@ -137,90 +1055,162 @@ fn main() {
             (false, true) => "prefix_sub",
             (true, true) => "prefix_super_sub",
         };
-        parser = parser
-            .add_filter(filter_type, v.to_string().as_str())
-            .unwrap();
+        cli_filters.push((filter_type.to_string(), v.to_string()));
+    }
+    if let Some(v) = opts.filters.prefix_list {
+        let filter_type = match (opts.filters.include_super, opts.filters.include_sub) {
+            (false, false) => "prefix_list",
+            (true, false) => "prefix_list_super",
+            (false, true) => "prefix_list_sub",
+            (true, true) => "prefix_list_super_sub",
+        };
+        cli_filters.push((filter_type.to_string(), v.to_string_lossy().to_string()));
     }
     if !opts.filters.peer_ip.is_empty() {
         let v = opts.filters.peer_ip.iter().map(|p| p.to_string()).join(",");
-        parser = parser.add_filter("peer_ips", v.as_str()).unwrap();
+        cli_filters.push(("peer_ips".to_string(), v));
     }
     if let Some(v) = opts.filters.peer_asn {
-        parser = parser
-            .add_filter("peer_asn", v.to_string().as_str())
-            .unwrap();
+        cli_filters.push(("peer_asn".to_string(), v.to_string()));
+    }
+    if let Some(v) = opts.filters.next_hop {
+        cli_filters.push(("next_hop".to_string(), v));
     }
     if let Some(v) = opts.filters.elem_type {
-        parser = parser.add_filter("type", v.as_str()).unwrap();
+        cli_filters.push(("type".to_string(), v));
     }
     if let Some(v) = opts.filters.start_ts {
-        parser = parser
-            .add_filter("start_ts", v.to_string().as_str())
-            .unwrap();
+        cli_filters.push(("start_ts".to_string(), v));
     }
     if let Some(v) = opts.filters.end_ts {
-        parser = parser.add_filter("end_ts", v.to_string().as_str()).unwrap();
+        cli_filters.push(("end_ts".to_string(), v));
     }
 
     match (opts.filters.ipv4_only, opts.filters.ipv6_only) {
         (true, true) => {
-            eprintln!("Error: --ipv4-only and --ipv6-only cannot be used together");
-            std::process::exit(1);
+            return Err("Error: --ipv4-only and --ipv6-only cannot be used together"
+                .to_string()
+                .into());
         }
         (false, false) => {
             // no filters on IP version, skip
         }
         (true, false) => {
-            parser = parser.add_filter("ip_version", "ipv4").unwrap();
+            cli_filters.push(("ip_version".to_string(), "ipv4".to_string()));
         }
         (false, true) => {
-            parser = parser.add_filter("ip_version", "ipv6").unwrap();
+            cli_filters.push(("ip_version".to_string(), "ipv6".to_string()));
         }
     }
 
+    if let Some(v) = opts.filters.safi {
+        cli_filters.push(("safi".to_string(), v));
+    }
+    if let Some(v) = opts.filters.rib_side {
+        cli_filters.push(("rib_side".to_string(), v));
+    }
+    if let Some(v) = opts.filters.policy {
+        cli_filters.push(("policy".to_string(), v));
+    }
+
+    // filters from the config file apply unless the same filter type is given on the command line
+    let mut filters = cli_filters;
+    for (filter_type, value) in &config.filters {
+        if !filters.iter().any(|(t, _)| t == filter_type) {
+            filters.push((filter_type.clone(), value.clone()));
+        }
+    }
+    parser = add_filters(parser, &filters)?;
+
+    if opts.notifications {
+        run_notifications(parser);
+        return Ok(());
+    }
+
+    if let Some(output_prefix) = opts.split_afi {
+        // filters are validated when added to the parser above
+        let filters: Vec<Filter> = filters
+            .iter()
+            .map(|(filter_type, value)| Filter::new(filter_type, value).unwrap())
+            .collect();
+        return run_split_afi(parser, &filters, &output_prefix);
+    }
+
+    let mut pipeline = match &opts.pipeline {
+        Some(path) => load_pipeline(path)?,
+        None => Pipeline::new(),
+    };
+
     match (opts.elems_count, opts.records_count) {
         (true, true) => {
             let mut elementor = Elementor::new();
             let (mut records_count, mut elems_count) = (0, 0);
             for record in parser.into_record_iter() {
                 records_count += 1;
-                elems_count += elementor.record_to_elems(record).len();
+                elems_count += elementor
+                    .record_to_elems(record)
+                    .into_iter()
+                    .filter_map(|elem| pipeline.process(elem))
+                    .count();
             }
             println!("total records: {}", records_count);
             println!("total elems:   {}", elems_count);
         }
         (false, true) => {
-            println!("total records: {}", parser.into_record_iter().count());
+            // elem filters need the parsed records, otherwise only the headers are read
+            let count = match filters.is_empty() {
+                true => parser.count_records(),
+                false => parser.into_record_iter().count() as u64,
+            };
+            println!("total records: {}", count);
         }
         (true, false) => {
-            println!("total records: {}", parser.into_elem_iter().count());
+            println!("total records: {}", pipeline.apply(parser).count());
         }
         (false, false) => {
+            let output_format = match (opts.json, opts.psv) {
+                (true, _) => OutputFormat::Json,
+                (false, true) => OutputFormat::Psv,
+                (false, false) => config.output_format.unwrap_or(OutputFormat::Plain),
+            };
+            let timestamp_format = opts
+                .timestamp_format
+                .or(config.timestamp_format)
+                .unwrap_or_default();
+            let compat = opts.compat.or(config.compat).unwrap_or_default();
+            if let Some(path) = &opts.output {
+                return run_output(pipeline.apply(parser), path, compat, timestamp_format);
+            }
             let mut stdout = std::io::stdout();
-            for (index, elem) in parser.into_elem_iter().enumerate() {
-                let output_str = if opts.json {
-                    let val = json!(elem);
+            for (index, elem) in pipeline.apply(parser).enumerate() {
+                let output_str = if output_format == OutputFormat::Json {
+                    let mut val = elem.to_json_compat(compat);
+                    format_json_timestamp(&mut val, &elem, timestamp_format);
                     if opts.pretty {
                         serde_json::to_string_pretty(&val).unwrap()
                     } else {
                         val.to_string()
                     }
-                } else if opts.psv {
+                } else if output_format == OutputFormat::Psv {
+                    let psv = elem.to_psv_with_timestamp(timestamp_format);
                     if index == 0 {
-                        format!("{}\n{}", BgpElem::get_psv_header(), elem.to_psv())
+                        let header = BgpElem::get_psv_header_with_timestamp(timestamp_format);
+                        format!("{}\n{}", header, psv)
                     } else {
-                        elem.to_psv()
+                        psv
                     }
                 } else {
                     elem.to_string()
                 };
                 if let Err(e) = writeln!(stdout, "{}", &output_str) {
-                    if e.kind() != std::io::ErrorKind::BrokenPipe {
-                        eprintln!("{}", e);
-                    }
-                    std::process::exit(1);
+                    return match e.kind() {
+                        // the reader of the output is gone, e.g. `head`
+                        std::io::ErrorKind::BrokenPipe => Err(CliError(None)),
+                        _ => Err(e.to_string().into()),
+                    };
                 }
             }
         }
     }
+    Ok(())
 }
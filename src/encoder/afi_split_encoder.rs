@@ -0,0 +1,150 @@
+#[cfg(feature = "oneio")]
+use crate::encoder::EncoderError;
+use crate::encoder::MrtEncoder;
+use crate::BgpElem;
+use bytes::Bytes;
+use ipnet::IpNet;
+
+/// Encoder that splits the processed elems into separate IPv4 and IPv6 MRT outputs in one pass.
+///
+/// Each address family is encoded by its own inner encoder `E`, e.g. [MrtUpdatesEncoder] or
+/// [MrtRibEncoder], based on the address family of the elem's prefix. An address family without
+/// any elems has no output, rather than e.g. a RIB dump with only a peer index table.
+///
+/// [MrtUpdatesEncoder]: crate::encoder::MrtUpdatesEncoder
+/// [MrtRibEncoder]: crate::encoder::MrtRibEncoder
+#[derive(Default)]
+pub struct MrtAfiSplitEncoder<E> {
+    ipv4: E,
+    ipv6: E,
+    has_ipv4: bool,
+    has_ipv6: bool,
+}
+
+impl<E: MrtEncoder + Default> MrtAfiSplitEncoder<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn process_elem(&mut self, elem: &BgpElem) {
+        match elem.prefix.prefix {
+            IpNet::V4(_) => {
+                self.has_ipv4 = true;
+                self.ipv4.process_elem(elem)
+            }
+            IpNet::V6(_) => {
+                self.has_ipv6 = true;
+                self.ipv6.process_elem(elem)
+            }
+        }
+    }
+
+    /// Returns true if IPv4 and IPv6 elems were processed since the last export, respectively.
+    pub fn has_elems(&self) -> (bool, bool) {
+        (self.has_ipv4, self.has_ipv6)
+    }
+
+    /// Export the processed elems as a pair of `(ipv4, ipv6)` MRT bytes. The bytes of an address
+    /// family without elems are empty.
+    ///
+    /// Both inner encoders are reset afterwards.
+    pub fn export_bytes(&mut self) -> (Bytes, Bytes) {
+        let ipv4 = self.ipv4.export_bytes();
+        let ipv6 = self.ipv6.export_bytes();
+        let ipv4 = match std::mem::take(&mut self.has_ipv4) {
+            true => ipv4,
+            false => Bytes::new(),
+        };
+        let ipv6 = match std::mem::take(&mut self.has_ipv6) {
+            true => ipv6,
+            false => Bytes::new(),
+        };
+        (ipv4, ipv6)
+    }
+
+    /// Export the processed IPv4 and IPv6 elems to two files, compressed according to their
+    /// extensions. No file is written for an address family without elems.
+    ///
+    /// Both inner encoders are reset afterwards, even if writing the IPv4 file fails.
+    #[cfg(feature = "oneio")]
+    pub fn export_to_paths(
+        &mut self,
+        ipv4_path: &str,
+        ipv6_path: &str,
+    ) -> Result<(), EncoderError> {
+        let ipv4 = match std::mem::take(&mut self.has_ipv4) {
+            true => self.ipv4.export_to_path(ipv4_path),
+            false => Ok(()),
+        };
+        let ipv6 = match std::mem::take(&mut self.has_ipv6) {
+            true => self.ipv6.export_to_path(ipv6_path),
+            false => Ok(()),
+        };
+        ipv4.and(ipv6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::{MrtRibEncoder, MrtUpdatesEncoder};
+    use crate::models::Asn;
+    use crate::parse_mrt_record;
+    use bytes::Buf;
+    use std::io::Cursor;
+    use std::net::IpAddr;
+
+    fn count_records(bytes: Bytes) -> usize {
+        let mut cursor = Cursor::new(bytes);
+        let mut count = 0;
+        while cursor.has_remaining() {
+            parse_mrt_record(&mut cursor).unwrap();
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_afi_split_encoder() {
+        let mut encoder = MrtAfiSplitEncoder::<MrtUpdatesEncoder>::new();
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            ..Default::default()
+        };
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        elem.prefix.prefix = "10.251.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        elem.prefix.prefix = "2001:db8::/32".parse().unwrap();
+        encoder.process_elem(&elem);
+
+        let (ipv4, ipv6) = encoder.export_bytes();
+        assert_eq!(count_records(ipv4), 2);
+        assert_eq!(count_records(ipv6), 1);
+
+        // encoders are reset after export
+        let (ipv4, ipv6) = encoder.export_bytes();
+        assert!(ipv4.is_empty());
+        assert!(ipv6.is_empty());
+    }
+
+    #[test]
+    fn test_afi_split_encoder_missing_family() {
+        let mut encoder = MrtAfiSplitEncoder::<MrtRibEncoder>::new();
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            ..Default::default()
+        };
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        assert_eq!(encoder.has_elems(), (true, false));
+
+        // no peer index table without IPv6 elems
+        let (ipv4, ipv6) = encoder.export_bytes();
+        assert_eq!(count_records(ipv4), 2);
+        assert!(ipv6.is_empty());
+        assert_eq!(encoder.has_elems(), (false, false));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::models::MrtRecord;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Errors reported by the encoders when verification is enabled or writing the output fails.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncoderError {
+    /// The encoded bytes of the record at `index` cannot be parsed back.
+    ReparseFailed { index: usize, error: String },
+    /// The record at `index` parsed back to a different record than the source.
+    Mismatch {
+        index: usize,
+        expected: Box<MrtRecord>,
+        actual: Box<MrtRecord>,
+    },
+    /// The encoded records cannot be written to the output.
+    WriteFailed { error: String },
+}
+
+impl Display for EncoderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncoderError::ReparseFailed { index, error } => {
+                write!(f, "cannot re-parse encoded record #{}: {}", index, error)
+            }
+            EncoderError::Mismatch { index, .. } => {
+                write!(
+                    f,
+                    "encoded record #{} does not match the source record",
+                    index
+                )
+            }
+            EncoderError::WriteFailed { error } => {
+                write!(f, "cannot write encoded records: {}", error)
+            }
+        }
+    }
+}
+
+impl Error for EncoderError {}
@@ -1,5 +1,235 @@
+mod afi_split_encoder;
+mod error;
 mod rib_encoder;
+mod splitter;
 mod updates_encoder;
 
+pub use afi_split_encoder::MrtAfiSplitEncoder;
+pub use error::EncoderError;
 pub use rib_encoder::MrtRibEncoder;
+pub use splitter::{MrtSplitter, SplitBy, SplitOutput};
 pub use updates_encoder::MrtUpdatesEncoder;
+
+use crate::models::MrtRecord;
+use crate::parse_mrt_record;
+use crate::BgpElem;
+use bytes::Bytes;
+use std::io::{Cursor, Write};
+
+/// Common interface of the elem-based MRT encoders.
+pub trait MrtEncoder {
+    /// Add a [BgpElem] to be encoded.
+    fn process_elem(&mut self, elem: &BgpElem);
+
+    /// Export the processed elems as MRT bytes and reset the encoder.
+    fn export_bytes(&mut self) -> Bytes;
+
+    /// Export the processed elems to a file, compressed according to its extension, and reset the
+    /// encoder.
+    ///
+    /// The default implementation writes the result of [MrtEncoder::export_bytes]; encoders
+    /// override it to stream their records to the file instead.
+    #[cfg(feature = "oneio")]
+    fn export_to_path(&mut self, path: &str) -> Result<(), EncoderError> {
+        let bytes = self.export_bytes();
+        write_to_path(path, |writer| write_record(writer, &bytes))
+    }
+}
+
+impl MrtEncoder for MrtRibEncoder {
+    fn process_elem(&mut self, elem: &BgpElem) {
+        MrtRibEncoder::process_elem(self, elem)
+    }
+
+    fn export_bytes(&mut self) -> Bytes {
+        MrtRibEncoder::export_bytes(self)
+    }
+
+    #[cfg(feature = "oneio")]
+    fn export_to_path(&mut self, path: &str) -> Result<(), EncoderError> {
+        MrtRibEncoder::export_to_path(self, path)
+    }
+}
+
+impl MrtEncoder for MrtUpdatesEncoder {
+    fn process_elem(&mut self, elem: &BgpElem) {
+        MrtUpdatesEncoder::process_elem(self, elem)
+    }
+
+    fn export_bytes(&mut self) -> Bytes {
+        MrtUpdatesEncoder::export_bytes(self)
+    }
+
+    #[cfg(feature = "oneio")]
+    fn export_to_path(&mut self, path: &str) -> Result<(), EncoderError> {
+        MrtUpdatesEncoder::export_to_path(self, path)
+    }
+}
+
+/// Re-parse the encoded bytes of a single record and compare it with the source record.
+pub(crate) fn verify_encoded_record(
+    index: usize,
+    record: &MrtRecord,
+    bytes: &[u8],
+) -> Result<(), EncoderError> {
+    let parsed =
+        parse_mrt_record(&mut Cursor::new(bytes)).map_err(|e| EncoderError::ReparseFailed {
+            index,
+            error: e.to_string(),
+        })?;
+    if parsed != *record {
+        return Err(EncoderError::Mismatch {
+            index,
+            expected: Box::new(record.clone()),
+            actual: Box::new(parsed),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn write_record<W: Write + ?Sized>(
+    writer: &mut W,
+    bytes: &[u8],
+) -> Result<(), EncoderError> {
+    writer
+        .write_all(bytes)
+        .map_err(|e| EncoderError::WriteFailed {
+            error: e.to_string(),
+        })
+}
+
+/// Writer of a file, compressed according to its extension.
+///
+/// Unlike the writers of oneio, gzip and bzip2 outputs are completed by [CompressedFile::finish],
+/// so that errors writing their trailers are returned instead of being lost when the writer is
+/// dropped. Other compressions supported by oneio are finished when the writer is dropped.
+#[cfg(feature = "oneio")]
+pub struct CompressedFile {
+    output: CompressedOutput,
+}
+
+#[cfg(feature = "oneio")]
+enum CompressedOutput {
+    Gzip(flate2::write::GzEncoder<std::io::BufWriter<std::fs::File>>),
+    Bzip2(bzip2::write::BzEncoder<std::io::BufWriter<std::fs::File>>),
+    Other(Box<dyn Write>),
+}
+
+#[cfg(feature = "oneio")]
+impl CompressedFile {
+    /// Create the file, and its parent directories for gzip and bzip2 outputs.
+    pub fn create(path: &str) -> std::io::Result<CompressedFile> {
+        let open_failed = |e: oneio::OneIoError| std::io::Error::other(format!("{}: {}", path, e));
+        let output = match path.rsplit('.').next() {
+            Some("gz" | "gzip" | "tgz") => CompressedOutput::Gzip(flate2::write::GzEncoder::new(
+                oneio::get_writer_raw(path).map_err(open_failed)?,
+                flate2::Compression::default(),
+            )),
+            Some("bz2" | "bz") => CompressedOutput::Bzip2(bzip2::write::BzEncoder::new(
+                oneio::get_writer_raw(path).map_err(open_failed)?,
+                bzip2::Compression::default(),
+            )),
+            _ => CompressedOutput::Other(oneio::get_writer(path).map_err(open_failed)?),
+        };
+        Ok(CompressedFile { output })
+    }
+
+    /// Write the trailer of gzip and bzip2 outputs and flush the file.
+    ///
+    /// Nothing can be written after the file is finished.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        match &mut self.output {
+            CompressedOutput::Gzip(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()
+            }
+            CompressedOutput::Bzip2(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()
+            }
+            CompressedOutput::Other(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(feature = "oneio")]
+impl Write for CompressedFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match &mut self.output {
+            CompressedOutput::Gzip(encoder) => encoder.write(buf),
+            CompressedOutput::Bzip2(encoder) => encoder.write(buf),
+            CompressedOutput::Other(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.output {
+            CompressedOutput::Gzip(encoder) => encoder.flush(),
+            CompressedOutput::Bzip2(encoder) => encoder.flush(),
+            CompressedOutput::Other(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Open a [CompressedFile] for the path, let `write` fill it and finish it.
+#[cfg(feature = "oneio")]
+pub(crate) fn write_to_path<F>(path: &str, write: F) -> Result<(), EncoderError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), EncoderError>,
+{
+    let mut file = CompressedFile::create(path).map_err(|e| EncoderError::WriteFailed {
+        error: e.to_string(),
+    })?;
+    write(&mut file)?;
+    file.finish().map_err(|e| EncoderError::WriteFailed {
+        error: format!("{}: {}", path, e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encoder relying on the default [MrtEncoder::export_to_path].
+    #[derive(Default)]
+    struct WrappedEncoder(MrtUpdatesEncoder);
+
+    impl MrtEncoder for WrappedEncoder {
+        fn process_elem(&mut self, elem: &BgpElem) {
+            self.0.process_elem(elem)
+        }
+
+        fn export_bytes(&mut self) -> Bytes {
+            self.0.export_bytes()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "oneio")]
+    fn test_default_export_to_path() {
+        let mut encoder = WrappedEncoder::default();
+        let mut elem = BgpElem::default();
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+
+        let file = tempfile::Builder::new()
+            .suffix(".mrt.bz2")
+            .tempfile()
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+        encoder.export_to_path(path).unwrap();
+        let elems: Vec<BgpElem> = crate::BgpkitParser::new(path)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(elems.len(), 1);
+        assert!(encoder.export_bytes().is_empty());
+
+        // the parent of the output is a file, not a directory
+        let path = file.path().join("out.mrt.gz");
+        assert!(matches!(
+            encoder.export_to_path(path.to_str().unwrap()),
+            Err(EncoderError::WriteFailed { .. })
+        ));
+    }
+}
@@ -4,23 +4,34 @@
 //! difficulty part of this process is the handling of TableDumpV2 RIB dumps, which requires
 //! reconstructing the peer index table before encoding all other contents.
 
+#[cfg(feature = "oneio")]
+use crate::encoder::write_to_path;
+use crate::encoder::{verify_encoded_record, write_record, EncoderError};
 use crate::models::{
-    Attributes, BgpElem, CommonHeader, EntryType, MrtMessage, NetworkPrefix, Peer, PeerIndexTable,
-    RibAfiEntries, RibEntry, TableDumpV2Message, TableDumpV2Type,
+    Attributes, BgpElem, BgpIdentifier, CommonHeader, ElemType, EntryType, MrtMessage, MrtRecord,
+    NetworkPrefix, Peer, PeerIndexTable, RibAfiEntries, RibEntry, Safi, TableDumpV2Message,
+    TableDumpV2Type,
 };
+use crate::parser::rib::RibTable;
 use crate::utils::convert_timestamp;
 use bytes::{Bytes, BytesMut};
 use ipnet::IpNet;
-use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::net::IpAddr;
 
 #[derive(Default)]
 pub struct MrtRibEncoder {
     index_table: PeerIndexTable,
 
-    per_prefix_entries_map: HashMap<IpNet, HashMap<u16, RibEntry>>,
+    /// RIB entries by prefix and RIB subtype, keyed by peer index and path ID
+    per_prefix_entries_map: HashMap<(IpNet, TableDumpV2Type), BTreeMap<(u16, u32), RibEntry>>,
 
     timestamp: f64,
+
+    verify: bool,
+
+    add_path: bool,
 }
 
 impl MrtRibEncoder {
@@ -28,6 +39,27 @@ impl MrtRibEncoder {
         Self::default()
     }
 
+    /// Enable or disable verification of the encoded output.
+    ///
+    /// When enabled, [MrtRibEncoder::try_export_bytes] re-parses each encoded record and checks
+    /// that it is equal to the source record. [MrtRibEncoder::export_bytes] verifies the records
+    /// too, and panics if the verification fails.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Enable or disable encoding the routes with the ADD-PATH RIB subtypes.
+    ///
+    /// When enabled, routes are keyed by peer and path ID and keep their path IDs. Path ID 0 is a
+    /// valid path identifier, so the subtypes are not derived from the path IDs. When disabled,
+    /// the path IDs of the elems are ignored.
+    pub fn add_path(mut self, add_path: bool) -> Self {
+        self.add_path = add_path;
+        self
+    }
+
+    /// Reset the encoder's internal state. The verification and ADD-PATH settings are kept.
     pub fn reset(&mut self) {
         self.index_table = PeerIndexTable::default();
         self.per_prefix_entries_map = HashMap::default();
@@ -36,6 +68,10 @@ impl MrtRibEncoder {
 
     /// Processes a BgpElem and updates the internal data structures.
     ///
+    /// Announcements replace the route of their peer for their prefix, and withdrawals remove
+    /// it. Multicast elems are encoded with the multicast RIB subtypes, and all elems with the
+    /// ADD-PATH subtypes if enabled with [MrtRibEncoder::add_path].
+    ///
     /// # Arguments
     ///
     /// * `elem` - A reference to a BgpElem that contains the information to be processed.
@@ -44,33 +80,102 @@ impl MrtRibEncoder {
             self.timestamp = elem.timestamp;
         }
         let bgp_identifier = match elem.peer_ip {
-            IpAddr::V4(ip) => ip,
-            IpAddr::V6(_ip) => Ipv4Addr::from(0),
+            IpAddr::V4(ip) => BgpIdentifier::from(ip),
+            IpAddr::V6(_ip) => BgpIdentifier::new(0),
         };
         let peer = Peer::new(bgp_identifier, elem.peer_ip, elem.peer_asn);
         let peer_id = self.index_table.add_peer(peer);
         let prefix = elem.prefix.prefix;
+        let path_id = match self.add_path {
+            true => elem.prefix.path_id,
+            false => 0,
+        };
+        let key = (prefix, rib_type(elem, self.add_path));
+
+        if elem.elem_type == ElemType::WITHDRAW {
+            if let Some(entries_map) = self.per_prefix_entries_map.get_mut(&key) {
+                entries_map.remove(&(peer_id, path_id));
+                if entries_map.is_empty() {
+                    self.per_prefix_entries_map.remove(&key);
+                }
+            }
+            return;
+        }
 
-        let entries_map = self.per_prefix_entries_map.entry(prefix).or_default();
+        let entries_map = self.per_prefix_entries_map.entry(key).or_default();
         let entry = RibEntry {
             peer_index: peer_id,
             originated_time: elem.timestamp as u32,
+            path_id: self.add_path.then_some(path_id),
             attributes: Attributes::from(elem),
         };
-        entries_map.insert(peer_id, entry);
+        entries_map.insert((peer_id, path_id), entry);
+    }
+
+    /// Processes all routes of a routing table, e.g. the snapshot of a
+    /// [RibBuilder](crate::parser::rib::RibBuilder) at some time.
+    ///
+    /// The timestamp of the dump is set to the time of the table.
+    pub fn process_table(&mut self, table: &RibTable) {
+        for elem in table.routes() {
+            self.process_elem(elem);
+        }
+        self.timestamp = table.timestamp();
     }
 
     /// Export the data stored in the struct to a byte array.
     ///
-    /// The function first encodes the peer-index-table data into a `MrtMessage` and appends it to the `BytesMut` object.
+    /// The function first encodes the peer-index-table data into a `MrtMessage` and appends it to the output buffer.
     /// Then, for each prefix in the `per_prefix_entries_map`, it creates a `RibAfiEntries` object and encodes it as a `MrtMessage`.
-    /// The resulting `BytesMut` object is then converted to an immutable `Bytes` object using `freeze()` and returned.
+    /// The resulting buffer is then converted to an immutable `Bytes` object and returned.
     ///
     /// # Return
     /// Returns a `Bytes` object containing the exported data as a byte array.
+    ///
+    /// # Panics
+    /// Panics if verification is enabled with [MrtRibEncoder::verify] and an encoded record does
+    /// not parse back to its source record. Use [MrtRibEncoder::try_export_bytes] to handle the
+    /// error instead.
     pub fn export_bytes(&mut self) -> Bytes {
-        let mut bytes = BytesMut::new();
+        let result = self.encode_records(self.verify);
+        self.reset();
+        result.unwrap_or_else(|e| panic!("encoded RIB failed verification: {}", e))
+    }
 
+    /// Export the data stored in the struct to a byte array, verifying each encoded record if
+    /// verification is enabled with [MrtRibEncoder::verify].
+    ///
+    /// The internal state is reset regardless of the verification result.
+    pub fn try_export_bytes(&mut self) -> Result<Bytes, EncoderError> {
+        let result = self.encode_records(self.verify);
+        self.reset();
+        result
+    }
+
+    /// Export the data stored in the struct to a file, with the compression selected by the file
+    /// extension, e.g. `rib.mrt.gz` or `rib.mrt.bz2`. Records are written one at a time, so the
+    /// whole output is never held in memory.
+    ///
+    /// Each encoded record is verified if verification is enabled with [MrtRibEncoder::verify].
+    /// The internal state is reset regardless of the result.
+    #[cfg(feature = "oneio")]
+    pub fn export_to_path(&mut self, path: &str) -> Result<(), EncoderError> {
+        let result = write_to_path(path, |writer| self.write_records(writer, self.verify));
+        self.reset();
+        result
+    }
+
+    fn encode_records(&self, verify: bool) -> Result<Bytes, EncoderError> {
+        let mut bytes = vec![];
+        self.write_records(&mut bytes, verify)?;
+        Ok(Bytes::from(bytes))
+    }
+
+    fn write_records<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        verify: bool,
+    ) -> Result<(), EncoderError> {
         // encode peer-index-table
         let mrt_message = MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(
             self.index_table.clone(),
@@ -85,26 +190,29 @@ impl MrtRibEncoder {
             entry_subtype: subtype,
             length: data_bytes.len() as u32,
         };
-        let header_bytes = header.encode();
-        bytes.extend(header_bytes);
+        let mut bytes = BytesMut::new();
+        bytes.extend(header.encode());
         bytes.extend(data_bytes);
-
-        // encode each RibAfiEntries
-        for (entry_count, (prefix, entries_map)) in self.per_prefix_entries_map.iter().enumerate() {
-            let rib_type = match prefix.addr().is_ipv6() {
-                true => TableDumpV2Type::RibIpv6Unicast,
-                false => TableDumpV2Type::RibIpv4Unicast,
+        if verify {
+            let record = MrtRecord {
+                common_header: header,
+                message: mrt_message,
             };
+            verify_encoded_record(0, &record, &bytes)?;
+        }
+        write_record(writer, &bytes)?;
 
-            let mut prefix_rib_entry = RibAfiEntries {
+        // encode each RibAfiEntries, grouped by subtype and sorted by prefix
+        let mut keys: Vec<&(IpNet, TableDumpV2Type)> = self.per_prefix_entries_map.keys().collect();
+        keys.sort_by_key(|(prefix, rib_type)| (*rib_type as u16, *prefix));
+        for (entry_count, key) in keys.into_iter().enumerate() {
+            let (prefix, rib_type) = *key;
+            let prefix_rib_entry = RibAfiEntries {
                 rib_type,
                 sequence_number: entry_count as u32,
-                prefix: NetworkPrefix::new(*prefix, 0),
-                rib_entries: vec![],
+                prefix: NetworkPrefix::new(prefix, 0),
+                rib_entries: self.per_prefix_entries_map[key].values().cloned().collect(),
             };
-            for entry in entries_map.values() {
-                prefix_rib_entry.rib_entries.push(entry.clone());
-            }
 
             let mrt_message =
                 MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(prefix_rib_entry));
@@ -112,21 +220,42 @@ impl MrtRibEncoder {
             let (seconds, _microseconds) = convert_timestamp(self.timestamp);
             let subtype = rib_type as u16;
             let data_bytes = mrt_message.encode(subtype);
-            let header_bytes = CommonHeader {
+            let header = CommonHeader {
                 timestamp: seconds,
                 microsecond_timestamp: None,
                 entry_type: EntryType::TABLE_DUMP_V2,
                 entry_subtype: subtype,
                 length: data_bytes.len() as u32,
-            }
-            .encode();
-            bytes.extend(header_bytes);
+            };
+            let mut bytes = BytesMut::new();
+            bytes.extend(header.encode());
             bytes.extend(data_bytes);
+            if verify {
+                let record = MrtRecord {
+                    common_header: header,
+                    message: mrt_message,
+                };
+                verify_encoded_record(entry_count + 1, &record, &bytes)?;
+            }
+            write_record(writer, &bytes)?;
         }
 
-        self.reset();
+        Ok(())
+    }
+}
 
-        bytes.freeze()
+/// RIB subtype of the route of an elem.
+fn rib_type(elem: &BgpElem, add_path: bool) -> TableDumpV2Type {
+    let multicast = elem.safi == Safi::Multicast;
+    match (elem.prefix.prefix, multicast, add_path) {
+        (IpNet::V4(_), false, false) => TableDumpV2Type::RibIpv4Unicast,
+        (IpNet::V4(_), true, false) => TableDumpV2Type::RibIpv4Multicast,
+        (IpNet::V4(_), false, true) => TableDumpV2Type::RibIpv4UnicastAddPath,
+        (IpNet::V4(_), true, true) => TableDumpV2Type::RibIpv4MulticastAddPath,
+        (IpNet::V6(_), false, false) => TableDumpV2Type::RibIpv6Unicast,
+        (IpNet::V6(_), true, false) => TableDumpV2Type::RibIpv6Multicast,
+        (IpNet::V6(_), false, true) => TableDumpV2Type::RibIpv6UnicastAddPath,
+        (IpNet::V6(_), true, true) => TableDumpV2Type::RibIpv6MulticastAddPath,
     }
 }
 
@@ -174,4 +303,139 @@ mod tests {
             let _parsed = parse_mrt_record(&mut cursor).unwrap();
         }
     }
+
+    #[test]
+    fn test_encoding_rib_order_and_withdrawals() {
+        let elem = |peer: &str, prefix: &str, path_id: u32| {
+            let mut elem = BgpElem {
+                timestamp: 100.0,
+                peer_ip: peer.parse().unwrap(),
+                peer_asn: Asn::from(65000),
+                ..Default::default()
+            };
+            elem.prefix = NetworkPrefix::new(prefix.parse().unwrap(), path_id);
+            elem
+        };
+        let mut withdrawal = elem("10.0.0.1", "10.251.0.0/24", 0);
+        withdrawal.elem_type = ElemType::WITHDRAW;
+
+        let mut encoder = MrtRibEncoder::new();
+        for elem in [
+            elem("10.0.0.1", "2001:db8::/32", 0),
+            elem("10.0.0.1", "10.251.0.0/24", 0),
+            elem("10.0.0.2", "10.251.0.0/24", 0),
+            elem("10.0.0.1", "10.250.0.0/24", 7),
+            withdrawal,
+        ] {
+            encoder.process_elem(&elem);
+        }
+        let bytes = encoder.export_bytes();
+
+        let mut cursor = Cursor::new(bytes);
+        let mut records = vec![];
+        while cursor.has_remaining() {
+            records.push(parse_mrt_record(&mut cursor).unwrap());
+        }
+        assert_eq!(records.len(), 4);
+        let ribs: Vec<_> = records[1..]
+            .iter()
+            .map(|record| match &record.message {
+                MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(rib)) => (
+                    rib.rib_type,
+                    rib.sequence_number,
+                    rib.prefix.prefix.to_string(),
+                    rib.rib_entries
+                        .iter()
+                        .map(|e| (e.peer_index, e.path_id))
+                        .collect::<Vec<_>>(),
+                ),
+                _ => panic!("not a RIB record"),
+            })
+            .collect();
+        assert_eq!(
+            ribs,
+            vec![
+                (
+                    TableDumpV2Type::RibIpv4Unicast,
+                    0,
+                    "10.250.0.0/24".to_string(),
+                    vec![(0, None)]
+                ),
+                (
+                    TableDumpV2Type::RibIpv4Unicast,
+                    1,
+                    "10.251.0.0/24".to_string(),
+                    vec![(1, None)]
+                ),
+                (
+                    TableDumpV2Type::RibIpv6Unicast,
+                    2,
+                    "2001:db8::/32".to_string(),
+                    vec![(0, None)]
+                ),
+            ]
+        );
+
+        // with ADD-PATH, path ID 0 is a path of its own
+        let mut encoder = MrtRibEncoder::new().add_path(true);
+        for path_id in [7, 0] {
+            encoder.process_elem(&elem("10.0.0.1", "10.0.0.0/8", path_id));
+        }
+        let bytes = encoder.export_bytes();
+        let mut cursor = Cursor::new(bytes);
+        parse_mrt_record(&mut cursor).unwrap();
+        match parse_mrt_record(&mut cursor).unwrap().message {
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(rib)) => {
+                assert_eq!(rib.rib_type, TableDumpV2Type::RibIpv4UnicastAddPath);
+                let path_ids: Vec<_> = rib.rib_entries.iter().map(|e| e.path_id).collect();
+                assert_eq!(path_ids, vec![Some(0), Some(7)]);
+            }
+            _ => panic!("not a RIB record"),
+        }
+        assert!(!cursor.has_remaining());
+    }
+
+    #[test]
+    fn test_encoding_rib_table() {
+        let mut table = RibTable::new();
+        let mut elem = BgpElem {
+            timestamp: 100.0,
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            ..Default::default()
+        };
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        table.apply(elem.clone());
+        elem.timestamp = 200.0;
+        elem.prefix.prefix = "10.251.0.0/24".parse().unwrap();
+        table.apply(elem);
+
+        let mut encoder = MrtRibEncoder::new();
+        encoder.process_table(&table);
+        let bytes = encoder.export_bytes();
+        let mut cursor = Cursor::new(bytes);
+        let mut records = vec![];
+        while cursor.has_remaining() {
+            records.push(parse_mrt_record(&mut cursor).unwrap());
+        }
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| r.common_header.timestamp == 200));
+    }
+
+    #[test]
+    fn test_encoding_rib_verify() {
+        let mut encoder = MrtRibEncoder::new().verify(true);
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            next_hop: Some(IpAddr::V4("10.0.0.2".parse().unwrap())),
+            ..Default::default()
+        };
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        elem.prefix.prefix = "10.251.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        let bytes = encoder.try_export_bytes().unwrap();
+        assert!(!bytes.is_empty());
+    }
 }
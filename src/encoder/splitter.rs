@@ -0,0 +1,438 @@
+//! MRT splitter module
+//!
+//! [MrtSplitter] re-shards an MRT stream into several MRT outputs, by time bucket, peer, record
+//! type, or maximum output size, encoding the records back without converting them to elems.
+//! Peer index tables are copied to every output, so that each output of a RIB dump can be parsed
+//! on its own.
+//!
+//! ```no_run
+//! use bgpkit_parser::encoder::{MrtSplitter, SplitBy};
+//! use bgpkit_parser::BgpkitParser;
+//!
+//! let parser = BgpkitParser::new("updates.20240101.0000.bz2").unwrap();
+//! let mut splitter = MrtSplitter::new(SplitBy::Peer, "split/updates.20240101.0000.{}.gz");
+//! splitter.split(parser.into_record_iter()).unwrap();
+//! for output in splitter.finish().unwrap() {
+//!     println!("{}: {} records", output.key, output.records);
+//! }
+//! ```
+
+use crate::encoder::{write_record, EncoderError};
+use crate::models::{
+    Asn, Bgp4MpEnum, MrtMessage, MrtRecord, PeerIndexTable, RibAfiEntries, RibEntry,
+    TableDumpV2Message,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::IpAddr;
+
+/// Key of the output of records without a peer when splitting by peer, e.g. `RIB_GENERIC`
+/// records.
+const UNKNOWN_PEER: &str = "unknown";
+
+type Opener = Box<dyn FnMut(&str) -> std::io::Result<Box<dyn Write>>>;
+
+/// How [MrtSplitter] assigns records to outputs, and the keys naming the outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    /// Time buckets of the given number of seconds, keyed by the start timestamp of the bucket.
+    Interval(u32),
+    /// Peer of the record, keyed by `<peer ASN>_<peer IP>` with the colons of IPv6 addresses
+    /// replaced by dashes. RIB records are split into one record per peer.
+    Peer,
+    /// MRT type and subtype, keyed by `<type>_<subtype>`, e.g. `16_4` for BGP4MP_MESSAGE_AS4.
+    RecordType,
+    /// Consecutive parts of at most the given number of bytes before compression, keyed by the
+    /// part number starting from 0. Records larger than the maximum get a part of their own.
+    Size(u64),
+}
+
+/// Output written by an [MrtSplitter].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitOutput {
+    pub key: String,
+    /// number of records written, excluding the copied peer index tables
+    pub records: u64,
+    /// number of bytes written before compression, including the copied peer index tables
+    pub bytes: u64,
+}
+
+struct Output {
+    info: SplitOutput,
+    /// None once the output is closed
+    writer: Option<Box<dyn Write>>,
+}
+
+/// Splitter of MRT records into several outputs, see [SplitBy].
+///
+/// Outputs are opened when their first record arrives and stay open until
+/// [MrtSplitter::finish], except when splitting by size, where each part is closed once the next
+/// one is opened.
+pub struct MrtSplitter {
+    split_by: SplitBy,
+    open: Opener,
+    outputs: Vec<Output>,
+    output_indices: HashMap<String, usize>,
+    /// encoded peer index table and geo peer table records, copied to every output
+    headers: Vec<Bytes>,
+    peer_table: Option<PeerIndexTable>,
+    /// current part when splitting by size
+    part: u64,
+}
+
+impl MrtSplitter {
+    /// Create a splitter writing each output to the file named by `pattern` with `{}` replaced
+    /// by the key of the output, compressed according to its extension.
+    #[cfg(feature = "oneio")]
+    pub fn new(split_by: SplitBy, pattern: &str) -> Self {
+        let pattern = pattern.to_string();
+        MrtSplitter::from_opener(split_by, move |key| {
+            let path = pattern.replace("{}", key);
+            oneio::get_writer(path.as_str()).map_err(|e| std::io::Error::other(e.to_string()))
+        })
+    }
+
+    /// Create a splitter writing each output to the writer returned by `open` for its key.
+    pub fn from_opener<F>(split_by: SplitBy, open: F) -> Self
+    where
+        F: FnMut(&str) -> std::io::Result<Box<dyn Write>> + 'static,
+    {
+        MrtSplitter {
+            split_by,
+            open: Box::new(open),
+            outputs: vec![],
+            output_indices: HashMap::new(),
+            headers: vec![],
+            peer_table: None,
+            part: 0,
+        }
+    }
+
+    /// Write a record to its output.
+    pub fn process_record(&mut self, record: &MrtRecord) -> Result<(), EncoderError> {
+        match &record.message {
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(table)) => {
+                self.peer_table = Some(table.clone());
+                self.headers = vec![record.encode()];
+                return self.write_header(0);
+            }
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::GeoPeerTable(_)) => {
+                self.headers.push(record.encode());
+                return self.write_header(self.headers.len() - 1);
+            }
+            _ => {}
+        }
+
+        match (self.split_by, &record.message) {
+            (SplitBy::Peer, MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(rib))) => {
+                for (key, entries) in self.split_rib_entries(rib) {
+                    let peer_record = MrtRecord {
+                        common_header: record.common_header,
+                        message: MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(
+                            RibAfiEntries {
+                                rib_entries: entries,
+                                ..rib.clone()
+                            },
+                        )),
+                    }
+                    .with_recomputed_header();
+                    self.write(&key, &peer_record.encode())?;
+                }
+                Ok(())
+            }
+            _ => {
+                let bytes = record.encode();
+                let key = self.key(record, bytes.len() as u64);
+                self.write(&key, &bytes)
+            }
+        }
+    }
+
+    /// Write all records to their outputs.
+    pub fn split<I: IntoIterator<Item = MrtRecord>>(
+        &mut self,
+        records: I,
+    ) -> Result<(), EncoderError> {
+        for record in records {
+            self.process_record(&record)?;
+        }
+        Ok(())
+    }
+
+    /// Flush and close all outputs, returning them in the order they were opened.
+    pub fn finish(mut self) -> Result<Vec<SplitOutput>, EncoderError> {
+        for output in &mut self.outputs {
+            close(output)?;
+        }
+        Ok(self.outputs.into_iter().map(|o| o.info).collect())
+    }
+
+    /// Key of the output of a record of `length` encoded bytes.
+    fn key(&mut self, record: &MrtRecord, length: u64) -> String {
+        match self.split_by {
+            SplitBy::Interval(interval) => {
+                let timestamp = record.common_header.timestamp;
+                let interval = interval.max(1);
+                (timestamp - timestamp % interval).to_string()
+            }
+            SplitBy::Peer => match &record.message {
+                MrtMessage::TableDumpMessage(m) => peer_key(m.peer_address, m.peer_asn),
+                MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(m)) => peer_key(m.peer_ip, m.peer_asn),
+                MrtMessage::Bgp4Mp(Bgp4MpEnum::StateChange(m)) => peer_key(m.peer_addr, m.peer_asn),
+                MrtMessage::TableDumpV2Message(_) => UNKNOWN_PEER.to_string(),
+            },
+            SplitBy::RecordType => format!(
+                "{}_{}",
+                record.common_header.entry_type as u16, record.common_header.entry_subtype
+            ),
+            SplitBy::Size(max_bytes) => {
+                let current = self.part.to_string();
+                if let Some(index) = self.output_indices.get(&current) {
+                    let output = &self.outputs[*index];
+                    if output.info.records > 0 && output.info.bytes + length > max_bytes {
+                        self.part += 1;
+                    }
+                }
+                self.part.to_string()
+            }
+        }
+    }
+
+    /// Group the entries of a RIB record by the key of their peer.
+    fn split_rib_entries(&self, rib: &RibAfiEntries) -> Vec<(String, Vec<RibEntry>)> {
+        let mut groups: Vec<(String, Vec<RibEntry>)> = vec![];
+        for entry in &rib.rib_entries {
+            let key = match self
+                .peer_table
+                .as_ref()
+                .and_then(|t| t.id_peer_map.get(&entry.peer_index))
+            {
+                Some(peer) => peer_key(peer.peer_address, peer.peer_asn),
+                None => UNKNOWN_PEER.to_string(),
+            };
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, entries)) => entries.push(entry.clone()),
+                None => groups.push((key, vec![entry.clone()])),
+            }
+        }
+        groups
+    }
+
+    /// Write a record to the output of the key, opening the output if needed.
+    fn write(&mut self, key: &str, bytes: &[u8]) -> Result<(), EncoderError> {
+        let index = match self.output_indices.get(key) {
+            Some(index) => *index,
+            None => self.open_output(key)?,
+        };
+        let output = &mut self.outputs[index];
+        let writer = match &mut output.writer {
+            Some(writer) => writer,
+            None => {
+                return Err(EncoderError::WriteFailed {
+                    error: format!("output {} is closed", key),
+                })
+            }
+        };
+        write_record(writer, bytes)?;
+        output.info.records += 1;
+        output.info.bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn open_output(&mut self, key: &str) -> Result<usize, EncoderError> {
+        if let SplitBy::Size(_) = self.split_by {
+            // only the current part stays open
+            for output in &mut self.outputs {
+                close(output)?;
+            }
+        }
+        let mut writer = (self.open)(key).map_err(|e| EncoderError::WriteFailed {
+            error: format!("cannot open output {}: {}", key, e),
+        })?;
+        let mut bytes = 0;
+        for header in &self.headers {
+            write_record(&mut writer, header)?;
+            bytes += header.len() as u64;
+        }
+        self.outputs.push(Output {
+            info: SplitOutput {
+                key: key.to_string(),
+                records: 0,
+                bytes,
+            },
+            writer: Some(writer),
+        });
+        self.output_indices
+            .insert(key.to_string(), self.outputs.len() - 1);
+        Ok(self.outputs.len() - 1)
+    }
+
+    /// Write the header at `index` to all open outputs.
+    fn write_header(&mut self, index: usize) -> Result<(), EncoderError> {
+        let header = &self.headers[index];
+        for output in &mut self.outputs {
+            if let Some(writer) = &mut output.writer {
+                write_record(writer, header)?;
+                output.info.bytes += header.len() as u64;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn peer_key(peer_ip: IpAddr, peer_asn: Asn) -> String {
+    format!("{}_{}", peer_asn, peer_ip.to_string().replace(':', "-"))
+}
+
+/// Flush and drop the writer of the output, finishing its compression.
+fn close(output: &mut Output) -> Result<(), EncoderError> {
+    if let Some(mut writer) = output.writer.take() {
+        writer.flush().map_err(|e| EncoderError::WriteFailed {
+            error: format!("{}: {}", output.info.key, e),
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_mrt_record;
+    use crate::test_vectors;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Buffers = Rc<RefCell<HashMap<String, Vec<u8>>>>;
+
+    /// Writer appending to the buffer of its key.
+    struct BufferWriter {
+        key: String,
+        buffers: Buffers,
+    }
+
+    impl Write for BufferWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut buffers = self.buffers.borrow_mut();
+            buffers.entry(self.key.clone()).or_default().extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn buffered_splitter(split_by: SplitBy) -> (MrtSplitter, Buffers) {
+        let buffers = Buffers::default();
+        let opener_buffers = buffers.clone();
+        let splitter = MrtSplitter::from_opener(split_by, move |key| {
+            Ok(Box::new(BufferWriter {
+                key: key.to_string(),
+                buffers: opener_buffers.clone(),
+            }))
+        });
+        (splitter, buffers)
+    }
+
+    fn parse_records(bytes: &[u8]) -> Vec<MrtRecord> {
+        let mut input = bytes;
+        let mut records = vec![];
+        while !input.is_empty() {
+            records.push(parse_mrt_record(&mut input).unwrap());
+        }
+        records
+    }
+
+    fn update_records() -> Vec<MrtRecord> {
+        test_vectors::mrt_updates()
+            .into_iter()
+            .flat_map(|v| parse_records(&v.bytes))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_by_interval() {
+        let mut records = update_records();
+        records[2].common_header.timestamp += 300;
+        let (mut splitter, buffers) = buffered_splitter(SplitBy::Interval(300));
+        splitter.split(records.clone()).unwrap();
+        let outputs = splitter.finish().unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].key, "1699999800");
+        assert_eq!(outputs[0].records, 2);
+        assert_eq!(outputs[1].key, "1700000100");
+        let buffers = buffers.borrow();
+        assert_eq!(parse_records(&buffers["1699999800"]), records[..2]);
+        assert_eq!(parse_records(&buffers["1700000100"]), records[2..]);
+    }
+
+    #[test]
+    fn test_split_by_peer() {
+        let mut records = update_records();
+        if let MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(m)) = &mut records[1].message {
+            m.peer_ip = "2001:db8::1".parse().unwrap();
+            m.peer_asn = Asn::new_32bit(64500);
+        }
+        let (mut splitter, buffers) = buffered_splitter(SplitBy::Peer);
+        splitter.split(records).unwrap();
+        let keys: Vec<String> = splitter
+            .finish()
+            .unwrap()
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(keys, vec!["64496_192.0.2.1", "64500_2001-db8--1"]);
+        assert_eq!(parse_records(&buffers.borrow()["64496_192.0.2.1"]).len(), 2);
+    }
+
+    #[test]
+    fn test_split_rib_by_peer() {
+        let vector = test_vectors::mrt_ribs().remove(0);
+        let records = parse_records(&vector.bytes);
+        let (mut splitter, buffers) = buffered_splitter(SplitBy::Peer);
+        splitter.split(records.clone()).unwrap();
+        let outputs = splitter.finish().unwrap();
+
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].key, "64496_192.0.2.1");
+        assert_eq!(outputs[0].records, 1);
+        // the peer index table is copied to the output
+        assert_eq!(parse_records(&buffers.borrow()["64496_192.0.2.1"]), records);
+    }
+
+    #[test]
+    fn test_split_by_record_type() {
+        let vector = test_vectors::mrt_ribs().remove(0);
+        let mut records = parse_records(&vector.bytes);
+        records.extend(update_records());
+        let (mut splitter, buffers) = buffered_splitter(SplitBy::RecordType);
+        splitter.split(records).unwrap();
+        let outputs = splitter.finish().unwrap();
+
+        let keys: Vec<&str> = outputs.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["13_2", "16_4"]);
+        assert_eq!(outputs[1].records, 3);
+        // the peer index table is copied to every output
+        assert_eq!(parse_records(&buffers.borrow()["16_4"]).len(), 4);
+    }
+
+    #[test]
+    fn test_split_by_size() {
+        let records = update_records();
+        let first_two: u64 = records[..2].iter().map(|r| r.encode().len() as u64).sum();
+        let (mut splitter, buffers) = buffered_splitter(SplitBy::Size(first_two));
+        splitter.split(records.clone()).unwrap();
+        let outputs = splitter.finish().unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].bytes, first_two);
+        assert_eq!(parse_records(&buffers.borrow()["1"]), records[2..]);
+
+        // a record larger than the maximum gets a part of its own
+        let (mut splitter, _) = buffered_splitter(SplitBy::Size(1));
+        splitter.split(update_records()).unwrap();
+        assert_eq!(splitter.finish().unwrap().len(), 3);
+    }
+}
@@ -1,9 +1,13 @@
+use std::io::Write;
 use std::net::IpAddr;
 use std::str::FromStr;
 
+#[cfg(feature = "oneio")]
+use crate::encoder::write_to_path;
+use crate::encoder::{verify_encoded_record, write_record, EncoderError};
 use crate::models::{
     Asn, Bgp4MpEnum, Bgp4MpMessage, Bgp4MpType, BgpMessage, BgpUpdateMessage, CommonHeader,
-    EntryType, MrtMessage,
+    EntryType, MrtMessage, MrtRecord,
 };
 use crate::utils::convert_timestamp;
 use crate::BgpElem;
@@ -12,6 +16,9 @@ use bytes::{Bytes, BytesMut};
 #[derive(Debug, Default)]
 pub struct MrtUpdatesEncoder {
     cached_elems: Vec<BgpElem>,
+    verify: bool,
+    recompute_header: bool,
+    add_path: bool,
 }
 
 impl MrtUpdatesEncoder {
@@ -19,6 +26,36 @@ impl MrtUpdatesEncoder {
         Self::default()
     }
 
+    /// Enable or disable verification of the encoded output.
+    ///
+    /// When enabled, [MrtUpdatesEncoder::try_export_bytes] re-parses each encoded record and
+    /// checks that it is equal to the source record. [MrtUpdatesEncoder::export_bytes] verifies
+    /// the records too, and panics if the verification fails.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Enable or disable deriving the record headers from the message content, see
+    /// [MrtRecord::with_recomputed_header].
+    ///
+    /// By default, all records are encoded as `BGP4MP_MESSAGE_AS4`, or as
+    /// `BGP4MP_MESSAGE_AS4_ADDPATH` with [MrtUpdatesEncoder::add_path].
+    pub fn recompute_header(mut self, recompute_header: bool) -> Self {
+        self.recompute_header = recompute_header;
+        self
+    }
+
+    /// Enable or disable encoding the records with the ADD-PATH subtype, which keeps the path IDs
+    /// of the elems.
+    ///
+    /// Path ID 0 is a valid path identifier, so the subtype is not derived from the path IDs.
+    pub fn add_path(mut self, add_path: bool) -> Self {
+        self.add_path = add_path;
+        self
+    }
+
+    /// Reset the encoder's internal state. The settings are kept.
     pub fn reset(&mut self) {
         self.cached_elems.clear();
     }
@@ -27,18 +64,72 @@ impl MrtUpdatesEncoder {
         self.cached_elems.push(elem.clone());
     }
 
+    /// Export the cached elems as bytes and reset the encoder.
+    ///
+    /// # Panics
+    /// Panics if verification is enabled with [MrtUpdatesEncoder::verify] and an encoded record
+    /// does not parse back to its source record. Use [MrtUpdatesEncoder::try_export_bytes] to
+    /// handle the error instead.
     pub fn export_bytes(&mut self) -> Bytes {
-        let mut bytes = BytesMut::new();
+        let result = self.encode_records(self.verify);
+        self.reset();
+        result.unwrap_or_else(|e| panic!("encoded updates failed verification: {}", e))
+    }
+
+    /// Export the cached elems as bytes, verifying each encoded record if verification is enabled
+    /// with [MrtUpdatesEncoder::verify].
+    ///
+    /// The internal state is reset regardless of the verification result.
+    pub fn try_export_bytes(&mut self) -> Result<Bytes, EncoderError> {
+        let result = self.encode_records(self.verify);
+        self.reset();
+        result
+    }
+
+    /// Export the cached elems to a file, with the compression selected by the file extension,
+    /// e.g. `updates.mrt.gz` or `updates.mrt.bz2`. Records are written one at a time, so the whole
+    /// output is never held in memory.
+    ///
+    /// Each encoded record is verified if verification is enabled with
+    /// [MrtUpdatesEncoder::verify]. The internal state is reset regardless of the result.
+    #[cfg(feature = "oneio")]
+    pub fn export_to_path(&mut self, path: &str) -> Result<(), EncoderError> {
+        let result = write_to_path(path, |writer| self.write_records(writer, self.verify));
+        self.reset();
+        result
+    }
 
-        for elem in &self.cached_elems {
-            let msg = BgpUpdateMessage::from(elem);
+    fn encode_records(&self, verify: bool) -> Result<Bytes, EncoderError> {
+        let mut bytes = vec![];
+        self.write_records(&mut bytes, verify)?;
+        Ok(Bytes::from(bytes))
+    }
+
+    fn write_records<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        verify: bool,
+    ) -> Result<(), EncoderError> {
+        for (index, elem) in self.cached_elems.iter().enumerate() {
+            let msg = match self.add_path || elem.prefix.path_id == 0 {
+                true => BgpUpdateMessage::from(elem),
+                false => {
+                    // path IDs cannot be encoded without the ADD-PATH subtype
+                    let mut elem = elem.clone();
+                    elem.prefix.path_id = 0;
+                    BgpUpdateMessage::from(&elem)
+                }
+            };
             let peer_asn = Asn::new_32bit(elem.peer_asn.to_u32());
             let local_asn = Asn::new_32bit(0);
             let local_ip = match elem.peer_ip {
                 IpAddr::V4(_) => IpAddr::from_str("0.0.0.0").unwrap(),
                 IpAddr::V6(_) => IpAddr::from_str("::").unwrap(),
             };
-            let msg_type = Bgp4MpType::MessageAs4;
+            let msg_type = match self.add_path {
+                true => Bgp4MpType::MessageAs4Addpath,
+                false => Bgp4MpType::MessageAs4,
+            };
 
             let bgp4mp_msg = Bgp4MpMessage {
                 msg_type,
@@ -54,30 +145,39 @@ impl MrtUpdatesEncoder {
 
             let (seconds, microseconds) = convert_timestamp(elem.timestamp);
 
-            let subtype = Bgp4MpType::MessageAs4 as u16;
-            let data_bytes = mrt_message.encode(subtype);
-            let header_bytes = CommonHeader {
-                timestamp: seconds,
-                microsecond_timestamp: Some(microseconds),
-                entry_type: EntryType::BGP4MP_ET,
-                entry_subtype: subtype,
-                length: data_bytes.len() as u32,
+            let mut record = MrtRecord {
+                common_header: CommonHeader {
+                    timestamp: seconds,
+                    microsecond_timestamp: Some(microseconds),
+                    entry_type: EntryType::BGP4MP_ET,
+                    entry_subtype: msg_type as u16,
+                    length: 0,
+                },
+                message: mrt_message,
+            };
+            if self.recompute_header {
+                record = record.with_recomputed_header();
             }
-            .encode();
-            bytes.extend(header_bytes);
+            let data_bytes = record.message.encode(record.common_header.entry_subtype);
+            record.common_header.length = data_bytes.len() as u32;
+
+            let mut bytes = BytesMut::new();
+            bytes.extend(record.common_header.encode());
             bytes.extend(data_bytes);
+            if verify {
+                verify_encoded_record(index, &record, &bytes)?;
+            }
+            write_record(writer, &bytes)?;
         }
 
-        self.reset();
-
-        bytes.freeze()
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::NetworkPrefix;
+    use crate::models::{AttrRaw, AttrType, NetworkPrefix};
     use crate::parse_mrt_record;
     use bytes::Buf;
     use std::io::Cursor;
@@ -120,4 +220,121 @@ mod tests {
             let _parsed = parse_mrt_record(&mut cursor).unwrap();
         }
     }
+
+    #[test]
+    fn test_encoding_updates_verify() {
+        let mut encoder = MrtUpdatesEncoder::new().verify(true);
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            next_hop: Some(IpAddr::V4("10.0.0.2".parse().unwrap())),
+            ..Default::default()
+        };
+        elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+        encoder.process_elem(&elem);
+        let bytes = encoder.try_export_bytes().unwrap();
+        assert!(!bytes.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "failed verification")]
+    fn test_export_bytes_verify() {
+        let mut encoder = MrtUpdatesEncoder::new().verify(true);
+        // a known attribute kept as raw bytes is parsed back as a decoded attribute
+        let elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            prefix: NetworkPrefix::from_str("10.250.0.0/24").unwrap(),
+            unknown: Some(vec![AttrRaw {
+                attr_type: AttrType::ORIGINATOR_ID,
+                bytes: vec![10, 0, 0, 1],
+            }]),
+            ..Default::default()
+        };
+        encoder.process_elem(&elem);
+        encoder.export_bytes();
+    }
+
+    #[test]
+    fn test_encoding_updates_recompute_header() {
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            next_hop: Some(IpAddr::V4("10.0.0.2".parse().unwrap())),
+            ..Default::default()
+        };
+        elem.prefix = NetworkPrefix::new("10.250.0.0/24".parse().unwrap(), 7);
+
+        // path IDs are only kept with the ADD-PATH subtype, which is not derived from them
+        let mut encoder = MrtUpdatesEncoder::new().verify(true).recompute_header(true);
+        encoder.process_elem(&elem);
+        let bytes = encoder.try_export_bytes().unwrap();
+        let record = parse_mrt_record(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            record.common_header.entry_subtype,
+            Bgp4MpType::MessageAs4 as u16
+        );
+
+        let mut encoder = MrtUpdatesEncoder::new()
+            .verify(true)
+            .recompute_header(true)
+            .add_path(true);
+        for path_id in [7, 0] {
+            elem.prefix.path_id = path_id;
+            encoder.process_elem(&elem);
+        }
+        let bytes = encoder.try_export_bytes().unwrap();
+        let mut cursor = Cursor::new(bytes);
+        for path_id in [7, 0] {
+            let record = parse_mrt_record(&mut cursor).unwrap();
+            assert_eq!(
+                record.common_header.entry_subtype,
+                Bgp4MpType::MessageAs4Addpath as u16
+            );
+            let elems = crate::Elementor::new().record_to_elems(record);
+            assert_eq!(elems[0].prefix.path_id, path_id);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "oneio")]
+    fn test_export_to_path() {
+        let mut encoder = MrtUpdatesEncoder::new();
+        let mut elem = BgpElem {
+            peer_ip: IpAddr::V4("10.0.0.1".parse().unwrap()),
+            peer_asn: Asn::from(65000),
+            ..Default::default()
+        };
+        let mut process_elems = |encoder: &mut MrtUpdatesEncoder| {
+            elem.prefix.prefix = "10.250.0.0/24".parse().unwrap();
+            encoder.process_elem(&elem);
+            elem.prefix.prefix = "10.251.0.0/24".parse().unwrap();
+            encoder.process_elem(&elem);
+        };
+
+        process_elems(&mut encoder);
+        let bytes = encoder.export_bytes();
+
+        for extension in [".mrt.gz", ".mrt.bz2", ".mrt"] {
+            let file = tempfile::Builder::new()
+                .suffix(extension)
+                .tempfile()
+                .unwrap();
+            let path = file.path().to_str().unwrap();
+            process_elems(&mut encoder);
+            encoder.export_to_path(path).unwrap();
+
+            let elems: Vec<BgpElem> = crate::BgpkitParser::new(path)
+                .unwrap()
+                .into_iter()
+                .collect();
+            assert_eq!(elems.len(), 2);
+            assert_eq!(elems[1].prefix.prefix, "10.251.0.0/24".parse().unwrap());
+            if extension == ".mrt" {
+                assert_eq!(std::fs::read(path).unwrap(), bytes);
+            }
+            // the encoder is reset after export
+            assert!(encoder.export_bytes().is_empty());
+        }
+    }
 }
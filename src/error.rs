@@ -20,6 +20,8 @@ pub enum ParserError {
     TruncatedMsg(String),
     Unsupported(String),
     FilterError(String),
+    /// the record exceeded the [RecordLimits](crate::parser::limits::RecordLimits) of the parser
+    LimitExceeded(String),
 }
 
 impl Error for ParserError {}
@@ -52,6 +54,7 @@ impl Display for ParserError {
             #[cfg(feature = "oneio")]
             ParserError::OneIoError(e) => write!(f, "Error: {}", e),
             ParserError::FilterError(e) => write!(f, "Error: {}", e),
+            ParserError::LimitExceeded(e) => write!(f, "Error: {}", e),
         }
     }
 }
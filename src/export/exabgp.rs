@@ -0,0 +1,350 @@
+/*!
+Render parsed BGP data as [ExaBGP](https://github.com/Exa-Networks/exabgp) API JSON messages.
+
+ExaBGP reports the updates received from its neighbors to API processes as JSON `update` messages
+with the attributes, the announced prefixes grouped by address family and next hop, and the
+withdrawn prefixes grouped by address family. [ExaBgpJson] produces messages of the same
+structure from [BgpElem]s and [BgpUpdateMessage]s, so parsed MRT data can be replayed into tools
+consuming ExaBGP's output.
+
+```no_run
+use bgpkit_parser::export::exabgp::ExaBgpJson;
+use bgpkit_parser::BgpkitParser;
+
+let mut exabgp = ExaBgpJson::new();
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+for elem in parser {
+    println!("{}", exabgp.elem_to_json(&elem));
+}
+```
+
+The local side of the sessions is unknown in MRT data and is reported as the unspecified address
+and AS 0, unless set with [ExaBgpJson::with_local].
+*/
+use crate::models::well_known::{
+    COMMUNITY_NO_ADVERTISE, COMMUNITY_NO_EXPORT, COMMUNITY_NO_EXPORT_SUBCONFED,
+};
+use crate::models::*;
+use crate::parser::bgp::attributes::encode_extended_communities;
+use crate::Elementor;
+use serde_json::{json, Map, Value};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Version of the ExaBGP API the messages are compatible with.
+pub const EXABGP_API_VERSION: &str = "4.0.1";
+
+/// Converter of elems and update messages to ExaBGP JSON messages.
+///
+/// Like ExaBGP, the converter numbers the messages it produces with an increasing counter.
+#[derive(Debug, Clone)]
+pub struct ExaBgpJson {
+    host: String,
+    local: Option<(IpAddr, Asn)>,
+    counter: u64,
+}
+
+impl Default for ExaBgpJson {
+    fn default() -> Self {
+        ExaBgpJson {
+            host: "bgpkit-parser".to_string(),
+            local: None,
+            counter: 0,
+        }
+    }
+}
+
+impl ExaBgpJson {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the host name reported in the messages.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Set the local address and ASN of the sessions reported in the messages.
+    pub fn with_local(mut self, address: IpAddr, asn: Asn) -> Self {
+        self.local = Some((address, asn));
+        self
+    }
+
+    /// Convert a single elem to an update message.
+    pub fn elem_to_json(&mut self, elem: &BgpElem) -> Value {
+        // the slice is not empty, so there is always a message
+        self.elems_to_json(std::slice::from_ref(elem)).unwrap()
+    }
+
+    /// Convert the elems of one BGP update to a single update message, or `None` if there are no
+    /// elems.
+    ///
+    /// The timestamp, the peer and the attributes are taken from the first elem, and from the
+    /// first announcement for the attributes, so the elems should come from the same update,
+    /// e.g. as returned by [Elementor::bgp_update_to_elems].
+    pub fn elems_to_json(&mut self, elems: &[BgpElem]) -> Option<Value> {
+        let first = elems.first()?;
+        let mut announce = Map::new();
+        let mut withdraw = Map::new();
+        for elem in elems {
+            let nlri = nlri_json(&elem.prefix);
+            let family = family_name(elem);
+            match elem.elem_type {
+                ElemType::ANNOUNCE => {
+                    let next_hop = elem
+                        .next_hop
+                        .unwrap_or_else(|| unspecified_addr(&elem.prefix))
+                        .to_string();
+                    let next_hops = announce
+                        .entry(family)
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    if let Value::Object(next_hops) = next_hops {
+                        push_json(next_hops, next_hop, nlri);
+                    }
+                }
+                ElemType::WITHDRAW => push_json(&mut withdraw, family, nlri),
+            }
+        }
+
+        let mut update = Map::new();
+        if let Some(elem) = elems.iter().find(|e| e.elem_type == ElemType::ANNOUNCE) {
+            update.insert("attribute".to_string(), attributes_json(elem));
+        }
+        if !announce.is_empty() {
+            update.insert("announce".to_string(), Value::Object(announce));
+        }
+        if !withdraw.is_empty() {
+            update.insert("withdraw".to_string(), Value::Object(withdraw));
+        }
+        Some(self.message(first, update))
+    }
+
+    /// Convert a BGP update message received from a peer to an update message, or `None` if the
+    /// message has no prefixes, e.g. an End-of-RIB marker.
+    pub fn update_to_json(
+        &mut self,
+        update: &BgpUpdateMessage,
+        timestamp: f64,
+        peer_ip: &IpAddr,
+        peer_asn: &Asn,
+    ) -> Option<Value> {
+        let elems = Elementor::bgp_update_to_elems(update.clone(), timestamp, peer_ip, peer_asn);
+        self.elems_to_json(&elems)
+    }
+
+    fn message(&mut self, elem: &BgpElem, update: Map<String, Value>) -> Value {
+        self.counter += 1;
+        let (local_address, local_asn) = match self.local {
+            Some((address, asn)) => (address, asn),
+            None => (unspecified_addr(&elem.prefix), Asn::RESERVED),
+        };
+        json!({
+            "exabgp": EXABGP_API_VERSION,
+            "time": elem.timestamp,
+            "host": self.host,
+            "pid": std::process::id(),
+            "ppid": 0,
+            "counter": self.counter,
+            "type": "update",
+            "neighbor": {
+                "address": {
+                    "local": local_address.to_string(),
+                    "peer": elem.peer_ip.to_string(),
+                },
+                "asn": {
+                    "local": local_asn.to_u32(),
+                    "peer": elem.peer_asn.to_u32(),
+                },
+                "direction": "receive",
+                "message": {
+                    "update": update,
+                },
+            },
+        })
+    }
+}
+
+/// Append a value to the array at `key`, creating the array if needed.
+fn push_json(map: &mut Map<String, Value>, key: String, value: Value) {
+    if let Value::Array(values) = map.entry(key).or_insert_with(|| Value::Array(vec![])) {
+        values.push(value);
+    }
+}
+
+/// ExaBGP address family name, e.g. `ipv4 unicast`.
+fn family_name(elem: &BgpElem) -> String {
+    let afi = match elem.prefix.prefix.addr() {
+        IpAddr::V4(_) => "ipv4",
+        IpAddr::V6(_) => "ipv6",
+    };
+    let safi = match elem.safi {
+        Safi::Unicast => "unicast",
+        Safi::Multicast | Safi::UnicastMulticast => "multicast",
+    };
+    format!("{} {}", afi, safi)
+}
+
+fn unspecified_addr(prefix: &NetworkPrefix) -> IpAddr {
+    match prefix.prefix.addr() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+/// NLRI object, with the ADD-PATH path identifier in the dotted form used by ExaBGP.
+fn nlri_json(prefix: &NetworkPrefix) -> Value {
+    match prefix.path_id {
+        0 => json!({ "nlri": prefix.prefix.to_string() }),
+        path_id => json!({
+            "nlri": prefix.prefix.to_string(),
+            "path-information": Ipv4Addr::from(path_id).to_string(),
+        }),
+    }
+}
+
+fn attributes_json(elem: &BgpElem) -> Value {
+    let mut attributes = Map::new();
+    if let Some(origin) = &elem.origin {
+        attributes.insert(
+            "origin".to_string(),
+            json!(origin.to_string().to_lowercase()),
+        );
+    }
+    if let Some(path) = &elem.as_path {
+        let mut as_path = vec![];
+        let mut confed_path = vec![];
+        for segment in path.iter_segments() {
+            let asns: Vec<u32> = segment.into_iter().map(|asn| asn.to_u32()).collect();
+            match segment {
+                AsPathSegment::AsSequence(_) => as_path.extend(asns.into_iter().map(Value::from)),
+                AsPathSegment::AsSet(_) => as_path.push(json!(asns)),
+                AsPathSegment::ConfedSequence(_) => {
+                    confed_path.extend(asns.into_iter().map(Value::from))
+                }
+                AsPathSegment::ConfedSet(_) => confed_path.push(json!(asns)),
+            }
+        }
+        attributes.insert("as-path".to_string(), Value::Array(as_path));
+        if !confed_path.is_empty() {
+            attributes.insert("confederation-path".to_string(), Value::Array(confed_path));
+        }
+    }
+    if let Some(med) = elem.med {
+        attributes.insert("med".to_string(), json!(med));
+    }
+    if let Some(local_pref) = elem.local_pref {
+        attributes.insert("local-preference".to_string(), json!(local_pref));
+    }
+    if elem.atomic {
+        attributes.insert("atomic-aggregate".to_string(), json!(true));
+    }
+    if let (Some(asn), Some(ip)) = (elem.aggr_asn, elem.aggr_ip) {
+        attributes.insert("aggregator".to_string(), json!(format!("{}:{}", asn, ip)));
+    }
+
+    let mut communities = vec![];
+    let mut extended = vec![];
+    let mut large = vec![];
+    for community in elem.communities.iter().flatten() {
+        match community {
+            MetaCommunity::Plain(c) => {
+                let value = match c {
+                    Community::NoExport => COMMUNITY_NO_EXPORT,
+                    Community::NoAdvertise => COMMUNITY_NO_ADVERTISE,
+                    Community::NoExportSubConfed => COMMUNITY_NO_EXPORT_SUBCONFED,
+                    Community::Custom(asn, value) => (asn.to_u32() << 16) | *value as u32,
+                };
+                communities.push(json!([value >> 16, value & 0xffff]));
+            }
+            MetaCommunity::Extended(c) => {
+                let bytes = encode_extended_communities(&vec![*c]);
+                let value = match <[u8; 8]>::try_from(bytes.as_ref()) {
+                    Ok(bytes) => u64::from_be_bytes(bytes),
+                    Err(_) => continue,
+                };
+                extended.push(json!({ "value": value, "string": c.to_string() }));
+            }
+            MetaCommunity::Ipv6Extended(c) => {
+                extended.push(json!({ "string": c.to_string() }));
+            }
+            MetaCommunity::Large(c) => {
+                large.push(json!([c.global_admin, c.local_data[0], c.local_data[1]]));
+            }
+        }
+    }
+    for (key, values) in [
+        ("community", communities),
+        ("extended-community", extended),
+        ("large-community", large),
+    ] {
+        if !values.is_empty() {
+            attributes.insert(key.to_string(), Value::Array(values));
+        }
+    }
+    Value::Object(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipnet::IpNet;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_elems_to_json() {
+        let announce = BgpElem {
+            timestamp: 10.5,
+            peer_ip: IpAddr::from_str("192.0.2.1").unwrap(),
+            peer_asn: Asn::new_32bit(64496),
+            prefix: NetworkPrefix::from_str("10.0.0.0/8").unwrap(),
+            next_hop: Some(IpAddr::from_str("192.0.2.1").unwrap()),
+            as_path: Some(AsPath::from_sequence([64496, 64497])),
+            origin: Some(Origin::IGP),
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Plain(Community::Custom(Asn::new_16bit(64496), 100)),
+                MetaCommunity::Large(LargeCommunity::new(64496, [1, 2])),
+            ]),
+            ..Default::default()
+        };
+        let withdraw = BgpElem {
+            elem_type: ElemType::WITHDRAW,
+            prefix: NetworkPrefix::new(IpNet::from_str("2001:db8::/32").unwrap(), 1),
+            next_hop: None,
+            ..announce.clone()
+        };
+
+        let mut exabgp = ExaBgpJson::new();
+        let message = exabgp.elems_to_json(&[announce, withdraw]).unwrap();
+        assert_eq!(message["counter"], 1);
+        assert_eq!(message["type"], "update");
+        let neighbor = &message["neighbor"];
+        assert_eq!(neighbor["address"]["peer"], "192.0.2.1");
+        assert_eq!(neighbor["address"]["local"], "0.0.0.0");
+        assert_eq!(neighbor["asn"]["peer"], 64496);
+
+        let update = &neighbor["message"]["update"];
+        assert_eq!(update["attribute"]["origin"], "igp");
+        assert_eq!(update["attribute"]["as-path"], json!([64496, 64497]));
+        assert_eq!(
+            update["attribute"]["community"],
+            json!([[65535, 65281], [64496, 100]])
+        );
+        assert_eq!(
+            update["attribute"]["large-community"],
+            json!([[64496, 1, 2]])
+        );
+        assert_eq!(
+            update["announce"]["ipv4 unicast"]["192.0.2.1"],
+            json!([{ "nlri": "10.0.0.0/8" }])
+        );
+        assert_eq!(
+            update["withdraw"]["ipv6 unicast"],
+            json!([{ "nlri": "2001:db8::/32", "path-information": "0.0.0.1" }])
+        );
+
+        assert!(exabgp.elems_to_json(&[]).is_none());
+        let message = exabgp.elem_to_json(&BgpElem::default());
+        assert_eq!(message["counter"], 2);
+    }
+}
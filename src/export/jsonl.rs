@@ -0,0 +1,195 @@
+#[cfg(feature = "oneio")]
+use crate::encoder::CompressedFile;
+use crate::models::{BgpElem, OutputCompat};
+use serde_json::Value;
+use std::io::{Result, Write};
+
+/// Default number of elems buffered before they are written to the output.
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// Writer of elems as JSON Lines, i.e. one JSON object per line.
+///
+/// Lines are buffered and written to the output in batches. The remaining lines are written when
+/// the writer is flushed, finished, or dropped; use [JsonlWriter::finish] to see write errors.
+/// For files opened with [JsonlWriter::create], finishing also writes the trailer of gzip and
+/// bzip2 outputs, see [CompressedFile](crate::encoder::CompressedFile).
+///
+/// ```no_run
+/// use bgpkit_parser::export::JsonlWriter;
+/// use bgpkit_parser::BgpkitParser;
+///
+/// let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+/// // compressed with gzip according to the file extension
+/// let mut writer = JsonlWriter::create("updates.jsonl.gz").unwrap();
+/// writer.write_elems(parser).unwrap();
+/// writer.finish().unwrap();
+/// ```
+pub struct JsonlWriter<W: Write> {
+    writer: W,
+    buffer: Vec<u8>,
+    buffered: usize,
+    batch_size: usize,
+    compat: OutputCompat,
+    count: u64,
+    /// completes the output, e.g. writing the trailer of a compressed file
+    finish_output: fn(&mut W) -> Result<()>,
+    finished: bool,
+}
+
+fn flush_output<W: Write>(writer: &mut W) -> Result<()> {
+    writer.flush()
+}
+
+impl<W: Write> JsonlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonlWriter {
+            writer,
+            buffer: vec![],
+            buffered: 0,
+            batch_size: DEFAULT_BATCH_SIZE,
+            compat: OutputCompat::default(),
+            count: 0,
+            finish_output: flush_output::<W>,
+            finished: false,
+        }
+    }
+
+    /// Write the buffered lines to the output every `batch_size` elems. Defaults to 1000.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Write the JSON fields of an older release, see [OutputCompat].
+    pub fn with_compat(mut self, compat: OutputCompat) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// Number of lines written so far, including the buffered ones.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn write_elem(&mut self, elem: &BgpElem) -> Result<()> {
+        self.write_json(&elem.to_json_compat(self.compat))
+    }
+
+    /// Write all elems of an iterator, returning the number of elems written.
+    pub fn write_elems<I: IntoIterator<Item = BgpElem>>(&mut self, elems: I) -> Result<u64> {
+        let mut written = 0;
+        for elem in elems {
+            self.write_elem(&elem)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Write a JSON value as a line, e.g. an elem with fields added or removed by the caller.
+    pub fn write_json(&mut self, value: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.buffer, value)?;
+        self.buffer.push(b'\n');
+        self.buffered += 1;
+        self.count += 1;
+        if self.buffered >= self.batch_size {
+            self.write_buffer()?;
+        }
+        Ok(())
+    }
+
+    fn write_buffer(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.buffered = 0;
+        Ok(())
+    }
+
+    /// Write the buffered lines and flush the output.
+    pub fn flush(&mut self) -> Result<()> {
+        self.write_buffer()?;
+        self.writer.flush()
+    }
+
+    /// Write the buffered lines and close the output.
+    pub fn finish(mut self) -> Result<()> {
+        self.write_buffer()?;
+        (self.finish_output)(&mut self.writer)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "oneio")]
+impl JsonlWriter<CompressedFile> {
+    /// Create a writer to a file, compressed according to its extension, e.g. gzip for
+    /// `elems.jsonl.gz`.
+    pub fn create(path: &str) -> Result<Self> {
+        let mut writer = JsonlWriter::new(CompressedFile::create(path)?);
+        writer.finish_output = CompressedFile::finish;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Drop for JsonlWriter<W> {
+    fn drop(&mut self) {
+        // errors can only be handled when flushing or finishing explicitly
+        if !self.finished {
+            let _ = self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jsonl_writer() {
+        let mut output = vec![];
+        {
+            let mut writer = JsonlWriter::new(&mut output).with_batch_size(2);
+            writer.write_elem(&BgpElem::default()).unwrap();
+            // the first line is still buffered
+            assert_eq!(writer.count(), 1);
+            let elems = vec![BgpElem::default(); 2];
+            assert_eq!(writer.write_elems(elems).unwrap(), 2);
+            writer.finish().unwrap();
+        }
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(lines.len(), 3);
+        let value: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value, serde_json::to_value(BgpElem::default()).unwrap());
+
+        let mut output = vec![];
+        JsonlWriter::new(&mut output)
+            .with_compat(OutputCompat::V0_10)
+            .write_elem(&BgpElem::default())
+            .unwrap();
+        let value: Value = serde_json::from_slice(&output).unwrap();
+        assert!(value.get("safi").is_none());
+    }
+
+    #[cfg(feature = "oneio")]
+    #[test]
+    fn test_create_compressed() {
+        use std::io::Read;
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["elems.jsonl.gz", "elems.jsonl.bz2", "elems.jsonl"] {
+            let path = dir.path().join(name);
+            let mut writer = JsonlWriter::create(path.to_str().unwrap()).unwrap();
+            writer.write_elems(vec![BgpElem::default(); 3]).unwrap();
+            writer.finish().unwrap();
+
+            let mut content = String::new();
+            let file = std::fs::File::open(&path).unwrap();
+            let mut reader: Box<dyn Read> = match name.rsplit('.').next() {
+                Some("gz") => Box::new(flate2::read::GzDecoder::new(file)),
+                Some("bz2") => Box::new(bzip2::read::BzDecoder::new(file)),
+                _ => Box::new(file),
+            };
+            reader.read_to_string(&mut content).unwrap();
+            assert_eq!(content.lines().count(), 3, "{}", name);
+        }
+    }
+}
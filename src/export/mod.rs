@@ -0,0 +1,9 @@
+/*!
+Export parsed BGP data as JSON Lines or in formats of other tools.
+*/
+pub mod exabgp;
+#[cfg(feature = "serde")]
+mod jsonl;
+
+#[cfg(feature = "serde")]
+pub use jsonl::JsonlWriter;
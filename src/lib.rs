@@ -212,7 +212,6 @@ filtered MRT file and prints out the number of BGP messages.
 ```no_run
 use bgpkit_parser::Elementor;
 use itertools::Itertools;
-use std::io::Write;
 
 let mut updates_encoder = bgpkit_parser::encoder::MrtUpdatesEncoder::new();
 
@@ -225,9 +224,8 @@ bgpkit_parser::BgpkitParser::new(
         updates_encoder.process_elem(&elem);
     });
 
-let mut mrt_writer = oneio::get_writer("as3356_mrt.gz").unwrap();
-mrt_writer.write_all(updates_encoder.export_bytes().as_ref()).unwrap();
-drop(mrt_writer);
+// the compression is selected by the file extension
+updates_encoder.export_to_path("as3356_mrt.gz").unwrap();
 ```
 
 # Command Line Tool
@@ -261,34 +259,82 @@ Run `bgpkit-parser --help` to see the full list of options.
 MRT/BGP/BMP data processing library
 
 Usage: bgpkit-parser [OPTIONS] <FILE>
+       bgpkit-parser <COMMAND>
+
+Commands:
+  config  Print the config file path and the effective configuration
+  stats   Count elems and unique prefixes, origin ASNs, and AS paths across MRT files
+  census  Report MRT type/subtype and BGP attribute type distributions across MRT files
+  index   Build the index of an MRT file, to start parsing it at a record or a timestamp
+  search  Search the BGPKIT Broker for MRT files and print their URLs, one per line
+  merge   Merge MRT update files into one timestamp-ordered MRT file
+  help    Print this message or the help of the given subcommand(s)
 
 Arguments:
   <FILE>  File path to a MRT file, local or remote
 
 Options:
+      --config <CONFIG>          Path to the config file. Defaults to `$XDG_CONFIG_HOME/bgpkit/parser.toml` or `~/.config/bgpkit/parser.toml`
+  -q, --quiet                    Print only errors to stderr, without the summary of parsing warnings
+  -v, --verbose...               Print warnings as they occur instead of a summary at the end; repeat for debug (-vv) and trace (-vvv) messages
   -c, --cache-dir <CACHE_DIR>    Set the cache directory for caching remote files. Default behavior does not enable caching
+      --completions <SHELL>      Print the shell completion script to stdout [possible values: bash, elvish, fish, powershell, zsh]
+      --man                      Print the man page in roff format to stdout
       --json                     Output as JSON objects
       --psv                      Output as full PSV entries with header
       --pretty                   Pretty-print JSON output
+      --timestamp-format <TIMESTAMP_FORMAT>
+                                 Timestamp format of JSON and PSV output: float, micros (seconds and microseconds as separate fields), or rfc3339
+      --compat <COMPAT>          Keep the JSON fields of an older release: 0.10 or latest
+      --output <OUTPUT>          Write elems as JSON Lines to a file instead of stdout, compressed according to its extension, e.g. `elems.jsonl.gz`. Cannot be combined with `--psv` or `--pretty`
   -e, --elems-count              Count BGP elems
   -r, --records-count            Count MRT records
+      --notifications            Print BGP NOTIFICATION messages with decoded error codes instead of elems
+      --pipeline <PIPELINE>      Path to a YAML pipeline file with stages applied to the elems after the filters
+      --split-afi <SPLIT_AFI>    Write matching elems as MRT files split by address family, to `<SPLIT_AFI>.ipv4.mrt` and `<SPLIT_AFI>.ipv6.mrt`; no file is written for a family without elems
   -o, --origin-asn <ORIGIN_ASN>  Filter by origin AS Number
+      --origin-asn-list <ORIGIN_ASN_LIST>  Filter by a file of origin AS numbers, one per line, matching any of them
   -p, --prefix <PREFIX>          Filter by network prefix
+      --prefix-list <PREFIX_LIST>  Filter by a file of network prefixes, one per line
   -4, --ipv4-only                Filter by IPv4 only
   -6, --ipv6-only                Filter by IPv6 only
+      --safi <SAFI>              Filter by SAFI: unicast or multicast
+      --rib-side <RIB_SIDE>      Filter BMP routes by RIB: adj-rib-in, adj-rib-out or loc-rib; MRT elems have no RIB side
+      --policy <POLICY>          Filter BMP routes by policy stage: pre or post; MRT elems have no policy stage
   -s, --include-super            Include super-prefix when filtering
   -S, --include-sub              Include sub-prefix when filtering
   -j, --peer-ip <PEER_IP>        Filter by peer IP address
   -J, --peer-asn <PEER_ASN>      Filter by peer ASN
+      --next-hop <NEXT_HOP>      Filter by next hop IP address or prefix
   -m, --elem-type <ELEM_TYPE>    Filter by elem type: announce (a) or withdraw (w)
-  -t, --start-ts <START_TS>      Filter by start unix timestamp inclusive
-  -T, --end-ts <END_TS>          Filter by end unix timestamp inclusive
+  -t, --start-ts <START_TS>      Filter by start unix timestamp or RFC 3339 time inclusive
+  -T, --end-ts <END_TS>          Filter by end unix timestamp or RFC 3339 time inclusive
   -a, --as-path <AS_PATH>        Filter by AS path regex string
   -h, --help                     Print help
   -V, --version                  Print version
 
 ```
 
+Shell completions and a man page can be generated from the same definitions:
+
+```bash
+bgpkit-parser --completions bash > /etc/bash_completion.d/bgpkit-parser
+bgpkit-parser --man > bgpkit-parser.1
+```
+
+Frequently used options can be stored in `~/.config/bgpkit/parser.toml`. Command-line flags take precedence over the
+values in the config file, and filters given on the command line replace config filters of the same type.
+
+```toml
+cache_dir = "/tmp/bgpkit-cache"
+output_format = "json" # plain, json, or psv
+broker_url = "https://api.bgpkit.com/v3/broker" # used by the search subcommand
+
+[filters]
+peer_asn = "13335"
+ip_version = "ipv4"
+```
+
 # Data Representation
 
 There are two key data structures to understand for the parsing results: [MrtRecord] and [BgpElem].
@@ -329,16 +375,17 @@ pub struct BgpElem {
     pub peer_ip: IpAddr,
     pub peer_asn: Asn,
     pub prefix: NetworkPrefix,
+    pub safi: Safi,
     pub next_hop: Option<IpAddr>,
     pub as_path: Option<AsPath>,
-    pub origin_asns: Option<Vec<Asn>>,
+    pub origin_asns: Option<AsnSet>,
     pub origin: Option<Origin>,
     pub local_pref: Option<u32>,
     pub med: Option<u32>,
     pub communities: Option<Vec<Community>>,
     pub atomic: Option<AtomicAggregate>,
     pub aggr_asn: Option<Asn>,
-    pub aggr_ip: Option<IpAddr>,
+    pub aggr_ip: Option<BgpIdentifier>,
 }
 ```
 
@@ -413,10 +460,15 @@ We support normal communities, extended communities, and large communities.
 pub mod encoder;
 #[cfg(feature = "parser")]
 pub mod error;
+#[cfg(all(feature = "parser", feature = "serde_json"))]
+pub mod export;
 pub mod models;
 #[cfg(feature = "parser")]
 pub mod parser;
 
+#[cfg(all(feature = "parser", any(test, feature = "test-vectors")))]
+pub mod test_vectors;
+
 pub use models::BgpElem;
 pub use models::MrtRecord;
 #[cfg(feature = "parser")]
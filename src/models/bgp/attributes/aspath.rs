@@ -7,6 +7,25 @@ use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::mem::discriminant;
 
+/// How the confederation segments (`AS_CONFED_SEQUENCE` and `AS_CONFED_SET`) of an AS path are
+/// handled when computing route lengths and origins, or when filtering elems.
+///
+/// Confederation segments only describe the path within a confederation ([RFC5065]) and are
+/// removed when a route leaves it, so they are excluded by default. Data collected inside a
+/// confederation, such as BMP feeds from confederation members, may need them included.
+///
+/// [RFC5065]: https://datatracker.ietf.org/doc/html/rfc5065
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ConfedSegments {
+    /// ignore confederation segments
+    #[default]
+    Exclude,
+    /// treat confederation sequences and sets like AS sequences and sets
+    Include,
+}
+
 /// Enum of AS path segment.
 #[derive(Debug, Clone)]
 pub enum AsPathSegment {
@@ -30,10 +49,18 @@ impl AsPathSegment {
     /// Get the number of ASNs this segment adds to the route. For the number of ASNs within the
     /// segment use [AsPathSegment::len] instead.
     pub fn route_len(&self) -> usize {
-        match self {
-            AsPathSegment::AsSequence(v) => v.len(),
-            AsPathSegment::AsSet(_) => 1,
-            AsPathSegment::ConfedSequence(_) | AsPathSegment::ConfedSet(_) => 0,
+        self.route_len_with(ConfedSegments::Exclude)
+    }
+
+    /// Get the number of ASNs this segment adds to the route, counting confederation segments
+    /// depending on `confed`.
+    pub fn route_len_with(&self, confed: ConfedSegments) -> usize {
+        match (self, confed) {
+            (AsPathSegment::AsSequence(v), _) => v.len(),
+            (AsPathSegment::AsSet(_), _) => 1,
+            (AsPathSegment::ConfedSequence(v), ConfedSegments::Include) => v.len(),
+            (AsPathSegment::ConfedSet(_), ConfedSegments::Include) => 1,
+            (AsPathSegment::ConfedSequence(_) | AsPathSegment::ConfedSet(_), _) => 0,
         }
     }
 
@@ -425,6 +452,29 @@ impl AsPath {
         self.segments.iter().map(AsPathSegment::route_len).sum()
     }
 
+    /// Get the total length of the routes this path represents, counting confederation segments
+    /// depending on `confed`.
+    pub fn route_len_with(&self, confed: ConfedSegments) -> usize {
+        self.segments.iter().map(|s| s.route_len_with(confed)).sum()
+    }
+
+    /// Check if the path has any confederation segments.
+    pub fn has_confed(&self) -> bool {
+        self.segments.iter().any(AsPathSegment::is_confed)
+    }
+
+    /// Get a copy of this path without its confederation segments.
+    pub fn without_confed(&self) -> AsPath {
+        AsPath {
+            segments: self
+                .segments
+                .iter()
+                .filter(|s| !s.is_confed())
+                .cloned()
+                .collect(),
+        }
+    }
+
     /// Get the number of segments that make up this path. For the number of ASNs in routes
     /// represented by this path, use [AsPath::route_len].
     pub fn len(&self) -> usize {
@@ -676,6 +726,28 @@ impl AsPath {
         origin_slice.iter().copied()
     }
 
+    /// Iterate through the originating ASNs of this path, handling confederation segments depending
+    /// on `confed`.
+    ///
+    /// With [ConfedSegments::Exclude], the origins are taken from the last segment that is not a
+    /// confederation segment. With [ConfedSegments::Include], a path ending in a confederation
+    /// segment, i.e. a route originated within the confederation, has the last member AS as origin.
+    pub fn iter_origins_with(&self, confed: ConfedSegments) -> impl '_ + Iterator<Item = Asn> {
+        let last = match confed {
+            ConfedSegments::Exclude => self.segments.iter().rev().find(|s| !s.is_confed()),
+            ConfedSegments::Include => self.segments.last(),
+        };
+        let origin_slice = match last {
+            Some(AsPathSegment::AsSequence(v) | AsPathSegment::ConfedSequence(v)) => {
+                v.last().map(std::slice::from_ref).unwrap_or(&[])
+            }
+            Some(AsPathSegment::AsSet(v) | AsPathSegment::ConfedSet(v)) => v.as_slice(),
+            None => &[],
+        };
+
+        origin_slice.iter().copied()
+    }
+
     /// This function serves as a alternative to [AsPath::iter_origins] which attempts to make the
     /// assumption that a path can only have exactly one origin. If a path does not have exactly 1
     /// origin (such as when empty or ending in a set), then `None` will be returned instead.
@@ -1112,6 +1184,32 @@ mod tests {
         assert_eq!(origins, Vec::<u32>::new());
     }
 
+    #[test]
+    fn test_confed_segments() {
+        let aspath = AsPath::from_segments(vec![
+            AsPathSegment::ConfedSequence(vec![Asn::new_32bit(65001), Asn::new_32bit(65002)]),
+            AsPathSegment::sequence([1, 2]),
+        ]);
+        assert!(aspath.has_confed());
+        assert_eq!(aspath.route_len(), 2);
+        assert_eq!(aspath.route_len_with(ConfedSegments::Exclude), 2);
+        assert_eq!(aspath.route_len_with(ConfedSegments::Include), 4);
+        assert_eq!(aspath.without_confed(), AsPath::from_sequence([1, 2]));
+        let origins: Vec<_> = aspath.iter_origins_with(ConfedSegments::Exclude).collect();
+        assert_eq!(origins, vec![Asn::new_32bit(2)]);
+
+        // route originated within the confederation
+        let aspath = AsPath::from_segments(vec![AsPathSegment::ConfedSet(vec![
+            Asn::new_32bit(65001),
+            Asn::new_32bit(65002),
+        ])]);
+        assert_eq!(aspath.route_len_with(ConfedSegments::Include), 1);
+        assert_eq!(aspath.iter_origins_with(ConfedSegments::Exclude).count(), 0);
+        let origins: Vec<_> = aspath.iter_origins_with(ConfedSegments::Include).collect();
+        assert_eq!(origins, vec![Asn::new_32bit(65001), Asn::new_32bit(65002)]);
+        assert!(!aspath.without_confed().has_confed());
+    }
+
     #[test]
     fn test_get_collector() {
         let aspath = AsPath::from_sequence([1, 2, 3, 5]);
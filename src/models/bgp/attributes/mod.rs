@@ -2,6 +2,7 @@
 mod aspath;
 mod nlri;
 mod origin;
+mod registry;
 
 use crate::models::network::*;
 use bitflags::bitflags;
@@ -17,6 +18,7 @@ use crate::models::*;
 pub use aspath::*;
 pub use nlri::*;
 pub use origin::*;
+pub use registry::*;
 
 bitflags! {
     /// The high-order bit (bit 0) of the Attribute Flags octet is the
@@ -156,6 +158,17 @@ impl Attributes {
         self.inner.push(attr);
     }
 
+    /// Replace the flags of all attributes with [Attribute::normalized_flags].
+    ///
+    /// Useful for normalizing parsed records before encoding them with [MrtRecord::encode].
+    ///
+    /// [MrtRecord::encode]: crate::models::MrtRecord::encode
+    pub fn normalize_flags(&mut self) {
+        for attr in self.inner.iter_mut() {
+            attr.flag = attr.normalized_flags();
+        }
+    }
+
     /// Get the `ORIGIN` attribute. In the event that this attribute is not present,
     /// [Origin::INCOMPLETE] will be returned instead.
     pub fn origin(&self) -> Origin {
@@ -240,6 +253,10 @@ impl Attributes {
         })
     }
 
+    /// Get the first MP_REACH_NLRI attribute.
+    ///
+    /// Malformed messages may contain more than one MP_REACH_NLRI attribute; use
+    /// [Attributes::iter_reachable_nlri] to access all of them.
     pub fn get_reachable_nlri(&self) -> Option<&Nlri> {
         self.inner.iter().find_map(|x| match &x.value {
             AttributeValue::MpReachNlri(x) => Some(x),
@@ -247,6 +264,10 @@ impl Attributes {
         })
     }
 
+    /// Get the first MP_UNREACH_NLRI attribute.
+    ///
+    /// Malformed messages may contain more than one MP_UNREACH_NLRI attribute; use
+    /// [Attributes::iter_unreachable_nlri] to access all of them.
     pub fn get_unreachable_nlri(&self) -> Option<&Nlri> {
         self.inner.iter().find_map(|x| match &x.value {
             AttributeValue::MpUnreachNlri(x) => Some(x),
@@ -254,6 +275,22 @@ impl Attributes {
         })
     }
 
+    /// Iterate over all MP_REACH_NLRI attributes in the order they appear in the message.
+    pub fn iter_reachable_nlri(&self) -> impl Iterator<Item = &Nlri> {
+        self.inner.iter().filter_map(|x| match &x.value {
+            AttributeValue::MpReachNlri(x) => Some(x),
+            _ => None,
+        })
+    }
+
+    /// Iterate over all MP_UNREACH_NLRI attributes in the order they appear in the message.
+    pub fn iter_unreachable_nlri(&self) -> impl Iterator<Item = &Nlri> {
+        self.inner.iter().filter_map(|x| match &x.value {
+            AttributeValue::MpUnreachNlri(x) => Some(x),
+            _ => None,
+        })
+    }
+
     pub fn iter_communities(&self) -> MetaCommunitiesIter<'_> {
         MetaCommunitiesIter {
             attributes: &self.inner,
@@ -414,6 +451,34 @@ impl Attribute {
     pub const fn is_extended(&self) -> bool {
         self.flag.contains(AttrFlags::EXTENDED)
     }
+
+    /// Get the flags expected for this attribute's type.
+    ///
+    /// For attribute types with a known category, the optional and transitive bits are taken from
+    /// [AttributeValue::default_flags], and the partial bit is only kept for optional transitive
+    /// attributes. Unknown attribute types keep their original flags. The extended length bit is
+    /// cleared, as the encoder sets it based on the value length.
+    pub fn normalized_flags(&self) -> AttrFlags {
+        let flags = match self.value.attr_category() {
+            None => self.flag,
+            Some(AttributeCategory::OptionalTransitive) => {
+                self.value.default_flags() | (self.flag & AttrFlags::PARTIAL)
+            }
+            Some(_) => self.value.default_flags(),
+        };
+        flags.difference(AttrFlags::EXTENDED)
+    }
+}
+
+/// How attribute flags are written when encoding attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AttrFlagsPolicy {
+    /// Write the flags as they are, e.g. as parsed from the original message, including flags that
+    /// do not match the attribute type. The extended length bit is still added when needed.
+    #[default]
+    Preserve,
+    /// Write the flags returned by [Attribute::normalized_flags].
+    Normalize,
 }
 
 impl From<AttributeValue> for Attribute {
@@ -455,6 +520,8 @@ pub enum AttributeValue {
     Development(Vec<u8>),
     Deprecated(AttrRaw),
     Unknown(AttrRaw),
+    /// attribute not decoded yet, see [LazyAttr]
+    Lazy(LazyAttr),
 }
 
 impl From<Origin> for AttributeValue {
@@ -510,6 +577,7 @@ impl AttributeValue {
             AttributeValue::MpUnreachNlri(_) => AttrType::MP_UNREACHABLE_NLRI,
             AttributeValue::Development(_) => AttrType::DEVELOPMENT,
             AttributeValue::Deprecated(x) | AttributeValue::Unknown(x) => x.attr_type,
+            AttributeValue::Lazy(x) => x.attr_type,
         }
     }
 
@@ -560,10 +628,23 @@ pub struct AttrRaw {
     pub bytes: Vec<u8>,
 }
 
+/// Attribute kept as raw bytes by the parser, to be decoded only when needed.
+///
+/// Parsers skip decoding AS paths, aggregators, communities and cluster lists when lazy attributes
+/// are enabled with `ParseContext::with_lazy_attributes`. Decode them with `LazyAttr::decode`, or
+/// all lazy attributes at once with `Attributes::decode_lazy`.
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LazyAttr {
+    pub attr_type: AttrType,
+    /// length of the AS numbers in the message the attribute was read from
+    pub asn_len: AsnLength,
+    pub bytes: Vec<u8>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::Ipv4Addr;
     use std::str::FromStr;
 
     #[test]
@@ -586,6 +667,43 @@ mod tests {
         assert_eq!(flags, AttrFlags::TRANSITIVE);
     }
 
+    #[test]
+    fn test_normalized_flags() {
+        // well-known attribute with wrong optional and partial bits
+        let attr = Attribute {
+            value: AttributeValue::Origin(Origin::IGP),
+            flag: AttrFlags::OPTIONAL | AttrFlags::PARTIAL | AttrFlags::EXTENDED,
+        };
+        assert_eq!(attr.normalized_flags(), AttrFlags::TRANSITIVE);
+
+        // partial bit is kept for optional transitive attributes
+        let attr = Attribute {
+            value: AttributeValue::Communities(vec![]),
+            flag: AttrFlags::OPTIONAL | AttrFlags::PARTIAL,
+        };
+        assert_eq!(
+            attr.normalized_flags(),
+            AttrFlags::OPTIONAL | AttrFlags::TRANSITIVE | AttrFlags::PARTIAL
+        );
+
+        // unknown attribute types keep their flags
+        let attr = Attribute {
+            value: AttributeValue::Unknown(AttrRaw {
+                attr_type: AttrType::Unknown(250),
+                bytes: vec![],
+            }),
+            flag: AttrFlags::OPTIONAL,
+        };
+        assert_eq!(attr.normalized_flags(), AttrFlags::OPTIONAL);
+
+        let mut attributes = Attributes::from(vec![Attribute {
+            value: AttributeValue::Origin(Origin::IGP),
+            flag: AttrFlags::OPTIONAL,
+        }]);
+        attributes.normalize_flags();
+        assert_eq!(attributes.inner[0].flag, AttrFlags::TRANSITIVE);
+    }
+
     #[test]
     fn test_get_attr() {
         let attribute = Attribute {
@@ -654,13 +772,13 @@ mod tests {
         attributes.add_attr(Attribute {
             value: AttributeValue::Aggregator {
                 asn: Asn::new_32bit(1),
-                id: Ipv4Addr::from_str("0.0.0.0").unwrap(),
+                id: BgpIdentifier::from_str("0.0.0.0").unwrap(),
                 is_as4: false,
             },
             flag: AttrFlags::TRANSITIVE,
         });
         attributes.add_attr(Attribute {
-            value: AttributeValue::OriginatorId(Ipv4Addr::from_str("0.0.0.0").unwrap()),
+            value: AttributeValue::OriginatorId(BgpIdentifier::from_str("0.0.0.0").unwrap()),
             flag: AttrFlags::TRANSITIVE,
         });
 
@@ -677,11 +795,14 @@ mod tests {
         assert_eq!(attributes.clusters(), Some(vec![1_u32, 2, 3].as_slice()));
         assert_eq!(
             attributes.aggregator(),
-            Some((Asn::new_32bit(1), Ipv4Addr::from_str("0.0.0.0").unwrap()))
+            Some((
+                Asn::new_32bit(1),
+                BgpIdentifier::from_str("0.0.0.0").unwrap()
+            ))
         );
         assert_eq!(
             attributes.origin_id(),
-            Some(Ipv4Addr::from_str("0.0.0.0").unwrap())
+            Some(BgpIdentifier::from_str("0.0.0.0").unwrap())
         );
 
         let aspath_attr = attributes.get_attr(AttrType::AS_PATH).unwrap();
@@ -760,7 +881,7 @@ mod tests {
         );
         let aggregator_attr = AttributeValue::Aggregator {
             asn: Asn::new_32bit(1),
-            id: Ipv4Addr::from_str("0.0.0.0").unwrap(),
+            id: BgpIdentifier::from_str("0.0.0.0").unwrap(),
             is_as4: false,
         };
         assert_eq!(
@@ -792,7 +913,7 @@ mod tests {
         );
         let aggregator_attr = AttributeValue::Aggregator {
             asn: Asn::new_32bit(1),
-            id: Ipv4Addr::from_str("0.0.0.0").unwrap(),
+            id: BgpIdentifier::from_str("0.0.0.0").unwrap(),
             is_as4: true,
         };
         assert_eq!(
@@ -809,7 +930,7 @@ mod tests {
             Some(AttributeCategory::OptionalNonTransitive)
         );
         let originator_id_attr =
-            AttributeValue::OriginatorId(Ipv4Addr::from_str("0.0.0.0").unwrap());
+            AttributeValue::OriginatorId(BgpIdentifier::from_str("0.0.0.0").unwrap());
         assert_eq!(
             originator_id_attr.attr_category(),
             Some(AttributeCategory::OptionalNonTransitive)
@@ -0,0 +1,195 @@
+//! Machine-readable registry of BGP path attribute types.
+//!
+//! The table follows the IANA "BGP Path Attributes" registry, so consumers such as the census tool
+//! or user interfaces can look up names, categories and references instead of duplicating it.
+//!
+//! <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#bgp-parameters-2>
+use crate::models::AttributeCategory::*;
+use crate::models::{AttrType, AttributeCategory};
+
+/// Registry entry of a BGP path attribute type code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AttrTypeInfo {
+    /// attribute type code
+    pub code: u8,
+    /// attribute name, matching the [AttrType] variant name for supported types
+    pub name: &'static str,
+    /// category defined by the reference, `None` for reserved and deprecated codes
+    pub category: Option<AttributeCategory>,
+    /// defining document
+    pub reference: &'static str,
+    /// whether the code is deprecated by IANA
+    pub deprecated: bool,
+}
+
+const fn entry(
+    code: u8,
+    name: &'static str,
+    category: Option<AttributeCategory>,
+    reference: &'static str,
+) -> AttrTypeInfo {
+    AttrTypeInfo {
+        code,
+        name,
+        category,
+        reference,
+        deprecated: false,
+    }
+}
+
+const fn deprecated(code: u8, name: &'static str, reference: &'static str) -> AttrTypeInfo {
+    AttrTypeInfo {
+        code,
+        name,
+        category: None,
+        reference,
+        deprecated: true,
+    }
+}
+
+/// All assigned BGP path attribute type codes, sorted by code.
+///
+/// Unassigned codes and temporary assignments of expired drafts are not included.
+pub const ATTR_TYPE_REGISTRY: &[AttrTypeInfo] = &[
+    entry(0, "RESERVED", None, "RFC4271"),
+    entry(1, "ORIGIN", Some(WellKnownMandatory), "RFC4271"),
+    entry(2, "AS_PATH", Some(WellKnownMandatory), "RFC4271"),
+    entry(3, "NEXT_HOP", Some(WellKnownMandatory), "RFC4271"),
+    entry(
+        4,
+        "MULTI_EXIT_DISCRIMINATOR",
+        Some(OptionalNonTransitive),
+        "RFC4271",
+    ),
+    entry(5, "LOCAL_PREFERENCE", Some(WellKnownMandatory), "RFC4271"),
+    entry(
+        6,
+        "ATOMIC_AGGREGATE",
+        Some(WellKnownDiscretionary),
+        "RFC4271",
+    ),
+    entry(7, "AGGREGATOR", Some(OptionalTransitive), "RFC4271"),
+    entry(8, "COMMUNITIES", Some(OptionalTransitive), "RFC1997"),
+    entry(9, "ORIGINATOR_ID", Some(OptionalNonTransitive), "RFC4456"),
+    entry(10, "CLUSTER_LIST", Some(OptionalNonTransitive), "RFC4456"),
+    deprecated(11, "DPA", "RFC6938"),
+    deprecated(12, "ADVERTISER", "RFC6938"),
+    deprecated(13, "CLUSTER_ID", "RFC6938"),
+    entry(
+        14,
+        "MP_REACHABLE_NLRI",
+        Some(OptionalNonTransitive),
+        "RFC4760",
+    ),
+    entry(
+        15,
+        "MP_UNREACHABLE_NLRI",
+        Some(OptionalNonTransitive),
+        "RFC4760",
+    ),
+    entry(
+        16,
+        "EXTENDED_COMMUNITIES",
+        Some(OptionalTransitive),
+        "RFC4360",
+    ),
+    entry(17, "AS4_PATH", Some(OptionalTransitive), "RFC6793"),
+    entry(18, "AS4_AGGREGATOR", Some(OptionalTransitive), "RFC6793"),
+    deprecated(19, "SAFI_SPECIFIC_ATTRIBUTE", "RFC6938"),
+    deprecated(20, "CONNECTOR_ATTRIBUTE", "RFC6938"),
+    deprecated(21, "AS_PATHLIMIT", "RFC6938"),
+    entry(22, "PMSI_TUNNEL", Some(OptionalTransitive), "RFC6514"),
+    entry(
+        23,
+        "TUNNEL_ENCAPSULATION",
+        Some(OptionalTransitive),
+        "RFC9012",
+    ),
+    entry(
+        24,
+        "TRAFFIC_ENGINEERING",
+        Some(OptionalTransitive),
+        "RFC5543",
+    ),
+    entry(
+        25,
+        "IPV6_ADDRESS_SPECIFIC_EXTENDED_COMMUNITIES",
+        Some(OptionalTransitive),
+        "RFC5701",
+    ),
+    entry(26, "AIGP", Some(OptionalNonTransitive), "RFC7311"),
+    entry(
+        27,
+        "PE_DISTINGUISHER_LABELS",
+        Some(OptionalTransitive),
+        "RFC6514",
+    ),
+    deprecated(28, "BGP_ENTROPY_LABEL_CAPABILITY", "RFC7447"),
+    entry(
+        29,
+        "BGP_LS_ATTRIBUTE",
+        Some(OptionalNonTransitive),
+        "RFC9552",
+    ),
+    deprecated(30, "DEPRECATED", "RFC8093"),
+    deprecated(31, "DEPRECATED", "RFC8093"),
+    entry(32, "LARGE_COMMUNITIES", Some(OptionalTransitive), "RFC8092"),
+    entry(33, "BGPSEC_PATH", Some(OptionalNonTransitive), "RFC8205"),
+    entry(35, "ONLY_TO_CUSTOMER", Some(OptionalTransitive), "RFC9234"),
+    entry(37, "SFP_ATTRIBUTE", Some(OptionalTransitive), "RFC9015"),
+    entry(38, "BFD_DISCRIMINATOR", Some(OptionalTransitive), "RFC9026"),
+    entry(40, "BGP_PREFIX_SID", Some(OptionalTransitive), "RFC8669"),
+    entry(128, "ATTR_SET", Some(OptionalTransitive), "RFC6368"),
+    deprecated(129, "DEPRECATED", "RFC8093"),
+    deprecated(241, "DEPRECATED", "RFC8093"),
+    deprecated(242, "DEPRECATED", "RFC8093"),
+    deprecated(243, "DEPRECATED", "RFC8093"),
+    entry(255, "DEVELOPMENT", None, "RFC2042"),
+];
+
+/// Look up the registry entry of an attribute type code. Returns `None` for unassigned codes.
+pub fn attr_type_info(code: u8) -> Option<&'static AttrTypeInfo> {
+    ATTR_TYPE_REGISTRY
+        .binary_search_by_key(&code, |info| info.code)
+        .ok()
+        .map(|index| &ATTR_TYPE_REGISTRY[index])
+}
+
+impl AttrType {
+    /// Get the registry entry of this attribute type. See [attr_type_info].
+    pub fn info(&self) -> Option<&'static AttrTypeInfo> {
+        attr_type_info(u8::from(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        assert!(ATTR_TYPE_REGISTRY
+            .windows(2)
+            .all(|pair| pair[0].code < pair[1].code));
+
+        // every named attribute type has an entry with the same name
+        for code in 0..=u8::MAX {
+            if let AttrType::Unknown(_) = AttrType::from(code) {
+                continue;
+            }
+            let info = attr_type_info(code).unwrap();
+            assert_eq!(format!("{:?}", AttrType::from(code)), info.name);
+        }
+
+        let info = AttrType::LARGE_COMMUNITIES.info().unwrap();
+        assert_eq!(info.code, 32);
+        assert_eq!(info.category, Some(AttributeCategory::OptionalTransitive));
+        assert_eq!(info.reference, "RFC8092");
+        assert!(!info.deprecated);
+
+        assert!(attr_type_info(21).unwrap().deprecated);
+        assert!(attr_type_info(34).is_none());
+        assert!(AttrType::Unknown(200).info().is_none());
+    }
+}
@@ -1,4 +1,5 @@
-use num_enum::{FromPrimitive, IntoPrimitive};
+use crate::models::{Afi, Asn, BgpRole, Safi};
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, FromPrimitive, IntoPrimitive, PartialEq, Eq, Hash, Copy, Clone)]
@@ -45,6 +46,61 @@ impl BgpCapabilityType {
     }
 }
 
+/// Direction of ADD-PATH support for an address family.
+///
+/// Defined in [RFC7911](https://www.rfc-editor.org/rfc/rfc7911.html#section-4).
+#[derive(Debug, TryFromPrimitive, IntoPrimitive, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AddPathMode {
+    Receive = 1,
+    Send = 2,
+    SendReceive = 3,
+}
+
+/// Typed value of the commonly used capabilities, to build and inspect OPEN messages.
+///
+/// Convert to and from the raw [Capability](crate::models::Capability) of an OPEN message
+/// with `Capability::from` and `CapabilityValue::try_from`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapabilityValue {
+    /// [RFC4760](https://www.rfc-editor.org/rfc/rfc4760.html#section-8)
+    MultiprotocolExtensions { afi: Afi, safi: Safi },
+    /// [RFC2918](https://www.rfc-editor.org/rfc/rfc2918.html)
+    RouteRefresh,
+    /// [RFC8654](https://www.rfc-editor.org/rfc/rfc8654.html)
+    ExtendedMessage,
+    /// [RFC9234](https://www.rfc-editor.org/rfc/rfc9234.html#section-4.1)
+    BgpRole(BgpRole),
+    /// [RFC6793](https://www.rfc-editor.org/rfc/rfc6793.html)
+    FourOctetAs(Asn),
+    /// [RFC7911](https://www.rfc-editor.org/rfc/rfc7911.html#section-4)
+    AddPath(Vec<(Afi, Safi, AddPathMode)>),
+    /// [RFC7313](https://www.rfc-editor.org/rfc/rfc7313.html)
+    EnhancedRouteRefresh,
+}
+
+impl CapabilityValue {
+    pub const fn capability_type(&self) -> BgpCapabilityType {
+        match self {
+            CapabilityValue::MultiprotocolExtensions { .. } => {
+                BgpCapabilityType::MULTIPROTOCOL_EXTENSIONS_FOR_BGP_4
+            }
+            CapabilityValue::RouteRefresh => BgpCapabilityType::ROUTE_REFRESH_CAPABILITY_FOR_BGP_4,
+            CapabilityValue::ExtendedMessage => BgpCapabilityType::BGP_EXTENDED_MESSAGE,
+            CapabilityValue::BgpRole(_) => BgpCapabilityType::BGP_ROLE,
+            CapabilityValue::FourOctetAs(_) => {
+                BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY
+            }
+            CapabilityValue::AddPath(_) => BgpCapabilityType::ADD_PATH_CAPABILITY,
+            CapabilityValue::EnhancedRouteRefresh => {
+                BgpCapabilityType::ENHANCED_ROUTE_REFRESH_CAPABILITY
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
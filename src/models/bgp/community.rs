@@ -22,6 +22,17 @@ pub enum Community {
     Custom(Asn, u16),
 }
 
+impl Community {
+    /// The `GRACEFUL_SHUTDOWN` well-known community `65535:0`, defined in
+    /// [RFC8326](https://datatracker.ietf.org/doc/html/rfc8326).
+    pub const GRACEFUL_SHUTDOWN: Community = Community::Custom(Asn::new_16bit(65535), 0);
+
+    /// Returns true if this is the `GRACEFUL_SHUTDOWN` community.
+    pub fn is_graceful_shutdown(&self) -> bool {
+        *self == Community::GRACEFUL_SHUTDOWN
+    }
+}
+
 /// Large community structure as defined in [RFC8092](https://datatracker.ietf.org/doc/html/rfc8092)
 ///
 /// ## Display
@@ -295,6 +306,14 @@ impl Display for MetaCommunity {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_graceful_shutdown() {
+        assert!(Community::Custom(Asn::new_32bit(65535), 0).is_graceful_shutdown());
+        assert!(!Community::Custom(Asn::new_32bit(65535), 1).is_graceful_shutdown());
+        assert!(!Community::NoExport.is_graceful_shutdown());
+        assert_eq!(Community::GRACEFUL_SHUTDOWN.to_string(), "65535:0");
+    }
+
     #[test]
     fn test_large_community_new() {
         let global_admin = 56;
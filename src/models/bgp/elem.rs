@@ -1,5 +1,4 @@
 use crate::models::*;
-use itertools::Itertools;
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
@@ -15,7 +14,8 @@ use std::str::FromStr;
 /// - `ANNOUNCE`: Indicates an announcement/reachable prefix.
 /// - `WITHDRAW`: Indicates a withdrawn/unreachable prefix.
 ///
-/// The enumeration derives the traits `Debug`, `Clone`, `Copy`, `PartialEq`, `Eq`, and `Hash`.
+/// The enumeration derives the traits `Debug`, `Clone`, `Copy`, `PartialEq`, `Eq`, `PartialOrd`,
+/// `Ord`, and `Hash`. Announcements sort before withdrawals.
 ///
 /// It also has the following attributes:
 ///
@@ -37,7 +37,7 @@ use std::str::FromStr;
 /// assert_eq!(announce_type, ElemType::ANNOUNCE);
 /// assert_eq!(withdraw_type, ElemType::WITHDRAW);
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename = "lowercase"))]
 pub enum ElemType {
@@ -69,6 +69,187 @@ impl ElemType {
     }
 }
 
+/// RIB of the monitored router that a BMP route was collected from.
+///
+/// ```
+/// use bgpkit_parser::models::RibSide;
+///
+/// let side: RibSide = "adj-rib-out".parse().unwrap();
+/// assert_eq!(side, RibSide::AdjRibOut);
+/// assert_eq!(side.to_string(), "adj-rib-out");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RibSide {
+    /// routes received from the peer
+    AdjRibIn,
+    /// routes advertised to the peer ([RFC8671](https://datatracker.ietf.org/doc/html/rfc8671))
+    AdjRibOut,
+    /// routes selected by the router ([RFC9069](https://datatracker.ietf.org/doc/html/rfc9069))
+    LocRib,
+}
+
+impl Display for RibSide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            RibSide::AdjRibIn => "adj-rib-in",
+            RibSide::AdjRibOut => "adj-rib-out",
+            RibSide::LocRib => "loc-rib",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for RibSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "adj-rib-in" => Ok(RibSide::AdjRibIn),
+            "adj-rib-out" => Ok(RibSide::AdjRibOut),
+            "loc-rib" => Ok(RibSide::LocRib),
+            _ => Err(format!("unknown RIB side {}", s)),
+        }
+    }
+}
+
+/// Whether a BMP route was collected before or after the policies of the router were applied.
+///
+/// Loc-RIB routes are always post-policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum RibPolicy {
+    Pre,
+    Post,
+}
+
+impl Display for RibPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RibPolicy::Pre => write!(f, "pre"),
+            RibPolicy::Post => write!(f, "post"),
+        }
+    }
+}
+
+impl FromStr for RibPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "pre" | "pre-policy" => Ok(RibPolicy::Pre),
+            "post" | "post-policy" => Ok(RibPolicy::Post),
+            _ => Err(format!("unknown policy {}", s)),
+        }
+    }
+}
+
+/// Output format of elem timestamps.
+///
+/// Formatting [BgpElem::timestamp] as `f64` may produce artifacts like `1637437798.1234999`.
+/// The other formats split the timestamp into whole seconds and microseconds first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TimestampFormat {
+    /// floating-point unix timestamp, e.g. `1637437798.5`
+    #[default]
+    Float,
+    /// integer unix seconds and microseconds as separate fields, e.g. `1637437798` and `500000`
+    Micros,
+    /// RFC 3339 string in UTC, e.g. `2021-11-20T19:49:58.500000Z`
+    Rfc3339,
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "float" => Ok(TimestampFormat::Float),
+            "micros" => Ok(TimestampFormat::Micros),
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            _ => Err(format!("unknown timestamp format {}", s)),
+        }
+    }
+}
+
+/// Output compatibility with older releases.
+///
+/// New elem fields are added to the JSON output as they are parsed. Downstream tools that expect
+/// the exact field set of an older release can freeze the output to that release instead. The
+/// text and PSV output of [BgpElem] keeps the fields of version 0.10 in all modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputCompat {
+    /// fields of the 0.10 releases
+    #[cfg_attr(feature = "serde", serde(rename = "0.10"))]
+    V0_10,
+    /// all fields of the current release
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "latest"))]
+    Latest,
+}
+
+impl OutputCompat {
+    /// The JSON fields of elems in this output version, or `None` for all fields.
+    pub const fn json_fields(&self) -> Option<&'static [&'static str]> {
+        match self {
+            OutputCompat::V0_10 => Some(&[
+                "timestamp",
+                "type",
+                "peer_ip",
+                "peer_asn",
+                "prefix",
+                "next_hop",
+                "as_path",
+                "origin_asns",
+                "origin",
+                "local_pref",
+                "med",
+                "communities",
+                "atomic",
+                "aggr_asn",
+                "aggr_ip",
+                "only_to_customer",
+                "unknown",
+                "deprecated",
+            ]),
+            OutputCompat::Latest => None,
+        }
+    }
+}
+
+impl FromStr for OutputCompat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim_start_matches('v') {
+            "0.10" => Ok(OutputCompat::V0_10),
+            "latest" => Ok(OutputCompat::Latest),
+            _ => Err(format!("unknown output compatibility version {}", s)),
+        }
+    }
+}
+
+/// Convert days since the unix epoch to a (year, month, day) date.
+///
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 /// BgpElem represents a per-prefix BGP element.
 ///
 /// This struct contains information about an announced/withdrawn prefix.
@@ -92,6 +273,9 @@ impl ElemType {
 /// - `only_to_customer`: The AS number to which the prefix is only announced.
 /// - `unknown`: Unknown attributes formatted as (TYPE, RAW_BYTES).
 /// - `deprecated`: Deprecated attributes formatted as (TYPE, RAW_BYTES).
+/// - `record_index`, `record_offset`: The position of the MRT record the element was created
+///   from, if enabled.
+/// - `rib_side`, `policy`: The RIB and policy stage of BMP routes.
 ///
 /// Note: Constructing BGP elements consumes more memory due to duplicate information
 /// shared between multiple elements of one MRT record.
@@ -109,6 +293,12 @@ pub struct BgpElem {
     pub peer_asn: Asn,
     /// The network prefix of the item.
     pub prefix: NetworkPrefix,
+    /// The SAFI of the prefix, i.e. whether it was announced for unicast or multicast routing.
+    ///
+    /// Prefixes from the plain NLRI and withdrawn routes fields of BGP updates, and from table dump
+    /// records, are unicast.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub safi: Safi,
     /// The next hop IP address for the item, if available.
     pub next_hop: Option<IpAddr>,
     /// The optional path representation of the item.
@@ -120,9 +310,10 @@ pub struct BgpElem {
     ///
     /// # Remarks
     /// An `Option` type is used to indicate that the `origin_asns` field may or may not have a value.
-    /// If it has a value, it will be a `Vec` (vector) of `Asn` objects representing the ASNs.
+    /// If it has a value, it will be an [AsnSet] of the origin ASNs in ascending order, which
+    /// usually contains a single ASN unless the AS path ends with an AS set.
     /// If it does not have a value, it will be `None`.
-    pub origin_asns: Option<Vec<Asn>>,
+    pub origin_asns: Option<AsnSet>,
     /// The origin of the item (IGP, EGP, INCOMPLETE), if known. Can be `None` if the origin is not available.
     pub origin: Option<Origin>,
     /// The local preference of the item, if available, represented as an option of unsigned 32-bit integer.
@@ -141,13 +332,45 @@ pub struct BgpElem {
     pub atomic: bool,
     /// The aggregated ASN of the item, represented as an optional [Asn] type.
     pub aggr_asn: Option<Asn>,
-    /// The aggregated IP address of the item, represented as an optional [BgpIdentifier], i.e. `Ipv4Addr`.
+    /// The BGP identifier of the aggregator, represented as an optional [BgpIdentifier].
     pub aggr_ip: Option<BgpIdentifier>,
     pub only_to_customer: Option<Asn>,
     /// unknown attributes formatted as (TYPE, RAW_BYTES)
     pub unknown: Option<Vec<AttrRaw>>,
     /// deprecated attributes formatted as (TYPE, RAW_BYTES)
     pub deprecated: Option<Vec<AttrRaw>>,
+    /// Index of the MRT record the elem was created from, counting from 0.
+    ///
+    /// Only set by the parser iterators when enabled with `BgpkitParser::with_record_provenance`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub record_index: Option<u64>,
+    /// Byte offset of the MRT record the elem was created from, in the uncompressed data.
+    ///
+    /// Only set by the parser iterators when enabled with `BgpkitParser::with_record_provenance`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub record_offset: Option<u64>,
+    /// RIB of the monitored router the route was collected from.
+    ///
+    /// Only set for elems of BMP route monitoring messages, based on the per-peer header.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub rib_side: Option<RibSide>,
+    /// Whether the route was collected before or after the policies of the router were applied.
+    ///
+    /// Only set for elems of BMP route monitoring messages, based on the per-peer header.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub policy: Option<RibPolicy>,
 }
 
 impl Eq for BgpElem {}
@@ -158,12 +381,99 @@ impl PartialOrd<Self> for BgpElem {
     }
 }
 
+/// Elems are ordered by [BgpElem::cmp_by_time_prefix].
 impl Ord for BgpElem {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.timestamp
-            .partial_cmp(&other.timestamp)
-            .unwrap()
+        self.cmp_by_time_prefix(other)
+    }
+}
+
+impl BgpElem {
+    /// Compare two elems by timestamp, peer IP, peer ASN, prefix (see [NetworkPrefix]'s
+    /// ordering), and elem type, in that order. This is also the [Ord] ordering of elems.
+    ///
+    /// Timestamps are compared with [f64::total_cmp], so sorting never panics, even on NaN.
+    /// The attributes are not compared, so elems that differ only in attributes are ordered as
+    /// equal and keep their relative order in a stable sort. Use [BgpElem::cmp_by_time] to
+    /// compare timestamps only.
+    ///
+    /// ```
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let mut elems = vec![
+    ///     BgpElem { timestamp: 2.0, ..Default::default() },
+    ///     BgpElem { timestamp: 1.0, ..Default::default() },
+    /// ];
+    /// elems.sort_by(BgpElem::cmp_by_time_prefix);
+    /// assert_eq!(elems[0].timestamp, 1.0);
+    /// ```
+    pub fn cmp_by_time_prefix(&self, other: &Self) -> Ordering {
+        self.cmp_by_time(other)
             .then_with(|| self.peer_ip.cmp(&other.peer_ip))
+            .then_with(|| self.peer_asn.cmp(&other.peer_asn))
+            .then_with(|| self.prefix.cmp(&other.prefix))
+            .then_with(|| self.elem_type.cmp(&other.elem_type))
+    }
+
+    /// Compare two elems by timestamp only, using [f64::total_cmp].
+    ///
+    /// Useful with [slice::sort_by] for a stable time sort that keeps the original order of
+    /// elems with the same timestamp.
+    pub fn cmp_by_time(&self, other: &Self) -> Ordering {
+        self.timestamp.total_cmp(&other.timestamp)
+    }
+
+    /// Split the timestamp into whole unix seconds and microseconds, rounded to the nearest
+    /// microsecond.
+    ///
+    /// ```
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let elem = BgpElem {
+    ///     timestamp: 1637437798.123456,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(elem.timestamp_parts(), (1637437798, 123456));
+    /// ```
+    pub fn timestamp_parts(&self) -> (i64, u32) {
+        let seconds = self.timestamp.floor();
+        let micros = ((self.timestamp - seconds) * 1_000_000.0).round() as u32;
+        match micros {
+            1_000_000 => (seconds as i64 + 1, 0),
+            _ => (seconds as i64, micros),
+        }
+    }
+
+    /// Format the timestamp as an RFC 3339 string in UTC. Fractional seconds are only included
+    /// if the timestamp has a sub-second part.
+    ///
+    /// ```
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let elem = BgpElem {
+    ///     timestamp: 1637437798.5,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(elem.timestamp_rfc3339(), "2021-11-20T19:49:58.500000Z");
+    /// ```
+    pub fn timestamp_rfc3339(&self) -> String {
+        let (seconds, micros) = self.timestamp_parts();
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let time = seconds.rem_euclid(86400);
+        let mut s = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        );
+        if micros > 0 {
+            s.push_str(&format!(".{:06}", micros));
+        }
+        s.push('Z');
+        s
     }
 }
 
@@ -175,6 +485,7 @@ impl Default for BgpElem {
             peer_ip: IpAddr::from_str("0.0.0.0").unwrap(),
             peer_asn: 0.into(),
             prefix: NetworkPrefix::from_str("0.0.0.0/0").unwrap(),
+            safi: Safi::Unicast,
             next_hop: Some(IpAddr::from_str("0.0.0.0").unwrap()),
             as_path: None,
             origin_asns: None,
@@ -188,6 +499,10 @@ impl Default for BgpElem {
             only_to_customer: None,
             unknown: None,
             deprecated: None,
+            record_index: None,
+            record_offset: None,
+            rib_side: None,
+            policy: None,
         }
     }
 }
@@ -238,11 +553,7 @@ impl<T: Display> Display for OptionToStrVec<'_, T> {
 
 #[inline(always)]
 pub fn option_to_string_communities(o: &Option<Vec<MetaCommunity>>) -> String {
-    if let Some(v) = o {
-        v.iter().join(" ")
-    } else {
-        String::new()
-    }
+    OptionToStrVec(o).to_string()
 }
 
 impl Display for BgpElem {
@@ -281,11 +592,116 @@ impl BgpElem {
         self.elem_type == ElemType::ANNOUNCE
     }
 
+    /// Returns true if the element carries the `GRACEFUL_SHUTDOWN` community ([RFC8326]), i.e. the
+    /// peer is about to go down for maintenance and the route should be de-preferred.
+    ///
+    /// [RFC8326]: https://datatracker.ietf.org/doc/html/rfc8326
+    pub fn is_graceful_shutdown(&self) -> bool {
+        self.communities.iter().flatten().any(|c| match c {
+            MetaCommunity::Plain(c) => c.is_graceful_shutdown(),
+            _ => false,
+        })
+    }
+
+    /// Returns true if the prefix of the element is a bogon, see [NetworkPrefix::is_bogon].
+    pub fn is_bogon(&self) -> bool {
+        self.prefix.is_bogon()
+    }
+
+    /// Returns true if the prefix of the element is within the private IPv4 address space, see
+    /// [NetworkPrefix::is_rfc1918].
+    pub fn is_rfc1918(&self) -> bool {
+        self.prefix.is_rfc1918()
+    }
+
+    /// Returns true if the prefix of the element is reserved for documentation, see
+    /// [NetworkPrefix::is_documentation].
+    pub fn is_documentation(&self) -> bool {
+        self.prefix.is_documentation()
+    }
+
     /// Returns the origin AS number as u32. Returns None if the origin AS number is not present or
     /// it's a AS set.
     pub fn get_origin_asn_opt(&self) -> Option<u32> {
         let origin_asns = self.origin_asns.as_ref()?;
-        (origin_asns.len() == 1).then(|| origin_asns[0].into())
+        match origin_asns.as_slice() {
+            [origin] => Some(origin.to_u32()),
+            _ => None,
+        }
+    }
+
+    /// Converts the elem into a JSON object with the fields of the given output version.
+    ///
+    /// For [OutputCompat::V0_10], the origin ASNs are listed in the order of the AS path as in the
+    /// 0.10 releases, instead of the sorted order of [AsnSet].
+    ///
+    /// ```
+    /// use bgpkit_parser::models::OutputCompat;
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let elem = BgpElem::default();
+    /// assert!(elem.to_json_compat(OutputCompat::Latest).get("safi").is_some());
+    /// assert!(elem.to_json_compat(OutputCompat::V0_10).get("safi").is_none());
+    /// ```
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn to_json_compat(&self, compat: OutputCompat) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let (Some(fields), serde_json::Value::Object(map)) = (compat.json_fields(), &mut value) {
+            map.retain(|key, _| fields.contains(&key.as_str()));
+            if let Some(origins) = self.path_ordered_origins() {
+                map.insert(
+                    "origin_asns".to_string(),
+                    serde_json::to_value(origins).unwrap_or_default(),
+                );
+            }
+        }
+        value
+    }
+
+    /// The origin ASNs in the order of the AS path they were taken from, if they match the path.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    fn path_ordered_origins(&self) -> Option<Vec<Asn>> {
+        let (as_path, origin_asns) = (self.as_path.as_ref()?, self.origin_asns.as_ref()?);
+        [ConfedSegments::Exclude, ConfedSegments::Include]
+            .into_iter()
+            .map(|confed| as_path.iter_origins_with(confed).collect::<Vec<Asn>>())
+            .find(|origins| origins.iter().copied().collect::<AsnSet>() == *origin_asns)
+    }
+
+    /// Serializes the elem to MessagePack, e.g. to hand elems over to other processes.
+    ///
+    /// Cheaper to encode and decode than JSON, and smaller, see the `elem_serialization`
+    /// benchmark. Fields are written with their names and values as in the JSON output, so the
+    /// bytes are self-describing and can be read by other MessagePack libraries. Read them back
+    /// with [BgpElem::from_bytes].
+    ///
+    /// ```
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let elem = BgpElem::default();
+    /// let bytes = elem.to_bytes().unwrap();
+    /// assert_eq!(BgpElem::from_bytes(&bytes).unwrap(), elem);
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        use serde::Serialize;
+
+        let mut bytes = Vec::with_capacity(256);
+        // the human-readable representations keep the untagged enums of the models unambiguous
+        let mut serializer = rmp_serde::Serializer::new(&mut bytes)
+            .with_struct_map()
+            .with_human_readable();
+        self.serialize(&mut serializer)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes an elem from the MessagePack bytes of [BgpElem::to_bytes].
+    #[cfg(feature = "msgpack")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<BgpElem, rmp_serde::decode::Error> {
+        use serde::Deserialize;
+
+        let mut deserializer = rmp_serde::Deserializer::new(bytes).with_human_readable();
+        BgpElem::deserialize(&mut deserializer)
     }
 
     /// Returns the PSV header as a string.
@@ -303,9 +719,19 @@ impl BgpElem {
     /// assert_eq!(header, "type|timestamp|peer_ip|peer_asn|prefix|as_path|origin_asns|origin|next_hop|local_pref|med|communities|atomic|aggr_asn|aggr_ip|only_to_customer");
     /// ```
     pub fn get_psv_header() -> String {
+        Self::get_psv_header_with_timestamp(TimestampFormat::Float)
+    }
+
+    /// Returns the PSV header for the given timestamp format. With [TimestampFormat::Micros], the
+    /// timestamp is split into the `timestamp` and `timestamp_us` columns.
+    pub fn get_psv_header_with_timestamp(format: TimestampFormat) -> String {
+        let timestamp = match format {
+            TimestampFormat::Micros => "timestamp|timestamp_us",
+            TimestampFormat::Float | TimestampFormat::Rfc3339 => "timestamp",
+        };
         let fields = [
             "type",
-            "timestamp",
+            timestamp,
             "peer_ip",
             "peer_asn",
             "prefix",
@@ -340,19 +766,45 @@ impl BgpElem {
     /// println!("{}", psv_string);
     /// ```
     pub fn to_psv(&self) -> String {
+        self.to_psv_with_timestamp(TimestampFormat::Float)
+    }
+
+    /// Converts the struct fields into a PSV formatted string, with the timestamp in the given
+    /// format. See [BgpElem::get_psv_header_with_timestamp] for the matching header.
+    ///
+    /// ```
+    /// use bgpkit_parser::models::TimestampFormat;
+    /// use bgpkit_parser::BgpElem;
+    ///
+    /// let elem = BgpElem {
+    ///     timestamp: 1637437798.25,
+    ///     ..Default::default()
+    /// };
+    /// let psv = elem.to_psv_with_timestamp(TimestampFormat::Micros);
+    /// assert!(psv.starts_with("A|1637437798|250000|"));
+    /// ```
+    pub fn to_psv_with_timestamp(&self, format: TimestampFormat) -> String {
         let t = match self.elem_type {
             ElemType::ANNOUNCE => "A",
             ElemType::WITHDRAW => "W",
         };
+        let timestamp = match format {
+            TimestampFormat::Float => self.timestamp.to_string(),
+            TimestampFormat::Micros => {
+                let (seconds, micros) = self.timestamp_parts();
+                format!("{}|{}", seconds, micros)
+            }
+            TimestampFormat::Rfc3339 => self.timestamp_rfc3339(),
+        };
         format!(
             "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
             t,
-            &self.timestamp,
+            timestamp,
             &self.peer_ip,
             &self.peer_asn,
             &self.prefix,
             OptionToStr(&self.as_path),
-            OptionToStrVec(&self.origin_asns),
+            OptionToStr(&self.origin_asns),
             OptionToStr(&self.origin),
             OptionToStr(&self.next_hop),
             OptionToStr(&self.local_pref),
@@ -415,6 +867,32 @@ mod tests {
 
         assert!(elem1 < elem2);
         assert!(elem2 < elem3);
+
+        let elem4 = BgpElem {
+            prefix: NetworkPrefix::from_str("8.8.0.0/16").unwrap(),
+            ..elem3.clone()
+        };
+        let elem5 = BgpElem {
+            elem_type: ElemType::WITHDRAW,
+            ..elem3.clone()
+        };
+        let elem6 = BgpElem {
+            timestamp: f64::NAN,
+            ..elem1.clone()
+        };
+        let mut elems = vec![
+            elem6.clone(),
+            elem5.clone(),
+            elem3.clone(),
+            elem4.clone(),
+            elem2.clone(),
+            elem1.clone(),
+        ];
+        elems.sort();
+        // NaN timestamps sort last
+        assert!(elems.pop().unwrap().timestamp.is_nan());
+        assert_eq!(elems, vec![elem1, elem2, elem4, elem3, elem5]);
+        assert_eq!(elem6.cmp_by_time(&elem6), Ordering::Equal);
     }
 
     #[test]
@@ -430,6 +908,127 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_output_compat() {
+        assert_eq!(OutputCompat::from_str("0.10"), Ok(OutputCompat::V0_10));
+        assert_eq!(OutputCompat::from_str("v0.10"), Ok(OutputCompat::V0_10));
+        assert_eq!(OutputCompat::from_str("latest"), Ok(OutputCompat::Latest));
+        assert!(OutputCompat::from_str("0.9").is_err());
+        assert!(OutputCompat::Latest.json_fields().is_none());
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    fn test_to_json_compat() {
+        let elem = BgpElem::default();
+        let latest = elem.to_json_compat(OutputCompat::Latest);
+        assert_eq!(latest, serde_json::to_value(&elem).unwrap());
+
+        let value = elem.to_json_compat(OutputCompat::V0_10);
+        let fields = OutputCompat::V0_10.json_fields().unwrap();
+        let map = value.as_object().unwrap();
+        assert_eq!(map.len(), fields.len());
+        assert!(fields.iter().all(|field| map.contains_key(*field)));
+        assert!(!map.contains_key("safi"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    fn test_to_json_compat_golden() {
+        let as_path = AsPath::from_segments(vec![
+            AsPathSegment::sequence([64496, 64497]),
+            AsPathSegment::set([64502, 64501]),
+        ]);
+        let elem = BgpElem {
+            timestamp: 1637437798.5,
+            elem_type: ElemType::ANNOUNCE,
+            peer_ip: IpAddr::from_str("192.0.2.1").unwrap(),
+            peer_asn: Asn::new_32bit(64496),
+            prefix: NetworkPrefix::from_str("198.51.100.0/24").unwrap(),
+            next_hop: Some(IpAddr::from_str("192.0.2.1").unwrap()),
+            origin_asns: Some(as_path.iter_origins().collect()),
+            as_path: Some(as_path),
+            origin: Some(Origin::IGP),
+            local_pref: Some(100),
+            med: Some(10),
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Large(LargeCommunity::new(64496, [1, 2])),
+            ]),
+            atomic: true,
+            aggr_asn: Some(Asn::new_32bit(64501)),
+            aggr_ip: Some(BgpIdentifier::from_str("192.0.2.2").unwrap()),
+            only_to_customer: Some(Asn::new_32bit(64510)),
+            ..Default::default()
+        };
+        // output of version 0.10.11 for the same elem
+        let golden: serde_json::Value = serde_json::from_str(
+            r#"{"timestamp":1637437798.5,"type":"ANNOUNCE","peer_ip":"192.0.2.1","peer_asn":64496,"prefix":"198.51.100.0/24","next_hop":"192.0.2.1","as_path":[64496,64497,[64502,64501]],"origin_asns":[64502,64501],"origin":"IGP","local_pref":100,"med":10,"communities":["NoExport",{"global_admin":64496,"local_data":[1,2]}],"atomic":true,"aggr_asn":64501,"aggr_ip":"192.0.2.2","only_to_customer":64510,"unknown":null,"deprecated":null}"#,
+        )
+        .unwrap();
+        assert_eq!(elem.to_json_compat(OutputCompat::V0_10), golden);
+        assert_eq!(
+            elem.to_json_compat(OutputCompat::Latest)["origin_asns"],
+            serde_json::json!([64501, 64502])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "msgpack")]
+    fn test_msgpack() {
+        let elem = BgpElem {
+            timestamp: 1637437798.5,
+            peer_ip: IpAddr::from_str("2001:db8::1").unwrap(),
+            peer_asn: Asn::new_32bit(4200000000),
+            prefix: NetworkPrefix::new("2001:db8:1::/48".parse().unwrap(), 7),
+            as_path: Some(AsPath::from_sequence([64496, 4200000000])),
+            origin_asns: Some(vec![Asn::new_32bit(4200000000)].into()),
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Large(LargeCommunity::new(64496, [1, 2])),
+            ]),
+            aggr_ip: Some(BgpIdentifier::from_str("192.0.2.1").unwrap()),
+            ..Default::default()
+        };
+        let bytes = elem.to_bytes().unwrap();
+        assert_eq!(BgpElem::from_bytes(&bytes).unwrap(), elem);
+        #[cfg(feature = "serde_json")]
+        assert!(bytes.len() < serde_json::to_vec(&elem).unwrap().len());
+
+        assert!(BgpElem::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_timestamp_formats() {
+        let mut elem = BgpElem {
+            timestamp: 1637437798.9999996,
+            ..Default::default()
+        };
+        assert_eq!(elem.timestamp_parts(), (1637437799, 0));
+        assert_eq!(elem.timestamp_rfc3339(), "2021-11-20T19:49:59Z");
+
+        elem.timestamp = 951782400.000001;
+        assert_eq!(elem.timestamp_parts(), (951782400, 1));
+        assert_eq!(elem.timestamp_rfc3339(), "2000-02-29T00:00:00.000001Z");
+
+        elem.timestamp = 0.0;
+        assert_eq!(elem.timestamp_rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            elem.to_psv_with_timestamp(TimestampFormat::Rfc3339),
+            "A|1970-01-01T00:00:00Z|0.0.0.0|0|0.0.0.0/0||||0.0.0.0||||false|||"
+        );
+        assert!(
+            BgpElem::get_psv_header_with_timestamp(TimestampFormat::Micros)
+                .starts_with("type|timestamp|timestamp_us|peer_ip")
+        );
+
+        assert_eq!(
+            TimestampFormat::from_str("RFC3339"),
+            Ok(TimestampFormat::Rfc3339)
+        );
+        assert!(TimestampFormat::from_str("iso").is_err());
+    }
+
     #[test]
     fn test_option_to_str() {
         let asn_opt: Option<u32> = Some(12);
@@ -0,0 +1,146 @@
+use crate::models::*;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Path attributes of an elem, shared between the [BgpElemRef]s of one BGP update.
+///
+/// The fields are the same as the attribute fields of [BgpElem].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ElemAttributes {
+    pub next_hop: Option<IpAddr>,
+    pub as_path: Option<AsPath>,
+    pub origin_asns: Option<AsnSet>,
+    pub origin: Option<Origin>,
+    pub local_pref: Option<u32>,
+    pub med: Option<u32>,
+    pub communities: Option<Vec<MetaCommunity>>,
+    pub atomic: bool,
+    pub aggr_asn: Option<Asn>,
+    pub aggr_ip: Option<BgpIdentifier>,
+    pub only_to_customer: Option<Asn>,
+    pub unknown: Option<Vec<AttrRaw>>,
+    pub deprecated: Option<Vec<AttrRaw>>,
+}
+
+/// Per-prefix BGP element whose attributes are shared with the other elems of the same update.
+///
+/// Every [BgpElem] of an update owns a copy of the AS path, communities and other attributes, so
+/// an update announcing 1000 prefixes copies them 1000 times. The [BgpElemRef]s created with
+/// [Elementor::record_to_elem_refs](crate::Elementor::record_to_elem_refs) hold the attributes
+/// of an update behind a single [Arc] instead. Convert them into [BgpElem]s only where owned
+/// attributes are needed, e.g. for output.
+///
+/// ```
+/// use bgpkit_parser::models::*;
+/// use bgpkit_parser::Elementor;
+/// use std::str::FromStr;
+///
+/// let msg = BgpUpdateMessage {
+///     withdrawn_prefixes: vec![],
+///     attributes: Attributes::from_iter(vec![AttributeValue::AsPath {
+///         path: AsPath::from_sequence([64496, 64497]),
+///         is_as4: true,
+///     }]),
+///     announced_prefixes: vec![
+///         NetworkPrefix::from_str("10.0.0.0/8").unwrap(),
+///         NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+///     ],
+/// };
+/// let peer_ip = "192.0.2.1".parse().unwrap();
+/// let elems = Elementor::bgp_update_to_elem_refs(msg, 0.0, &peer_ip, &Asn::new_32bit(64496));
+/// assert!(elems[0].shares_attributes(&elems[1]));
+///
+/// let elem = BgpElem::from(elems[1].clone());
+/// assert_eq!(elem.as_path, Some(AsPath::from_sequence([64496, 64497])));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BgpElemRef {
+    pub timestamp: f64,
+    pub elem_type: ElemType,
+    pub peer_ip: IpAddr,
+    pub peer_asn: Asn,
+    pub prefix: NetworkPrefix,
+    pub safi: Safi,
+    pub attributes: Arc<ElemAttributes>,
+    pub record_index: Option<u64>,
+    pub record_offset: Option<u64>,
+    pub rib_side: Option<RibSide>,
+    pub policy: Option<RibPolicy>,
+}
+
+impl BgpElemRef {
+    /// Returns true if both elems share the same copy of the attributes.
+    pub fn shares_attributes(&self, other: &BgpElemRef) -> bool {
+        Arc::ptr_eq(&self.attributes, &other.attributes)
+    }
+
+    /// Convert to a [BgpElem] with its own copy of the attributes.
+    pub fn to_elem(&self) -> BgpElem {
+        BgpElem::from(self.clone())
+    }
+}
+
+impl From<BgpElemRef> for BgpElem {
+    fn from(elem: BgpElemRef) -> Self {
+        // the last elem of an update takes the attributes without copying them
+        let attributes = Arc::try_unwrap(elem.attributes).unwrap_or_else(|a| a.as_ref().clone());
+        BgpElem {
+            timestamp: elem.timestamp,
+            elem_type: elem.elem_type,
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+            prefix: elem.prefix,
+            safi: elem.safi,
+            next_hop: attributes.next_hop,
+            as_path: attributes.as_path,
+            origin_asns: attributes.origin_asns,
+            origin: attributes.origin,
+            local_pref: attributes.local_pref,
+            med: attributes.med,
+            communities: attributes.communities,
+            atomic: attributes.atomic,
+            aggr_asn: attributes.aggr_asn,
+            aggr_ip: attributes.aggr_ip,
+            only_to_customer: attributes.only_to_customer,
+            unknown: attributes.unknown,
+            deprecated: attributes.deprecated,
+            record_index: elem.record_index,
+            record_offset: elem.record_offset,
+            rib_side: elem.rib_side,
+            policy: elem.policy,
+        }
+    }
+}
+
+impl From<BgpElem> for BgpElemRef {
+    fn from(elem: BgpElem) -> Self {
+        let attributes = ElemAttributes {
+            next_hop: elem.next_hop,
+            as_path: elem.as_path,
+            origin_asns: elem.origin_asns,
+            origin: elem.origin,
+            local_pref: elem.local_pref,
+            med: elem.med,
+            communities: elem.communities,
+            atomic: elem.atomic,
+            aggr_asn: elem.aggr_asn,
+            aggr_ip: elem.aggr_ip,
+            only_to_customer: elem.only_to_customer,
+            unknown: elem.unknown,
+            deprecated: elem.deprecated,
+        };
+        BgpElemRef {
+            timestamp: elem.timestamp,
+            elem_type: elem.elem_type,
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+            prefix: elem.prefix,
+            safi: elem.safi,
+            attributes: Arc::new(attributes),
+            record_index: elem.record_index,
+            record_offset: elem.record_offset,
+            rib_side: elem.rib_side,
+            policy: elem.policy,
+        }
+    }
+}
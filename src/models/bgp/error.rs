@@ -4,6 +4,7 @@
 //! <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#bgp-parameters-3>.
 use log::warn;
 use num_enum::{FromPrimitive, IntoPrimitive};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, FromPrimitive, IntoPrimitive)]
 #[repr(u8)]
@@ -16,10 +17,29 @@ pub enum BgpErrorCode {
     FiniteStateMachineError = 5,
     CeaseNotification = 6,
     RouteFreshError = 7,
+    SendHoldTimerExpired = 8,
     #[num_enum(catch_all)]
     Unknown(u8),
 }
 
+impl BgpErrorCode {
+    /// Human-readable name of the error code as registered by IANA.
+    pub const fn description(&self) -> &'static str {
+        match self {
+            BgpErrorCode::Reserved => "Reserved",
+            BgpErrorCode::MessageHeaderError => "Message Header Error",
+            BgpErrorCode::OpenError => "OPEN Message Error",
+            BgpErrorCode::UpdateError => "UPDATE Message Error",
+            BgpErrorCode::HoldTimerExpired => "Hold Timer Expired",
+            BgpErrorCode::FiniteStateMachineError => "Finite State Machine Error",
+            BgpErrorCode::CeaseNotification => "Cease",
+            BgpErrorCode::RouteFreshError => "ROUTE-REFRESH Message Error",
+            BgpErrorCode::SendHoldTimerExpired => "Send Hold Timer Expired",
+            BgpErrorCode::Unknown(_) => "Unknown",
+        }
+    }
+}
+
 /// BGP Error Subcode enum.
 ///
 /// <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#bgp-parameters-4>
@@ -37,6 +57,10 @@ pub enum BgpError {
     FiniteStateMachineError(FiniteStateMachineError),
     CeaseNotification(CeaseNotification),
     RouteFreshError(RouteRefreshError),
+    /// Includes subcode. Currently, no subcodes have been assigned.
+    ///
+    /// <https://datatracker.ietf.org/doc/html/rfc9687>
+    SendHoldTimerExpired(u8),
     Unknown(u8, u8),
 }
 
@@ -59,6 +83,7 @@ impl BgpError {
             BgpErrorCode::RouteFreshError => {
                 BgpError::RouteFreshError(RouteRefreshError::from(subcode))
             }
+            BgpErrorCode::SendHoldTimerExpired => BgpError::SendHoldTimerExpired(subcode),
             BgpErrorCode::Unknown(_) => {
                 warn!(
                     "error parsing BGP notification error code: {}, subcode: {}",
@@ -79,9 +104,52 @@ impl BgpError {
             BgpError::FiniteStateMachineError(v) => (5, (*v).into()),
             BgpError::CeaseNotification(v) => (6, (*v).into()),
             BgpError::RouteFreshError(v) => (7, (*v).into()),
+            BgpError::SendHoldTimerExpired(v) => (8, *v),
             BgpError::Unknown(code, subcode) => (*code, *subcode),
         }
     }
+
+    /// Human-readable name of the error code.
+    pub fn code_description(&self) -> &'static str {
+        BgpErrorCode::from(self.get_codes().0).description()
+    }
+
+    /// Human-readable name of the error subcode.
+    ///
+    /// Returns `None` for error codes without a subcode registry and for unknown error codes.
+    pub const fn subcode_description(&self) -> Option<&'static str> {
+        match self {
+            BgpError::MessageHeaderError(v) => Some(v.description()),
+            BgpError::OpenError(v) => Some(v.description()),
+            BgpError::UpdateError(v) => Some(v.description()),
+            BgpError::FiniteStateMachineError(v) => Some(v.description()),
+            BgpError::CeaseNotification(v) => Some(v.description()),
+            BgpError::RouteFreshError(v) => Some(v.description()),
+            BgpError::Reserved(_)
+            | BgpError::HoldTimerExpired(_)
+            | BgpError::SendHoldTimerExpired(_)
+            | BgpError::Unknown(_, _) => None,
+        }
+    }
+}
+
+/// Formats the error with the IANA names followed by the numeric code and subcode, e.g.
+/// `Cease: Administrative Shutdown (6/2)`.
+impl Display for BgpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (code, subcode) = self.get_codes();
+        match self.subcode_description() {
+            Some(desc) => write!(
+                f,
+                "{}: {} ({}/{})",
+                self.code_description(),
+                desc,
+                code,
+                subcode
+            ),
+            None => write!(f, "{} ({}/{})", self.code_description(), code, subcode),
+        }
+    }
 }
 
 /// Message Header Error subcodes
@@ -103,6 +171,18 @@ pub enum MessageHeaderError {
     Unknown(u8),
 }
 
+impl MessageHeaderError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            MessageHeaderError::UNSPECIFIC => "Unspecific",
+            MessageHeaderError::CONNECTION_NOT_SYNCHRONIZED => "Connection Not Synchronized",
+            MessageHeaderError::BAD_MESSAGE_LENGTH => "Bad Message Length",
+            MessageHeaderError::BAD_MESSAGE_TYPE => "Bad Message Type",
+            MessageHeaderError::Unknown(_) => "Unassigned",
+        }
+    }
+}
+
 /// OPEN Message Error subcodes
 ///
 /// <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#bgp-parameters-6>
@@ -134,6 +214,21 @@ impl OpenError {
     pub const fn is_deprecated(&self) -> bool {
         matches!(self, OpenError::Unknown(5 | 8 | 9 | 10))
     }
+
+    pub const fn description(&self) -> &'static str {
+        match self {
+            OpenError::UNSPECIFIC => "Unspecific",
+            OpenError::UNSUPPORTED_VERSION_NUMBER => "Unsupported Version Number",
+            OpenError::BAD_PEER_AS => "Bad Peer AS",
+            OpenError::BAD_BGP_IDENTIFIER => "Bad BGP Identifier",
+            OpenError::UNSUPPORTED_OPTIONAL_PARAMETER => "Unsupported Optional Parameter",
+            OpenError::UNACCEPTABLE_HOLD_TIME => "Unacceptable Hold Time",
+            OpenError::UNSUPPORTED_CAPACITY => "Unsupported Capability",
+            OpenError::ROLE_MISMATCH => "Role Mismatch",
+            OpenError::Unknown(5 | 8 | 9 | 10) => "Deprecated",
+            OpenError::Unknown(_) => "Unassigned",
+        }
+    }
 }
 
 /// UPDATE Message Error subcodes
@@ -167,6 +262,24 @@ impl UpdateError {
     pub const fn is_deprecated(&self) -> bool {
         matches!(self, UpdateError::Unknown(7))
     }
+
+    pub const fn description(&self) -> &'static str {
+        match self {
+            UpdateError::UNSPECIFIC => "Unspecific",
+            UpdateError::MALFORMED_ATTRIBUTE_LIST => "Malformed Attribute List",
+            UpdateError::UNRECOGNIZED_WELL_KNOWN_ATTRIBUTE => "Unrecognized Well-known Attribute",
+            UpdateError::MISSING_WELL_KNOWN_ATTRIBUTE => "Missing Well-known Attribute",
+            UpdateError::ATTRIBUTE_FLAGS_ERROR => "Attribute Flags Error",
+            UpdateError::ATTRIBUTE_LENGTH_ERROR => "Attribute Length Error",
+            UpdateError::INVALID_ORIGIN_ERROR => "Invalid ORIGIN Attribute",
+            UpdateError::INVALID_NEXT_HOP_ATTRIBUTE => "Invalid NEXT_HOP Attribute",
+            UpdateError::OPTIONAL_ATTRIBUTE_ERROR => "Optional Attribute Error",
+            UpdateError::INVALID_NETWORK_FIELD => "Invalid Network Field",
+            UpdateError::MALFORMED_AS_PATH => "Malformed AS_PATH",
+            UpdateError::Unknown(7) => "Deprecated",
+            UpdateError::Unknown(_) => "Unassigned",
+        }
+    }
 }
 
 /// BGP Finite State Machine Error Subcodes
@@ -188,6 +301,24 @@ pub enum FiniteStateMachineError {
     Unknown(u8),
 }
 
+impl FiniteStateMachineError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            FiniteStateMachineError::UNSPECIFIED => "Unspecified Error",
+            FiniteStateMachineError::RECEIVE_UNEXPECTED_MESSAGE_IN_OPENSENT_State => {
+                "Receive Unexpected Message in OpenSent State"
+            }
+            FiniteStateMachineError::RECEIVE_UNEXPECTED_MESSAGE_IN_OPENCONFIRM_STATE => {
+                "Receive Unexpected Message in OpenConfirm State"
+            }
+            FiniteStateMachineError::RECEIVE_UNEXPECTED_MESSAGE_IN_ESTABLISHED_STATE => {
+                "Receive Unexpected Message in Established State"
+            }
+            FiniteStateMachineError::Unknown(_) => "Unassigned",
+        }
+    }
+}
+
 /// BGP Cease NOTIFICATION message subcodes
 ///
 /// <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#bgp-parameters-8>
@@ -208,12 +339,34 @@ pub enum CeaseNotification {
     CONNECTION_COLLISION_RESOLUTION = 7,
     OUT_OF_RESOURCES = 8,
     HARD_RESET = 9,
-    BFD_DOWN = 10, // TEMPORARY - registered 2022-02-23, expires 2023-02-23
+    /// <https://datatracker.ietf.org/doc/html/rfc9384>
+    BFD_DOWN = 10,
     // 11 - 255: unassigned
     #[num_enum(catch_all)]
     Unknown(u8),
 }
 
+impl CeaseNotification {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            CeaseNotification::RESERVED => "Reserved",
+            CeaseNotification::MAXIMUM_NUMBER_OF_PREFIXES_REACHED => {
+                "Maximum Number of Prefixes Reached"
+            }
+            CeaseNotification::ADMINISTRATIVE_SHUTDOWN => "Administrative Shutdown",
+            CeaseNotification::PEER_DE_CONFIGURED => "Peer De-configured",
+            CeaseNotification::ADMINISTRATIVE_RESET => "Administrative Reset",
+            CeaseNotification::CONNECTION_REJECTED => "Connection Rejected",
+            CeaseNotification::OTHER_CONFIGURATION_CHANGE => "Other Configuration Change",
+            CeaseNotification::CONNECTION_COLLISION_RESOLUTION => "Connection Collision Resolution",
+            CeaseNotification::OUT_OF_RESOURCES => "Out of Resources",
+            CeaseNotification::HARD_RESET => "Hard Reset",
+            CeaseNotification::BFD_DOWN => "BFD Down",
+            CeaseNotification::Unknown(_) => "Unassigned",
+        }
+    }
+}
+
 /// BGP ROUTE-REFRESH Message Error subcodes
 ///
 /// <https://www.iana.org/assignments/bgp-parameters/bgp-parameters.xhtml#route-refresh-error-subcodes>
@@ -231,6 +384,16 @@ pub enum RouteRefreshError {
     Unknown(u8),
 }
 
+impl RouteRefreshError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            RouteRefreshError::RESERVED => "Reserved",
+            RouteRefreshError::INVALID_MESSAGE_LENGTH => "Invalid Message Length",
+            RouteRefreshError::Unknown(_) => "Unassigned",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -454,6 +617,25 @@ mod tests {
             BgpError::RouteFreshError(RouteRefreshError::Unknown(2))
         );
 
-        assert_eq!(BgpError::new(8, 2), BgpError::Unknown(8, 2));
+        assert_eq!(BgpError::new(8, 0), BgpError::SendHoldTimerExpired(0));
+        assert_eq!(BgpError::new(9, 2), BgpError::Unknown(9, 2));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            BgpError::new(6, 2).to_string(),
+            "Cease: Administrative Shutdown (6/2)"
+        );
+        assert_eq!(
+            BgpError::new(2, 8).to_string(),
+            "OPEN Message Error: Deprecated (2/8)"
+        );
+        assert_eq!(
+            BgpError::new(3, 12).to_string(),
+            "UPDATE Message Error: Unassigned (3/12)"
+        );
+        assert_eq!(BgpError::new(4, 0).to_string(), "Hold Timer Expired (4/0)");
+        assert_eq!(BgpError::new(9, 2).to_string(), "Unknown (9/2)");
     }
 }
@@ -0,0 +1,182 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::net::{AddrParseError, IpAddr, Ipv4Addr};
+use std::str::FromStr;
+
+/// 32-bit BGP identifier, e.g. the router ID in OPEN messages, AGGREGATOR and ORIGINATOR_ID
+/// attributes, or peer tables.
+///
+/// BGP identifiers are conventionally written as IPv4 addresses, but are arbitrary 32-bit values
+/// that do not need to be an address of the router ([RFC6286]). The raw value is preserved, and
+/// both the dotted-quad and the integer forms are accepted when parsing.
+///
+/// ```
+/// use bgpkit_parser::models::BgpIdentifier;
+/// use std::net::Ipv4Addr;
+///
+/// let id: BgpIdentifier = "192.0.2.1".parse().unwrap();
+/// assert_eq!(id, "3221225985".parse::<BgpIdentifier>().unwrap());
+/// assert_eq!(id.to_u32(), 3221225985);
+/// assert_eq!(id.to_string(), "192.0.2.1");
+/// assert_eq!(id, Ipv4Addr::new(192, 0, 2, 1));
+/// ```
+///
+/// [RFC6286]: https://datatracker.ietf.org/doc/html/rfc6286
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct BgpIdentifier(u32);
+
+impl BgpIdentifier {
+    pub const fn new(value: u32) -> Self {
+        BgpIdentifier(value)
+    }
+
+    pub const fn to_u32(&self) -> u32 {
+        self.0
+    }
+
+    /// The identifier in its conventional IPv4 address form.
+    pub const fn to_ipv4(&self) -> Ipv4Addr {
+        let [a, b, c, d] = self.0.to_be_bytes();
+        Ipv4Addr::new(a, b, c, d)
+    }
+}
+
+impl From<u32> for BgpIdentifier {
+    fn from(value: u32) -> Self {
+        BgpIdentifier(value)
+    }
+}
+
+impl From<Ipv4Addr> for BgpIdentifier {
+    fn from(value: Ipv4Addr) -> Self {
+        BgpIdentifier(u32::from(value))
+    }
+}
+
+impl From<BgpIdentifier> for u32 {
+    fn from(value: BgpIdentifier) -> Self {
+        value.0
+    }
+}
+
+impl From<BgpIdentifier> for Ipv4Addr {
+    fn from(value: BgpIdentifier) -> Self {
+        value.to_ipv4()
+    }
+}
+
+impl From<BgpIdentifier> for IpAddr {
+    fn from(value: BgpIdentifier) -> Self {
+        IpAddr::V4(value.to_ipv4())
+    }
+}
+
+impl PartialEq<Ipv4Addr> for BgpIdentifier {
+    fn eq(&self, other: &Ipv4Addr) -> bool {
+        self.0 == u32::from(*other)
+    }
+}
+
+impl PartialEq<BgpIdentifier> for Ipv4Addr {
+    fn eq(&self, other: &BgpIdentifier) -> bool {
+        u32::from(*self) == other.0
+    }
+}
+
+/// Parse a dotted-quad (`192.0.2.1`) or a decimal integer (`3221225985`) identifier.
+impl FromStr for BgpIdentifier {
+    type Err = AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match u32::from_str(s) {
+            Ok(v) => Ok(BgpIdentifier(v)),
+            Err(_) => Ipv4Addr::from_str(s).map(BgpIdentifier::from),
+        }
+    }
+}
+
+/// Displayed in the dotted-quad form.
+impl Display for BgpIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_ipv4())
+    }
+}
+
+// Keep the debug output as short as the one of the IPv4 address it used to be
+impl Debug for BgpIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_ipv4())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SerdeBgpIdentifierRepr {
+        Integer(u32),
+        Text(String),
+    }
+
+    /// Serialized as a dotted-quad string in human-readable formats, and as an integer otherwise.
+    impl Serialize for BgpIdentifier {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            if serializer.is_human_readable() {
+                serializer.collect_str(self)
+            } else {
+                serializer.serialize_u32(self.0)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BgpIdentifier {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match SerdeBgpIdentifierRepr::deserialize(deserializer)? {
+                SerdeBgpIdentifierRepr::Integer(v) => Ok(BgpIdentifier(v)),
+                SerdeBgpIdentifierRepr::Text(s) => BgpIdentifier::from_str(&s)
+                    .map_err(|_| D::Error::custom(format!("invalid BGP identifier {}", s))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bgp_identifier() {
+        let id = BgpIdentifier::new(0x0a000001);
+        assert_eq!(id.to_ipv4(), Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(format!("{} {:?}", id, id), "10.0.0.1 10.0.0.1");
+        assert_eq!(BgpIdentifier::from_str("10.0.0.1").unwrap(), id);
+        assert_eq!(BgpIdentifier::from_str("167772161").unwrap(), id);
+        assert!(BgpIdentifier::from_str("10.0.0.256").is_err());
+        assert_eq!(BgpIdentifier::from(Ipv4Addr::new(10, 0, 0, 1)), id);
+        assert_eq!(u32::from(id), 167772161);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_bgp_identifier_serde() {
+        let id = BgpIdentifier::new(0x0a000001);
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"10.0.0.1\"");
+        assert_eq!(
+            serde_json::from_str::<BgpIdentifier>("\"10.0.0.1\"").unwrap(),
+            id
+        );
+        assert_eq!(
+            serde_json::from_str::<BgpIdentifier>("167772161").unwrap(),
+            id
+        );
+    }
+}
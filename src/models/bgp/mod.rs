@@ -4,21 +4,22 @@ pub mod attributes;
 pub mod capabilities;
 pub mod community;
 pub mod elem;
+pub mod elem_ref;
 pub mod error;
+pub mod identifier;
 pub mod role;
 
 pub use attributes::*;
 pub use community::*;
 pub use elem::*;
+pub use elem_ref::*;
 pub use error::*;
+pub use identifier::*;
 pub use role::*;
 
 use crate::models::network::*;
 use capabilities::BgpCapabilityType;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use std::net::Ipv4Addr;
-
-pub type BgpIdentifier = Ipv4Addr;
 
 #[derive(Debug, TryFromPrimitive, IntoPrimitive, Copy, Clone, PartialEq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -78,7 +79,7 @@ pub struct BgpOpenMessage {
     pub version: u8,
     pub asn: Asn,
     pub hold_time: u16,
-    pub sender_ip: Ipv4Addr,
+    pub sender_ip: BgpIdentifier,
     pub extended_length: bool,
     pub opt_params: Vec<OptParam>,
 }
@@ -127,6 +128,7 @@ pub struct BgpNotificationMessage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::Ipv4Addr;
 
     #[test]
     fn test_message_type() {
@@ -134,7 +136,7 @@ mod tests {
             version: 4,
             asn: Asn::new_32bit(1),
             hold_time: 180,
-            sender_ip: Ipv4Addr::new(192, 0, 2, 1),
+            sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
             extended_length: false,
             opt_params: vec![],
         });
@@ -160,7 +162,7 @@ mod tests {
             version: 4,
             asn: Asn::new_32bit(1),
             hold_time: 180,
-            sender_ip: Ipv4Addr::new(192, 0, 2, 1),
+            sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
             extended_length: false,
             opt_params: vec![],
         });
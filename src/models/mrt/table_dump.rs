@@ -16,6 +16,48 @@ pub struct TableDumpMessage {
     pub attributes: Attributes,
 }
 
+impl TableDumpMessage {
+    /// MRT subtype of the entry, i.e. the AFI of the prefix: 1 for IPv4 and 2 for IPv6.
+    pub const fn subtype(&self) -> u16 {
+        match self.prefix.prefix {
+            ipnet::IpNet::V4(_) => 1,
+            ipnet::IpNet::V6(_) => 2,
+        }
+    }
+
+    /// The `AS_PATH` attribute as encoded in the entry.
+    ///
+    /// TABLE_DUMP entries always encode `AS_PATH` with 16-bit ASNs, so 32-bit ASNs appear as
+    /// `AS_TRANS` (23456) here.
+    pub fn raw_as_path(&self) -> Option<&AsPath> {
+        self.attributes.iter().find_map(|x| match x {
+            AttributeValue::AsPath {
+                path,
+                is_as4: false,
+            } => Some(path),
+            _ => None,
+        })
+    }
+
+    /// The `AS4_PATH` attribute if present.
+    pub fn as4_path(&self) -> Option<&AsPath> {
+        self.attributes.iter().find_map(|x| match x {
+            AttributeValue::AsPath { path, is_as4: true } => Some(path),
+            _ => None,
+        })
+    }
+
+    /// The AS path with `AS_TRANS` resolved by merging `AS_PATH` and `AS4_PATH` as specified in
+    /// [RFC 6793](https://datatracker.ietf.org/doc/html/rfc6793#section-4.2.3).
+    pub fn as_path(&self) -> Option<AsPath> {
+        match (self.raw_as_path(), self.as4_path()) {
+            (None, None) => None,
+            (Some(v), None) | (None, Some(v)) => Some(v.clone()),
+            (Some(v1), Some(v2)) => Some(AsPath::merge_aspath_as4path(v1, v2)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,6 +83,36 @@ mod tests {
         assert_eq!(cloned, table_dump_message);
     }
 
+    #[test]
+    fn test_as4_path_merge() {
+        let raw_path = AsPath::from_sequence([65000, 23456]);
+        let as4_path = AsPath::from_sequence([400000]);
+        let mut table_dump_message = TableDumpMessage {
+            view_number: 0,
+            sequence_number: 0,
+            prefix: NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+            status: 1,
+            originated_time: 1,
+            peer_address: IpAddr::from_str("192.0.2.1").unwrap(),
+            peer_asn: Asn::new_16bit(65000),
+            attributes: Attributes::from_iter([AttributeValue::from(raw_path.clone())]),
+        };
+        assert_eq!(table_dump_message.as_path(), Some(raw_path.clone()));
+
+        table_dump_message
+            .attributes
+            .add_attr(Attribute::from(AttributeValue::AsPath {
+                path: as4_path.clone(),
+                is_as4: true,
+            }));
+        assert_eq!(table_dump_message.raw_as_path(), Some(&raw_path));
+        assert_eq!(table_dump_message.as4_path(), Some(&as4_path));
+        assert_eq!(
+            table_dump_message.as_path(),
+            Some(AsPath::from_sequence([65000, 400000]))
+        );
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn serialize_and_deserialize_table_dump_message() {
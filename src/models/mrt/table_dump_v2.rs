@@ -3,8 +3,7 @@ use crate::models::*;
 use bitflags::bitflags;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
-use std::str::FromStr;
+use std::net::IpAddr;
 
 /// TableDump message version 2 enum
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,6 +13,7 @@ pub enum TableDumpV2Message {
     RibAfi(RibAfiEntries),
     /// Currently unsupported
     RibGeneric(RibGenericEntries),
+    GeoPeerTable(GeoPeerTable),
 }
 
 impl TableDumpV2Message {
@@ -22,6 +22,7 @@ impl TableDumpV2Message {
             TableDumpV2Message::PeerIndexTable(_) => TableDumpV2Type::PeerIndexTable,
             TableDumpV2Message::RibAfi(x) => x.rib_type,
             TableDumpV2Message::RibGeneric(_) => TableDumpV2Type::RibGeneric,
+            TableDumpV2Message::GeoPeerTable(_) => TableDumpV2Type::GeoPeerTable,
         }
     }
 }
@@ -141,6 +142,12 @@ pub struct RibGenericEntries {
 pub struct RibEntry {
     pub peer_index: u16,
     pub originated_time: u32,
+    /// path identifier of entries in ADD-PATH RIB subtypes ([RFC8050](https://datatracker.ietf.org/doc/html/rfc8050))
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub path_id: Option<u32>,
     pub attributes: Attributes,
 }
 
@@ -162,19 +169,180 @@ pub struct PeerIndexTable {
     pub view_name: String,
     pub id_peer_map: HashMap<u16, Peer>,
     pub peer_addr_id_map: HashMap<IpAddr, u16>,
+    /// Peer geo-locations merged from a [GeoPeerTable], keyed by peer ID.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub peer_geo_map: HashMap<u16, GeoLocation>,
 }
 
 impl Default for PeerIndexTable {
     fn default() -> Self {
         PeerIndexTable {
-            collector_bgp_id: Ipv4Addr::from_str("0.0.0.0").unwrap(),
+            collector_bgp_id: BgpIdentifier::new(0),
             view_name: "".to_string(),
             id_peer_map: HashMap::new(),
             peer_addr_id_map: HashMap::new(),
+            peer_geo_map: HashMap::new(),
         }
     }
 }
 
+/// Geo-location in decimal degrees, as used in [GeoPeerTable].
+///
+/// RFC6397 encodes both values as 32-bit IEEE floating point numbers.
+///
+/// Locations are equal if their coordinates have the same bit patterns, see [f32::to_bits], which
+/// keeps the equality reflexive for `Eq`: a NaN coordinate equals the same NaN, while `0.0` and
+/// `-0.0` differ.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoLocation {
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+impl PartialEq for GeoLocation {
+    fn eq(&self, other: &Self) -> bool {
+        self.latitude.to_bits() == other.latitude.to_bits()
+            && self.longitude.to_bits() == other.longitude.to_bits()
+    }
+}
+
+impl Eq for GeoLocation {}
+
+impl GeoLocation {
+    pub const fn new(latitude: f32, longitude: f32) -> Self {
+        GeoLocation {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Great-circle distance to the given coordinates in kilometers, using the haversine formula.
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = ((self.latitude as f64).to_radians(), latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = longitude.to_radians() - (self.longitude as f64).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// Geo-location peer table.
+///
+/// <https://www.rfc-editor.org/rfc/rfc6397#section-4>
+///
+/// ```text
+///         0                   1                   2                   3
+///         0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                      Collector BGP ID                         |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |       View Name Length        |     View Name (variable)      |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                      Collector Latitude                       |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                      Collector Longitude                      |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |          Peer Count           |    Peer Entries (variable)
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPeerTable {
+    pub collector_bgp_id: BgpIdentifier,
+    pub view_name: String,
+    pub collector_location: GeoLocation,
+    pub geo_peers: Vec<GeoPeer>,
+}
+
+impl Default for GeoPeerTable {
+    fn default() -> Self {
+        GeoPeerTable {
+            collector_bgp_id: BgpIdentifier::new(0),
+            view_name: "".to_string(),
+            collector_location: GeoLocation::default(),
+            geo_peers: vec![],
+        }
+    }
+}
+
+/// Geo-location peer entry.
+///
+/// ```text
+///         0                   1                   2                   3
+///         0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |   Peer Type   |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                         Peer BGP ID                           |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                        Peer Latitude                          |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///        |                        Peer Longitude                         |
+///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPeer {
+    pub peer_type: PeerType,
+    pub peer_bgp_id: BgpIdentifier,
+    pub location: GeoLocation,
+}
+
+impl PeerIndexTable {
+    /// Merge the peer locations from a [GeoPeerTable] into this peer index table.
+    ///
+    /// The geo peer entries are matched to the indexed peers by position. If the BGP ID at the
+    /// same position does not match, the first peer with the same BGP ID is used instead.
+    /// Returns the number of peers that received a location.
+    pub fn merge_geo_peer_table(&mut self, geo_table: &GeoPeerTable) -> usize {
+        let mut merged = 0;
+        for (index, geo_peer) in geo_table.geo_peers.iter().enumerate() {
+            let same_position = u16::try_from(index).ok().filter(|id| {
+                self.id_peer_map
+                    .get(id)
+                    .map(|p| p.peer_bgp_id == geo_peer.peer_bgp_id)
+                    .unwrap_or(false)
+            });
+            let peer_id = same_position.or_else(|| {
+                self.id_peer_map
+                    .iter()
+                    .filter(|(_, p)| p.peer_bgp_id == geo_peer.peer_bgp_id)
+                    .map(|(id, _)| *id)
+                    .min()
+            });
+            if let Some(id) = peer_id {
+                self.peer_geo_map.insert(id, geo_peer.location);
+                merged += 1;
+            }
+        }
+        merged
+    }
+
+    /// Returns the geo-location of the peer with the given ID, if known.
+    pub fn get_peer_location(&self, peer_id: &u16) -> Option<&GeoLocation> {
+        self.peer_geo_map.get(peer_id)
+    }
+
+    /// Returns the peers located within `radius_km` kilometers of the given coordinates.
+    ///
+    /// Only peers with a known location (see [PeerIndexTable::merge_geo_peer_table]) are
+    /// considered. The peers are returned in ascending order of their peer IDs.
+    pub fn peers_within(&self, radius_km: f64, latitude: f64, longitude: f64) -> Vec<&Peer> {
+        let mut ids: Vec<u16> = self
+            .peer_geo_map
+            .iter()
+            .filter(|(_, loc)| loc.distance_km(latitude, longitude) <= radius_km)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        ids.iter()
+            .filter_map(|id| self.id_peer_map.get(id))
+            .collect()
+    }
+}
+
 bitflags! {
     #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
     #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -218,10 +386,12 @@ impl Peer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
 
     // Create a helper function to initialize Peer structure
     fn create_peer() -> Peer {
-        let bgp_id = Ipv4Addr::from_str("1.1.1.1").unwrap();
+        let bgp_id = BgpIdentifier::from_str("1.1.1.1").unwrap();
         let peer_address: IpAddr = Ipv4Addr::from_str("2.2.2.2").unwrap().into();
         // Assuming Asn::new(u32) is defined.
         let asn = Asn::new_32bit(65000);
@@ -297,6 +467,64 @@ mod tests {
             rib_entries: vec![],
         });
         assert_eq!(rib_generic.dump_type(), TableDumpV2Type::RibGeneric);
+
+        let geo_peer_table = TableDumpV2Message::GeoPeerTable(GeoPeerTable::default());
+        assert_eq!(geo_peer_table.dump_type(), TableDumpV2Type::GeoPeerTable);
+    }
+
+    #[test]
+    fn test_geo_location_eq() {
+        let nan = GeoLocation::new(f32::NAN, 0.0);
+        assert_eq!(nan, nan);
+        assert_ne!(GeoLocation::new(0.0, 0.0), GeoLocation::new(-0.0, 0.0));
+        assert_eq!(
+            GeoLocation::new(51.51, -0.13),
+            GeoLocation::new(51.51, -0.13)
+        );
+    }
+
+    #[test]
+    fn test_merge_geo_peer_table() {
+        let mut index_table = PeerIndexTable::default();
+        let peer_1 = create_peer();
+        let peer_2 = Peer::new(
+            BgpIdentifier::from_str("3.3.3.3").unwrap(),
+            Ipv4Addr::from_str("4.4.4.4").unwrap().into(),
+            Asn::new_32bit(65001),
+        );
+        index_table.add_peer(peer_1);
+        index_table.add_peer(peer_2);
+
+        // entries listed in a different order than the index table
+        let geo_table = GeoPeerTable {
+            collector_bgp_id: BgpIdentifier::new(0),
+            view_name: "".to_string(),
+            collector_location: GeoLocation::new(0.0, 0.0),
+            geo_peers: vec![
+                GeoPeer {
+                    peer_type: PeerType::empty(),
+                    peer_bgp_id: peer_2.peer_bgp_id,
+                    // Tokyo
+                    location: GeoLocation::new(35.68, 139.69),
+                },
+                GeoPeer {
+                    peer_type: PeerType::empty(),
+                    peer_bgp_id: peer_1.peer_bgp_id,
+                    // London
+                    location: GeoLocation::new(51.51, -0.13),
+                },
+            ],
+        };
+        assert_eq!(index_table.merge_geo_peer_table(&geo_table), 2);
+        assert_eq!(
+            index_table.get_peer_location(&0),
+            Some(&GeoLocation::new(51.51, -0.13))
+        );
+
+        // Paris is about 340km away from London
+        assert_eq!(index_table.peers_within(500.0, 48.86, 2.35), vec![&peer_1]);
+        assert!(index_table.peers_within(100.0, 48.86, 2.35).is_empty());
+        assert_eq!(index_table.peers_within(20000.0, 0.0, 0.0).len(), 2);
     }
 
     #[test]
@@ -310,6 +538,7 @@ mod tests {
         let rib_entry = RibEntry {
             peer_index: 1,
             originated_time: 1,
+            path_id: None,
             attributes: Attributes::default(),
         };
         let rib_afi = TableDumpV2Message::RibAfi(RibAfiEntries {
@@ -138,6 +138,21 @@ impl Asn {
         }
     }
 
+    /// Shorthand for [Asn::is_reserved_for_documentation].
+    #[inline]
+    pub const fn is_documentation(&self) -> bool {
+        self.is_reserved_for_documentation()
+    }
+
+    /// Checks if the given ASN may be used on the public Internet, i.e. it is not included within
+    /// IANA's "Special-Purpose AS Numbers" registry. See [Asn::is_reserved] for the covered ranges.
+    ///
+    /// Note that this does not check whether the ASN has actually been allocated by an RIR.
+    #[inline]
+    pub const fn is_public(&self) -> bool {
+        !self.is_reserved()
+    }
+
     /// Return if an ASN is 4 bytes or not.
     #[inline]
     pub const fn is_four_byte(&self) -> bool {
@@ -358,6 +373,19 @@ mod tests {
         assert!(!asn.is_private());
     }
 
+    #[test]
+    fn test_asn_is_public() {
+        assert!(Asn::new_32bit(64497).is_documentation());
+        assert!(!Asn::new_32bit(400644).is_documentation());
+
+        assert!(Asn::new_32bit(400644).is_public());
+        assert!(Asn::new_16bit(3356).is_public());
+        assert!(Asn::new_32bit(4294967294).is_private() && !Asn::new_32bit(4294967294).is_public());
+        for asn in [0, 112, 23456, 64496, 64512, 65535, 65536, 4294967295] {
+            assert!(!Asn::new_32bit(asn).is_public());
+        }
+    }
+
     #[test]
     fn test_asn_display() {
         let asn = Asn::from_str("AS12345").unwrap();
@@ -0,0 +1,178 @@
+use crate::models::Asn;
+use smallvec::SmallVec;
+use std::fmt::{Display, Formatter};
+
+/// Sorted set of ASNs, e.g. the origin ASNs of a route.
+///
+/// ASNs are kept in ascending numeric order without duplicates, so iteration, [Display] and the
+/// serialized JSON array are deterministic regardless of the order the ASNs were added in. Sets
+/// with a single ASN, by far the most common case for origins, are stored inline.
+///
+/// ```
+/// use bgpkit_parser::models::{Asn, AsnSet};
+///
+/// let origins: AsnSet = [Asn::new_32bit(64497), Asn::new_32bit(64496), Asn::new_32bit(64497)]
+///     .into_iter()
+///     .collect();
+/// assert_eq!(origins.to_string(), "64496 64497");
+/// assert!(origins.contains(&Asn::new_32bit(64496)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<Asn>", into = "Vec<Asn>"))]
+pub struct AsnSet {
+    asns: SmallVec<[Asn; 1]>,
+}
+
+impl AsnSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert an ASN. Returns `false` if the ASN was already present.
+    pub fn insert(&mut self, asn: Asn) -> bool {
+        match self.asns.binary_search(&asn) {
+            Ok(_) => false,
+            Err(index) => {
+                self.asns.insert(index, asn);
+                true
+            }
+        }
+    }
+
+    pub fn contains(&self, asn: &Asn) -> bool {
+        self.asns.binary_search(asn).is_ok()
+    }
+
+    /// Whether the two sets have at least one ASN in common.
+    pub fn intersects(&self, other: &AsnSet) -> bool {
+        // both sets are sorted, so a single merge pass suffices
+        let (mut a, mut b) = (self.asns.iter().peekable(), other.asns.iter().peekable());
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            match x.cmp(y) {
+                std::cmp::Ordering::Less => {
+                    a.next();
+                }
+                std::cmp::Ordering::Greater => {
+                    b.next();
+                }
+                std::cmp::Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+
+    pub fn len(&self) -> usize {
+        self.asns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.asns.is_empty()
+    }
+
+    /// Iterate over the ASNs in ascending order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Asn> {
+        self.asns.iter()
+    }
+
+    pub fn as_slice(&self) -> &[Asn] {
+        self.asns.as_slice()
+    }
+}
+
+impl FromIterator<Asn> for AsnSet {
+    fn from_iter<T: IntoIterator<Item = Asn>>(iter: T) -> Self {
+        let mut asns: SmallVec<[Asn; 1]> = iter.into_iter().collect();
+        asns.sort_unstable();
+        asns.dedup();
+        AsnSet { asns }
+    }
+}
+
+impl From<Vec<Asn>> for AsnSet {
+    fn from(value: Vec<Asn>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl From<AsnSet> for Vec<Asn> {
+    fn from(value: AsnSet) -> Self {
+        value.asns.into_vec()
+    }
+}
+
+impl Extend<Asn> for AsnSet {
+    fn extend<T: IntoIterator<Item = Asn>>(&mut self, iter: T) {
+        for asn in iter {
+            self.insert(asn);
+        }
+    }
+}
+
+impl IntoIterator for AsnSet {
+    type Item = Asn;
+    type IntoIter = smallvec::IntoIter<[Asn; 1]>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.asns.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a AsnSet {
+    type Item = &'a Asn;
+    type IntoIter = std::slice::Iter<'a, Asn>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.asns.iter()
+    }
+}
+
+/// ASNs separated by spaces, in ascending order.
+impl Display for AsnSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, asn) in self.asns.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", asn)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(asns: &[u32]) -> AsnSet {
+        asns.iter().map(|asn| Asn::new_32bit(*asn)).collect()
+    }
+
+    #[test]
+    fn test_asn_set() {
+        let mut origins = set(&[3, 1, 2, 1]);
+        assert_eq!(origins.as_slice(), set(&[1, 2, 3]).as_slice());
+        assert_eq!(origins.len(), 3);
+        assert!(!origins.insert(Asn::new_32bit(2)));
+        assert!(origins.insert(Asn::new_32bit(0)));
+        assert_eq!(origins.to_string(), "0 1 2 3");
+        assert_eq!(origins, set(&[3, 0, 2, 1]));
+
+        assert!(origins.intersects(&set(&[3, 4])));
+        assert!(!origins.intersects(&set(&[4, 5])));
+        assert!(!origins.intersects(&AsnSet::new()));
+        assert!(AsnSet::new().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_asn_set_serde() {
+        let origins = set(&[64497, 64496]);
+        let json = serde_json::to_string(&origins).unwrap();
+        assert_eq!(json, "[64496,64497]");
+        assert_eq!(
+            serde_json::from_str::<AsnSet>("[64497,64496]").unwrap(),
+            origins
+        );
+    }
+}
@@ -2,10 +2,14 @@
 
 mod afi;
 mod asn;
+mod asn_set;
 mod nexthop;
 mod prefix;
+mod prefix_set;
 
 pub use afi::*;
 pub use asn::*;
+pub use asn_set::*;
 pub use nexthop::*;
 pub use prefix::*;
+pub use prefix_set::*;
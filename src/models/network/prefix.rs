@@ -1,7 +1,8 @@
-use crate::models::BgpModelsError;
+use crate::models::{well_known, BgpModelsError};
 #[cfg(feature = "parser")]
 use bytes::{BufMut, Bytes, BytesMut};
 use ipnet::IpNet;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 
@@ -37,6 +38,106 @@ impl NetworkPrefix {
         NetworkPrefix { prefix, path_id }
     }
 
+    /// Checks if this prefix covers `other`, i.e. `other` is the same prefix or one of its
+    /// more-specifics. Prefixes of different address families never cover each other, and path
+    /// IDs are ignored.
+    ///
+    /// ```rust
+    /// use std::str::FromStr;
+    /// use bgpkit_parser::models::NetworkPrefix;
+    ///
+    /// let prefix = NetworkPrefix::from_str("10.0.0.0/8").unwrap();
+    /// assert!(prefix.covers(&NetworkPrefix::from_str("10.1.0.0/16").unwrap()));
+    /// assert!(prefix.covers(&prefix));
+    /// assert!(!prefix.covers(&NetworkPrefix::from_str("11.0.0.0/16").unwrap()));
+    /// ```
+    pub fn covers(&self, other: &NetworkPrefix) -> bool {
+        self.prefix.contains(&other.prefix)
+    }
+
+    /// Checks if this prefix is a bogon, i.e. within a special-purpose block of the IANA
+    /// registries that should not be announced on the public Internet, see
+    /// [well_known::is_bogon_prefix](crate::models::well_known::is_bogon_prefix).
+    ///
+    /// ```rust
+    /// use std::str::FromStr;
+    /// use bgpkit_parser::models::NetworkPrefix;
+    ///
+    /// assert!(NetworkPrefix::from_str("10.1.0.0/16").unwrap().is_bogon());
+    /// assert!(NetworkPrefix::from_str("fe80::/64").unwrap().is_bogon());
+    /// assert!(!NetworkPrefix::from_str("1.1.1.0/24").unwrap().is_bogon());
+    /// ```
+    pub fn is_bogon(&self) -> bool {
+        well_known::is_bogon_prefix(&self.prefix)
+    }
+
+    /// Checks if this prefix is within the private IPv4 address space of [RFC1918].
+    ///
+    /// [RFC1918]: https://datatracker.ietf.org/doc/html/rfc1918
+    pub fn is_rfc1918(&self) -> bool {
+        well_known::PRIVATE_V4
+            .iter()
+            .any(|p| p.contains(&self.prefix))
+    }
+
+    /// Checks if this prefix is within one of the prefixes reserved for documentation.
+    pub fn is_documentation(&self) -> bool {
+        well_known::is_documentation_prefix(&self.prefix)
+    }
+
+    /// Aggregates the given prefixes into the minimal list of prefixes covering exactly the same
+    /// address space (CIDR aggregation). Overlapping prefixes are merged and adjacent prefixes are
+    /// combined into their covering prefix where possible.
+    ///
+    /// Path IDs are not preserved: all returned prefixes have a path ID of 0. The result is sorted,
+    /// with IPv4 prefixes before IPv6 prefixes.
+    ///
+    /// ```rust
+    /// use std::str::FromStr;
+    /// use bgpkit_parser::models::NetworkPrefix;
+    ///
+    /// let prefixes = ["10.0.0.0/24", "10.0.1.0/24", "10.0.1.128/25"]
+    ///     .iter()
+    ///     .map(|p| NetworkPrefix::from_str(p).unwrap())
+    ///     .collect();
+    /// let aggregated = NetworkPrefix::aggregate(prefixes);
+    /// assert_eq!(aggregated, vec![NetworkPrefix::from_str("10.0.0.0/23").unwrap()]);
+    /// ```
+    pub fn aggregate(prefixes: Vec<NetworkPrefix>) -> Vec<NetworkPrefix> {
+        let nets: Vec<IpNet> = prefixes.into_iter().map(|p| p.prefix).collect();
+        IpNet::aggregate(&nets)
+            .into_iter()
+            .map(|prefix| NetworkPrefix::new(prefix, 0))
+            .collect()
+    }
+
+    /// Splits this prefix into its more-specifics of the given length. The path ID is kept on
+    /// every returned prefix.
+    ///
+    /// The more-specifics are generated lazily, as there can be up to 2^128 of them, e.g. for
+    /// `::/0` split into `/128`s. Collect them only when the lengths are close enough.
+    ///
+    /// Returns `None` if `len` is shorter than the length of this prefix or longer than the
+    /// maximum prefix length of its address family.
+    ///
+    /// ```rust
+    /// use std::str::FromStr;
+    /// use bgpkit_parser::models::NetworkPrefix;
+    ///
+    /// let prefix = NetworkPrefix::from_str("10.0.0.0/23").unwrap();
+    /// let split: Vec<String> = prefix.split(24).unwrap().map(|p| p.to_string()).collect();
+    /// assert_eq!(split, vec!["10.0.0.0/24", "10.0.1.0/24"]);
+    /// assert!(prefix.split(22).is_none());
+    /// ```
+    pub fn split(&self, len: u8) -> Option<impl Iterator<Item = NetworkPrefix>> {
+        if len < self.prefix.prefix_len() {
+            return None;
+        }
+        let subnets = self.prefix.subnets(len).ok()?;
+        let path_id = self.path_id;
+        Some(subnets.map(move |prefix| NetworkPrefix::new(prefix, path_id)))
+    }
+
     #[cfg(feature = "parser")]
     /// Encodes the IPNet prefix into a byte slice.
     ///
@@ -85,6 +186,26 @@ impl NetworkPrefix {
     }
 }
 
+/// Network order: IPv4 prefixes sort before IPv6 prefixes, then by network address, then by
+/// prefix length (so a covering prefix sorts right before its more-specifics), and finally by the
+/// host bits of the address and the path ID.
+impl Ord for NetworkPrefix {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.prefix
+            .network()
+            .cmp(&other.prefix.network())
+            .then_with(|| self.prefix.prefix_len().cmp(&other.prefix.prefix_len()))
+            .then_with(|| self.prefix.addr().cmp(&other.prefix.addr()))
+            .then_with(|| self.path_id.cmp(&other.path_id))
+    }
+}
+
+impl PartialOrd for NetworkPrefix {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Display for NetworkPrefix {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.prefix)
@@ -188,6 +309,87 @@ mod tests {
         assert_eq!(deserialized.path_id, 1);
     }
 
+    #[test]
+    fn test_ordering() {
+        let mut prefixes: Vec<NetworkPrefix> = [
+            "2001:db8::/32",
+            "10.0.1.0/24",
+            "10.0.0.0/24",
+            "10.0.0.0/8",
+            "9.0.0.0/8",
+            "10.0.0.0/16",
+        ]
+        .iter()
+        .map(|s| NetworkPrefix::from_str(s).unwrap())
+        .collect();
+        prefixes.sort();
+        let sorted: Vec<String> = prefixes.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "9.0.0.0/8",
+                "10.0.0.0/8",
+                "10.0.0.0/16",
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+                "2001:db8::/32"
+            ]
+        );
+
+        let prefix = IpNet::from_str("10.0.0.0/24").unwrap();
+        assert!(NetworkPrefix::new(prefix, 1) < NetworkPrefix::new(prefix, 2));
+    }
+
+    #[test]
+    fn test_covers() {
+        let p = |s: &str| NetworkPrefix::from_str(s).unwrap();
+        assert!(p("10.0.0.0/8").covers(&p("10.0.0.0/8")));
+        assert!(p("10.0.0.0/8").covers(&p("10.255.0.0/16")));
+        assert!(!p("10.255.0.0/16").covers(&p("10.0.0.0/8")));
+        assert!(!p("10.0.0.0/8").covers(&p("2001:db8::/32")));
+        assert!(p("::/0").covers(&p("2001:db8::/32")));
+        assert!(!p("0.0.0.0/0").covers(&p("2001:db8::/32")));
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let p = |s: &str| NetworkPrefix::from_str(s).unwrap();
+        let prefixes = vec![
+            NetworkPrefix::new(IpNet::from_str("10.0.1.0/24").unwrap(), 3),
+            p("10.0.0.0/24"),
+            p("10.0.0.128/25"),
+            p("10.0.3.0/24"),
+            p("2001:db8:1::/48"),
+            p("2001:db8::/48"),
+        ];
+        assert_eq!(
+            NetworkPrefix::aggregate(prefixes),
+            vec![p("10.0.0.0/23"), p("10.0.3.0/24"), p("2001:db8::/47"),]
+        );
+        assert!(NetworkPrefix::aggregate(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_split() {
+        let prefix = NetworkPrefix::new(IpNet::from_str("10.0.0.0/22").unwrap(), 7);
+        let split: Vec<NetworkPrefix> = prefix.split(24).unwrap().collect();
+        assert_eq!(split.len(), 4);
+        assert_eq!(split[3].to_string(), "10.0.3.0/24");
+        assert!(split.iter().all(|p| p.path_id == 7 && prefix.covers(p)));
+
+        assert_eq!(prefix.split(22).unwrap().collect::<Vec<_>>(), vec![prefix]);
+        assert!(prefix.split(21).is_none());
+        assert!(prefix.split(33).is_none());
+
+        // huge splits are only generated as far as they are consumed
+        let default_route = NetworkPrefix::from_str("0.0.0.0/0").unwrap();
+        let mut split = default_route.split(32).unwrap();
+        assert_eq!(split.nth(1).unwrap().to_string(), "0.0.0.1/32");
+        let default_route = NetworkPrefix::from_str("::/0").unwrap();
+        let mut split = default_route.split(128).unwrap();
+        assert_eq!(split.next().unwrap().to_string(), "::/128");
+    }
+
     #[test]
     fn test_debug() {
         let prefix = IpNet::from_str("192.168.0.0/24").unwrap();
@@ -0,0 +1,287 @@
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::fmt::{Debug, Formatter};
+use std::iter::FromIterator;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Set of IP prefixes stored in a binary trie, for matching prefixes against large prefix lists.
+///
+/// Lookups take time proportional to the prefix length instead of the number of prefixes in the
+/// set. Besides exact lookups, the set can tell whether it contains a prefix covering a given
+/// prefix, or one covered by it.
+///
+/// ```
+/// use bgpkit_parser::models::PrefixSet;
+/// use ipnet::IpNet;
+///
+/// let set: PrefixSet = ["10.0.0.0/8", "192.0.2.0/24"]
+///     .iter()
+///     .map(|p| p.parse::<IpNet>().unwrap())
+///     .collect();
+/// assert!(set.contains(&"10.0.0.0/8".parse().unwrap()));
+/// assert!(set.contains_super(&"10.1.0.0/16".parse().unwrap()));
+/// assert!(set.contains_sub(&"192.0.0.0/16".parse().unwrap()));
+/// assert!(!set.contains_super(&"192.0.0.0/16".parse().unwrap()));
+/// ```
+#[derive(Clone, Default)]
+pub struct PrefixSet {
+    v4: PrefixTrie,
+    v6: PrefixTrie,
+    len: usize,
+}
+
+/// Binary trie over the leading bits of addresses, with nodes stored in a single vector.
+///
+/// Prefixes are never removed, so every node leads to at least one prefix of the set.
+#[derive(Clone, Default)]
+struct PrefixTrie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct TrieNode {
+    /// indices of the child nodes, 0 if absent as the root is never a child
+    children: [u32; 2],
+    /// whether the prefix ending at this node is in the set
+    present: bool,
+}
+
+#[inline]
+fn bit(bits: u128, index: u8) -> usize {
+    ((bits >> (127 - index)) & 1) as usize
+}
+
+impl PrefixTrie {
+    fn insert(&mut self, bits: u128, len: u8) -> bool {
+        if self.nodes.is_empty() {
+            self.nodes.push(TrieNode::default());
+        }
+        let mut node = 0;
+        for index in 0..len {
+            let b = bit(bits, index);
+            node = match self.nodes[node].children[b] {
+                0 => {
+                    self.nodes.push(TrieNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[b] = child as u32;
+                    child
+                }
+                child => child as usize,
+            };
+        }
+        let inserted = !self.nodes[node].present;
+        self.nodes[node].present = true;
+        inserted
+    }
+
+    /// Node of the prefix, if the trie has a node for it.
+    fn find(&self, bits: u128, len: u8) -> Option<&TrieNode> {
+        let mut node = self.nodes.first()?;
+        for index in 0..len {
+            match node.children[bit(bits, index)] {
+                0 => return None,
+                child => node = &self.nodes[child as usize],
+            }
+        }
+        Some(node)
+    }
+
+    fn contains(&self, bits: u128, len: u8) -> bool {
+        matches!(self.find(bits, len), Some(node) if node.present)
+    }
+
+    /// Whether the trie has a prefix equal to or less specific than the given one.
+    fn contains_super(&self, bits: u128, len: u8) -> bool {
+        let mut node = match self.nodes.first() {
+            Some(node) => node,
+            None => return false,
+        };
+        for index in 0..len {
+            if node.present {
+                return true;
+            }
+            match node.children[bit(bits, index)] {
+                0 => return false,
+                child => node = &self.nodes[child as usize],
+            }
+        }
+        node.present
+    }
+
+    /// Whether the trie has a prefix equal to or more specific than the given one.
+    fn contains_sub(&self, bits: u128, len: u8) -> bool {
+        // every node leads to a prefix of the set
+        self.find(bits, len).is_some()
+    }
+
+    /// Visit the prefixes in the order of their bits, less specific prefixes first.
+    fn for_each(&self, mut f: impl FnMut(u128, u8)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![(0usize, 0u128, 0u8)];
+        while let Some((node, bits, len)) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.present {
+                f(bits, len);
+            }
+            for b in [1, 0] {
+                let child = node.children[b];
+                if child != 0 {
+                    stack.push((child as usize, bits | ((b as u128) << (127 - len)), len + 1));
+                }
+            }
+        }
+    }
+}
+
+/// The address bits of a prefix aligned to the most significant bit, and its length.
+fn prefix_bits(prefix: &IpNet) -> (u128, u8) {
+    match prefix.addr() {
+        IpAddr::V4(addr) => ((u32::from(addr) as u128) << 96, prefix.prefix_len()),
+        IpAddr::V6(addr) => (u128::from(addr), prefix.prefix_len()),
+    }
+}
+
+impl PrefixSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn trie(&self, prefix: &IpNet) -> &PrefixTrie {
+        match prefix {
+            IpNet::V4(_) => &self.v4,
+            IpNet::V6(_) => &self.v6,
+        }
+    }
+
+    /// Add a prefix. Host bits of the prefix are ignored. Returns `false` if the prefix was
+    /// already in the set.
+    pub fn insert(&mut self, prefix: IpNet) -> bool {
+        let (bits, len) = prefix_bits(&prefix);
+        let inserted = match prefix {
+            IpNet::V4(_) => self.v4.insert(bits, len),
+            IpNet::V6(_) => self.v6.insert(bits, len),
+        };
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Number of prefixes in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the set contains exactly this prefix.
+    pub fn contains(&self, prefix: &IpNet) -> bool {
+        let (bits, len) = prefix_bits(prefix);
+        self.trie(prefix).contains(bits, len)
+    }
+
+    /// Whether the set contains this prefix or a less specific prefix covering it.
+    pub fn contains_super(&self, prefix: &IpNet) -> bool {
+        let (bits, len) = prefix_bits(prefix);
+        self.trie(prefix).contains_super(bits, len)
+    }
+
+    /// Whether the set contains this prefix or a more specific prefix covered by it.
+    pub fn contains_sub(&self, prefix: &IpNet) -> bool {
+        let (bits, len) = prefix_bits(prefix);
+        self.trie(prefix).contains_sub(bits, len)
+    }
+
+    /// All prefixes of the set, IPv4 first, sorted by address and less specific first.
+    pub fn prefixes(&self) -> Vec<IpNet> {
+        let mut prefixes = Vec::with_capacity(self.len);
+        self.v4.for_each(|bits, len| {
+            let addr = Ipv4Addr::from((bits >> 96) as u32);
+            prefixes.push(IpNet::V4(Ipv4Net::new(addr, len).unwrap()));
+        });
+        self.v6.for_each(|bits, len| {
+            let addr = Ipv6Addr::from(bits);
+            prefixes.push(IpNet::V6(Ipv6Net::new(addr, len).unwrap()));
+        });
+        prefixes
+    }
+}
+
+impl Debug for PrefixSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.prefixes()).finish()
+    }
+}
+
+// the layout of the tries depends on the insertion order, so compare the prefixes instead
+impl PartialEq for PrefixSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.prefixes() == other.prefixes()
+    }
+}
+
+impl Eq for PrefixSet {}
+
+impl FromIterator<IpNet> for PrefixSet {
+    fn from_iter<T: IntoIterator<Item = IpNet>>(iter: T) -> Self {
+        let mut set = PrefixSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<IpNet> for PrefixSet {
+    fn extend<T: IntoIterator<Item = IpNet>>(&mut self, iter: T) {
+        for prefix in iter {
+            self.insert(prefix);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn net(s: &str) -> IpNet {
+        IpNet::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_prefix_set() {
+        let mut set = PrefixSet::new();
+        assert!(!set.contains_super(&net("10.0.0.0/8")));
+        assert!(!set.contains_sub(&net("0.0.0.0/0")));
+
+        assert!(set.insert(net("10.0.0.0/8")));
+        assert!(set.insert(net("192.0.2.0/24")));
+        assert!(set.insert(net("2001:db8::/32")));
+        assert!(!set.insert(net("10.1.2.3/8")));
+        assert_eq!(set.len(), 3);
+
+        assert!(set.contains(&net("10.0.0.0/8")));
+        assert!(!set.contains(&net("10.0.0.0/9")));
+        assert!(set.contains_super(&net("10.1.0.0/16")));
+        assert!(set.contains_super(&net("10.0.0.0/8")));
+        assert!(!set.contains_super(&net("10.0.0.0/7")));
+        assert!(set.contains_sub(&net("10.0.0.0/7")));
+        assert!(set.contains_sub(&net("192.0.0.0/16")));
+        assert!(!set.contains_sub(&net("192.0.3.0/24")));
+
+        // address families are kept apart, a00::/8 has the same leading bits as 10.0.0.0/8
+        assert!(!set.contains(&net("a00::/8")));
+        assert!(!set.contains_super(&net("a00::/16")));
+        assert!(set.contains_sub(&net("::/0")));
+        assert!(set.contains_super(&net("2001:db8:1::/48")));
+
+        assert_eq!(
+            set.prefixes(),
+            vec![net("10.0.0.0/8"), net("192.0.2.0/24"), net("2001:db8::/32")]
+        );
+        let other: PrefixSet = set.prefixes().into_iter().rev().collect();
+        assert_eq!(set, other);
+    }
+}
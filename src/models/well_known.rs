@@ -0,0 +1,587 @@
+/*!
+Well-known prefixes, AS numbers, and community values.
+
+A single place for the special-use values defined by IANA registries and RFCs, to be shared by
+filters, validators and user code instead of repeating them.
+
+```
+use bgpkit_parser::models::well_known;
+use ipnet::IpNet;
+
+let prefix: IpNet = "192.0.2.0/25".parse().unwrap();
+assert!(well_known::is_documentation_prefix(&prefix));
+assert!(well_known::is_special_use_prefix(&prefix));
+assert!(well_known::is_bogon_prefix(&prefix));
+assert_eq!(well_known::community_name(0xFFFFFF01), Some("no-export"));
+```
+*/
+use crate::models::Asn;
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+
+/*
+Prefixes
+*/
+
+/// IPv4 prefixes reserved for documentation: TEST-NET-1, TEST-NET-2 and TEST-NET-3 ([RFC5737]).
+///
+/// [RFC5737]: https://datatracker.ietf.org/doc/html/rfc5737
+pub const DOCUMENTATION_V4: [IpNet; 3] = [
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(192, 0, 2, 0), 24)),
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(198, 51, 100, 0), 24)),
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(203, 0, 113, 0), 24)),
+];
+
+/// IPv6 prefix reserved for documentation ([RFC3849]).
+///
+/// [RFC3849]: https://datatracker.ietf.org/doc/html/rfc3849
+pub const DOCUMENTATION_V6: IpNet = IpNet::V6(Ipv6Net::new_assert(
+    Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0),
+    32,
+));
+
+/// Shared address space for carrier-grade NAT ([RFC6598]).
+///
+/// [RFC6598]: https://datatracker.ietf.org/doc/html/rfc6598
+pub const SHARED_ADDRESS_SPACE: IpNet =
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(100, 64, 0, 0), 10));
+
+/// Private IPv4 address space ([RFC1918]).
+///
+/// [RFC1918]: https://datatracker.ietf.org/doc/html/rfc1918
+pub const PRIVATE_V4: [IpNet; 3] = [
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(10, 0, 0, 0), 8)),
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(172, 16, 0, 0), 12)),
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(192, 168, 0, 0), 16)),
+];
+
+/// IPv6 unique local addresses ([RFC4193]).
+///
+/// [RFC4193]: https://datatracker.ietf.org/doc/html/rfc4193
+pub const UNIQUE_LOCAL_V6: IpNet = IpNet::V6(Ipv6Net::new_assert(
+    Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0),
+    7,
+));
+
+/// Whether the prefix is within one of the documentation prefixes.
+pub fn is_documentation_prefix(prefix: &IpNet) -> bool {
+    DOCUMENTATION_V4.iter().any(|p| p.contains(prefix)) || DOCUMENTATION_V6.contains(prefix)
+}
+
+/// Whether the prefix is within the shared address space.
+pub fn is_shared_address_prefix(prefix: &IpNet) -> bool {
+    SHARED_ADDRESS_SPACE.contains(prefix)
+}
+
+/// Whether the prefix is within the private IPv4 or unique local IPv6 address space.
+pub fn is_private_prefix(prefix: &IpNet) -> bool {
+    PRIVATE_V4.iter().any(|p| p.contains(prefix)) || UNIQUE_LOCAL_V6.contains(prefix)
+}
+
+/// Whether the prefix is within any of the special-use prefixes of this module, i.e. it should
+/// not be routed on the public Internet.
+pub fn is_special_use_prefix(prefix: &IpNet) -> bool {
+    is_documentation_prefix(prefix) || is_shared_address_prefix(prefix) || is_private_prefix(prefix)
+}
+
+/// Entry of the IANA special-purpose address registries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecialPurposePrefix {
+    pub prefix: IpNet,
+    /// registry name of the block, e.g. `Private-Use`
+    pub name: &'static str,
+    /// RFC defining the block, e.g. `RFC1918`
+    pub rfc: &'static str,
+    /// whether addresses of the block are globally reachable, i.e. can be announced on the
+    /// public Internet
+    pub globally_reachable: bool,
+}
+
+const fn v4(a: u8, b: u8, c: u8, d: u8, len: u8) -> IpNet {
+    IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::new(a, b, c, d), len))
+}
+
+const fn v6(segments: [u16; 8], len: u8) -> IpNet {
+    let [a, b, c, d, e, f, g, h] = segments;
+    IpNet::V6(Ipv6Net::new_assert(
+        Ipv6Addr::new(a, b, c, d, e, f, g, h),
+        len,
+    ))
+}
+
+const fn entry(
+    prefix: IpNet,
+    name: &'static str,
+    rfc: &'static str,
+    reachable: bool,
+) -> SpecialPurposePrefix {
+    SpecialPurposePrefix {
+        prefix,
+        name,
+        rfc,
+        globally_reachable: reachable,
+    }
+}
+
+/// The [IANA IPv4] and [IANA IPv6] special-purpose address registries, together with the
+/// multicast, reserved and returned blocks of the address space registries.
+///
+/// Blocks may be nested, e.g. the anycast addresses of `192.0.0.0/24`, in which case the most
+/// specific entry applies, see [special_purpose_entry]. Blocks the registries do not give a
+/// reachability for, such as the deprecated 6to4 relay anycast prefix, are marked as globally
+/// reachable only if they are routed in practice.
+///
+/// [IANA IPv4]: https://www.iana.org/assignments/iana-ipv4-special-registry
+/// [IANA IPv6]: https://www.iana.org/assignments/iana-ipv6-special-registry
+pub const SPECIAL_PURPOSE_PREFIXES: [SpecialPurposePrefix; 51] = [
+    // IPv4
+    entry(v4(0, 0, 0, 0, 8), "This network", "RFC791", false),
+    entry(
+        v4(0, 0, 0, 0, 32),
+        "This host on this network",
+        "RFC1122",
+        false,
+    ),
+    entry(v4(10, 0, 0, 0, 8), "Private-Use", "RFC1918", false),
+    entry(
+        v4(100, 64, 0, 0, 10),
+        "Shared Address Space",
+        "RFC6598",
+        false,
+    ),
+    entry(v4(127, 0, 0, 0, 8), "Loopback", "RFC1122", false),
+    entry(v4(169, 254, 0, 0, 16), "Link Local", "RFC3927", false),
+    entry(v4(172, 16, 0, 0, 12), "Private-Use", "RFC1918", false),
+    entry(
+        v4(192, 0, 0, 0, 24),
+        "IETF Protocol Assignments",
+        "RFC6890",
+        false,
+    ),
+    entry(
+        v4(192, 0, 0, 0, 29),
+        "IPv4 Service Continuity Prefix",
+        "RFC7335",
+        false,
+    ),
+    entry(v4(192, 0, 0, 8, 32), "IPv4 dummy address", "RFC7600", false),
+    entry(
+        v4(192, 0, 0, 9, 32),
+        "Port Control Protocol Anycast",
+        "RFC7723",
+        true,
+    ),
+    entry(
+        v4(192, 0, 0, 10, 32),
+        "Traversal Using Relays around NAT Anycast",
+        "RFC8155",
+        true,
+    ),
+    entry(
+        v4(192, 0, 0, 170, 32),
+        "NAT64/DNS64 Discovery",
+        "RFC8880",
+        false,
+    ),
+    entry(
+        v4(192, 0, 0, 171, 32),
+        "NAT64/DNS64 Discovery",
+        "RFC8880",
+        false,
+    ),
+    entry(
+        v4(192, 0, 2, 0, 24),
+        "Documentation (TEST-NET-1)",
+        "RFC5737",
+        false,
+    ),
+    entry(v4(192, 31, 196, 0, 24), "AS112-v4", "RFC7535", true),
+    entry(v4(192, 52, 193, 0, 24), "AMT", "RFC7450", true),
+    entry(
+        v4(192, 88, 99, 0, 24),
+        "Deprecated (6to4 Relay Anycast)",
+        "RFC7526",
+        false,
+    ),
+    entry(v4(192, 168, 0, 0, 16), "Private-Use", "RFC1918", false),
+    entry(
+        v4(192, 175, 48, 0, 24),
+        "Direct Delegation AS112 Service",
+        "RFC7534",
+        true,
+    ),
+    entry(v4(198, 18, 0, 0, 15), "Benchmarking", "RFC2544", false),
+    entry(
+        v4(198, 51, 100, 0, 24),
+        "Documentation (TEST-NET-2)",
+        "RFC5737",
+        false,
+    ),
+    entry(
+        v4(203, 0, 113, 0, 24),
+        "Documentation (TEST-NET-3)",
+        "RFC5737",
+        false,
+    ),
+    entry(v4(224, 0, 0, 0, 4), "Multicast", "RFC5771", false),
+    entry(v4(240, 0, 0, 0, 4), "Reserved", "RFC1112", false),
+    entry(
+        v4(255, 255, 255, 255, 32),
+        "Limited Broadcast",
+        "RFC919",
+        false,
+    ),
+    // IPv6
+    entry(
+        v6([0, 0, 0, 0, 0, 0, 0, 0], 8),
+        "Reserved by IETF",
+        "RFC4291",
+        false,
+    ),
+    entry(
+        v6([0, 0, 0, 0, 0, 0, 0, 1], 128),
+        "Loopback Address",
+        "RFC4291",
+        false,
+    ),
+    entry(
+        v6([0, 0, 0, 0, 0, 0, 0, 0], 128),
+        "Unspecified Address",
+        "RFC4291",
+        false,
+    ),
+    entry(
+        v6([0, 0, 0, 0, 0, 0xffff, 0, 0], 96),
+        "IPv4-mapped Address",
+        "RFC4291",
+        false,
+    ),
+    entry(
+        v6([0x64, 0xff9b, 0, 0, 0, 0, 0, 0], 96),
+        "IPv4-IPv6 Translat.",
+        "RFC6052",
+        true,
+    ),
+    entry(
+        v6([0x64, 0xff9b, 1, 0, 0, 0, 0, 0], 48),
+        "IPv4-IPv6 Translat.",
+        "RFC8215",
+        false,
+    ),
+    entry(
+        v6([0x100, 0, 0, 0, 0, 0, 0, 0], 64),
+        "Discard-Only Address Block",
+        "RFC6666",
+        false,
+    ),
+    entry(
+        v6([0x2001, 0, 0, 0, 0, 0, 0, 0], 23),
+        "IETF Protocol Assignments",
+        "RFC2928",
+        false,
+    ),
+    entry(
+        v6([0x2001, 0, 0, 0, 0, 0, 0, 0], 32),
+        "TEREDO",
+        "RFC4380",
+        true,
+    ),
+    entry(
+        v6([0x2001, 1, 0, 0, 0, 0, 0, 1], 128),
+        "Port Control Protocol Anycast",
+        "RFC7723",
+        true,
+    ),
+    entry(
+        v6([0x2001, 1, 0, 0, 0, 0, 0, 2], 128),
+        "Traversal Using Relays around NAT Anycast",
+        "RFC8155",
+        true,
+    ),
+    entry(
+        v6([0x2001, 2, 0, 0, 0, 0, 0, 0], 48),
+        "Benchmarking",
+        "RFC5180",
+        false,
+    ),
+    entry(
+        v6([0x2001, 3, 0, 0, 0, 0, 0, 0], 32),
+        "AMT",
+        "RFC7450",
+        true,
+    ),
+    entry(
+        v6([0x2001, 4, 0x112, 0, 0, 0, 0, 0], 48),
+        "AS112-v6",
+        "RFC7535",
+        true,
+    ),
+    entry(
+        v6([0x2001, 0x10, 0, 0, 0, 0, 0, 0], 28),
+        "Deprecated (previously ORCHID)",
+        "RFC4843",
+        false,
+    ),
+    entry(
+        v6([0x2001, 0x20, 0, 0, 0, 0, 0, 0], 28),
+        "ORCHIDv2",
+        "RFC7343",
+        true,
+    ),
+    entry(
+        v6([0x2001, 0xdb8, 0, 0, 0, 0, 0, 0], 32),
+        "Documentation",
+        "RFC3849",
+        false,
+    ),
+    entry(
+        v6([0x2002, 0, 0, 0, 0, 0, 0, 0], 16),
+        "6to4",
+        "RFC3056",
+        false,
+    ),
+    entry(
+        v6([0x3ffe, 0, 0, 0, 0, 0, 0, 0], 16),
+        "Returned (6bone)",
+        "RFC3701",
+        false,
+    ),
+    entry(
+        v6([0x3fff, 0, 0, 0, 0, 0, 0, 0], 20),
+        "Documentation",
+        "RFC9637",
+        false,
+    ),
+    entry(
+        v6([0x5f00, 0, 0, 0, 0, 0, 0, 0], 16),
+        "Segment Routing (SRv6) SIDs",
+        "RFC9602",
+        false,
+    ),
+    entry(
+        v6([0xfc00, 0, 0, 0, 0, 0, 0, 0], 7),
+        "Unique-Local",
+        "RFC4193",
+        false,
+    ),
+    entry(
+        v6([0xfe80, 0, 0, 0, 0, 0, 0, 0], 10),
+        "Link-Local Unicast",
+        "RFC4291",
+        false,
+    ),
+    entry(
+        v6([0xfec0, 0, 0, 0, 0, 0, 0, 0], 10),
+        "Deprecated (Site-Local)",
+        "RFC3879",
+        false,
+    ),
+    entry(
+        v6([0xff00, 0, 0, 0, 0, 0, 0, 0], 8),
+        "Multicast",
+        "RFC4291",
+        false,
+    ),
+];
+
+/// The most specific entry of [SPECIAL_PURPOSE_PREFIXES] containing the prefix, if any.
+pub fn special_purpose_entry(prefix: &IpNet) -> Option<&'static SpecialPurposePrefix> {
+    SPECIAL_PURPOSE_PREFIXES
+        .iter()
+        .filter(|e| e.prefix.contains(prefix))
+        .max_by_key(|e| e.prefix.prefix_len())
+}
+
+/// Whether the prefix is a bogon, i.e. within a special-purpose block that is not globally
+/// reachable and should therefore not be announced on the public Internet.
+///
+/// Prefixes covering a bogon block, such as the default route, are not bogons themselves.
+pub fn is_bogon_prefix(prefix: &IpNet) -> bool {
+    matches!(special_purpose_entry(prefix), Some(e) if !e.globally_reachable)
+}
+
+/*
+AS numbers
+*/
+
+/// `AS_TRANS`, used in 2-octet AS fields in place of 4-octet AS numbers ([RFC6793]).
+///
+/// [RFC6793]: https://datatracker.ietf.org/doc/html/rfc6793
+pub const AS_TRANS: Asn = Asn::TRANSITION;
+
+/// AS numbers reserved for documentation ([RFC5398]), see [Asn::is_reserved_for_documentation].
+///
+/// [RFC5398]: https://datatracker.ietf.org/doc/html/rfc5398
+pub const DOCUMENTATION_ASNS: [RangeInclusive<u32>; 2] = [64496..=64511, 65536..=65551];
+
+/// AS numbers reserved for private use ([RFC6996]), see [Asn::is_private].
+///
+/// [RFC6996]: https://datatracker.ietf.org/doc/html/rfc6996
+pub const PRIVATE_ASNS: [RangeInclusive<u32>; 2] = [64512..=65534, 4200000000..=4294967294];
+
+/*
+Communities
+*/
+
+/// `NO_EXPORT` community ([RFC1997]).
+///
+/// [RFC1997]: https://datatracker.ietf.org/doc/html/rfc1997
+pub const COMMUNITY_NO_EXPORT: u32 = 0xFFFFFF01;
+/// `NO_ADVERTISE` community ([RFC1997]).
+///
+/// [RFC1997]: https://datatracker.ietf.org/doc/html/rfc1997
+pub const COMMUNITY_NO_ADVERTISE: u32 = 0xFFFFFF02;
+/// `NO_EXPORT_SUBCONFED` community ([RFC1997]).
+///
+/// [RFC1997]: https://datatracker.ietf.org/doc/html/rfc1997
+pub const COMMUNITY_NO_EXPORT_SUBCONFED: u32 = 0xFFFFFF03;
+/// `NOPEER` community ([RFC3765]).
+///
+/// [RFC3765]: https://datatracker.ietf.org/doc/html/rfc3765
+pub const COMMUNITY_NOPEER: u32 = 0xFFFFFF04;
+/// `GRACEFUL_SHUTDOWN` community ([RFC8326]).
+///
+/// [RFC8326]: https://datatracker.ietf.org/doc/html/rfc8326
+pub const COMMUNITY_GRACEFUL_SHUTDOWN: u32 = 0xFFFF0000;
+/// `ACCEPT_OWN` community ([RFC7611]).
+///
+/// [RFC7611]: https://datatracker.ietf.org/doc/html/rfc7611
+pub const COMMUNITY_ACCEPT_OWN: u32 = 0xFFFF0001;
+/// `LLGR_STALE` community ([RFC9494]).
+///
+/// [RFC9494]: https://datatracker.ietf.org/doc/html/rfc9494
+pub const COMMUNITY_LLGR_STALE: u32 = 0xFFFF0006;
+/// `NO_LLGR` community ([RFC9494]).
+///
+/// [RFC9494]: https://datatracker.ietf.org/doc/html/rfc9494
+pub const COMMUNITY_NO_LLGR: u32 = 0xFFFF0007;
+/// `BLACKHOLE` community ([RFC7999]).
+///
+/// [RFC7999]: https://datatracker.ietf.org/doc/html/rfc7999
+pub const COMMUNITY_BLACKHOLE: u32 = 0xFFFF029A;
+
+/// Community values reserved by [RFC1997]: the first and the last 65536 values, i.e. those with
+/// AS number 0 or 65535. Well-known communities are allocated from the last range.
+///
+/// [RFC1997]: https://datatracker.ietf.org/doc/html/rfc1997
+pub const RESERVED_COMMUNITIES: [RangeInclusive<u32>; 2] =
+    [0x00000000..=0x0000FFFF, 0xFFFF0000..=0xFFFFFFFF];
+
+/// Whether the community value is in one of the reserved ranges.
+pub fn is_reserved_community(value: u32) -> bool {
+    RESERVED_COMMUNITIES.iter().any(|r| r.contains(&value))
+}
+
+/// Name of a well-known community value, as written in the IANA "BGP Well-known Communities"
+/// registry but in lowercase with dashes, e.g. `no-export`.
+pub fn community_name(value: u32) -> Option<&'static str> {
+    match value {
+        COMMUNITY_NO_EXPORT => Some("no-export"),
+        COMMUNITY_NO_ADVERTISE => Some("no-advertise"),
+        COMMUNITY_NO_EXPORT_SUBCONFED => Some("no-export-sub-confed"),
+        COMMUNITY_NOPEER => Some("nopeer"),
+        COMMUNITY_GRACEFUL_SHUTDOWN => Some("graceful-shutdown"),
+        COMMUNITY_ACCEPT_OWN => Some("accept-own"),
+        COMMUNITY_LLGR_STALE => Some("llgr-stale"),
+        COMMUNITY_NO_LLGR => Some("no-llgr"),
+        COMMUNITY_BLACKHOLE => Some("blackhole"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn net(s: &str) -> IpNet {
+        IpNet::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_special_use_prefixes() {
+        assert_eq!(DOCUMENTATION_V4[1], net("198.51.100.0/24"));
+        assert_eq!(DOCUMENTATION_V6, net("2001:db8::/32"));
+        assert_eq!(SHARED_ADDRESS_SPACE, net("100.64.0.0/10"));
+        assert_eq!(UNIQUE_LOCAL_V6, net("fc00::/7"));
+
+        assert!(is_documentation_prefix(&net("203.0.113.128/25")));
+        assert!(is_documentation_prefix(&net("2001:db8:1::/48")));
+        assert!(!is_documentation_prefix(&net("192.0.0.0/16")));
+        assert!(is_shared_address_prefix(&net("100.127.0.0/16")));
+        assert!(!is_shared_address_prefix(&net("100.128.0.0/16")));
+        assert!(is_private_prefix(&net("172.31.0.0/16")));
+        assert!(is_private_prefix(&net("fd00::/8")));
+        assert!(is_special_use_prefix(&net("10.1.0.0/16")));
+        assert!(!is_special_use_prefix(&net("1.1.1.0/24")));
+    }
+
+    #[test]
+    fn test_special_purpose_registry() {
+        let e = special_purpose_entry(&net("192.168.1.0/24")).unwrap();
+        assert_eq!(e.name, "Private-Use");
+        assert_eq!(e.rfc, "RFC1918");
+        // most specific entry applies
+        let e = special_purpose_entry(&net("192.0.0.9/32")).unwrap();
+        assert!(e.globally_reachable);
+        assert!(special_purpose_entry(&net("1.1.1.0/24")).is_none());
+
+        for bogon in [
+            "0.0.0.0/8",
+            "10.10.0.0/16",
+            "127.0.0.1/32",
+            "192.0.0.0/24",
+            "198.18.0.0/16",
+            "203.0.113.0/24",
+            "224.0.0.0/8",
+            "240.0.0.0/4",
+            "::1/128",
+            "2001:db8::/48",
+            "2002::/16",
+            "fe80::/64",
+            "ff02::/16",
+        ] {
+            assert!(is_bogon_prefix(&net(bogon)), "{}", bogon);
+        }
+        for prefix in [
+            "0.0.0.0/0",
+            "1.1.1.0/24",
+            "192.0.0.9/32",
+            "192.175.48.0/24",
+            "::/0",
+            "2001::/32",
+            "2001:4:112::/48",
+            "2606:4700::/32",
+        ] {
+            assert!(!is_bogon_prefix(&net(prefix)), "{}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_asns() {
+        assert_eq!(AS_TRANS.to_u32(), 23456);
+        for asn in [
+            0, 64495, 64496, 64511, 64512, 65534, 65536, 65551, 65552, 4200000000,
+        ] {
+            let asn = Asn::new_32bit(asn);
+            assert_eq!(
+                DOCUMENTATION_ASNS.iter().any(|r| r.contains(&asn.to_u32())),
+                asn.is_reserved_for_documentation()
+            );
+            assert_eq!(
+                PRIVATE_ASNS.iter().any(|r| r.contains(&asn.to_u32())),
+                asn.is_private()
+            );
+        }
+    }
+
+    #[test]
+    fn test_communities() {
+        assert!(is_reserved_community(COMMUNITY_BLACKHOLE));
+        assert!(is_reserved_community(100));
+        assert!(!is_reserved_community(0xFDE80064));
+        assert_eq!(community_name(COMMUNITY_NOPEER), Some("nopeer"));
+        assert_eq!(community_name(0xFFFF0002), None);
+    }
+}
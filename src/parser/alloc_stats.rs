@@ -0,0 +1,310 @@
+/*!
+Allocation statistics per parsing stage.
+
+[CountingAllocator] wraps a global allocator and counts all allocations and allocated bytes of
+the process. The parser measures the stages of reading record bytes, parsing records and
+converting records to elems with [enter_stage]: the allocations counted while a stage guard is
+alive are attributed to its [AllocStage], and everything else to [AllocStage::Other]. The
+statistics show where the allocations happen, e.g. to compare allocators or to check the effect
+of buffer reuse.
+
+As the counters are shared by all threads, a stage also counts the allocations other threads make
+at the same time. The statistics are exact for single-threaded parsing; with parallel parsing or a
+busy application they only give an estimate.
+
+The counters only increase while a [CountingAllocator] is installed as the global allocator:
+
+```no_run
+use bgpkit_parser::alloc_stats::{self, AllocStage, CountingAllocator};
+use bgpkit_parser::BgpkitParser;
+use std::alloc::System;
+
+#[global_allocator]
+static GLOBAL: CountingAllocator<System> = CountingAllocator::new(System);
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+alloc_stats::reset();
+let count = parser.into_elem_iter().count();
+let stats = alloc_stats::snapshot();
+println!(
+    "{} elems, {} allocations while parsing, {} bytes allocated for elems",
+    count,
+    stats.get(AllocStage::Parse).count,
+    stats.get(AllocStage::Elem).bytes,
+);
+```
+
+Freed memory is not tracked: the statistics count allocation activity, not memory usage.
+*/
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of [AllocStage]s.
+const NUM_STAGES: usize = 4;
+
+/// Stage of processing, used to attribute allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AllocStage {
+    /// outside of the parser stages, e.g. in user code
+    Other = 0,
+    /// reading the bytes of MRT records
+    Read = 1,
+    /// parsing MRT records
+    Parse = 2,
+    /// converting records to elems
+    Elem = 3,
+}
+
+impl AllocStage {
+    pub const ALL: [AllocStage; NUM_STAGES] = [
+        AllocStage::Other,
+        AllocStage::Read,
+        AllocStage::Parse,
+        AllocStage::Elem,
+    ];
+}
+
+/// Allocations of the process, counted by [CountingAllocator].
+static TOTAL_COUNT: AtomicU64 = AtomicU64::new(0);
+static TOTAL_BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+/// Allocations attributed to each stage by dropped [StageGuard]s.
+static COUNTS: [AtomicU64; NUM_STAGES] = [ZERO; NUM_STAGES];
+static BYTES: [AtomicU64; NUM_STAGES] = [ZERO; NUM_STAGES];
+/// Sum of all attributed allocations, to not attribute those of nested stages twice.
+static ATTRIBUTED_COUNT: AtomicU64 = AtomicU64::new(0);
+static ATTRIBUTED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Allocation counters at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Counters {
+    total: StageAllocStats,
+    attributed: StageAllocStats,
+}
+
+impl Counters {
+    fn load() -> Counters {
+        Counters {
+            total: StageAllocStats {
+                count: TOTAL_COUNT.load(Ordering::Relaxed),
+                bytes: TOTAL_BYTES.load(Ordering::Relaxed),
+            },
+            attributed: StageAllocStats {
+                count: ATTRIBUTED_COUNT.load(Ordering::Relaxed),
+                bytes: ATTRIBUTED_BYTES.load(Ordering::Relaxed),
+            },
+        }
+    }
+
+    /// Allocations between `start` and `self` that no nested stage has attributed yet.
+    fn unattributed_since(&self, start: &Counters) -> StageAllocStats {
+        let total_count = self.total.count.saturating_sub(start.total.count);
+        let total_bytes = self.total.bytes.saturating_sub(start.total.bytes);
+        let nested_count = self.attributed.count.saturating_sub(start.attributed.count);
+        let nested_bytes = self.attributed.bytes.saturating_sub(start.attributed.bytes);
+        StageAllocStats {
+            count: total_count.saturating_sub(nested_count),
+            bytes: total_bytes.saturating_sub(nested_bytes),
+        }
+    }
+}
+
+/// Guard attributing the allocations counted until it is dropped to a stage, see [enter_stage].
+pub struct StageGuard {
+    stage: AllocStage,
+    start: Counters,
+}
+
+impl Drop for StageGuard {
+    fn drop(&mut self) {
+        let stats = Counters::load().unattributed_since(&self.start);
+        let stage = self.stage as usize;
+        COUNTS[stage].fetch_add(stats.count, Ordering::Relaxed);
+        BYTES[stage].fetch_add(stats.bytes, Ordering::Relaxed);
+        ATTRIBUTED_COUNT.fetch_add(stats.count, Ordering::Relaxed);
+        ATTRIBUTED_BYTES.fetch_add(stats.bytes, Ordering::Relaxed);
+    }
+}
+
+/// Attribute the allocations counted from now on to `stage` until the guard is dropped.
+///
+/// Allocations of a stage entered while the guard is alive are only attributed to the inner stage.
+pub fn enter_stage(stage: AllocStage) -> StageGuard {
+    StageGuard {
+        stage,
+        start: Counters::load(),
+    }
+}
+
+fn record_alloc(size: usize) {
+    TOTAL_COUNT.fetch_add(1, Ordering::Relaxed);
+    TOTAL_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+/// Allocations of one stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StageAllocStats {
+    /// number of allocations, including reallocations
+    pub count: u64,
+    /// number of bytes allocated, with reallocations counting their new size
+    pub bytes: u64,
+}
+
+/// Allocations of all stages since the start of the process or the last [reset].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AllocStats {
+    stages: [StageAllocStats; NUM_STAGES],
+}
+
+impl AllocStats {
+    pub fn get(&self, stage: AllocStage) -> StageAllocStats {
+        self.stages[stage as usize]
+    }
+
+    /// Allocations of all stages combined.
+    pub fn total(&self) -> StageAllocStats {
+        self.stages
+            .iter()
+            .fold(StageAllocStats::default(), |total, s| StageAllocStats {
+                count: total.count + s.count,
+                bytes: total.bytes + s.bytes,
+            })
+    }
+
+    /// Allocations between an earlier snapshot and this one.
+    pub fn since(&self, earlier: &AllocStats) -> AllocStats {
+        let mut stats = AllocStats::default();
+        for (i, s) in stats.stages.iter_mut().enumerate() {
+            s.count = self.stages[i].count.saturating_sub(earlier.stages[i].count);
+            s.bytes = self.stages[i].bytes.saturating_sub(earlier.stages[i].bytes);
+        }
+        stats
+    }
+}
+
+/// Current allocation statistics of all threads.
+///
+/// Allocations of stages that are still running are counted as [AllocStage::Other] until their
+/// guards are dropped.
+pub fn snapshot() -> AllocStats {
+    let counters = Counters::load();
+    let mut stats = AllocStats::default();
+    for (i, s) in stats.stages.iter_mut().enumerate() {
+        s.count = COUNTS[i].load(Ordering::Relaxed);
+        s.bytes = BYTES[i].load(Ordering::Relaxed);
+    }
+    let other = &mut stats.stages[AllocStage::Other as usize];
+    other.count += counters
+        .total
+        .count
+        .saturating_sub(counters.attributed.count);
+    other.bytes += counters
+        .total
+        .bytes
+        .saturating_sub(counters.attributed.bytes);
+    stats
+}
+
+/// Reset the allocation statistics to zero.
+pub fn reset() {
+    for (count, bytes) in COUNTS.iter().zip(BYTES.iter()) {
+        count.store(0, Ordering::Relaxed);
+        bytes.store(0, Ordering::Relaxed);
+    }
+    for counter in [
+        &TOTAL_COUNT,
+        &TOTAL_BYTES,
+        &ATTRIBUTED_COUNT,
+        &ATTRIBUTED_BYTES,
+    ] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Global allocator counting the allocations of the wrapped allocator.
+pub struct CountingAllocator<A> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        CountingAllocator { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_alloc(layout.size());
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_alloc(new_size);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counters(total: u64, attributed: u64) -> Counters {
+        Counters {
+            total: StageAllocStats {
+                count: total,
+                bytes: total * 10,
+            },
+            attributed: StageAllocStats {
+                count: attributed,
+                bytes: attributed * 10,
+            },
+        }
+    }
+
+    #[test]
+    fn test_unattributed_since() {
+        // outer stage started at 5 allocations, 2 of them attributed before
+        let start = counters(5, 2);
+        // 10 allocations later, a nested stage attributed 4 of them
+        let end = counters(15, 6);
+        assert_eq!(
+            end.unattributed_since(&start),
+            StageAllocStats {
+                count: 6,
+                bytes: 60
+            }
+        );
+        // counters reset while the stage was running
+        assert_eq!(
+            counters(1, 0).unattributed_since(&start),
+            StageAllocStats::default()
+        );
+    }
+
+    #[test]
+    fn test_stage_guard() {
+        let before = snapshot();
+        {
+            let _stage = enter_stage(AllocStage::Parse);
+            record_alloc(100);
+        }
+        let stats = snapshot().since(&before);
+        assert!(stats.total().count >= 1);
+        assert!(stats.total().bytes >= 100);
+    }
+}
@@ -0,0 +1,318 @@
+/*!
+AS-level topology extraction from streams of BGP elems.
+
+[AsGraphBuilder] records an undirected edge for every pair of adjacent ASes in the AS paths of
+announcements, along with when the edge was first and last seen and how many times it was
+observed. Builders of separate inputs can be merged, and the resulting graph exported as an edge
+list CSV or as GraphML.
+
+```no_run
+use bgpkit_parser::analysis::AsGraphBuilder;
+use bgpkit_parser::BgpkitParser;
+
+let mut builder = AsGraphBuilder::new();
+builder.extend(BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap());
+println!("{} edges", builder.num_edges());
+builder.write_csv(std::io::stdout()).unwrap();
+```
+*/
+use crate::models::{AsPath, AsPathSegment, Asn, BgpElem};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+
+/// How AS sets in AS paths are turned into edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsSetPolicy {
+    /// ignore AS paths containing an AS set
+    #[default]
+    Discard,
+    /// use the AS path up to the first AS set
+    Truncate,
+    /// connect every member of an AS set to its neighboring ASes
+    Expand,
+}
+
+/// Observations of an edge between two ASes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AsEdgeInfo {
+    /// timestamp of the first observation
+    pub first_seen: f64,
+    /// timestamp of the last observation
+    pub last_seen: f64,
+    /// number of AS paths the edge was observed in
+    pub count: u64,
+}
+
+impl AsEdgeInfo {
+    fn observe(&mut self, timestamp: f64) {
+        self.first_seen = self.first_seen.min(timestamp);
+        self.last_seen = self.last_seen.max(timestamp);
+        self.count += 1;
+    }
+
+    fn merge(&mut self, other: &AsEdgeInfo) {
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+        self.count += other.count;
+    }
+}
+
+/// Incrementally built AS adjacency graph.
+///
+/// Edges are undirected and keyed by the ASN pair with the lower ASN first. Consecutive
+/// duplicates caused by AS path prepending and confederation segments are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct AsGraphBuilder {
+    as_set_policy: AsSetPolicy,
+    edges: HashMap<(Asn, Asn), AsEdgeInfo>,
+}
+
+impl AsGraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how AS sets are handled. Defaults to [AsSetPolicy::Discard].
+    pub fn with_as_set_policy(mut self, policy: AsSetPolicy) -> Self {
+        self.as_set_policy = policy;
+        self
+    }
+
+    /// Add the AS path of an announcement. Withdrawals and elems without AS path are ignored.
+    pub fn add_elem(&mut self, elem: &BgpElem) {
+        if let Some(path) = &elem.as_path {
+            self.add_path(path, elem.timestamp);
+        }
+    }
+
+    /// Add the edges of an AS path observed at `timestamp`.
+    pub fn add_path(&mut self, path: &AsPath, timestamp: f64) {
+        let hops = match self.path_hops(path) {
+            Some(hops) => hops,
+            None => return,
+        };
+
+        // an edge appearing more than once in the same path is only counted once
+        let mut edges = BTreeSet::new();
+        for pair in hops.windows(2) {
+            for &a in pair[0] {
+                for &b in pair[1] {
+                    if a != b {
+                        edges.insert((a.min(b), a.max(b)));
+                    }
+                }
+            }
+        }
+
+        for edge in edges {
+            self.edges
+                .entry(edge)
+                .or_insert(AsEdgeInfo {
+                    first_seen: timestamp,
+                    last_seen: timestamp,
+                    count: 0,
+                })
+                .observe(timestamp);
+        }
+    }
+
+    /// Merge the edges of another builder, e.g. one built from a different file.
+    pub fn merge(&mut self, other: &AsGraphBuilder) {
+        for (edge, info) in &other.edges {
+            self.edges
+                .entry(*edge)
+                .and_modify(|e| e.merge(info))
+                .or_insert(*info);
+        }
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Number of distinct ASes with at least one edge.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes().len()
+    }
+
+    /// Get the observations of the edge between two ASes, in either order.
+    pub fn edge(&self, a: Asn, b: Asn) -> Option<&AsEdgeInfo> {
+        self.edges.get(&(a.min(b), a.max(b)))
+    }
+
+    /// All edges sorted by ASN pair.
+    pub fn edges(&self) -> Vec<((Asn, Asn), AsEdgeInfo)> {
+        let mut edges: Vec<_> = self.edges.iter().map(|(k, v)| (*k, *v)).collect();
+        edges.sort_by_key(|(k, _)| *k);
+        edges
+    }
+
+    /// All ASes with at least one edge, sorted.
+    pub fn nodes(&self) -> BTreeSet<Asn> {
+        self.edges.keys().flat_map(|(a, b)| [*a, *b]).collect()
+    }
+
+    /// Write the edge list as CSV with the header `asn1,asn2,first_seen,last_seen,count`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "asn1,asn2,first_seen,last_seen,count")?;
+        for ((a, b), info) in self.edges() {
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                a, b, info.first_seen, info.last_seen, info.count
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the graph as GraphML, with node IDs of the form `AS<number>` and the edge
+    /// observations as edge data.
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (id, attr_type) in [
+            ("first_seen", "double"),
+            ("last_seen", "double"),
+            ("count", "long"),
+        ] {
+            writeln!(
+                writer,
+                r#"  <key id="{}" for="edge" attr.name="{}" attr.type="{}"/>"#,
+                id, id, attr_type
+            )?;
+        }
+        writeln!(
+            writer,
+            r#"  <graph id="as_graph" edgedefault="undirected">"#
+        )?;
+        for asn in self.nodes() {
+            writeln!(writer, r#"    <node id="AS{}"/>"#, asn)?;
+        }
+        for ((a, b), info) in self.edges() {
+            writeln!(writer, r#"    <edge source="AS{}" target="AS{}">"#, a, b)?;
+            writeln!(
+                writer,
+                r#"      <data key="first_seen">{}</data>"#,
+                info.first_seen
+            )?;
+            writeln!(
+                writer,
+                r#"      <data key="last_seen">{}</data>"#,
+                info.last_seen
+            )?;
+            writeln!(writer, r#"      <data key="count">{}</data>"#, info.count)?;
+            writeln!(writer, "    </edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+
+    /// Convert the path into hops of one or more ASNs, or `None` if the path is discarded.
+    fn path_hops<'a>(&self, path: &'a AsPath) -> Option<Vec<&'a [Asn]>> {
+        let mut hops: Vec<&[Asn]> = vec![];
+        for segment in path.iter_segments() {
+            match segment {
+                AsPathSegment::AsSequence(asns) => {
+                    for asn in asns.iter() {
+                        let hop = std::slice::from_ref(asn);
+                        // skip prepended ASNs
+                        if hops.last() != Some(&hop) {
+                            hops.push(hop);
+                        }
+                    }
+                }
+                AsPathSegment::AsSet(asns) => match self.as_set_policy {
+                    AsSetPolicy::Discard => return None,
+                    AsSetPolicy::Truncate => break,
+                    AsSetPolicy::Expand => hops.push(asns.as_slice()),
+                },
+                AsPathSegment::ConfedSequence(_) | AsPathSegment::ConfedSet(_) => {}
+            }
+        }
+        Some(hops)
+    }
+}
+
+impl Extend<BgpElem> for AsGraphBuilder {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.add_elem(&elem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asn(n: u32) -> Asn {
+        Asn::new_32bit(n)
+    }
+
+    #[test]
+    fn test_add_path() {
+        let mut builder = AsGraphBuilder::new();
+        builder.add_path(&AsPath::from_sequence([3, 2, 2, 1]), 10.0);
+        builder.add_path(&AsPath::from_sequence([4, 2, 1]), 5.0);
+
+        assert_eq!(builder.num_edges(), 3);
+        assert_eq!(builder.num_nodes(), 4);
+        assert_eq!(
+            builder.edge(asn(1), asn(2)),
+            Some(&AsEdgeInfo {
+                first_seen: 5.0,
+                last_seen: 10.0,
+                count: 2,
+            })
+        );
+        assert_eq!(builder.edge(asn(3), asn(2)).unwrap().count, 1);
+        assert!(builder.edge(asn(3), asn(1)).is_none());
+    }
+
+    #[test]
+    fn test_as_set_policy() {
+        let path = AsPath::from_segments(vec![
+            AsPathSegment::sequence([3, 2]),
+            AsPathSegment::set([5, 6]),
+        ]);
+
+        let mut builder = AsGraphBuilder::new();
+        builder.add_path(&path, 0.0);
+        assert_eq!(builder.num_edges(), 0);
+
+        let mut builder = AsGraphBuilder::new().with_as_set_policy(AsSetPolicy::Truncate);
+        builder.add_path(&path, 0.0);
+        assert_eq!(builder.num_edges(), 1);
+
+        let mut builder = AsGraphBuilder::new().with_as_set_policy(AsSetPolicy::Expand);
+        builder.add_path(&path, 0.0);
+        assert_eq!(builder.num_edges(), 3);
+        assert!(builder.edge(asn(2), asn(6)).is_some());
+    }
+
+    #[test]
+    fn test_merge_and_export() {
+        let mut a = AsGraphBuilder::new();
+        a.add_path(&AsPath::from_sequence([2, 1]), 1.0);
+        let mut b = AsGraphBuilder::new();
+        b.add_path(&AsPath::from_sequence([3, 1, 2]), 2.0);
+        a.merge(&b);
+
+        let mut csv = vec![];
+        a.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "asn1,asn2,first_seen,last_seen,count\n1,2,1,2,2\n1,3,2,2,1\n"
+        );
+
+        let mut graphml = vec![];
+        a.write_graphml(&mut graphml).unwrap();
+        let graphml = String::from_utf8(graphml).unwrap();
+        assert_eq!(graphml.matches("<node ").count(), 3);
+        assert!(graphml.contains(r#"<edge source="AS1" target="AS3">"#));
+    }
+}
@@ -0,0 +1,244 @@
+/*!
+Detection of graceful shutdown session drains.
+
+Before taking a BGP session down for maintenance, operators can re-announce the routes of the
+session tagged with the `GRACEFUL_SHUTDOWN` community ([RFC8326]), so that neighbors move traffic
+away before the session goes down. [DrainDetector] tracks bursts of such tagged re-announcements
+per peer and reports a [DrainEvent] when the session goes down shortly after.
+
+Session state changes are only available from MRT BGP4MP state change records (or BMP peer down
+notifications, see [DrainDetector::session_down]), so the detector is fed with records rather than
+elems.
+
+```no_run
+use bgpkit_parser::analysis::DrainDetector;
+use bgpkit_parser::BgpkitParser;
+
+let mut detector = DrainDetector::new().with_window(300.0);
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+for record in parser.into_record_iter() {
+    if let Some(event) = detector.add_record(&record) {
+        println!(
+            "{} AS{} drained {} prefixes before going down at {}",
+            event.peer_ip, event.peer_asn, event.prefixes, event.session_down
+        );
+    }
+}
+```
+
+[RFC8326]: https://datatracker.ietf.org/doc/html/rfc8326
+*/
+use crate::models::*;
+use crate::Elementor;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Default maximum gap in seconds between the events of a drain.
+const DEFAULT_WINDOW: f64 = 600.0;
+
+/// A burst of graceful shutdown announcements followed by the session going down.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DrainEvent {
+    pub peer_ip: IpAddr,
+    pub peer_asn: Asn,
+    /// timestamp of the first tagged announcement
+    pub drain_start: f64,
+    /// timestamp of the last tagged announcement
+    pub drain_end: f64,
+    /// number of tagged announcements
+    pub announcements: u64,
+    /// number of distinct prefixes announced with the tag
+    pub prefixes: usize,
+    /// timestamp of the session going down
+    pub session_down: f64,
+}
+
+/// Tagged announcements of a peer not yet followed by the session going down.
+#[derive(Debug, Clone)]
+struct DrainBurst {
+    start: f64,
+    end: f64,
+    announcements: u64,
+    prefixes: HashSet<NetworkPrefix>,
+}
+
+/// Per-peer detector of graceful shutdown drains.
+#[derive(Debug, Clone)]
+pub struct DrainDetector {
+    window: f64,
+    min_prefixes: usize,
+    bursts: HashMap<(IpAddr, Asn), DrainBurst>,
+}
+
+impl Default for DrainDetector {
+    fn default() -> Self {
+        DrainDetector {
+            window: DEFAULT_WINDOW,
+            min_prefixes: 1,
+            bursts: HashMap::new(),
+        }
+    }
+}
+
+impl DrainDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum gap in seconds between consecutive tagged announcements of a burst, and
+    /// between the last of them and the session going down. Defaults to 600.
+    pub fn with_window(mut self, seconds: f64) -> Self {
+        self.window = seconds;
+        self
+    }
+
+    /// Set the minimum number of distinct tagged prefixes for a drain to be reported. Defaults to 1.
+    pub fn with_min_prefixes(mut self, min_prefixes: usize) -> Self {
+        self.min_prefixes = min_prefixes;
+        self
+    }
+
+    /// Add an elem. Only announcements with the `GRACEFUL_SHUTDOWN` community are considered.
+    pub fn add_elem(&mut self, elem: &BgpElem) {
+        if !elem.is_announcement() || !elem.is_graceful_shutdown() {
+            return;
+        }
+        let window = self.window;
+        let burst = self
+            .bursts
+            .entry((elem.peer_ip, elem.peer_asn))
+            .or_insert_with(|| DrainBurst {
+                start: elem.timestamp,
+                end: elem.timestamp,
+                announcements: 0,
+                prefixes: HashSet::new(),
+            });
+        if elem.timestamp - burst.end > window {
+            // too long since the last tagged announcement, start a new burst
+            *burst = DrainBurst {
+                start: elem.timestamp,
+                end: elem.timestamp,
+                announcements: 0,
+                prefixes: HashSet::new(),
+            };
+        }
+        burst.end = burst.end.max(elem.timestamp);
+        burst.announcements += 1;
+        burst.prefixes.insert(elem.prefix);
+    }
+
+    /// Report the session with a peer going down, e.g. from a BMP peer down notification.
+    ///
+    /// Returns the drain event if the peer sent tagged announcements within the window before.
+    pub fn session_down(
+        &mut self,
+        peer_ip: IpAddr,
+        peer_asn: Asn,
+        timestamp: f64,
+    ) -> Option<DrainEvent> {
+        let burst = self.bursts.remove(&(peer_ip, peer_asn))?;
+        if timestamp - burst.end > self.window || burst.prefixes.len() < self.min_prefixes {
+            return None;
+        }
+        Some(DrainEvent {
+            peer_ip,
+            peer_asn,
+            drain_start: burst.start,
+            drain_end: burst.end,
+            announcements: burst.announcements,
+            prefixes: burst.prefixes.len(),
+            session_down: timestamp,
+        })
+    }
+
+    /// Add an MRT record.
+    ///
+    /// BGP4MP updates are added with [DrainDetector::add_elem], and BGP4MP state changes leaving
+    /// the established state are reported with [DrainDetector::session_down].
+    pub fn add_record(&mut self, record: &MrtRecord) -> Option<DrainEvent> {
+        let header = &record.common_header;
+        let timestamp = header.timestamp as f64
+            + header.microsecond_timestamp.unwrap_or(0) as f64 / 1_000_000.0;
+        match &record.message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => {
+                if let BgpMessage::Update(_) = &msg.bgp_message {
+                    let elems = Elementor::bgp_to_elems(
+                        msg.bgp_message.clone(),
+                        timestamp,
+                        &msg.peer_ip,
+                        &msg.peer_asn,
+                    );
+                    for elem in &elems {
+                        self.add_elem(elem);
+                    }
+                }
+                None
+            }
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::StateChange(msg))
+                if msg.old_state == BgpState::Established
+                    && msg.new_state != BgpState::Established =>
+            {
+                self.session_down(msg.peer_addr, msg.peer_asn, timestamp)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn gshut_elem(timestamp: f64, prefix: &str) -> BgpElem {
+        BgpElem {
+            timestamp,
+            peer_ip: IpAddr::from_str("192.0.2.1").unwrap(),
+            peer_asn: Asn::new_32bit(64496),
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            communities: Some(vec![MetaCommunity::Plain(Community::GRACEFUL_SHUTDOWN)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_drain_detector() {
+        let peer_ip = IpAddr::from_str("192.0.2.1").unwrap();
+        let peer_asn = Asn::new_32bit(64496);
+        let mut detector = DrainDetector::new().with_window(60.0);
+
+        // an untagged announcement is ignored
+        let mut elem = gshut_elem(10.0, "10.0.0.0/8");
+        elem.communities = None;
+        detector.add_elem(&elem);
+        assert!(detector.session_down(peer_ip, peer_asn, 20.0).is_none());
+
+        detector.add_elem(&gshut_elem(100.0, "10.0.0.0/8"));
+        detector.add_elem(&gshut_elem(110.0, "10.1.0.0/16"));
+        detector.add_elem(&gshut_elem(120.0, "10.1.0.0/16"));
+        let event = detector.session_down(peer_ip, peer_asn, 150.0).unwrap();
+        assert_eq!(event.drain_start, 100.0);
+        assert_eq!(event.drain_end, 120.0);
+        assert_eq!(event.announcements, 3);
+        assert_eq!(event.prefixes, 2);
+
+        // the burst was consumed by the previous session down
+        assert!(detector.session_down(peer_ip, peer_asn, 160.0).is_none());
+
+        // session down too long after the burst
+        detector.add_elem(&gshut_elem(200.0, "10.0.0.0/8"));
+        assert!(detector.session_down(peer_ip, peer_asn, 300.0).is_none());
+
+        // a gap larger than the window starts a new burst
+        detector.add_elem(&gshut_elem(400.0, "10.0.0.0/8"));
+        detector.add_elem(&gshut_elem(500.0, "10.1.0.0/16"));
+        let event = detector.session_down(peer_ip, peer_asn, 510.0).unwrap();
+        assert_eq!(event.drain_start, 500.0);
+        assert_eq!(event.prefixes, 1);
+
+        let mut detector = DrainDetector::new().with_min_prefixes(2);
+        detector.add_elem(&gshut_elem(100.0, "10.0.0.0/8"));
+        assert!(detector.session_down(peer_ip, peer_asn, 110.0).is_none());
+    }
+}
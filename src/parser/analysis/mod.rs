@@ -0,0 +1,16 @@
+/*!
+Analyses built on top of streams of BGP elems.
+*/
+pub mod as_graph;
+pub mod drain;
+pub mod path_diversity;
+pub mod pfx2as;
+pub mod rib_diff;
+pub mod summary;
+
+pub use as_graph::{AsEdgeInfo, AsGraphBuilder, AsSetPolicy};
+pub use drain::{DrainDetector, DrainEvent};
+pub use path_diversity::{PathDiversityBuilder, PeerPathDiversity, PrefixPathDiversity};
+pub use pfx2as::{Pfx2AsBuilder, Pfx2AsEntry};
+pub use rib_diff::{changed_attributes, rib_diff, RibDiff, RouteDiff};
+pub use summary::{PeerSummary, Summary, SummaryReport};
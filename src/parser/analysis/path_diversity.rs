@@ -0,0 +1,189 @@
+/*!
+Per-prefix path diversity of ADD-PATH data.
+
+With ADD-PATH ([RFC7911]), a peer can announce several paths for the same prefix, distinguished
+by their path identifier. [PathDiversityBuilder] groups announcements by prefix and peer and counts
+the distinct path identifiers, AS paths and next hops, e.g. to measure how many alternative paths
+route servers and route reflectors expose in ADD-PATH RIB dumps.
+
+```no_run
+use bgpkit_parser::analysis::PathDiversityBuilder;
+use bgpkit_parser::BgpkitParser;
+
+let mut builder = PathDiversityBuilder::new();
+builder.extend(BgpkitParser::new("rib.20240101.0000.bz2").unwrap());
+for diversity in builder.prefixes().iter().filter(|d| d.max_path_ids > 1) {
+    println!("{} {} {}", diversity.prefix, diversity.peers, diversity.max_path_ids);
+}
+```
+
+[RFC7911]: https://datatracker.ietf.org/doc/html/rfc7911
+*/
+use crate::models::{AsPath, Asn, BgpElem};
+use ipnet::IpNet;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Paths of a prefix announced by one peer.
+#[derive(Debug, Clone, Default)]
+struct PeerPaths {
+    path_ids: HashSet<u32>,
+    as_paths: HashSet<AsPath>,
+    next_hops: HashSet<IpAddr>,
+}
+
+/// Path diversity of a prefix as announced by one peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeerPathDiversity {
+    /// number of distinct path identifiers
+    pub path_ids: usize,
+    /// number of distinct AS paths
+    pub as_paths: usize,
+    /// number of distinct next hops
+    pub next_hops: usize,
+}
+
+/// Path diversity of a prefix across all peers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrefixPathDiversity {
+    pub prefix: IpNet,
+    /// number of peers announcing the prefix
+    pub peers: usize,
+    /// largest number of distinct path identifiers announced by a single peer
+    pub max_path_ids: usize,
+    /// average number of distinct path identifiers per peer
+    pub mean_path_ids: f64,
+    /// number of distinct AS paths across all peers
+    pub as_paths: usize,
+}
+
+/// Incrementally built per-prefix, per-peer path diversity.
+#[derive(Debug, Clone, Default)]
+pub struct PathDiversityBuilder {
+    prefixes: HashMap<IpNet, HashMap<(IpAddr, Asn), PeerPaths>>,
+}
+
+impl PathDiversityBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an announcement. Withdrawals are ignored.
+    ///
+    /// Elems without ADD-PATH have a path identifier of 0, so each peer counts as one path ID.
+    pub fn add_elem(&mut self, elem: &BgpElem) {
+        if !elem.is_announcement() {
+            return;
+        }
+        let paths = self
+            .prefixes
+            .entry(elem.prefix.prefix)
+            .or_default()
+            .entry((elem.peer_ip, elem.peer_asn))
+            .or_default();
+        paths.path_ids.insert(elem.prefix.path_id);
+        if let Some(as_path) = &elem.as_path {
+            paths.as_paths.insert(as_path.clone());
+        }
+        if let Some(next_hop) = elem.next_hop {
+            paths.next_hops.insert(next_hop);
+        }
+    }
+
+    /// Number of distinct prefixes.
+    pub fn len(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    /// Path diversity of a prefix as announced by a peer.
+    pub fn peer_diversity(
+        &self,
+        prefix: &IpNet,
+        peer_ip: IpAddr,
+        peer_asn: Asn,
+    ) -> Option<PeerPathDiversity> {
+        let paths = self.prefixes.get(prefix)?.get(&(peer_ip, peer_asn))?;
+        Some(PeerPathDiversity {
+            path_ids: paths.path_ids.len(),
+            as_paths: paths.as_paths.len(),
+            next_hops: paths.next_hops.len(),
+        })
+    }
+
+    /// Path diversity of a prefix across all peers.
+    pub fn prefix_diversity(&self, prefix: &IpNet) -> Option<PrefixPathDiversity> {
+        let peers = self.prefixes.get(prefix)?;
+        let path_ids: Vec<usize> = peers.values().map(|p| p.path_ids.len()).collect();
+        let as_paths: HashSet<&AsPath> = peers.values().flat_map(|p| &p.as_paths).collect();
+        Some(PrefixPathDiversity {
+            prefix: *prefix,
+            peers: peers.len(),
+            max_path_ids: path_ids.iter().copied().max().unwrap_or_default(),
+            mean_path_ids: path_ids.iter().sum::<usize>() as f64 / path_ids.len() as f64,
+            as_paths: as_paths.len(),
+        })
+    }
+
+    /// Path diversity of all prefixes, sorted by prefix.
+    pub fn prefixes(&self) -> Vec<PrefixPathDiversity> {
+        let mut prefixes: Vec<_> = self.prefixes.keys().collect();
+        prefixes.sort();
+        prefixes
+            .into_iter()
+            .filter_map(|prefix| self.prefix_diversity(prefix))
+            .collect()
+    }
+}
+
+impl Extend<BgpElem> for PathDiversityBuilder {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.add_elem(&elem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_elems::{announce, withdraw};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_path_diversity() {
+        let mut builder = PathDiversityBuilder::new();
+        builder.extend([
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 1]).with_path_id(1),
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 2, 1]).with_path_id(2),
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 2, 1]).with_path_id(2),
+            announce("192.0.2.2", "10.0.0.0/8", &[64496, 1]),
+            withdraw("192.0.2.3", "10.0.0.0/8").with_path_id(1),
+        ]);
+        assert_eq!(builder.len(), 1);
+
+        let prefix = IpNet::from_str("10.0.0.0/8").unwrap();
+        let peer = builder
+            .peer_diversity(
+                &prefix,
+                IpAddr::from_str("192.0.2.1").unwrap(),
+                Asn::new_32bit(64496),
+            )
+            .unwrap();
+        assert_eq!(peer.path_ids, 2);
+        assert_eq!(peer.as_paths, 2);
+        assert_eq!(peer.next_hops, 1);
+
+        let diversity = builder.prefix_diversity(&prefix).unwrap();
+        assert_eq!(diversity.peers, 2);
+        assert_eq!(diversity.max_path_ids, 2);
+        assert_eq!(diversity.mean_path_ids, 1.5);
+        assert_eq!(diversity.as_paths, 2);
+        assert_eq!(builder.prefixes(), vec![diversity]);
+    }
+}
@@ -0,0 +1,188 @@
+/*!
+Prefix-to-origin mappings from RIB dumps.
+
+[Pfx2AsBuilder] collects the origin ASes announcing each prefix and counts the peers seeing each
+prefix-origin pair, deduplicating the announcements of the same pair across peers and across RIB
+dumps, e.g. of several collectors. Pairs seen by few peers are often leaks or misconfigurations,
+pairs seen by most peers are the global view of who originates what.
+
+```no_run
+use bgpkit_parser::analysis::Pfx2AsBuilder;
+use bgpkit_parser::BgpkitParser;
+
+let mut builder = Pfx2AsBuilder::new();
+for rib in ["route-views2/rib.20240101.0000.bz2", "rrc00/bview.20240101.0000.gz"] {
+    builder.extend(BgpkitParser::new(rib).unwrap());
+}
+builder.write_csv(std::fs::File::create("pfx2as.csv").unwrap()).unwrap();
+```
+
+Announcements whose AS path ends with an AS set map the prefix to every AS of the set.
+*/
+use crate::models::{Asn, BgpElem};
+use ipnet::IpNet;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::net::IpAddr;
+
+/// Prefix-origin pair with the number of peers seeing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pfx2AsEntry {
+    pub prefix: IpNet,
+    pub origin: Asn,
+    /// number of distinct peers announcing the prefix with this origin
+    pub peers: usize,
+}
+
+/// Incrementally built prefix-to-origin mappings.
+#[derive(Debug, Clone, Default)]
+pub struct Pfx2AsBuilder {
+    mappings: HashMap<(IpNet, Asn), HashSet<(IpAddr, Asn)>>,
+}
+
+impl Pfx2AsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an announcement. Withdrawals and announcements without AS path are ignored.
+    pub fn add_elem(&mut self, elem: &BgpElem) {
+        if !elem.is_announcement() {
+            return;
+        }
+        let origins = match &elem.origin_asns {
+            Some(origins) => origins,
+            None => return,
+        };
+        for origin in origins {
+            self.mappings
+                .entry((elem.prefix.prefix, *origin))
+                .or_default()
+                .insert((elem.peer_ip, elem.peer_asn));
+        }
+    }
+
+    /// Number of distinct prefix-origin pairs.
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Origins of a prefix with their numbers of peers, sorted by origin.
+    pub fn origins(&self, prefix: &IpNet) -> Vec<(Asn, usize)> {
+        let mut origins: Vec<(Asn, usize)> = self
+            .mappings
+            .iter()
+            .filter(|((p, _), _)| p == prefix)
+            .map(|((_, origin), peers)| (*origin, peers.len()))
+            .collect();
+        origins.sort();
+        origins
+    }
+
+    /// All prefix-origin pairs, sorted by prefix then origin.
+    pub fn entries(&self) -> Vec<Pfx2AsEntry> {
+        let mut entries: Vec<Pfx2AsEntry> = self
+            .mappings
+            .iter()
+            .map(|((prefix, origin), peers)| Pfx2AsEntry {
+                prefix: *prefix,
+                origin: *origin,
+                peers: peers.len(),
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.prefix, e.origin));
+        entries
+    }
+
+    /// Write the mappings as CSV with the header `prefix,origin,peers`.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "prefix,origin,peers")?;
+        for entry in self.entries() {
+            writeln!(writer, "{},{},{}", entry.prefix, entry.origin, entry.peers)?;
+        }
+        Ok(())
+    }
+
+    /// Write the mappings as a JSON array of [Pfx2AsEntry].
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn write_json<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        serde_json::to_writer(writer, &self.entries()).map_err(std::io::Error::from)
+    }
+}
+
+impl Extend<BgpElem> for Pfx2AsBuilder {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.add_elem(&elem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_elems::{announce, withdraw};
+    use std::str::FromStr;
+
+    fn builder() -> Pfx2AsBuilder {
+        let mut builder = Pfx2AsBuilder::new();
+        builder.extend([
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 64500]),
+            announce("192.0.2.2", "10.0.0.0/8", &[64496, 64500]),
+            // same peer in another RIB dump
+            announce("192.0.2.2", "10.0.0.0/8", &[64496, 64500]),
+            announce("192.0.2.2", "10.0.0.0/8", &[64496]).with_origins(&[64501, 64502]),
+            announce("192.0.2.1", "192.0.2.0/24", &[64496, 64500]),
+            withdraw("192.0.2.3", "10.0.0.0/8").with_origins(&[64511]),
+        ]);
+        builder
+    }
+
+    #[test]
+    fn test_pfx2as() {
+        let builder = builder();
+        assert_eq!(builder.len(), 4);
+
+        let prefix = IpNet::from_str("10.0.0.0/8").unwrap();
+        assert_eq!(
+            builder.origins(&prefix),
+            vec![
+                (Asn::new_32bit(64500), 2),
+                (Asn::new_32bit(64501), 1),
+                (Asn::new_32bit(64502), 1),
+            ]
+        );
+        let entries = builder.entries();
+        assert_eq!(entries[0].prefix, prefix);
+        assert_eq!(entries[0].peers, 2);
+        assert_eq!(entries[3].prefix, IpNet::from_str("192.0.2.0/24").unwrap());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut output = vec![];
+        builder().write_csv(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "prefix,origin,peers\n\
+             10.0.0.0/8,64500,2\n\
+             10.0.0.0/8,64501,1\n\
+             10.0.0.0/8,64502,1\n\
+             192.0.2.0/24,64500,1\n"
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    #[test]
+    fn test_write_json() {
+        let mut output = vec![];
+        builder().write_json(&mut output).unwrap();
+        let entries: Vec<Pfx2AsEntry> = serde_json::from_slice(&output).unwrap();
+        assert_eq!(entries, builder().entries());
+    }
+}
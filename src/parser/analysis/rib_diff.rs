@@ -0,0 +1,230 @@
+/*!
+Route-level differences between two RIB snapshots.
+
+[rib_diff] compares the routes of two RIB dumps, e.g. of the same collector at 00:00 and 08:00,
+and yields the routes added, removed, or changed per peer and prefix. For changed routes, the
+names of the attributes that differ are listed, so that e.g. AS path changes can be told apart
+from community churn.
+
+```no_run
+use bgpkit_parser::analysis::{rib_diff, RouteDiff};
+use bgpkit_parser::BgpkitParser;
+
+let a = BgpkitParser::new("rib.20240101.0000.bz2").unwrap();
+let b = BgpkitParser::new("rib.20240101.0800.bz2").unwrap();
+for diff in rib_diff(a, b) {
+    match diff {
+        RouteDiff::Added(new) => println!("+ {} {}", new.peer_ip, new.prefix),
+        RouteDiff::Removed(old) => println!("- {} {}", old.peer_ip, old.prefix),
+        RouteDiff::Changed { new, attributes, .. } => {
+            println!("~ {} {} {}", new.peer_ip, new.prefix, attributes.join(","))
+        }
+    }
+}
+```
+
+The routes of the first snapshot are kept in memory while the second one is read.
+*/
+use crate::models::{BgpElem, NetworkPrefix};
+use crate::parser::demux::PeerKey;
+use std::collections::HashMap;
+
+/// Difference of the route of a peer for a prefix between two RIB snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteDiff {
+    /// route only in the second snapshot
+    Added(BgpElem),
+    /// route only in the first snapshot
+    Removed(BgpElem),
+    /// route in both snapshots with different attributes
+    Changed {
+        old: Box<BgpElem>,
+        new: Box<BgpElem>,
+        /// names of the attributes that differ, see [changed_attributes]
+        attributes: Vec<&'static str>,
+    },
+}
+
+impl RouteDiff {
+    /// The route of the second snapshot, or of the first one for removed routes.
+    pub fn elem(&self) -> &BgpElem {
+        match self {
+            RouteDiff::Added(elem) | RouteDiff::Removed(elem) => elem,
+            RouteDiff::Changed { new, .. } => new,
+        }
+    }
+
+    pub fn peer(&self) -> PeerKey {
+        let elem = self.elem();
+        PeerKey {
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+        }
+    }
+
+    pub fn prefix(&self) -> NetworkPrefix {
+        self.elem().prefix
+    }
+}
+
+/// Names of the route attributes of `a` and `b` that differ, e.g. `as_path` or `communities`.
+///
+/// Origin ASNs are derived from the AS path, and are therefore not compared separately.
+pub fn changed_attributes(a: &BgpElem, b: &BgpElem) -> Vec<&'static str> {
+    let mut attributes = vec![];
+    if a.next_hop != b.next_hop {
+        attributes.push("next_hop");
+    }
+    if a.as_path != b.as_path {
+        attributes.push("as_path");
+    }
+    if a.origin != b.origin {
+        attributes.push("origin");
+    }
+    if a.local_pref != b.local_pref {
+        attributes.push("local_pref");
+    }
+    if a.med != b.med {
+        attributes.push("med");
+    }
+    if a.communities != b.communities {
+        attributes.push("communities");
+    }
+    if a.atomic != b.atomic {
+        attributes.push("atomic");
+    }
+    if a.aggr_asn != b.aggr_asn || a.aggr_ip != b.aggr_ip {
+        attributes.push("aggregator");
+    }
+    if a.only_to_customer != b.only_to_customer {
+        attributes.push("only_to_customer");
+    }
+    if a.unknown != b.unknown {
+        attributes.push("unknown");
+    }
+    if a.deprecated != b.deprecated {
+        attributes.push("deprecated");
+    }
+    attributes
+}
+
+/// Compare the routes of two RIB snapshots.
+///
+/// Routes are identified by peer and prefix, including the path identifier of ADD-PATH RIBs.
+/// Withdrawals are ignored, and routes with identical attributes are not reported, even if their
+/// timestamps differ.
+pub fn rib_diff<A, B>(a: A, b: B) -> RibDiff<B::IntoIter>
+where
+    A: IntoIterator<Item = BgpElem>,
+    B: IntoIterator<Item = BgpElem>,
+{
+    let old = a
+        .into_iter()
+        .filter(|elem| elem.is_announcement())
+        .map(|elem| (route_key(&elem), elem))
+        .collect();
+    RibDiff {
+        old,
+        new: b.into_iter(),
+        removed: None,
+    }
+}
+
+fn route_key(elem: &BgpElem) -> (PeerKey, NetworkPrefix) {
+    let peer = PeerKey {
+        peer_ip: elem.peer_ip,
+        peer_asn: elem.peer_asn,
+    };
+    (peer, elem.prefix)
+}
+
+/// Iterator returned by [rib_diff].
+///
+/// Added and changed routes come in the order of the second snapshot, followed by the removed
+/// routes sorted by peer and prefix.
+pub struct RibDiff<I> {
+    /// routes of the first snapshot not seen in the second one yet
+    old: HashMap<(PeerKey, NetworkPrefix), BgpElem>,
+    new: I,
+    /// removed routes, once the second snapshot is read
+    removed: Option<std::vec::IntoIter<BgpElem>>,
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for RibDiff<I> {
+    type Item = RouteDiff;
+
+    fn next(&mut self) -> Option<RouteDiff> {
+        if self.removed.is_none() {
+            for new in self.new.by_ref() {
+                if !new.is_announcement() {
+                    continue;
+                }
+                let old = match self.old.remove(&route_key(&new)) {
+                    Some(old) => old,
+                    None => return Some(RouteDiff::Added(new)),
+                };
+                let attributes = changed_attributes(&old, &new);
+                if !attributes.is_empty() {
+                    return Some(RouteDiff::Changed {
+                        old: Box::new(old),
+                        new: Box::new(new),
+                        attributes,
+                    });
+                }
+            }
+            let mut removed: Vec<BgpElem> = self.old.drain().map(|(_, elem)| elem).collect();
+            removed.sort_by_key(|e| (e.peer_ip, e.peer_asn, e.prefix.prefix, e.prefix.path_id));
+            self.removed = Some(removed.into_iter());
+        }
+        self.removed.as_mut()?.next().map(RouteDiff::Removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Asn;
+    use crate::parser::test_elems::{announce, withdraw};
+
+    #[test]
+    fn test_rib_diff() {
+        let a = vec![
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 1]),
+            announce("192.0.2.1", "192.0.2.0/24", &[64496, 2]),
+            announce("192.0.2.2", "10.0.0.0/8", &[64496, 1]).with_med(10),
+            announce("192.0.2.2", "198.51.100.0/24", &[64496, 3]),
+        ];
+        let b = vec![
+            a[0].clone().at(100.0),
+            announce("192.0.2.1", "192.0.2.0/24", &[64496, 4, 2]).with_med(20),
+            announce("192.0.2.2", "203.0.113.0/24", &[64496, 5]),
+            withdraw("192.0.2.2", "10.0.0.0/8"),
+        ];
+
+        let diffs: Vec<RouteDiff> = rib_diff(a.clone(), b.clone()).collect();
+        assert_eq!(diffs.len(), 4);
+        assert_eq!(
+            diffs[0],
+            RouteDiff::Changed {
+                old: Box::new(a[1].clone()),
+                new: Box::new(b[1].clone()),
+                attributes: vec!["as_path", "med"],
+            }
+        );
+        assert_eq!(diffs[1], RouteDiff::Added(b[2].clone()));
+        // removed routes are sorted by peer and prefix
+        assert_eq!(diffs[2], RouteDiff::Removed(a[2].clone()));
+        assert_eq!(diffs[3], RouteDiff::Removed(a[3].clone()));
+        assert_eq!(diffs[3].prefix(), a[3].prefix);
+    }
+
+    #[test]
+    fn test_changed_attributes() {
+        let a = announce("192.0.2.1", "10.0.0.0/8", &[64496, 1]);
+        let mut b = a.clone();
+        assert!(changed_attributes(&a, &b).is_empty());
+        b.aggr_asn = Some(Asn::new_32bit(64496));
+        b.atomic = true;
+        assert_eq!(changed_attributes(&a, &b), vec!["atomic", "aggregator"]);
+    }
+}
@@ -0,0 +1,235 @@
+/*!
+One-pass statistics summary of a stream of BGP elems.
+
+[Summary] counts elems per peer, per prefix length and per elem type, along with the unique
+prefixes and origin ASes, and the time range of the elems to derive announcement and withdrawal
+rates. Elems are counted as they stream through, so the summary of whole archives can be computed
+without collecting the elems.
+
+```no_run
+use bgpkit_parser::analysis::Summary;
+use bgpkit_parser::BgpkitParser;
+
+let mut summary = Summary::new();
+summary.extend(BgpkitParser::new("updates.20240101.0000.bz2").unwrap());
+let report = summary.report();
+println!(
+    "{} announcements, {} withdrawals, {} unique prefixes",
+    report.announcements, report.withdrawals, report.unique_prefixes
+);
+```
+
+The unique prefixes and origins are kept in memory, which is a few tens of megabytes for a full
+table. See [ApproxElemStats](crate::ApproxElemStats) for bounded-memory counting.
+*/
+use crate::models::{Asn, BgpElem, ElemType};
+use ipnet::IpNet;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+
+/// Elem counts of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeerSummary {
+    /// ASN of the peer, of the latest elem if the peer IP was used with several ASNs
+    pub asn: Asn,
+    pub announcements: u64,
+    pub withdrawals: u64,
+}
+
+/// Counts of a [Summary] at one point of the stream.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SummaryReport {
+    pub elems: u64,
+    pub announcements: u64,
+    pub withdrawals: u64,
+    /// earliest elem timestamp
+    pub first_timestamp: Option<f64>,
+    /// latest elem timestamp
+    pub last_timestamp: Option<f64>,
+    /// announcements per second between the first and last timestamps
+    pub announcement_rate: Option<f64>,
+    /// withdrawals per second between the first and last timestamps
+    pub withdrawal_rate: Option<f64>,
+    pub unique_prefixes: usize,
+    pub unique_origins: usize,
+    /// elem counts by peer IP
+    pub peers: BTreeMap<IpAddr, PeerSummary>,
+    /// number of IPv4 elems by prefix length
+    pub ipv4_prefix_lengths: BTreeMap<u8, u64>,
+    /// number of IPv6 elems by prefix length
+    pub ipv6_prefix_lengths: BTreeMap<u8, u64>,
+}
+
+/// Incrementally built statistics summary of BGP elems.
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    report: SummaryReport,
+    prefixes: HashSet<IpNet>,
+    origins: HashSet<Asn>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an elem to the summary.
+    pub fn add_elem(&mut self, elem: &BgpElem) {
+        let report = &mut self.report;
+        report.elems += 1;
+
+        let peer = report.peers.entry(elem.peer_ip).or_insert(PeerSummary {
+            asn: elem.peer_asn,
+            announcements: 0,
+            withdrawals: 0,
+        });
+        peer.asn = elem.peer_asn;
+        match elem.elem_type {
+            ElemType::ANNOUNCE => {
+                report.announcements += 1;
+                peer.announcements += 1;
+            }
+            ElemType::WITHDRAW => {
+                report.withdrawals += 1;
+                peer.withdrawals += 1;
+            }
+        }
+
+        let prefix_lengths = match elem.prefix.prefix {
+            IpNet::V4(_) => &mut report.ipv4_prefix_lengths,
+            IpNet::V6(_) => &mut report.ipv6_prefix_lengths,
+        };
+        *prefix_lengths
+            .entry(elem.prefix.prefix.prefix_len())
+            .or_default() += 1;
+
+        report.first_timestamp = Some(match report.first_timestamp {
+            Some(t) => t.min(elem.timestamp),
+            None => elem.timestamp,
+        });
+        report.last_timestamp = Some(match report.last_timestamp {
+            Some(t) => t.max(elem.timestamp),
+            None => elem.timestamp,
+        });
+
+        self.prefixes.insert(elem.prefix.prefix);
+        if let Some(origins) = &elem.origin_asns {
+            self.origins.extend(origins.iter().copied());
+        }
+    }
+
+    /// Merge the summary of another stream, e.g. of another file, into this one.
+    pub fn merge(&mut self, other: &Summary) {
+        let report = &mut self.report;
+        let other_report = &other.report;
+        report.elems += other_report.elems;
+        report.announcements += other_report.announcements;
+        report.withdrawals += other_report.withdrawals;
+        for (peer_ip, other_peer) in &other_report.peers {
+            let peer = report.peers.entry(*peer_ip).or_insert(PeerSummary {
+                asn: other_peer.asn,
+                announcements: 0,
+                withdrawals: 0,
+            });
+            peer.announcements += other_peer.announcements;
+            peer.withdrawals += other_peer.withdrawals;
+        }
+        for (len, count) in &other_report.ipv4_prefix_lengths {
+            *report.ipv4_prefix_lengths.entry(*len).or_default() += count;
+        }
+        for (len, count) in &other_report.ipv6_prefix_lengths {
+            *report.ipv6_prefix_lengths.entry(*len).or_default() += count;
+        }
+        report.first_timestamp = match (report.first_timestamp, other_report.first_timestamp) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        report.last_timestamp = match (report.last_timestamp, other_report.last_timestamp) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.prefixes.extend(other.prefixes.iter().copied());
+        self.origins.extend(other.origins.iter().copied());
+    }
+
+    /// Counts of the elems added so far, with the unique counts and rates computed.
+    ///
+    /// Rates are `None` until elems with two different timestamps have been added.
+    pub fn report(&self) -> SummaryReport {
+        let mut report = self.report.clone();
+        report.unique_prefixes = self.prefixes.len();
+        report.unique_origins = self.origins.len();
+        if let (Some(first), Some(last)) = (report.first_timestamp, report.last_timestamp) {
+            let duration = last - first;
+            if duration > 0.0 {
+                report.announcement_rate = Some(report.announcements as f64 / duration);
+                report.withdrawal_rate = Some(report.withdrawals as f64 / duration);
+            }
+        }
+        report
+    }
+
+    /// Write the [SummaryReport] as a JSON object.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn write_json<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
+        serde_json::to_writer(writer, &self.report()).map_err(std::io::Error::from)
+    }
+}
+
+impl Extend<BgpElem> for Summary {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.add_elem(&elem);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_elems::{announce, withdraw};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::new();
+        summary.extend([
+            announce("192.0.2.1", "10.0.0.0/8", &[64496, 64500]).at(100.0),
+            announce("192.0.2.1", "192.0.2.0/24", &[64496, 64501]).at(102.0),
+            announce("192.0.2.2", "10.0.0.0/8", &[64496, 64500]).at(104.0),
+            announce("2001:db8::1", "2001:db8::/32", &[64496, 64502]).at(105.0),
+            withdraw("192.0.2.2", "10.0.0.0/8").at(110.0),
+        ]);
+        let report = summary.report();
+        assert_eq!(report.elems, 5);
+        assert_eq!(report.announcements, 4);
+        assert_eq!(report.withdrawals, 1);
+        assert_eq!(report.first_timestamp, Some(100.0));
+        assert_eq!(report.last_timestamp, Some(110.0));
+        assert_eq!(report.announcement_rate, Some(0.4));
+        assert_eq!(report.withdrawal_rate, Some(0.1));
+        assert_eq!(report.unique_prefixes, 3);
+        assert_eq!(report.unique_origins, 3);
+        assert_eq!(report.peers.len(), 3);
+        let peer = report.peers[&IpAddr::from_str("192.0.2.2").unwrap()];
+        assert_eq!((peer.announcements, peer.withdrawals), (1, 1));
+        assert_eq!(report.ipv4_prefix_lengths[&8], 3);
+        assert_eq!(report.ipv4_prefix_lengths[&24], 1);
+        assert_eq!(report.ipv6_prefix_lengths[&32], 1);
+
+        let mut merged = summary.clone();
+        merged.merge(&summary);
+        let merged_report = merged.report();
+        assert_eq!(merged_report.elems, 10);
+        assert_eq!(merged_report.unique_prefixes, 3);
+        assert_eq!(merged_report.ipv4_prefix_lengths[&8], 6);
+        assert_eq!(merged_report.first_timestamp, Some(100.0));
+
+        // a single timestamp gives no rates
+        let mut summary = Summary::new();
+        summary.add_elem(&announce("192.0.2.1", "10.0.0.0/8", &[64496, 64500]).at(100.0));
+        assert_eq!(summary.report().announcement_rate, None);
+    }
+}
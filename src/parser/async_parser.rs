@@ -0,0 +1,208 @@
+/*!
+Parsing MRT data from a tokio [AsyncRead], e.g. inside services fetching MRT files over HTTP.
+
+[AsyncBgpkitParser] reads the bytes of each record asynchronously, so waiting for the input does
+not block the worker threads of the runtime, and parses the record once it is read completely.
+The data must be decompressed already, e.g. with `async-compression`.
+
+```no_run
+use bgpkit_parser::AsyncBgpkitParser;
+use futures_util::StreamExt;
+
+# async fn run() {
+let file = tokio::fs::File::open("updates.20211001.0000.mrt").await.unwrap();
+let parser = AsyncBgpkitParser::from_async_reader(file)
+    .add_filter("peer_asn", "13335")
+    .unwrap();
+let mut elems = Box::pin(parser.elem_stream());
+while let Some(elem) = elems.next().await {
+    println!("{}", elem);
+}
+# }
+```
+*/
+use crate::models::{BgpElem, MrtRecord};
+use crate::parser::{parse_mrt_record, Filter, Filterable, ParserErrorWithBytes};
+use crate::Elementor;
+use futures_util::stream::{self, Stream};
+use log::{error, warn};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Parser of MRT data read from a tokio [AsyncRead], see
+/// [async_parser](crate::parser::async_parser).
+pub struct AsyncBgpkitParser<R> {
+    reader: R,
+    filters: Vec<Filter>,
+    show_warnings: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncBgpkitParser<R> {
+    /// Creating a new parser from an object that implements [AsyncRead].
+    pub fn from_async_reader(reader: R) -> Self {
+        AsyncBgpkitParser {
+            reader,
+            filters: vec![],
+            show_warnings: true,
+        }
+    }
+
+    /// Add a filter applied to the elems of [AsyncBgpkitParser::elem_stream], see
+    /// [BgpkitParser::add_filter](crate::BgpkitParser::add_filter).
+    pub fn add_filter(
+        mut self,
+        filter_type: &str,
+        filter_value: &str,
+    ) -> Result<Self, ParserErrorWithBytes> {
+        self.filters.push(Filter::new(filter_type, filter_value)?);
+        Ok(self)
+    }
+
+    /// Do not log warnings for records failing to parse.
+    pub fn disable_warnings(mut self) -> Self {
+        self.show_warnings = false;
+        self
+    }
+
+    /// Read the bytes of the next record, including its common header, or None at the end of the
+    /// data.
+    async fn next_record_bytes(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let mut header = [0u8; 12];
+        let mut filled = 0;
+        while filled < header.len() {
+            match self.reader.read(&mut header[filled..]).await? {
+                0 if filled == 0 => return Ok(None),
+                0 => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "truncated MRT common header",
+                    ))
+                }
+                n => filled += n,
+            }
+        }
+        // the length excludes the common header, but includes the microsecond timestamp of
+        // BGP4MP_ET records
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as u64;
+        let mut bytes = header.to_vec();
+        let read = (&mut self.reader)
+            .take(length)
+            .read_to_end(&mut bytes)
+            .await?;
+        if (read as u64) < length {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "truncated MRT record",
+            ));
+        }
+        Ok(Some(bytes))
+    }
+
+    /// Read and parse the next record, or None at the end of the data.
+    ///
+    /// Records failing to parse are skipped; reading stops at the first IO error.
+    pub async fn next_record(&mut self) -> Option<MrtRecord> {
+        loop {
+            let bytes = match self.next_record_bytes().await {
+                Ok(bytes) => bytes?,
+                Err(e) => {
+                    error!("{:?}", e);
+                    return None;
+                }
+            };
+            match parse_mrt_record(&mut bytes.as_slice()) {
+                Ok(record) => return Some(record),
+                Err(e) => {
+                    if self.show_warnings {
+                        warn!("parser warn: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stream of the records of the data.
+    ///
+    /// The filters of the parser only apply to [AsyncBgpkitParser::elem_stream].
+    pub fn record_stream(self) -> impl Stream<Item = MrtRecord> {
+        stream::unfold(self, |mut parser| async move {
+            let record = parser.next_record().await?;
+            Some((record, parser))
+        })
+    }
+
+    /// Stream of the elems of the data matching the filters of the parser.
+    pub fn elem_stream(self) -> impl Stream<Item = BgpElem> {
+        let state = (self, Elementor::new(), VecDeque::new());
+        stream::unfold(state, |(mut parser, mut elementor, mut elems)| async move {
+            loop {
+                if let Some(elem) = elems.pop_front() {
+                    return Some((elem, (parser, elementor, elems)));
+                }
+                let record = parser.next_record().await?;
+                elems.extend(
+                    elementor
+                        .record_to_elems(record)
+                        .into_iter()
+                        .filter(|e| e.match_filters(&parser.filters)),
+                );
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_elem_stream() {
+        for vector in test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+        {
+            let elems: Vec<BgpElem> = AsyncBgpkitParser::from_async_reader(vector.bytes.as_slice())
+                .elem_stream()
+                .collect()
+                .await;
+            assert_eq!(elems, vector.expected, "{}", vector.name);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_stream() {
+        let vectors = test_vectors::mrt_updates();
+        let bytes: Vec<u8> = vectors.iter().flat_map(|v| v.bytes.clone()).collect();
+        let records: Vec<MrtRecord> = AsyncBgpkitParser::from_async_reader(bytes.as_slice())
+            .record_stream()
+            .collect()
+            .await;
+        assert_eq!(records.len(), vectors.len());
+
+        // a truncated last record ends the stream
+        let truncated = &bytes[..bytes.len() - 1];
+        let records: Vec<MrtRecord> = AsyncBgpkitParser::from_async_reader(truncated)
+            .record_stream()
+            .collect()
+            .await;
+        assert_eq!(records.len(), vectors.len() - 1);
+    }
+
+    #[tokio::test]
+    async fn test_elem_stream_filters() {
+        let bytes: Vec<u8> = test_vectors::mrt_updates()
+            .into_iter()
+            .flat_map(|v| v.bytes)
+            .collect();
+        let elems: Vec<BgpElem> = AsyncBgpkitParser::from_async_reader(bytes.as_slice())
+            .add_filter("type", "withdraw")
+            .unwrap()
+            .elem_stream()
+            .collect()
+            .await;
+        assert_eq!(elems.len(), 1);
+    }
+}
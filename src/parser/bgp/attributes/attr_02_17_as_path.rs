@@ -1,5 +1,5 @@
 use crate::models::*;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
@@ -9,11 +9,16 @@ const AS_PATH_AS_SEQUENCE: u8 = 2;
 const AS_PATH_CONFED_SEQUENCE: u8 = 3;
 const AS_PATH_CONFED_SET: u8 = 4;
 
-pub fn parse_as_path(mut input: Bytes, asn_len: &AsnLength) -> Result<AsPath, ParserError> {
+pub fn parse_as_path(
+    mut input: Bytes,
+    asn_len: &AsnLength,
+    context: &mut ParseContext,
+) -> Result<AsPath, ParserError> {
     let mut output = AsPath {
         segments: Vec::with_capacity(5),
     };
     while input.remaining() > 0 {
+        context.record_step()?;
         let segment = parse_as_path_segment(&mut input, asn_len)?;
         output.append_segment(segment);
     }
@@ -124,10 +129,11 @@ mod tests {
             0, 2, // AS2
             0, 3, // AS3
         ]);
-        let path = parse_as_path(data.clone(), &AsnLength::Bits16).unwrap();
+        let path =
+            parse_as_path(data.clone(), &AsnLength::Bits16, &mut ParseContext::new()).unwrap();
         assert_eq!(vec![1, 2, 3], path.to_u32_vec_opt(false).unwrap());
 
-        let path = parse_as_path(data, &AsnLength::Bits16).unwrap();
+        let path = parse_as_path(data, &AsnLength::Bits16, &mut ParseContext::new()).unwrap();
         assert_eq!(
             path.to_u32_vec_opt(false),
             AsPath::from_sequence([1, 2, 3]).to_u32_vec_opt(false)
@@ -207,7 +213,8 @@ mod tests {
             0, 2, // AS2
             0, 3, // AS3
         ]);
-        let path = parse_as_path(data.clone(), &AsnLength::Bits16).unwrap();
+        let path =
+            parse_as_path(data.clone(), &AsnLength::Bits16, &mut ParseContext::new()).unwrap();
         let encoded_bytes = encode_as_path(&path, AsnLength::Bits16);
         assert_eq!(data, encoded_bytes);
 
@@ -218,7 +225,8 @@ mod tests {
             0, 0, 0, 2, // AS2
             0, 0, 0, 3, // AS3
         ]);
-        let path = parse_as_path(data.clone(), &AsnLength::Bits32).unwrap();
+        let path =
+            parse_as_path(data.clone(), &AsnLength::Bits32, &mut ParseContext::new()).unwrap();
         let encoded_bytes = encode_as_path(&path, AsnLength::Bits32);
         assert_eq!(data, encoded_bytes);
     }
@@ -230,7 +238,8 @@ mod tests {
             1, // 1 AS in path
             0, 1,
         ]);
-        let path = parse_as_path(data.clone(), &AsnLength::Bits16).unwrap();
+        let path =
+            parse_as_path(data.clone(), &AsnLength::Bits16, &mut ParseContext::new()).unwrap();
         let encoded_bytes = encode_as_path(&path, AsnLength::Bits16);
         assert_eq!(data, encoded_bytes);
 
@@ -239,7 +248,8 @@ mod tests {
             1, // 1 AS in path
             0, 1,
         ]);
-        let path = parse_as_path(data.clone(), &AsnLength::Bits16).unwrap();
+        let path =
+            parse_as_path(data.clone(), &AsnLength::Bits16, &mut ParseContext::new()).unwrap();
         let encoded_bytes = encode_as_path(&path, AsnLength::Bits16);
         assert_eq!(data, encoded_bytes);
     }
@@ -3,7 +3,6 @@ use crate::parser::ReadUtils;
 use crate::ParserError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use log::warn;
-use std::net::IpAddr;
 
 /// Parse aggregator attribute.
 ///
@@ -38,36 +37,30 @@ pub fn parse_aggregator(
     }
     let asn = input.read_asn(asn_len_found)?;
 
-    // the BGP identifier is always 4 bytes or IPv4 address
-    let identifier = input.read_ipv4_address()?;
+    // the BGP identifier is always 4 bytes
+    let identifier = BgpIdentifier::from(input.read_u32()?);
     Ok((asn, identifier))
 }
 
-pub fn encode_aggregator(asn: &Asn, addr: &IpAddr) -> Bytes {
+pub fn encode_aggregator(asn: &Asn, id: &BgpIdentifier) -> Bytes {
     let mut bytes = BytesMut::new();
 
     bytes.extend(asn.encode());
-    match addr {
-        IpAddr::V4(ip) => bytes.put_u32((*ip).into()),
-        IpAddr::V6(ip) => {
-            bytes.put_u128((*ip).into());
-        }
-    }
+    bytes.put_u32((*id).into());
     bytes.freeze()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::{Ipv4Addr, Ipv6Addr};
     use std::str::FromStr;
 
     #[test]
     fn test_parse_aggregator() {
-        let identifier = Ipv4Addr::from_str("10.0.0.1").unwrap();
+        let identifier = BgpIdentifier::from_str("10.0.0.1").unwrap();
         let mut data = vec![];
         data.extend([1u8, 2]);
-        data.extend(identifier.octets());
+        data.extend(identifier.to_u32().to_be_bytes());
         let bytes = Bytes::from(data);
 
         if let Ok((asn, n)) = parse_aggregator(bytes, &AsnLength::Bits16) {
@@ -77,7 +70,7 @@ mod tests {
 
         let mut data = vec![];
         data.extend([0u8, 0, 1, 2]);
-        data.extend(identifier.octets());
+        data.extend(identifier.to_u32().to_be_bytes());
         let bytes = Bytes::from(data);
 
         if let Ok((asn, n)) = parse_aggregator(bytes, &AsnLength::Bits32) {
@@ -88,33 +81,28 @@ mod tests {
         // invalid number of bytes
         let mut data = vec![];
         data.extend([0u8, 0, 1, 2, 3]);
-        data.extend(identifier.octets());
+        data.extend(identifier.to_u32().to_be_bytes());
         let bytes = Bytes::from(data);
         assert!(parse_aggregator(bytes, &AsnLength::Bits32).is_err());
 
         // bytes length not matching
         let mut data = vec![];
         data.extend([0u8, 0, 1, 2, 3, 4]); // 6 bytes --> 2 bytes ASN
-        data.extend(identifier.octets());
+        data.extend(identifier.to_u32().to_be_bytes());
         let bytes = Bytes::from(data);
         assert!(parse_aggregator(bytes, &AsnLength::Bits32).is_err());
     }
 
     #[test]
     fn test_encode_aggregator() {
-        let ipv4 = Ipv4Addr::from_str("10.0.0.1").unwrap();
+        let identifier = BgpIdentifier::from_str("10.0.0.1").unwrap();
         let asn = Asn::new_16bit(258);
-        let bytes = encode_aggregator(&asn, &ipv4.into());
+        let bytes = encode_aggregator(&asn, &identifier);
         assert_eq!(bytes, Bytes::from_static(&[1u8, 2, 10, 0, 0, 1]));
 
-        let ipv6 = Ipv6Addr::from_str("fc00::1").unwrap();
+        // identifiers that are not meaningful IPv4 addresses are kept as is
         let asn = Asn::new_32bit(258);
-        let bytes = encode_aggregator(&asn, &ipv6.into());
-        assert_eq!(
-            bytes,
-            Bytes::from_static(&[
-                0u8, 0, 1, 2, 0xfc, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x01
-            ])
-        );
+        let bytes = encode_aggregator(&asn, &BgpIdentifier::new(258));
+        assert_eq!(bytes, Bytes::from_static(&[0u8, 0, 1, 2, 0, 0, 1, 2]));
     }
 }
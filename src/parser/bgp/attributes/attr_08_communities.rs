@@ -1,16 +1,19 @@
+use crate::models::well_known::{
+    COMMUNITY_NO_ADVERTISE, COMMUNITY_NO_EXPORT, COMMUNITY_NO_EXPORT_SUBCONFED,
+};
 use crate::models::*;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
-const COMMUNITY_NO_EXPORT: u32 = 0xFFFFFF01;
-const COMMUNITY_NO_ADVERTISE: u32 = 0xFFFFFF02;
-const COMMUNITY_NO_EXPORT_SUBCONFED: u32 = 0xFFFFFF03;
-
-pub fn parse_regular_communities(mut input: Bytes) -> Result<AttributeValue, ParserError> {
+pub fn parse_regular_communities(
+    mut input: Bytes,
+    context: &mut ParseContext,
+) -> Result<AttributeValue, ParserError> {
     let mut communities = vec![];
 
     while input.remaining() > 0 {
+        context.record_step()?;
         let community_val = input.read_u32()?;
         communities.push(match community_val {
             COMMUNITY_NO_EXPORT => Community::NoExport,
@@ -52,14 +55,15 @@ mod tests {
     /// Test parsing of communities values, as defined in RFC1997.
     #[test]
     fn test_parse_communities() {
-        if let Ok(AttributeValue::Communities(communities)) =
-            parse_regular_communities(Bytes::from(vec![
+        if let Ok(AttributeValue::Communities(communities)) = parse_regular_communities(
+            Bytes::from(vec![
                 0xFF, 0xFF, 0xFF, 0x01, // NoExport
                 0xFF, 0xFF, 0xFF, 0x02, // NoAdvertise
                 0xFF, 0xFF, 0xFF, 0x03, // NoExportSubConfed
                 0x00, 0x7B, 0x01, 0xC8, // Custom(123, 456)
-            ]))
-        {
+            ]),
+            &mut ParseContext::new(),
+        ) {
             assert_eq!(communities.len(), 4);
             assert_eq!(communities[0], Community::NoExport);
             assert_eq!(communities[1], Community::NoAdvertise);
@@ -68,6 +72,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_communities_limit() {
+        use crate::parser::limits::RecordLimits;
+
+        let limits = RecordLimits {
+            timeout: None,
+            max_steps: Some(10),
+        };
+        let mut context = ParseContext::new().with_limits(limits);
+        context.start_record();
+        assert!(matches!(
+            parse_regular_communities(Bytes::from(vec![0; 4 * 100]), &mut context),
+            Err(ParserError::LimitExceeded(_))
+        ));
+    }
+
     #[test]
     fn test_encode_communities() {
         let communities = vec![
@@ -1,6 +1,6 @@
 use crate::models::*;
 use crate::parser::bgp::attributes::attr_03_next_hop::parse_mp_next_hop;
-use crate::parser::{parse_nlri_list, ReadUtils};
+use crate::parser::{parse_nlri_list_with_context, ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{BufMut, Bytes, BytesMut};
 
@@ -29,6 +29,7 @@ pub fn parse_nlri(
     prefixes: &Option<&[NetworkPrefix]>,
     reachable: bool,        // whether the NLRI is announcements or withdrawals
     additional_paths: bool, // whether the NLRI is part of an additional paths message
+    context: &mut ParseContext,
 ) -> Result<AttributeValue, ParserError> {
     let first_byte_zero = input[0] == 0;
 
@@ -75,7 +76,7 @@ pub fn parse_nlri(
                         warn!("NRLI reserved byte not 0");
                     }
                 }
-                parse_nlri_list(input, additional_paths, &afi)?
+                parse_nlri_list_with_context(input, additional_paths, &afi, context)?
             } else {
                 pfxs.to_vec()
             }
@@ -87,7 +88,7 @@ pub fn parse_nlri(
                     warn!("NRLI reserved byte not 0");
                 }
             }
-            parse_nlri_list(input, additional_paths, &afi)?
+            parse_nlri_list_with_context(input, additional_paths, &afi, context)?
         }
     };
 
@@ -166,7 +167,15 @@ mod tests {
             0x18, // 24 bits prefix length
             0xC0, 0x00, 0x02, // 192.0.2
         ]);
-        let res = parse_nlri(test_bytes, &None, &None, &None, true, false);
+        let res = parse_nlri(
+            test_bytes,
+            &None,
+            &None,
+            &None,
+            true,
+            false,
+            &mut ParseContext::new(),
+        );
 
         if let Ok(AttributeValue::MpReachNlri(nlri)) = res {
             assert_eq!(nlri.afi, Afi::Ipv4);
@@ -203,6 +212,7 @@ mod tests {
             &None,
             true,
             false,
+            &mut ParseContext::new(),
         );
 
         if let Ok(AttributeValue::MpReachNlri(nlri)) = res {
@@ -241,6 +251,7 @@ mod tests {
             &None,
             true,
             false,
+            &mut ParseContext::new(),
         );
         assert!(res.is_err());
     }
@@ -258,7 +269,15 @@ mod tests {
             0x18, // 24 bits prefix length
             0xC0, 0x00, 0x02, // 192.0.2
         ]);
-        let res = parse_nlri(test_bytes, &None, &None, &None, true, true);
+        let res = parse_nlri(
+            test_bytes,
+            &None,
+            &None,
+            &None,
+            true,
+            true,
+            &mut ParseContext::new(),
+        );
 
         if let Ok(AttributeValue::MpReachNlri(nlri)) = res {
             assert_eq!(nlri.afi, Afi::Ipv4);
@@ -304,7 +323,16 @@ mod tests {
                 0xC0, 0x00, 0x01, // 192.0.1
             ])
         );
-        let parsed_nlri = parse_nlri(bytes, &None, &None, &None, true, false).unwrap();
+        let parsed_nlri = parse_nlri(
+            bytes,
+            &None,
+            &None,
+            &None,
+            true,
+            false,
+            &mut ParseContext::new(),
+        )
+        .unwrap();
         assert_eq!(parsed_nlri, AttributeValue::MpReachNlri(nlri));
 
         let nlri = Nlri {
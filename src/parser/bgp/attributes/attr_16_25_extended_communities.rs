@@ -4,16 +4,20 @@
 //! IANA Codes: <https://www.iana.org/assignments/bgp-extended-communities/bgp-extended-communities.xhtml>
 
 use crate::models::*;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::net::Ipv4Addr;
 
-pub fn parse_extended_community(mut input: Bytes) -> Result<AttributeValue, ParserError> {
+pub fn parse_extended_community(
+    mut input: Bytes,
+    context: &mut ParseContext,
+) -> Result<AttributeValue, ParserError> {
     let mut communities = Vec::new();
 
     while input.remaining() > 0 {
+        context.record_step()?;
         let ec_type_u8 = input.read_u8()?;
         let ec: ExtendedCommunity = match ExtendedCommunityType::from(ec_type_u8) {
             ExtendedCommunityType::TransitiveTwoOctetAs => {
@@ -116,9 +120,13 @@ pub fn parse_extended_community(mut input: Bytes) -> Result<AttributeValue, Pars
     Ok(AttributeValue::ExtendedCommunities(communities))
 }
 
-pub fn parse_ipv6_extended_community(mut input: Bytes) -> Result<AttributeValue, ParserError> {
+pub fn parse_ipv6_extended_community(
+    mut input: Bytes,
+    context: &mut ParseContext,
+) -> Result<AttributeValue, ParserError> {
     let mut communities = Vec::new();
     while input.remaining() > 0 {
+        context.record_step()?;
         let ec_type_u8 = input.read_u8()?;
         let sub_type = input.read_u8()?;
         let global = input.read_ipv6_address()?;
@@ -214,7 +222,7 @@ mod tests {
         ];
 
         if let AttributeValue::ExtendedCommunities(communities) =
-            parse_extended_community(Bytes::from(data)).unwrap()
+            parse_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             if let ExtendedCommunity::TransitiveTwoOctetAs(community) = &communities[0] {
@@ -233,7 +241,7 @@ mod tests {
         ];
 
         if let AttributeValue::ExtendedCommunities(communities) =
-            parse_extended_community(Bytes::from(data)).unwrap()
+            parse_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             if let ExtendedCommunity::NonTransitiveTwoOctetAs(community) = &communities[0] {
@@ -254,7 +262,7 @@ mod tests {
         ];
 
         if let AttributeValue::ExtendedCommunities(communities) =
-            parse_extended_community(Bytes::from(data)).unwrap()
+            parse_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             if let ExtendedCommunity::TransitiveIpv4Addr(community) = &communities[0] {
@@ -279,7 +287,7 @@ mod tests {
         ];
 
         if let AttributeValue::ExtendedCommunities(communities) =
-            parse_extended_community(Bytes::from(data)).unwrap()
+            parse_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             if let ExtendedCommunity::TransitiveFourOctetAs(community) = &communities[0] {
@@ -303,7 +311,7 @@ mod tests {
         ];
 
         if let AttributeValue::ExtendedCommunities(communities) =
-            parse_extended_community(Bytes::from(data)).unwrap()
+            parse_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             if let ExtendedCommunity::TransitiveOpaque(community) = &communities[0] {
@@ -328,7 +336,7 @@ mod tests {
         ];
 
         if let AttributeValue::Ipv6AddressSpecificExtendedCommunities(communities) =
-            parse_ipv6_extended_community(Bytes::from(data)).unwrap()
+            parse_ipv6_extended_community(Bytes::from(data), &mut ParseContext::new()).unwrap()
         {
             assert_eq!(communities.len(), 1);
             let community = communities[0];
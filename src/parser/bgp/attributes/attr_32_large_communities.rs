@@ -1,11 +1,15 @@
 use crate::models::*;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
-pub fn parse_large_communities(mut input: Bytes) -> Result<AttributeValue, ParserError> {
+pub fn parse_large_communities(
+    mut input: Bytes,
+    context: &mut ParseContext,
+) -> Result<AttributeValue, ParserError> {
     let mut communities = Vec::new();
     while input.remaining() > 0 {
+        context.record_step()?;
         input.has_n_remaining(12)?; // 12 bytes for large community (3x 32 bits integers)
         let global_administrator = input.get_u32();
         let local_data = [input.get_u32(), input.get_u32()];
@@ -40,7 +44,7 @@ mod tests {
         ];
 
         if let Ok(AttributeValue::LargeCommunities(communities)) =
-            parse_large_communities(Bytes::from(data))
+            parse_large_communities(Bytes::from(data), &mut ParseContext::new())
         {
             assert_eq!(communities.len(), 2);
             assert_eq!(communities[0].global_admin, 1);
@@ -11,10 +11,10 @@ mod attr_14_15_nlri;
 mod attr_16_25_extended_communities;
 mod attr_32_large_communities;
 mod attr_35_otc;
+mod nested_tlvs;
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use log::{debug, warn};
-use std::net::IpAddr;
 
 use crate::models::*;
 
@@ -33,9 +33,9 @@ use crate::parser::bgp::attributes::attr_09_originator::{
 };
 use crate::parser::bgp::attributes::attr_10_13_cluster::{encode_clusters, parse_clusters};
 use crate::parser::bgp::attributes::attr_14_15_nlri::{encode_nlri, parse_nlri};
+pub(crate) use crate::parser::bgp::attributes::attr_16_25_extended_communities::encode_extended_communities;
 use crate::parser::bgp::attributes::attr_16_25_extended_communities::{
-    encode_extended_communities, encode_ipv6_extended_communities, parse_extended_community,
-    parse_ipv6_extended_community,
+    encode_ipv6_extended_communities, parse_extended_community, parse_ipv6_extended_community,
 };
 use crate::parser::bgp::attributes::attr_32_large_communities::{
     encode_large_communities, parse_large_communities,
@@ -43,25 +43,79 @@ use crate::parser::bgp::attributes::attr_32_large_communities::{
 use crate::parser::bgp::attributes::attr_35_otc::{
     encode_only_to_customer, parse_only_to_customer,
 };
-use crate::parser::ReadUtils;
+use crate::parser::bgp::attributes::nested_tlvs::{check_nested_tlvs, has_nested_tlvs};
+use crate::parser::{ParseContext, ReadUtils};
+
+/// Attribute types kept as [AttributeValue::Lazy] with lazy attributes enabled.
+///
+/// These are the attributes holding lists that are costly to decode and not needed to find the
+/// prefixes of a message. MP_REACH_NLRI and MP_UNREACH_NLRI, and the attributes of fixed size,
+/// are always decoded.
+fn is_lazy_attr_type(attr_type: AttrType) -> bool {
+    matches!(
+        attr_type,
+        AttrType::AS_PATH
+            | AttrType::AS4_PATH
+            | AttrType::AGGREGATOR
+            | AttrType::AS4_AGGREGATOR
+            | AttrType::COMMUNITIES
+            | AttrType::EXTENDED_COMMUNITIES
+            | AttrType::IPV6_ADDRESS_SPECIFIC_EXTENDED_COMMUNITIES
+            | AttrType::LARGE_COMMUNITIES
+            | AttrType::CLUSTER_LIST
+    )
+}
 
 /// Parse BGP attributes given a slice of u8 and some options.
 ///
 /// The `data: &[u8]` contains the entirety of the attributes bytes, therefore the size of
 /// the slice is the total byte length of the attributes section of the message.
+///
+/// Attributes with nested TLV structures (`TUNNEL_ENCAPSULATION`, `BGP_LS_ATTRIBUTE`, `ATTR_SET`)
+/// are not decoded and are kept as [AttributeValue::Unknown] with their raw bytes. Their structure
+/// is walked once to bound crafted messages: nesting deeper than 8 levels fails the whole message
+/// with [ParserError::LimitExceeded], and each nested TLV counts as a step of the
+/// [record limits](crate::parser::limits).
+///
+/// Attribute values are decoded from slices of `data`. For records parsed with
+/// [ParseContext::with_copy_values_up_to](crate::ParseContext::with_copy_values_up_to), values of
+/// up to the given length are copied first instead, so that no part of the attribute parsing
+/// references the buffer `data` was sliced from.
 pub fn parse_attributes(
+    data: Bytes,
+    asn_len: &AsnLength,
+    add_path: bool,
+    afi: Option<Afi>,
+    safi: Option<Safi>,
+    prefixes: Option<&[NetworkPrefix]>,
+) -> Result<Attributes, ParserError> {
+    parse_attributes_with_context(
+        data,
+        asn_len,
+        add_path,
+        afi,
+        safi,
+        prefixes,
+        &mut ParseContext::new(),
+    )
+}
+
+/// Parse BGP attributes with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_attributes_with_context(
     mut data: Bytes,
     asn_len: &AsnLength,
     add_path: bool,
     afi: Option<Afi>,
     safi: Option<Safi>,
     prefixes: Option<&[NetworkPrefix]>,
+    context: &mut ParseContext,
 ) -> Result<Attributes, ParserError> {
-    let mut attributes: Vec<Attribute> = Vec::with_capacity(20);
+    let mut attributes: Vec<Attribute> = context.vecs.take_attributes(20);
 
     while data.remaining() >= 3 {
         // each attribute is at least 3 bytes: flag(1) + type(1) + length(1)
         // thus the while loop condition is set to be at least 3 bytes to read.
+        context.record_step()?;
 
         // has content to read
         let flag = AttrFlags::from_bits_retain(data.get_u8());
@@ -128,73 +182,50 @@ pub fn parse_attributes(
 
         // we know data has enough bytes to read, so we can split the bytes into a new Bytes object
         data.has_n_remaining(attr_length)?;
-        let mut attr_data = data.split_to(attr_length);
-
-        let attr = match attr_type {
-            AttrType::ORIGIN => parse_origin(attr_data),
-            AttrType::AS_PATH => {
-                parse_as_path(attr_data, asn_len).map(|path| AttributeValue::AsPath {
-                    path,
-                    is_as4: false,
-                })
+        let attr_data = match context.copy_values_up_to {
+            Some(max_len) if attr_length <= max_len => {
+                let attr_data = Bytes::copy_from_slice(&data[..attr_length]);
+                data.advance(attr_length);
+                attr_data
             }
-            AttrType::NEXT_HOP => parse_next_hop(attr_data, &afi),
-            AttrType::MULTI_EXIT_DISCRIMINATOR => parse_med(attr_data),
-            AttrType::LOCAL_PREFERENCE => parse_local_pref(attr_data),
-            AttrType::ATOMIC_AGGREGATE => Ok(AttributeValue::AtomicAggregate),
-            AttrType::AGGREGATOR => {
-                parse_aggregator(attr_data, asn_len).map(|(asn, id)| AttributeValue::Aggregator {
-                    asn,
-                    id,
-                    is_as4: false,
-                })
-            }
-            AttrType::ORIGINATOR_ID => parse_originator_id(attr_data),
-            AttrType::CLUSTER_LIST => parse_clusters(attr_data),
-            AttrType::MP_REACHABLE_NLRI => {
-                parse_nlri(attr_data, &afi, &safi, &prefixes, true, add_path)
-            }
-            AttrType::MP_UNREACHABLE_NLRI => {
-                parse_nlri(attr_data, &afi, &safi, &prefixes, false, add_path)
-            }
-            AttrType::AS4_PATH => parse_as_path(attr_data, &AsnLength::Bits32)
-                .map(|path| AttributeValue::AsPath { path, is_as4: true }),
-            AttrType::AS4_AGGREGATOR => {
-                parse_aggregator(attr_data, &AsnLength::Bits32).map(|(asn, id)| {
-                    AttributeValue::Aggregator {
-                        asn,
-                        id,
-                        is_as4: true,
-                    }
-                })
-            }
-
-            // communities
-            AttrType::COMMUNITIES => parse_regular_communities(attr_data),
-            AttrType::LARGE_COMMUNITIES => parse_large_communities(attr_data),
-            AttrType::EXTENDED_COMMUNITIES => parse_extended_community(attr_data),
-            AttrType::IPV6_ADDRESS_SPECIFIC_EXTENDED_COMMUNITIES => {
-                parse_ipv6_extended_community(attr_data)
-            }
-            AttrType::DEVELOPMENT => {
-                let mut value = vec![];
-                for _i in 0..attr_length {
-                    value.push(attr_data.get_u8());
-                }
-                Ok(AttributeValue::Development(value))
-            }
-            AttrType::ONLY_TO_CUSTOMER => parse_only_to_customer(attr_data),
-            _ => Err(ParserError::Unsupported(format!(
-                "unsupported attribute type: {:?}",
-                attr_type
-            ))),
+            _ => data.split_to(attr_length),
         };
 
+        if context.lazy_attributes && is_lazy_attr_type(attr_type) {
+            attributes.push(Attribute {
+                value: AttributeValue::Lazy(LazyAttr {
+                    attr_type,
+                    asn_len: *asn_len,
+                    bytes: attr_data.to_vec(),
+                }),
+                flag,
+            });
+            continue;
+        }
+
+        let attr = parse_attr_value(
+            attr_type, attr_data, asn_len, &afi, &safi, &prefixes, add_path, context,
+        );
+
         match attr {
             Ok(value) => {
                 assert_eq!(attr_type, value.attr_type());
+                if matches!(
+                    attr_type,
+                    AttrType::MP_REACHABLE_NLRI | AttrType::MP_UNREACHABLE_NLRI
+                ) && attributes.iter().any(|a| a.value.attr_type() == attr_type)
+                {
+                    // RFC 7606 section 3(g) asks to treat repeated MP_REACH/MP_UNREACH attributes
+                    // as withdrawal. We keep all of them instead so that no NLRI is lost, and
+                    // leave the decision to the consumers.
+                    warn!(
+                        "repeated attribute type {:?}; keeping all of them",
+                        attr_type
+                    );
+                }
                 attributes.push(Attribute { value, flag });
             }
+            Err(e @ ParserError::LimitExceeded(_)) => return Err(e),
             Err(e) => {
                 if partial {
                     // it's ok to have errors when reading partial bytes
@@ -210,15 +241,134 @@ pub fn parse_attributes(
     Ok(Attributes::from(attributes))
 }
 
+/// Decode the value of an attribute of a known type.
+#[allow(clippy::too_many_arguments)]
+fn parse_attr_value(
+    attr_type: AttrType,
+    attr_data: Bytes,
+    asn_len: &AsnLength,
+    afi: &Option<Afi>,
+    safi: &Option<Safi>,
+    prefixes: &Option<&[NetworkPrefix]>,
+    add_path: bool,
+    context: &mut ParseContext,
+) -> Result<AttributeValue, ParserError> {
+    match attr_type {
+        AttrType::ORIGIN => parse_origin(attr_data),
+        AttrType::AS_PATH => {
+            parse_as_path(attr_data, asn_len, context).map(|path| AttributeValue::AsPath {
+                path,
+                is_as4: false,
+            })
+        }
+        AttrType::NEXT_HOP => parse_next_hop(attr_data, afi),
+        AttrType::MULTI_EXIT_DISCRIMINATOR => parse_med(attr_data),
+        AttrType::LOCAL_PREFERENCE => parse_local_pref(attr_data),
+        AttrType::ATOMIC_AGGREGATE => Ok(AttributeValue::AtomicAggregate),
+        AttrType::AGGREGATOR => {
+            parse_aggregator(attr_data, asn_len).map(|(asn, id)| AttributeValue::Aggregator {
+                asn,
+                id,
+                is_as4: false,
+            })
+        }
+        AttrType::ORIGINATOR_ID => parse_originator_id(attr_data),
+        AttrType::CLUSTER_LIST => parse_clusters(attr_data),
+        AttrType::MP_REACHABLE_NLRI => {
+            parse_nlri(attr_data, afi, safi, prefixes, true, add_path, context)
+        }
+        AttrType::MP_UNREACHABLE_NLRI => {
+            parse_nlri(attr_data, afi, safi, prefixes, false, add_path, context)
+        }
+        AttrType::AS4_PATH => parse_as_path(attr_data, &AsnLength::Bits32, context)
+            .map(|path| AttributeValue::AsPath { path, is_as4: true }),
+        AttrType::AS4_AGGREGATOR => {
+            parse_aggregator(attr_data, &AsnLength::Bits32).map(|(asn, id)| {
+                AttributeValue::Aggregator {
+                    asn,
+                    id,
+                    is_as4: true,
+                }
+            })
+        }
+
+        // communities
+        AttrType::COMMUNITIES => parse_regular_communities(attr_data, context),
+        AttrType::LARGE_COMMUNITIES => parse_large_communities(attr_data, context),
+        AttrType::EXTENDED_COMMUNITIES => parse_extended_community(attr_data, context),
+        AttrType::IPV6_ADDRESS_SPECIFIC_EXTENDED_COMMUNITIES => {
+            parse_ipv6_extended_community(attr_data, context)
+        }
+        AttrType::DEVELOPMENT => Ok(AttributeValue::Development(attr_data.to_vec())),
+        AttrType::ONLY_TO_CUSTOMER => parse_only_to_customer(attr_data),
+        _ => {
+            if has_nested_tlvs(attr_type) {
+                match check_nested_tlvs(attr_type, attr_data.clone(), context) {
+                    Ok(()) => {}
+                    Err(e @ ParserError::LimitExceeded(_)) => return Err(e),
+                    Err(e) => debug!("malformed {:?} attribute: {}", attr_type, e),
+                }
+            }
+            // keep the raw bytes of assigned but not yet supported attribute types
+            debug!("unsupported attribute type: {:?}", attr_type);
+            Ok(AttributeValue::Unknown(AttrRaw {
+                attr_type,
+                bytes: attr_data.to_vec(),
+            }))
+        }
+    }
+}
+
+impl LazyAttr {
+    /// Decode the attribute.
+    pub fn decode(&self) -> Result<AttributeValue, ParserError> {
+        parse_attr_value(
+            self.attr_type,
+            Bytes::from(self.bytes.clone()),
+            &self.asn_len,
+            &None,
+            &None,
+            &None,
+            false,
+            &mut ParseContext::new(),
+        )
+    }
+}
+
+impl Attributes {
+    /// Decode all [AttributeValue::Lazy] attributes, dropping those that fail to decode like the
+    /// parser drops invalid attributes.
+    pub fn decode_lazy(&mut self) {
+        self.inner.retain_mut(|attr| match &attr.value {
+            AttributeValue::Lazy(lazy) => match lazy.decode() {
+                Ok(value) => {
+                    attr.value = value;
+                    true
+                }
+                Err(e) => {
+                    debug!("{}", e);
+                    false
+                }
+            },
+            _ => true,
+        });
+    }
+}
+
 impl Attribute {
+    /// Encode the attribute with its flags as they are. See [Attribute::encode_with_flags_policy].
     pub fn encode(&self, add_path: bool, asn_len: AsnLength) -> Bytes {
-        let mut bytes = BytesMut::new();
-
-        let flag = self.flag.bits();
-        let type_code = self.value.attr_type().into();
+        self.encode_with_flags_policy(add_path, asn_len, AttrFlagsPolicy::Preserve)
+    }
 
-        bytes.put_u8(flag);
-        bytes.put_u8(type_code);
+    /// Encode the attribute, writing the flags according to the given [AttrFlagsPolicy].
+    pub fn encode_with_flags_policy(
+        &self,
+        add_path: bool,
+        asn_len: AsnLength,
+        flags_policy: AttrFlagsPolicy,
+    ) -> Bytes {
+        let mut bytes = BytesMut::new();
 
         let value_bytes = match &self.value {
             AttributeValue::Origin(v) => encode_origin(v),
@@ -237,25 +387,46 @@ impl Attribute {
             AttributeValue::LocalPreference(v) => encode_local_pref(*v),
             AttributeValue::OnlyToCustomer(v) => encode_only_to_customer(v.into()),
             AttributeValue::AtomicAggregate => Bytes::default(),
-            AttributeValue::Aggregator { asn, id, is_as4: _ } => {
-                encode_aggregator(asn, &IpAddr::from(*id))
-            }
+            AttributeValue::Aggregator { asn, id, is_as4: _ } => encode_aggregator(asn, id),
             AttributeValue::Communities(v) => encode_regular_communities(v),
             AttributeValue::ExtendedCommunities(v) => encode_extended_communities(v),
             AttributeValue::LargeCommunities(v) => encode_large_communities(v),
             AttributeValue::Ipv6AddressSpecificExtendedCommunities(v) => {
                 encode_ipv6_extended_communities(v)
             }
-            AttributeValue::OriginatorId(v) => encode_originator_id(&IpAddr::from(*v)),
+            AttributeValue::OriginatorId(v) => encode_originator_id(v),
             AttributeValue::Clusters(v) => encode_clusters(v),
             AttributeValue::MpReachNlri(v) => encode_nlri(v, true, add_path),
             AttributeValue::MpUnreachNlri(v) => encode_nlri(v, false, add_path),
             AttributeValue::Development(v) => Bytes::from(v.to_owned()),
             AttributeValue::Deprecated(v) => Bytes::from(v.bytes.to_owned()),
             AttributeValue::Unknown(v) => Bytes::from(v.bytes.to_owned()),
+            AttributeValue::Lazy(v) => match v.decode() {
+                Ok(value) => {
+                    let attr = Attribute {
+                        value,
+                        flag: self.flag,
+                    };
+                    return attr.encode_with_flags_policy(add_path, asn_len, flags_policy);
+                }
+                // written back as read if it cannot be decoded
+                Err(_) => Bytes::from(v.bytes.to_owned()),
+            },
         };
 
-        match self.is_extended() {
+        // values longer than 255 bytes can only be encoded with the extended length flag set
+        let mut flag = match flags_policy {
+            AttrFlagsPolicy::Preserve => self.flag,
+            AttrFlagsPolicy::Normalize => self.normalized_flags(),
+        };
+        if value_bytes.len() > u8::MAX as usize {
+            flag.insert(AttrFlags::EXTENDED);
+        }
+        let type_code = self.value.attr_type().into();
+
+        bytes.put_u8(flag.bits());
+        bytes.put_u8(type_code);
+        match flag.contains(AttrFlags::EXTENDED) {
             false => {
                 bytes.put_u8(value_bytes.len() as u8);
             }
@@ -270,9 +441,19 @@ impl Attribute {
 
 impl Attributes {
     pub fn encode(&self, add_path: bool, asn_len: AsnLength) -> Bytes {
+        self.encode_with_flags_policy(add_path, asn_len, AttrFlagsPolicy::Preserve)
+    }
+
+    /// Encode the attributes, writing the flags according to the given [AttrFlagsPolicy].
+    pub fn encode_with_flags_policy(
+        &self,
+        add_path: bool,
+        asn_len: AsnLength,
+        flags_policy: AttrFlagsPolicy,
+    ) -> Bytes {
         let mut bytes = BytesMut::new();
         for attr in &self.inner {
-            bytes.extend(attr.encode(add_path, asn_len));
+            bytes.extend(attr.encode_with_flags_policy(add_path, asn_len, flags_policy));
         }
         bytes.freeze()
     }
@@ -299,4 +480,164 @@ mod tests {
             AttrType::Unknown(254)
         );
     }
+
+    #[test]
+    fn test_unsupported_attribute_type() {
+        // BGPSEC_PATH (33) is assigned but not decoded; it used to be dropped with an
+        // `Unsupported` error
+        let data = Bytes::from(vec![0x80, 0x21, 0x02, 0xAB, 0xCD]);
+        let attributes =
+            parse_attributes(data, &AsnLength::Bits32, false, None, None, None).unwrap();
+        assert_eq!(attributes.inner.len(), 1);
+        assert_eq!(
+            attributes.inner[0].value,
+            AttributeValue::Unknown(AttrRaw {
+                attr_type: AttrType::BGPSEC_PATH,
+                bytes: vec![0xAB, 0xCD],
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_flags_policy() {
+        // ORIGIN with the optional bit wrongly set
+        let attr = Attribute {
+            value: AttributeValue::Origin(Origin::IGP),
+            flag: AttrFlags::OPTIONAL | AttrFlags::TRANSITIVE,
+        };
+        let preserved = attr.encode(false, AsnLength::Bits32);
+        assert_eq!(preserved.as_ref(), &[0xC0, 0x01, 0x01, 0x00]);
+        let normalized =
+            attr.encode_with_flags_policy(false, AsnLength::Bits32, AttrFlagsPolicy::Normalize);
+        assert_eq!(normalized.as_ref(), &[0x40, 0x01, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_nested_tlv_attributes_kept_raw() {
+        // ATTR_SET (128) with a nested ATTR_SET holding an origin attribute
+        let inner = vec![
+            0xD0, 0x80, 0x00, 0x08, 0, 0, 0xFD, 0xE9, 0x40, 0x01, 0x01, 0x00,
+        ];
+        let mut attr_set = vec![0xD0, 0x80, 0x00, 0x10, 0, 0, 0xFD, 0xE8];
+        attr_set.extend(&inner);
+        // BGP-LS attribute (29) with many empty TLVs
+        let mut bgp_ls = vec![0x90, 0x1D, 0xFF, 0xFC];
+        bgp_ls.extend([0x04, 0x00, 0x00, 0x00].repeat(0x3FFF));
+        // tunnel encapsulation (23) with a sub-TLV claiming more bytes than available
+        let tunnel = vec![
+            0xC0, 0x17, 0x08, 0x00, 0x08, 0x00, 0x04, 0x01, 0xFF, 0xFF, 0xFF,
+        ];
+
+        let data = Bytes::from([attr_set, bgp_ls.clone(), tunnel].concat());
+        let attributes =
+            parse_attributes(data, &AsnLength::Bits32, false, None, None, None).unwrap();
+        let types: Vec<AttrType> = attributes.iter().map(|a| a.attr_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                AttrType::ATTR_SET,
+                AttrType::BGP_LS_ATTRIBUTE,
+                AttrType::TUNNEL_ENCAPSULATION
+            ]
+        );
+        assert!(attributes
+            .iter()
+            .all(|a| matches!(a, AttributeValue::Unknown(_))));
+
+        // the TLVs count as steps of the record limits
+        let limits = crate::parser::RecordLimits {
+            timeout: None,
+            max_steps: Some(1000),
+        };
+        let mut context = ParseContext::new().with_limits(limits);
+        context.start_record();
+        let res = parse_attributes_with_context(
+            Bytes::from(bgp_ls),
+            &AsnLength::Bits32,
+            false,
+            None,
+            None,
+            None,
+            &mut context,
+        );
+        assert!(matches!(res, Err(ParserError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_nested_tlv_depth_limit() {
+        // ATTR_SET (128) nested as deep as the extended length field allows: origin AS followed by
+        // another ATTR_SET
+        let nest = |depth: usize| {
+            let mut nested: Vec<u8> = vec![];
+            for _ in 0..depth {
+                let mut value = vec![0, 0, 0xFD, 0xE8];
+                value.extend(&nested);
+                nested = vec![0xD0, 0x80];
+                nested.extend((value.len() as u16).to_be_bytes());
+                nested.extend(value);
+            }
+            Bytes::from(nested)
+        };
+
+        let attributes =
+            parse_attributes(nest(8), &AsnLength::Bits32, false, None, None, None).unwrap();
+        assert_eq!(attributes.iter().count(), 1);
+
+        let res = parse_attributes(nest(9), &AsnLength::Bits32, false, None, None, None);
+        assert!(matches!(res, Err(ParserError::LimitExceeded(_))));
+        let res = parse_attributes(nest(8000), &AsnLength::Bits32, false, None, None, None);
+        assert!(matches!(res, Err(ParserError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_copy_values() {
+        // ORIGIN, unknown attribute 254 and COMMUNITIES with two communities
+        let data = Bytes::from(vec![
+            0x40, 0x01, 0x01, 0x00, 0xC0, 0xFE, 0x02, 0x01, 0x02, 0xC0, 0x08, 0x08, 0, 1, 0, 2, 0,
+            3, 0, 4,
+        ]);
+        let sliced =
+            parse_attributes(data.clone(), &AsnLength::Bits32, false, None, None, None).unwrap();
+        let mut context = ParseContext::new().with_copy_values_up_to(4);
+        let copied = parse_attributes_with_context(
+            data,
+            &AsnLength::Bits32,
+            false,
+            None,
+            None,
+            None,
+            &mut context,
+        )
+        .unwrap();
+        assert_eq!(sliced, copied);
+    }
+
+    #[test]
+    fn test_repeated_mp_reach_nlri() {
+        // two MP_REACH_NLRI attributes, each announcing one IPv6 prefix
+        let mp_reach = |prefix: u8| {
+            vec![
+                0x80, 0x0E, 0x1A, // flag, type, length
+                0x00, 0x02, 0x01, // afi, safi
+                0x10, // next hop length
+                0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,    // next hop
+                0x00, // reserved
+                0x20, 0x20, 0x01, 0x0d, prefix, // 2001:d<prefix>::/32
+            ]
+        };
+        let data = Bytes::from([mp_reach(0xb8), mp_reach(0xb9)].concat());
+        let attributes =
+            parse_attributes(data, &AsnLength::Bits32, false, None, None, None).unwrap();
+        assert_eq!(attributes.inner.len(), 2);
+        assert_eq!(
+            attributes.get_reachable_nlri().unwrap().prefixes[0].to_string(),
+            "2001:db8::/32"
+        );
+        let prefixes: Vec<String> = attributes
+            .iter_reachable_nlri()
+            .flat_map(|nlri| nlri.prefixes.iter().map(|p| p.to_string()))
+            .collect();
+        assert_eq!(prefixes, vec!["2001:db8::/32", "2001:db9::/32"]);
+        assert_eq!(attributes.iter_unreachable_nlri().count(), 0);
+    }
 }
@@ -0,0 +1,115 @@
+//! Bounds checks of attributes with nested TLV structures.
+//!
+//! `TUNNEL_ENCAPSULATION`, `BGP_LS_ATTRIBUTE` and `ATTR_SET` are not decoded and are kept as raw
+//! bytes, but their structure is walked once so that crafted messages are bounded: each TLV and
+//! nested attribute counts as a step of the [record limits](crate::parser::limits), and nesting
+//! deeper than [MAX_NESTED_DEPTH] fails with [ParserError::LimitExceeded].
+use crate::error::ParserError;
+use crate::models::{AttrFlags, AttrType};
+use crate::parser::tlv::TlvReader;
+use crate::parser::{ParseContext, ReadUtils};
+use bytes::{Buf, Bytes};
+
+/// Maximum nesting depth of TLVs and attribute sets within an attribute.
+pub(crate) const MAX_NESTED_DEPTH: usize = 8;
+
+/// Returns true for the attribute types whose values are checked by [check_nested_tlvs].
+pub(crate) fn has_nested_tlvs(attr_type: AttrType) -> bool {
+    matches!(
+        attr_type,
+        AttrType::TUNNEL_ENCAPSULATION | AttrType::BGP_LS_ATTRIBUTE | AttrType::ATTR_SET
+    )
+}
+
+/// Walk the nested structure of an attribute value.
+///
+/// Returns [ParserError::LimitExceeded] if the nesting is too deep or the record limits are
+/// exceeded, and other errors if the structure is malformed.
+pub(crate) fn check_nested_tlvs(
+    attr_type: AttrType,
+    data: Bytes,
+    context: &mut ParseContext,
+) -> Result<(), ParserError> {
+    check_attr_value(attr_type, data, 1, context)
+}
+
+fn check_depth(depth: usize) -> Result<(), ParserError> {
+    match depth > MAX_NESTED_DEPTH {
+        true => Err(ParserError::LimitExceeded(format!(
+            "attribute TLVs nested more than {} levels deep",
+            MAX_NESTED_DEPTH
+        ))),
+        false => Ok(()),
+    }
+}
+
+fn check_attr_value(
+    attr_type: AttrType,
+    data: Bytes,
+    depth: usize,
+    context: &mut ParseContext,
+) -> Result<(), ParserError> {
+    check_depth(depth)?;
+    match attr_type {
+        AttrType::TUNNEL_ENCAPSULATION => check_tunnel_encapsulation(data, depth, context),
+        AttrType::BGP_LS_ATTRIBUTE => {
+            // RFC 7752 section 3.3: a flat list of TLVs with 2-octet type and length
+            for tlv in TlvReader::new(data) {
+                context.record_step()?;
+                tlv?;
+            }
+            Ok(())
+        }
+        AttrType::ATTR_SET => check_attr_set(data, depth, context),
+        _ => Ok(()),
+    }
+}
+
+/// RFC 9012 section 2: tunnel TLVs with 2-octet type and length, each holding sub-TLVs with a
+/// 1-octet type, and a 1-octet length for types below 128 or a 2-octet length otherwise.
+fn check_tunnel_encapsulation(
+    data: Bytes,
+    depth: usize,
+    context: &mut ParseContext,
+) -> Result<(), ParserError> {
+    for tlv in TlvReader::new(data) {
+        context.record_step()?;
+        let mut sub_tlvs = tlv?.value;
+        check_depth(depth + 1)?;
+        while sub_tlvs.has_remaining() {
+            context.record_step()?;
+            let sub_type = sub_tlvs.read_u8()?;
+            let length = match sub_type < 128 {
+                true => sub_tlvs.read_u8()? as usize,
+                false => sub_tlvs.read_u16()? as usize,
+            };
+            sub_tlvs.skip_n_bytes(length)?;
+        }
+    }
+    Ok(())
+}
+
+/// RFC 6368 section 5: an origin AS followed by path attributes, which may be nested attributes
+/// themselves.
+fn check_attr_set(
+    mut data: Bytes,
+    depth: usize,
+    context: &mut ParseContext,
+) -> Result<(), ParserError> {
+    data.skip_n_bytes(4)?;
+    while data.has_remaining() {
+        context.record_step()?;
+        let flag = AttrFlags::from_bits_retain(data.read_u8()?);
+        let attr_type = AttrType::from(data.read_u8()?);
+        let length = match flag.contains(AttrFlags::EXTENDED) {
+            true => data.read_u16()? as usize,
+            false => data.read_u8()? as usize,
+        };
+        data.has_n_remaining(length)?;
+        let value = data.split_to(length);
+        if has_nested_tlvs(attr_type) {
+            check_attr_value(attr_type, value, depth + 1, context)?;
+        }
+    }
+    Ok(())
+}
@@ -3,10 +3,10 @@ use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryFrom;
 
 use crate::error::ParserError;
-use crate::models::capabilities::BgpCapabilityType;
+use crate::models::capabilities::{AddPathMode, BgpCapabilityType, CapabilityValue};
 use crate::models::error::BgpError;
-use crate::parser::bgp::attributes::parse_attributes;
-use crate::parser::{encode_ipaddr, encode_nlri_prefixes, parse_nlri_list, ReadUtils};
+use crate::parser::bgp::attributes::parse_attributes_with_context;
+use crate::parser::{encode_nlri_prefixes, parse_nlri_list_with_context, ParseContext, ReadUtils};
 use log::warn;
 
 /// BGP message
@@ -31,6 +31,16 @@ pub fn parse_bgp_message(
     data: &mut Bytes,
     add_path: bool,
     asn_len: &AsnLength,
+) -> Result<BgpMessage, ParserError> {
+    parse_bgp_message_with_context(data, add_path, asn_len, &mut ParseContext::new())
+}
+
+/// Parse a BGP message with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_bgp_message_with_context(
+    data: &mut Bytes,
+    add_path: bool,
+    asn_len: &AsnLength,
+    context: &mut ParseContext,
 ) -> Result<BgpMessage, ParserError> {
     let total_size = data.len();
     data.has_n_remaining(19)?;
@@ -81,9 +91,9 @@ pub fn parse_bgp_message(
 
     Ok(match msg_type {
         BgpMessageType::OPEN => BgpMessage::Open(parse_bgp_open_message(&mut msg_data)?),
-        BgpMessageType::UPDATE => {
-            BgpMessage::Update(parse_bgp_update_message(msg_data, add_path, asn_len)?)
-        }
+        BgpMessageType::UPDATE => BgpMessage::Update(parse_bgp_update_message_with_context(
+            msg_data, add_path, asn_len, context,
+        )?),
         BgpMessageType::NOTIFICATION => {
             BgpMessage::Notification(parse_bgp_notification_message(msg_data)?)
         }
@@ -129,7 +139,7 @@ pub fn parse_bgp_open_message(input: &mut Bytes) -> Result<BgpOpenMessage, Parse
     let asn = Asn::new_16bit(input.get_u16());
     let hold_time = input.get_u16();
 
-    let sender_ip = input.read_ipv4_address()?;
+    let sender_ip = BgpIdentifier::from(input.read_u32()?);
     let mut opt_params_len: u16 = input.get_u8() as u16;
 
     let mut extended_length = false;
@@ -192,11 +202,10 @@ pub fn parse_bgp_open_message(input: &mut Bytes) -> Result<BgpOpenMessage, Parse
             2 => {
                 // capability codes:
                 // https://www.iana.org/assignments/capability-codes/capability-codes.xhtml#capability-codes-2
+                // the capability length is one octet in both formats, RFC9072 only extends
+                // the parameter length
                 let code = input.read_u8()?;
-                let len = match extended_length {
-                    true => input.read_u16()?,
-                    false => input.read_u8()? as u16,
-                };
+                let len = input.read_u8()?;
 
                 ParamValue::Capability(Capability {
                     ty: BgpCapabilityType::from(code),
@@ -227,28 +236,173 @@ pub fn parse_bgp_open_message(input: &mut Bytes) -> Result<BgpOpenMessage, Parse
 }
 
 impl BgpOpenMessage {
+    /// Encode the OPEN message.
+    ///
+    /// Parameter lengths are computed from the parameter values. The extended optional parameters
+    /// format of RFC9072 is used if `extended_length` is set, or if the parameters do not fit in
+    /// 255 bytes. A 4-byte ASN is encoded as AS_TRANS, the actual ASN being carried by the 4-octet
+    /// AS number capability.
     pub fn encode(&self) -> Bytes {
+        let params: Vec<(u8, Bytes)> = self
+            .opt_params
+            .iter()
+            .map(|param| {
+                let value = match &param.param_value {
+                    ParamValue::Capability(cap) => {
+                        let mut value = BytesMut::with_capacity(2 + cap.value.len());
+                        value.put_u8(cap.ty.into());
+                        value.put_u8(cap.value.len() as u8);
+                        value.put_slice(&cap.value);
+                        value.freeze()
+                    }
+                    ParamValue::Raw(bytes) => Bytes::from(bytes.clone()),
+                };
+                (param.param_type, value)
+            })
+            .collect();
+        let params_len: usize = params.iter().map(|(_, value)| 2 + value.len()).sum();
+        let extended_length = self.extended_length || params_len > 255;
+
         let mut buf = BytesMut::new();
         buf.put_u8(self.version);
-        buf.put_u16(self.asn.into());
+        match self.asn.required_len() {
+            AsnLength::Bits16 => buf.put_u16(self.asn.into()),
+            AsnLength::Bits32 => buf.put_u16(Asn::TRANSITION.into()),
+        }
         buf.put_u16(self.hold_time);
-        buf.extend(encode_ipaddr(&self.sender_ip.into()));
-        buf.put_u8(self.opt_params.len() as u8);
-        for param in &self.opt_params {
-            buf.put_u8(param.param_type);
-            buf.put_u8(param.param_len as u8);
-            match &param.param_value {
-                ParamValue::Capability(cap) => {
-                    buf.put_u8(cap.ty.into());
-                    buf.put_u8(cap.value.len() as u8);
-                    buf.extend(&cap.value);
+        buf.put_u32(self.sender_ip.into());
+        match extended_length {
+            true => {
+                buf.put_u8(255);
+                buf.put_u8(255);
+                buf.put_u16((params_len + params.len()) as u16);
+            }
+            false => buf.put_u8(params_len as u8),
+        }
+        for (param_type, value) in params {
+            buf.put_u8(param_type);
+            match extended_length {
+                true => buf.put_u16(value.len() as u16),
+                false => buf.put_u8(value.len() as u8),
+            }
+            buf.put_slice(&value);
+        }
+        buf.freeze()
+    }
+}
+
+impl From<&CapabilityValue> for Capability {
+    fn from(value: &CapabilityValue) -> Self {
+        let mut bytes = BytesMut::new();
+        match value {
+            CapabilityValue::MultiprotocolExtensions { afi, safi } => {
+                bytes.put_u16(*afi as u16);
+                bytes.put_u8(0); // reserved
+                bytes.put_u8(*safi as u8);
+            }
+            CapabilityValue::RouteRefresh
+            | CapabilityValue::ExtendedMessage
+            | CapabilityValue::EnhancedRouteRefresh => {}
+            CapabilityValue::BgpRole(role) => bytes.put_u8((*role).into()),
+            CapabilityValue::FourOctetAs(asn) => bytes.put_u32(asn.to_u32()),
+            CapabilityValue::AddPath(families) => {
+                for (afi, safi, mode) in families {
+                    bytes.put_u16(*afi as u16);
+                    bytes.put_u8(*safi as u8);
+                    bytes.put_u8((*mode).into());
                 }
-                ParamValue::Raw(bytes) => {
-                    buf.extend(bytes);
+            }
+        }
+        Capability {
+            ty: value.capability_type(),
+            value: bytes.to_vec(),
+        }
+    }
+}
+
+impl From<CapabilityValue> for Capability {
+    fn from(value: CapabilityValue) -> Self {
+        Capability::from(&value)
+    }
+}
+
+impl TryFrom<&Capability> for CapabilityValue {
+    type Error = ParserError;
+
+    /// Decode the value of a capability, failing for capability types without typed value.
+    fn try_from(capability: &Capability) -> Result<Self, Self::Error> {
+        let mut input = Bytes::from(capability.value.clone());
+        let value = match capability.ty {
+            BgpCapabilityType::MULTIPROTOCOL_EXTENSIONS_FOR_BGP_4 => {
+                let afi = input.read_afi()?;
+                input.read_u8()?; // reserved
+                let safi = input.read_safi()?;
+                CapabilityValue::MultiprotocolExtensions { afi, safi }
+            }
+            BgpCapabilityType::ROUTE_REFRESH_CAPABILITY_FOR_BGP_4 => CapabilityValue::RouteRefresh,
+            BgpCapabilityType::BGP_EXTENDED_MESSAGE => CapabilityValue::ExtendedMessage,
+            BgpCapabilityType::BGP_ROLE => {
+                let role = input.read_u8()?;
+                match BgpRole::try_from(role) {
+                    Ok(role) => CapabilityValue::BgpRole(role),
+                    Err(_) => {
+                        return Err(ParserError::ParseError(format!(
+                            "unknown BGP role {}",
+                            role
+                        )))
+                    }
+                }
+            }
+            BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY => {
+                CapabilityValue::FourOctetAs(input.read_asn(AsnLength::Bits32)?)
+            }
+            BgpCapabilityType::ADD_PATH_CAPABILITY => {
+                let mut families = vec![];
+                while input.remaining() > 0 {
+                    let afi = input.read_afi()?;
+                    let safi = input.read_safi()?;
+                    let mode = input.read_u8()?;
+                    match AddPathMode::try_from(mode) {
+                        Ok(mode) => families.push((afi, safi, mode)),
+                        Err(_) => {
+                            return Err(ParserError::ParseError(format!(
+                                "unknown ADD-PATH send/receive value {}",
+                                mode
+                            )))
+                        }
+                    }
                 }
+                CapabilityValue::AddPath(families)
             }
+            BgpCapabilityType::ENHANCED_ROUTE_REFRESH_CAPABILITY => {
+                CapabilityValue::EnhancedRouteRefresh
+            }
+            ty => {
+                return Err(ParserError::Unsupported(format!(
+                    "no typed value for capability {:?}",
+                    ty
+                )))
+            }
+        };
+        if input.has_remaining() {
+            return Err(ParserError::ParseError(format!(
+                "{} trailing bytes in capability {:?}",
+                input.remaining(),
+                capability.ty
+            )));
+        }
+        Ok(value)
+    }
+}
+
+impl From<Capability> for OptParam {
+    /// Wrap a capability into a capabilities optional parameter.
+    fn from(capability: Capability) -> Self {
+        OptParam {
+            param_type: 2,
+            param_len: 2 + capability.value.len() as u16,
+            param_value: ParamValue::Capability(capability),
         }
-        buf.freeze()
     }
 }
 
@@ -257,6 +411,7 @@ fn read_nlri(
     mut input: Bytes,
     afi: &Afi,
     add_path: bool,
+    context: &mut ParseContext,
 ) -> Result<Vec<NetworkPrefix>, ParserError> {
     let length = input.len();
     if length == 0 {
@@ -269,16 +424,26 @@ fn read_nlri(
         return Ok(vec![]);
     }
 
-    parse_nlri_list(input, add_path, afi)
+    parse_nlri_list_with_context(input, add_path, afi, context)
 }
 
 /// read bgp update message.
 ///
 /// RFC: <https://tools.ietf.org/html/rfc4271#section-4.3>
 pub fn parse_bgp_update_message(
+    input: Bytes,
+    add_path: bool,
+    asn_len: &AsnLength,
+) -> Result<BgpUpdateMessage, ParserError> {
+    parse_bgp_update_message_with_context(input, add_path, asn_len, &mut ParseContext::new())
+}
+
+/// Parse a BGP update message with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_bgp_update_message_with_context(
     mut input: Bytes,
     add_path: bool,
     asn_len: &AsnLength,
+    context: &mut ParseContext,
 ) -> Result<BgpUpdateMessage, ParserError> {
     // NOTE: AFI for routes outside attributes are IPv4 ONLY.
     let afi = Afi::Ipv4;
@@ -287,18 +452,26 @@ pub fn parse_bgp_update_message(
     let withdrawn_bytes_length = input.read_u16()? as usize;
     input.has_n_remaining(withdrawn_bytes_length)?;
     let withdrawn_bytes = input.split_to(withdrawn_bytes_length);
-    let withdrawn_prefixes = read_nlri(withdrawn_bytes, &afi, add_path)?;
+    let withdrawn_prefixes = read_nlri(withdrawn_bytes, &afi, add_path, context)?;
 
     // parse attributes
     let attribute_length = input.read_u16()? as usize;
 
     input.has_n_remaining(attribute_length)?;
     let attr_data_slice = input.split_to(attribute_length);
-    let attributes = parse_attributes(attr_data_slice, asn_len, add_path, None, None, None)?;
+    let attributes = parse_attributes_with_context(
+        attr_data_slice,
+        asn_len,
+        add_path,
+        None,
+        None,
+        None,
+        context,
+    )?;
 
     // parse announced prefixes nlri.
     // the remaining bytes are announced prefixes.
-    let announced_prefixes = read_nlri(input, &afi, add_path)?;
+    let announced_prefixes = read_nlri(input, &afi, add_path, context)?;
 
     Ok(BgpUpdateMessage {
         withdrawn_prefixes,
@@ -607,7 +780,7 @@ mod tests {
             version: 4,
             asn: Asn::new_16bit(1),
             hold_time: 180,
-            sender_ip: Ipv4Addr::new(192, 0, 2, 1),
+            sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
             extended_length: false,
             opt_params: vec![],
         };
@@ -624,6 +797,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_encode_bgp_open_message_capabilities() {
+        let capabilities = vec![
+            CapabilityValue::MultiprotocolExtensions {
+                afi: Afi::Ipv6,
+                safi: Safi::Unicast,
+            },
+            CapabilityValue::RouteRefresh,
+            CapabilityValue::FourOctetAs(Asn::new_32bit(400000)),
+            CapabilityValue::AddPath(vec![(Afi::Ipv4, Safi::Unicast, AddPathMode::SendReceive)]),
+            CapabilityValue::BgpRole(BgpRole::Customer),
+        ];
+        let msg = BgpOpenMessage {
+            version: 4,
+            asn: Asn::new_32bit(400000),
+            hold_time: 90,
+            sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
+            extended_length: false,
+            opt_params: capabilities
+                .iter()
+                .map(|value| OptParam::from(Capability::from(value)))
+                .collect(),
+        };
+        let bytes = msg.encode();
+        // 4-byte ASN encoded as AS_TRANS
+        assert_eq!(&bytes[1..3], &[0x5b, 0xa0]);
+        // optional parameters length
+        assert_eq!(bytes[9] as usize, bytes.len() - 10);
+
+        let parsed = parse_bgp_open_message(&mut bytes.clone()).unwrap();
+        assert_eq!(parsed.asn, Asn::TRANSITION);
+        assert_eq!(parsed.opt_params, msg.opt_params);
+        let parsed_capabilities: Vec<CapabilityValue> = parsed
+            .opt_params
+            .iter()
+            .map(|param| match &param.param_value {
+                ParamValue::Capability(cap) => CapabilityValue::try_from(cap).unwrap(),
+                ParamValue::Raw(_) => panic!("expected capability"),
+            })
+            .collect();
+        assert_eq!(parsed_capabilities, capabilities);
+
+        // extended length format
+        let msg = BgpOpenMessage {
+            extended_length: true,
+            ..msg
+        };
+        let bytes = msg.encode();
+        assert_eq!(&bytes[9..11], &[0xff, 0xff]);
+        let parsed = parse_bgp_open_message(&mut bytes.clone()).unwrap();
+        assert!(parsed.extended_length);
+        assert_eq!(parsed.opt_params, msg.opt_params);
+
+        let unknown = Capability {
+            ty: BgpCapabilityType::FQDN_CAPABILITY,
+            value: vec![],
+        };
+        assert!(CapabilityValue::try_from(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_encode_bgp_session_messages() {
+        let messages = vec![
+            BgpMessage::Open(BgpOpenMessage {
+                version: 4,
+                asn: Asn::new_16bit(64496),
+                hold_time: 180,
+                sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
+                extended_length: false,
+                opt_params: vec![OptParam::from(Capability::from(
+                    CapabilityValue::ExtendedMessage,
+                ))],
+            }),
+            BgpMessage::KeepAlive,
+            BgpMessage::Notification(BgpNotificationMessage {
+                error: BgpError::MessageHeaderError(MessageHeaderError::BAD_MESSAGE_LENGTH),
+                data: vec![0x00, 0x13],
+            }),
+        ];
+        for msg in messages {
+            let mut bytes = msg.encode(false, AsnLength::Bits32);
+            let parsed = parse_bgp_message(&mut bytes, false, &AsnLength::Bits32).unwrap();
+            assert_eq!(parsed, msg);
+        }
+        assert_eq!(
+            BgpMessage::KeepAlive.encode(false, AsnLength::Bits16).len(),
+            19
+        );
+    }
+
     #[test]
     fn test_encode_bgp_notification_message() {
         let bgp_message = BgpMessage::Notification(BgpNotificationMessage {
@@ -18,6 +18,8 @@ pub enum ParserBmpError {
     UnknownTlvValue,
     CorruptedBmpMessage,
     TruncatedBmpMessage,
+    /// the [record limits](crate::parser::limits) were exceeded while parsing the message
+    LimitExceeded,
 }
 
 impl Display for ParserBmpError {
@@ -41,6 +43,9 @@ impl Display for ParserBmpError {
             ParserBmpError::UnknownTlvValue => {
                 write!(f, "Unknown TLV value")
             }
+            ParserBmpError::LimitExceeded => {
+                write!(f, "Parsing limits exceeded")
+            }
         }
     }
 }
@@ -55,8 +60,11 @@ impl From<std::io::Error> for ParserBmpError {
 }
 
 impl From<ParserError> for ParserBmpError {
-    fn from(_: ParserError) -> Self {
-        ParserBmpError::CorruptedBmpMessage
+    fn from(e: ParserError) -> Self {
+        match e {
+            ParserError::LimitExceeded(_) => ParserBmpError::LimitExceeded,
+            _ => ParserBmpError::CorruptedBmpMessage,
+        }
     }
 }
 
@@ -0,0 +1,400 @@
+/*!
+OpenBMP Kafka consumer: read BMP messages published by OpenBMP collectors, e.g. the RouteViews
+BMP stream, from Kafka topics.
+
+Each Kafka record holds an [OpenBmpHeader] followed by BMP data of one router. The BMP data of a
+record does not always hold exactly one BMP message: collectors may pack several messages into one
+record, or split a message across consecutive records of the same router. [OpenBmpDecoder]
+buffers the BMP data of each router and returns the messages once they are complete, keeping a
+[BmpSession] per router to convert them into elems. [OpenBmpConsumer] feeds it from an `rdkafka`
+consumer.
+
+```no_run
+use bgpkit_parser::bmp::kafka::OpenBmpConsumer;
+
+// topics starting with `^` are regular expressions
+let consumer = OpenBmpConsumer::new(
+    "stream.routeviews.org:9092",
+    "bgpkit-parser-example",
+    &[r"^routeviews\.amsix\..*\.bmp_raw"],
+)
+.unwrap();
+for elem in consumer {
+    println!("{}", elem);
+}
+```
+*/
+use crate::models::BgpElem;
+use crate::parser::bmp::error::ParserBmpError;
+use crate::parser::bmp::messages::BmpMessage;
+use crate::parser::bmp::openbmp::{parse_openbmp_header, OpenBmpHeader};
+use crate::parser::bmp::parse_bmp_msg;
+use crate::parser::bmp::station::{bmp_message_length, BmpSession};
+use bytes::{Buf, Bytes, BytesMut};
+use log::warn;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::Message;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// BMP data of a router not forming a complete message yet, and the state of its session.
+struct RouterState {
+    buffer: BytesMut,
+    session: BmpSession,
+}
+
+/// Decoder of the values of OpenBMP Kafka records, see [kafka](crate::parser::bmp::kafka).
+///
+/// Routers are identified by the admin ID of their collector and their IP address.
+#[derive(Default)]
+pub struct OpenBmpDecoder {
+    routers: HashMap<(String, IpAddr), RouterState>,
+    synthesize_withdrawals: bool,
+}
+
+impl OpenBmpDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Synthesize withdrawals on peer down notifications, see
+    /// [BmpSession::with_withdrawal_synthesis].
+    pub fn with_withdrawal_synthesis(mut self) -> Self {
+        self.synthesize_withdrawals = true;
+        self
+    }
+
+    /// State of the session of a router, once a message of it was decoded.
+    pub fn session(&self, admin_id: &str, router_ip: IpAddr) -> Option<&BmpSession> {
+        self.routers
+            .get(&(admin_id.to_string(), router_ip))
+            .map(|r| &r.session)
+    }
+
+    /// Decode the value of a Kafka record, returning its OpenBMP header and the BMP messages it
+    /// completes.
+    ///
+    /// Messages that fail to parse are logged and skipped. An invalid BMP message length drops the
+    /// buffered data of the router, as its stream cannot be re-synchronized otherwise. The state
+    /// of the session is not updated, see [OpenBmpDecoder::decode_elems].
+    pub fn decode(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<(OpenBmpHeader, Vec<BmpMessage>), ParserBmpError> {
+        let (header, router) = self.buffer_payload(payload)?;
+        let messages = split_messages(router, &header)?;
+        Ok((header, messages))
+    }
+
+    /// Decode the value of a Kafka record, returning the elems of the BMP messages it completes
+    /// and updating the session of the router.
+    ///
+    /// Records of OpenBMP objects other than BMP messages, e.g. collector heartbeats, have no
+    /// elems.
+    pub fn decode_elems(&mut self, payload: &[u8]) -> Result<Vec<BgpElem>, ParserBmpError> {
+        let (header, router) = match self.buffer_payload(payload) {
+            Ok(decoded) => decoded,
+            Err(ParserBmpError::UnsupportedOpenBmpMessage) => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut elems = vec![];
+        for msg in split_messages(router, &header)? {
+            elems.extend(router.session.process_message(msg));
+        }
+        Ok(elems)
+    }
+
+    /// Parse the OpenBMP header of a record and append its BMP data to the buffer of the router.
+    fn buffer_payload(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<(OpenBmpHeader, &mut RouterState), ParserBmpError> {
+        let mut data = Bytes::copy_from_slice(payload);
+        let header = parse_openbmp_header(&mut data)?;
+        if data.remaining() < header.msg_len as usize {
+            return Err(ParserBmpError::TruncatedBmpMessage);
+        }
+        let synthesize_withdrawals = self.synthesize_withdrawals;
+        let router = self
+            .routers
+            .entry((header.admin_id.clone(), header.router_ip))
+            .or_insert_with(|| {
+                let session = BmpSession::new(SocketAddr::new(header.router_ip, 0));
+                RouterState {
+                    buffer: BytesMut::new(),
+                    session: match synthesize_withdrawals {
+                        true => session.with_withdrawal_synthesis(),
+                        false => session,
+                    },
+                }
+            });
+        router
+            .buffer
+            .extend_from_slice(&data[..header.msg_len as usize]);
+        Ok((header, router))
+    }
+}
+
+/// Take the complete messages out of the buffer of a router.
+fn split_messages(
+    router: &mut RouterState,
+    header: &OpenBmpHeader,
+) -> Result<Vec<BmpMessage>, ParserBmpError> {
+    let mut messages = vec![];
+    while router.buffer.len() >= 6 {
+        let mut common_header = [0u8; 6];
+        common_header.copy_from_slice(&router.buffer[..6]);
+        let msg_len = match bmp_message_length(&common_header) {
+            Ok(msg_len) if common_header[0] == 3 => msg_len,
+            _ => {
+                warn!(
+                    "dropping {} bytes of BMP data from {} with an invalid common header",
+                    router.buffer.len(),
+                    header.router_ip
+                );
+                router.buffer.clear();
+                return Err(ParserBmpError::CorruptedBmpMessage);
+            }
+        };
+        if router.buffer.len() < msg_len {
+            break;
+        }
+        let mut data = router.buffer.split_to(msg_len).freeze();
+        match parse_bmp_msg(&mut data) {
+            Ok(msg) => messages.push(msg),
+            Err(e) => warn!(
+                "failed to parse BMP message from {}: {}",
+                header.router_ip, e
+            ),
+        }
+    }
+    Ok(messages)
+}
+
+/// Consumer of OpenBMP Kafka topics, see [kafka](crate::parser::bmp::kafka).
+///
+/// Iterating over the consumer polls Kafka and returns the elems of all routers, logging Kafka
+/// and decoding errors; the iterator never ends.
+pub struct OpenBmpConsumer {
+    consumer: BaseConsumer,
+    decoder: OpenBmpDecoder,
+    poll_timeout: Duration,
+    elems: VecDeque<BgpElem>,
+}
+
+impl OpenBmpConsumer {
+    /// Subscribe to `topics` on `brokers` as part of the consumer group `group_id`, starting
+    /// from the earliest records for a new group.
+    ///
+    /// Topics starting with `^` are regular expressions matching topic names.
+    pub fn new(brokers: &str, group_id: &str, topics: &[&str]) -> KafkaResult<Self> {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id)
+            .set("auto.offset.reset", "earliest")
+            .create()?;
+        consumer.subscribe(topics)?;
+        Ok(OpenBmpConsumer::from_consumer(consumer))
+    }
+
+    /// Read from a consumer configured and subscribed by the caller, e.g. to set up
+    /// authentication or to assign partitions manually.
+    pub fn from_consumer(consumer: BaseConsumer) -> Self {
+        OpenBmpConsumer {
+            consumer,
+            decoder: OpenBmpDecoder::new(),
+            poll_timeout: Duration::from_secs(1),
+            elems: VecDeque::new(),
+        }
+    }
+
+    /// Synthesize withdrawals on peer down notifications, see
+    /// [BmpSession::with_withdrawal_synthesis].
+    pub fn with_withdrawal_synthesis(mut self) -> Self {
+        self.decoder.synthesize_withdrawals = true;
+        self
+    }
+
+    /// Set how long a poll waits for a record, 1 second by default.
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    /// The decoder, holding the state of the session of each router.
+    pub fn decoder(&self) -> &OpenBmpDecoder {
+        &self.decoder
+    }
+
+    pub fn consumer(&self) -> &BaseConsumer {
+        &self.consumer
+    }
+
+    /// Wait for the next record, returning the BMP messages it completes, or an empty vector if
+    /// no record arrived before the poll timeout or the record could not be decoded.
+    ///
+    /// The state of the sessions is not updated, see [OpenBmpDecoder::decode].
+    pub fn poll_messages(&mut self) -> KafkaResult<Vec<BmpMessage>> {
+        let decoded = match self.consumer.poll(self.poll_timeout) {
+            None => return Ok(vec![]),
+            Some(record) => match record?.payload() {
+                Some(payload) => self.decoder.decode(payload),
+                None => return Ok(vec![]),
+            },
+        };
+        match decoded {
+            Ok((_header, messages)) => Ok(messages),
+            Err(ParserBmpError::UnsupportedOpenBmpMessage) => Ok(vec![]),
+            Err(e) => {
+                warn!("failed to decode OpenBMP record: {}", e);
+                Ok(vec![])
+            }
+        }
+    }
+
+    /// Wait for the next record, returning the elems of the BMP messages it completes, or an
+    /// empty vector if no record arrived before the poll timeout or the record could not be
+    /// decoded.
+    pub fn poll_elems(&mut self) -> KafkaResult<Vec<BgpElem>> {
+        let decoded = match self.consumer.poll(self.poll_timeout) {
+            None => return Ok(vec![]),
+            Some(record) => match record?.payload() {
+                Some(payload) => self.decoder.decode_elems(payload),
+                None => return Ok(vec![]),
+            },
+        };
+        match decoded {
+            Ok(elems) => Ok(elems),
+            Err(e) => {
+                warn!("failed to decode OpenBMP record: {}", e);
+                Ok(vec![])
+            }
+        }
+    }
+}
+
+impl Iterator for OpenBmpConsumer {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        loop {
+            if let Some(elem) = self.elems.pop_front() {
+                return Some(elem);
+            }
+            match self.poll_elems() {
+                Ok(elems) => self.elems.extend(elems),
+                Err(e) => warn!("failed to poll OpenBMP records: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::bmp::messages::BmpMsgType;
+    use crate::test_vectors;
+    use std::net::Ipv4Addr;
+
+    const ROUTER_IP: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 254);
+
+    /// Kafka record value with an OpenBMP header for the BMP data of [ROUTER_IP].
+    fn openbmp_record(object_type: u8, bmp_data: &[u8]) -> Vec<u8> {
+        let admin_id = b"collector";
+        let header_len = 4 + 2 + 2 + 4 + 2 + 8 + 16 + 2 + admin_id.len() + 16 + 16 + 2 + 4;
+        let mut bytes = b"OBMP".to_vec();
+        bytes.extend([1, 7]);
+        bytes.extend((header_len as u16).to_be_bytes());
+        bytes.extend((bmp_data.len() as u32).to_be_bytes());
+        // router message from an IPv4 router
+        bytes.extend([0x80, object_type]);
+        bytes.extend(1700000000u32.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        // collector hash
+        bytes.extend([0; 16]);
+        bytes.extend((admin_id.len() as u16).to_be_bytes());
+        bytes.extend(admin_id);
+        // router hash
+        bytes.extend([0; 16]);
+        bytes.extend(ROUTER_IP.octets());
+        bytes.extend([0; 12]);
+        // empty router group and one row
+        bytes.extend([0, 0, 0, 0, 0, 1]);
+        bytes.extend(bmp_data);
+        bytes
+    }
+
+    /// Initiation message with a sysName TLV, followed by the route monitoring test vectors.
+    fn bmp_stream() -> Vec<u8> {
+        let mut data = vec![3, 0, 0, 0, 13, 4, 0, 2, 0, 3, b'r', b't', b'r'];
+        for vector in test_vectors::bmp_route_monitoring() {
+            data.extend(vector.bytes);
+        }
+        data
+    }
+
+    fn expected_elems() -> Vec<BgpElem> {
+        test_vectors::bmp_route_monitoring()
+            .into_iter()
+            .flat_map(|v| v.expected)
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_messages() {
+        let mut decoder = OpenBmpDecoder::new();
+        // both messages in one record
+        let (header, messages) = decoder.decode(&openbmp_record(12, &bmp_stream())).unwrap();
+        assert_eq!(header.router_ip, IpAddr::V4(ROUTER_IP));
+        let types: Vec<BmpMsgType> = messages.iter().map(|m| m.common_header.msg_type).collect();
+        assert_eq!(
+            types,
+            vec![BmpMsgType::InitiationMessage, BmpMsgType::RouteMonitoring]
+        );
+    }
+
+    #[test]
+    fn test_decode_split_messages() {
+        let stream = bmp_stream();
+        let mut decoder = OpenBmpDecoder::new();
+        let mut elems = vec![];
+        // the initiation message is split across the first two records, the route monitoring
+        // message across the last two
+        for chunk in [&stream[..5], &stream[5..20], &stream[20..]] {
+            elems.extend(decoder.decode_elems(&openbmp_record(12, chunk)).unwrap());
+        }
+        assert_eq!(elems, expected_elems());
+        let session = decoder.session("collector", IpAddr::V4(ROUTER_IP)).unwrap();
+        assert_eq!(session.sys_name.as_deref(), Some("rtr"));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut decoder = OpenBmpDecoder::new();
+        // collector heartbeat
+        assert_eq!(
+            decoder.decode_elems(&openbmp_record(4, &[])).unwrap(),
+            vec![]
+        );
+        // BMP length shorter than the common header
+        assert_eq!(
+            decoder.decode_elems(&openbmp_record(12, &[3, 0, 0, 0, 2, 4])),
+            Err(ParserBmpError::CorruptedBmpMessage)
+        );
+        // the buffer is dropped, and the next messages decode again
+        let elems = decoder
+            .decode_elems(&openbmp_record(12, &bmp_stream()))
+            .unwrap();
+        assert_eq!(elems, expected_elems());
+        // header announcing more BMP data than the record holds
+        let mut record = openbmp_record(12, &bmp_stream());
+        record.truncate(record.len() - 1);
+        assert_eq!(
+            decoder.decode(&record).unwrap_err(),
+            ParserBmpError::TruncatedBmpMessage
+        );
+    }
+}
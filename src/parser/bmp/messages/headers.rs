@@ -2,7 +2,7 @@ use crate::models::*;
 use crate::parser::bmp::error::ParserBmpError;
 use crate::parser::ReadUtils;
 use bitflags::bitflags;
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
@@ -126,7 +126,7 @@ impl Default for BmpPerPeerHeader {
             peer_distinguisher: 0,
             peer_ip: IpAddr::V4(Ipv4Addr::from(0)),
             peer_asn: Default::default(),
-            peer_bgp_id: Ipv4Addr::from(0),
+            peer_bgp_id: BgpIdentifier::new(0),
             timestamp: 0.0,
         }
     }
@@ -182,6 +182,43 @@ impl BmpPerPeerHeader {
             PerPeerFlags::LocalRibPeerFlags(_) => AsnLength::Bits32,
         }
     }
+
+    /// Returns true if the routes are from the Adj-RIB-Out of the peer (RFC 8671).
+    ///
+    /// Always false for Loc-RIB peers.
+    pub fn is_adj_rib_out(&self) -> bool {
+        match self.peer_flags {
+            PerPeerFlags::PeerFlags(f) => f.is_adj_rib_out(),
+            PerPeerFlags::LocalRibPeerFlags(_) => false,
+        }
+    }
+
+    /// Returns true if the routes are post-policy.
+    ///
+    /// Loc-RIB routes (RFC 9069) are always considered post-policy.
+    pub fn is_post_policy(&self) -> bool {
+        match self.peer_flags {
+            PerPeerFlags::PeerFlags(f) => f.is_post_policy(),
+            PerPeerFlags::LocalRibPeerFlags(_) => true,
+        }
+    }
+
+    /// The RIB the routes of the message are collected from, see [BgpElem::rib_side].
+    pub fn rib_side(&self) -> RibSide {
+        match self.peer_flags {
+            PerPeerFlags::PeerFlags(f) if f.is_adj_rib_out() => RibSide::AdjRibOut,
+            PerPeerFlags::PeerFlags(_) => RibSide::AdjRibIn,
+            PerPeerFlags::LocalRibPeerFlags(_) => RibSide::LocRib,
+        }
+    }
+
+    /// The policy stage of the routes of the message, see [BgpElem::policy].
+    pub fn policy(&self) -> RibPolicy {
+        match self.is_post_policy() {
+            true => RibPolicy::Post,
+            false => RibPolicy::Pre,
+        }
+    }
 }
 
 /// Peer type
@@ -317,7 +354,7 @@ pub fn parse_per_peer_header(data: &mut Bytes) -> Result<BmpPerPeerHeader, Parse
             let peer_distinguisher = data.read_u64()?;
             let peer_ip = match peer_flags.address_family() {
                 Afi::Ipv4 => {
-                    data.advance(12);
+                    data.skip_n_bytes(12)?;
                     IpAddr::V4(data.read_ipv4_address()?)
                 }
                 Afi::Ipv6 => IpAddr::V6(data.read_ipv6_address()?),
@@ -325,13 +362,13 @@ pub fn parse_per_peer_header(data: &mut Bytes) -> Result<BmpPerPeerHeader, Parse
 
             let peer_asn = match peer_flags.asn_length() {
                 AsnLength::Bits16 => {
-                    data.advance(2);
+                    data.skip_n_bytes(2)?;
                     Asn::new_16bit(data.read_u16()?)
                 }
                 AsnLength::Bits32 => Asn::new_32bit(data.read_u32()?),
             };
 
-            let peer_bgp_id = data.read_ipv4_address()?;
+            let peer_bgp_id = BgpIdentifier::from(data.read_u32()?);
 
             let t_sec = data.read_u32()?;
             let t_usec = data.read_u32()?;
@@ -353,11 +390,11 @@ pub fn parse_per_peer_header(data: &mut Bytes) -> Result<BmpPerPeerHeader, Parse
             let peer_distinguisher = data.read_u64()?;
             // zero-filled peer_ip address field
             let peer_ip = IpAddr::V4(Ipv4Addr::from(0));
-            data.advance(16);
+            data.skip_n_bytes(16)?;
 
             let peer_asn = Asn::new_32bit(data.read_u32()?);
 
-            let peer_bgp_id = data.read_ipv4_address()?;
+            let peer_bgp_id = BgpIdentifier::from(data.read_u32()?);
 
             let t_sec = data.read_u32()?;
             let t_usec = data.read_u32()?;
@@ -399,13 +436,28 @@ mod tests {
             peer_distinguisher: 0,
             peer_ip: IpAddr::V4(Ipv4Addr::from(0)),
             peer_asn: Default::default(),
-            peer_bgp_id: Ipv4Addr::from(0),
+            peer_bgp_id: BgpIdentifier::new(0),
             timestamp: 0.0,
         };
         assert_eq!(per_peer_header.afi(), Afi::Ipv4);
 
         // check ASN length
         assert_eq!(per_peer_header.asn_length(), AsnLength::Bits32);
+
+        // Loc-RIB routes are post-policy and not Adj-RIB-Out
+        assert!(per_peer_header.is_post_policy());
+        assert!(!per_peer_header.is_adj_rib_out());
+        assert_eq!(per_peer_header.rib_side(), RibSide::LocRib);
+        assert_eq!(per_peer_header.policy(), RibPolicy::Post);
+
+        let per_peer_header = BmpPerPeerHeader {
+            peer_flags: PerPeerFlags::PeerFlags(PeerFlags::IS_ADJ_RIB_OUT),
+            ..per_peer_header
+        };
+        assert!(!per_peer_header.is_post_policy());
+        assert!(per_peer_header.is_adj_rib_out());
+        assert_eq!(per_peer_header.rib_side(), RibSide::AdjRibOut);
+        assert_eq!(per_peer_header.policy(), RibPolicy::Pre);
     }
 
     #[test]
@@ -1,5 +1,5 @@
 use crate::parser::bmp::error::ParserBmpError;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils, TlvReader};
 use bytes::{Buf, Bytes};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::convert::TryFrom;
@@ -36,16 +36,22 @@ pub enum InitiationTlvType {
 ///
 /// <https://www.rfc-editor.org/rfc/rfc7854#section-4.3>
 pub fn parse_initiation_message(data: &mut Bytes) -> Result<InitiationMessage, ParserBmpError> {
+    parse_initiation_message_with_context(data, &mut ParseContext::new())
+}
+
+/// Parse BMP initiation message within the limits of the given [ParseContext].
+pub(crate) fn parse_initiation_message_with_context(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<InitiationMessage, ParserBmpError> {
     let mut tlvs = vec![];
 
-    while data.remaining() > 4 {
-        let info_type: InitiationTlvType = InitiationTlvType::try_from(data.get_u16())?;
-        let info_len = data.get_u16();
-        if data.remaining() < info_len as usize {
-            // not enough bytes to read
-            break;
-        }
-        let info = data.read_n_bytes_to_string(info_len as usize)?;
+    // stop at a truncated TLV
+    for mut tlv in TlvReader::new(data.split_to(data.remaining())).lenient() {
+        context.record_step()?;
+        let info_type: InitiationTlvType = InitiationTlvType::try_from(tlv.tlv_type)?;
+        let info_len = tlv.value.len() as u16;
+        let info = tlv.value.read_n_bytes_to_string(info_len as usize)?;
         tlvs.push(InitiationTlv {
             info_type,
             info_len,
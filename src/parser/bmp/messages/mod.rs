@@ -10,6 +10,7 @@ pub use route_mirroring::*;
 pub use route_monitoring::*;
 pub use stats_report::*;
 pub use termination_message::*;
+pub use tlv::*;
 
 pub(crate) mod headers;
 pub(crate) mod initiation_message;
@@ -19,6 +20,7 @@ pub(crate) mod route_mirroring;
 pub(crate) mod route_monitoring;
 pub(crate) mod stats_report;
 pub(crate) mod termination_message;
+pub(crate) mod tlv;
 
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -1,7 +1,9 @@
+use crate::models::BgpError;
 use crate::parser::bmp::error::ParserBmpError;
 use crate::parser::ReadUtils;
 use bytes::{Buf, Bytes};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -23,6 +25,85 @@ pub enum PeerDownReason {
     LocalSystemClosedTlvDataFollows = 6,
 }
 
+impl PeerDownReason {
+    /// Whether the monitored router closed the session (reasons 1, 2 and 6).
+    pub const fn is_local_close(&self) -> bool {
+        matches!(
+            self,
+            PeerDownReason::LocalSystemClosedNotificationPduFollows
+                | PeerDownReason::LocalSystemClosedFsmEvenFollows
+                | PeerDownReason::LocalSystemClosedTlvDataFollows
+        )
+    }
+
+    /// Whether the remote peer closed the session (reasons 3 and 4).
+    pub const fn is_remote_close(&self) -> bool {
+        matches!(
+            self,
+            PeerDownReason::RemoteSystemClosedNotificationPduFollows
+                | PeerDownReason::RemoteSystemsClosedNoData
+        )
+    }
+
+    /// Whether the BGP session went down.
+    ///
+    /// A de-configured peer (reason 5) only means that the monitoring station no longer receives
+    /// updates for the peer, the session itself may still be up.
+    pub const fn is_session_down(&self) -> bool {
+        self.is_local_close() || self.is_remote_close()
+    }
+}
+
+impl PeerDownNotification {
+    /// Get the BGP error carried by the NOTIFICATION PDU that follows reason code 1 or 3.
+    ///
+    /// Returns `None` for other reasons or if the data is not a BGP NOTIFICATION message.
+    pub fn bgp_error(&self) -> Option<BgpError> {
+        match self.reason {
+            PeerDownReason::LocalSystemClosedNotificationPduFollows
+            | PeerDownReason::RemoteSystemClosedNotificationPduFollows => {}
+            _ => return None,
+        }
+        // 16-byte marker, 2-byte length, 1-byte type, followed by error code and subcode
+        match self.data.as_deref() {
+            Some(data) if data.len() >= 21 && data[18] == 3 => {
+                Some(BgpError::new(data[19], data[20]))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for PeerDownReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let desc = match self {
+            PeerDownReason::Reserved => "reserved",
+            PeerDownReason::LocalSystemClosedNotificationPduFollows => {
+                "local system closed with NOTIFICATION"
+            }
+            PeerDownReason::LocalSystemClosedFsmEvenFollows => "local system closed with FSM event",
+            PeerDownReason::RemoteSystemClosedNotificationPduFollows => {
+                "remote system closed with NOTIFICATION"
+            }
+            PeerDownReason::RemoteSystemsClosedNoData => "remote system closed without data",
+            PeerDownReason::PeerDeConfigured => "peer de-configured",
+            PeerDownReason::LocalSystemClosedTlvDataFollows => "local system closed with TLV data",
+        };
+        write!(f, "{}", desc)
+    }
+}
+
+/// Formats the reason, followed by the decoded BGP error if a NOTIFICATION PDU is included, e.g.
+/// `remote system closed with NOTIFICATION: Cease: Administrative Shutdown (6/2)`.
+impl Display for PeerDownNotification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.bgp_error() {
+            Some(error) => write!(f, "{}: {}", self.reason, error),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
 pub fn parse_peer_down_notification(
     data: &mut Bytes,
 ) -> Result<PeerDownNotification, ParserBmpError> {
@@ -187,4 +268,39 @@ mod tests {
         let result = parse_peer_down_notification(&mut data);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_peer_down_reason_taxonomy() {
+        assert!(PeerDownReason::LocalSystemClosedFsmEvenFollows.is_local_close());
+        assert!(PeerDownReason::LocalSystemClosedTlvDataFollows.is_session_down());
+        assert!(PeerDownReason::RemoteSystemsClosedNoData.is_remote_close());
+        assert!(!PeerDownReason::RemoteSystemsClosedNoData.is_local_close());
+        assert!(!PeerDownReason::PeerDeConfigured.is_session_down());
+        assert!(!PeerDownReason::Reserved.is_session_down());
+    }
+
+    #[test]
+    fn test_peer_down_bgp_error() {
+        let mut pdu = vec![0xFF; 16];
+        pdu.extend([0, 21, 3, 6, 2]);
+        let notification = PeerDownNotification {
+            reason: PeerDownReason::RemoteSystemClosedNotificationPduFollows,
+            data: Some(pdu),
+        };
+        assert_eq!(notification.bgp_error(), Some(BgpError::new(6, 2)));
+        assert_eq!(
+            notification.to_string(),
+            "remote system closed with NOTIFICATION: Cease: Administrative Shutdown (6/2)"
+        );
+
+        let notification = PeerDownNotification {
+            reason: PeerDownReason::RemoteSystemsClosedNoData,
+            data: None,
+        };
+        assert_eq!(notification.bgp_error(), None);
+        assert_eq!(
+            notification.to_string(),
+            "remote system closed without data"
+        );
+    }
 }
@@ -1,7 +1,7 @@
-use crate::bgp::parse_bgp_message;
 use crate::models::*;
+use crate::parser::bgp::messages::parse_bgp_message_with_context;
 use crate::parser::bmp::error::ParserBmpError;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils, TlvReader};
 use bytes::{Buf, Bytes};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::net::IpAddr;
@@ -43,10 +43,20 @@ pub fn parse_peer_up_notification(
     data: &mut Bytes,
     afi: &Afi,
     asn_len: &AsnLength,
+) -> Result<PeerUpNotification, ParserBmpError> {
+    parse_peer_up_notification_with_context(data, afi, asn_len, &mut ParseContext::new())
+}
+
+/// Parse a BMP peer up notification within the limits of the given [ParseContext].
+pub(crate) fn parse_peer_up_notification_with_context(
+    data: &mut Bytes,
+    afi: &Afi,
+    asn_len: &AsnLength,
+    context: &mut ParseContext,
 ) -> Result<PeerUpNotification, ParserBmpError> {
     let local_addr: IpAddr = match afi {
         Afi::Ipv4 => {
-            data.advance(12);
+            data.skip_n_bytes(12)?;
             let ip = data.read_ipv4_address()?;
             ip.into()
         }
@@ -56,14 +66,17 @@ pub fn parse_peer_up_notification(
     let local_port = data.read_u16()?;
     let remote_port = data.read_u16()?;
 
-    let sent_open = parse_bgp_message(data, false, asn_len)?;
-    let received_open = parse_bgp_message(data, false, asn_len)?;
+    let sent_open = parse_bgp_message_with_context(data, false, asn_len, context)?;
+    let received_open = parse_bgp_message_with_context(data, false, asn_len, context)?;
     // let received_open = parse_bgp_open_message(data)?;
     let mut tlvs = vec![];
-    while data.remaining() >= 4 {
-        let info_type = PeerUpTlvType::try_from(data.read_u16()?)?;
-        let info_len = data.read_u16()?;
-        let info_value = data.read_n_bytes_to_string(info_len as usize)?;
+    // trailing bytes too short for a TLV header are ignored
+    for tlv in TlvReader::new(data.split_to(data.remaining())).allow_partial_header() {
+        context.record_step()?;
+        let mut tlv = tlv?;
+        let info_type = PeerUpTlvType::try_from(tlv.tlv_type)?;
+        let info_len = tlv.value.len() as u16;
+        let info_value = tlv.value.read_n_bytes_to_string(info_len as usize)?;
         tlvs.push(PeerUpNotificationTlv {
             info_type,
             info_len,
@@ -84,7 +97,7 @@ pub fn parse_peer_up_notification(
 mod tests {
     use super::*;
     use bytes::BytesMut;
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::net::IpAddr;
 
     #[test]
     fn test_parse_peer_up_notification() {
@@ -102,7 +115,7 @@ mod tests {
             version: 0,
             asn: Default::default(),
             hold_time: 0,
-            sender_ip: Ipv4Addr::new(0, 0, 0, 0),
+            sender_ip: BgpIdentifier::new(0),
             extended_length: false,
             opt_params: vec![],
         });
@@ -140,4 +153,39 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_peer_up_notification_trailing_partial_tlv() {
+        let mut data = BytesMut::new();
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[10, 1, 1, 1]);
+        data.extend_from_slice(&[0x1F, 0x40, 0x23, 0x28]);
+        let bgp_open_message = crate::models::BgpMessage::Open(BgpOpenMessage {
+            version: 4,
+            asn: Default::default(),
+            hold_time: 0,
+            sender_ip: BgpIdentifier::new(0),
+            extended_length: false,
+            opt_params: vec![],
+        });
+        let bgp_open_message_bytes = bgp_open_message.encode(false, AsnLength::Bits32);
+        data.extend_from_slice(&bgp_open_message_bytes);
+        data.extend_from_slice(&bgp_open_message_bytes);
+        data.extend_from_slice(&[0x00, 0x01, 0x00, 0x01, b'r']);
+        let prefix = data.clone().freeze();
+
+        // too short for a TLV header
+        data.extend_from_slice(&[0x00, 0x02]);
+        let peer_up =
+            parse_peer_up_notification(&mut data.freeze(), &Afi::Ipv4, &AsnLength::Bits32).unwrap();
+        assert_eq!(peer_up.tlvs.len(), 1);
+        assert_eq!(peer_up.tlvs[0].info_value, "r");
+
+        // a truncated value is an error
+        let mut data = BytesMut::from(prefix.as_ref());
+        data.extend_from_slice(&[0x00, 0x02, 0x00, 0x05, b'a']);
+        assert!(
+            parse_peer_up_notification(&mut data.freeze(), &Afi::Ipv4, &AsnLength::Bits32).is_err()
+        );
+    }
 }
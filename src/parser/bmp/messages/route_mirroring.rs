@@ -1,7 +1,7 @@
-use crate::bgp::parse_bgp_message;
 use crate::models::*;
+use crate::parser::bgp::messages::parse_bgp_message_with_context;
 use crate::parser::bmp::error::ParserBmpError;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils, TlvReader};
 use bytes::{Buf, Bytes};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::convert::TryFrom;
@@ -37,23 +37,33 @@ pub enum RouteMirroringInfo {
 pub fn parse_route_mirroring(
     data: &mut Bytes,
     asn_len: &AsnLength,
+) -> Result<RouteMirroring, ParserBmpError> {
+    parse_route_mirroring_with_context(data, asn_len, &mut ParseContext::new())
+}
+
+/// Parse a BMP route mirroring message within the limits of the given [ParseContext].
+pub(crate) fn parse_route_mirroring_with_context(
+    data: &mut Bytes,
+    asn_len: &AsnLength,
+    context: &mut ParseContext,
 ) -> Result<RouteMirroring, ParserBmpError> {
     let mut tlvs = vec![];
-    while data.remaining() > 4 {
-        match data.read_u16()? {
+    // trailing bytes too short for a TLV header are ignored
+    for tlv in TlvReader::new(data.split_to(data.remaining())).allow_partial_header() {
+        context.record_step()?;
+        let mut tlv = tlv?;
+        let info_len = tlv.value.len() as u16;
+        match tlv.tlv_type {
             0 => {
-                let info_len = data.read_u16()?;
-                data.has_n_remaining(info_len as usize)?;
-                let mut bytes = data.split_to(info_len as usize);
-                let value = parse_bgp_message(&mut bytes, false, asn_len)?;
+                let value =
+                    parse_bgp_message_with_context(&mut tlv.value, false, asn_len, context)?;
                 tlvs.push(RouteMirroringTlv {
                     info_len,
                     value: RouteMirroringValue::BgpMessage(value),
                 });
             }
             1 => {
-                let info_len = data.read_u16()?;
-                let value = RouteMirroringInfo::try_from(data.read_u16()?)?;
+                let value = RouteMirroringInfo::try_from(tlv.value.read_u16()?)?;
                 tlvs.push(RouteMirroringTlv {
                     info_len,
                     value: RouteMirroringValue::Information(value),
@@ -77,7 +87,7 @@ mod tests {
             version: 4,
             asn: Asn::new_32bit(1),
             hold_time: 180,
-            sender_ip: Ipv4Addr::new(192, 0, 2, 1),
+            sender_ip: BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 1)),
             extended_length: false,
             opt_params: vec![],
         });
@@ -120,4 +130,23 @@ mod tests {
             _ => panic!("Expected RouteMirroringValue::Information"),
         }
     }
+
+    #[test]
+    fn route_mirroring_trailing_partial_tlv() {
+        let mut message = BytesMut::new();
+        message.put_u16(1);
+        message.put_u16(2);
+        message.put_u16(1);
+        // too short for a TLV header
+        message.put_slice(&[0, 1, 0]);
+        let result = parse_route_mirroring(&mut message.freeze(), &AsnLength::Bits32).unwrap();
+        assert_eq!(result.tlvs.len(), 1);
+
+        // a truncated value is an error
+        let mut message = BytesMut::new();
+        message.put_u16(0);
+        message.put_u16(19);
+        message.put_slice(&[0xff; 10]);
+        assert!(parse_route_mirroring(&mut message.freeze(), &AsnLength::Bits32).is_err());
+    }
 }
@@ -1,21 +1,40 @@
 use crate::models::*;
-use crate::parser::bgp::messages::parse_bgp_message;
+use crate::parser::bgp::messages::parse_bgp_message_with_context;
 use crate::parser::bmp::error::ParserBmpError;
+use crate::parser::bmp::messages::tlv::{parse_bmp_tlvs_with_context, BmpTlv};
+use crate::parser::ParseContext;
 use bytes::Bytes;
 
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RouteMonitoring {
     pub bgp_message: BgpMessage,
+    /// TLVs following the BGP PDU, e.g. path status or enterprise-specific TLVs
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub tlvs: Vec<BmpTlv>,
 }
 
 pub fn parse_route_monitoring(
     data: &mut Bytes,
     asn_len: &AsnLength,
 ) -> Result<RouteMonitoring, ParserBmpError> {
-    let bgp_update = parse_bgp_message(data, false, asn_len)?;
+    parse_route_monitoring_with_context(data, asn_len, &mut ParseContext::new())
+}
+
+/// Parse a BMP route monitoring message with the buffers and limits of the given [ParseContext].
+pub(crate) fn parse_route_monitoring_with_context(
+    data: &mut Bytes,
+    asn_len: &AsnLength,
+    context: &mut ParseContext,
+) -> Result<RouteMonitoring, ParserBmpError> {
+    let bgp_update = parse_bgp_message_with_context(data, false, asn_len, context)?;
+    let tlvs = parse_bmp_tlvs_with_context(data, context)?;
     Ok(RouteMonitoring {
         bgp_message: bgp_update,
+        tlvs,
     })
 }
 
@@ -45,11 +64,13 @@ mod tests {
 
         let mon_msg = RouteMonitoring {
             bgp_message: BgpMessage::Update(msg),
+            tlvs: vec![],
         };
         assert!(mon_msg.is_end_of_rib());
 
         let mon_msg = RouteMonitoring {
             bgp_message: BgpMessage::KeepAlive,
+            tlvs: vec![],
         };
         assert!(!mon_msg.is_end_of_rib());
     }
@@ -63,10 +84,11 @@ mod tests {
         };
         let mon_msg = RouteMonitoring {
             bgp_message: BgpMessage::Update(msg),
+            tlvs: vec![],
         };
         assert_eq!(
             format!("{:?}", mon_msg),
-            "RouteMonitoring { bgp_message: Update(BgpUpdateMessage { withdrawn_prefixes: [], attributes: Attributes { inner: [] }, announced_prefixes: [] }) }"
+            "RouteMonitoring { bgp_message: Update(BgpUpdateMessage { withdrawn_prefixes: [], attributes: Attributes { inner: [] }, announced_prefixes: [] }), tlvs: [] }"
         );
     }
 }
@@ -1,5 +1,5 @@
 use crate::parser::bmp::error::ParserBmpError;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use bytes::{Buf, Bytes};
 use num_enum::{FromPrimitive, IntoPrimitive};
 
@@ -58,9 +58,18 @@ pub enum StatType {
 }
 
 pub fn parse_stats_report(data: &mut Bytes) -> Result<StatsReport, ParserBmpError> {
+    parse_stats_report_with_context(data, &mut ParseContext::new())
+}
+
+/// Parse a BMP statistics report within the limits of the given [ParseContext].
+pub(crate) fn parse_stats_report_with_context(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<StatsReport, ParserBmpError> {
     let stats_count = data.read_u32()?;
     let mut counters = vec![];
     for _ in 0..stats_count {
+        context.record_step()?;
         let stat_type = StatType::from(data.read_u16()?);
         let stat_len = data.read_u16()?;
         data.has_n_remaining(stat_len as usize)?;
@@ -1,5 +1,5 @@
 use crate::parser::bmp::error::ParserBmpError;
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils, TlvReader};
 use bytes::{Buf, Bytes};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use std::convert::TryFrom;
@@ -48,22 +48,28 @@ pub enum TerminationTlvType {
 }
 
 pub fn parse_termination_message(data: &mut Bytes) -> Result<TerminationMessage, ParserBmpError> {
+    parse_termination_message_with_context(data, &mut ParseContext::new())
+}
+
+/// Parse a BMP termination message within the limits of the given [ParseContext].
+pub(crate) fn parse_termination_message_with_context(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<TerminationMessage, ParserBmpError> {
     let mut tlvs = vec![];
 
-    while data.remaining() > 4 {
-        let info_type: TerminationTlvType = TerminationTlvType::try_from(data.read_u16()?)?;
-        let info_len = data.read_u16()?;
-        if data.remaining() < info_len as usize {
-            // not enough bytes to read
-            break;
-        }
+    // stop at a truncated TLV
+    for mut tlv in TlvReader::new(data.split_to(data.remaining())).lenient() {
+        context.record_step()?;
+        let info_type: TerminationTlvType = TerminationTlvType::try_from(tlv.tlv_type)?;
+        let info_len = tlv.value.len() as u16;
         let info_value = match info_type {
             TerminationTlvType::String => {
-                let info = data.read_n_bytes_to_string(info_len as usize)?;
+                let info = tlv.value.read_n_bytes_to_string(info_len as usize)?;
                 TerminationTlvValue::String(info)
             }
             TerminationTlvType::Reason => {
-                let reason = TerminationReason::try_from(data.read_u16()?)?;
+                let reason = TerminationReason::try_from(tlv.value.read_u16()?)?;
                 TerminationTlvValue::Reason(reason)
             }
         };
@@ -0,0 +1,207 @@
+//! TLVs following the BGP PDU of route monitoring messages.
+//!
+//! BMP version 4 allows TLVs at the end of route monitoring messages, and vendors use them to
+//! export per-path information such as the path status.
+//!
+//! - <https://datatracker.ietf.org/doc/html/draft-ietf-grow-bmp-tlv>
+//! - <https://datatracker.ietf.org/doc/html/draft-ietf-grow-bmp-tlv-ebit>
+//! - <https://datatracker.ietf.org/doc/html/draft-ietf-grow-bmp-path-marking-tlv>
+use crate::parser::bmp::error::ParserBmpError;
+use crate::parser::{ParseContext, ReadUtils, TlvReader};
+use bitflags::bitflags;
+use bytes::{Buf, Bytes};
+
+/// Enterprise bit of the TLV type. If set, the value starts with an IANA Private Enterprise Number.
+const ENTERPRISE_BIT: u16 = 0x8000;
+
+/// TLV type of the path marking TLV, as used by current implementations of the path marking draft.
+pub const PATH_MARKING_TLV_TYPE: u16 = 0;
+
+/// A TLV following the BGP PDU of a route monitoring message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BmpTlv {
+    /// TLV type without the enterprise bit
+    pub tlv_type: u16,
+    /// index of the NLRI within the BGP PDU this TLV applies to, 0 for all NLRI
+    pub index: u16,
+    pub value: BmpTlvValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BmpTlvValue {
+    PathStatus(PathStatusTlv),
+    Enterprise(EnterpriseTlv),
+    Unknown(Vec<u8>),
+}
+
+/// Enterprise-specific TLV, kept as raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnterpriseTlv {
+    /// IANA Private Enterprise Number, e.g. 9 for Cisco or 2636 for Juniper
+    pub enterprise_number: u32,
+    pub value: Vec<u8>,
+}
+
+/// Value of the path marking TLV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathStatusTlv {
+    pub status: PathStatus,
+    pub reason_code: Option<u16>,
+}
+
+bitflags! {
+    /// Path status bits of the path marking TLV.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PathStatus: u32 {
+        const INVALID           = 0x00000001;
+        const BEST              = 0x00000002;
+        const NON_SELECTED      = 0x00000004;
+        const PRIMARY           = 0x00000008;
+        const BACKUP            = 0x00000010;
+        const NON_INSTALLED     = 0x00000020;
+        const BEST_EXTERNAL     = 0x00000040;
+        const ADD_PATH          = 0x00000080;
+        const FILTERED_INBOUND  = 0x00000100;
+        const FILTERED_OUTBOUND = 0x00000200;
+        const INVALID_ROV       = 0x00000400;
+        const STALE             = 0x00000800;
+        const SUPPRESSED        = 0x00001000;
+    }
+}
+
+/// Parse all TLVs remaining in `data`.
+///
+/// TLVs are parsed on a best-effort basis: a TLV with a length exceeding the remaining bytes ends
+/// the parsing, and the TLVs parsed so far are returned. Values too short for their type, such as
+/// enterprise TLVs without a complete enterprise number, are kept as [BmpTlvValue::Unknown].
+pub fn parse_bmp_tlvs(data: &mut Bytes) -> Result<Vec<BmpTlv>, ParserBmpError> {
+    parse_bmp_tlvs_with_context(data, &mut ParseContext::new())
+}
+
+/// Parse all TLVs remaining in `data` within the limits of the given [ParseContext].
+pub(crate) fn parse_bmp_tlvs_with_context(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<Vec<BmpTlv>, ParserBmpError> {
+    let mut tlvs = vec![];
+
+    // the 2-octet index follows the length, which only counts the value
+    for tlv in TlvReader::new(data.split_to(data.remaining()))
+        .with_extra_header(2)
+        .lenient()
+    {
+        context.record_step()?;
+        let mut value = tlv.value;
+        let index = value.read_u16()?;
+        let length = value.remaining();
+
+        let tlv_type = tlv.tlv_type & !ENTERPRISE_BIT;
+        let value = if tlv.tlv_type & ENTERPRISE_BIT != 0 && length >= 4 {
+            let enterprise_number = value.read_u32()?;
+            BmpTlvValue::Enterprise(EnterpriseTlv {
+                enterprise_number,
+                value: value.to_vec(),
+            })
+        } else if tlv_type == PATH_MARKING_TLV_TYPE && (length == 4 || length == 6) {
+            let status = PathStatus::from_bits_retain(value.read_u32()?);
+            let reason_code = match value.remaining() {
+                2 => Some(value.read_u16()?),
+                _ => None,
+            };
+            BmpTlvValue::PathStatus(PathStatusTlv {
+                status,
+                reason_code,
+            })
+        } else {
+            BmpTlvValue::Unknown(value.to_vec())
+        };
+
+        tlvs.push(BmpTlv {
+            tlv_type,
+            index,
+            value,
+        });
+    }
+
+    Ok(tlvs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::{BufMut, BytesMut};
+
+    #[test]
+    fn test_parse_bmp_tlvs() {
+        let mut buffer = BytesMut::new();
+        // path marking with reason code
+        buffer.put_u16(PATH_MARKING_TLV_TYPE);
+        buffer.put_u16(6);
+        buffer.put_u16(1);
+        buffer.put_u32(0x00000082);
+        buffer.put_u16(3);
+        // enterprise TLV of Juniper
+        buffer.put_u16(0x8000 | 7);
+        buffer.put_u16(6);
+        buffer.put_u16(0);
+        buffer.put_u32(2636);
+        buffer.put_slice(&[1, 2]);
+        // unknown TLV
+        buffer.put_u16(5);
+        buffer.put_u16(1);
+        buffer.put_u16(0);
+        buffer.put_u8(9);
+        // truncated TLV
+        buffer.put_u16(5);
+        buffer.put_u16(10);
+        buffer.put_u16(0);
+        buffer.put_u8(9);
+
+        let tlvs = parse_bmp_tlvs(&mut buffer.freeze()).unwrap();
+        assert_eq!(
+            tlvs,
+            vec![
+                BmpTlv {
+                    tlv_type: PATH_MARKING_TLV_TYPE,
+                    index: 1,
+                    value: BmpTlvValue::PathStatus(PathStatusTlv {
+                        status: PathStatus::BEST | PathStatus::ADD_PATH,
+                        reason_code: Some(3),
+                    }),
+                },
+                BmpTlv {
+                    tlv_type: 7,
+                    index: 0,
+                    value: BmpTlvValue::Enterprise(EnterpriseTlv {
+                        enterprise_number: 2636,
+                        value: vec![1, 2],
+                    }),
+                },
+                BmpTlv {
+                    tlv_type: 5,
+                    index: 0,
+                    value: BmpTlvValue::Unknown(vec![9]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_bmp_tlvs_invalid_enterprise() {
+        // enterprise bit set, but too short for the enterprise number
+        let mut data = Bytes::from_static(&[0x80, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x09]);
+        assert_eq!(
+            parse_bmp_tlvs(&mut data).unwrap(),
+            vec![BmpTlv {
+                tlv_type: 1,
+                index: 0,
+                value: BmpTlvValue::Unknown(vec![0x00, 0x09]),
+            }]
+        );
+    }
+}
@@ -3,13 +3,22 @@ Provides parsing for BMP and OpenBMP binary-formatted messages.
 */
 use crate::parser::bmp::error::ParserBmpError;
 use crate::parser::bmp::messages::*;
-pub use crate::parser::bmp::openbmp::parse_openbmp_header;
-use crate::utils::ReadUtils;
-use bytes::Bytes;
+pub use crate::parser::bmp::openbmp::{
+    encode_openbmp_header, encode_openbmp_msg, parse_openbmp_header,
+};
+use crate::parser::limits::RecordLimits;
+use crate::parser::ParseContext;
+use bytes::{Buf, Bytes};
 
 pub mod error;
+#[cfg(feature = "bmp-kafka")]
+pub mod kafka;
 pub mod messages;
 pub mod openbmp;
+#[cfg(feature = "bmp-server")]
+pub mod server;
+#[cfg(feature = "bmp-station")]
+pub mod station;
 
 /// Parse OpenBMP `raw_bmp` message.
 ///
@@ -21,29 +30,54 @@ pub fn parse_openbmp_msg(mut data: Bytes) -> Result<BmpMessage, ParserBmpError>
     parse_bmp_msg(&mut data)
 }
 
+/// Parse a BMP message, bounding the time and the number of steps spent on it with the same
+/// [RecordLimits] as MRT records, see [limits](crate::parser::limits).
+///
+/// Each TLV, attribute, community or prefix of the message counts as a step. A message exceeding
+/// the limits fails with [ParserBmpError::LimitExceeded].
+pub fn parse_bmp_msg_with_limits(
+    data: &mut Bytes,
+    limits: &RecordLimits,
+) -> Result<BmpMessage, ParserBmpError> {
+    let mut context = ParseContext::new().with_limits(*limits);
+    context.start_record();
+    let result = parse_bmp_msg_with_context(data, &mut context);
+    // errors of some steps are only logged, e.g. of attributes other than the last one
+    match context.finish_record() {
+        Some(_) => Err(ParserBmpError::LimitExceeded),
+        None => result,
+    }
+}
+
 /// Parse a BMP message.
 pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
+    parse_bmp_msg_with_context(data, &mut ParseContext::new())
+}
+
+/// Parse a BMP message within the limits of the given [ParseContext].
+fn parse_bmp_msg_with_context(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<BmpMessage, ParserBmpError> {
     let common_header = parse_bmp_common_header(data)?;
 
-    let content_length = common_header.msg_len as usize - 6;
-    data.has_n_remaining(content_length)?;
+    // the message length includes the 6-byte common header
+    let content_length = (common_header.msg_len as usize)
+        .checked_sub(6)
+        .ok_or(ParserBmpError::CorruptedBmpMessage)?;
+    if data.remaining() < content_length {
+        return Err(ParserBmpError::TruncatedBmpMessage);
+    }
     let mut content = data.split_to(content_length);
 
-    // if total_len>common_header.msg_len {
-    //     // truncated message
-    //     let diff = total_len - common_header.msg_len;
-    //     let bytes_left = reader.get_ref().len() as u32 - reader.position() as u32;
-    //     buffer = Vec::with_capacity((bytes_left - diff) as usize);
-    // } else if total_len == common_header.msg_len {
-    // } else {
-    //     return Err(ParserBmpError::CorruptedBmpMessage)
-    // };
-    // reader.read_exact(&mut buffer)?;
-
     match &common_header.msg_type {
         BmpMsgType::RouteMonitoring => {
             let per_peer_header = parse_per_peer_header(&mut content)?;
-            let msg = parse_route_monitoring(&mut content, &per_peer_header.asn_length())?;
+            let msg = parse_route_monitoring_with_context(
+                &mut content,
+                &per_peer_header.asn_length(),
+                context,
+            )?;
             Ok(BmpMessage {
                 common_header,
                 per_peer_header: Some(per_peer_header),
@@ -52,7 +86,11 @@ pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
         }
         BmpMsgType::RouteMirroringMessage => {
             let per_peer_header = parse_per_peer_header(&mut content)?;
-            let msg = parse_route_mirroring(&mut content, &per_peer_header.asn_length())?;
+            let msg = parse_route_mirroring_with_context(
+                &mut content,
+                &per_peer_header.asn_length(),
+                context,
+            )?;
             Ok(BmpMessage {
                 common_header,
                 per_peer_header: Some(per_peer_header),
@@ -61,7 +99,7 @@ pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
         }
         BmpMsgType::StatisticsReport => {
             let per_peer_header = parse_per_peer_header(&mut content)?;
-            let msg = parse_stats_report(&mut content)?;
+            let msg = parse_stats_report_with_context(&mut content, context)?;
             Ok(BmpMessage {
                 common_header,
                 per_peer_header: Some(per_peer_header),
@@ -79,10 +117,11 @@ pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
         }
         BmpMsgType::PeerUpNotification => {
             let per_peer_header = parse_per_peer_header(&mut content)?;
-            let msg = parse_peer_up_notification(
+            let msg = parse_peer_up_notification_with_context(
                 &mut content,
                 &per_peer_header.afi(),
                 &per_peer_header.asn_length(),
+                context,
             )?;
             Ok(BmpMessage {
                 common_header,
@@ -91,7 +130,7 @@ pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
             })
         }
         BmpMsgType::InitiationMessage => {
-            let msg = parse_initiation_message(&mut content)?;
+            let msg = parse_initiation_message_with_context(&mut content, context)?;
             Ok(BmpMessage {
                 common_header,
                 per_peer_header: None,
@@ -99,7 +138,7 @@ pub fn parse_bmp_msg(data: &mut Bytes) -> Result<BmpMessage, ParserBmpError> {
             })
         }
         BmpMsgType::TerminationMessage => {
-            let msg = parse_termination_message(&mut content)?;
+            let msg = parse_termination_message_with_context(&mut content, context)?;
             Ok(BmpMessage {
                 common_header,
                 per_peer_header: None,
@@ -135,4 +174,58 @@ mod tests {
 
     #[test]
     fn parse_peer_up_notification() {}
+
+    #[test]
+    fn test_tlv_flood_limit() {
+        // initiation message with 10000 empty string TLVs
+        let mut data = vec![3, 0, 0, 0, 0, 4];
+        for _ in 0..10000 {
+            data.extend([0, 0, 0, 0]);
+        }
+        let msg_len = (data.len() as u32).to_be_bytes();
+        data[1..5].copy_from_slice(&msg_len);
+        let data = Bytes::from(data);
+
+        let msg = parse_bmp_msg_with_limits(&mut data.clone(), &RecordLimits::default()).unwrap();
+        match msg.message_body {
+            BmpMessageBody::InitiationMessage(m) => assert_eq!(m.tlvs.len(), 10000),
+            _ => panic!("not an initiation message"),
+        }
+
+        let limits = RecordLimits {
+            timeout: None,
+            max_steps: Some(100),
+        };
+        assert_eq!(
+            parse_bmp_msg_with_limits(&mut data.clone(), &limits).unwrap_err(),
+            ParserBmpError::LimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_malformed_lengths() {
+        // message length shorter than the common header
+        let mut data = Bytes::from_static(&[3, 0, 0, 0, 5, 4]);
+        assert_eq!(
+            parse_bmp_msg(&mut data).unwrap_err(),
+            ParserBmpError::CorruptedBmpMessage
+        );
+
+        // message length longer than the available bytes
+        let mut data = Bytes::from_static(&[3, 0, 0, 0, 100, 4, 0, 0]);
+        assert_eq!(
+            parse_bmp_msg(&mut data).unwrap_err(),
+            ParserBmpError::TruncatedBmpMessage
+        );
+
+        // route monitoring message with a truncated per-peer header
+        let mut data = Bytes::from_static(&[3, 0, 0, 0, 10, 0, 0, 0, 0, 0]);
+        assert!(parse_bmp_msg(&mut data).is_err());
+
+        // LocalRib per-peer header cut off within the zero-filled peer address
+        let mut data = Bytes::from_static(&[
+            3, 0, 0, 0, 21, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]);
+        assert!(parse_bmp_msg(&mut data).is_err());
+    }
 }
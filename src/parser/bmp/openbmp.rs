@@ -1,8 +1,15 @@
 use crate::parser::bmp::error::ParserBmpError;
 use crate::parser::ReadUtils;
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::net::IpAddr;
 
+/// OpenBMP message version written by [encode_openbmp_header], the only one parsed.
+const OPENBMP_VERSION: (u8, u8) = (1, 7);
+/// Object type of `bmp_raw` messages.
+const OBJECT_TYPE_BMP_RAW: u8 = 12;
+/// Size of the fixed-length fields of the header.
+const FIXED_HEADER_LEN: usize = 78;
+
 ///
 /// ```text
 ///   0                   1                   2                   3
@@ -42,7 +49,7 @@ use std::net::IpAddr;
 ///  |                           Row Count                           |
 ///  +---------------------------------------------------------------+
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OpenBmpHeader {
     pub major_version: u8,
     pub minor_version: u8,
@@ -52,11 +59,37 @@ pub struct OpenBmpHeader {
     pub msg_len: u32,
     pub object_type: u8,
     pub timestamp: f64,
+    /// hash identifying the collector
+    pub collector_hash: [u8; 16],
     pub admin_id: String,
+    /// hash identifying the router
+    pub router_hash: [u8; 16],
     pub router_ip: IpAddr,
     pub router_group: Option<String>,
 }
 
+impl OpenBmpHeader {
+    /// Header of a `bmp_raw` message of a router, with zero hashes and no router group.
+    ///
+    /// The message length is set by [encode_openbmp_msg], or must be set before
+    /// [encode_openbmp_header].
+    pub fn new(admin_id: &str, router_ip: IpAddr, timestamp: f64) -> Self {
+        OpenBmpHeader {
+            major_version: OPENBMP_VERSION.0,
+            minor_version: OPENBMP_VERSION.1,
+            header_len: (FIXED_HEADER_LEN + admin_id.len().min(255)) as u16,
+            msg_len: 0,
+            object_type: OBJECT_TYPE_BMP_RAW,
+            timestamp,
+            collector_hash: [0; 16],
+            admin_id: admin_id.to_string(),
+            router_hash: [0; 16],
+            router_ip,
+            router_group: None,
+        }
+    }
+}
+
 pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmpError> {
     // read magic number
     let magic_number = data.read_n_bytes_to_string(4)?;
@@ -67,7 +100,7 @@ pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmp
     // read version numbers
     let version_major = data.read_u8()?;
     let version_minor = data.read_u8()?;
-    if (version_major, version_minor) != (1, 7) {
+    if (version_major, version_minor) != OPENBMP_VERSION {
         return Err(ParserBmpError::InvalidOpenBmpHeader);
     }
 
@@ -84,7 +117,7 @@ pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmp
 
     // read object type
     let object_type = data.read_u8()?;
-    if object_type != 12 {
+    if object_type != OBJECT_TYPE_BMP_RAW {
         return Err(ParserBmpError::UnsupportedOpenBmpMessage);
     }
 
@@ -94,7 +127,9 @@ pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmp
     let timestamp = t_sec as f64 + (t_usec as f64) / 1_000_000.0;
 
     // read admin-id
-    data.advance(16);
+    let mut collector_hash = [0u8; 16];
+    data.has_n_remaining(16)?;
+    data.copy_to_slice(&mut collector_hash);
     let mut name_len = data.read_u16()?;
     if name_len > 255 {
         name_len = 255;
@@ -102,12 +137,14 @@ pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmp
     let admin_id = data.read_n_bytes_to_string(name_len as usize)?;
 
     // read router IP
-    data.advance(16);
+    let mut router_hash = [0u8; 16];
+    data.has_n_remaining(16)?;
+    data.copy_to_slice(&mut router_hash);
     let ip: IpAddr = if is_router_ipv6 {
         data.read_ipv6_address()?.into()
     } else {
         let ip = data.read_ipv4_address()?;
-        data.advance(12);
+        data.skip_n_bytes(12)?;
         ip.into()
     };
 
@@ -130,15 +167,82 @@ pub fn parse_openbmp_header(data: &mut Bytes) -> Result<OpenBmpHeader, ParserBmp
         msg_len,
         object_type,
         timestamp,
+        collector_hash,
         admin_id,
+        router_hash,
         router_ip: ip,
         router_group: Some(group),
     })
 }
 
+/// Encode an OpenBMP `bmp_raw` header, e.g. to publish BMP messages to OpenBMP Kafka topics.
+///
+/// The header length is computed from the admin ID and router group, truncated to 255 bytes,
+/// and the message length is taken from the header. The version and object type are always 1.7
+/// and `bmp_raw`, the only ones [parse_openbmp_header] accepts.
+pub fn encode_openbmp_header(header: &OpenBmpHeader) -> Bytes {
+    let admin_id = &header.admin_id.as_bytes()[..header.admin_id.len().min(255)];
+    let router_group = match &header.router_group {
+        Some(group) => &group.as_bytes()[..group.len().min(255)],
+        None => &[],
+    };
+
+    let mut bytes = BytesMut::with_capacity(FIXED_HEADER_LEN + admin_id.len() + router_group.len());
+    bytes.put_slice(b"OBMP");
+    bytes.put_u8(OPENBMP_VERSION.0);
+    bytes.put_u8(OPENBMP_VERSION.1);
+    bytes.put_u16((FIXED_HEADER_LEN + admin_id.len() + router_group.len()) as u16);
+    bytes.put_u32(header.msg_len);
+
+    // router message, with IPv6 flag
+    let flags = match header.router_ip {
+        IpAddr::V4(_) => 0x80,
+        IpAddr::V6(_) => 0xC0,
+    };
+    bytes.put_u8(flags);
+    bytes.put_u8(OBJECT_TYPE_BMP_RAW);
+
+    let seconds = header.timestamp.trunc();
+    let microseconds = ((header.timestamp - seconds) * 1_000_000.0).round() as u32;
+    bytes.put_u32(seconds as u32);
+    bytes.put_u32(microseconds.min(999_999));
+
+    bytes.put_slice(&header.collector_hash);
+    bytes.put_u16(admin_id.len() as u16);
+    bytes.put_slice(admin_id);
+
+    bytes.put_slice(&header.router_hash);
+    match header.router_ip {
+        IpAddr::V4(ip) => {
+            bytes.put_slice(&ip.octets());
+            bytes.put_bytes(0, 12);
+        }
+        IpAddr::V6(ip) => bytes.put_slice(&ip.octets()),
+    }
+    bytes.put_u16(router_group.len() as u16);
+    bytes.put_slice(router_group);
+
+    // row count
+    bytes.put_u32(1);
+    bytes.freeze()
+}
+
+/// Encode an OpenBMP `bmp_raw` message: the header, with the message length of the BMP data,
+/// followed by the BMP data.
+pub fn encode_openbmp_msg(header: &OpenBmpHeader, bmp_data: &[u8]) -> Bytes {
+    let header = OpenBmpHeader {
+        msg_len: bmp_data.len() as u32,
+        ..header.clone()
+    };
+    let mut bytes = BytesMut::from(encode_openbmp_header(&header).as_ref());
+    bytes.put_slice(bmp_data);
+    bytes.freeze()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::bmp::parse_openbmp_msg;
 
     #[test]
     fn test_open_bmp_header() {
@@ -147,4 +251,35 @@ mod tests {
         let mut data = Bytes::from(decoded);
         let _header = parse_openbmp_header(&mut data).unwrap();
     }
+
+    #[test]
+    fn test_encode_openbmp_header() {
+        let input = "4f424d500107006400000033800c6184b9c2000c602cbf4f072f3ae149d23486024bc3dadfc4000a69732d63632d626d7031c677060bdd020a9e92be000200de2e3180df3369000000000000000000000000000c726f7574652d76696577733500000001030000003302000000000000000000000000000000000000000000003fda060e00000da30000000061523c36000c0e1c0200000a";
+        let decoded = hex::decode(input).unwrap();
+        let mut data = Bytes::from(decoded.clone());
+        let header = parse_openbmp_header(&mut data).unwrap();
+        assert_eq!(header.admin_id, "is-cc-bmp1");
+        assert_eq!(header.router_group.as_deref(), Some("route-views5"));
+
+        let encoded = encode_openbmp_header(&header);
+        assert_eq!(encoded.len(), header.header_len as usize);
+        assert_eq!(encoded.as_ref(), &decoded[..header.header_len as usize]);
+        let msg_end = header.header_len as usize + header.msg_len as usize;
+        let bmp_data = &data[..header.msg_len as usize];
+        assert_eq!(
+            encode_openbmp_msg(&header, bmp_data).as_ref(),
+            &decoded[..msg_end]
+        );
+
+        let header = OpenBmpHeader::new("relay", "2001:db8::1".parse().unwrap(), 1700000000.25);
+        let msg = encode_openbmp_msg(&header, bmp_data);
+        let mut msg_data = msg.clone();
+        let parsed = parse_openbmp_header(&mut msg_data).unwrap();
+        assert_eq!(parsed.admin_id, "relay");
+        assert_eq!(parsed.router_ip, header.router_ip);
+        assert_eq!(parsed.timestamp, 1700000000.25);
+        assert_eq!(parsed.header_len, header.header_len);
+        assert_eq!(parsed.msg_len as usize, bmp_data.len());
+        assert!(parse_openbmp_msg(msg).is_ok());
+    }
 }
@@ -0,0 +1,342 @@
+/*!
+Async BMP server: accept BMP sessions from routers on a tokio runtime.
+
+This is the async counterpart of [station](crate::parser::bmp::station), sharing its
+[BmpSession] state. [BmpServer::accept] returns each session as a [BmpConnection] yielding the
+messages or the elems of the router, and [BmpServer::into_elem_stream] handles every session on
+its own task and merges their elems into one stream.
+
+```no_run
+use bgpkit_parser::bmp::server::BmpServer;
+use futures_util::StreamExt;
+
+#[tokio::main]
+async fn main() {
+    let server = BmpServer::bind("0.0.0.0:11019").await.unwrap();
+    let mut elems = Box::pin(server.into_elem_stream());
+    while let Some((router_addr, elem)) = elems.next().await {
+        println!("{} {}", router_addr, elem);
+    }
+}
+```
+*/
+use crate::models::BgpElem;
+use crate::parser::bmp::error::ParserBmpError;
+use crate::parser::bmp::messages::BmpMessage;
+use crate::parser::bmp::station::{
+    bmp_message_buffer, parse_bmp_buffer, BmpSession, MAX_BMP_SESSIONS,
+};
+use futures_util::stream::{self, Stream};
+use log::{info, warn};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, Semaphore};
+
+/// Number of elems buffered by [BmpServer::into_elem_stream] before the sessions wait for the
+/// stream to be read.
+const ELEM_CHANNEL_SIZE: usize = 1024;
+
+/// First and longest delay before accepting connections again after an error, e.g. when the
+/// process runs out of file descriptors.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+type NewSession = Arc<dyn Fn(SocketAddr) -> BmpSession + Send + Sync>;
+
+/// Read the next BMP message from an async stream. Returns `Ok(None)` once the stream is closed
+/// at a message boundary, see [read_bmp_message](crate::parser::bmp::station::read_bmp_message).
+pub async fn read_bmp_message_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> std::io::Result<Option<Result<BmpMessage, ParserBmpError>>> {
+    let mut header = [0u8; 6];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut buffer = bmp_message_buffer(&header)?;
+    reader.read_exact(&mut buffer[header.len()..]).await?;
+    Ok(Some(parse_bmp_buffer(buffer)))
+}
+
+/// Listener accepting BMP sessions, see [server](crate::parser::bmp::server).
+pub struct BmpServer {
+    listener: TcpListener,
+    new_session: NewSession,
+}
+
+impl BmpServer {
+    /// Listen for BMP sessions on `addr`.
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> std::io::Result<BmpServer> {
+        Ok(BmpServer {
+            listener: TcpListener::bind(addr).await?,
+            new_session: Arc::new(BmpSession::new),
+        })
+    }
+
+    /// Create the state of each session with `new_session` from the router's address, e.g. to
+    /// synthesize withdrawals on peer down notifications, see
+    /// [run_bmp_station_with_sessions](crate::parser::bmp::station::run_bmp_station_with_sessions).
+    pub fn with_sessions<S>(mut self, new_session: S) -> Self
+    where
+        S: Fn(SocketAddr) -> BmpSession + Send + Sync + 'static,
+    {
+        self.new_session = Arc::new(new_session);
+        self
+    }
+
+    /// Address the server listens on.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Wait for the next router to connect.
+    pub async fn accept(&self) -> std::io::Result<BmpConnection> {
+        let (stream, router_addr) = self.listener.accept().await?;
+        info!("BMP session with {} established", router_addr);
+        Ok(BmpConnection {
+            stream,
+            session: (self.new_session)(router_addr),
+        })
+    }
+
+    /// Handle every session on its own task, returning the elems of all sessions with the
+    /// address of their router.
+    ///
+    /// At most [MAX_BMP_SESSIONS] sessions are handled at once, further connections are closed
+    /// until a session ends. Must be called within a tokio runtime. The tasks stop once the stream
+    /// is dropped.
+    pub fn into_elem_stream(self) -> impl Stream<Item = (SocketAddr, BgpElem)> {
+        let (sender, receiver) = mpsc::channel(ELEM_CHANNEL_SIZE);
+        let sessions = Arc::new(Semaphore::new(MAX_BMP_SESSIONS));
+        tokio::spawn(async move {
+            let mut backoff = MIN_ACCEPT_BACKOFF;
+            loop {
+                let mut connection = tokio::select! {
+                    _ = sender.closed() => return,
+                    connection = self.accept() => match connection {
+                        Ok(connection) => connection,
+                        Err(e) => {
+                            // accept errors such as running out of file descriptors persist
+                            // until a connection is closed, so retrying at once would spin
+                            warn!("failed to accept BMP connection: {}", e);
+                            tokio::select! {
+                                _ = sender.closed() => return,
+                                _ = tokio::time::sleep(backoff) => {}
+                            }
+                            backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                            continue;
+                        }
+                    },
+                };
+                backoff = MIN_ACCEPT_BACKOFF;
+                let slot = match Arc::clone(&sessions).try_acquire_owned() {
+                    Ok(slot) => slot,
+                    Err(_) => {
+                        warn!(
+                            "closing BMP connection from {}: {} sessions already active",
+                            connection.session.router_addr, MAX_BMP_SESSIONS
+                        );
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let _slot = slot;
+                    let router_addr = connection.session.router_addr;
+                    while let Some(elems) = connection.next_elems().await {
+                        for elem in elems {
+                            if sender.send((router_addr, elem)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        stream::unfold(receiver, |mut receiver| async move {
+            let item = receiver.recv().await?;
+            Some((item, receiver))
+        })
+    }
+}
+
+/// BMP session with a router accepted by a [BmpServer].
+pub struct BmpConnection {
+    stream: TcpStream,
+    session: BmpSession,
+}
+
+impl BmpConnection {
+    /// State of the session, updated by [BmpConnection::next_elems].
+    pub fn session(&self) -> &BmpSession {
+        &self.session
+    }
+
+    /// Read the next message of the router, or `Ok(None)` once the router closed the session.
+    ///
+    /// The state of the session is not updated, see [BmpSession::process_message].
+    pub async fn next_message(
+        &mut self,
+    ) -> std::io::Result<Option<Result<BmpMessage, ParserBmpError>>> {
+        read_bmp_message_async(&mut self.stream).await
+    }
+
+    /// Read the next message of the router, update the state of the session with it, and return
+    /// its elems, or None once the session is closed.
+    ///
+    /// Messages that fail to parse are logged and skipped; the session ends at the first IO
+    /// error.
+    pub async fn next_elems(&mut self) -> Option<Vec<BgpElem>> {
+        let router_addr = self.session.router_addr;
+        match self.next_message().await {
+            Ok(Some(Ok(msg))) => Some(self.session.process_message(msg)),
+            Ok(Some(Err(e))) => {
+                warn!("failed to parse BMP message from {}: {}", router_addr, e);
+                Some(vec![])
+            }
+            Ok(None) => {
+                info!("BMP session with {} closed", router_addr);
+                None
+            }
+            Err(e) => {
+                warn!("BMP session with {} failed: {}", router_addr, e);
+                None
+            }
+        }
+    }
+
+    /// Stream of the messages of the router, skipping messages that fail to parse.
+    ///
+    /// The state of the session is not updated, see [BmpSession::process_message].
+    pub fn message_stream(self) -> impl Stream<Item = BmpMessage> {
+        stream::unfold(self, |mut connection| async move {
+            loop {
+                match connection.next_message().await {
+                    Ok(Some(Ok(msg))) => return Some((msg, connection)),
+                    Ok(Some(Err(e))) => warn!(
+                        "failed to parse BMP message from {}: {}",
+                        connection.session.router_addr, e
+                    ),
+                    Ok(None) => return None,
+                    Err(e) => {
+                        warn!(
+                            "BMP session with {} failed: {}",
+                            connection.session.router_addr, e
+                        );
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Stream of the elems of the router.
+    pub fn elem_stream(self) -> impl Stream<Item = BgpElem> {
+        let state = (self, Vec::new().into_iter());
+        stream::unfold(state, |(mut connection, mut elems)| async move {
+            loop {
+                if let Some(elem) = elems.next() {
+                    return Some((elem, (connection, elems)));
+                }
+                elems = connection.next_elems().await?.into_iter();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::bmp::messages::BmpMsgType;
+    use crate::test_vectors;
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    /// Initiation message with a sysName TLV, followed by the route monitoring test vectors.
+    fn session_bytes() -> Vec<u8> {
+        let mut data = vec![3, 0, 0, 0, 13, 4, 0, 2, 0, 3, b'r', b't', b'r'];
+        for vector in test_vectors::bmp_route_monitoring() {
+            data.extend(vector.bytes);
+        }
+        data
+    }
+
+    fn expected_elems() -> Vec<BgpElem> {
+        test_vectors::bmp_route_monitoring()
+            .into_iter()
+            .flat_map(|v| v.expected)
+            .collect()
+    }
+
+    async fn send(addr: SocketAddr, data: Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(&data).await.unwrap();
+        stream.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_bmp_message_async() {
+        let mut data = session_bytes();
+        data.extend([3, 0, 0, 0, 6, 5]);
+        let mut reader = data.as_slice();
+        let msg = read_bmp_message_async(&mut reader).await.unwrap().unwrap();
+        assert_eq!(
+            msg.unwrap().common_header.msg_type,
+            BmpMsgType::InitiationMessage
+        );
+        let msg = read_bmp_message_async(&mut reader).await.unwrap().unwrap();
+        assert_eq!(
+            msg.unwrap().common_header.msg_type,
+            BmpMsgType::RouteMonitoring
+        );
+        let msg = read_bmp_message_async(&mut reader).await.unwrap().unwrap();
+        assert_eq!(
+            msg.unwrap().common_header.msg_type,
+            BmpMsgType::TerminationMessage
+        );
+        assert!(read_bmp_message_async(&mut reader).await.unwrap().is_none());
+
+        // length shorter than the common header
+        let mut reader: &[u8] = &[3, 0, 0, 0, 2, 4];
+        assert!(read_bmp_message_async(&mut reader).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accept() {
+        let server = BmpServer::bind("127.0.0.1:0").await.unwrap();
+        let client = tokio::spawn(send(server.local_addr().unwrap(), session_bytes()));
+
+        let mut connection = server.accept().await.unwrap();
+        let mut elems = vec![];
+        while let Some(batch) = connection.next_elems().await {
+            elems.extend(batch);
+        }
+        assert_eq!(elems, expected_elems());
+        assert_eq!(connection.session().sys_name.as_deref(), Some("rtr"));
+        client.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_into_elem_stream() {
+        let server = BmpServer::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut stream = Box::pin(server.into_elem_stream());
+        for _ in 0..2 {
+            tokio::spawn(send(addr, session_bytes()));
+        }
+
+        let expected = expected_elems();
+        let mut elems = vec![];
+        for _ in 0..2 * expected.len() {
+            let (_router_addr, elem) = stream.next().await.unwrap();
+            elems.push(elem);
+        }
+        assert!(elems.iter().all(|e| expected.contains(e)));
+    }
+}
@@ -0,0 +1,506 @@
+/*!
+Minimal BMP station: accept BMP sessions from routers over TCP and hand the routes to a callback.
+
+This ties together BMP message framing, per-session router state, and the conversion of route
+monitoring messages into [BgpElem]s. It is intended as a starting point for collectors built on
+this crate; production deployments will likely want their own connection handling.
+
+```no_run
+use bgpkit_parser::bmp::station::run_bmp_station;
+
+run_bmp_station("0.0.0.0:11019", |session, elem| {
+    println!("{} {}", session.router_addr, elem);
+})
+.unwrap();
+```
+*/
+use crate::models::{Asn, BgpElem, ElemType, NetworkPrefix, RibPolicy, RibSide, Safi};
+use crate::parser::bmp::error::ParserBmpError;
+use crate::parser::bmp::messages::*;
+use crate::parser::bmp::parse_bmp_msg;
+use crate::Elementor;
+use bytes::{BufMut, Bytes, BytesMut};
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Maximum length of a BMP message accepted from a stream.
+///
+/// Route monitoring messages hold a single BGP message of at most 65535 bytes ([RFC8654]), so
+/// longer lengths are only expected from corrupted or malicious streams, and are rejected before
+/// the message is buffered.
+///
+/// [RFC8654]: https://datatracker.ietf.org/doc/html/rfc8654
+pub const MAX_BMP_MESSAGE_LENGTH: usize = 1 << 20;
+
+/// Maximum number of sessions handled at once by [run_bmp_station] and by the elem stream of the
+/// async BMP server. Connections beyond it are closed until a session ends.
+pub const MAX_BMP_SESSIONS: usize = 256;
+
+/// One RIB of a peer monitored by the router: the peer address, ASN and distinguisher, and the RIB
+/// side and policy of the routes.
+type PeerRib = (IpAddr, Asn, u64, RibSide, RibPolicy);
+
+/// State of a BMP session with a monitored router.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BmpSession {
+    /// remote address of the router's TCP connection
+    pub router_addr: SocketAddr,
+    /// sysName reported in the initiation message
+    pub sys_name: Option<String>,
+    /// sysDescr reported in the initiation message
+    pub sys_descr: Option<String>,
+    /// peers with an established BGP session, based on peer up and down notifications
+    pub peers: HashSet<(IpAddr, Asn)>,
+    /// prefixes currently announced in each RIB of each peer, only tracked when synthesizing
+    /// withdrawals
+    routes: HashMap<PeerRib, HashSet<(NetworkPrefix, Safi)>>,
+    synthesize_withdrawals: bool,
+}
+
+impl BmpSession {
+    pub fn new(router_addr: SocketAddr) -> Self {
+        BmpSession {
+            router_addr,
+            sys_name: None,
+            sys_descr: None,
+            peers: HashSet::new(),
+            routes: HashMap::new(),
+            synthesize_withdrawals: false,
+        }
+    }
+
+    /// Emit a withdrawal elem for every prefix announced by a peer when a peer down notification
+    /// for the peer is received, as a BGP speaker would remove the routes of a closed session.
+    ///
+    /// The prefixes announced by each peer are tracked to do so, which takes memory proportional
+    /// to the size of the peers' tables. Peers are told apart by their address, ASN and
+    /// distinguisher, and the routes of each RIB side and policy of a peer are tracked separately.
+    /// The withdrawals carry the RIB side and policy of the routes they withdraw.
+    ///
+    /// Withdrawals are synthesized for every peer down reason, including de-configured peers,
+    /// whose routes are no longer updated by the router.
+    pub fn with_withdrawal_synthesis(mut self) -> Self {
+        self.synthesize_withdrawals = true;
+        self
+    }
+
+    /// Number of prefixes currently announced by a peer, summed over its RIBs, if withdrawals are
+    /// synthesized.
+    pub fn num_routes(&self, peer_ip: IpAddr, peer_asn: Asn) -> usize {
+        self.routes
+            .iter()
+            .filter(|((ip, asn, ..), _)| *ip == peer_ip && *asn == peer_asn)
+            .map(|(_, routes)| routes.len())
+            .sum()
+    }
+
+    fn update_routes(&mut self, header: &BmpPerPeerHeader, elems: &[BgpElem]) {
+        let key = peer_rib(header);
+        for elem in elems {
+            match elem.elem_type {
+                ElemType::ANNOUNCE => {
+                    self.routes
+                        .entry(key)
+                        .or_default()
+                        .insert((elem.prefix, elem.safi));
+                }
+                ElemType::WITHDRAW => {
+                    if let Some(routes) = self.routes.get_mut(&key) {
+                        routes.remove(&(elem.prefix, elem.safi));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Withdrawals of all prefixes of the peer of the header, ordered by RIB and prefix.
+    fn withdraw_routes(&mut self, header: &BmpPerPeerHeader) -> Vec<BgpElem> {
+        let mut ribs: Vec<PeerRib> = self
+            .routes
+            .keys()
+            .filter(|(ip, asn, distinguisher, ..)| {
+                *ip == header.peer_ip
+                    && *asn == header.peer_asn
+                    && *distinguisher == header.peer_distinguisher
+            })
+            .copied()
+            .collect();
+        ribs.sort();
+
+        let mut withdrawals = vec![];
+        for rib in ribs {
+            let (peer_ip, peer_asn, _, rib_side, policy) = rib;
+            let mut routes: Vec<_> = match self.routes.remove(&rib) {
+                Some(routes) => routes.into_iter().collect(),
+                None => continue,
+            };
+            routes.sort_by_key(|(prefix, safi)| (*prefix, u8::from(*safi)));
+            withdrawals.extend(routes.into_iter().map(|(prefix, safi)| BgpElem {
+                timestamp: header.timestamp,
+                elem_type: ElemType::WITHDRAW,
+                peer_ip,
+                peer_asn,
+                prefix,
+                safi,
+                next_hop: None,
+                rib_side: Some(rib_side),
+                policy: Some(policy),
+                ..Default::default()
+            }));
+        }
+        withdrawals
+    }
+
+    /// Update the session state with a message and convert route monitoring messages to elems.
+    ///
+    /// The [RIB side](BgpElem::rib_side) and [policy](BgpElem::policy) of the elems are set from
+    /// the per-peer header of the message.
+    pub fn process_message(&mut self, msg: BmpMessage) -> Vec<BgpElem> {
+        match (msg.message_body, msg.per_peer_header) {
+            (BmpMessageBody::InitiationMessage(m), _) => {
+                for tlv in m.tlvs {
+                    match tlv.info_type {
+                        InitiationTlvType::SysName => self.sys_name = Some(tlv.info),
+                        InitiationTlvType::SysDescr => self.sys_descr = Some(tlv.info),
+                        _ => {}
+                    }
+                }
+                vec![]
+            }
+            (BmpMessageBody::PeerUpNotification(_), Some(h)) => {
+                self.peers.insert((h.peer_ip, h.peer_asn));
+                vec![]
+            }
+            (BmpMessageBody::PeerDownNotification(_), Some(h)) => {
+                self.peers.remove(&(h.peer_ip, h.peer_asn));
+                match self.synthesize_withdrawals {
+                    true => self.withdraw_routes(&h),
+                    false => vec![],
+                }
+            }
+            (BmpMessageBody::RouteMonitoring(m), Some(h)) => {
+                let mut elems =
+                    Elementor::bgp_to_elems(m.bgp_message, h.timestamp, &h.peer_ip, &h.peer_asn);
+                for elem in &mut elems {
+                    elem.rib_side = Some(h.rib_side());
+                    elem.policy = Some(h.policy());
+                }
+                if self.synthesize_withdrawals {
+                    self.update_routes(&h, &elems);
+                }
+                elems
+            }
+            _ => vec![],
+        }
+    }
+}
+
+fn peer_rib(header: &BmpPerPeerHeader) -> PeerRib {
+    (
+        header.peer_ip,
+        header.peer_asn,
+        header.peer_distinguisher,
+        header.rib_side(),
+        header.policy(),
+    )
+}
+
+/// Read the next BMP message from a stream. Returns `Ok(None)` once the stream is closed at a
+/// message boundary.
+pub fn read_bmp_message<R: Read>(
+    reader: &mut R,
+) -> std::io::Result<Option<Result<BmpMessage, ParserBmpError>>> {
+    let mut header = [0u8; 6];
+    match reader.read_exact(&mut header) {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut buffer = bmp_message_buffer(&header)?;
+    reader.read_exact(&mut buffer[header.len()..])?;
+    Ok(Some(parse_bmp_buffer(buffer)))
+}
+
+/// Buffer for a BMP message starting with the common header `header`, with room for the rest of
+/// the message after the header.
+pub(crate) fn bmp_message_buffer(header: &[u8; 6]) -> std::io::Result<BytesMut> {
+    let msg_len = bmp_message_length(header)?;
+    let mut buffer = BytesMut::with_capacity(msg_len);
+    buffer.put_slice(header);
+    buffer.resize(msg_len, 0);
+    Ok(buffer)
+}
+
+/// Parse a BMP message read into a buffer from [bmp_message_buffer].
+pub(crate) fn parse_bmp_buffer(buffer: BytesMut) -> Result<BmpMessage, ParserBmpError> {
+    let mut data: Bytes = buffer.freeze();
+    parse_bmp_msg(&mut data)
+}
+
+/// Length of a BMP message, from the first 6 bytes of its common header.
+///
+/// Lengths shorter than the common header or longer than [MAX_BMP_MESSAGE_LENGTH] are an error.
+pub(crate) fn bmp_message_length(header: &[u8; 6]) -> std::io::Result<usize> {
+    let msg_len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if msg_len < header.len() || msg_len > MAX_BMP_MESSAGE_LENGTH {
+        // the stream cannot be re-synchronized without a valid length
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid BMP message length {}", msg_len),
+        ));
+    }
+    Ok(msg_len)
+}
+
+fn handle_session<F>(mut stream: TcpStream, mut session: BmpSession, handler: &F)
+where
+    F: Fn(&BmpSession, BgpElem),
+{
+    let router_addr = session.router_addr;
+    loop {
+        match read_bmp_message(&mut stream) {
+            Ok(Some(Ok(msg))) => {
+                for elem in session.process_message(msg) {
+                    handler(&session, elem);
+                }
+            }
+            Ok(Some(Err(e))) => warn!("failed to parse BMP message from {}: {}", router_addr, e),
+            Ok(None) => break,
+            Err(e) => {
+                warn!("BMP session with {} failed: {}", router_addr, e);
+                break;
+            }
+        }
+    }
+    info!("BMP session with {} closed", router_addr);
+}
+
+/// Frees a session slot of [run_bmp_station_with_sessions] once its thread ends, even if the
+/// handler panics.
+struct SessionSlot(Arc<AtomicUsize>);
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Listen for BMP sessions on `addr` and call `handler` with every elem received.
+///
+/// Each session is handled on its own thread, for at most [MAX_BMP_SESSIONS] sessions at once.
+/// Messages that fail to parse are logged and skipped. This function only returns if binding the
+/// listener fails.
+pub fn run_bmp_station<A, F>(addr: A, handler: F) -> std::io::Result<()>
+where
+    A: ToSocketAddrs,
+    F: Fn(&BmpSession, BgpElem) + Send + Sync + 'static,
+{
+    run_bmp_station_with_sessions(addr, BmpSession::new, handler)
+}
+
+/// Like [run_bmp_station], with the state of each session created by `new_session` from the
+/// router's address, e.g. to synthesize withdrawals on peer down notifications:
+///
+/// ```no_run
+/// use bgpkit_parser::bmp::station::{run_bmp_station_with_sessions, BmpSession};
+///
+/// run_bmp_station_with_sessions(
+///     "0.0.0.0:11019",
+///     |router_addr| BmpSession::new(router_addr).with_withdrawal_synthesis(),
+///     |session, elem| println!("{} {}", session.router_addr, elem),
+/// )
+/// .unwrap();
+/// ```
+pub fn run_bmp_station_with_sessions<A, S, F>(
+    addr: A,
+    new_session: S,
+    handler: F,
+) -> std::io::Result<()>
+where
+    A: ToSocketAddrs,
+    S: Fn(SocketAddr) -> BmpSession,
+    F: Fn(&BmpSession, BgpElem) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)?;
+    let handler = Arc::new(handler);
+    let active_sessions = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("failed to accept BMP connection: {}", e);
+                continue;
+            }
+        };
+        let router_addr = match stream.peer_addr() {
+            Ok(a) => a,
+            Err(e) => {
+                warn!("failed to get address of BMP connection: {}", e);
+                continue;
+            }
+        };
+        // sessions are only added by this thread, so the count cannot grow past the check
+        if active_sessions.load(Ordering::Acquire) >= MAX_BMP_SESSIONS {
+            warn!(
+                "closing BMP connection from {}: {} sessions already active",
+                router_addr, MAX_BMP_SESSIONS
+            );
+            continue;
+        }
+        active_sessions.fetch_add(1, Ordering::AcqRel);
+        let slot = SessionSlot(Arc::clone(&active_sessions));
+        info!("BMP session with {} established", router_addr);
+
+        let session = new_session(router_addr);
+        let handler = Arc::clone(&handler);
+        std::thread::spawn(move || {
+            let _slot = slot;
+            handle_session(stream, session, handler.as_ref())
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_bmp_message() {
+        // initiation message with a sysName TLV, followed by a termination message
+        let mut data = vec![3, 0, 0, 0, 13, 4, 0, 2, 0, 3, b'r', b't', b'r'];
+        data.extend([3, 0, 0, 0, 6, 5]);
+        let mut reader = Cursor::new(data);
+
+        let msg = read_bmp_message(&mut reader).unwrap().unwrap().unwrap();
+        let mut session = BmpSession::new("192.0.2.1:11019".parse().unwrap());
+        assert!(session.process_message(msg).is_empty());
+        assert_eq!(session.sys_name.as_deref(), Some("rtr"));
+
+        let msg = read_bmp_message(&mut reader).unwrap().unwrap().unwrap();
+        assert_eq!(msg.common_header.msg_type, BmpMsgType::TerminationMessage);
+        assert!(read_bmp_message(&mut reader).unwrap().is_none());
+
+        // length shorter than the common header
+        let mut reader = Cursor::new(vec![3, 0, 0, 0, 2, 4]);
+        assert!(read_bmp_message(&mut reader).is_err());
+
+        // length longer than the maximum is rejected before reading the message
+        let mut reader = Cursor::new(vec![3, 0xff, 0xff, 0xff, 0xff, 0]);
+        let err = read_bmp_message(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_withdrawal_synthesis() {
+        use crate::models::{Attributes, BgpMessage, BgpUpdateMessage, RibPolicy, RibSide};
+        use std::str::FromStr;
+
+        let peer_ip = IpAddr::from_str("192.0.2.2").unwrap();
+        let peer_asn = Asn::new_32bit(64496);
+        let header = |peer_distinguisher, flags, timestamp| BmpPerPeerHeader {
+            peer_distinguisher,
+            peer_flags: PerPeerFlags::PeerFlags(flags),
+            peer_ip,
+            peer_asn,
+            timestamp,
+            ..Default::default()
+        };
+        let message = |msg_type, header, body| BmpMessage {
+            common_header: BmpCommonHeader {
+                version: 3,
+                msg_len: 0,
+                msg_type,
+            },
+            per_peer_header: Some(header),
+            message_body: body,
+        };
+        let update = |header, announced: &[&str], withdrawn: &[&str]| {
+            let prefixes = |p: &[&str]| -> Vec<NetworkPrefix> {
+                p.iter()
+                    .map(|p| NetworkPrefix::from_str(p).unwrap())
+                    .collect()
+            };
+            message(
+                BmpMsgType::RouteMonitoring,
+                header,
+                BmpMessageBody::RouteMonitoring(RouteMonitoring {
+                    bgp_message: BgpMessage::Update(BgpUpdateMessage {
+                        withdrawn_prefixes: prefixes(withdrawn),
+                        attributes: Attributes::default(),
+                        announced_prefixes: prefixes(announced),
+                    }),
+                    tlvs: vec![],
+                }),
+            )
+        };
+        let peer_down = |header| {
+            message(
+                BmpMsgType::PeerDownNotification,
+                header,
+                BmpMessageBody::PeerDownNotification(PeerDownNotification {
+                    reason: PeerDownReason::RemoteSystemsClosedNoData,
+                    data: None,
+                }),
+            )
+        };
+        let pre_policy = header(0, PeerFlags::empty(), 1.0);
+        let post_policy = header(0, PeerFlags::IS_POST_POLICY, 1.0);
+        let other_distinguisher = header(1, PeerFlags::empty(), 1.0);
+
+        let router_addr = "192.0.2.1:11019".parse().unwrap();
+        let mut session = BmpSession::new(router_addr).with_withdrawal_synthesis();
+        let elems =
+            session.process_message(update(pre_policy, &["10.0.0.0/8", "10.1.0.0/16"], &[]));
+        assert_eq!(elems.len(), 2);
+        assert!(elems
+            .iter()
+            .all(|e| e.rib_side == Some(RibSide::AdjRibIn) && e.policy == Some(RibPolicy::Pre)));
+        session.process_message(update(pre_policy, &["192.0.2.0/24"], &["10.1.0.0/16"]));
+        assert_eq!(session.num_routes(peer_ip, peer_asn), 2);
+
+        // the post-policy RIB and the peer with another distinguisher are tracked separately
+        session.process_message(update(post_policy, &["10.1.0.0/16"], &[]));
+        session.process_message(update(other_distinguisher, &["10.2.0.0/16"], &[]));
+        // a withdrawal in one RIB does not remove the route from the other
+        session.process_message(update(post_policy, &[], &["10.0.0.0/8"]));
+        assert_eq!(session.num_routes(peer_ip, peer_asn), 4);
+
+        let withdrawals = session.process_message(peer_down(header(0, PeerFlags::empty(), 2.0)));
+        let withdrawn: Vec<(String, Option<RibPolicy>)> = withdrawals
+            .iter()
+            .map(|e| (e.prefix.to_string(), e.policy))
+            .collect();
+        assert_eq!(
+            withdrawn,
+            vec![
+                ("10.0.0.0/8".to_string(), Some(RibPolicy::Pre)),
+                ("192.0.2.0/24".to_string(), Some(RibPolicy::Pre)),
+                ("10.1.0.0/16".to_string(), Some(RibPolicy::Post)),
+            ]
+        );
+        assert!(withdrawals.iter().all(|e| e.elem_type == ElemType::WITHDRAW
+            && e.timestamp == 2.0
+            && e.rib_side == Some(RibSide::AdjRibIn)));
+        // the routes of the peer with another distinguisher are kept
+        assert_eq!(session.num_routes(peer_ip, peer_asn), 1);
+        assert!(session.process_message(peer_down(pre_policy)).is_empty());
+
+        let withdrawals = session.process_message(peer_down(other_distinguisher));
+        assert_eq!(withdrawals.len(), 1);
+        assert_eq!(session.num_routes(peer_ip, peer_asn), 0);
+
+        // routes are not tracked by default
+        let mut session = BmpSession::new(router_addr);
+        session.process_message(update(pre_policy, &["10.0.0.0/8"], &[]));
+        assert!(session.process_message(peer_down(pre_policy)).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+/*!
+MRT content census.
+
+[MrtCensus] scans MRT data and reports the distribution of MRT types and subtypes, along with the
+frequencies of BGP attribute types. For attribute types the parser does not decode (unknown,
+deprecated, or not yet supported), it also keeps a histogram of the attribute byte lengths. The
+census of multiple files can be merged, and with the `serde` feature the result can be serialized
+to JSON for aggregation across an archive.
+*/
+use crate::error::ParserError;
+use crate::models::*;
+use crate::parser::mrt::mrt_header::parse_common_header;
+use crate::parser::mrt::mrt_record::parse_mrt_body;
+use bytes::BytesMut;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// Census of MRT record types and BGP attribute types.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MrtCensus {
+    /// total number of MRT records scanned
+    pub records: u64,
+    /// number of records by MRT type and subtype
+    pub record_types: BTreeMap<u16, BTreeMap<u16, u64>>,
+    /// number of records failed to parse by MRT type and subtype
+    pub failed_record_types: BTreeMap<u16, BTreeMap<u16, u64>>,
+    /// number of BGP attributes by attribute type code
+    pub attr_types: BTreeMap<u8, u64>,
+    /// byte-length histograms of undecoded attributes by attribute type code
+    pub raw_attr_lengths: BTreeMap<u8, BTreeMap<usize, u64>>,
+}
+
+impl MrtCensus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan all MRT records from the reader.
+    ///
+    /// Records that fail to parse are counted in [MrtCensus::failed_record_types]. Errors that
+    /// prevent reading further records, like an invalid common header, are returned.
+    pub fn scan<R: Read>(&mut self, reader: &mut R) -> Result<(), ParserError> {
+        loop {
+            let common_header = match parse_common_header(reader) {
+                Ok(h) => h,
+                Err(ParserError::EofError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
+
+            let mut buffer = BytesMut::zeroed(common_header.length as usize);
+            reader.read_exact(&mut buffer)?;
+
+            let entry_type = common_header.entry_type as u16;
+            let entry_subtype = common_header.entry_subtype;
+            match parse_mrt_body(entry_type, entry_subtype, buffer.freeze()) {
+                Ok(message) => self.observe_record(&MrtRecord {
+                    common_header,
+                    message,
+                }),
+                Err(_) => {
+                    self.records += 1;
+                    *self
+                        .failed_record_types
+                        .entry(entry_type)
+                        .or_default()
+                        .entry(entry_subtype)
+                        .or_default() += 1;
+                }
+            }
+        }
+    }
+
+    /// Add a parsed MRT record to the census.
+    pub fn observe_record(&mut self, record: &MrtRecord) {
+        self.records += 1;
+        *self
+            .record_types
+            .entry(record.common_header.entry_type as u16)
+            .or_default()
+            .entry(record.common_header.entry_subtype)
+            .or_default() += 1;
+
+        match &record.message {
+            MrtMessage::TableDumpMessage(msg) => self.observe_attributes(&msg.attributes),
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(entries)) => {
+                for entry in &entries.rib_entries {
+                    self.observe_attributes(&entry.attributes);
+                }
+            }
+            MrtMessage::TableDumpV2Message(_) => {}
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => {
+                if let BgpMessage::Update(update) = &msg.bgp_message {
+                    self.observe_attributes(&update.attributes);
+                }
+            }
+            MrtMessage::Bgp4Mp(_) => {}
+        }
+    }
+
+    /// Add a set of BGP attributes to the census.
+    pub fn observe_attributes(&mut self, attributes: &Attributes) {
+        for attr in attributes.iter() {
+            let attr_type = u8::from(attr.attr_type());
+            *self.attr_types.entry(attr_type).or_default() += 1;
+            if let AttributeValue::Unknown(raw) | AttributeValue::Deprecated(raw) = attr {
+                *self
+                    .raw_attr_lengths
+                    .entry(attr_type)
+                    .or_default()
+                    .entry(raw.bytes.len())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// Merge the census of another scan into this one.
+    pub fn merge(&mut self, other: &MrtCensus) {
+        self.records += other.records;
+        merge_nested(&mut self.record_types, &other.record_types);
+        merge_nested(&mut self.failed_record_types, &other.failed_record_types);
+        for (attr_type, count) in &other.attr_types {
+            *self.attr_types.entry(*attr_type).or_default() += count;
+        }
+        merge_nested(&mut self.raw_attr_lengths, &other.raw_attr_lengths);
+    }
+}
+
+fn merge_nested<K: Ord + Copy, V: Ord + Copy>(
+    target: &mut BTreeMap<K, BTreeMap<V, u64>>,
+    source: &BTreeMap<K, BTreeMap<V, u64>>,
+) {
+    for (key, inner) in source {
+        let target_inner = target.entry(*key).or_default();
+        for (inner_key, count) in inner {
+            *target_inner.entry(*inner_key).or_default() += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_census() {
+        let mut attributes = Attributes::default();
+        attributes.add_attr(Attribute::from(AttributeValue::Origin(Origin::IGP)));
+        attributes.add_attr(Attribute::from(AttributeValue::Unknown(AttrRaw {
+            attr_type: AttrType::Unknown(99),
+            bytes: vec![1, 2, 3],
+        })));
+        let message = MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+            msg_type: Bgp4MpType::MessageAs4,
+            peer_asn: Asn::new_32bit(65000),
+            local_asn: Asn::new_32bit(65001),
+            interface_index: 0,
+            peer_ip: "10.0.0.1".parse().unwrap(),
+            local_ip: "10.0.0.2".parse().unwrap(),
+            bgp_message: BgpMessage::Update(BgpUpdateMessage {
+                withdrawn_prefixes: vec![],
+                attributes,
+                announced_prefixes: vec![],
+            }),
+        }));
+        let subtype = Bgp4MpType::MessageAs4 as u16;
+        let body = message.encode(subtype);
+        let header = CommonHeader {
+            timestamp: 0,
+            microsecond_timestamp: None,
+            entry_type: EntryType::BGP4MP,
+            entry_subtype: subtype,
+            length: body.len() as u32,
+        };
+
+        let mut bytes = header.encode().to_vec();
+        bytes.extend(body);
+        // append a record with unsupported TABLE_DUMP_V2 RIB_GENERIC subtype
+        bytes.extend([0, 0, 0, 0, 0, 13, 0, 6, 0, 0, 0, 0]);
+
+        let mut census = MrtCensus::new();
+        census.scan(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(census.records, 2);
+        assert_eq!(census.record_types[&16][&4], 1);
+        assert_eq!(census.failed_record_types[&13][&6], 1);
+        assert_eq!(census.attr_types[&1], 1);
+        assert_eq!(census.attr_types[&99], 1);
+        assert_eq!(census.raw_attr_lengths[&99][&3], 1);
+
+        let mut merged = census.clone();
+        merged.merge(&census);
+        assert_eq!(merged.records, 4);
+        assert_eq!(merged.raw_attr_lengths[&99][&3], 2);
+    }
+}
@@ -0,0 +1,259 @@
+/*!
+Semantic comparison of MRT files.
+
+[compare_mrt_files] reports the records present in one file but not the other. Records are
+compared by their parsed content rather than their bytes, so encoding differences such as length
+fields, the order of BGP attributes, or the extended length flag of attributes do not count as
+differences. This is useful to validate the output of the MRT encoder against the original file,
+or to check that mirrored archives hold the same data.
+
+```no_run
+use bgpkit_parser::compare_mrt_files;
+
+let report = compare_mrt_files("updates.a.bz2", "updates.b.bz2", 0.0).unwrap();
+println!(
+    "{} matched, {} only in a, {} only in b",
+    report.matched,
+    report.only_in_a.len(),
+    report.only_in_b.len()
+);
+```
+*/
+use crate::models::*;
+#[cfg(feature = "oneio")]
+use crate::{BgpkitParser, ParserErrorWithBytes};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Result of comparing two sequences of MRT records.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MrtComparison {
+    /// number of records read from the first input
+    pub records_a: usize,
+    /// number of records read from the second input
+    pub records_b: usize,
+    /// number of records present in both inputs
+    pub matched: usize,
+    /// records of the first input without an equivalent in the second, in input order
+    pub only_in_a: Vec<MrtRecord>,
+    /// records of the second input without an equivalent in the first, in input order
+    pub only_in_b: Vec<MrtRecord>,
+}
+
+impl MrtComparison {
+    /// Whether every record has an equivalent in the other input.
+    pub fn is_equivalent(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty()
+    }
+}
+
+/// Compare the MRT records of two files. See [compare_mrt_records].
+#[cfg(feature = "oneio")]
+pub fn compare_mrt_files(
+    a: &str,
+    b: &str,
+    tolerance: f64,
+) -> Result<MrtComparison, ParserErrorWithBytes> {
+    let records_a = BgpkitParser::new(a)?.into_record_iter();
+    let records_b = BgpkitParser::new(b)?.into_record_iter();
+    Ok(compare_mrt_records(records_a, records_b, tolerance))
+}
+
+/// Compare two sequences of MRT records.
+///
+/// Each record is matched with at most one equivalent record of the other input, see
+/// [records_equivalent]. Records do not need to be in the same order. All records of `b` are
+/// kept in memory while `a` is read.
+pub fn compare_mrt_records<A, B>(a: A, b: B, tolerance: f64) -> MrtComparison
+where
+    A: IntoIterator<Item = MrtRecord>,
+    B: IntoIterator<Item = MrtRecord>,
+{
+    let mut comparison = MrtComparison::default();
+
+    // unmatched records of b by content hash, as (input index, normalized, original)
+    let mut pending: HashMap<u64, Vec<(usize, MrtRecord, MrtRecord)>> = HashMap::new();
+    for (index, record) in b.into_iter().enumerate() {
+        let normalized = normalize_record(&record);
+        pending
+            .entry(content_hash(&normalized))
+            .or_default()
+            .push((index, normalized, record));
+        comparison.records_b += 1;
+    }
+
+    for record in a {
+        comparison.records_a += 1;
+        let normalized = normalize_record(&record);
+        let candidates = pending.entry(content_hash(&normalized)).or_default();
+        match candidates
+            .iter()
+            .position(|(_, other, _)| normalized_equivalent(&normalized, other, tolerance))
+        {
+            Some(pos) => {
+                candidates.remove(pos);
+                comparison.matched += 1;
+            }
+            None => comparison.only_in_a.push(record),
+        }
+    }
+
+    let mut only_in_b: Vec<_> = pending.into_values().flatten().collect();
+    only_in_b.sort_by_key(|(index, _, _)| *index);
+    comparison.only_in_b = only_in_b.into_iter().map(|(_, _, record)| record).collect();
+
+    comparison
+}
+
+/// Whether two records carry the same content.
+///
+/// The timestamps, including microseconds, may differ by up to `tolerance` seconds. MRT types and
+/// subtypes must be equal. Length fields, the order of BGP attributes and the extended length
+/// flag of attributes are ignored.
+pub fn records_equivalent(a: &MrtRecord, b: &MrtRecord, tolerance: f64) -> bool {
+    normalized_equivalent(&normalize_record(a), &normalize_record(b), tolerance)
+}
+
+fn normalized_equivalent(a: &MrtRecord, b: &MrtRecord, tolerance: f64) -> bool {
+    a.common_header.entry_type == b.common_header.entry_type
+        && a.common_header.entry_subtype == b.common_header.entry_subtype
+        && (record_timestamp(a) - record_timestamp(b)).abs() <= tolerance
+        && a.message == b.message
+}
+
+fn record_timestamp(record: &MrtRecord) -> f64 {
+    let header = &record.common_header;
+    header.timestamp as f64 + header.microsecond_timestamp.unwrap_or(0) as f64 / 1_000_000.0
+}
+
+/// Hash of the parts of a normalized record that must be equal for records to be equivalent.
+fn content_hash(record: &MrtRecord) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (record.common_header.entry_type as u16).hash(&mut hasher);
+    record.common_header.entry_subtype.hash(&mut hasher);
+    // peer index tables hold hash maps, whose debug output has no stable order
+    if !matches!(
+        record.message,
+        MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(_))
+    ) {
+        let _ = std::fmt::Write::write_fmt(
+            &mut HashWriter(&mut hasher),
+            format_args!("{:?}", record.message),
+        );
+    }
+    hasher.finish()
+}
+
+/// Feeds formatted output into a hasher without allocating.
+struct HashWriter<'a, H>(&'a mut H);
+
+impl<H: Hasher> std::fmt::Write for HashWriter<'_, H> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Clear the header length and put the attributes of the record in a canonical form.
+fn normalize_record(record: &MrtRecord) -> MrtRecord {
+    let mut record = record.clone();
+    record.common_header.length = 0;
+    match &mut record.message {
+        MrtMessage::TableDumpMessage(m) => normalize_attributes(&mut m.attributes),
+        MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(m)) => {
+            for entry in &mut m.rib_entries {
+                normalize_attributes(&mut entry.attributes);
+            }
+        }
+        MrtMessage::TableDumpV2Message(TableDumpV2Message::RibGeneric(m)) => {
+            for entry in &mut m.rib_entries {
+                normalize_attributes(&mut entry.attributes);
+            }
+        }
+        MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(m)) => {
+            if let BgpMessage::Update(update) = &mut m.bgp_message {
+                normalize_attributes(&mut update.attributes);
+            }
+        }
+        _ => {}
+    }
+    record
+}
+
+fn normalize_attributes(attributes: &mut Attributes) {
+    for attr in &mut attributes.inner {
+        attr.flag.remove(AttrFlags::EXTENDED);
+    }
+    attributes
+        .inner
+        .sort_by_key(|attr| u8::from(attr.value.attr_type()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::str::FromStr;
+
+    fn update(timestamp: u32, prefix: &str, attrs: Vec<AttributeValue>) -> MrtRecord {
+        MrtRecord {
+            common_header: CommonHeader {
+                timestamp,
+                microsecond_timestamp: None,
+                entry_type: EntryType::BGP4MP,
+                entry_subtype: Bgp4MpType::MessageAs4 as u16,
+                length: 0,
+            },
+            message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                msg_type: Bgp4MpType::MessageAs4,
+                peer_asn: Asn::new_32bit(64496),
+                local_asn: Asn::new_32bit(64497),
+                interface_index: 0,
+                peer_ip: Ipv4Addr::new(192, 0, 2, 1).into(),
+                local_ip: Ipv4Addr::new(192, 0, 2, 2).into(),
+                bgp_message: BgpMessage::Update(BgpUpdateMessage {
+                    withdrawn_prefixes: vec![],
+                    attributes: Attributes::from_iter(attrs),
+                    announced_prefixes: vec![NetworkPrefix::from_str(prefix).unwrap()],
+                }),
+            })),
+        }
+    }
+
+    #[test]
+    fn test_records_equivalent() {
+        let origin = AttributeValue::Origin(Origin::IGP);
+        let next_hop = AttributeValue::NextHop(Ipv4Addr::new(192, 0, 2, 1).into());
+
+        let a = update(10, "10.0.0.0/8", vec![origin.clone(), next_hop.clone()]);
+        let mut b = update(10, "10.0.0.0/8", vec![next_hop, origin]);
+        b.common_header.length = 100;
+        assert!(records_equivalent(&a, &b, 0.0));
+
+        b.common_header.timestamp = 11;
+        assert!(!records_equivalent(&a, &b, 0.0));
+        assert!(records_equivalent(&a, &b, 1.0));
+    }
+
+    #[test]
+    fn test_compare_mrt_records() {
+        let a = vec![
+            update(1, "10.0.0.0/8", vec![]),
+            update(2, "10.1.0.0/16", vec![]),
+            update(2, "10.1.0.0/16", vec![]),
+        ];
+        let b = vec![
+            update(2, "10.1.0.0/16", vec![]),
+            update(1, "10.0.0.0/8", vec![]),
+            update(3, "10.2.0.0/16", vec![]),
+        ];
+        let comparison = compare_mrt_records(a, b, 0.0);
+        assert_eq!(comparison.records_a, 3);
+        assert_eq!(comparison.records_b, 3);
+        assert_eq!(comparison.matched, 2);
+        assert_eq!(comparison.only_in_a, vec![update(2, "10.1.0.0/16", vec![])]);
+        assert_eq!(comparison.only_in_b, vec![update(3, "10.2.0.0/16", vec![])]);
+        assert!(!comparison.is_equivalent());
+    }
+}
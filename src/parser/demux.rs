@@ -0,0 +1,133 @@
+/*!
+Demultiplex a stream of BGP elems into per-peer sub-streams in one pass.
+
+Each peer gets its own [PeerStream] backed by a bounded channel, so analyses that process peers
+independently can run concurrently without parsing the input once per peer or collecting all elems
+in memory. Elems of each peer are delivered in their original order.
+
+```no_run
+use bgpkit_parser::BgpkitParser;
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+std::thread::scope(|s| {
+    parser.into_elem_iter().demux_by_peer(1024, |peer, stream| {
+        s.spawn(move || {
+            let count = stream.count();
+            println!("{} AS{}: {} elems", peer.peer_ip, peer.peer_asn, count);
+        });
+    });
+});
+```
+*/
+use crate::models::{Asn, BgpElem};
+use crate::parser::iters::{ElemIterator, ErrorSummary};
+use crate::parser::MrtRecordSource;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+/// Identifies a BGP peer of the demultiplexed stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerKey {
+    pub peer_ip: IpAddr,
+    pub peer_asn: Asn,
+}
+
+/// Ordered stream of elems from a single peer.
+///
+/// The stream ends once the whole input has been demultiplexed.
+pub struct PeerStream {
+    receiver: Receiver<BgpElem>,
+}
+
+impl Iterator for PeerStream {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Split elems into per-peer streams.
+///
+/// `on_new_peer` is called with a new [PeerStream] the first time a peer is seen. Each stream
+/// buffers up to `buffer_size` elems; when a buffer is full, demultiplexing blocks until the stream
+/// is consumed. The streams therefore must be consumed concurrently, e.g. by spawning a thread in
+/// `on_new_peer`. Dropping a stream discards the remaining elems of that peer.
+pub fn demux_by_peer<I, F>(elems: I, buffer_size: usize, mut on_new_peer: F)
+where
+    I: IntoIterator<Item = BgpElem>,
+    F: FnMut(PeerKey, PeerStream),
+{
+    let mut senders: HashMap<PeerKey, Option<SyncSender<BgpElem>>> = HashMap::new();
+    for elem in elems {
+        let key = PeerKey {
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+        };
+        let sender = senders.entry(key).or_insert_with(|| {
+            let (sender, receiver) = sync_channel(buffer_size);
+            on_new_peer(key, PeerStream { receiver });
+            Some(sender)
+        });
+        if let Some(s) = sender {
+            if s.send(elem).is_err() {
+                // the stream has been dropped, stop sending to it
+                *sender = None;
+            }
+        }
+    }
+    // dropping the senders ends all streams
+}
+
+impl<R: MrtRecordSource> ElemIterator<R> {
+    /// Split the elems into per-peer streams in one pass. See [demux_by_peer] for details.
+    ///
+    /// Returns the error summary of the parse once all elems have been dispatched.
+    pub fn demux_by_peer<F>(mut self, buffer_size: usize, on_new_peer: F) -> ErrorSummary
+    where
+        F: FnMut(PeerKey, PeerStream),
+    {
+        demux_by_peer(self.by_ref(), buffer_size, on_new_peer);
+        self.error_summary().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_demux_by_peer() {
+        let elems: Vec<BgpElem> = (0..100)
+            .map(|i| BgpElem {
+                timestamp: i as f64,
+                peer_ip: IpAddr::from([10, 0, 0, (i % 3) as u8]),
+                peer_asn: Asn::new_32bit(65000 + i % 3),
+                ..Default::default()
+            })
+            .collect();
+
+        let (result_tx, result_rx) = channel();
+        std::thread::scope(|s| {
+            demux_by_peer(elems, 2, |peer, stream| {
+                let result_tx = result_tx.clone();
+                s.spawn(move || {
+                    let timestamps: Vec<f64> = stream.map(|e| e.timestamp).collect();
+                    result_tx.send((peer, timestamps)).unwrap();
+                });
+            });
+        });
+        drop(result_tx);
+
+        let mut results: Vec<(PeerKey, Vec<f64>)> = result_rx.iter().collect();
+        results.sort_by_key(|(peer, _)| peer.peer_ip);
+        assert_eq!(results.len(), 3);
+        for (i, (peer, timestamps)) in results.iter().enumerate() {
+            assert_eq!(peer.peer_asn, Asn::new_32bit(65000 + i as u32));
+            let expected: Vec<f64> = (0..100).filter(|t| t % 3 == i).map(|t| t as f64).collect();
+            assert_eq!(timestamps, &expected);
+        }
+    }
+}
@@ -0,0 +1,349 @@
+/*!
+Resumable downloads of remote MRT files.
+
+A connection dropping in the middle of a multi-GB RIB dump makes [BgpkitParser::new] fail, and
+the download has to start over. [BgpkitParser::new_resumable] downloads remote files with a
+[ResumableReader] instead: when reading fails, it waits according to its [RetryPolicy] and
+requests the rest of the file with an HTTP range request, starting at the first byte not yet
+read. Decompression and parsing continue as if nothing happened.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, RetryPolicy};
+
+let policy = RetryPolicy {
+    max_retries: 10,
+    ..Default::default()
+};
+let parser = BgpkitParser::new_resumable(
+    "http://archive.routeviews.org/bgpdata/2021.10/RIBS/rib.20211001.0000.bz2",
+    policy,
+)
+.unwrap();
+for elem in parser {
+    println!("{}", elem);
+}
+```
+
+Resumed downloads support gzip and bzip2 compressed files; other files are opened with
+[BgpkitParser::new].
+*/
+use crate::error::ParserError;
+use crate::parser::{BgpkitParser, ParserErrorWithBytes};
+use log::warn;
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+/// How often and how long to wait before resuming a failed download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// maximum number of consecutive failed attempts before giving up
+    pub max_retries: u32,
+    /// delay before the first retry, doubled for each consecutive retry
+    pub initial_backoff: Duration,
+    /// maximum delay between two retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given consecutive retry, starting from 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+type Opener = Box<dyn FnMut(u64) -> std::io::Result<Box<dyn Read + Send>> + Send>;
+
+/// Reader reopening its input at the current position when reading fails.
+///
+/// The input is opened by a function returning a reader of the data from the given byte offset
+/// on, e.g. with an HTTP range request. Reads failing after the maximum number of consecutive
+/// retries return the last error.
+pub struct ResumableReader {
+    open: Opener,
+    reader: Option<Box<dyn Read + Send>>,
+    offset: u64,
+    retries: u32,
+    policy: RetryPolicy,
+}
+
+impl ResumableReader {
+    pub fn new<F>(open: F, policy: RetryPolicy) -> Self
+    where
+        F: FnMut(u64) -> std::io::Result<Box<dyn Read + Send>> + Send + 'static,
+    {
+        ResumableReader {
+            open: Box::new(open),
+            reader: None,
+            offset: 0,
+            retries: 0,
+            policy,
+        }
+    }
+
+    /// Number of bytes read so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl Read for ResumableReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let result = match &mut self.reader {
+                Some(reader) => reader.read(buf),
+                None => match (self.open)(self.offset) {
+                    Ok(reader) => {
+                        self.reader = Some(reader);
+                        continue;
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            match result {
+                Ok(n) => {
+                    if n > 0 {
+                        self.retries = 0;
+                    }
+                    self.offset += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    if self.retries >= self.policy.max_retries {
+                        return Err(e);
+                    }
+                    self.retries += 1;
+                    let backoff = self.policy.backoff(self.retries);
+                    warn!(
+                        "download failed at byte {}: {}; retrying in {:?}",
+                        self.offset, e, backoff
+                    );
+                    std::thread::sleep(backoff);
+                    self.reader = None;
+                }
+            }
+        }
+    }
+}
+
+/// Reader failing with [ErrorKind::UnexpectedEof] if its input ends before the expected length,
+/// e.g. when the server closes the connection early.
+struct ExactLength<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for ExactLength<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.inner.read(buf)?;
+        if n == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("connection closed with {} bytes left", self.remaining),
+            ));
+        }
+        self.remaining = self.remaining.saturating_sub(n as u64);
+        Ok(n)
+    }
+}
+
+fn to_io_error(e: reqwest::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+/// Opener of the remote file at `url` from a byte offset on, with HTTP range requests.
+fn http_range_opener(url: &str) -> Result<Opener, ParserError> {
+    let client = reqwest::blocking::Client::builder()
+        // the body of a large file takes longer than any sensible total timeout
+        .timeout(None)
+        .connect_timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| ParserError::IoError(to_io_error(e)))?;
+    let url = url.to_string();
+    Ok(Box::new(
+        move |offset: u64| -> std::io::Result<Box<dyn Read + Send>> {
+            let mut request = client.get(&url);
+            if offset > 0 {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+            }
+            let response = request
+                .send()
+                .and_then(|r| r.error_for_status())
+                .map_err(to_io_error)?;
+            let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+            let mut length = response.content_length();
+            let mut reader: Box<dyn Read + Send> = Box::new(response);
+            if offset > 0 && !partial {
+                // the server ignored the range, skip the bytes read already
+                let skipped =
+                    std::io::copy(&mut reader.by_ref().take(offset), &mut std::io::sink())?;
+                if skipped < offset {
+                    return Err(std::io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("{} is shorter than {} bytes", url, offset),
+                    ));
+                }
+                length = length.map(|l| l.saturating_sub(offset));
+            }
+            Ok(match length {
+                Some(remaining) => Box::new(ExactLength {
+                    inner: reader,
+                    remaining,
+                }),
+                None => reader,
+            })
+        },
+    ))
+}
+
+impl BgpkitParser<Box<dyn Read + Send>> {
+    /// Creating a new parser of a remote file whose download resumes where it failed, see
+    /// [download](crate::parser::download).
+    ///
+    /// Local files, and remote files compressed with other algorithms than gzip and bzip2, are
+    /// opened with [BgpkitParser::new].
+    pub fn new_resumable(path: &str, policy: RetryPolicy) -> Result<Self, ParserErrorWithBytes> {
+        if !(path.starts_with("http://") || path.starts_with("https://")) {
+            return BgpkitParser::new(path);
+        }
+        let raw = ResumableReader::new(http_range_opener(path)?, policy);
+        let reader: Box<dyn Read + Send> =
+            match path.rsplit('/').next().unwrap_or(path).rsplit('.').next() {
+                Some("gz" | "gzip") => Box::new(flate2::read::MultiGzDecoder::new(raw)),
+                Some("bz2" | "bz") => Box::new(bzip2::read::MultiBzDecoder::new(raw)),
+                Some("xz" | "lzma" | "lz4" | "lz" | "zst" | "zstd") => {
+                    return BgpkitParser::new(path)
+                }
+                _ => Box::new(raw),
+            };
+        Ok(BgpkitParser::from_reader(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                ErrorKind::ConnectionReset,
+                "connection reset",
+            ))
+        }
+    }
+
+    fn no_backoff(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+        };
+        let delays: Vec<u64> = (1..=6).map(|i| policy.backoff(i).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+    }
+
+    #[test]
+    fn test_resume() {
+        let data: Vec<u8> = (0..100).collect();
+        let opens = Arc::new(AtomicUsize::new(0));
+        let opener = {
+            let data = data.clone();
+            let opens = opens.clone();
+            move |offset: u64| -> std::io::Result<Box<dyn Read + Send>> {
+                // every connection fails after 30 bytes, and every other connection attempt fails
+                let attempt = opens.fetch_add(1, Ordering::SeqCst);
+                if attempt % 2 == 1 {
+                    return Err(std::io::Error::other("connection refused"));
+                }
+                let start = offset as usize;
+                let end = (start + 30).min(data.len());
+                let chunk = Cursor::new(data[start..end].to_vec());
+                Ok(match end == data.len() {
+                    true => Box::new(chunk),
+                    false => Box::new(chunk.chain(FailingReader)),
+                })
+            }
+        };
+
+        let mut reader = ResumableReader::new(opener, no_backoff(2));
+        let mut output = vec![];
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, data);
+        assert_eq!(reader.offset(), 100);
+        assert_eq!(opens.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn test_max_retries() {
+        let mut reader = ResumableReader::new(
+            |_offset| -> std::io::Result<Box<dyn Read + Send>> {
+                Ok(Box::new(Cursor::new(vec![1, 2, 3]).chain(FailingReader)))
+            },
+            no_backoff(2),
+        );
+        // every retry reads 3 more bytes, so the consecutive failures never reach the maximum
+        let mut buf = [0; 8];
+        for _ in 0..5 {
+            assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        }
+        assert_eq!(reader.offset(), 15);
+
+        let mut reader = ResumableReader::new(
+            |_offset| -> std::io::Result<Box<dyn Read + Send>> { Ok(Box::new(FailingReader)) },
+            no_backoff(2),
+        );
+        let error = reader.read(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConnectionReset);
+    }
+
+    #[test]
+    fn test_exact_length() {
+        let mut reader = ExactLength {
+            inner: Cursor::new(vec![1, 2, 3]),
+            remaining: 5,
+        };
+        let mut output = vec![];
+        let error = reader.read_to_end(&mut output).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(output, vec![1, 2, 3]);
+
+        let mut reader = ExactLength {
+            inner: Cursor::new(vec![1, 2, 3]),
+            remaining: 3,
+        };
+        let mut output = vec![];
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,191 @@
+/*!
+Detect duplicate announcements in a stream of BGP elems.
+
+A duplicate announcement re-announces a prefix with the same attributes as the last announcement
+of the same peer for the same prefix, without a withdrawal in between. Such announcements do not
+change the route, but are frequent in update dumps, e.g. after session resets or because of
+changes of attributes that are not propagated. [DuplicateTracker] keeps the last announcement of
+every peer for every prefix to detect them, and the iterator adapters either flag or drop them:
+
+```no_run
+use bgpkit_parser::{flag_duplicates, suppress_duplicates, BgpkitParser};
+
+let parser = BgpkitParser::new("updates.20240101.0000.bz2").unwrap();
+let duplicates = flag_duplicates(parser).filter(|(_, duplicate)| *duplicate).count();
+println!("{} duplicate announcements", duplicates);
+
+let parser = BgpkitParser::new("updates.20240101.0000.bz2").unwrap();
+for elem in suppress_duplicates(parser) {
+    println!("{}", elem);
+}
+```
+
+Attributes are compared with [changed_attributes], so the timestamps of the announcements do not
+matter. The last announcement of each peer and prefix is kept in memory, like a RIB of all peers.
+*/
+use crate::models::{BgpElem, ElemType, NetworkPrefix};
+use crate::parser::analysis::rib_diff::changed_attributes;
+use crate::parser::demux::PeerKey;
+use std::collections::HashMap;
+
+/// Last announcement of every peer for every prefix, to detect duplicate announcements.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateTracker {
+    routes: HashMap<(PeerKey, NetworkPrefix), BgpElem>,
+    duplicates: u64,
+}
+
+impl DuplicateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track an elem, returning true if it is a duplicate announcement.
+    ///
+    /// Withdrawals are never duplicates, and forget the last announcement of their peer for
+    /// their prefix.
+    pub fn observe(&mut self, elem: &BgpElem) -> bool {
+        let peer = PeerKey {
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+        };
+        let key = (peer, elem.prefix);
+        match elem.elem_type {
+            ElemType::WITHDRAW => {
+                self.routes.remove(&key);
+                false
+            }
+            ElemType::ANNOUNCE => {
+                let duplicate = match self.routes.get(&key) {
+                    Some(last) => changed_attributes(last, elem).is_empty(),
+                    None => false,
+                };
+                match duplicate {
+                    true => self.duplicates += 1,
+                    false => {
+                        self.routes.insert(key, elem.clone());
+                    }
+                }
+                duplicate
+            }
+        }
+    }
+
+    /// Number of duplicate announcements seen so far.
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// Number of peer and prefix pairs with an announcement tracked.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// Pair each elem with whether it is a duplicate announcement.
+pub fn flag_duplicates<I: IntoIterator<Item = BgpElem>>(
+    elems: I,
+) -> FlaggedDuplicates<I::IntoIter> {
+    FlaggedDuplicates {
+        elems: elems.into_iter(),
+        tracker: DuplicateTracker::new(),
+    }
+}
+
+/// Drop duplicate announcements.
+pub fn suppress_duplicates<I: IntoIterator<Item = BgpElem>>(
+    elems: I,
+) -> SuppressedDuplicates<I::IntoIter> {
+    SuppressedDuplicates {
+        elems: elems.into_iter(),
+        tracker: DuplicateTracker::new(),
+    }
+}
+
+/// Iterator returned by [flag_duplicates], yielding each elem with true for duplicate
+/// announcements.
+pub struct FlaggedDuplicates<I> {
+    elems: I,
+    tracker: DuplicateTracker,
+}
+
+impl<I> FlaggedDuplicates<I> {
+    pub fn tracker(&self) -> &DuplicateTracker {
+        &self.tracker
+    }
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for FlaggedDuplicates<I> {
+    type Item = (BgpElem, bool);
+
+    fn next(&mut self) -> Option<(BgpElem, bool)> {
+        let elem = self.elems.next()?;
+        let duplicate = self.tracker.observe(&elem);
+        Some((elem, duplicate))
+    }
+}
+
+/// Iterator returned by [suppress_duplicates], skipping duplicate announcements.
+pub struct SuppressedDuplicates<I> {
+    elems: I,
+    tracker: DuplicateTracker,
+}
+
+impl<I> SuppressedDuplicates<I> {
+    /// The tracker, e.g. to get the number of announcements suppressed so far.
+    pub fn tracker(&self) -> &DuplicateTracker {
+        &self.tracker
+    }
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for SuppressedDuplicates<I> {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        let tracker = &mut self.tracker;
+        self.elems.find(|elem| !tracker.observe(elem))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_elems::{announce, withdraw};
+
+    fn elems() -> Vec<BgpElem> {
+        vec![
+            announce("192.0.2.1", "10.0.0.0/8", &[1, 2]).at(1.0),
+            // duplicate, at a later time
+            announce("192.0.2.1", "10.0.0.0/8", &[1, 2]).at(2.0),
+            // same route from another peer
+            announce("192.0.2.2", "10.0.0.0/8", &[1, 2]).at(3.0),
+            announce("192.0.2.1", "10.0.0.0/8", &[1, 3, 2]).at(4.0),
+            // duplicate of the changed route
+            announce("192.0.2.1", "10.0.0.0/8", &[1, 3, 2]).at(5.0),
+            withdraw("192.0.2.1", "10.0.0.0/8").at(6.0),
+            // announcement after a withdrawal
+            announce("192.0.2.1", "10.0.0.0/8", &[1, 3, 2]).at(7.0),
+        ]
+    }
+
+    #[test]
+    fn test_flag_duplicates() {
+        let mut iter = flag_duplicates(elems());
+        let flags: Vec<bool> = iter.by_ref().map(|(_, duplicate)| duplicate).collect();
+        assert_eq!(flags, vec![false, true, false, false, true, false, false]);
+        assert_eq!(iter.tracker().duplicates(), 2);
+        assert_eq!(iter.tracker().len(), 2);
+    }
+
+    #[test]
+    fn test_suppress_duplicates() {
+        let mut iter = suppress_duplicates(elems());
+        let timestamps: Vec<f64> = iter.by_ref().map(|e| e.timestamp).collect();
+        assert_eq!(timestamps, vec![1.0, 3.0, 4.0, 6.0, 7.0]);
+        assert_eq!(iter.tracker().duplicates(), 2);
+    }
+}
@@ -6,14 +6,47 @@ the filtering mechanism for [BgpElem].
 
 The available filters are:
 - `origin_asn` -- origin AS number
+- `origin_asns` -- comma-separated origin AS numbers
+- `origin_asn_list` -- path to a file of origin AS numbers, one per line
 - `prefix` -- network prefix and match type
+- `prefixes` -- comma-separated prefixes and match type
+- `prefix_list` -- path to a file of prefixes, one per line, and match type
 - `peer_ip` -- peer's IP address
 - `peer_ips` -- peers' IP addresses
 - `peer_asn` -- peer's IP address
+- `next_hop` -- next hop IP address or prefix containing it
 - `type` -- message type (`withdraw` or `announce`)
-- `ts_start` -- start and end unix timestamp
+- `ts_start` -- start and end unix timestamp, as (fractional) unix seconds or an RFC 3339 string
 - `as_path` -- regular expression for AS path string
+- `as_path_contains` -- ASN or comma-separated ASNs, any of which appears in the AS path
+- `community`, `large_community`, `extended_community` -- community pattern, e.g. `13335:*`
+- `community_regex` -- regular expression for community string
 - `ip_version` -- IP version (`ipv4` or `ipv6`)
+- `safi` -- SAFI of the prefix (`unicast`, `multicast` or `unicast_multicast`)
+- `bogons` -- whether the prefix is a bogon (`true` or `false`)
+- `rib_side` -- RIB of BMP routes (`adj-rib-in`, `adj-rib-out` or `loc-rib`)
+- `policy` -- policy stage of BMP routes (`pre` or `post`)
+
+Any filter type can be prefixed with `exclude_`, e.g. `exclude_peer_asn` or
+`exclude_prefix_super_sub`, to keep only the elems that do *not* match the filter.
+
+### Filter configuration
+
+With the `serde` feature, a [Filter] is serialized as its filter type and value, e.g.
+`{"filter_type": "peer_asn", "value": "13335"}`, and validated with [Filter::new] when
+deserialized. [Filter::type_and_value] returns the pair for any filter, so filters created in code
+can be stored as well. `BgpkitParser::add_filters_from_json` and `add_filters_from_toml` load a list
+of filters, e.g. from a TOML file:
+
+```toml
+[[filters]]
+filter_type = "peer_asn"
+value = "13335"
+
+[[filters]]
+filter_type = "exclude_prefix_super_sub"
+value = "10.0.0.0/8"
+```
 
 [Filter::new] function takes a `str` as the filter type and `str` as the filter value and returns a
 Result of a [Filter] or a parsing error.
@@ -46,10 +79,23 @@ Note, by default, the prefix filtering is for the exact prefix. You can include
 sub-prefixes when fitlering by using `"prefix_super"`, `"prefix_sub"`, or  `"prefix_super_sub"` as
 the filter type string.
 
-### Note
+### Record filters
+
+[RecordFilter]s select MRT records by their common header only (entry type, subtype and
+timestamp). They are added with `add_record_filter` and evaluated before the record body is parsed,
+so the bodies of non-matching records are skipped without being parsed, which makes
+timestamp-bounded scans of large update archives much faster.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, RecordFilter};
 
-Currently, only [BgpElem] implements the filtering capability. Support for [MrtRecord] will come in
-later releases.
+let parser = BgpkitParser::new("http://archive.routeviews.org/bgpdata/2021.10/UPDATES/updates.20211001.0000.bz2").unwrap()
+    .add_record_filter(RecordFilter::TsStart(1633046700.0))
+    .add_record_filter(RecordFilter::TsEnd(1633046760.0));
+for elem in parser {
+    println!("{}", elem);
+}
+```
 
 */
 use crate::models::*;
@@ -57,6 +103,8 @@ use crate::parser::ComparableRegex;
 use crate::ParserError;
 use crate::ParserError::FilterError;
 use ipnet::IpNet;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
 use std::net::IpAddr;
 use std::str::FromStr;
 
@@ -64,28 +112,165 @@ use std::str::FromStr;
 ///
 /// The available filters are (`filter_type` (`FilterType`) -- definition):
 /// - `origin_asn` (`OriginAsn(u32)`) -- origin AS number
+/// - `origin_asns` (`OriginAsnSet(HashSet<Asn>)`) -- comma-separated AS numbers, matching elems
+///   originated by any of them
+/// - `origin_asn_list` (`OriginAsnSet(HashSet<Asn>)`) -- path to a file with one AS number per
+///   line (`#` starts a comment), matching elems originated by any of them
 /// - `prefix(_super, _sub, _super_sub)` (`Prefix(IpNet, PrefixMatchType)`) -- network prefix and match type
+/// - `prefixes(_super, _sub, _super_sub)` (`PrefixSet(PrefixSet, PrefixMatchType)`) --
+///   comma-separated prefixes, matched against all of them at once
+/// - `prefix_list(_super, _sub, _super_sub)` (`PrefixSet(PrefixSet, PrefixMatchType)`) -- path to a
+///   file with one prefix per line (`#` starts a comment), matched against all of them at once
 /// - `peer_ip` (`PeerIp(IpAddr)`) -- peer's IP address
 /// - `peer_ips` (`Vec<PeerIp(IpAddr)>`) -- peers' IP addresses
 /// - `peer_asn` (`PeerAsn(u32)`) -- peer's IP address
+/// - `next_hop` (`NextHop(IpNet)`) -- next hop IP address, or prefix such as `2001:db8::/32`
+///   containing it; matches the next hop of the MP_REACH_NLRI attribute for multiprotocol routes
 /// - `type` (`Type(ElemType)`) -- message type (`withdraw` or `announce`)
-/// - `ts_start` (`TsStart(f64)`) and `ts_end` (`TsEnd(f64)`) -- start and end unix timestamp
+/// - `ts_start` (`TsStart(f64)`) and `ts_end` (`TsEnd(f64)`) -- start and end unix timestamp,
+///   given as (fractional) unix seconds or an RFC 3339 string
 /// - `as_path` (`ComparableRegex`) -- regular expression for AS path string
-/// - `community` (`ComparableRegex`) -- regular expression for community string
+/// - `as_path_contains` (`AsPathContains(Vec<Asn>)`) -- ASN or comma-separated ASNs, matching
+///   elems with any of them in the AS path; much faster than an `as_path` regex
+/// - `community` (`Community(CommunityPattern)`) -- standard community such as `13335:100` or
+///   `no-export`, with `*` matching any value of a field; values that are not a community pattern
+///   are treated as `community_regex`
+/// - `large_community` (`LargeCommunity(CommunityPattern)`) -- large community such as `13335:*:100`
+/// - `extended_community` (`ExtendedCommunity(CommunityPattern)`) -- extended community in the
+///   `type:subtype:global_admin:local_admin` format of its string representation, e.g. `0:2:13335:*`;
+///   IPv6 address specific extended communities can only be matched with `community_regex`
+/// - `community_regex` (`CommunityRegex(ComparableRegex)`) -- regular expression for the string
+///   of any community
 /// - `ip_version` (`IpVersion`) -- IP version (`ipv4` or `ipv6`)
+/// - `safi` (`Safi`) -- SAFI of the prefix (`unicast`, `multicast`, `unicast_multicast` or the
+///   SAFI number), e.g. to select the multicast RIB entries of a table dump. Only the SAFIs whose
+///   NLRI are parsed into elems are accepted; FlowSpec (`flowspec`, `flowspec_vpn`, 133 or 134)
+///   and other SAFIs are rejected, as their NLRI are not parsed
+/// - `bogons` (`Bogons(bool)`) -- `true` to match only bogon prefixes, `false` to drop them; see
+///   [NetworkPrefix::is_bogon]
+/// - `rib_side` (`RibSide(RibSide)`) -- RIB of the monitored router (`adj-rib-in`, `adj-rib-out` or
+///   `loc-rib`); only elems of BMP route monitoring messages have a RIB side, see
+///   [BgpElem::rib_side]
+/// - `policy` (`Policy(RibPolicy)`) -- policy stage (`pre` or `post`); only elems of BMP route
+///   monitoring messages have a policy stage, see [BgpElem::policy]
+/// - `exclude_<filter_type>` (`Exclude(Box<Filter>)`) -- any of the above, matching the elems the
+///   inner filter does not match, e.g. `exclude_peer_asn`
 #[derive(Debug, Clone, PartialEq)]
 pub enum Filter {
     OriginAsn(u32),
+    OriginAsnSet(HashSet<Asn>),
     Prefix(IpNet, PrefixMatchType),
+    PrefixSet(PrefixSet, PrefixMatchType),
     PeerIp(IpAddr),
     PeerIps(Vec<IpAddr>),
     PeerAsn(u32),
+    NextHop(IpNet),
     Type(ElemType),
     IpVersion(IpVersion),
+    Safi(Safi),
+    Bogons(bool),
+    RibSide(RibSide),
+    Policy(RibPolicy),
     TsStart(f64),
     TsEnd(f64),
     AsPath(ComparableRegex),
-    Community(ComparableRegex),
+    AsPathContains(Vec<Asn>),
+    Community(CommunityPattern),
+    LargeCommunity(CommunityPattern),
+    ExtendedCommunity(CommunityPattern),
+    CommunityRegex(ComparableRegex),
+    Exclude(Box<Filter>),
+}
+
+/// Pattern matching the colon-separated fields of a community's string representation.
+///
+/// A field of `*` matches any value. Numeric fields are compared by value, other fields
+/// case-insensitively.
+///
+/// ```
+/// use bgpkit_parser::CommunityPattern;
+///
+/// let pattern = CommunityPattern::new("13335:*");
+/// assert!(pattern.is_match("13335:100"));
+/// assert!(!pattern.is_match("13335:100:1"));
+/// assert!(!pattern.is_match("6939:100"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommunityPattern {
+    /// pattern fields, `None` for wildcards
+    fields: Vec<Option<String>>,
+}
+
+impl CommunityPattern {
+    pub fn new(pattern: &str) -> Self {
+        let fields = pattern
+            .split(':')
+            .map(|field| match field.trim() {
+                "*" => None,
+                field => Some(normalize_community_field(field)),
+            })
+            .collect();
+        CommunityPattern { fields }
+    }
+
+    /// Number of colon-separated fields of the pattern.
+    pub fn num_fields(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_match(&self, community: &str) -> bool {
+        let mut fields = community.split(':');
+        let matched = self.fields.iter().all(|pattern| match fields.next() {
+            None => false,
+            Some(field) => match pattern {
+                None => true,
+                Some(pattern) => *pattern == normalize_community_field(field),
+            },
+        });
+        matched && fields.next().is_none()
+    }
+
+    /// Whether every non-wildcard field is a number that fits in `max`.
+    fn fields_fit(&self, max: u32) -> bool {
+        self.fields.iter().flatten().all(|field| {
+            field
+                .parse::<u64>()
+                .map(|v| v <= max as u64)
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Displayed as the normalized pattern, with `*` for wildcards.
+impl std::fmt::Display for CommunityPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<&str> = self
+            .fields
+            .iter()
+            .map(|field| field.as_deref().unwrap_or("*"))
+            .collect();
+        write!(f, "{}", fields.join(":"))
+    }
+}
+
+/// Numeric fields are compared without leading zeros, and other fields in lowercase.
+fn normalize_community_field(field: &str) -> String {
+    match field.parse::<u64>() {
+        Ok(v) => v.to_string(),
+        Err(_) => field.to_lowercase(),
+    }
+}
+
+fn parse_community_pattern(filter_value: &str) -> Option<CommunityPattern> {
+    let pattern = CommunityPattern::new(filter_value);
+    let well_known = ["no-export", "no-advertise", "no-export-sub-confed"];
+    match pattern.num_fields() {
+        1 => well_known
+            .contains(&filter_value.trim().to_lowercase().as_str())
+            .then_some(pattern),
+        2 => pattern.fields_fit(u16::MAX as u32).then_some(pattern),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -102,9 +287,87 @@ pub enum PrefixMatchType {
     IncludeSuperSub,
 }
 
+/// Load a prefix list file with one prefix or IP address per line. Empty lines and everything
+/// after a `#` are ignored.
+fn load_prefix_list(path: &str) -> Result<PrefixSet, ParserError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| FilterError(format!("cannot open prefix list {}: {}", path, e)))?;
+    let mut set = PrefixSet::new();
+    for line in BufReader::new(file).lines() {
+        let line =
+            line.map_err(|e| FilterError(format!("cannot read prefix list {}: {}", path, e)))?;
+        let value = line.split('#').next().unwrap_or_default().trim();
+        if value.is_empty() {
+            continue;
+        }
+        match parse_prefix_or_ip(value) {
+            Some(prefix) => set.insert(prefix),
+            None => {
+                return Err(FilterError(format!(
+                    "cannot parse prefix from {} in prefix list {}",
+                    value, path
+                )))
+            }
+        };
+    }
+    Ok(set)
+}
+
+/// Parse comma-separated prefixes or IP addresses.
+fn parse_prefixes(value: &str) -> Result<PrefixSet, ParserError> {
+    let mut set = PrefixSet::new();
+    for prefix_str in value.replace(' ', "").split(',').filter(|v| !v.is_empty()) {
+        match parse_prefix_or_ip(prefix_str) {
+            Some(prefix) => set.insert(prefix),
+            None => {
+                return Err(FilterError(format!(
+                    "cannot parse prefix from {}",
+                    prefix_str
+                )))
+            }
+        };
+    }
+    Ok(set)
+}
+
+fn parse_prefix_or_ip(value: &str) -> Option<IpNet> {
+    match IpNet::from_str(value) {
+        Ok(v) => Some(v),
+        Err(_) => IpAddr::from_str(value).ok().map(IpNet::from),
+    }
+}
+
+/// Load an ASN list file with one AS number per line, with or without the `AS` prefix. Empty lines
+/// and everything after a `#` are ignored.
+fn load_asn_list(path: &str) -> Result<HashSet<Asn>, ParserError> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| FilterError(format!("cannot open ASN list {}: {}", path, e)))?;
+    let mut asns = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line =
+            line.map_err(|e| FilterError(format!("cannot read ASN list {}: {}", path, e)))?;
+        let value = line.split('#').next().unwrap_or_default().trim();
+        if value.is_empty() {
+            continue;
+        }
+        match Asn::from_str(value) {
+            Ok(v) => asns.insert(v),
+            Err(_) => {
+                return Err(FilterError(format!(
+                    "cannot parse ASN from {} in ASN list {}",
+                    value, path
+                )))
+            }
+        };
+    }
+    Ok(asns)
+}
+
 fn parse_time_str(time_str: &str) -> Option<chrono::NaiveDateTime> {
     if let Ok(t) = time_str.parse::<f64>() {
-        return chrono::DateTime::from_timestamp(t as i64, 0).map(|t| t.naive_utc());
+        // keep sub-second precision, e.g. `1637437798.5`
+        return chrono::DateTime::from_timestamp_micros((t * 1_000_000.0).round() as i64)
+            .map(|t| t.naive_utc());
     }
     if let Ok(t) = chrono::DateTime::parse_from_rfc3339(time_str) {
         return Some(t.naive_utc());
@@ -112,8 +375,18 @@ fn parse_time_str(time_str: &str) -> Option<chrono::NaiveDateTime> {
     None
 }
 
+fn micros_to_f64(t: chrono::NaiveDateTime) -> f64 {
+    t.and_utc().timestamp_micros() as f64 / 1_000_000.0
+}
+
 impl Filter {
     pub fn new(filter_type: &str, filter_value: &str) -> Result<Filter, ParserError> {
+        if let Some(inner_type) = filter_type.strip_prefix("exclude_") {
+            return Ok(Filter::Exclude(Box::new(Filter::new(
+                inner_type,
+                filter_value,
+            )?)));
+        }
         match filter_type {
             "origin_asn" => match u32::from_str(filter_value) {
                 Ok(v) => Ok(Filter::OriginAsn(v)),
@@ -122,6 +395,26 @@ impl Filter {
                     filter_value
                 ))),
             },
+            "origin_asns" => {
+                let mut asns = HashSet::new();
+                for asn_str in filter_value
+                    .replace(' ', "")
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                {
+                    match Asn::from_str(asn_str) {
+                        Ok(v) => asns.insert(v),
+                        Err(_) => {
+                            return Err(FilterError(format!(
+                                "cannot parse origin asn from {}",
+                                asn_str
+                            )))
+                        }
+                    };
+                }
+                Ok(Filter::OriginAsnSet(asns))
+            }
+            "origin_asn_list" => Ok(Filter::OriginAsnSet(load_asn_list(filter_value)?)),
             "prefix" => match IpNet::from_str(filter_value) {
                 Ok(v) => Ok(Filter::Prefix(v, PrefixMatchType::Exact)),
                 Err(_) => Err(FilterError(format!(
@@ -150,6 +443,38 @@ impl Filter {
                     filter_value
                 ))),
             },
+            "prefixes" => Ok(Filter::PrefixSet(
+                parse_prefixes(filter_value)?,
+                PrefixMatchType::Exact,
+            )),
+            "prefixes_super" => Ok(Filter::PrefixSet(
+                parse_prefixes(filter_value)?,
+                PrefixMatchType::IncludeSuper,
+            )),
+            "prefixes_sub" => Ok(Filter::PrefixSet(
+                parse_prefixes(filter_value)?,
+                PrefixMatchType::IncludeSub,
+            )),
+            "prefixes_super_sub" => Ok(Filter::PrefixSet(
+                parse_prefixes(filter_value)?,
+                PrefixMatchType::IncludeSuperSub,
+            )),
+            "prefix_list" => Ok(Filter::PrefixSet(
+                load_prefix_list(filter_value)?,
+                PrefixMatchType::Exact,
+            )),
+            "prefix_list_super" => Ok(Filter::PrefixSet(
+                load_prefix_list(filter_value)?,
+                PrefixMatchType::IncludeSuper,
+            )),
+            "prefix_list_sub" => Ok(Filter::PrefixSet(
+                load_prefix_list(filter_value)?,
+                PrefixMatchType::IncludeSub,
+            )),
+            "prefix_list_super_sub" => Ok(Filter::PrefixSet(
+                load_prefix_list(filter_value)?,
+                PrefixMatchType::IncludeSuperSub,
+            )),
             "peer_ip" => match IpAddr::from_str(filter_value) {
                 Ok(v) => Ok(Filter::PeerIp(v)),
                 Err(_) => Err(FilterError(format!(
@@ -179,6 +504,16 @@ impl Filter {
                     filter_value
                 ))),
             },
+            "next_hop" => match IpNet::from_str(filter_value) {
+                Ok(v) => Ok(Filter::NextHop(v.trunc())),
+                Err(_) => match IpAddr::from_str(filter_value) {
+                    Ok(v) => Ok(Filter::NextHop(IpNet::from(v))),
+                    Err(_) => Err(FilterError(format!(
+                        "cannot parse next hop IP or prefix from {}",
+                        filter_value
+                    ))),
+                },
+            },
             "type" => match filter_value {
                 "w" | "withdraw" | "withdrawal" => Ok(Filter::Type(ElemType::WITHDRAW)),
                 "a" | "announce" | "announcement" => Ok(Filter::Type(ElemType::ANNOUNCE)),
@@ -188,14 +523,14 @@ impl Filter {
                 ))),
             },
             "ts_start" | "start_ts" => match parse_time_str(filter_value) {
-                Some(t) => Ok(Filter::TsStart(t.and_utc().timestamp() as f64)),
+                Some(t) => Ok(Filter::TsStart(micros_to_f64(t))),
                 None => Err(FilterError(format!(
                     "cannot parse TsStart filter from {}",
                     filter_value
                 ))),
             },
             "ts_end" | "end_ts" => match parse_time_str(filter_value) {
-                Some(t) => Ok(Filter::TsEnd(t.and_utc().timestamp() as f64)),
+                Some(t) => Ok(Filter::TsEnd(micros_to_f64(t))),
                 None => Err(FilterError(format!(
                     "cannot parse TsEnd filter from {}",
                     filter_value
@@ -208,8 +543,50 @@ impl Filter {
                     filter_value
                 ))),
             },
-            "community" => match ComparableRegex::new(filter_value) {
-                Ok(v) => Ok(Filter::Community(v)),
+            "as_path_contains" => {
+                let mut asns = vec![];
+                for asn_str in filter_value.replace(' ', "").split(',') {
+                    match Asn::from_str(asn_str) {
+                        Ok(v) => asns.push(v),
+                        Err(_) => {
+                            return Err(FilterError(format!(
+                                "cannot parse AS path ASN from {}",
+                                asn_str
+                            )))
+                        }
+                    }
+                }
+                Ok(Filter::AsPathContains(asns))
+            }
+            "community" => match parse_community_pattern(filter_value) {
+                Some(v) => Ok(Filter::Community(v)),
+                // fall back to a regex for compatibility with earlier versions
+                None => Filter::new("community_regex", filter_value),
+            },
+            "large_community" => {
+                let pattern = CommunityPattern::new(filter_value);
+                if pattern.num_fields() == 3 && pattern.fields_fit(u32::MAX) {
+                    Ok(Filter::LargeCommunity(pattern))
+                } else {
+                    Err(FilterError(format!(
+                        "cannot parse large community pattern from {}",
+                        filter_value
+                    )))
+                }
+            }
+            "extended_community" => {
+                let pattern = CommunityPattern::new(filter_value);
+                if pattern.num_fields() == 4 {
+                    Ok(Filter::ExtendedCommunity(pattern))
+                } else {
+                    Err(FilterError(format!(
+                        "cannot parse extended community pattern from {}",
+                        filter_value
+                    )))
+                }
+            }
+            "community_regex" => match ComparableRegex::new(filter_value) {
+                Ok(v) => Ok(Filter::CommunityRegex(v)),
                 Err(_) => Err(FilterError(format!(
                     "cannot parse Community regex from {}",
                     filter_value
@@ -223,9 +600,198 @@ impl Filter {
                     filter_value
                 ))),
             },
+            "safi" => {
+                let value = filter_value.to_lowercase();
+                if matches!(value.as_str(), "flowspec" | "flowspec_vpn" | "133" | "134") {
+                    return Err(FilterError(format!(
+                        "cannot filter by SAFI {}, FlowSpec NLRI are not parsed into elems",
+                        filter_value
+                    )));
+                }
+                let safi = match value.as_str() {
+                    "unicast" => Some(Safi::Unicast),
+                    "multicast" => Some(Safi::Multicast),
+                    "unicast_multicast" => Some(Safi::UnicastMulticast),
+                    v => v.parse::<u8>().ok().and_then(|v| Safi::try_from(v).ok()),
+                };
+                match safi {
+                    Some(v) => Ok(Filter::Safi(v)),
+                    None => Err(FilterError(format!(
+                        "cannot parse SAFI from {}, only unicast and multicast prefixes are parsed",
+                        filter_value
+                    ))),
+                }
+            }
+            "bogons" | "bogon" => match filter_value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Filter::Bogons(true)),
+                "false" | "no" | "0" => Ok(Filter::Bogons(false)),
+                _ => Err(FilterError(format!(
+                    "cannot parse bogons filter value from {}, expected true or false",
+                    filter_value
+                ))),
+            },
+            "rib_side" => match RibSide::from_str(filter_value) {
+                Ok(v) => Ok(Filter::RibSide(v)),
+                Err(_) => Err(FilterError(format!(
+                    "cannot parse RIB side from {}, expected adj-rib-in, adj-rib-out or loc-rib",
+                    filter_value
+                ))),
+            },
+            "policy" => match RibPolicy::from_str(filter_value) {
+                Ok(v) => Ok(Filter::Policy(v)),
+                Err(_) => Err(FilterError(format!(
+                    "cannot parse policy from {}, expected pre or post",
+                    filter_value
+                ))),
+            },
             _ => Err(FilterError(format!("unknown filter type: {}", filter_type))),
         }
     }
+
+    /// The filter type and value that [Filter::new] turns into this filter.
+    ///
+    /// Filters loaded from a file, such as `prefix_list`, are returned with the inline filter type
+    /// of their content, e.g. `prefixes`.
+    pub fn type_and_value(&self) -> (String, String) {
+        fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+            values
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+        fn match_suffix(t: &PrefixMatchType) -> &'static str {
+            match t {
+                PrefixMatchType::Exact => "",
+                PrefixMatchType::IncludeSuper => "_super",
+                PrefixMatchType::IncludeSub => "_sub",
+                PrefixMatchType::IncludeSuperSub => "_super_sub",
+            }
+        }
+
+        match self {
+            Filter::OriginAsn(v) => ("origin_asn".to_string(), v.to_string()),
+            Filter::OriginAsnSet(set) => {
+                let mut asns: Vec<&Asn> = set.iter().collect();
+                asns.sort();
+                ("origin_asns".to_string(), join(asns))
+            }
+            Filter::Prefix(v, t) => (format!("prefix{}", match_suffix(t)), v.to_string()),
+            Filter::PrefixSet(set, t) => {
+                (format!("prefixes{}", match_suffix(t)), join(set.prefixes()))
+            }
+            Filter::PeerIp(v) => ("peer_ip".to_string(), v.to_string()),
+            Filter::PeerIps(v) => ("peer_ips".to_string(), join(v)),
+            Filter::PeerAsn(v) => ("peer_asn".to_string(), v.to_string()),
+            Filter::NextHop(v) => ("next_hop".to_string(), v.to_string()),
+            Filter::Type(v) => (
+                "type".to_string(),
+                match v {
+                    ElemType::ANNOUNCE => "announce".to_string(),
+                    ElemType::WITHDRAW => "withdraw".to_string(),
+                },
+            ),
+            Filter::IpVersion(v) => (
+                "ip_version".to_string(),
+                match v {
+                    IpVersion::Ipv4 => "ipv4".to_string(),
+                    IpVersion::Ipv6 => "ipv6".to_string(),
+                },
+            ),
+            Filter::Safi(v) => (
+                "safi".to_string(),
+                match v {
+                    Safi::Unicast => "unicast".to_string(),
+                    Safi::Multicast => "multicast".to_string(),
+                    Safi::UnicastMulticast => "unicast_multicast".to_string(),
+                },
+            ),
+            Filter::Bogons(v) => ("bogons".to_string(), v.to_string()),
+            Filter::RibSide(v) => ("rib_side".to_string(), v.to_string()),
+            Filter::Policy(v) => ("policy".to_string(), v.to_string()),
+            Filter::TsStart(v) => ("ts_start".to_string(), v.to_string()),
+            Filter::TsEnd(v) => ("ts_end".to_string(), v.to_string()),
+            Filter::AsPath(v) => ("as_path".to_string(), v.as_str().to_string()),
+            Filter::AsPathContains(v) => ("as_path_contains".to_string(), join(v)),
+            Filter::Community(v) => ("community".to_string(), v.to_string()),
+            Filter::LargeCommunity(v) => ("large_community".to_string(), v.to_string()),
+            Filter::ExtendedCommunity(v) => ("extended_community".to_string(), v.to_string()),
+            Filter::CommunityRegex(v) => ("community_regex".to_string(), v.as_str().to_string()),
+            Filter::Exclude(inner) => {
+                let (filter_type, value) = inner.type_and_value();
+                (format!("exclude_{}", filter_type), value)
+            }
+        }
+    }
+
+    /// Filter for elems originated by any of the given AS numbers.
+    pub fn origin_asn_set(asns: &[u32]) -> Filter {
+        Filter::OriginAsnSet(asns.iter().map(|asn| Asn::from(*asn)).collect())
+    }
+
+    /// Returns true if the filter matches on the AS path or the communities of elems, which are
+    /// not decoded with [lazy attributes](crate::ParseContext::with_lazy_attributes).
+    pub(crate) fn needs_lazy_attributes(&self) -> bool {
+        match self {
+            Filter::OriginAsn(_)
+            | Filter::OriginAsnSet(_)
+            | Filter::AsPath(_)
+            | Filter::AsPathContains(_)
+            | Filter::Community(_)
+            | Filter::LargeCommunity(_)
+            | Filter::ExtendedCommunity(_)
+            | Filter::CommunityRegex(_) => true,
+            Filter::Prefix(..)
+            | Filter::PrefixSet(..)
+            | Filter::PeerIp(_)
+            | Filter::PeerIps(_)
+            | Filter::PeerAsn(_)
+            | Filter::NextHop(_)
+            | Filter::Type(_)
+            | Filter::IpVersion(_)
+            | Filter::Safi(_)
+            | Filter::Bogons(_)
+            | Filter::RibSide(_)
+            | Filter::Policy(_)
+            | Filter::TsStart(_)
+            | Filter::TsEnd(_) => false,
+            Filter::Exclude(inner) => inner.needs_lazy_attributes(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct FilterRepr {
+        filter_type: String,
+        value: String,
+    }
+
+    /// Serialized as its filter type and value, see [Filter::type_and_value].
+    impl Serialize for Filter {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let (filter_type, value) = self.type_and_value();
+            FilterRepr { filter_type, value }.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Filter {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let repr = FilterRepr::deserialize(deserializer)?;
+            Filter::new(&repr.filter_type, &repr.value).map_err(D::Error::custom)
+        }
+    }
 }
 
 pub trait Filterable {
@@ -233,54 +799,19 @@ pub trait Filterable {
     fn match_filters(&self, filters: &[Filter]) -> bool;
 }
 
-const fn same_family(prefix_1: &IpNet, prefix_2: &IpNet) -> bool {
-    matches!(
-        (prefix_1, prefix_2),
-        (IpNet::V4(_), IpNet::V4(_)) | (IpNet::V6(_), IpNet::V6(_))
-    )
-}
-
+/// Matches prefixes using [NetworkPrefix::covers] semantics: a prefix covers itself and all of its
+/// more-specifics of the same address family.
 fn prefix_match(match_prefix: &IpNet, input_prefix: &IpNet, t: &PrefixMatchType) -> bool {
-    let exact = input_prefix.eq(match_prefix);
+    let match_prefix = NetworkPrefix::new(*match_prefix, 0);
+    let input_prefix = NetworkPrefix::new(*input_prefix, 0);
     match t {
-        PrefixMatchType::Exact => exact,
-        PrefixMatchType::IncludeSuper => {
-            if exact {
-                exact
-            } else if !same_family(match_prefix, input_prefix) {
-                // version not match
-                false
-            } else {
-                // input_prefix is super prefix of match_prefix
-                match_prefix.addr() >= input_prefix.addr()
-                    && match_prefix.broadcast() <= input_prefix.broadcast()
-            }
-        }
-        PrefixMatchType::IncludeSub => {
-            if exact {
-                exact
-            } else if !same_family(match_prefix, input_prefix) {
-                // version not match
-                false
-            } else {
-                // input_prefix is sub prefix of match_prefix
-                match_prefix.addr() <= input_prefix.addr()
-                    && match_prefix.broadcast() >= input_prefix.broadcast()
-            }
-        }
+        PrefixMatchType::Exact => input_prefix == match_prefix,
+        // input_prefix is super prefix of match_prefix
+        PrefixMatchType::IncludeSuper => input_prefix.covers(&match_prefix),
+        // input_prefix is sub prefix of match_prefix
+        PrefixMatchType::IncludeSub => match_prefix.covers(&input_prefix),
         PrefixMatchType::IncludeSuperSub => {
-            if exact {
-                exact
-            } else if !same_family(match_prefix, input_prefix) {
-                // version not match
-                false
-            } else {
-                // input_prefix is super prefix of match_prefix
-                (match_prefix.addr() >= input_prefix.addr()
-                    && match_prefix.broadcast() <= input_prefix.broadcast())
-                    || (match_prefix.addr() <= input_prefix.addr()
-                        && match_prefix.broadcast() >= input_prefix.broadcast())
-            }
+            input_prefix.covers(&match_prefix) || match_prefix.covers(&input_prefix)
         }
     }
 }
@@ -296,10 +827,28 @@ impl Filterable for BgpElem {
                     false
                 }
             }
+            Filter::OriginAsnSet(set) => match &self.origin_asns {
+                Some(origins) => origins.iter().any(|asn| set.contains(asn)),
+                None => false,
+            },
             Filter::Prefix(v, t) => prefix_match(v, &self.prefix.prefix, t),
+            Filter::PrefixSet(set, t) => {
+                let prefix = &self.prefix.prefix;
+                match t {
+                    PrefixMatchType::Exact => set.contains(prefix),
+                    // the elem prefix is a super prefix of a prefix of the set
+                    PrefixMatchType::IncludeSuper => set.contains_sub(prefix),
+                    // the elem prefix is a sub prefix of a prefix of the set
+                    PrefixMatchType::IncludeSub => set.contains_super(prefix),
+                    PrefixMatchType::IncludeSuperSub => {
+                        set.contains_sub(prefix) || set.contains_super(prefix)
+                    }
+                }
+            }
             Filter::PeerIp(v) => self.peer_ip == *v,
             Filter::PeerIps(v) => v.contains(&self.peer_ip),
             Filter::PeerAsn(v) => self.peer_asn.eq(v),
+            Filter::NextHop(v) => matches!(self.next_hop, Some(ip) if v.contains(&ip)),
             Filter::Type(v) => self.elem_type.eq(v),
             Filter::TsStart(v) => self.timestamp >= *v,
             Filter::TsEnd(v) => self.timestamp <= *v,
@@ -310,7 +859,23 @@ impl Filterable for BgpElem {
                     false
                 }
             }
-            Filter::Community(r) => {
+            Filter::AsPathContains(asns) => match &self.as_path {
+                Some(path) => path.iter_segments().flatten().any(|a| asns.contains(a)),
+                None => false,
+            },
+            Filter::Community(p) => self.communities.iter().flatten().any(|c| match c {
+                MetaCommunity::Plain(c) => p.is_match(&c.to_string()),
+                _ => false,
+            }),
+            Filter::LargeCommunity(p) => self.communities.iter().flatten().any(|c| match c {
+                MetaCommunity::Large(c) => p.is_match(&c.to_string()),
+                _ => false,
+            }),
+            Filter::ExtendedCommunity(p) => self.communities.iter().flatten().any(|c| match c {
+                MetaCommunity::Extended(c) => p.is_match(&c.to_string()),
+                _ => false,
+            }),
+            Filter::CommunityRegex(r) => {
                 if let Some(communities) = &self.communities {
                     communities.iter().any(|c| r.is_match(c.to_string()))
                 } else {
@@ -321,6 +886,11 @@ impl Filterable for BgpElem {
                 IpVersion::Ipv4 => self.prefix.prefix.addr().is_ipv4(),
                 IpVersion::Ipv6 => self.prefix.prefix.addr().is_ipv6(),
             },
+            Filter::Safi(safi) => self.safi == *safi,
+            Filter::Bogons(bogons) => self.prefix.is_bogon() == *bogons,
+            Filter::RibSide(v) => self.rib_side == Some(*v),
+            Filter::Policy(v) => self.policy == Some(*v),
+            Filter::Exclude(inner) => !self.match_filter(inner),
         }
     }
 
@@ -329,6 +899,42 @@ impl Filterable for BgpElem {
     }
 }
 
+/// Filter on the MRT common header of a record, evaluated before the record body is parsed.
+///
+/// A record is kept if it matches all record filters. Note that elems of RIB dumps need the peer
+/// index table record, so filtering out `TABLE_DUMP_V2` records of the `PEER_INDEX_TABLE` subtype
+/// leaves the RIB entries without peer information.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordFilter {
+    /// MRT entry type
+    EntryType(EntryType),
+    /// MRT entry subtype, whose meaning depends on the entry type
+    EntrySubtype(u16),
+    /// records with a timestamp, including microseconds, at or after the given unix timestamp
+    TsStart(f64),
+    /// records with a timestamp, including microseconds, at or before the given unix timestamp
+    TsEnd(f64),
+}
+
+impl RecordFilter {
+    pub fn matches(&self, header: &CommonHeader) -> bool {
+        let timestamp = || {
+            header.timestamp as f64 + header.microsecond_timestamp.unwrap_or(0) as f64 / 1_000_000.0
+        };
+        match self {
+            RecordFilter::EntryType(v) => header.entry_type == *v,
+            RecordFilter::EntrySubtype(v) => header.entry_subtype == *v,
+            RecordFilter::TsStart(v) => timestamp() >= *v,
+            RecordFilter::TsEnd(v) => timestamp() <= *v,
+        }
+    }
+}
+
+/// Whether the header matches all the record filters.
+pub fn match_record_filters(header: &CommonHeader, filters: &[RecordFilter]) -> bool {
+    filters.iter().all(|f| f.matches(header))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -435,6 +1041,62 @@ mod tests {
         assert_eq!(count, 3393 + 834);
     }
 
+    #[test]
+    fn test_community_filters() {
+        let elem = BgpElem {
+            communities: Some(vec![
+                MetaCommunity::Plain(Community::Custom(Asn::new_16bit(13335), 100)),
+                MetaCommunity::Plain(Community::NoExport),
+                MetaCommunity::Extended(ExtendedCommunity::TransitiveTwoOctetAs(
+                    TwoOctetAsExtCommunity {
+                        subtype: 2,
+                        global_admin: Asn::new_16bit(13335),
+                        local_admin: [0, 0, 0, 0xab],
+                    },
+                )),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Filter::new("community", "13335:*").unwrap(),
+            Filter::Community(CommunityPattern::new("13335:*"))
+        );
+        for (filter_type, value, expected) in [
+            ("community", "13335:100", true),
+            ("community", "13335:1000", false),
+            ("community", "*:100", true),
+            ("community", "13335:*", true),
+            ("community", "NO-EXPORT", true),
+            ("community", "no-advertise", false),
+            ("large_community", "13335:*:*", false),
+            ("extended_community", "0:2:13335:*", true),
+            ("extended_community", "0:2:13335:000000ab", true),
+            ("extended_community", "*:*:6939:*", false),
+            ("community_regex", "^13335:1", true),
+        ] {
+            let filter = Filter::new(filter_type, value).unwrap();
+            assert_eq!(
+                elem.match_filter(&filter),
+                expected,
+                "{} {}",
+                filter_type,
+                value
+            );
+        }
+
+        // values that are not a standard community pattern are regexes
+        assert!(matches!(
+            Filter::new("community", "13335:1.*").unwrap(),
+            Filter::CommunityRegex(_)
+        ));
+        assert!(matches!(
+            Filter::new("community", "70000:1").unwrap(),
+            Filter::CommunityRegex(_)
+        ));
+        assert!(Filter::new("extended_community", "0:2:13335").is_err());
+    }
+
     #[test]
     fn test_filter_incorrect_filters() {
         // filter by community with large community (i.e. with 3 values, separated by ':')
@@ -459,6 +1121,15 @@ mod tests {
         assert_eq!(parse_time_str("2021-11-20T19:49:58ZDXV"), None);
         assert_eq!(parse_time_str("2021-11-20 19:49:58"), None);
         assert_eq!(parse_time_str("2021-11-20"), None);
+
+        // sub-second precision is kept for both formats
+        let ts = chrono::NaiveDateTime::from_str("2021-11-20T19:49:58.25").unwrap();
+        assert_eq!(parse_time_str("1637437798.25"), Some(ts));
+        assert_eq!(parse_time_str("2021-11-20T19:49:58.250Z"), Some(ts));
+        assert_eq!(
+            Filter::new("start_ts", "2021-11-20T19:49:58.250Z").unwrap(),
+            Filter::TsStart(1637437798.25)
+        );
     }
 
     #[test]
@@ -620,9 +1291,10 @@ mod tests {
             peer_ip: IpAddr::from_str("192.168.1.1").unwrap(),
             peer_asn: Asn::new_32bit(12345),
             prefix: NetworkPrefix::new(IpNet::from_str("192.168.1.0/24").unwrap(), 0),
+            safi: Safi::Unicast,
             next_hop: None,
             as_path: Some(AsPath::from_sequence(vec![174, 1916, 52888])),
-            origin_asns: Some(vec![Asn::new_16bit(12345)]),
+            origin_asns: Some(vec![Asn::new_16bit(12345)].into()),
             origin: None,
             local_pref: None,
             med: None,
@@ -637,6 +1309,10 @@ mod tests {
             unknown: None,
             elem_type: ElemType::ANNOUNCE,
             deprecated: None,
+            record_index: None,
+            record_offset: None,
+            rib_side: None,
+            policy: None,
         };
 
         let mut filters = vec![];
@@ -676,6 +1352,21 @@ mod tests {
         filters.push(filter.clone());
         assert!(elem.match_filter(&filter));
 
+        let filter = Filter::new("as_path_contains", "1916").unwrap();
+        filters.push(filter.clone());
+        assert!(elem.match_filter(&filter));
+
+        let filter = Filter::new("as_path_contains", "AS3356, 52888").unwrap();
+        assert_eq!(
+            filter,
+            Filter::AsPathContains(vec![Asn::new_32bit(3356), Asn::new_32bit(52888)])
+        );
+        assert!(elem.match_filter(&filter));
+
+        let filter = Filter::new("as_path_contains", "3356").unwrap();
+        assert!(!elem.match_filter(&filter));
+        assert!(Filter::new("as_path_contains", "3356,x").is_err());
+
         let filter = Filter::new("ip_version", "4").unwrap();
         filters.push(filter.clone());
         assert!(elem.match_filter(&filter));
@@ -683,10 +1374,271 @@ mod tests {
         let filter = Filter::new("ip", "ipv6").unwrap();
         assert!(!elem.match_filter(&filter));
 
+        let filter = Filter::new("safi", "unicast").unwrap();
+        assert_eq!(filter, Filter::Safi(Safi::Unicast));
+        assert!(elem.match_filter(&filter));
+        let filter = Filter::new("safi", "2").unwrap();
+        assert_eq!(filter, Filter::Safi(Safi::Multicast));
+        assert!(!elem.match_filter(&filter));
+        assert!(elem.match_filter(&Filter::new("exclude_safi", "multicast").unwrap()));
+        assert!(matches!(
+            Filter::new("safi", "flowspec"),
+            Err(FilterError(msg)) if msg.contains("FlowSpec")
+        ));
+        assert!(Filter::new("safi", "134").is_err());
+        assert!(Filter::new("safi", "133").is_err());
+
+        let filter = Filter::new("bogons", "false").unwrap();
+        assert_eq!(filter, Filter::Bogons(false));
+        assert_eq!(elem.match_filter(&filter), !elem.prefix.is_bogon());
+        assert_eq!(
+            elem.match_filter(&Filter::new("exclude_bogons", "true").unwrap()),
+            !elem.prefix.is_bogon()
+        );
+        assert!(Filter::new("bogons", "maybe").is_err());
+
+        // MRT elems have no RIB side or policy
+        let filter = Filter::new("rib_side", "adj-rib-out").unwrap();
+        assert_eq!(filter, Filter::RibSide(RibSide::AdjRibOut));
+        assert!(!elem.match_filter(&filter));
+        let bmp_elem = BgpElem {
+            rib_side: Some(RibSide::AdjRibOut),
+            policy: Some(RibPolicy::Post),
+            ..elem.clone()
+        };
+        assert!(bmp_elem.match_filter(&filter));
+        assert!(!bmp_elem.match_filter(&Filter::new("rib_side", "adj_rib_in").unwrap()));
+        let filter = Filter::new("policy", "post").unwrap();
+        assert_eq!(filter, Filter::Policy(RibPolicy::Post));
+        assert!(!elem.match_filter(&filter));
+        assert!(bmp_elem.match_filter(&filter));
+        assert!(!bmp_elem.match_filter(&Filter::new("policy", "pre").unwrap()));
+        assert!(Filter::new("rib_side", "adj-rib").is_err());
+        assert!(Filter::new("policy", "mid").is_err());
+
         let filter = Filter::new("community", r"12345:678910:111213$").unwrap();
         filters.push(filter.clone());
         assert!(elem.match_filter(&filter));
 
+        let filter = Filter::new("large_community", "12345:*:111213").unwrap();
+        filters.push(filter.clone());
+        assert!(elem.match_filter(&filter));
+
+        let filter = Filter::new("large_community", "12345:*").unwrap_err();
+        assert!(matches!(filter, FilterError(_)));
+
+        // standard community patterns do not match large communities
+        let filter = Filter::new("community", "12345:*").unwrap();
+        assert!(!elem.match_filter(&filter));
+
         assert!(elem.match_filters(&filters));
     }
+
+    #[test]
+    fn test_next_hop_filter() {
+        let mut elem = BgpElem {
+            next_hop: Some(IpAddr::from_str("2001:db8::1").unwrap()),
+            ..Default::default()
+        };
+
+        assert!(elem.match_filter(&Filter::new("next_hop", "2001:db8::1").unwrap()));
+        assert!(!elem.match_filter(&Filter::new("next_hop", "2001:db8::2").unwrap()));
+        assert!(elem.match_filter(&Filter::new("next_hop", "2001:db8::/32").unwrap()));
+        assert!(!elem.match_filter(&Filter::new("next_hop", "192.0.2.0/24").unwrap()));
+        assert_eq!(
+            Filter::new("next_hop", "2001:db8::1/32").unwrap(),
+            Filter::NextHop(IpNet::from_str("2001:db8::/32").unwrap())
+        );
+        assert!(Filter::new("next_hop", "example").is_err());
+
+        elem.next_hop = None;
+        assert!(!elem.match_filter(&Filter::new("next_hop", "::/0").unwrap()));
+    }
+
+    #[test]
+    fn test_record_filters() {
+        let header = CommonHeader {
+            timestamp: 100,
+            microsecond_timestamp: Some(500_000),
+            entry_type: EntryType::BGP4MP_ET,
+            entry_subtype: 4,
+            length: 0,
+        };
+        assert!(RecordFilter::EntryType(EntryType::BGP4MP_ET).matches(&header));
+        assert!(!RecordFilter::EntryType(EntryType::BGP4MP).matches(&header));
+        assert!(RecordFilter::EntrySubtype(4).matches(&header));
+        assert!(RecordFilter::TsStart(100.5).matches(&header));
+        assert!(!RecordFilter::TsStart(100.6).matches(&header));
+        assert!(RecordFilter::TsEnd(100.5).matches(&header));
+        assert!(!RecordFilter::TsEnd(100.0).matches(&header));
+
+        assert!(match_record_filters(&header, &[]));
+        assert!(!match_record_filters(
+            &header,
+            &[RecordFilter::EntrySubtype(4), RecordFilter::TsEnd(100.0)]
+        ));
+    }
+
+    #[test]
+    fn test_prefix_list_filter() {
+        let path = std::env::temp_dir().join("bgpkit-parser-test-prefix-list.txt");
+        std::fs::write(
+            &path,
+            "# test prefixes\n10.0.0.0/8\n\n192.0.2.1 # host\n2001:db8::/32\n",
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+
+        let elem = |prefix: &str| BgpElem {
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            ..Default::default()
+        };
+
+        let filter = Filter::new("prefix_list", path).unwrap();
+        match &filter {
+            Filter::PrefixSet(set, _) => assert_eq!(set.len(), 3),
+            _ => panic!("unexpected filter {:?}", filter),
+        }
+        assert!(elem("10.0.0.0/8").match_filter(&filter));
+        assert!(elem("192.0.2.1/32").match_filter(&filter));
+        assert!(!elem("10.1.0.0/16").match_filter(&filter));
+
+        let filter = Filter::new("prefix_list_sub", path).unwrap();
+        assert!(elem("10.1.0.0/16").match_filter(&filter));
+        assert!(elem("2001:db8:1::/48").match_filter(&filter));
+        assert!(!elem("0.0.0.0/0").match_filter(&filter));
+
+        let filter = Filter::new("prefix_list_super", path).unwrap();
+        assert!(elem("0.0.0.0/0").match_filter(&filter));
+        assert!(!elem("10.1.0.0/16").match_filter(&filter));
+
+        let filter = Filter::new("prefix_list_super_sub", path).unwrap();
+        assert!(elem("192.0.0.0/16").match_filter(&filter));
+        assert!(elem("10.1.0.0/16").match_filter(&filter));
+        assert!(!elem("172.16.0.0/12").match_filter(&filter));
+
+        assert!(Filter::new("prefix_list", "/nonexistent/prefixes.txt").is_err());
+        std::fs::write(path, "10.0.0.0/8\nnot a prefix\n").unwrap();
+        assert!(Filter::new("prefix_list", path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_filter_type_and_value() {
+        let filters = [
+            ("origin_asn", "13335"),
+            ("origin_asns", "13335,15169"),
+            ("prefix_super_sub", "10.0.0.0/8"),
+            ("prefixes_sub", "10.0.0.0/8,2001:db8::/32"),
+            ("peer_ip", "192.0.2.1"),
+            ("peer_ips", "192.0.2.1,2001:db8::1"),
+            ("peer_asn", "64496"),
+            ("next_hop", "192.0.2.0/24"),
+            ("type", "withdraw"),
+            ("ip_version", "ipv6"),
+            ("safi", "multicast"),
+            ("bogons", "false"),
+            ("rib_side", "loc-rib"),
+            ("policy", "pre"),
+            ("ts_start", "1637437798.5"),
+            ("ts_end", "1637437799"),
+            ("as_path", "^13335 .*"),
+            ("as_path_contains", "64496,64497"),
+            ("community", "13335:*"),
+            ("community", "no-export"),
+            ("large_community", "13335:*:100"),
+            ("extended_community", "0:2:13335:*"),
+            ("community_regex", ".+:784$"),
+            ("exclude_peer_asn", "64496"),
+        ];
+        for (filter_type, value) in filters {
+            let filter = Filter::new(filter_type, value).unwrap();
+            let (t, v) = filter.type_and_value();
+            assert_eq!(t, filter_type);
+            assert_eq!(Filter::new(&t, &v).unwrap(), filter, "{} {}", t, v);
+        }
+
+        let filter = Filter::new("type", "a").unwrap();
+        assert_eq!(
+            filter.type_and_value(),
+            ("type".to_string(), "announce".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_filter_serde() {
+        let filters = vec![
+            Filter::new("peer_asn", "13335").unwrap(),
+            Filter::new("exclude_prefix_super_sub", "10.0.0.0/8").unwrap(),
+            Filter::new("as_path", "^13335 .*").unwrap(),
+        ];
+        let json = serde_json::to_string(&filters).unwrap();
+        assert!(json.starts_with(r#"[{"filter_type":"peer_asn","value":"13335"}"#));
+        let parsed: Vec<Filter> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, filters);
+
+        let invalid = r#"{"filter_type": "peer_asn", "value": "x"}"#;
+        assert!(serde_json::from_str::<Filter>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_exclude_filters() {
+        let elem = |peer_asn: u32, prefix: &str| BgpElem {
+            peer_asn: Asn::from(peer_asn),
+            prefix: NetworkPrefix::from_str(prefix).unwrap(),
+            ..Default::default()
+        };
+
+        let filter = Filter::new("exclude_peer_asn", "64496").unwrap();
+        assert_eq!(filter, Filter::Exclude(Box::new(Filter::PeerAsn(64496))));
+        assert!(!elem(64496, "10.0.0.0/8").match_filter(&filter));
+        assert!(elem(64497, "10.0.0.0/8").match_filter(&filter));
+
+        let filters = vec![
+            Filter::new("prefix_super_sub", "10.0.0.0/8").unwrap(),
+            Filter::new("exclude_prefix_sub", "10.1.0.0/16").unwrap(),
+        ];
+        assert!(elem(64496, "10.0.0.0/8").match_filters(&filters));
+        assert!(elem(64496, "10.2.0.0/16").match_filters(&filters));
+        assert!(!elem(64496, "10.1.1.0/24").match_filters(&filters));
+        assert!(!elem(64496, "192.0.2.0/24").match_filters(&filters));
+
+        assert!(Filter::new("exclude_peer_asn", "x").is_err());
+        assert!(Filter::new("exclude_unknown", "1").is_err());
+    }
+
+    #[test]
+    fn test_origin_asn_set_filter() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "# test ASNs\n13335\n\nAS15169 # google\n").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let elem = |origins: Option<Vec<u32>>| BgpElem {
+            origin_asns: origins.map(|v| v.into_iter().map(Asn::from).collect()),
+            ..Default::default()
+        };
+
+        let filter = Filter::new("origin_asn_list", path).unwrap();
+        assert_eq!(
+            filter,
+            Filter::OriginAsnSet(HashSet::from([Asn::from(13335), Asn::from(15169)]))
+        );
+        assert!(elem(Some(vec![13335])).match_filter(&filter));
+        // any origin of an AS set matches
+        assert!(elem(Some(vec![64496, 15169])).match_filter(&filter));
+        assert!(!elem(Some(vec![64496])).match_filter(&filter));
+        assert!(!elem(None).match_filter(&filter));
+
+        assert_eq!(
+            Filter::new("origin_asns", "13335, AS15169").unwrap(),
+            filter
+        );
+        assert_eq!(Filter::origin_asn_set(&[15169, 13335]), filter);
+        assert!(Filter::new("origin_asns", "13335,x").is_err());
+
+        assert!(Filter::new("origin_asn_list", "/nonexistent/asns.txt").is_err());
+        std::fs::write(path, "13335\nnot an asn\n").unwrap();
+        assert!(Filter::new("origin_asn_list", path).is_err());
+    }
 }
@@ -0,0 +1,346 @@
+/*!
+Check the origins of announced prefixes against Internet Routing Registry (IRR) route objects.
+
+An [IrrDatabase] is loaded from RPSL `route`/`route6` objects, e.g. a registry dump such as
+`radb.db.route.gz`, or from a prepared JSON array of [IrrRoute]s. Elems are then classified into
+an [IrrStatus] by comparing their origin ASNs with the origins registered for the exact prefix.
+
+```no_run
+use bgpkit_parser::irr::IrrDatabase;
+use bgpkit_parser::BgpkitParser;
+
+let irr = IrrDatabase::from_rpsl(std::io::BufReader::new(
+    std::fs::File::open("radb.db.route").unwrap(),
+))
+.unwrap();
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+for (elem, status) in irr.annotate(parser.into_elem_iter()) {
+    println!("{} {:?}", elem.prefix, status);
+}
+```
+*/
+use crate::models::{Asn, BgpElem, NetworkPrefix};
+use crate::ParserError;
+use ipnet::IpNet;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read};
+use std::str::FromStr;
+
+/// A prefix and origin pair registered by an IRR route object.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IrrRoute {
+    pub prefix: IpNet,
+    pub origin: Asn,
+    /// name of the registry the object comes from, e.g. `RADB`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Result of checking an announcement against IRR data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum IrrStatus {
+    /// a route object registers the prefix with the announced origin
+    Match,
+    /// route objects exist for the prefix, but none with the announced origin
+    OriginMismatch,
+    /// no route object exists for the prefix
+    Unregistered,
+}
+
+/// Route objects indexed by exact prefix.
+#[derive(Debug, Clone, Default)]
+pub struct IrrDatabase {
+    routes: HashMap<IpNet, Vec<IrrRoute>>,
+}
+
+impl IrrDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load route objects from RPSL text.
+    ///
+    /// Objects other than `route` and `route6` are ignored, as are route objects without an
+    /// `origin` attribute. Route objects with an invalid prefix or origin are skipped with a
+    /// warning, so that one malformed object does not prevent loading a whole registry dump.
+    /// Returns an error only if reading fails.
+    pub fn from_rpsl<R: BufRead>(reader: R) -> Result<Self, ParserError> {
+        let mut database = IrrDatabase::new();
+        let mut object = RpslRouteObject::default();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                // a blank line ends the current object
+                database.insert_rpsl_object(std::mem::take(&mut object));
+                continue;
+            }
+            if line.starts_with('#') || line.starts_with('%') {
+                continue;
+            }
+            if line.starts_with([' ', '\t', '+']) {
+                // continuation of a multi-line attribute, not used by the attributes we read
+                continue;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            // strip end-of-line comments
+            let value = value.split('#').next().unwrap_or_default().trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "route" | "route6" => object.prefix = Some(value.to_string()),
+                "origin" => object.origin = Some(value.to_string()),
+                "source" => object.source = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        database.insert_rpsl_object(object);
+
+        Ok(database)
+    }
+
+    /// Load routes from a JSON array of [IrrRoute]s, e.g.
+    /// `[{"prefix": "192.0.2.0/24", "origin": 64496, "source": "RADB"}]`.
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, ParserError> {
+        let routes: Vec<IrrRoute> = serde_json::from_reader(reader)
+            .map_err(|e| ParserError::ParseError(format!("invalid IRR JSON: {}", e)))?;
+        Ok(routes.into_iter().collect())
+    }
+
+    /// Add a route object. Objects registering the same prefix and origin in the same registry
+    /// are only kept once.
+    pub fn insert(&mut self, mut route: IrrRoute) {
+        route.prefix = route.prefix.trunc();
+        let routes = self.routes.entry(route.prefix).or_default();
+        if !routes.contains(&route) {
+            routes.push(route);
+        }
+    }
+
+    /// Number of distinct registered prefixes.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Route objects registered for exactly this prefix, with the registries they come from.
+    pub fn routes(&self, prefix: &IpNet) -> &[IrrRoute] {
+        self.routes
+            .get(&prefix.trunc())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Origins registered for exactly this prefix.
+    pub fn origins(&self, prefix: &IpNet) -> Option<HashSet<Asn>> {
+        let routes = self.routes.get(&prefix.trunc())?;
+        Some(routes.iter().map(|route| route.origin).collect())
+    }
+
+    /// Check an announcement of `prefix` by `origin`.
+    pub fn check(&self, prefix: &NetworkPrefix, origin: Asn) -> IrrStatus {
+        match self.routes.get(&prefix.prefix.trunc()) {
+            None => IrrStatus::Unregistered,
+            Some(routes) if routes.iter().any(|r| r.origin == origin) => IrrStatus::Match,
+            Some(_) => IrrStatus::OriginMismatch,
+        }
+    }
+
+    /// Check the origin of an elem.
+    ///
+    /// Returns `None` for elems without origin, i.e. withdrawals. If the AS path ends with an
+    /// AS set, the elem matches if any of the origins is registered.
+    pub fn check_elem(&self, elem: &BgpElem) -> Option<IrrStatus> {
+        let origins = elem.origin_asns.as_ref().filter(|o| !o.is_empty())?;
+        let registered = match self.routes.get(&elem.prefix.prefix.trunc()) {
+            Some(registered) => registered,
+            None => return Some(IrrStatus::Unregistered),
+        };
+        if registered
+            .iter()
+            .any(|route| origins.contains(&route.origin))
+        {
+            Some(IrrStatus::Match)
+        } else {
+            Some(IrrStatus::OriginMismatch)
+        }
+    }
+
+    /// Pair each elem with its IRR status. See [IrrDatabase::check_elem].
+    pub fn annotate<'a, I>(
+        &'a self,
+        elems: I,
+    ) -> impl Iterator<Item = (BgpElem, Option<IrrStatus>)> + 'a
+    where
+        I: IntoIterator<Item = BgpElem>,
+        I::IntoIter: 'a,
+    {
+        elems.into_iter().map(move |elem| {
+            let status = self.check_elem(&elem);
+            (elem, status)
+        })
+    }
+
+    fn insert_rpsl_object(&mut self, object: RpslRouteObject) {
+        let (prefix, origin) = match (object.prefix, object.origin) {
+            (Some(prefix), Some(origin)) => (prefix, origin),
+            _ => return,
+        };
+        let prefix = match IpNet::from_str(&prefix) {
+            Ok(prefix) => prefix,
+            Err(_) => {
+                warn!("skipping IRR route object with invalid prefix {}", prefix);
+                return;
+            }
+        };
+        let origin = match Asn::from_str(&origin.to_ascii_uppercase()) {
+            Ok(origin) => origin,
+            Err(_) => {
+                warn!(
+                    "skipping IRR route object of {} with invalid origin {}",
+                    prefix, origin
+                );
+                return;
+            }
+        };
+        self.insert(IrrRoute {
+            prefix,
+            origin,
+            source: object.source,
+        });
+    }
+}
+
+impl FromIterator<IrrRoute> for IrrDatabase {
+    fn from_iter<T: IntoIterator<Item = IrrRoute>>(iter: T) -> Self {
+        let mut database = IrrDatabase::new();
+        for route in iter {
+            database.insert(route);
+        }
+        database
+    }
+}
+
+/// Attributes of the RPSL object being read.
+#[derive(Default)]
+struct RpslRouteObject {
+    prefix: Option<String>,
+    origin: Option<String>,
+    source: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AsPath;
+
+    const RPSL: &str = "\
+% comment
+route:          192.0.2.0/24
+descr:          example
+                continued
+origin:         AS64496
+source:         RADB
+
+route6:         2001:db8::/32
+origin:         as64497 # end-of-line comment
+source:         RADB
+
+aut-num:        AS64496
+as-name:        EXAMPLE
+";
+
+    #[test]
+    fn test_from_rpsl() {
+        let irr = IrrDatabase::from_rpsl(RPSL.as_bytes()).unwrap();
+        assert_eq!(irr.len(), 2);
+
+        let prefix = NetworkPrefix::from_str("192.0.2.0/24").unwrap();
+        assert_eq!(irr.check(&prefix, Asn::new_32bit(64496)), IrrStatus::Match);
+        assert_eq!(
+            irr.check(&prefix, Asn::new_32bit(64500)),
+            IrrStatus::OriginMismatch
+        );
+        let prefix = NetworkPrefix::from_str("2001:db8::/32").unwrap();
+        assert_eq!(irr.check(&prefix, Asn::new_32bit(64497)), IrrStatus::Match);
+        let prefix = NetworkPrefix::from_str("198.51.100.0/24").unwrap();
+        assert_eq!(
+            irr.check(&prefix, Asn::new_32bit(64496)),
+            IrrStatus::Unregistered
+        );
+
+        assert_eq!(
+            irr.routes(&"192.0.2.0/24".parse().unwrap()),
+            &[IrrRoute {
+                prefix: "192.0.2.0/24".parse().unwrap(),
+                origin: Asn::new_32bit(64496),
+                source: Some("RADB".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_from_rpsl_skips_invalid_objects() {
+        let rpsl = "\
+route:          192.0.2.0/33
+origin:         AS64496
+source:         RADB
+
+route:          198.51.100.0/24
+origin:         ASX
+source:         RADB
+
+route:          203.0.113.0/24
+origin:         AS64496
+source:         RADB
+";
+        let irr = IrrDatabase::from_rpsl(rpsl.as_bytes()).unwrap();
+        assert_eq!(irr.len(), 1);
+        let prefix = NetworkPrefix::from_str("203.0.113.0/24").unwrap();
+        assert_eq!(irr.check(&prefix, Asn::new_32bit(64496)), IrrStatus::Match);
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = r#"[{"prefix": "192.0.2.0/24", "origin": 64496, "source": "RADB"},
+                       {"prefix": "192.0.2.0/24", "origin": 64497}]"#;
+        let irr = IrrDatabase::from_json(json.as_bytes()).unwrap();
+        assert_eq!(irr.len(), 1);
+        assert_eq!(
+            irr.origins(&"192.0.2.0/24".parse().unwrap()).unwrap().len(),
+            2
+        );
+        assert!(IrrDatabase::from_json("{}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_check_elem() {
+        let irr: IrrDatabase = [IrrRoute {
+            prefix: "192.0.2.0/24".parse().unwrap(),
+            origin: Asn::new_32bit(64496),
+            source: None,
+        }]
+        .into_iter()
+        .collect();
+
+        let mut elem = BgpElem {
+            prefix: NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+            as_path: Some(AsPath::from_sequence([64510, 64496])),
+            origin_asns: Some(vec![Asn::new_32bit(64496)].into()),
+            ..Default::default()
+        };
+        assert_eq!(irr.check_elem(&elem), Some(IrrStatus::Match));
+
+        elem.origin_asns = Some(vec![Asn::new_32bit(64501)].into());
+        assert_eq!(irr.check_elem(&elem), Some(IrrStatus::OriginMismatch));
+
+        elem.origin_asns = None;
+        let annotated: Vec<_> = irr.annotate([elem]).collect();
+        assert_eq!(annotated[0].1, None);
+    }
+}
@@ -3,13 +3,17 @@ Provides parser iterator implementation.
 */
 use crate::error::ParserError;
 use crate::models::*;
-use crate::parser::BgpkitParser;
+#[cfg(feature = "alloc-stats")]
+use crate::parser::alloc_stats::{enter_stage, AllocStage};
+use crate::parser::{BgpkitParser, MrtRecordSource, ParseContext, RecordOffset};
 use crate::{Elementor, Filterable};
 use log::{error, warn};
-use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Use [ElemIterator] as the default iterator to return [BgpElem]s instead of [MrtRecord]s.
-impl<R: Read> IntoIterator for BgpkitParser<R> {
+impl<R: MrtRecordSource> IntoIterator for BgpkitParser<R> {
     type Item = BgpElem;
     type IntoIter = ElemIterator<R>;
 
@@ -25,48 +29,194 @@ impl<R> BgpkitParser<R> {
     pub fn into_elem_iter(self) -> ElemIterator<R> {
         ElemIterator::new(self)
     }
+
+    /// Iterate over the common headers of the records, skipping the record bodies without parsing
+    /// them.
+    ///
+    /// Only the record filters of the parser apply, as elem filters need the parsed bodies.
+    pub fn scan_headers(self) -> HeaderIterator<R> {
+        HeaderIterator {
+            parser: self,
+            errors: ErrorSummary::default(),
+            context: ParseContext::new(),
+        }
+    }
+}
+
+impl<R: MrtRecordSource> BgpkitParser<R> {
+    /// Count the records without parsing their bodies, see [BgpkitParser::scan_headers].
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+    /// println!("{} records", parser.count_records());
+    /// ```
+    pub fn count_records(self) -> u64 {
+        self.scan_headers().count() as u64
+    }
 }
 
+/// Context of the MRT record an elem was created from, passed to the enricher of a parser.
+///
+/// See [BgpkitParser::with_enricher].
+#[derive(Debug, Clone, Copy)]
+pub struct ElemContext<'a> {
+    /// common header of the record
+    pub header: &'a CommonHeader,
+    /// peer index table of the table dump being processed, if any
+    pub peer_table: Option<&'a PeerIndexTable>,
+    /// how confederation segments of AS paths are handled, if set with
+    /// [BgpkitParser::with_confed_segments]
+    pub confed: Option<ConfedSegments>,
+}
+
+/// Callback annotating the elems of a parser, see [BgpkitParser::with_enricher].
+pub type ElemEnricher = Box<dyn FnMut(&mut BgpElem, &ElemContext) + Send>;
+
 /*********
 MrtRecord Iterator
 **********/
 
+/// Summary of errors encountered (and skipped) during iteration.
+///
+/// The default iterators do not surface parsing errors to the caller. Instead, the errors are
+/// counted here and can be retrieved with [RecordIterator::error_summary] or
+/// [ElemIterator::error_summary] after the iterator is exhausted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorSummary {
+    /// number of truncated records skipped
+    pub truncated: u64,
+    /// number of records with unsupported types skipped
+    pub unsupported: u64,
+    /// number of records failed to parse
+    pub parse_errors: u64,
+    /// number of records skipped for exceeding the [RecordLimits](crate::RecordLimits) of the
+    /// parser
+    pub limit_exceeded: u64,
+    /// number of IO errors encountered, which stop the iteration
+    pub io_errors: u64,
+    /// the message of the last error encountered
+    pub last_error: Option<String>,
+    /// whether the iteration was stopped by a [CancellationToken]
+    pub cancelled: bool,
+}
+
+impl ErrorSummary {
+    /// Total number of errors encountered.
+    pub fn total(&self) -> u64 {
+        self.truncated + self.unsupported + self.parse_errors + self.limit_exceeded + self.io_errors
+    }
+
+    /// Returns true if any error has been encountered during iteration.
+    pub fn has_errors(&self) -> bool {
+        self.total() > 0
+    }
+}
+
+/// Cooperative cancellation handle for parser iterators.
+///
+/// Obtain one with [BgpkitParser::cancellation_token] before turning the parser into an iterator.
+///
+/// ```no_run
+/// use bgpkit_parser::BgpkitParser;
+///
+/// let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+/// let token = parser.cancellation_token();
+/// let mut iter = parser.into_elem_iter();
+/// let mut matches = 0;
+/// for elem in iter.by_ref() {
+///     if elem.peer_asn.to_u32() == 13335 {
+///         matches += 1;
+///         if matches == 10 {
+///             token.cancel();
+///         }
+///     }
+/// }
+/// assert!(iter.error_summary().cancelled);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request the iteration to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Iterator over the [MrtRecord]s of a parser, [Send] if the reader of the parser is.
 pub struct RecordIterator<R> {
     pub parser: BgpkitParser<R>,
     pub count: u64,
     elementor: Elementor,
+    errors: ErrorSummary,
+    context: ParseContext,
 }
 
 impl<R> RecordIterator<R> {
     fn new(parser: BgpkitParser<R>) -> Self {
         RecordIterator {
+            elementor: parser.options.elementor(),
             parser,
             count: 0,
-            elementor: Elementor::new(),
+            errors: ErrorSummary::default(),
+            context: ParseContext::new(),
         }
     }
+
+    /// Returns the position of the last record read, including records skipped for errors or
+    /// filters, e.g. to extract the record with [extract_records](crate::extract_records).
+    pub fn last_record_position(&self) -> Option<RecordOffset> {
+        self.context.last_record_position()
+    }
+
+    /// Returns the summary of errors encountered so far.
+    ///
+    /// Call this after the iterator is exhausted to check whether any records were skipped.
+    pub fn error_summary(&self) -> &ErrorSummary {
+        &self.errors
+    }
 }
 
-impl<R: Read> Iterator for RecordIterator<R> {
+impl<R: MrtRecordSource> Iterator for RecordIterator<R> {
     type Item = MrtRecord;
 
     fn next(&mut self) -> Option<MrtRecord> {
         self.count += 1;
         loop {
-            return match self.parser.next_record() {
+            if self.parser.options.cancellation.is_cancelled() {
+                self.errors.cancelled = true;
+                return None;
+            }
+            return match self.parser.next_record_with_context(&mut self.context) {
                 Ok(v) => {
                     // if None, the reaches EoF.
                     let filters = &self.parser.filters;
                     if filters.is_empty() {
                         Some(v)
                     } else {
-                        if let MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(
-                            _,
-                        )) = &v.message
+                        if let MrtMessage::TableDumpV2Message(
+                            TableDumpV2Message::PeerIndexTable(_)
+                            | TableDumpV2Message::GeoPeerTable(_),
+                        ) = &v.message
                         {
                             let _ = self.elementor.record_to_elems(v.clone());
                             return Some(v);
                         }
+                        #[cfg(feature = "alloc-stats")]
+                        let _elem_stage = enter_stage(AllocStage::Elem);
                         let elems = self.elementor.record_to_elems(v.clone());
                         if elems.iter().any(|e| e.match_filters(&self.parser.filters)) {
                             Some(v)
@@ -76,11 +226,17 @@ impl<R: Read> Iterator for RecordIterator<R> {
                     }
                 }
                 Err(e) => {
+                    match &e.error {
+                        ParserError::TruncatedMsg(_) => self.errors.truncated += 1,
+                        ParserError::Unsupported(_) => self.errors.unsupported += 1,
+                        _ => {}
+                    }
                     match e.error {
                         ParserError::TruncatedMsg(err_str) | ParserError::Unsupported(err_str) => {
                             if self.parser.options.show_warnings {
                                 warn!("parser warn: {}", err_str);
                             }
+                            self.errors.last_error = Some(err_str);
                             if let Some(bytes) = e.bytes {
                                 std::fs::write("mrt_core_dump", bytes)
                                     .expect("Unable to write to mrt_core_dump");
@@ -89,6 +245,8 @@ impl<R: Read> Iterator for RecordIterator<R> {
                         }
                         ParserError::ParseError(err_str) => {
                             error!("parser error: {}", err_str);
+                            self.errors.parse_errors += 1;
+                            self.errors.last_error = Some(err_str);
                             if self.parser.core_dump {
                                 if let Some(bytes) = e.bytes {
                                     std::fs::write("mrt_core_dump", bytes)
@@ -99,6 +257,14 @@ impl<R: Read> Iterator for RecordIterator<R> {
                                 continue;
                             }
                         }
+                        ParserError::LimitExceeded(err_str) => {
+                            if self.parser.options.show_warnings {
+                                warn!("parser warn: {}", err_str);
+                            }
+                            self.errors.limit_exceeded += 1;
+                            self.errors.last_error = Some(err_str);
+                            continue;
+                        }
                         ParserError::EofExpected => {
                             // normal end of file
                             None
@@ -106,6 +272,8 @@ impl<R: Read> Iterator for RecordIterator<R> {
                         ParserError::IoError(err) | ParserError::EofError(err) => {
                             // when reaching IO error, stop iterating
                             error!("{:?}", err);
+                            self.errors.io_errors += 1;
+                            self.errors.last_error = Some(err.to_string());
                             if self.parser.core_dump {
                                 if let Some(bytes) = e.bytes {
                                     std::fs::write("mrt_core_dump", bytes)
@@ -115,7 +283,11 @@ impl<R: Read> Iterator for RecordIterator<R> {
                             None
                         }
                         #[cfg(feature = "oneio")]
-                        ParserError::OneIoError(_) => None,
+                        ParserError::OneIoError(err) => {
+                            self.errors.io_errors += 1;
+                            self.errors.last_error = Some(err.to_string());
+                            None
+                        }
                         ParserError::FilterError(_) => {
                             // this should not happen at this stage
                             None
@@ -127,10 +299,67 @@ impl<R: Read> Iterator for RecordIterator<R> {
     }
 }
 
+/*********
+CommonHeader Iterator
+**********/
+
+/// Iterator over the [CommonHeader]s of the records of a parser, see
+/// [BgpkitParser::scan_headers].
+pub struct HeaderIterator<R> {
+    parser: BgpkitParser<R>,
+    errors: ErrorSummary,
+    context: ParseContext,
+}
+
+impl<R> HeaderIterator<R> {
+    /// Returns the position of the record of the last header read.
+    pub fn last_record_position(&self) -> Option<RecordOffset> {
+        self.context.last_record_position()
+    }
+
+    /// Returns the summary of errors encountered so far, i.e. the IO error or truncated record
+    /// that stopped the iteration, if any.
+    pub fn error_summary(&self) -> &ErrorSummary {
+        &self.errors
+    }
+}
+
+impl<R: MrtRecordSource> Iterator for HeaderIterator<R> {
+    type Item = CommonHeader;
+
+    fn next(&mut self) -> Option<CommonHeader> {
+        if self.parser.options.cancellation.is_cancelled() {
+            self.errors.cancelled = true;
+            return None;
+        }
+        let result = self
+            .parser
+            .reader
+            .next_matching_mrt_header(&mut self.context, &self.parser.options.record_filters);
+        match result {
+            Ok(header) => Some(header),
+            Err(e) => {
+                match e.error {
+                    ParserError::EofExpected => {}
+                    error => {
+                        if self.parser.options.show_warnings {
+                            warn!("parser warn: {}", error);
+                        }
+                        self.errors.io_errors += 1;
+                        self.errors.last_error = Some(error.to_string());
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
 /*********
 BgpElem Iterator
 **********/
 
+/// Iterator over the [BgpElem]s of a parser, [Send] if the reader of the parser is.
 pub struct ElemIterator<R> {
     cache_elems: Vec<BgpElem>,
     record_iter: RecordIterator<R>,
@@ -140,21 +369,97 @@ pub struct ElemIterator<R> {
 
 impl<R> ElemIterator<R> {
     fn new(parser: BgpkitParser<R>) -> Self {
+        // filters not looking at AS paths or communities are matched before decoding them, so that
+        // the attributes of the many elems dropped by selective filters are never decoded
+        let lazy = !parser.filters.is_empty()
+            && parser.options.enricher.is_none()
+            && !parser.filters.iter().any(|f| f.needs_lazy_attributes());
+        let mut elementor = parser.options.elementor();
+        let mut record_iter = RecordIterator::new(parser);
+        if lazy {
+            let filters = record_iter.parser.filters.clone();
+            record_iter.context = ParseContext::new().with_lazy_attributes(true);
+            record_iter.elementor = record_iter.elementor.with_lazy_filters(filters.clone());
+            elementor = elementor.with_lazy_filters(filters);
+        }
         ElemIterator {
-            record_iter: RecordIterator::new(parser),
+            elementor,
+            record_iter,
             count: 0,
             cache_elems: vec![],
-            elementor: Elementor::new(),
         }
     }
+
+    /// Returns the summary of errors encountered so far.
+    ///
+    /// Call this after the iterator is exhausted to check whether any records were skipped.
+    pub fn error_summary(&self) -> &ErrorSummary {
+        self.record_iter.error_summary()
+    }
+}
+
+impl<R: MrtRecordSource> ElemIterator<R> {
+    /// Returns the next `n` elems at once, or fewer at the end of the input, with an empty batch
+    /// once the iterator is exhausted.
+    ///
+    /// The elems of a record are moved into the batch together instead of one at a time, which
+    /// saves most of the per-elem overhead of [Iterator::next] on RIB dumps, e.g. when sending
+    /// batches through a channel or converting them into columnar formats.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let mut iter = BgpkitParser::new("rib.20230215.0000.bz2").unwrap().into_elem_iter();
+    /// loop {
+    ///     let batch = iter.next_batch(10_000);
+    ///     if batch.is_empty() {
+    ///         break;
+    ///     }
+    ///     println!("{} elems", batch.len());
+    /// }
+    /// ```
+    pub fn next_batch(&mut self, n: usize) -> Vec<BgpElem> {
+        let mut batch = Vec::with_capacity(n.min(1024));
+        while batch.len() < n {
+            if self.cache_elems.is_empty()
+                || self.record_iter.parser.options.cancellation.is_cancelled()
+            {
+                // refill the cache, or stop, one elem at a time
+                match self.next() {
+                    Some(elem) => batch.push(elem),
+                    None => break,
+                }
+                continue;
+            }
+            // the cache is in reverse order, so take the elems from its end
+            let take = (n - batch.len()).min(self.cache_elems.len());
+            let start = self.cache_elems.len() - take;
+            self.count += take as u64;
+            let filters = &self.record_iter.parser.filters;
+            batch.extend(
+                self.cache_elems
+                    .drain(start..)
+                    .rev()
+                    .filter(|e| e.match_filters(filters)),
+            );
+        }
+        batch
+    }
 }
 
-impl<R: Read> Iterator for ElemIterator<R> {
+impl<R: MrtRecordSource> Iterator for ElemIterator<R> {
     type Item = BgpElem;
 
     fn next(&mut self) -> Option<BgpElem> {
         self.count += 1;
 
+        if self.record_iter.parser.options.cancellation.is_cancelled() {
+            // drop the remaining elems of the current record as well
+            self.cache_elems.clear();
+            self.record_iter.errors.cancelled = true;
+            return None;
+        }
+
         loop {
             if self.cache_elems.is_empty() {
                 // refill cache elems
@@ -165,7 +470,28 @@ impl<R: Read> Iterator for ElemIterator<R> {
                             return None;
                         }
                         Some(r) => {
+                            let header = r.common_header;
+                            #[cfg(feature = "alloc-stats")]
+                            let _elem_stage = enter_stage(AllocStage::Elem);
                             let mut elems = self.elementor.record_to_elems(r);
+                            if self.record_iter.parser.options.record_provenance {
+                                if let Some(position) = self.record_iter.last_record_position() {
+                                    for elem in elems.iter_mut() {
+                                        elem.record_index = Some(position.index);
+                                        elem.record_offset = Some(position.offset);
+                                    }
+                                }
+                            }
+                            if let Some(enricher) = &mut self.record_iter.parser.options.enricher {
+                                let context = ElemContext {
+                                    header: &header,
+                                    peer_table: self.elementor.peer_table(),
+                                    confed: self.elementor.confed_segments(),
+                                };
+                                for elem in elems.iter_mut() {
+                                    enricher(elem, &context);
+                                }
+                            }
                             if elems.is_empty() {
                                 // somehow this record does not contain any elems, continue to parse next record
                                 continue;
@@ -193,3 +519,449 @@ impl<R: Read> Iterator for ElemIterator<R> {
         }
     }
 }
+
+/*********
+Time-bounded Iterator
+**********/
+
+/// Items with a timestamp, used by [TimeBounded] to check the timestamp cutoff.
+pub trait Timestamped {
+    /// Unix timestamp of the item in seconds.
+    fn timestamp(&self) -> f64;
+}
+
+impl Timestamped for BgpElem {
+    fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+}
+
+impl Timestamped for MrtRecord {
+    fn timestamp(&self) -> f64 {
+        let header = &self.common_header;
+        match header.microsecond_timestamp {
+            Some(micro) => header.timestamp as f64 + micro as f64 / 1_000_000.0,
+            None => header.timestamp as f64,
+        }
+    }
+}
+
+/// Reason why a [TimeBounded] iterator stopped early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// the wall-clock deadline has passed
+    Deadline,
+    /// an item with timestamp after the cutoff was reached
+    TimestampCutoff,
+}
+
+/// Iterator adapter that stops after a wall-clock deadline, or once an item's timestamp passes a
+/// cutoff.
+///
+/// Unlike the `end_ts` filter, which has to scan the input to the end, the timestamp cutoff stops
+/// reading at the first item after the cutoff. This assumes the input is ordered by timestamp, which
+/// is the case for most update files.
+///
+/// Created with [RecordIterator::time_bounded] or [ElemIterator::time_bounded].
+pub struct TimeBounded<I> {
+    inner: I,
+    deadline: Option<Instant>,
+    end_ts: Option<f64>,
+    stop_reason: Option<StopReason>,
+}
+
+impl<I> TimeBounded<I> {
+    fn new(inner: I) -> Self {
+        TimeBounded {
+            inner,
+            deadline: None,
+            end_ts: None,
+            stop_reason: None,
+        }
+    }
+
+    /// Stop yielding items once the wall-clock deadline passes.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stop at the first item with timestamp later than `end_ts`, without reading the rest of the
+    /// input.
+    pub fn until_timestamp(mut self, end_ts: f64) -> Self {
+        self.end_ts = Some(end_ts);
+        self
+    }
+
+    /// Returns why the iterator stopped early, or `None` if it has not stopped early.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// Get a reference to the wrapped iterator, e.g. to retrieve its error summary.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I> Iterator for TimeBounded<I>
+where
+    I: Iterator,
+    I::Item: Timestamped,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stop_reason.is_some() {
+            return None;
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                self.stop_reason = Some(StopReason::Deadline);
+                return None;
+            }
+        }
+        let item = self.inner.next()?;
+        if let Some(end_ts) = self.end_ts {
+            if item.timestamp() > end_ts {
+                self.stop_reason = Some(StopReason::TimestampCutoff);
+                return None;
+            }
+        }
+        Some(item)
+    }
+}
+
+impl<R> RecordIterator<R> {
+    /// Wrap the iterator to stop early by wall-clock deadline or timestamp cutoff.
+    ///
+    /// See [TimeBounded] for details.
+    pub fn time_bounded(self) -> TimeBounded<Self> {
+        TimeBounded::new(self)
+    }
+}
+
+impl<R> ElemIterator<R> {
+    /// Wrap the iterator to stop early by wall-clock deadline or timestamp cutoff.
+    ///
+    /// See [TimeBounded] for details.
+    pub fn time_bounded(self) -> TimeBounded<Self> {
+        TimeBounded::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::MrtBytes;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_error_summary() {
+        // one TABLE_DUMP_V2 RIB_GENERIC record (unsupported) with an empty body
+        let bytes: Vec<u8> = vec![
+            0, 0, 0, 0, // timestamp
+            0, 13, // type: TABLE_DUMP_V2
+            0, 6, // subtype: RIB_GENERIC
+            0, 0, 0, 0, // length
+        ];
+        let parser = BgpkitParser::from_reader(Cursor::new(bytes)).disable_warnings();
+        let mut iter = parser.into_elem_iter();
+        assert!(iter.next().is_none());
+        let summary = iter.error_summary();
+        assert_eq!(summary.unsupported, 1);
+        assert_eq!(summary.total(), 1);
+        assert!(summary.has_errors());
+        assert!(summary.last_error.is_some());
+
+        let parser = BgpkitParser::from_reader(Cursor::new(vec![])).disable_warnings();
+        let mut iter = parser.into_record_iter();
+        assert!(iter.next().is_none());
+        assert!(!iter.error_summary().has_errors());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let parser = BgpkitParser::from_reader(Cursor::new(vec![0u8; 12])).disable_warnings();
+        let token = parser.cancellation_token();
+        token.cancel();
+        let mut iter = parser.into_record_iter();
+        assert!(iter.next().is_none());
+        assert!(iter.error_summary().cancelled);
+        // nothing was read, so no error was encountered
+        assert!(!iter.error_summary().has_errors());
+    }
+
+    #[test]
+    fn test_record_limits() {
+        // an announcement, a withdrawal, and an announcement with communities
+        let bytes: Vec<u8> = crate::test_vectors::mrt_updates()
+            .into_iter()
+            .flat_map(|v| v.bytes)
+            .collect();
+
+        let parser = BgpkitParser::from_reader(Cursor::new(bytes.clone()))
+            .with_record_step_limit(4)
+            .disable_warnings();
+        let mut iter = parser.into_elem_iter();
+        let elems: Vec<BgpElem> = iter.by_ref().collect();
+        assert_eq!(elems.len(), 1);
+        assert_eq!(elems[0].elem_type, ElemType::WITHDRAW);
+        assert_eq!(iter.error_summary().limit_exceeded, 2);
+
+        let parser = BgpkitParser::from_bytes(bytes.clone())
+            .with_record_timeout(std::time::Duration::ZERO)
+            .disable_warnings();
+        let mut iter = parser.into_record_iter();
+        assert!(iter.next().is_none());
+        assert_eq!(iter.error_summary().limit_exceeded, 3);
+
+        let parser = BgpkitParser::from_bytes(bytes).with_record_step_limit(100);
+        assert_eq!(parser.into_elem_iter().count(), 4);
+    }
+
+    #[test]
+    fn test_record_provenance() {
+        let vectors = crate::test_vectors::mrt_updates();
+        let offsets: Vec<u64> = vectors
+            .iter()
+            .scan(0, |offset, v| {
+                let current = *offset;
+                *offset += v.bytes.len() as u64;
+                Some(current)
+            })
+            .collect();
+        let bytes: Vec<u8> = vectors.into_iter().flat_map(|v| v.bytes).collect();
+
+        let elems: Vec<BgpElem> = BgpkitParser::from_reader(Cursor::new(bytes.clone()))
+            .with_record_provenance()
+            .into_elem_iter()
+            .collect();
+        let positions: Vec<(Option<u64>, Option<u64>)> = elems
+            .iter()
+            .map(|e| (e.record_index, e.record_offset))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (Some(0), Some(offsets[0])),
+                (Some(0), Some(offsets[0])),
+                (Some(1), Some(offsets[1])),
+                (Some(2), Some(offsets[2])),
+            ]
+        );
+
+        // the bytes source counts the same positions, also with records skipped by filters
+        let mut iter = BgpkitParser::from_bytes(bytes.clone())
+            .add_filter("type", "withdraw")
+            .unwrap()
+            .with_record_provenance()
+            .into_elem_iter();
+        let elem = iter.next().unwrap();
+        assert_eq!(elem.record_offset, Some(offsets[1]));
+        assert!(iter.next().is_none());
+
+        // not set by default
+        let elem = BgpkitParser::from_bytes(bytes).into_elem_iter().next();
+        assert_eq!(elem.unwrap().record_index, None);
+    }
+
+    #[test]
+    fn test_enricher() {
+        use crate::encoder::MrtUpdatesEncoder;
+        use std::str::FromStr;
+
+        let mut encoder = MrtUpdatesEncoder::new();
+        for prefix in ["10.0.0.0/8", "192.0.2.0/24"] {
+            encoder.process_elem(&BgpElem {
+                timestamp: 10.0,
+                prefix: NetworkPrefix::from_str(prefix).unwrap(),
+                as_path: Some(AsPath::from_sequence([64496, 64497])),
+                ..Default::default()
+            });
+        }
+        let bytes = encoder.export_bytes();
+
+        let elems: Vec<BgpElem> = BgpkitParser::from_bytes(bytes)
+            .with_enricher(|elem, context| {
+                assert_eq!(context.header.entry_type, EntryType::BGP4MP_ET);
+                assert!(context.peer_table.is_none());
+                elem.local_pref = Some(context.header.timestamp);
+            })
+            .add_filter("prefix", "10.0.0.0/8")
+            .unwrap()
+            .into_elem_iter()
+            .collect();
+        assert_eq!(elems.len(), 1);
+        assert_eq!(elems[0].local_pref, Some(10));
+    }
+
+    #[test]
+    fn test_lazy_attributes() {
+        use crate::parser::Filter;
+        use crate::test_vectors;
+
+        for vector in test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+        {
+            for (filter_type, filter_value) in [
+                ("prefix", "192.0.2.0/24"),
+                ("peer_asn", "64496"),
+                ("as_path", "64497$"),
+                ("community", "64496:100"),
+            ] {
+                let expected: Vec<BgpElem> = vector
+                    .expected
+                    .iter()
+                    .filter(|e| e.match_filters(&[Filter::new(filter_type, filter_value).unwrap()]))
+                    .cloned()
+                    .collect();
+                let elems: Vec<BgpElem> = BgpkitParser::from_bytes(vector.bytes.clone())
+                    .add_filter(filter_type, filter_value)
+                    .unwrap()
+                    .into_elem_iter()
+                    .collect();
+                assert_eq!(elems, expected, "{} {}", vector.name, filter_type);
+            }
+        }
+
+        // records read lazily keep the AS path undecoded
+        let vector = test_vectors::mrt_updates().remove(0);
+        let mut iter = BgpkitParser::from_bytes(vector.bytes).into_record_iter();
+        iter.context = ParseContext::new().with_lazy_attributes(true);
+        let mut attributes = match iter.next().unwrap().message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(m)) => match m.bgp_message {
+                BgpMessage::Update(u) => u.attributes,
+                _ => panic!("not an update"),
+            },
+            _ => panic!("not a BGP4MP message"),
+        };
+        assert!(attributes
+            .iter()
+            .any(|a| matches!(a, AttributeValue::Lazy(l) if l.attr_type == AttrType::AS_PATH)));
+        attributes.decode_lazy();
+        assert_eq!(
+            attributes.as_path(),
+            Some(&AsPath::from_sequence([64496, 64497]))
+        );
+    }
+
+    #[test]
+    fn test_next_batch() {
+        use crate::test_vectors;
+
+        let vectors = test_vectors::mrt_updates();
+        let bytes: Vec<u8> = vectors.iter().flat_map(|v| v.bytes.clone()).collect();
+        let expected: Vec<BgpElem> = vectors.into_iter().flat_map(|v| v.expected).collect();
+        assert_eq!(expected.len(), 4);
+
+        for n in [1, 3, 10] {
+            let mut iter = BgpkitParser::from_bytes(bytes.clone()).into_elem_iter();
+            let mut elems = vec![];
+            loop {
+                let batch = iter.next_batch(n);
+                assert!(batch.len() <= n);
+                if batch.is_empty() {
+                    break;
+                }
+                elems.extend(batch);
+            }
+            assert_eq!(elems, expected, "batches of {}", n);
+        }
+
+        // filters apply to batches as well
+        let mut iter = BgpkitParser::from_bytes(bytes)
+            .add_filter("type", "w")
+            .unwrap()
+            .into_elem_iter();
+        let batch = iter.next_batch(10);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].elem_type, ElemType::WITHDRAW);
+        assert!(iter.next_batch(10).is_empty());
+    }
+
+    #[test]
+    fn test_scan_headers() {
+        use crate::parser::RecordFilter;
+        use crate::test_vectors;
+
+        let vectors: Vec<_> = test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+            .collect();
+        let bytes: Vec<u8> = vectors.iter().flat_map(|v| v.bytes.clone()).collect();
+        let records: Vec<CommonHeader> = BgpkitParser::from_bytes(bytes.clone())
+            .into_record_iter()
+            .map(|r| r.common_header)
+            .collect();
+        assert_eq!(records.len(), 5);
+
+        let headers: Vec<CommonHeader> = BgpkitParser::from_bytes(bytes.clone())
+            .scan_headers()
+            .collect();
+        assert_eq!(headers, records);
+        let mut iter = BgpkitParser::from_reader(Cursor::new(bytes.clone())).scan_headers();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), records);
+        assert!(!iter.error_summary().has_errors());
+
+        assert_eq!(BgpkitParser::from_bytes(bytes.clone()).count_records(), 5);
+        assert_eq!(
+            BgpkitParser::from_bytes(bytes.clone())
+                .add_record_filter(RecordFilter::EntryType(EntryType::TABLE_DUMP_V2))
+                .count_records(),
+            2
+        );
+
+        // a truncated record stops the scan with an error
+        let mut iter = BgpkitParser::from_reader(Cursor::new(bytes[..bytes.len() - 1].to_vec()))
+            .disable_warnings()
+            .scan_headers();
+        assert_eq!(iter.by_ref().count(), 4);
+        assert_eq!(iter.error_summary().io_errors, 1);
+    }
+
+    #[test]
+    fn test_time_bounded() {
+        let elems: Vec<BgpElem> = (0..10)
+            .map(|i| BgpElem {
+                timestamp: i as f64,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut iter = TimeBounded::new(elems.clone().into_iter()).until_timestamp(4.0);
+        assert_eq!(iter.by_ref().count(), 5);
+        assert_eq!(iter.stop_reason(), Some(StopReason::TimestampCutoff));
+        // the elem after the cutoff is consumed, but nothing more
+        assert_eq!(iter.into_inner().count(), 4);
+
+        let mut iter = TimeBounded::new(elems.into_iter()).with_deadline(Instant::now());
+        assert!(iter.next().is_none());
+        assert_eq!(iter.stop_reason(), Some(StopReason::Deadline));
+    }
+
+    #[test]
+    fn test_iterators_are_send() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<BgpkitParser<Box<dyn std::io::Read + Send>>>();
+        assert_send::<RecordIterator<Box<dyn std::io::Read + Send>>>();
+        assert_send::<ElemIterator<Box<dyn std::io::Read + Send>>>();
+        assert_send::<ElemIterator<MrtBytes>>();
+        assert_send::<TimeBounded<ElemIterator<MrtBytes>>>();
+        assert_sync::<ErrorSummary>();
+        assert_sync::<CancellationToken>();
+        assert_sync::<Elementor>();
+
+        let parser = BgpkitParser::from_bytes(vec![]).with_enricher(|_, _| {});
+        let count = std::thread::spawn(move || parser.into_elem_iter().count())
+            .join()
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
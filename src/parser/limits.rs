@@ -0,0 +1,135 @@
+/*!
+Limits on the work spent parsing a single record.
+
+Crafted records can make the parser loop over a large number of tiny attributes, prefixes or RIB
+entries within a single record. Services parsing untrusted files can bound the time and the
+number of steps spent on each record with [RecordLimits]: a record exceeding them fails with
+[ParserError::LimitExceeded], and the iterators skip it and continue with the next record.
+
+```no_run
+use bgpkit_parser::BgpkitParser;
+use std::time::Duration;
+
+let parser = BgpkitParser::new("uploaded.mrt.gz")
+    .unwrap()
+    .with_record_timeout(Duration::from_millis(100))
+    .with_record_step_limit(1_000_000);
+let mut iter = parser.into_elem_iter();
+let count = iter.by_ref().count();
+println!(
+    "{} elems, {} records over the limits",
+    count,
+    iter.error_summary().limit_exceeded
+);
+```
+
+A step is one attribute, AS path segment, community, NLRI prefix, table dump v2 RIB entry, or TLV.
+The limits are checked at each step, so the time spent on a single step, e.g. on one large
+attribute, is not interrupted. BMP messages are bounded the same way with
+[parse_bmp_msg_with_limits](crate::parser::bmp::parse_bmp_msg_with_limits).
+*/
+use crate::error::ParserError;
+use std::time::{Duration, Instant};
+
+/// Limits on the time and number of steps spent parsing the body of a single record.
+///
+/// The default has no limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordLimits {
+    /// maximum time spent parsing the body of a record
+    pub timeout: Option<Duration>,
+    /// maximum number of steps of parsing the body of a record
+    pub max_steps: Option<u64>,
+}
+
+impl RecordLimits {
+    /// Returns true if neither the time nor the number of steps is limited.
+    pub fn is_unlimited(&self) -> bool {
+        self.timeout.is_none() && self.max_steps.is_none()
+    }
+}
+
+/// Remaining budget of the record being parsed, held by the
+/// [ParseContext](crate::ParseContext) the record is parsed with.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    limits: RecordLimits,
+    deadline: Option<Instant>,
+    steps: u64,
+    exceeded: bool,
+}
+
+impl Budget {
+    /// Start parsing the body of a record with the given limits, or None if it is unlimited.
+    pub(crate) fn start(limits: &RecordLimits) -> Option<Budget> {
+        match limits.is_unlimited() {
+            true => None,
+            false => Some(Budget {
+                limits: *limits,
+                deadline: limits.timeout.map(|t| Instant::now() + t),
+                steps: 0,
+                exceeded: false,
+            }),
+        }
+    }
+
+    /// Count a step of parsing the record, returning an error once its limits are exceeded.
+    pub(crate) fn step(&mut self) -> Result<(), ParserError> {
+        self.steps += 1;
+        let over_steps = matches!(self.limits.max_steps, Some(max) if self.steps > max);
+        let over_time = matches!(self.deadline, Some(d) if Instant::now() >= d);
+        self.exceeded |= over_steps || over_time;
+        match self.exceeded {
+            true => Err(self.limit_exceeded_error()),
+            false => Ok(()),
+        }
+    }
+
+    /// The error to report for the record if the limits were exceeded while parsing it.
+    ///
+    /// Some errors of steps are only logged by the parser, e.g. of attributes other than the
+    /// last one, so the error is reported from here instead of relying on it to be propagated.
+    pub(crate) fn exceeded(&self) -> Option<ParserError> {
+        match self.exceeded {
+            true => Some(self.limit_exceeded_error()),
+            false => None,
+        }
+    }
+
+    fn limit_exceeded_error(&self) -> ParserError {
+        let reason = match self.limits.max_steps {
+            Some(max) if self.steps > max => format!("more than {} steps", max),
+            _ => format!("more than {:?}", self.limits.timeout.unwrap_or_default()),
+        };
+        ParserError::LimitExceeded(format!("parsing record took {}", reason))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_steps() {
+        assert!(Budget::start(&RecordLimits::default()).is_none());
+
+        let limits = RecordLimits {
+            timeout: None,
+            max_steps: Some(2),
+        };
+        let mut budget = Budget::start(&limits).unwrap();
+        assert!(budget.step().is_ok());
+        assert!(budget.step().is_ok());
+        assert!(budget.exceeded().is_none());
+        assert!(matches!(budget.step(), Err(ParserError::LimitExceeded(_))));
+        assert!(budget.exceeded().is_some());
+
+        let limits = RecordLimits {
+            timeout: Some(Duration::ZERO),
+            max_steps: None,
+        };
+        let mut budget = Budget::start(&limits).unwrap();
+        assert!(budget.step().is_err());
+        assert!(budget.exceeded().is_some());
+    }
+}
@@ -1,35 +1,130 @@
 /*!
 parser module maintains the main logic for processing BGP and MRT messages.
 */
-use std::io::Read;
+use bytes::Bytes;
+use std::io::{BufReader, Read};
+use std::time::Duration;
 
 #[macro_use]
 pub mod utils;
+pub mod analysis;
 pub mod bgp;
 pub mod bmp;
+pub mod census;
+pub mod compare;
+pub mod demux;
+#[cfg(feature = "resumable")]
+pub mod download;
+pub mod duplicates;
 pub mod filter;
 pub mod iters;
+pub mod limits;
 pub mod mrt;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pipeline;
+#[cfg(feature = "oneio")]
+pub mod pool;
+pub mod prefetch;
+pub mod replay;
+pub mod rib;
+pub(crate) mod scratch;
+pub mod sort;
+pub mod stats;
+#[cfg(test)]
+pub(crate) mod test_elems;
+pub mod tlv;
+pub mod validate;
 
+#[cfg(feature = "irr")]
+pub mod irr;
+#[cfg(feature = "pcap")]
+pub mod pcap;
 #[cfg(feature = "rislive")]
 pub mod rislive;
+#[cfg(feature = "rpki")]
+pub mod rpki;
+
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
+#[cfg(feature = "async")]
+pub mod async_parser;
 
 pub(crate) use self::utils::*;
 
-use crate::models::MrtRecord;
+use crate::models::{BgpElem, ConfedSegments, MrtRecord};
 pub use mrt::mrt_elem::Elementor;
 #[cfg(feature = "oneio")]
 use oneio::{get_cache_reader, get_reader};
 
 pub use crate::error::{ParserError, ParserErrorWithBytes};
-pub use bmp::{parse_bmp_msg, parse_openbmp_header, parse_openbmp_msg};
+#[cfg(feature = "async")]
+pub use async_parser::AsyncBgpkitParser;
+pub use bmp::{
+    encode_openbmp_header, encode_openbmp_msg, parse_bmp_msg, parse_bmp_msg_with_limits,
+    parse_openbmp_header, parse_openbmp_msg,
+};
+pub use census::MrtCensus;
+#[cfg(feature = "oneio")]
+pub use compare::compare_mrt_files;
+pub use compare::{compare_mrt_records, records_equivalent, MrtComparison};
+pub use demux::{demux_by_peer, PeerKey, PeerStream};
+#[cfg(feature = "resumable")]
+pub use download::{ResumableReader, RetryPolicy};
+pub use duplicates::{
+    flag_duplicates, suppress_duplicates, DuplicateTracker, FlaggedDuplicates, SuppressedDuplicates,
+};
 pub use filter::*;
 pub use iters::*;
+pub use limits::RecordLimits;
 pub use mrt::*;
+#[cfg(feature = "parallel")]
+pub use parallel::{ParallelConfig, ParallelElemIterator};
+#[cfg(feature = "rpki")]
+pub use pipeline::EnricherConfig;
+pub use pipeline::{Enricher, Pipeline, PipelineConfig, PipelineIter, StageConfig};
+#[cfg(feature = "oneio")]
+pub use pool::{BgpkitParserPool, FileSummary, PoolElemIterator};
+pub use prefetch::PrefetchReader;
+#[cfg(feature = "oneio")]
+pub use sort::merge_mrt_files;
+pub use sort::{
+    merge_elems_stable, merge_records_stable, sort_elems_stable, MergedElems, MergedRecords,
+};
+pub use stats::{ApproxElemStats, HyperLogLog, TopK, TopKCount};
+pub use tlv::{Tlv, TlvFieldWidth, TlvReader};
+pub use validate::{ValidationIssue, ValidationReport};
 
 #[cfg(feature = "rislive")]
 pub use rislive::parse_ris_live_message;
 
+/// Document holding a list of serialized filters.
+#[cfg(all(feature = "serde", any(feature = "serde_json", feature = "toml")))]
+#[derive(serde::Deserialize)]
+struct FiltersConfig {
+    filters: Vec<Filter>,
+}
+
+/// Parser of MRT data from a reader `R`, turned into iterators of [MrtRecord]s or [BgpElem]s.
+///
+/// # Thread safety
+///
+/// The parser and its iterators ([RecordIterator], [ElemIterator] and the iterators built on
+/// them) are [Send] if the reader is, so they can be moved into another thread, e.g. with
+/// [std::thread::spawn], `tokio::task::spawn_blocking` or a rayon scope. Parsers created with
+/// [BgpkitParser::new] read from a `Box<dyn Read + Send>` and are always [Send].
+///
+/// They are not [Sync]: iterating requires `&mut` access, and the callback of
+/// [BgpkitParser::with_enricher] is only required to be [Send]. Parse different files on
+/// different threads instead of sharing one parser.
+///
+/// ```no_run
+/// use bgpkit_parser::BgpkitParser;
+///
+/// let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+/// let handle = std::thread::spawn(move || parser.into_elem_iter().count());
+/// println!("{} elems", handle.join().unwrap());
+/// ```
 pub struct BgpkitParser<R> {
     reader: R,
     core_dump: bool,
@@ -39,11 +134,33 @@ pub struct BgpkitParser<R> {
 
 pub(crate) struct ParserOptions {
     show_warnings: bool,
+    cancellation: CancellationToken,
+    record_filters: Vec<RecordFilter>,
+    confed: Option<ConfedSegments>,
+    enricher: Option<ElemEnricher>,
+    record_limits: RecordLimits,
+    record_provenance: bool,
 }
 impl Default for ParserOptions {
     fn default() -> Self {
         ParserOptions {
             show_warnings: true,
+            cancellation: CancellationToken::new(),
+            record_filters: vec![],
+            confed: None,
+            enricher: None,
+            record_limits: RecordLimits::default(),
+            record_provenance: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Create an [Elementor] for the options of the parser.
+    pub(crate) fn elementor(&self) -> Elementor {
+        match self.confed {
+            Some(confed) => Elementor::new().with_confed_segments(confed),
+            None => Elementor::new(),
         }
     }
 }
@@ -51,7 +168,11 @@ impl Default for ParserOptions {
 #[cfg(feature = "oneio")]
 impl BgpkitParser<Box<dyn Read + Send>> {
     /// Creating a new parser from a object that implements [Read] trait.
+    ///
+    /// The file is decompressed according to its extension: `.gz` and `.bz2` are always
+    /// supported, `.xz`, `.lz4` and `.zst` require the `xz`, `lz` and `zstd` features respectively.
     pub fn new(path: &str) -> Result<Self, ParserErrorWithBytes> {
+        check_compression_support(path)?;
         let reader = get_reader(path)?;
         Ok(BgpkitParser {
             reader,
@@ -67,6 +188,7 @@ impl BgpkitParser<Box<dyn Read + Send>> {
     /// For example, the remote file `http://archive.routeviews.org/route-views.chile/bgpdata/2023.03/RIBS/rib.20230326.0600.bz2`
     /// will be cached as `cache-682cb1eb-rib.20230326.0600.bz2` in the cache directory.
     pub fn new_cached(path: &str, cache_dir: &str) -> Result<Self, ParserErrorWithBytes> {
+        check_compression_support(path)?;
         let file_name = path.rsplit('/').next().unwrap().to_string();
         let new_file_name = format!(
             "cache-{}",
@@ -82,6 +204,23 @@ impl BgpkitParser<Box<dyn Read + Send>> {
     }
 }
 
+/// Fail with the feature to enable if the compression of the file, selected by its extension, is
+/// not compiled in, instead of the generic error of oneio.
+#[cfg(feature = "oneio")]
+fn check_compression_support(path: &str) -> Result<(), ParserError> {
+    let extension = path.rsplit('/').next().unwrap_or(path).rsplit('.').next();
+    let feature = match extension {
+        Some("xz" | "lzma") if cfg!(not(feature = "xz")) => "xz",
+        Some("lz4" | "lz") if cfg!(not(feature = "lz")) => "lz",
+        Some("zst" | "zstd") if cfg!(not(feature = "zstd")) => "zstd",
+        _ => return Ok(()),
+    };
+    Err(ParserError::Unsupported(format!(
+        "cannot decompress {}: enable the `{}` feature of bgpkit-parser",
+        path, feature
+    )))
+}
+
 #[cfg(feature = "oneio")]
 fn add_suffix_to_filename(filename: &str, suffix: &str) -> String {
     let mut parts: Vec<&str> = filename.split('.').collect(); // Split filename by dots
@@ -107,13 +246,126 @@ impl<R: Read> BgpkitParser<R> {
         }
     }
 
+    /// Read the input through a buffer of the given size, in bytes.
+    ///
+    /// The parser reads every record with small reads, e.g. of its 12-byte header, each going
+    /// through the decompressor and, for remote files, the network stream. A larger buffer turns
+    /// them into fewer large reads from the decompressor, which helps most when streaming remote
+    /// files over high-latency links.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz")
+    ///     .unwrap()
+    ///     .with_buffer_size(4 * 1024 * 1024);
+    /// for elem in parser {
+    ///     println!("{}", elem);
+    /// }
+    /// ```
+    pub fn with_buffer_size(self, bytes: usize) -> BgpkitParser<BufReader<R>> {
+        BgpkitParser {
+            reader: BufReader::with_capacity(bytes, self.reader),
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options: self.options,
+        }
+    }
+}
+
+impl BgpkitParser<MrtBytes> {
+    /// Creating a new parser from uncompressed MRT data already held in memory, e.g. a Kafka
+    /// payload or an object store download.
+    ///
+    /// Unlike [BgpkitParser::from_reader], record bodies are parsed directly from slices of the
+    /// data without being copied into an intermediate buffer.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let data = std::fs::read("updates.20230215.0630").unwrap();
+    /// for elem in BgpkitParser::from_bytes(data) {
+    ///     println!("{}", elem);
+    /// }
+    /// ```
+    pub fn from_bytes<B: Into<Bytes>>(data: B) -> Self {
+        BgpkitParser {
+            reader: MrtBytes::new(data.into()),
+            core_dump: false,
+            filters: vec![],
+            options: ParserOptions::default(),
+        }
+    }
+
+    /// Creating a new parser from a local uncompressed MRT file, memory-mapped instead of read.
+    ///
+    /// Like [BgpkitParser::from_bytes], records are parsed directly from the mapped file without
+    /// being copied into intermediate buffers, which is much faster than [BgpkitParser::new] for
+    /// large uncompressed RIB dumps. Compressed files are not supported.
+    ///
+    /// The file must not be modified or truncated while the parser is in use: the mapped data would
+    /// change under the parser, and reading truncated parts of the file crashes the process.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let parser = BgpkitParser::new_mmap("bview.20230215.0000").unwrap();
+    /// for elem in parser {
+    ///     println!("{}", elem);
+    /// }
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub fn new_mmap<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ParserErrorWithBytes> {
+        let file = std::fs::File::open(path).map_err(ParserError::from)?;
+        // SAFETY: the file is required not to be modified while mapped, see above
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(ParserError::from)?;
+        Ok(BgpkitParser::from_bytes(Bytes::from_owner(mmap)))
+    }
+}
+
+impl<R: MrtRecordSource> BgpkitParser<R> {
     /// This is used in for loop `for item in parser{}`
     pub fn next_record(&mut self) -> Result<MrtRecord, ParserErrorWithBytes> {
-        parse_mrt_record(&mut self.reader)
+        let mut context = ParseContext::new().with_limits(self.options.record_limits);
+        self.reader
+            .next_matching_mrt_record(&mut context, &self.options.record_filters)
+    }
+
+    /// Read the next record, reusing the buffers of the given [ParseContext].
+    ///
+    /// The [RecordLimits] of the parser, if set, replace those of the context.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::{BgpkitParser, ParseContext};
+    ///
+    /// let mut parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+    /// let mut context = ParseContext::new();
+    /// while let Ok(record) = parser.next_record_with_context(&mut context) {
+    ///     println!("{}", record.common_header.timestamp);
+    /// }
+    /// ```
+    pub fn next_record_with_context(
+        &mut self,
+        context: &mut ParseContext,
+    ) -> Result<MrtRecord, ParserErrorWithBytes> {
+        if !self.options.record_limits.is_unlimited() {
+            context.set_limits(self.options.record_limits);
+        }
+        self.reader
+            .next_matching_mrt_record(context, &self.options.record_filters)
     }
 }
 
 impl<R> BgpkitParser<R> {
+    /// Get a handle to cancel the iteration over this parser.
+    ///
+    /// The returned [CancellationToken] can be cloned and moved to other threads. Once
+    /// [CancellationToken::cancel] is called, the record and elem iterators created from this
+    /// parser stop at the next item, and their error summaries remain available.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.options.cancellation.clone()
+    }
+
     pub fn enable_core_dump(self) -> Self {
         BgpkitParser {
             reader: self.reader,
@@ -148,6 +400,173 @@ impl<R> BgpkitParser<R> {
             options: self.options,
         })
     }
+
+    /// Add the filters of a JSON document of the form
+    /// `{"filters": [{"filter_type": "peer_asn", "value": "13335"}]}`. See [Filter::type_and_value].
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn add_filters_from_json(self, json: &str) -> Result<Self, ParserErrorWithBytes> {
+        let config: FiltersConfig = serde_json::from_str(json)
+            .map_err(|e| ParserError::FilterError(format!("invalid filters JSON: {}", e)))?;
+        Ok(self.add_filters(config.filters))
+    }
+
+    /// Add the filters of a TOML document with a `[[filters]]` array of tables, each with a
+    /// `filter_type` and a `value`. See [Filter::type_and_value].
+    #[cfg(all(feature = "serde", feature = "toml"))]
+    pub fn add_filters_from_toml(self, toml: &str) -> Result<Self, ParserErrorWithBytes> {
+        let config: FiltersConfig = toml::from_str(toml)
+            .map_err(|e| ParserError::FilterError(format!("invalid filters TOML: {}", e)))?;
+        Ok(self.add_filters(config.filters))
+    }
+
+    #[cfg(all(feature = "serde", any(feature = "serde_json", feature = "toml")))]
+    fn add_filters(self, new_filters: Vec<Filter>) -> Self {
+        let mut filters = self.filters;
+        filters.extend(new_filters);
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters,
+            options: self.options,
+        }
+    }
+
+    /// Set how the confederation segments of AS paths are handled in the elems of this parser,
+    /// including the origins and the AS path filters. See [Elementor::with_confed_segments].
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::BgpkitParser;
+    /// use bgpkit_parser::models::ConfedSegments;
+    ///
+    /// let parser = BgpkitParser::new("updates.bmp.mrt").unwrap()
+    ///     .with_confed_segments(ConfedSegments::Include)
+    ///     .add_filter("as_path_contains", "65001").unwrap();
+    /// ```
+    pub fn with_confed_segments(self, confed: ConfedSegments) -> Self {
+        let mut options = self.options;
+        options.confed = Some(confed);
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
+
+    /// Bound the time spent parsing the body of a single record.
+    ///
+    /// Records taking longer fail with [ParserError::LimitExceeded] and are skipped by the
+    /// iterators, see [limits] for the details.
+    pub fn with_record_timeout(self, timeout: Duration) -> Self {
+        let mut options = self.options;
+        options.record_limits.timeout = Some(timeout);
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
+
+    /// Bound the number of steps, e.g. attributes or prefixes, of parsing the body of a single
+    /// record.
+    ///
+    /// Records with more steps fail with [ParserError::LimitExceeded] and are skipped by the
+    /// iterators, see [limits] for the details.
+    pub fn with_record_step_limit(self, max_steps: u64) -> Self {
+        let mut options = self.options;
+        options.record_limits.max_steps = Some(max_steps);
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
+
+    /// Set the position of the record each elem was created from, i.e. [BgpElem::record_index]
+    /// and [BgpElem::record_offset], e.g. to extract the record of an unexpected elem with
+    /// [extract_records].
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::{extract_records, BgpkitParser, RecordPosition};
+    ///
+    /// let parser = BgpkitParser::new("rib.20240101.0000.bz2")
+    ///     .unwrap()
+    ///     .with_record_provenance();
+    /// for elem in parser {
+    ///     if elem.as_path.is_none() {
+    ///         let offset = elem.record_offset.unwrap();
+    ///         println!("{} from the record at byte {}", elem.prefix, offset);
+    ///         let mut input = oneio::get_reader("rib.20240101.0000.bz2").unwrap();
+    ///         let mut output = std::fs::File::create("record.mrt").unwrap();
+    ///         extract_records(&mut input, &[RecordPosition::Offset(offset)], &mut output).unwrap();
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    pub fn with_record_provenance(self) -> Self {
+        let mut options = self.options;
+        options.record_provenance = true;
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
+
+    /// Call `enricher` on every elem of the elem iterator, before the elem filters are applied.
+    ///
+    /// Besides the elem, the enricher gets an [ElemContext] with the common header of the record
+    /// the elem was created from, the peer index table of table dumps, and the confederation
+    /// settings of the parser, so that elems can be annotated without reimplementing the
+    /// [Elementor]. Setting another enricher replaces the previous one.
+    ///
+    /// ```no_run
+    /// use bgpkit_parser::models::{LargeCommunity, MetaCommunity};
+    /// use bgpkit_parser::BgpkitParser;
+    ///
+    /// let parser = BgpkitParser::new("rib.20240101.0000.bz2")
+    ///     .unwrap()
+    ///     .with_enricher(|elem, context| {
+    ///         // tag the elems with the BGP ID of the collector of the RIB dump
+    ///         if let Some(table) = context.peer_table {
+    ///             let tag = LargeCommunity::new(64496, [0, table.collector_bgp_id.to_u32()]);
+    ///             elem.communities
+    ///                 .get_or_insert_with(Vec::new)
+    ///                 .push(MetaCommunity::Large(tag));
+    ///         }
+    ///     });
+    /// ```
+    pub fn with_enricher<F>(self, enricher: F) -> Self
+    where
+        F: FnMut(&mut BgpElem, &ElemContext) + Send + 'static,
+    {
+        let mut options = self.options;
+        options.enricher = Some(Box::new(enricher));
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
+
+    /// Add a filter on the MRT common header of records.
+    ///
+    /// Unlike [BgpkitParser::add_filter], record filters are evaluated before the record body is
+    /// parsed, and the bodies of records not matching them are skipped entirely.
+    pub fn add_record_filter(self, filter: RecordFilter) -> Self {
+        let mut options = self.options;
+        options.record_filters.push(filter);
+        BgpkitParser {
+            reader: self.reader,
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -184,4 +603,115 @@ mod tests {
         let count = parser.into_elem_iter().count();
         assert_eq!(8160, count);
     }
+
+    #[test]
+    fn test_with_buffer_size() {
+        use crate::test_vectors;
+        use std::io::Cursor;
+
+        for vector in test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+        {
+            for size in [1, 16, 1 << 20] {
+                let elems: Vec<BgpElem> =
+                    BgpkitParser::from_reader(Cursor::new(vector.bytes.clone()))
+                        .with_buffer_size(size)
+                        .into_elem_iter()
+                        .collect();
+                assert_eq!(elems, vector.expected, "{} {}", vector.name, size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_compressed() {
+        use crate::test_vectors;
+        use std::io::Write;
+
+        let vector = test_vectors::mrt_updates().remove(0);
+        for (extension, supported) in [
+            ("gz", true),
+            ("bz2", true),
+            ("xz", cfg!(feature = "xz")),
+            ("lz4", cfg!(feature = "lz")),
+            ("zst", cfg!(feature = "zstd")),
+        ] {
+            let path =
+                std::env::temp_dir().join(format!("bgpkit-parser-test-new.mrt.{}", extension));
+            let path = path.to_str().unwrap();
+            if !supported {
+                let error = BgpkitParser::new(path).err().unwrap().error;
+                assert!(
+                    matches!(error, ParserError::Unsupported(_)),
+                    "{}",
+                    extension
+                );
+                continue;
+            }
+            // oneio does not write lz4
+            if extension == "lz4" {
+                continue;
+            }
+            let mut writer = oneio::get_writer(path).unwrap();
+            writer.write_all(&vector.bytes).unwrap();
+            drop(writer);
+            let elems: Vec<BgpElem> = BgpkitParser::new(path).unwrap().into_iter().collect();
+            assert_eq!(elems, vector.expected, "{}", extension);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_new_mmap() {
+        use crate::test_vectors;
+        use std::io::Write;
+
+        for vector in test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+        {
+            let mut file = tempfile::NamedTempFile::new().unwrap();
+            file.write_all(&vector.bytes).unwrap();
+            let elems: Vec<BgpElem> = BgpkitParser::new_mmap(file.path())
+                .unwrap()
+                .into_elem_iter()
+                .collect();
+            assert_eq!(elems, vector.expected, "{}", vector.name);
+        }
+
+        assert!(BgpkitParser::new_mmap("/nonexistent/bgpkit-parser-test.mrt").is_err());
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "serde_json", feature = "toml"))]
+    fn test_add_filters_from_config() {
+        let json = r#"{"filters": [{"filter_type": "peer_asn", "value": "13335"}]}"#;
+        let parser = BgpkitParser::from_reader(std::io::empty())
+            .add_filters_from_json(json)
+            .unwrap();
+        assert_eq!(parser.filters, vec![Filter::PeerAsn(13335)]);
+
+        let toml = r#"
+            [[filters]]
+            filter_type = "type"
+            value = "a"
+
+            [[filters]]
+            filter_type = "exclude_peer_asn"
+            value = "13335"
+        "#;
+        let parser = parser.add_filters_from_toml(toml).unwrap();
+        assert_eq!(parser.filters.len(), 3);
+        assert_eq!(
+            parser.filters[2],
+            Filter::Exclude(Box::new(Filter::PeerAsn(13335)))
+        );
+
+        let invalid = r#"{"filters": [{"filter_type": "peer_asn", "value": "x"}]}"#;
+        assert!(BgpkitParser::from_reader(std::io::empty())
+            .add_filters_from_json(invalid)
+            .is_err());
+    }
 }
@@ -1,7 +1,7 @@
 use crate::error::ParserError;
 use crate::models::*;
-use crate::parser::bgp::messages::parse_bgp_message;
-use crate::parser::{encode_asn, encode_ipaddr, ReadUtils};
+use crate::parser::bgp::messages::parse_bgp_message_with_context;
+use crate::parser::{encode_asn, encode_ipaddr, ParseContext, ReadUtils};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryFrom;
 
@@ -10,6 +10,15 @@ use std::convert::TryFrom;
 /// RFC: <https://www.rfc-editor.org/rfc/rfc6396#section-4.4>
 ///
 pub fn parse_bgp4mp(sub_type: u16, input: Bytes) -> Result<Bgp4MpEnum, ParserError> {
+    parse_bgp4mp_with_context(sub_type, input, &mut ParseContext::new())
+}
+
+/// Parse MRT BGP4MP type with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_bgp4mp_with_context(
+    sub_type: u16,
+    input: Bytes,
+    context: &mut ParseContext,
+) -> Result<Bgp4MpEnum, ParserError> {
     let bgp4mp_type: Bgp4MpType = Bgp4MpType::try_from(sub_type)?;
     let msg: Bgp4MpEnum = match bgp4mp_type {
         Bgp4MpType::StateChange => Bgp4MpEnum::StateChange(parse_bgp4mp_state_change(
@@ -23,16 +32,16 @@ pub fn parse_bgp4mp(sub_type: u16, input: Bytes) -> Result<Bgp4MpEnum, ParserErr
             &bgp4mp_type,
         )?),
         Bgp4MpType::Message | Bgp4MpType::MessageLocal => Bgp4MpEnum::Message(
-            parse_bgp4mp_message(input, false, AsnLength::Bits16, &bgp4mp_type)?,
+            parse_bgp4mp_message(input, false, AsnLength::Bits16, &bgp4mp_type, context)?,
         ),
         Bgp4MpType::MessageAs4 | Bgp4MpType::MessageAs4Local => Bgp4MpEnum::Message(
-            parse_bgp4mp_message(input, false, AsnLength::Bits32, &bgp4mp_type)?,
+            parse_bgp4mp_message(input, false, AsnLength::Bits32, &bgp4mp_type, context)?,
         ),
         Bgp4MpType::MessageAddpath | Bgp4MpType::MessageLocalAddpath => Bgp4MpEnum::Message(
-            parse_bgp4mp_message(input, true, AsnLength::Bits16, &bgp4mp_type)?,
+            parse_bgp4mp_message(input, true, AsnLength::Bits16, &bgp4mp_type, context)?,
         ),
         Bgp4MpType::MessageAs4Addpath | Bgp4MpType::MessageLocalAs4Addpath => Bgp4MpEnum::Message(
-            parse_bgp4mp_message(input, true, AsnLength::Bits32, &bgp4mp_type)?,
+            parse_bgp4mp_message(input, true, AsnLength::Bits32, &bgp4mp_type, context)?,
         ),
     };
 
@@ -70,6 +79,7 @@ pub fn parse_bgp4mp_message(
     add_path: bool,
     asn_len: AsnLength,
     msg_type: &Bgp4MpType,
+    context: &mut ParseContext,
 ) -> Result<Bgp4MpMessage, ParserError> {
     let total_size = data.len();
 
@@ -88,7 +98,8 @@ pub fn parse_bgp4mp_message(
             data.remaining()
         )));
     }
-    let bgp_message: BgpMessage = parse_bgp_message(&mut data, add_path, &asn_len)?;
+    let bgp_message: BgpMessage =
+        parse_bgp_message_with_context(&mut data, add_path, &asn_len, context)?;
 
     Ok(Bgp4MpMessage {
         msg_type: *msg_type,
@@ -104,8 +115,8 @@ pub fn parse_bgp4mp_message(
 impl Bgp4MpMessage {
     pub fn encode(&self, add_path: bool, asn_len: AsnLength) -> Bytes {
         let mut bytes = BytesMut::new();
-        bytes.extend(self.peer_asn.encode());
-        bytes.extend(self.local_asn.encode());
+        bytes.extend(encode_asn(&self.peer_asn, &asn_len));
+        bytes.extend(encode_asn(&self.local_asn, &asn_len));
         bytes.put_u16(self.interface_index);
         bytes.put_u16(address_family(&self.peer_ip));
         bytes.extend(encode_ipaddr(&self.peer_ip));
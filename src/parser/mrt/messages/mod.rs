@@ -12,6 +12,7 @@ impl MrtMessage {
             MrtMessage::TableDumpV2Message(m) => match m {
                 TableDumpV2Message::PeerIndexTable(p) => p.encode(),
                 TableDumpV2Message::RibAfi(r) => r.encode(),
+                TableDumpV2Message::GeoPeerTable(g) => g.encode(),
                 TableDumpV2Message::RibGeneric(_) => {
                     todo!("RibGeneric message is not supported yet");
                 }
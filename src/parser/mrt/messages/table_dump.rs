@@ -1,10 +1,11 @@
 use crate::error::*;
 use crate::models::*;
-use crate::parser::bgp::attributes::parse_attributes;
-use crate::parser::ReadUtils;
+use crate::parser::bgp::attributes::parse_attributes_with_context;
+use crate::parser::{ParseContext, ReadUtils};
 use bytes::{BufMut, Bytes, BytesMut};
 use ipnet::IpNet;
-use std::net::IpAddr;
+use log::warn;
+use std::net::{IpAddr, Ipv4Addr};
 
 /// Parse MRT TABLE_DUMP type message.
 ///
@@ -36,8 +37,17 @@ use std::net::IpAddr;
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 /// ```
 pub fn parse_table_dump_message(
+    sub_type: u16,
+    data: Bytes,
+) -> Result<TableDumpMessage, ParserError> {
+    parse_table_dump_message_with_context(sub_type, data, &mut ParseContext::new())
+}
+
+/// Parse MRT TABLE_DUMP type message with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_table_dump_message_with_context(
     sub_type: u16,
     mut data: Bytes,
+    context: &mut ParseContext,
 ) -> Result<TableDumpMessage, ParserError> {
     // ####
     // Step 0. prepare
@@ -93,8 +103,15 @@ pub fn parse_table_dump_message(
     let attr_data_slice = data.split_to(attribute_length);
 
     // for TABLE_DUMP type, the AS number length is always 2-byte.
-    let attributes =
-        parse_attributes(attr_data_slice, &AsnLength::Bits16, false, None, None, None)?;
+    let attributes = parse_attributes_with_context(
+        attr_data_slice,
+        &AsnLength::Bits16,
+        false,
+        None,
+        None,
+        None,
+        context,
+    )?;
 
     Ok(TableDumpMessage {
         view_number,
@@ -109,6 +126,16 @@ pub fn parse_table_dump_message(
 }
 
 impl TableDumpMessage {
+    /// Encode the entry. Use [TableDumpMessage::subtype] as the subtype of its MRT record.
+    ///
+    /// TABLE_DUMP entries only have 2-byte AS numbers. As in BGP sessions with old speakers
+    /// ([RFC6793](https://datatracker.ietf.org/doc/html/rfc6793)), 4-byte peer, `AS_PATH` and
+    /// `AGGREGATOR` AS numbers are written as `AS_TRANS`, and the actual path and aggregator are
+    /// added as `AS4_PATH` and `AS4_AGGREGATOR` attributes unless already present.
+    ///
+    /// The peer address is written in the address family of the prefix: IPv4 peers of IPv6
+    /// prefixes are written as IPv4-mapped IPv6 addresses. IPv6 peers of IPv4 prefixes can not be
+    /// represented, and are written as `0.0.0.0` unless IPv4-mapped.
     pub fn encode(&self) -> Bytes {
         let mut bytes = BytesMut::new();
         bytes.put_u16(self.view_number);
@@ -126,25 +153,26 @@ impl TableDumpMessage {
         bytes.put_u8(self.status);
         bytes.put_u32(self.originated_time as u32);
 
-        // peer address and peer asn
-        match self.peer_address {
-            IpAddr::V4(a) => {
-                bytes.put_u32(a.into());
+        // peer address, in the address family of the prefix, and peer asn
+        match (&self.prefix.prefix, self.peer_address) {
+            (IpNet::V4(_), IpAddr::V4(a)) => bytes.put_u32(a.into()),
+            (IpNet::V4(_), IpAddr::V6(a)) => {
+                let mapped = a.to_ipv4_mapped().unwrap_or_else(|| {
+                    warn!(
+                        "IPv6 peer {} of IPv4 TABLE_DUMP entry written as 0.0.0.0",
+                        a
+                    );
+                    Ipv4Addr::UNSPECIFIED
+                });
+                bytes.put_u32(mapped.into());
             }
-            IpAddr::V6(a) => {
-                bytes.put_u128(a.into());
-            }
-        }
-        bytes.put_u16(self.peer_asn.into());
-
-        // encode attributes
-        let mut attr_bytes = BytesMut::new();
-        for attr in &self.attributes.inner {
-            // add_path always false for v1 table dump
-            // asn_len always 16 bites
-            attr_bytes.extend(attr.encode(false, AsnLength::Bits16));
+            (IpNet::V6(_), IpAddr::V4(a)) => bytes.put_u128(a.to_ipv6_mapped().into()),
+            (IpNet::V6(_), IpAddr::V6(a)) => bytes.put_u128(a.into()),
         }
+        bytes.put_u16(as_trans_asn(&self.peer_asn).into());
 
+        // add_path always false and asn_len always 16 bits for v1 table dump
+        let attr_bytes = encode_attributes_as16(&self.attributes);
         bytes.put_u16(attr_bytes.len() as u16);
         bytes.put_slice(&attr_bytes);
 
@@ -152,6 +180,81 @@ impl TableDumpMessage {
     }
 }
 
+/// The ASN, or `AS_TRANS` if it does not fit in 2 bytes.
+fn as_trans_asn(asn: &Asn) -> Asn {
+    match u16::try_from(asn.to_u32()) {
+        Ok(v) => Asn::new_16bit(v),
+        Err(_) => Asn::TRANSITION,
+    }
+}
+
+/// Encode attributes with 2-byte AS numbers, adding `AS4_PATH` and `AS4_AGGREGATOR` for 4-byte
+/// AS numbers replaced by `AS_TRANS`.
+fn encode_attributes_as16(attributes: &Attributes) -> Bytes {
+    let has_as4_path = attributes.has_attr(AttrType::AS4_PATH);
+    let has_as4_aggregator = attributes.has_attr(AttrType::AS4_AGGREGATOR);
+    let mut as4_values = vec![];
+    let mut bytes = BytesMut::new();
+    for attr in attributes.inner.iter() {
+        let value = match &attr.value {
+            AttributeValue::AsPath {
+                path,
+                is_as4: false,
+            } if matches!(path.required_asn_length(), AsnLength::Bits32) => {
+                if !has_as4_path {
+                    as4_values.push(AttributeValue::AsPath {
+                        path: path.without_confed(),
+                        is_as4: true,
+                    });
+                }
+                let mut path = path.clone();
+                for segment in path.iter_segments_mut() {
+                    for asn in segment.iter_mut() {
+                        *asn = as_trans_asn(asn);
+                    }
+                }
+                AttributeValue::AsPath {
+                    path,
+                    is_as4: false,
+                }
+            }
+            AttributeValue::Aggregator { asn, id, is_as4 } => {
+                let asn = match *is_as4 {
+                    true => Asn::new_32bit(asn.to_u32()),
+                    false => {
+                        if asn.to_u32() > u16::MAX as u32 && !has_as4_aggregator {
+                            as4_values.push(AttributeValue::Aggregator {
+                                asn: Asn::new_32bit(asn.to_u32()),
+                                id: *id,
+                                is_as4: true,
+                            });
+                        }
+                        as_trans_asn(asn)
+                    }
+                };
+                AttributeValue::Aggregator {
+                    asn,
+                    id: *id,
+                    is_as4: *is_as4,
+                }
+            }
+            _ => {
+                bytes.extend(attr.encode(false, AsnLength::Bits16));
+                continue;
+            }
+        };
+        let attr = Attribute {
+            value,
+            flag: attr.flag,
+        };
+        bytes.extend(attr.encode(false, AsnLength::Bits16));
+    }
+    for value in as4_values {
+        bytes.extend(Attribute::from(value).encode(false, AsnLength::Bits16));
+    }
+    bytes.freeze()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,6 +311,86 @@ mod tests {
         let encoded = table_dump_message.encode();
         assert_eq!(encoded, bytes);
     }
+    #[test]
+    fn test_encode_table_dump_message_as4() {
+        let path = AsPath::from_sequence([65000u32, 400000]);
+        let identifier = BgpIdentifier::from(Ipv4Addr::new(192, 0, 2, 9));
+        let message = TableDumpMessage {
+            view_number: 1,
+            sequence_number: 2,
+            prefix: NetworkPrefix::new("192.0.2.0/24".parse().unwrap(), 0),
+            status: 1,
+            originated_time: 1700000000,
+            peer_address: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            peer_asn: Asn::new_32bit(400000),
+            attributes: Attributes::from_iter([
+                AttributeValue::Origin(Origin::IGP),
+                AttributeValue::AsPath {
+                    path: path.clone(),
+                    is_as4: false,
+                },
+                AttributeValue::NextHop(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+                AttributeValue::Aggregator {
+                    asn: Asn::new_32bit(400000),
+                    id: identifier,
+                    is_as4: false,
+                },
+            ]),
+        };
+        assert_eq!(message.subtype(), 1);
+        let encoded = message.encode();
+
+        let parsed = parse_table_dump_message(message.subtype(), encoded.clone()).unwrap();
+        assert_eq!(parsed.view_number, 1);
+        assert_eq!(parsed.sequence_number, 2);
+        assert_eq!(parsed.prefix, message.prefix);
+        assert_eq!(parsed.status, 1);
+        assert_eq!(parsed.originated_time, 1700000000);
+        assert_eq!(parsed.peer_address, message.peer_address);
+        assert_eq!(parsed.peer_asn, Asn::TRANSITION);
+        assert_eq!(
+            parsed.raw_as_path(),
+            Some(&AsPath::from_sequence([65000u32, 23456]))
+        );
+        assert_eq!(parsed.as_path(), Some(path));
+        let aggregators: Vec<(Asn, bool)> = parsed
+            .attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                AttributeValue::Aggregator { asn, is_as4, .. } => Some((*asn, *is_as4)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            aggregators,
+            vec![(Asn::TRANSITION, false), (Asn::new_32bit(400000), true)]
+        );
+
+        // re-encoding the parsed entry gives the same bytes
+        assert_eq!(parsed.encode(), encoded);
+    }
+
+    #[test]
+    fn test_encode_table_dump_message_mapped_peer() {
+        let message = TableDumpMessage {
+            view_number: 0,
+            sequence_number: 0,
+            prefix: NetworkPrefix::new("2001:db8::/32".parse().unwrap(), 0),
+            status: 1,
+            originated_time: 0,
+            peer_address: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            peer_asn: Asn::new_16bit(65000),
+            attributes: Attributes::default(),
+        };
+        assert_eq!(message.subtype(), 2);
+        let parsed = parse_table_dump_message(2, message.encode()).unwrap();
+        assert_eq!(
+            parsed.peer_address,
+            IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped())
+        );
+        assert_eq!(parsed.peer_asn, Asn::new_16bit(65000));
+    }
+
     #[test]
     fn test_parse_table_dump_message_ipv6() {
         let mut bytes_mut = BytesMut::new();
@@ -0,0 +1,91 @@
+use crate::models::{BgpIdentifier, GeoLocation, GeoPeer, GeoPeerTable, PeerType};
+use crate::parser::ReadUtils;
+use crate::ParserError;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Parses a byte slice into a [GeoPeerTable].
+///
+/// RFC: https://www.rfc-editor.org/rfc/rfc6397#section-4
+pub fn parse_geo_peer_table(data: &mut Bytes) -> Result<GeoPeerTable, ParserError> {
+    let collector_bgp_id = BgpIdentifier::from(data.read_u32()?);
+    let view_name_length = data.read_u16()?;
+    let view_name =
+        String::from_utf8(data.read_n_bytes(view_name_length as usize)?).unwrap_or("".to_string());
+    let collector_location = read_geo_location(data)?;
+
+    let peer_count = data.read_u16()?;
+    let mut geo_peers = Vec::with_capacity(peer_count as usize);
+    for _index in 0..peer_count {
+        let peer_type = PeerType::from_bits_retain(data.read_u8()?);
+        let peer_bgp_id = BgpIdentifier::from(data.read_u32()?);
+        let location = read_geo_location(data)?;
+        geo_peers.push(GeoPeer {
+            peer_type,
+            peer_bgp_id,
+            location,
+        });
+    }
+
+    Ok(GeoPeerTable {
+        collector_bgp_id,
+        view_name,
+        collector_location,
+        geo_peers,
+    })
+}
+
+fn read_geo_location(data: &mut Bytes) -> Result<GeoLocation, ParserError> {
+    let latitude = f32::from_bits(data.read_u32()?);
+    let longitude = f32::from_bits(data.read_u32()?);
+    Ok(GeoLocation {
+        latitude,
+        longitude,
+    })
+}
+
+impl GeoPeerTable {
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        buf.put_u32(self.collector_bgp_id.into());
+
+        let view_name_bytes = self.view_name.as_bytes();
+        buf.put_u16(view_name_bytes.len() as u16);
+        buf.extend(view_name_bytes);
+
+        buf.put_f32(self.collector_location.latitude);
+        buf.put_f32(self.collector_location.longitude);
+
+        buf.put_u16(self.geo_peers.len() as u16);
+        for peer in &self.geo_peers {
+            buf.put_u8(peer.peer_type.bits());
+            buf.put_u32(peer.peer_bgp_id.into());
+            buf.put_f32(peer.location.latitude);
+            buf.put_f32(peer.location.longitude);
+        }
+
+        buf.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_peer_table_encode() {
+        let geo_table = GeoPeerTable {
+            collector_bgp_id: BgpIdentifier::from(1234),
+            view_name: String::from("example"),
+            collector_location: GeoLocation::new(37.77, -122.42),
+            geo_peers: vec![GeoPeer {
+                peer_type: PeerType::AS_SIZE_32BIT,
+                peer_bgp_id: BgpIdentifier::from(12345),
+                location: GeoLocation::new(51.51, -0.13),
+            }],
+        };
+
+        let encoded = geo_table.encode();
+        let parsed = parse_geo_peer_table(&mut encoded.clone()).unwrap();
+        assert_eq!(geo_table, parsed);
+    }
+}
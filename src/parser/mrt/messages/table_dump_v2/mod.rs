@@ -1,10 +1,13 @@
+mod geo_peer_table;
 mod peer_index_table;
 mod rib_afi_entries;
 
 use crate::error::ParserError;
+use crate::messages::table_dump_v2::geo_peer_table::parse_geo_peer_table;
 use crate::messages::table_dump_v2::peer_index_table::parse_peer_index_table;
 use crate::messages::table_dump_v2::rib_afi_entries::parse_rib_afi_entries;
 use crate::models::*;
+use crate::parser::ParseContext;
 use bytes::Bytes;
 use std::convert::TryFrom;
 
@@ -19,17 +22,28 @@ use std::convert::TryFrom;
 /// 4. RIB_IPV6_UNICAST
 /// 5. RIB_IPV6_MULTICAST
 /// 6. RIB_GENERIC
+/// 7. GEO_PEER_TABLE
 ///
 pub fn parse_table_dump_v2_message(
+    sub_type: u16,
+    input: Bytes,
+) -> Result<TableDumpV2Message, ParserError> {
+    parse_table_dump_v2_message_with_context(sub_type, input, &mut ParseContext::new())
+}
+
+/// Parse TABLE_DUMP V2 format MRT message with the buffers and settings of the given
+/// [ParseContext].
+pub(crate) fn parse_table_dump_v2_message_with_context(
     sub_type: u16,
     mut input: Bytes,
+    context: &mut ParseContext,
 ) -> Result<TableDumpV2Message, ParserError> {
     let v2_type: TableDumpV2Type = TableDumpV2Type::try_from(sub_type)?;
 
     let msg: TableDumpV2Message = match v2_type {
         TableDumpV2Type::PeerIndexTable => {
             // peer index table type
-            TableDumpV2Message::PeerIndexTable(parse_peer_index_table(&mut input)?)
+            TableDumpV2Message::PeerIndexTable(parse_peer_index_table(&mut input, context)?)
         }
         TableDumpV2Type::RibIpv4Unicast
         | TableDumpV2Type::RibIpv4Multicast
@@ -39,13 +53,14 @@ pub fn parse_table_dump_v2_message(
         | TableDumpV2Type::RibIpv4MulticastAddPath
         | TableDumpV2Type::RibIpv6UnicastAddPath
         | TableDumpV2Type::RibIpv6MulticastAddPath => {
-            TableDumpV2Message::RibAfi(parse_rib_afi_entries(&mut input, v2_type)?)
+            TableDumpV2Message::RibAfi(parse_rib_afi_entries(&mut input, v2_type, context)?)
+        }
+        TableDumpV2Type::GeoPeerTable => {
+            TableDumpV2Message::GeoPeerTable(parse_geo_peer_table(&mut input)?)
         }
-        TableDumpV2Type::RibGeneric
-        | TableDumpV2Type::RibGenericAddPath
-        | TableDumpV2Type::GeoPeerTable => {
+        TableDumpV2Type::RibGeneric | TableDumpV2Type::RibGenericAddPath => {
             return Err(ParserError::Unsupported(
-                "TableDumpV2 RibGeneric and GeoPeerTable is not currently supported".to_string(),
+                "TableDumpV2 RibGeneric is not currently supported".to_string(),
             ))
         }
     };
@@ -59,7 +74,7 @@ mod tests {
 
     #[test]
     fn test_unsupported_type() {
-        let msg = parse_table_dump_v2_message(7, Bytes::new());
+        let msg = parse_table_dump_v2_message(6, Bytes::new());
         assert!(msg.is_err());
     }
 }
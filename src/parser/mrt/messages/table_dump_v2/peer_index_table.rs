@@ -1,9 +1,9 @@
-use crate::models::{Afi, AsnLength, Peer, PeerIndexTable, PeerType};
-use crate::parser::ReadUtils;
+use crate::models::{Afi, AsnLength, BgpIdentifier, Peer, PeerIndexTable, PeerType};
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{BufMut, Bytes, BytesMut};
 use std::collections::HashMap;
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::IpAddr;
 
 /// Parses a byte slice into a [PeerIndexTable].
 ///
@@ -12,13 +12,17 @@ use std::net::{IpAddr, Ipv4Addr};
 /// # Arguments
 ///
 /// * `data` - The byte slice to parse.
+/// * `context` - The [ParseContext] holding the limits of the record.
 ///
 /// # Returns
 ///
 /// - `Ok(PeerIndexTable)` if the parsing is successful.
 /// - `Err(ParserError)` if an error occurs during parsing.
-pub fn parse_peer_index_table(data: &mut Bytes) -> Result<PeerIndexTable, ParserError> {
-    let collector_bgp_id = Ipv4Addr::from(data.read_u32()?);
+pub fn parse_peer_index_table(
+    data: &mut Bytes,
+    context: &mut ParseContext,
+) -> Result<PeerIndexTable, ParserError> {
+    let collector_bgp_id = BgpIdentifier::from(data.read_u32()?);
     // read and ignore view name
     let view_name_length = data.read_u16()?;
     let view_name =
@@ -27,6 +31,7 @@ pub fn parse_peer_index_table(data: &mut Bytes) -> Result<PeerIndexTable, Parser
     let peer_count = data.read_u16()?;
     let mut peers = vec![];
     for _index in 0..peer_count {
+        context.record_step()?;
         let peer_type = PeerType::from_bits_retain(data.read_u8()?);
         let afi = match peer_type.contains(PeerType::ADDRESS_FAMILY_IPV6) {
             true => Afi::Ipv6,
@@ -37,7 +42,7 @@ pub fn parse_peer_index_table(data: &mut Bytes) -> Result<PeerIndexTable, Parser
             false => AsnLength::Bits16,
         };
 
-        let peer_bgp_id = Ipv4Addr::from(data.read_u32()?);
+        let peer_bgp_id = BgpIdentifier::from(data.read_u32()?);
         let peer_address: IpAddr = data.read_address(&afi)?;
         let peer_asn = data.read_asn(asn_len)?;
         peers.push(Peer {
@@ -61,6 +66,7 @@ pub fn parse_peer_index_table(data: &mut Bytes) -> Result<PeerIndexTable, Parser
         view_name,
         id_peer_map,
         peer_addr_id_map,
+        peer_geo_map: HashMap::new(),
     })
 }
 
@@ -126,14 +132,14 @@ impl PeerIndexTable {
     ///
     /// ```
     /// use std::collections::HashMap;
-    /// use std::net::Ipv4Addr;
-    /// use bgpkit_parser::models::PeerIndexTable;
+    /// use bgpkit_parser::models::{BgpIdentifier, PeerIndexTable};
     ///
     /// let data = PeerIndexTable {
-    ///     collector_bgp_id: Ipv4Addr::from(1234),
+    ///     collector_bgp_id: BgpIdentifier::from(1234),
     ///     view_name: String::from("example"),
     ///     id_peer_map: HashMap::new(),
     ///     peer_addr_id_map: Default::default(),
+    ///     peer_geo_map: Default::default(),
     /// };
     ///
     /// let encoded = data.encode();
@@ -197,44 +203,47 @@ mod tests {
     #[test]
     fn test_peer_index_table_encode() {
         let mut index_table = PeerIndexTable {
-            collector_bgp_id: Ipv4Addr::from(1234),
+            collector_bgp_id: BgpIdentifier::from(1234),
             view_name: String::from("example"),
             id_peer_map: HashMap::new(),
             peer_addr_id_map: Default::default(),
+            peer_geo_map: Default::default(),
         };
 
         index_table.add_peer(Peer::new(
-            Ipv4Addr::from(1234),
+            BgpIdentifier::from(1234),
             IpAddr::from_str("192.168.1.1").unwrap(),
             Asn::new_32bit(1234),
         ));
         index_table.add_peer(Peer::new(
-            Ipv4Addr::from(12345),
+            BgpIdentifier::from(12345),
             IpAddr::from_str("192.168.1.2").unwrap(),
             Asn::new_32bit(12345),
         ));
 
         let encoded = index_table.encode();
-        let parsed_index_table = parse_peer_index_table(&mut encoded.clone()).unwrap();
+        let parsed_index_table =
+            parse_peer_index_table(&mut encoded.clone(), &mut ParseContext::new()).unwrap();
         assert_eq!(index_table, parsed_index_table);
     }
 
     #[test]
     fn test_get_peer_by_id() {
         let mut index_table = PeerIndexTable {
-            collector_bgp_id: Ipv4Addr::from(1234),
+            collector_bgp_id: BgpIdentifier::from(1234),
             view_name: String::from("example"),
             id_peer_map: HashMap::new(),
             peer_addr_id_map: Default::default(),
+            peer_geo_map: Default::default(),
         };
 
         let peer1 = Peer::new(
-            Ipv4Addr::from(1234),
+            BgpIdentifier::from(1234),
             IpAddr::from_str("10.0.0.1").unwrap(),
             Asn::new_32bit(1234),
         );
         let peer2 = Peer::new(
-            Ipv4Addr::from(12345),
+            BgpIdentifier::from(12345),
             IpAddr::from_str("10.0.0.2").unwrap(),
             Asn::new_32bit(12345),
         );
@@ -245,7 +254,7 @@ mod tests {
         assert_eq!(
             index_table.get_peer_by_id(&peer1_id),
             Some(&Peer::new(
-                Ipv4Addr::from(1234),
+                BgpIdentifier::from(1234),
                 IpAddr::from_str("10.0.0.1").unwrap(),
                 Asn::new_32bit(1234),
             ))
@@ -253,7 +262,7 @@ mod tests {
         assert_eq!(
             index_table.get_peer_by_id(&peer2_id),
             Some(&Peer::new(
-                Ipv4Addr::from(12345),
+                BgpIdentifier::from(12345),
                 IpAddr::from_str("10.0.0.2").unwrap(),
                 Asn::new_32bit(12345),
             ))
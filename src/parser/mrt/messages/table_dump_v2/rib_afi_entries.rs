@@ -1,8 +1,8 @@
-use crate::bgp::attributes::parse_attributes;
+use crate::bgp::attributes::parse_attributes_with_context;
 use crate::models::{
     Afi, AsnLength, NetworkPrefix, RibAfiEntries, RibEntry, Safi, TableDumpV2Type,
 };
-use crate::parser::ReadUtils;
+use crate::parser::{ParseContext, ReadUtils};
 use crate::ParserError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use log::warn;
@@ -44,6 +44,7 @@ fn extract_afi_safi_from_rib_type(rib_type: &TableDumpV2Type) -> Result<(Afi, Sa
 pub fn parse_rib_afi_entries(
     data: &mut Bytes,
     rib_type: TableDumpV2Type,
+    context: &mut ParseContext,
 ) -> Result<RibAfiEntries, ParserError> {
     let (afi, safi) = extract_afi_safi_from_rib_type(&rib_type)?;
 
@@ -62,14 +63,16 @@ pub fn parse_rib_afi_entries(
     let prefix = data.read_nlri_prefix(&afi, false)?;
 
     let entry_count = data.read_u16()?;
-    let mut rib_entries = Vec::with_capacity((entry_count * 2) as usize);
+    let mut rib_entries = context.vecs.take_rib_entries(entry_count as usize * 2);
 
     // get the u8 slice of the rest of the data
     // let attr_data_slice = &input.into_inner()[(input.position() as usize)..];
 
     for _i in 0..entry_count {
-        let entry = match parse_rib_entry(data, add_path, &afi, &safi, prefix) {
+        context.record_step()?;
+        let entry = match parse_rib_entry(data, add_path, &afi, &safi, prefix, context) {
             Ok(entry) => entry,
+            Err(e @ ParserError::LimitExceeded(_)) => return Err(e),
             Err(e) => {
                 warn!("early break due to error {}", e.to_string());
                 break;
@@ -111,6 +114,7 @@ pub fn parse_rib_entry(
     afi: &Afi,
     safi: &Safi,
     prefix: NetworkPrefix,
+    context: &mut ParseContext,
 ) -> Result<RibEntry, ParserError> {
     if input.remaining() < 8 {
         // total length - current position less than 16 --
@@ -120,25 +124,28 @@ pub fn parse_rib_entry(
 
     let peer_index = input.read_u16()?;
     let originated_time = input.read_u32()?;
-    if add_path {
-        let _path_id = input.read_u32()?;
-    }
+    let path_id = match add_path {
+        true => Some(input.read_u32()?),
+        false => None,
+    };
     let attribute_length = input.read_u16()? as usize;
 
     input.has_n_remaining(attribute_length)?;
     let attr_data_slice = input.split_to(attribute_length);
-    let attributes = parse_attributes(
+    let attributes = parse_attributes_with_context(
         attr_data_slice,
         &AsnLength::Bits32,
         add_path,
         Some(*afi),
         Some(*safi),
         Some(&[prefix]),
+        context,
     )?;
 
     Ok(RibEntry {
         peer_index,
         originated_time,
+        path_id,
         attributes,
     })
 }
@@ -166,6 +173,9 @@ impl RibEntry {
         let mut bytes = BytesMut::new();
         bytes.put_u16(self.peer_index);
         bytes.put_u32(self.originated_time);
+        if let Some(path_id) = self.path_id {
+            bytes.put_u32(path_id);
+        }
         let attr_bytes = self.attributes.encode(false, AsnLength::Bits32);
         bytes.put_u16(attr_bytes.len() as u16);
         bytes.extend(attr_bytes);
@@ -176,6 +186,8 @@ impl RibEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::Attributes;
+    use std::str::FromStr;
 
     #[test]
     fn test_extract_afi_safi_from_rib_type() {
@@ -223,4 +235,42 @@ mod tests {
         let res = extract_afi_safi_from_rib_type(&rib_type);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_rib_entry_path_id() {
+        let prefix = NetworkPrefix::from_str("10.0.0.0/8").unwrap();
+        let entry = RibEntry {
+            peer_index: 1,
+            originated_time: 2,
+            path_id: Some(3),
+            attributes: Attributes::default(),
+        };
+        let mut data = entry.encode();
+        let parsed = parse_rib_entry(
+            &mut data,
+            true,
+            &Afi::Ipv4,
+            &Safi::Unicast,
+            prefix,
+            &mut ParseContext::new(),
+        )
+        .unwrap();
+        assert_eq!(parsed, entry);
+
+        let entry = RibEntry {
+            path_id: None,
+            ..entry
+        };
+        let mut data = entry.encode();
+        let parsed = parse_rib_entry(
+            &mut data,
+            false,
+            &Afi::Ipv4,
+            &Safi::Unicast,
+            prefix,
+            &mut ParseContext::new(),
+        )
+        .unwrap();
+        assert_eq!(parsed, entry);
+    }
 }
@@ -5,10 +5,19 @@ Parse MRT header and content, provides [BgpElem][BgpElem] struct for per-prefix
 */
 pub mod messages;
 pub mod mrt_elem;
+pub mod mrt_extract;
 pub mod mrt_header;
+pub mod mrt_index;
 pub mod mrt_record;
+pub mod mrt_scan;
 
 pub use messages::bgp4mp::parse_bgp4mp;
 pub use messages::table_dump::parse_table_dump_message;
 pub use messages::table_dump_v2::parse_table_dump_v2_message;
-pub use mrt_record::parse_mrt_record;
+pub use mrt_extract::{extract_records, RecordPosition};
+pub use mrt_index::{IndexEntry, MrtIndex, PeerTableSpan};
+pub use mrt_record::{
+    parse_mrt_record, parse_mrt_record_filtered, parse_mrt_record_with_context, MrtBytes,
+    MrtRecordSource, ParseContext,
+};
+pub use mrt_scan::{scan_prefixes, PrefixScanner, RecordOffset};
@@ -6,14 +6,18 @@
 //! process BGP information on a per-prefix basis.
 use crate::models::*;
 use crate::parser::bgp::messages::parse_bgp_update_message;
+use crate::parser::filter::{Filter, Filterable};
 use itertools::Itertools;
 use log::{error, warn};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::net::{IpAddr, Ipv4Addr};
+use std::net::IpAddr;
+use std::sync::Arc;
 
 pub struct Elementor {
     peer_table: Option<PeerIndexTable>,
+    confed: Option<ConfedSegments>,
+    lazy_filters: Option<Vec<Filter>>,
 }
 
 // use macro_rules! <name of macro>{<Body>}
@@ -40,8 +44,8 @@ fn get_relevant_attributes(
     Option<Vec<MetaCommunity>>,
     bool,
     Option<(Asn, BgpIdentifier)>,
-    Option<Nlri>,
-    Option<Nlri>,
+    Vec<Nlri>,
+    Vec<Nlri>,
     Option<Asn>,
     Option<Vec<AttrRaw>>,
     Option<Vec<AttrRaw>>,
@@ -54,8 +58,8 @@ fn get_relevant_attributes(
     let mut med = Some(0);
     let mut atomic = false;
     let mut aggregator = None;
-    let mut announced = None;
-    let mut withdrawn = None;
+    let mut announced = vec![];
+    let mut withdrawn = vec![];
     let mut otc = None;
     let mut unknown = vec![];
     let mut deprecated = vec![];
@@ -95,8 +99,10 @@ fn get_relevant_attributes(
                     .collect::<Vec<MetaCommunity>>(),
             ),
             AttributeValue::Aggregator { asn, id, .. } => aggregator = Some((asn, id)),
-            AttributeValue::MpReachNlri(nlri) => announced = Some(nlri),
-            AttributeValue::MpUnreachNlri(nlri) => withdrawn = Some(nlri),
+            // malformed messages may carry more than one MP_REACH/MP_UNREACH attribute, keep
+            // the NLRI from all of them
+            AttributeValue::MpReachNlri(nlri) => announced.push(nlri),
+            AttributeValue::MpUnreachNlri(nlri) => withdrawn.push(nlri),
             AttributeValue::OnlyToCustomer(o) => otc = Some(o),
 
             AttributeValue::Unknown(t) => {
@@ -108,7 +114,8 @@ fn get_relevant_attributes(
 
             AttributeValue::OriginatorId(_)
             | AttributeValue::Clusters(_)
-            | AttributeValue::Development(_) => {}
+            | AttributeValue::Development(_)
+            | AttributeValue::Lazy(_) => {}
         };
     }
 
@@ -145,7 +152,51 @@ fn get_relevant_attributes(
 
 impl Elementor {
     pub fn new() -> Elementor {
-        Elementor { peer_table: None }
+        Elementor {
+            peer_table: None,
+            confed: None,
+            lazy_filters: None,
+        }
+    }
+
+    /// Set how the confederation segments of AS paths are handled in the elems of
+    /// [Elementor::record_to_elems].
+    ///
+    /// With [ConfedSegments::Exclude], confederation segments are removed from the AS path of
+    /// elems, so that filters on the AS path only see the path outside the confederation. With
+    /// [ConfedSegments::Include], they are kept and a route originated within the confederation
+    /// has the last member AS as origin. By default, AS paths are kept as they are and origins
+    /// are taken from non-confederation segments only.
+    pub fn with_confed_segments(mut self, confed: ConfedSegments) -> Self {
+        self.confed = Some(confed);
+        self
+    }
+
+    /// Drop the elems not matching the filters before decoding the [lazy
+    /// attributes](AttributeValue::Lazy) of their update or RIB entry, so that lazy attributes are
+    /// only decoded for the elems that are kept.
+    ///
+    /// The filters must not need the lazy attributes, see [Filter::needs_lazy_attributes]. Elems
+    /// may still be returned without matching the filters if their record has no lazy attributes.
+    pub(crate) fn with_lazy_filters(mut self, filters: Vec<Filter>) -> Self {
+        self.lazy_filters = Some(filters);
+        self
+    }
+
+    /// The peer index table of the table dump being processed, if any.
+    pub fn peer_table(&self) -> Option<&PeerIndexTable> {
+        self.peer_table.as_ref()
+    }
+
+    /// Replace the peer index table, e.g. with one read by another elementor.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn set_peer_table(&mut self, peer_table: Option<PeerIndexTable>) {
+        self.peer_table = peer_table;
+    }
+
+    /// How confederation segments are handled, if set with [Elementor::with_confed_segments].
+    pub fn confed_segments(&self) -> Option<ConfedSegments> {
+        self.confed
     }
 
     /// Convert a [BgpMessage] to a vector of [BgpElem]s.
@@ -175,127 +226,34 @@ impl Elementor {
         peer_ip: &IpAddr,
         peer_asn: &Asn,
     ) -> Vec<BgpElem> {
-        let mut elems = vec![];
-
-        let (
-            as_path,
-            as4_path, // Table dump v1 does not have 4-byte AS number
-            origin,
-            next_hop,
-            local_pref,
-            med,
-            communities,
-            atomic,
-            aggregator,
-            announced,
-            withdrawn,
-            only_to_customer,
-            unknown,
-            deprecated,
-        ) = get_relevant_attributes(msg.attributes);
-
-        let path = match (as_path, as4_path) {
-            (None, None) => None,
-            (Some(v), None) => Some(v),
-            (None, Some(v)) => Some(v),
-            (Some(v1), Some(v2)) => Some(AsPath::merge_aspath_as4path(&v1, &v2)),
-        };
-
-        let origin_asns = path
-            .as_ref()
-            .map(|as_path| as_path.iter_origins().collect());
-
-        elems.extend(msg.announced_prefixes.into_iter().map(|p| BgpElem {
-            timestamp,
-            elem_type: ElemType::ANNOUNCE,
-            peer_ip: *peer_ip,
-            peer_asn: *peer_asn,
-            prefix: p,
-            next_hop,
-            as_path: path.clone(),
-            origin_asns: origin_asns.clone(),
-            origin,
-            local_pref,
-            med,
-            communities: communities.clone(),
-            atomic,
-            aggr_asn: aggregator.as_ref().map(|v| v.0),
-            aggr_ip: aggregator.as_ref().map(|v| v.1),
-            only_to_customer,
-            unknown: unknown.clone(),
-            deprecated: deprecated.clone(),
-        }));
-
-        if let Some(nlri) = announced {
-            elems.extend(nlri.prefixes.into_iter().map(|p| BgpElem {
-                timestamp,
-                elem_type: ElemType::ANNOUNCE,
-                peer_ip: *peer_ip,
-                peer_asn: *peer_asn,
-                prefix: p,
-                next_hop,
-                as_path: path.clone(),
-                origin,
-                origin_asns: origin_asns.clone(),
-                local_pref,
-                med,
-                communities: communities.clone(),
-                atomic,
-                aggr_asn: aggregator.as_ref().map(|v| v.0),
-                aggr_ip: aggregator.as_ref().map(|v| v.1),
-                only_to_customer,
-                unknown: unknown.clone(),
-                deprecated: deprecated.clone(),
-            }));
-        }
+        Elementor::bgp_update_to_elem_refs(msg, timestamp, peer_ip, peer_asn)
+            .into_iter()
+            .map(BgpElem::from)
+            .collect()
+    }
 
-        elems.extend(msg.withdrawn_prefixes.into_iter().map(|p| BgpElem {
-            timestamp,
-            elem_type: ElemType::WITHDRAW,
-            peer_ip: *peer_ip,
-            peer_asn: *peer_asn,
-            prefix: p,
-            next_hop: None,
-            as_path: None,
-            origin: None,
-            origin_asns: None,
-            local_pref: None,
-            med: None,
-            communities: None,
-            atomic: false,
-            aggr_asn: None,
-            aggr_ip: None,
-            only_to_customer,
-            unknown: None,
-            deprecated: None,
-        }));
-        if let Some(nlri) = withdrawn {
-            elems.extend(nlri.prefixes.into_iter().map(|p| BgpElem {
-                timestamp,
-                elem_type: ElemType::WITHDRAW,
-                peer_ip: *peer_ip,
-                peer_asn: *peer_asn,
-                prefix: p,
-                next_hop: None,
-                as_path: None,
-                origin: None,
-                origin_asns: None,
-                local_pref: None,
-                med: None,
-                communities: None,
-                atomic: false,
-                aggr_asn: None,
-                aggr_ip: None,
-                only_to_customer,
-                unknown: None,
-                deprecated: None,
-            }));
-        };
-        elems
+    /// Convert a [BgpUpdateMessage] to a vector of [BgpElemRef]s sharing the attributes of the
+    /// update instead of copying them for each prefix.
+    pub fn bgp_update_to_elem_refs(
+        msg: BgpUpdateMessage,
+        timestamp: f64,
+        peer_ip: &IpAddr,
+        peer_asn: &Asn,
+    ) -> Vec<BgpElemRef> {
+        update_to_elem_refs(msg, timestamp, peer_ip, peer_asn, None, None)
     }
 
     /// Convert a [MrtRecord] to a vector of [BgpElem]s.
     pub fn record_to_elems(&mut self, record: MrtRecord) -> Vec<BgpElem> {
+        self.record_to_elem_refs(record)
+            .into_iter()
+            .map(BgpElem::from)
+            .collect()
+    }
+
+    /// Convert a [MrtRecord] to a vector of [BgpElemRef]s, where the elems of a BGP update share
+    /// the attributes of the update.
+    pub fn record_to_elem_refs(&mut self, record: MrtRecord) -> Vec<BgpElemRef> {
         let mut elems = vec![];
         let t = record.common_header.timestamp;
         let timestamp: f64 = if let Some(micro) = &record.common_header.microsecond_timestamp {
@@ -307,47 +265,31 @@ impl Elementor {
 
         match record.message {
             MrtMessage::TableDumpMessage(msg) => {
-                let (
-                    as_path,
-                    _as4_path, // Table dump v1 does not have 4-byte AS number
-                    origin,
-                    next_hop,
-                    local_pref,
-                    med,
-                    communities,
-                    atomic,
-                    aggregator,
-                    _announced,
-                    _withdrawn,
-                    only_to_customer,
-                    unknown,
-                    deprecated,
-                ) = get_relevant_attributes(msg.attributes);
-
-                let origin_asns = as_path
-                    .as_ref()
-                    .map(|as_path| as_path.iter_origins().collect());
-
-                elems.push(BgpElem {
+                // applies the same AS4_PATH merge as BGP4MP messages (RFC 6793); the unmerged path
+                // is available from the record with `TableDumpMessage::raw_as_path`
+                let (attributes, lazy) =
+                    split_lazy_attributes(msg.attributes, self.lazy_filters.as_deref());
+                let (attributes, _announced, _withdrawn) = elem_attributes(attributes, self.confed);
+                elems.push(BgpElemRef {
                     timestamp,
                     elem_type: ElemType::ANNOUNCE,
                     peer_ip: msg.peer_address,
                     peer_asn: msg.peer_asn,
                     prefix: msg.prefix,
-                    next_hop,
-                    as_path,
-                    origin,
-                    origin_asns,
-                    local_pref,
-                    med,
-                    communities,
-                    atomic,
-                    aggr_asn: aggregator.map(|v| v.0),
-                    aggr_ip: aggregator.map(|v| v.1),
-                    only_to_customer,
-                    unknown,
-                    deprecated,
+                    safi: Safi::Unicast,
+                    attributes: Arc::new(attributes),
+                    record_index: None,
+                    record_offset: None,
+                    rib_side: None,
+                    policy: None,
                 });
+                complete_lazy_elems(
+                    &mut elems,
+                    0,
+                    lazy,
+                    self.lazy_filters.as_deref(),
+                    self.confed,
+                );
             }
 
             MrtMessage::TableDumpV2Message(msg) => {
@@ -355,9 +297,26 @@ impl Elementor {
                     TableDumpV2Message::PeerIndexTable(p) => {
                         self.peer_table = Some(p);
                     }
+                    TableDumpV2Message::GeoPeerTable(g) => {
+                        if let Some(table) = self.peer_table.as_mut() {
+                            table.merge_geo_peer_table(&g);
+                        }
+                    }
                     TableDumpV2Message::RibAfi(t) => {
                         let prefix = t.prefix;
+                        let safi = match t.rib_type {
+                            TableDumpV2Type::RibIpv4Multicast
+                            | TableDumpV2Type::RibIpv4MulticastAddPath
+                            | TableDumpV2Type::RibIpv6Multicast
+                            | TableDumpV2Type::RibIpv6MulticastAddPath => Safi::Multicast,
+                            _ => Safi::Unicast,
+                        };
                         for e in t.rib_entries {
+                            // entries of ADD-PATH RIBs carry their own path identifier
+                            let prefix = match e.path_id {
+                                Some(path_id) => NetworkPrefix::new(prefix.prefix, path_id),
+                                None => prefix,
+                            };
                             let pid = e.peer_index;
                             let peer = match self.peer_table.as_ref() {
                                 None => {
@@ -372,77 +331,40 @@ impl Elementor {
                                     Some(peer) => peer,
                                 },
                             };
-                            let (
-                                as_path,
-                                as4_path, // Table dump v1 does not have 4-byte AS number
-                                origin,
-                                next_hop,
-                                local_pref,
-                                med,
-                                communities,
-                                atomic,
-                                aggregator,
-                                announced,
-                                _withdrawn,
-                                only_to_customer,
-                                unknown,
-                                deprecated,
-                            ) = get_relevant_attributes(e.attributes);
-
-                            let path = match (as_path, as4_path) {
-                                (None, None) => None,
-                                (Some(v), None) => Some(v),
-                                (None, Some(v)) => Some(v),
-                                (Some(v1), Some(v2)) => {
-                                    Some(AsPath::merge_aspath_as4path(&v1, &v2))
-                                }
-                            };
-
-                            let next = match next_hop {
-                                None => {
-                                    if let Some(v) = announced {
-                                        if let Some(h) = v.next_hop {
-                                            match h {
-                                                NextHopAddress::Ipv4(v) => Some(IpAddr::from(v)),
-                                                NextHopAddress::Ipv6(v) => Some(IpAddr::from(v)),
-                                                NextHopAddress::Ipv6LinkLocal(v, _) => {
-                                                    Some(IpAddr::from(v))
-                                                }
-                                            }
-                                        } else {
-                                            None
-                                        }
-                                    } else {
-                                        None
-                                    }
-                                }
-                                Some(v) => Some(v),
-                            };
+                            let (attributes, lazy) =
+                                split_lazy_attributes(e.attributes, self.lazy_filters.as_deref());
+                            let (mut attributes, announced, _withdrawn) =
+                                elem_attributes(attributes, self.confed);
+                            if attributes.next_hop.is_none() {
+                                // RIB entries of other AFIs carry the next hop in MP_REACH_NLRI
+                                attributes.next_hop = announced
+                                    .into_iter()
+                                    .next()
+                                    .and_then(|nlri| nlri.next_hop)
+                                    .map(|h| h.addr());
+                            }
 
-                            let origin_asns = path
-                                .as_ref()
-                                .map(|as_path| as_path.iter_origins().collect());
-
-                            elems.push(BgpElem {
+                            let start = elems.len();
+                            elems.push(BgpElemRef {
                                 timestamp,
                                 elem_type: ElemType::ANNOUNCE,
                                 peer_ip: peer.peer_address,
                                 peer_asn: peer.peer_asn,
                                 prefix,
-                                next_hop: next,
-                                as_path: path,
-                                origin,
-                                origin_asns,
-                                local_pref,
-                                med,
-                                communities,
-                                atomic,
-                                aggr_asn: aggregator.map(|v| v.0),
-                                aggr_ip: aggregator.map(|v| v.1),
-                                only_to_customer,
-                                unknown,
-                                deprecated,
+                                safi,
+                                attributes: Arc::new(attributes),
+                                record_index: None,
+                                record_offset: None,
+                                rib_side: None,
+                                policy: None,
                             });
+                            complete_lazy_elems(
+                                &mut elems,
+                                start,
+                                lazy,
+                                self.lazy_filters.as_deref(),
+                                self.confed,
+                            );
                         }
                     }
                     TableDumpV2Message::RibGeneric(_t) => {
@@ -455,12 +377,17 @@ impl Elementor {
             MrtMessage::Bgp4Mp(msg) => match msg {
                 Bgp4MpEnum::StateChange(_) => {}
                 Bgp4MpEnum::Message(v) => {
-                    elems.extend(Elementor::bgp_to_elems(
-                        v.bgp_message,
-                        timestamp,
-                        &v.peer_ip,
-                        &v.peer_asn,
-                    ));
+                    // only UPDATE messages contain elems
+                    if let BgpMessage::Update(update) = v.bgp_message {
+                        elems.extend(update_to_elem_refs(
+                            update,
+                            timestamp,
+                            &v.peer_ip,
+                            &v.peer_asn,
+                            self.confed,
+                            self.lazy_filters.as_deref(),
+                        ));
+                    }
                 }
             },
         }
@@ -468,6 +395,234 @@ impl Elementor {
     }
 }
 
+/// Elem attributes of the path attributes of an update or RIB entry, with the announced and
+/// withdrawn NLRI of the MP_REACH_NLRI and MP_UNREACH_NLRI attributes.
+fn elem_attributes(
+    attributes: Attributes,
+    confed: Option<ConfedSegments>,
+) -> (ElemAttributes, Vec<Nlri>, Vec<Nlri>) {
+    let (
+        as_path,
+        as4_path, // Table dump v1 does not have 4-byte AS number
+        origin,
+        next_hop,
+        local_pref,
+        med,
+        communities,
+        atomic,
+        aggregator,
+        announced,
+        withdrawn,
+        only_to_customer,
+        unknown,
+        deprecated,
+    ) = get_relevant_attributes(attributes);
+
+    let as_path = match (as_path, as4_path) {
+        (None, None) => None,
+        (Some(v), None) => Some(v),
+        (None, Some(v)) => Some(v),
+        (Some(v1), Some(v2)) => Some(AsPath::merge_aspath_as4path(&v1, &v2)),
+    };
+
+    let origin_asns = as_path
+        .as_ref()
+        .map(|as_path| as_path.iter_origins().collect());
+
+    let mut attributes = ElemAttributes {
+        next_hop,
+        as_path,
+        origin_asns,
+        origin,
+        local_pref,
+        med,
+        communities,
+        atomic,
+        aggr_asn: aggregator.map(|v| v.0),
+        aggr_ip: aggregator.map(|v| v.1),
+        only_to_customer,
+        unknown,
+        deprecated,
+    };
+    if let Some(confed) = confed {
+        apply_confed_segments(&mut attributes, confed);
+    }
+    (attributes, announced, withdrawn)
+}
+
+/// Split the lazily parsed attributes off to decode them after filtering the elems with the lazy
+/// filters, or decode them right away without lazy filters.
+fn split_lazy_attributes(
+    mut attributes: Attributes,
+    lazy_filters: Option<&[Filter]>,
+) -> (Attributes, Vec<LazyAttr>) {
+    if !attributes
+        .inner
+        .iter()
+        .any(|a| matches!(a.value, AttributeValue::Lazy(_)))
+    {
+        return (attributes, vec![]);
+    }
+    match lazy_filters {
+        None => {
+            attributes.decode_lazy();
+            (attributes, vec![])
+        }
+        Some(_) => {
+            let (lazy, decoded): (Vec<Attribute>, Vec<Attribute>) = attributes
+                .inner
+                .into_iter()
+                .partition(|a| matches!(a.value, AttributeValue::Lazy(_)));
+            let lazy = lazy
+                .into_iter()
+                .filter_map(|a| match a.value {
+                    AttributeValue::Lazy(l) => Some(l),
+                    _ => None,
+                })
+                .collect();
+            (Attributes::from(decoded), lazy)
+        }
+    }
+}
+
+/// Keep only the elems from `start` on matching the lazy filters, and complete their attributes
+/// with the decoded lazy attributes of their update or RIB entry.
+fn complete_lazy_elems(
+    elems: &mut Vec<BgpElemRef>,
+    start: usize,
+    lazy: Vec<LazyAttr>,
+    lazy_filters: Option<&[Filter]>,
+    confed: Option<ConfedSegments>,
+) {
+    let filters = match lazy_filters {
+        Some(filters) if !lazy.is_empty() => filters,
+        _ => return,
+    };
+    let mut group = elems.split_off(start);
+    group.retain(|elem| elem.to_elem().match_filters(filters));
+    if group.is_empty() {
+        return;
+    }
+
+    let mut decoded = Attributes::from_iter(lazy.into_iter().map(AttributeValue::Lazy));
+    decoded.decode_lazy();
+    let (decoded, _, _) = elem_attributes(decoded, confed);
+    // elems of an update share a few copies of the attributes, which are completed once each
+    let mut completed: Vec<(Arc<ElemAttributes>, Arc<ElemAttributes>)> = vec![];
+    for elem in group.iter_mut() {
+        if elem.elem_type == ElemType::WITHDRAW {
+            continue;
+        }
+        let attributes = match completed
+            .iter()
+            .find(|(partial, _)| Arc::ptr_eq(partial, &elem.attributes))
+        {
+            Some((_, attributes)) => attributes.clone(),
+            None => {
+                let attributes = Arc::new(ElemAttributes {
+                    as_path: decoded.as_path.clone(),
+                    origin_asns: decoded.origin_asns.clone(),
+                    communities: decoded.communities.clone(),
+                    aggr_asn: decoded.aggr_asn,
+                    aggr_ip: decoded.aggr_ip,
+                    ..elem.attributes.as_ref().clone()
+                });
+                completed.push((elem.attributes.clone(), attributes.clone()));
+                attributes
+            }
+        };
+        elem.attributes = attributes;
+    }
+    elems.extend(group);
+}
+
+/// Convert an update to elems, sharing one copy of the attributes between all announcements with
+/// the same next hop, and one between all withdrawals.
+fn update_to_elem_refs(
+    msg: BgpUpdateMessage,
+    timestamp: f64,
+    peer_ip: &IpAddr,
+    peer_asn: &Asn,
+    confed: Option<ConfedSegments>,
+    lazy_filters: Option<&[Filter]>,
+) -> Vec<BgpElemRef> {
+    let (attributes, lazy) = split_lazy_attributes(msg.attributes, lazy_filters);
+    let (attributes, announced, withdrawn) = elem_attributes(attributes, confed);
+    let withdrawal = Arc::new(ElemAttributes {
+        only_to_customer: attributes.only_to_customer,
+        ..Default::default()
+    });
+    let attributes = Arc::new(attributes);
+
+    let elem = |elem_type: ElemType,
+                prefix: NetworkPrefix,
+                safi: Safi,
+                attributes: &Arc<ElemAttributes>| BgpElemRef {
+        timestamp,
+        elem_type,
+        peer_ip: *peer_ip,
+        peer_asn: *peer_asn,
+        prefix,
+        safi,
+        attributes: attributes.clone(),
+        record_index: None,
+        record_offset: None,
+        rib_side: None,
+        policy: None,
+    };
+
+    let mut elems = vec![];
+    elems.extend(
+        msg.announced_prefixes
+            .into_iter()
+            .map(|p| elem(ElemType::ANNOUNCE, p, Safi::Unicast, &attributes)),
+    );
+    for nlri in announced {
+        // prefixes announced in MP_REACH_NLRI use the next hop of that attribute
+        let attributes = match nlri.next_hop.map(|h| h.addr()) {
+            Some(next_hop) if Some(next_hop) != attributes.next_hop => Arc::new(ElemAttributes {
+                next_hop: Some(next_hop),
+                ..attributes.as_ref().clone()
+            }),
+            _ => attributes.clone(),
+        };
+        let safi = nlri.safi;
+        elems.extend(
+            nlri.prefixes
+                .into_iter()
+                .map(|p| elem(ElemType::ANNOUNCE, p, safi, &attributes)),
+        );
+    }
+
+    elems.extend(
+        msg.withdrawn_prefixes
+            .into_iter()
+            .map(|p| elem(ElemType::WITHDRAW, p, Safi::Unicast, &withdrawal)),
+    );
+    for nlri in withdrawn {
+        let safi = nlri.safi;
+        elems.extend(
+            nlri.prefixes
+                .into_iter()
+                .map(|p| elem(ElemType::WITHDRAW, p, safi, &withdrawal)),
+        );
+    }
+    complete_lazy_elems(&mut elems, 0, lazy, lazy_filters, confed);
+    elems
+}
+
+/// Strip or keep the confederation segments of the AS path and recompute its origins.
+fn apply_confed_segments(attributes: &mut ElemAttributes, confed: ConfedSegments) {
+    let path = match &mut attributes.as_path {
+        Some(path) if path.has_confed() => path,
+        _ => return,
+    };
+    attributes.origin_asns = Some(path.iter_origins_with(confed).collect());
+    if confed == ConfedSegments::Exclude {
+        *path = path.without_confed();
+    }
+}
+
 #[inline(always)]
 pub fn option_to_string<T>(o: &Option<T>) -> String
 where
@@ -487,15 +642,16 @@ impl From<&BgpElem> for Attributes {
         let prefix = value.prefix;
 
         if value.elem_type == ElemType::WITHDRAW {
-            values.push(AttributeValue::MpUnreachNlri(Nlri::new_unreachable(prefix)));
+            let mut nlri = Nlri::new_unreachable(prefix);
+            nlri.safi = value.safi;
+            values.push(AttributeValue::MpUnreachNlri(nlri));
             attributes.extend(values);
             return attributes;
         }
 
-        values.push(AttributeValue::MpReachNlri(Nlri::new_reachable(
-            prefix,
-            value.next_hop,
-        )));
+        let mut nlri = Nlri::new_reachable(prefix, value.next_hop);
+        nlri.safi = value.safi;
+        values.push(AttributeValue::MpReachNlri(nlri));
 
         if let Some(v) = value.next_hop {
             values.push(AttributeValue::NextHop(v));
@@ -554,10 +710,7 @@ impl From<&BgpElem> for Attributes {
         }
 
         if let Some(v) = value.aggr_asn {
-            let aggregator_id = match value.aggr_ip {
-                Some(v) => v,
-                None => Ipv4Addr::UNSPECIFIED,
-            };
+            let aggregator_id = value.aggr_ip.unwrap_or_default();
             values.push(AttributeValue::Aggregator {
                 asn: v,
                 id: aggregator_id,
@@ -590,7 +743,7 @@ impl From<&BgpElem> for Attributes {
 mod tests {
     use super::*;
     use crate::BgpkitParser;
-    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::net::Ipv6Addr;
     use std::str::FromStr;
 
     #[test]
@@ -637,10 +790,11 @@ mod tests {
             peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
             peer_asn: Asn::new_32bit(65000),
             prefix: NetworkPrefix::from_str("10.0.1.0/24").unwrap(),
+            safi: Safi::Unicast,
             next_hop: Some(IpAddr::from_str("10.0.0.2").unwrap()),
             as_path: Some(AsPath::from_sequence([65000, 65001, 65002])),
             origin: Some(Origin::EGP),
-            origin_asns: Some(vec![Asn::new_32bit(65000)]),
+            origin_asns: Some(vec![Asn::new_32bit(65000)].into()),
             local_pref: Some(100),
             med: Some(200),
             communities: Some(vec![
@@ -659,7 +813,7 @@ mod tests {
             ]),
             atomic: false,
             aggr_asn: Some(Asn::new_32bit(65000)),
-            aggr_ip: Some(Ipv4Addr::from_str("10.2.0.0").unwrap()),
+            aggr_ip: Some(BgpIdentifier::from_str("10.2.0.0").unwrap()),
             only_to_customer: Some(Asn::new_32bit(65000)),
             unknown: Some(vec![AttrRaw {
                 attr_type: AttrType::RESERVED,
@@ -669,6 +823,10 @@ mod tests {
                 attr_type: AttrType::RESERVED,
                 bytes: vec![],
             }]),
+            record_index: None,
+            record_offset: None,
+            rib_side: None,
+            policy: None,
         };
 
         let _attributes = Attributes::from(&elem);
@@ -690,7 +848,7 @@ mod tests {
             AttributeValue::AtomicAggregate,
             AttributeValue::Aggregator {
                 asn: Asn::new_32bit(65000),
-                id: Ipv4Addr::from_str("10.0.0.1").unwrap(),
+                id: BgpIdentifier::from_str("10.0.0.1").unwrap(),
                 is_as4: false,
             },
             AttributeValue::Communities(vec![Community::NoExport]),
@@ -747,4 +905,131 @@ mod tests {
             _deprecated,
         ) = get_relevant_attributes(attributes);
     }
+
+    #[test]
+    fn test_repeated_mp_nlri_to_elems() {
+        let attributes = Attributes::from(
+            [
+                AttributeValue::MpReachNlri(Nlri::new_reachable(
+                    NetworkPrefix::from_str("2001:db8::/32").unwrap(),
+                    Some(IpAddr::from_str("2001:db8::1").unwrap()),
+                )),
+                AttributeValue::MpReachNlri(Nlri::new_reachable(
+                    NetworkPrefix::from_str("2001:db9::/32").unwrap(),
+                    Some(IpAddr::from_str("2001:db8::1").unwrap()),
+                )),
+                AttributeValue::MpUnreachNlri(Nlri::new_unreachable(
+                    NetworkPrefix::from_str("2001:dba::/32").unwrap(),
+                )),
+                AttributeValue::MpUnreachNlri(Nlri::new_unreachable(
+                    NetworkPrefix::from_str("2001:dbb::/32").unwrap(),
+                )),
+            ]
+            .into_iter()
+            .map(Attribute::from)
+            .collect::<Vec<Attribute>>(),
+        );
+        let msg = BgpUpdateMessage {
+            withdrawn_prefixes: vec![],
+            attributes,
+            announced_prefixes: vec![],
+        };
+        let elems = Elementor::bgp_update_to_elems(
+            msg,
+            0.0,
+            &IpAddr::from_str("10.0.0.1").unwrap(),
+            &Asn::new_32bit(65000),
+        );
+        let summary: Vec<(ElemType, String)> = elems
+            .iter()
+            .map(|e| (e.elem_type, e.prefix.to_string()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ElemType::ANNOUNCE, "2001:db8::/32".to_string()),
+                (ElemType::ANNOUNCE, "2001:db9::/32".to_string()),
+                (ElemType::WITHDRAW, "2001:dba::/32".to_string()),
+                (ElemType::WITHDRAW, "2001:dbb::/32".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_elem_refs_share_attributes() {
+        let attributes = Attributes::from_iter(vec![
+            AttributeValue::AsPath {
+                path: AsPath::from_sequence([65000, 65001]),
+                is_as4: true,
+            },
+            AttributeValue::NextHop(IpAddr::from_str("10.0.0.1").unwrap()),
+            AttributeValue::MpReachNlri(Nlri::new_reachable(
+                NetworkPrefix::from_str("2001:db8::/32").unwrap(),
+                Some(IpAddr::from_str("2001:db8::1").unwrap()),
+            )),
+        ]);
+        let msg = BgpUpdateMessage {
+            withdrawn_prefixes: vec![NetworkPrefix::from_str("10.2.0.0/16").unwrap()],
+            attributes,
+            announced_prefixes: vec![
+                NetworkPrefix::from_str("10.0.0.0/16").unwrap(),
+                NetworkPrefix::from_str("10.1.0.0/16").unwrap(),
+            ],
+        };
+        let peer_ip = IpAddr::from_str("10.0.0.1").unwrap();
+        let peer_asn = Asn::new_32bit(65000);
+        let refs = Elementor::bgp_update_to_elem_refs(msg.clone(), 0.0, &peer_ip, &peer_asn);
+        assert_eq!(refs.len(), 4);
+        assert!(refs[0].shares_attributes(&refs[1]));
+        // different next hop of MP_REACH_NLRI
+        assert!(!refs[1].shares_attributes(&refs[2]));
+        assert_eq!(
+            refs[2].attributes.next_hop,
+            Some(IpAddr::from_str("2001:db8::1").unwrap())
+        );
+        assert_eq!(refs[2].attributes.as_path, refs[0].attributes.as_path);
+        assert_eq!(refs[3].attributes.as_path, None);
+
+        let elems: Vec<BgpElem> = refs.iter().map(|e| e.to_elem()).collect();
+        assert_eq!(
+            elems,
+            Elementor::bgp_update_to_elems(msg, 0.0, &peer_ip, &peer_asn)
+        );
+        assert_eq!(BgpElemRef::from(elems[0].clone()).to_elem(), elems[0]);
+    }
+
+    #[test]
+    fn test_apply_confed_segments() {
+        let as_path = AsPath::from_segments(vec![
+            AsPathSegment::ConfedSequence(vec![Asn::new_32bit(65001)]),
+            AsPathSegment::sequence([1, 2]),
+        ]);
+        let elem = ElemAttributes {
+            as_path: Some(as_path.clone()),
+            origin_asns: Some(vec![Asn::new_32bit(2)].into()),
+            ..Default::default()
+        };
+
+        let mut excluded = elem.clone();
+        apply_confed_segments(&mut excluded, ConfedSegments::Exclude);
+        assert_eq!(excluded.as_path, Some(AsPath::from_sequence([1, 2])));
+        assert_eq!(excluded.origin_asns, elem.origin_asns);
+
+        let mut included = elem.clone();
+        apply_confed_segments(&mut included, ConfedSegments::Include);
+        assert_eq!(included.as_path, Some(as_path));
+
+        // route originated within the confederation
+        let mut internal = ElemAttributes {
+            as_path: Some(AsPath::from_segments(vec![AsPathSegment::ConfedSequence(
+                vec![Asn::new_32bit(65001), Asn::new_32bit(65002)],
+            )])),
+            ..Default::default()
+        };
+        apply_confed_segments(&mut internal, ConfedSegments::Include);
+        assert_eq!(
+            internal.origin_asns,
+            Some(vec![Asn::new_32bit(65002)].into())
+        );
+    }
 }
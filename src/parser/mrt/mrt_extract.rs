@@ -0,0 +1,110 @@
+//! Extract a subset of MRT records verbatim into a new MRT file.
+use crate::error::ParserError;
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+
+/// Position of an MRT record in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RecordPosition {
+    /// zero-based index of the record
+    Index(u64),
+    /// byte offset of the record's common header from the start of the input
+    Offset(u64),
+}
+
+/// Copy the records at the given positions from `input` to `output` without decoding them.
+///
+/// Records are only framed using the common header, so records that fail to parse can be
+/// extracted as well, e.g. to create a minimal file reproducing a parsing issue. The records are
+/// written in the input order, and the output is a valid MRT file.
+///
+/// Reading stops as soon as all requested positions have been passed. Offsets that do not point to
+/// the start of a record are ignored; compare the returned number of extracted records with the
+/// number of positions to detect them.
+pub fn extract_records<R: Read, W: Write>(
+    input: &mut R,
+    positions: &[RecordPosition],
+    output: &mut W,
+) -> Result<usize, ParserError> {
+    let mut indexes = BTreeSet::new();
+    let mut offsets = BTreeSet::new();
+    for position in positions {
+        match position {
+            RecordPosition::Index(i) => indexes.insert(*i),
+            RecordPosition::Offset(o) => offsets.insert(*o),
+        };
+    }
+    let last_index = indexes.last().copied();
+    let last_offset = offsets.last().copied();
+
+    let mut index: u64 = 0;
+    let mut offset: u64 = 0;
+    let mut extracted = 0;
+    let mut header = [0u8; 12];
+    let mut body = vec![];
+    loop {
+        let index_done = !matches!(last_index, Some(i) if index <= i);
+        let offset_done = !matches!(last_offset, Some(o) if offset <= o);
+        if index_done && offset_done {
+            break;
+        }
+
+        match input.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        // the length field does not include the common header, but includes the microsecond
+        // timestamp of extended timestamp records
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as usize;
+        body.resize(length, 0);
+        input.read_exact(&mut body)?;
+
+        if indexes.contains(&index) || offsets.contains(&offset) {
+            output.write_all(&header)?;
+            output.write_all(&body)?;
+            extracted += 1;
+        }
+
+        index += 1;
+        offset += (header.len() + length) as u64;
+    }
+
+    Ok(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn raw_record(subtype: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 0, 0, 13, 0, subtype];
+        bytes.extend((body.len() as u32).to_be_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn test_extract_records() {
+        let records = [
+            raw_record(1, &[1, 2, 3]),
+            raw_record(2, &[4, 5]),
+            raw_record(6, &[]),
+            raw_record(4, &[6]),
+        ];
+        let input = records.concat();
+
+        let positions = [
+            RecordPosition::Index(0),
+            // offset of the third record
+            RecordPosition::Offset((records[0].len() + records[1].len()) as u64),
+            // not a record boundary
+            RecordPosition::Offset(1),
+        ];
+        let mut output = vec![];
+        let count = extract_records(&mut Cursor::new(input), &positions, &mut output).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(output, [records[0].clone(), records[2].clone()].concat());
+    }
+}
@@ -53,7 +53,13 @@ pub fn parse_common_header<T: Read>(input: &mut T) -> Result<CommonHeader, Parse
 
     let microsecond_timestamp = match &entry_type {
         EntryType::BGP4MP_ET => {
-            length -= 4;
+            // the length includes the 4-byte microsecond timestamp
+            length = length.checked_sub(4).ok_or_else(|| {
+                ParserError::ParseError(format!(
+                    "invalid extended timestamp MRT record length {}",
+                    length
+                ))
+            })?;
             let mut raw_bytes: [u8; 4] = [0; 4];
             input.read_exact(&mut raw_bytes)?;
             Some(BytesMut::from(&raw_bytes[..]).get_u32())
@@ -96,6 +102,22 @@ mod tests {
     use crate::models::EntryType;
     use bytes::Buf;
 
+    #[test]
+    fn test_parse_common_header_invalid_et_length() {
+        let data = Bytes::from_static(&[
+            0, 0, 0, 1, // timestamp
+            0, 17, // entry type: BGP4MP_ET
+            0, 4, // entry subtype
+            0, 0, 0, 3, // length, shorter than the microsecond timestamp
+            0, 0, 0, 0, // microsecond timestamp
+        ]);
+        let mut reader = data.reader();
+        assert!(matches!(
+            parse_common_header(&mut reader),
+            Err(ParserError::ParseError(_))
+        ));
+    }
+
     /// Test that the length is not adjusted when the microsecond timestamp is not present.
     #[test]
     fn test_encode_common_header() {
@@ -0,0 +1,435 @@
+/*!
+Index of the records of an MRT file, to start parsing at a record or a timestamp.
+
+Compressed files, e.g. bzip2 RIB dumps, can only be read from the start. An [MrtIndex] records
+the position and timestamp of every n-th record of a file, built once with [MrtIndex::build] (or
+`bgpkit-parser index <FILE>`) and saved next to the file. Parsers created with
+[BgpkitParser::new_at_record] or [BgpkitParser::new_at_timestamp] then skip the data before the
+nearest checkpoint without parsing it, and only read the headers of the records between the
+checkpoint and the target.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, MrtIndex};
+
+let path = "rib.20230215.0000.bz2";
+let index = MrtIndex::build(oneio::get_reader(path).unwrap(), 1000).unwrap();
+index.write_to_path("rib.20230215.0000.bz2.idx").unwrap();
+
+let index = MrtIndex::read_from_path("rib.20230215.0000.bz2.idx").unwrap();
+let parser = BgpkitParser::new_at_record(path, &index, 500_000).unwrap();
+for elem in parser.into_elem_iter().take(10) {
+    println!("{}", elem);
+}
+```
+
+Table dump v2 RIB records refer to the peer index table at the start of the dump, so the index
+also keeps the positions of the peer index tables, and the parsers read the last table before
+the target first. The record positions reported by parsers created from an index, e.g. with
+[BgpkitParser::with_record_provenance], count from the first record returned.
+*/
+use crate::error::ParserError;
+use crate::models::{CommonHeader, EntryType};
+use crate::parser::mrt::mrt_header::parse_common_header;
+use crate::parser::mrt::mrt_record::read_mrt_header_filtered;
+#[cfg(feature = "oneio")]
+use crate::parser::{BgpkitParser, ParserErrorWithBytes};
+use crate::parser::{ParseContext, RecordOffset};
+use std::io::{Cursor, Read, Write};
+
+/// Magic bytes and format version at the start of serialized indexes.
+const INDEX_MAGIC: &[u8; 8] = b"BGPKIDX1";
+
+/// Position and timestamps of an indexed record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// position of the record
+    pub position: RecordOffset,
+    /// timestamp of the record
+    pub timestamp: u32,
+    /// highest timestamp of the records before this one, to find where to start for a timestamp
+    /// in files whose records are not sorted by timestamp
+    pub max_timestamp_before: u32,
+}
+
+/// Position and length, including the common header, of a peer index table record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerTableSpan {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Index of every n-th record of an MRT file, see [mrt_index](crate::parser::mrt::mrt_index).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MrtIndex {
+    /// number of records between two entries
+    pub interval: u64,
+    /// total number of records of the file
+    pub records: u64,
+    /// the indexed records, starting with the first record of the file
+    pub entries: Vec<IndexEntry>,
+    /// the table dump v2 peer index tables of the file
+    pub peer_tables: Vec<PeerTableSpan>,
+}
+
+impl MrtIndex {
+    /// Build the index of the decompressed MRT data, with an entry every `interval` records.
+    ///
+    /// Only the common headers are parsed, the record bodies are skipped.
+    pub fn build<R: Read>(mut reader: R, interval: u64) -> Result<MrtIndex, ParserError> {
+        let interval = interval.max(1);
+        let mut index = MrtIndex {
+            interval,
+            ..Default::default()
+        };
+        let mut context = ParseContext::new();
+        let mut max_timestamp: Option<u32> = None;
+        loop {
+            let header = match read_mrt_header_filtered(&mut reader, &mut context, &[]) {
+                Ok(header) => header,
+                Err(e) => match e.error {
+                    ParserError::EofExpected => break,
+                    error => return Err(error),
+                },
+            };
+            // the header was read, so the position is known
+            let position = context.last_record_position().unwrap_or_default();
+            if position.index.is_multiple_of(interval) {
+                index.entries.push(IndexEntry {
+                    position,
+                    timestamp: header.timestamp,
+                    max_timestamp_before: max_timestamp.unwrap_or(0),
+                });
+            }
+            if is_peer_table(&header) {
+                index.peer_tables.push(PeerTableSpan {
+                    offset: position.offset,
+                    length: header_length(&header) + header.length as u64,
+                });
+            }
+            max_timestamp = max_timestamp.max(Some(header.timestamp));
+            index.records += 1;
+        }
+        Ok(index)
+    }
+
+    /// The last entry at or before the record with the given index.
+    pub fn entry_for_record(&self, record: u64) -> Option<&IndexEntry> {
+        let n = self.entries.partition_point(|e| e.position.index <= record);
+        n.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    /// The last entry all of whose preceding records are before the timestamp.
+    pub fn entry_for_timestamp(&self, timestamp: u32) -> Option<&IndexEntry> {
+        let n = self
+            .entries
+            .partition_point(|e| e.max_timestamp_before < timestamp || e.position.index == 0);
+        n.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    /// The last peer index table before the offset.
+    #[cfg_attr(not(feature = "oneio"), allow(dead_code))]
+    fn peer_table_before(&self, offset: u64) -> Option<&PeerTableSpan> {
+        self.peer_tables.iter().rev().find(|t| t.offset < offset)
+    }
+
+    /// Serialize the index.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(INDEX_MAGIC)?;
+        writer.write_all(&self.interval.to_be_bytes())?;
+        writer.write_all(&self.records.to_be_bytes())?;
+        writer.write_all(&(self.peer_tables.len() as u64).to_be_bytes())?;
+        for table in &self.peer_tables {
+            writer.write_all(&table.offset.to_be_bytes())?;
+            writer.write_all(&table.length.to_be_bytes())?;
+        }
+        writer.write_all(&(self.entries.len() as u64).to_be_bytes())?;
+        for entry in &self.entries {
+            writer.write_all(&entry.position.index.to_be_bytes())?;
+            writer.write_all(&entry.position.offset.to_be_bytes())?;
+            writer.write_all(&entry.timestamp.to_be_bytes())?;
+            writer.write_all(&entry.max_timestamp_before.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize an index written with [MrtIndex::write_to].
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<MrtIndex, ParserError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC {
+            return Err(ParserError::ParseError(
+                "not an MRT index, or an unsupported version".to_string(),
+            ));
+        }
+        let interval = read_u64(reader)?;
+        let records = read_u64(reader)?;
+        let mut peer_tables = vec![];
+        for _ in 0..read_u64(reader)? {
+            peer_tables.push(PeerTableSpan {
+                offset: read_u64(reader)?,
+                length: read_u64(reader)?,
+            });
+        }
+        let mut entries = vec![];
+        for _ in 0..read_u64(reader)? {
+            entries.push(IndexEntry {
+                position: RecordOffset {
+                    index: read_u64(reader)?,
+                    offset: read_u64(reader)?,
+                },
+                timestamp: read_u32(reader)?,
+                max_timestamp_before: read_u32(reader)?,
+            });
+        }
+        Ok(MrtIndex {
+            interval,
+            records,
+            entries,
+            peer_tables,
+        })
+    }
+
+    /// Write the index to a local file.
+    pub fn write_to_path(&self, path: &str) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read the index from a local file.
+    pub fn read_from_path(path: &str) -> Result<MrtIndex, ParserError> {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        MrtIndex::read_from(&mut reader)
+    }
+
+    /// Read the decompressed MRT data indexed by this index from the entry on, skipping the
+    /// records before the first one matching `is_target`, given its index and header.
+    ///
+    /// Returns the data to parse: the last peer index table before the target record, followed
+    /// by the rest of the data from the target record on.
+    #[cfg_attr(not(feature = "oneio"), allow(dead_code))]
+    pub(crate) fn seek<R: Read>(
+        &self,
+        mut reader: R,
+        entry: &IndexEntry,
+        is_target: impl Fn(u64, &CommonHeader) -> bool,
+    ) -> Result<std::io::Chain<Cursor<Vec<u8>>, R>, ParserError> {
+        let mut prefix = vec![];
+        let mut offset = 0;
+        if let Some(table) = self.peer_table_before(entry.position.offset) {
+            skip_bytes(&mut reader, table.offset)?;
+            prefix.resize(table.length as usize, 0);
+            reader.read_exact(&mut prefix)?;
+            offset = table.offset + table.length;
+        }
+        skip_bytes(&mut reader, entry.position.offset - offset)?;
+
+        let mut record = entry.position.index;
+        loop {
+            let header = match parse_common_header(&mut reader) {
+                Ok(header) => header,
+                // no record matches
+                Err(ParserError::EofError(_)) => break,
+                Err(e) => return Err(e),
+            };
+            if is_target(record, &header) {
+                // put the header back in front of the rest of the data
+                prefix.extend_from_slice(&header.encode());
+                break;
+            }
+            if is_peer_table(&header) {
+                // replaces the previous peer index table
+                prefix.clear();
+                prefix.extend_from_slice(&header.encode());
+                let start = prefix.len();
+                prefix.resize(start + header.length as usize, 0);
+                reader.read_exact(&mut prefix[start..])?;
+            } else {
+                skip_bytes(&mut reader, header.length as u64)?;
+            }
+            record += 1;
+        }
+        Ok(Cursor::new(prefix).chain(reader))
+    }
+}
+
+#[cfg(feature = "oneio")]
+impl BgpkitParser<Box<dyn Read + Send>> {
+    /// Creating a new parser starting at the record with the given index, skipping the data
+    /// before it with the [MrtIndex] of the file.
+    pub fn new_at_record(
+        path: &str,
+        index: &MrtIndex,
+        record: u64,
+    ) -> Result<Self, ParserErrorWithBytes> {
+        let entry = match index.entry_for_record(record) {
+            Some(entry) => *entry,
+            None => return Ok(BgpkitParser::from_reader(Box::new(std::io::empty()))),
+        };
+        let reader = BgpkitParser::new(path)?.reader;
+        let reader = index.seek(reader, &entry, |i, _| i >= record)?;
+        Ok(BgpkitParser::from_reader(Box::new(reader)))
+    }
+
+    /// Creating a new parser starting at the first record at or after the timestamp, skipping
+    /// the data before it with the [MrtIndex] of the file.
+    ///
+    /// Records after that one are all returned, even if they are older than the timestamp.
+    pub fn new_at_timestamp(
+        path: &str,
+        index: &MrtIndex,
+        timestamp: u32,
+    ) -> Result<Self, ParserErrorWithBytes> {
+        let entry = match index.entry_for_timestamp(timestamp) {
+            Some(entry) => *entry,
+            None => return Ok(BgpkitParser::from_reader(Box::new(std::io::empty()))),
+        };
+        let reader = BgpkitParser::new(path)?.reader;
+        let reader = index.seek(reader, &entry, |_, h| h.timestamp >= timestamp)?;
+        Ok(BgpkitParser::from_reader(Box::new(reader)))
+    }
+}
+
+fn is_peer_table(header: &CommonHeader) -> bool {
+    // PEER_INDEX_TABLE
+    header.entry_type == EntryType::TABLE_DUMP_V2 && header.entry_subtype == 1
+}
+
+/// Length of the encoded common header.
+fn header_length(header: &CommonHeader) -> u64 {
+    match header.microsecond_timestamp {
+        Some(_) => 16,
+        None => 12,
+    }
+}
+
+#[cfg_attr(not(feature = "oneio"), allow(dead_code))]
+fn skip_bytes<R: Read>(reader: &mut R, n: u64) -> Result<(), ParserError> {
+    match std::io::copy(&mut reader.by_ref().take(n), &mut std::io::sink())? == n {
+        true => Ok(()),
+        false => Err(ParserError::EofError(std::io::Error::from(
+            std::io::ErrorKind::UnexpectedEof,
+        ))),
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, ParserError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, ParserError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BgpElem;
+    use crate::test_vectors;
+
+    /// Length of the peer index table record of the RIB test vector.
+    fn peer_table_length() -> usize {
+        let rib = test_vectors::mrt_ribs().remove(0).bytes;
+        12 + u32::from_be_bytes(rib[8..12].try_into().unwrap()) as usize
+    }
+
+    /// A RIB dump followed by updates and another RIB record, with the number of records.
+    fn test_data() -> (Vec<u8>, u64) {
+        let rib = test_vectors::mrt_ribs().remove(0).bytes;
+        let mut data = rib.clone();
+        for vector in test_vectors::mrt_updates() {
+            data.extend(vector.bytes);
+        }
+        data.extend(&rib[peer_table_length()..]);
+        (data, 6)
+    }
+
+    fn elems_from(data: &[u8], record: u64) -> Vec<BgpElem> {
+        BgpkitParser::from_reader(Cursor::new(data.to_vec()))
+            .with_record_provenance()
+            .into_elem_iter()
+            .filter(|e| e.record_index >= Some(record))
+            .map(|e| BgpElem {
+                record_index: None,
+                record_offset: None,
+                rib_side: None,
+                policy: None,
+                ..e
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_index() {
+        let (data, records) = test_data();
+        let index = MrtIndex::build(Cursor::new(&data), 2).unwrap();
+        assert_eq!(index.records, records);
+        assert_eq!(
+            index
+                .entries
+                .iter()
+                .map(|e| e.position.index)
+                .collect::<Vec<_>>(),
+            vec![0, 2, 4]
+        );
+        assert_eq!(index.entries[0].position.offset, 0);
+        assert_eq!(index.peer_tables.len(), 1);
+        assert_eq!(index.peer_tables[0].offset, 0);
+        assert_eq!(index.peer_tables[0].length, peer_table_length() as u64);
+
+        let mut bytes = vec![];
+        index.write_to(&mut bytes).unwrap();
+        assert_eq!(MrtIndex::read_from(&mut Cursor::new(bytes)).unwrap(), index);
+        assert!(MrtIndex::read_from(&mut Cursor::new(b"not an index")).is_err());
+    }
+
+    #[test]
+    fn test_entry_lookup() {
+        let entry = |index, timestamp, max_timestamp_before| IndexEntry {
+            position: RecordOffset {
+                index,
+                offset: index * 100,
+            },
+            timestamp,
+            max_timestamp_before,
+        };
+        let index = MrtIndex {
+            interval: 10,
+            records: 35,
+            entries: vec![
+                entry(0, 10, 0),
+                entry(10, 30, 20),
+                entry(20, 15, 30),
+                entry(30, 40, 40),
+            ],
+            peer_tables: vec![],
+        };
+        assert_eq!(index.entry_for_record(0).unwrap().position.index, 0);
+        assert_eq!(index.entry_for_record(19).unwrap().position.index, 10);
+        assert_eq!(index.entry_for_record(34).unwrap().position.index, 30);
+        assert_eq!(index.entry_for_timestamp(5).unwrap().position.index, 0);
+        assert_eq!(index.entry_for_timestamp(25).unwrap().position.index, 10);
+        // a record before the third entry is newer than the timestamp
+        assert_eq!(index.entry_for_timestamp(30).unwrap().position.index, 10);
+        assert_eq!(index.entry_for_timestamp(50).unwrap().position.index, 30);
+        assert!(MrtIndex::default().entry_for_record(0).is_none());
+    }
+
+    #[test]
+    fn test_seek() {
+        let (data, records) = test_data();
+        let index = MrtIndex::build(Cursor::new(&data), 2).unwrap();
+        for record in 0..=records {
+            let entry = index.entry_for_record(record).unwrap();
+            let reader = index
+                .seek(Cursor::new(data.clone()), entry, |i, _| i >= record)
+                .unwrap();
+            let elems: Vec<BgpElem> = BgpkitParser::from_reader(reader).into_elem_iter().collect();
+            assert_eq!(elems, elems_from(&data, record), "{}", record);
+        }
+    }
+}
@@ -2,55 +2,217 @@ use super::mrt_header::parse_common_header;
 use crate::bmp::messages::{BmpMessage, BmpMessageBody};
 use crate::error::ParserError;
 use crate::models::*;
-use crate::parser::{
-    parse_bgp4mp, parse_table_dump_message, parse_table_dump_v2_message, ParserErrorWithBytes,
-};
+#[cfg(feature = "alloc-stats")]
+use crate::parser::alloc_stats::AllocStage;
+use crate::parser::limits::{Budget, RecordLimits};
+use crate::parser::mrt::messages::bgp4mp::parse_bgp4mp_with_context;
+use crate::parser::mrt::messages::table_dump::parse_table_dump_message_with_context;
+use crate::parser::mrt::messages::table_dump_v2::parse_table_dump_v2_message_with_context;
+use crate::parser::mrt::RecordOffset;
+use crate::parser::scratch::VecPool;
+use crate::parser::{match_record_filters, ParserErrorWithBytes, RecordFilter};
 use crate::utils::convert_timestamp;
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use ipnet::IpNet;
 use log::warn;
 use std::convert::TryFrom;
 use std::io::Read;
 use std::net::IpAddr;
 use std::str::FromStr;
 
-pub fn parse_mrt_record(input: &mut impl Read) -> Result<MrtRecord, ParserErrorWithBytes> {
-    // parse common header
-    let common_header = match parse_common_header(input) {
-        Ok(v) => v,
-        Err(e) => {
-            if let ParserError::EofError(e) = &e {
-                if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                    return Err(ParserErrorWithBytes::from(ParserError::EofExpected));
+/// Reusable buffers for parsing MRT records.
+///
+/// [parse_mrt_record] allocates a new buffer for the body of every record. Passing the same
+/// context to [parse_mrt_record_with_context] instead reuses the buffer of a previous record once
+/// that record has been dropped, which avoids most per-record allocations when records are
+/// processed one at a time, e.g. when parsing large RIB dumps.
+///
+/// Parsed records own their attribute, prefix and RIB entry lists. Records that are no longer
+/// needed can be given back with [ParseContext::recycle], and the following records reuse their
+/// lists instead of allocating new ones:
+///
+/// ```no_run
+/// use bgpkit_parser::{BgpkitParser, ParseContext};
+///
+/// let mut parser = BgpkitParser::new("rib.20240101.0000.bz2").unwrap();
+/// let mut context = ParseContext::new();
+/// while let Ok(record) = parser.next_record_with_context(&mut context) {
+///     // ... use the record
+///     context.recycle(record);
+/// }
+/// ```
+///
+/// The context also holds the [RecordLimits] applied to the body of each record, see
+/// [ParseContext::with_limits], whether attributes are decoded lazily, see
+/// [ParseContext::with_lazy_attributes], and counts the records read with it to tell their
+/// positions, see [ParseContext::last_record_position].
+///
+/// # Memory usage
+///
+/// Parsed records never hold on to slices of the buffer: attribute values, unknown attributes and
+/// BMP TLVs are copied into the records. Keeping records, or values taken from them, for a long
+/// time therefore does not keep the buffer alive, and the buffer is reused for the next record.
+/// The buffer keeps the capacity of the largest record read so far, so long-running processes
+/// reading the occasional very large record can use a new context to release the memory.
+///
+/// Attribute values are decoded from slices of the buffer, which are dropped once the record is
+/// parsed. [ParseContext::with_copy_values_up_to] copies small attribute values out of the buffer
+/// before decoding them instead, e.g. for services keeping parsed messages around for a long time.
+#[derive(Debug, Default)]
+pub struct ParseContext {
+    buffer: BytesMut,
+    limits: RecordLimits,
+    /// remaining budget of the record being parsed
+    budget: Option<Budget>,
+    /// whether the attributes that are costly to decode are kept as [AttributeValue::Lazy]
+    pub(crate) lazy_attributes: bool,
+    /// attribute values of up to this many bytes are copied out of the record buffer
+    pub(crate) copy_values_up_to: Option<usize>,
+    /// lists of recycled records, taken by the parser instead of allocating new ones
+    pub(crate) vecs: VecPool,
+    /// position of the next record
+    next_record: RecordOffset,
+    last_record: Option<RecordOffset>,
+}
+
+impl ParseContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a context with an initial buffer capacity in bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        ParseContext {
+            buffer: BytesMut::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Bound the work spent parsing the body of each record, see [RecordLimits].
+    pub fn with_limits(mut self, limits: RecordLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Keep the AS path, aggregator, community and cluster list attributes of the records as
+    /// [AttributeValue::Lazy] instead of decoding them, e.g. when only the prefixes are needed.
+    ///
+    /// Decode them later with [Attributes::decode_lazy] if needed.
+    pub fn with_lazy_attributes(mut self, lazy_attributes: bool) -> Self {
+        self.lazy_attributes = lazy_attributes;
+        self
+    }
+
+    /// Copy the attribute values of up to `max_len` bytes into their own allocations before
+    /// decoding them, instead of slicing the record buffer. Values are not copied by default.
+    ///
+    /// See [TlvReader::copy_values_up_to](crate::parser::TlvReader::copy_values_up_to) for the same
+    /// option on TLV values.
+    pub fn with_copy_values_up_to(mut self, max_len: usize) -> Self {
+        self.copy_values_up_to = Some(max_len);
+        self
+    }
+
+    /// Keep the attribute, prefix and RIB entry lists of a record that is no longer needed, to
+    /// reuse them for the next records parsed with this context.
+    pub fn recycle(&mut self, record: MrtRecord) {
+        match record.message {
+            MrtMessage::TableDumpMessage(msg) => self.vecs.put_attributes(msg.attributes),
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(rib)) => {
+                self.vecs.put_rib_entries(rib.rib_entries)
+            }
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => {
+                if let BgpMessage::Update(update) = msg.bgp_message {
+                    self.vecs.put_prefixes(update.withdrawn_prefixes);
+                    self.vecs.put_attributes(update.attributes);
+                    self.vecs.put_prefixes(update.announced_prefixes);
                 }
             }
-            return Err(ParserErrorWithBytes {
-                error: e,
-                bytes: None,
-            });
+            _ => {}
         }
-    };
+    }
 
-    // read the whole message bytes to buffer
-    let mut buffer = BytesMut::with_capacity(common_header.length as usize);
-    buffer.resize(common_header.length as usize, 0);
-    match input
-        .take(common_header.length as u64)
-        .read_exact(&mut buffer)
-    {
-        Ok(_) => {}
-        Err(e) => {
-            return Err(ParserErrorWithBytes {
-                error: ParserError::IoError(e),
-                bytes: None,
-            })
+    pub(crate) fn set_limits(&mut self, limits: RecordLimits) {
+        self.limits = limits;
+    }
+
+    /// Start parsing the body of a record within the [RecordLimits] of the context.
+    pub(crate) fn start_record(&mut self) {
+        self.budget = Budget::start(&self.limits);
+    }
+
+    /// Count a step of parsing the current record, returning an error once its limits are
+    /// exceeded.
+    ///
+    /// Does nothing outside of records, e.g. when parsing BGP messages directly.
+    pub(crate) fn record_step(&mut self) -> Result<(), ParserError> {
+        match &mut self.budget {
+            Some(budget) => budget.step(),
+            None => Ok(()),
         }
     }
 
-    match parse_mrt_body(
-        common_header.entry_type as u16,
-        common_header.entry_subtype,
-        buffer.freeze(), // freeze the BytesMute to Bytes
-    ) {
+    /// Finish parsing the current record, returning the error to report for it if its limits
+    /// were exceeded.
+    pub(crate) fn finish_record(&mut self) -> Option<ParserError> {
+        self.budget.take().and_then(|budget| budget.exceeded())
+    }
+
+    /// Position of the last record read with this context, or None if no record has been read.
+    ///
+    /// All records read with the context are counted, including those skipped by filters. The
+    /// position is set before the body of the record is parsed, so it is also the position of a
+    /// record that failed to parse.
+    pub fn last_record_position(&self) -> Option<RecordOffset> {
+        self.last_record
+    }
+
+    /// Count the record of the header as read, returning its position.
+    fn count_record(&mut self, header: &CommonHeader) -> RecordOffset {
+        let position = self.next_record;
+        // the length of extended timestamp records excludes the microsecond timestamp here
+        let header_len = match header.microsecond_timestamp {
+            Some(_) => 16,
+            None => 12,
+        };
+        self.next_record = RecordOffset {
+            index: position.index + 1,
+            offset: position.offset + header_len + header.length as u64,
+        };
+        position
+    }
+}
+
+pub fn parse_mrt_record(input: &mut impl Read) -> Result<MrtRecord, ParserErrorWithBytes> {
+    parse_mrt_record_with_context(input, &mut ParseContext::new())
+}
+
+/// Parse an MRT record, reusing the buffers of the given [ParseContext].
+pub fn parse_mrt_record_with_context(
+    input: &mut impl Read,
+    context: &mut ParseContext,
+) -> Result<MrtRecord, ParserErrorWithBytes> {
+    parse_mrt_record_filtered(input, context, &[])
+}
+
+/// Parse the next MRT record whose common header matches all the [RecordFilter]s.
+///
+/// The bodies of records not matching the filters are skipped without being parsed.
+pub fn parse_mrt_record_filtered(
+    input: &mut impl Read,
+    context: &mut ParseContext,
+    filters: &[RecordFilter],
+) -> Result<MrtRecord, ParserErrorWithBytes> {
+    let (common_header, body) = {
+        #[cfg(feature = "alloc-stats")]
+        let _read_stage = crate::parser::alloc_stats::enter_stage(AllocStage::Read);
+        read_mrt_record_filtered(input, context, filters)?
+    };
+
+    #[cfg(feature = "alloc-stats")]
+    let _parse_stage = crate::parser::alloc_stats::enter_stage(AllocStage::Parse);
+
+    match parse_mrt_body_limited(&common_header, body, context) {
         Ok(message) => Ok(MrtRecord {
             common_header,
             message,
@@ -75,6 +237,263 @@ pub fn parse_mrt_record(input: &mut impl Read) -> Result<MrtRecord, ParserErrorW
     }
 }
 
+/// Read the header and the unparsed body of the next MRT record whose common header matches all
+/// the [RecordFilter]s.
+pub(crate) fn read_mrt_record_filtered(
+    input: &mut impl Read,
+    context: &mut ParseContext,
+    filters: &[RecordFilter],
+) -> Result<(CommonHeader, Bytes), ParserErrorWithBytes> {
+    // parse common header, skipping the records not matching the filters
+    let common_header = loop {
+        let common_header = read_common_header(input)?;
+        let position = context.count_record(&common_header);
+        if match_record_filters(&common_header, filters) {
+            context.last_record = Some(position);
+            break common_header;
+        }
+        skip_record_body(input, &common_header)?;
+    };
+
+    // read the whole message bytes to buffer. `reserve` takes back the space of previous records
+    // if they have been dropped.
+    let buffer = &mut context.buffer;
+    buffer.clear();
+    buffer.reserve(common_header.length as usize);
+    buffer.resize(common_header.length as usize, 0);
+    match input.take(common_header.length as u64).read_exact(buffer) {
+        Ok(_) => {}
+        Err(e) => {
+            return Err(ParserErrorWithBytes {
+                error: ParserError::IoError(e),
+                bytes: None,
+            })
+        }
+    }
+    // freeze the BytesMut to Bytes
+    Ok((common_header, buffer.split().freeze()))
+}
+
+/// Read the common header of the next MRT record matching all the [RecordFilter]s, skipping the
+/// bodies of all records without parsing them.
+pub(crate) fn read_mrt_header_filtered(
+    input: &mut impl Read,
+    context: &mut ParseContext,
+    filters: &[RecordFilter],
+) -> Result<CommonHeader, ParserErrorWithBytes> {
+    loop {
+        let common_header = read_common_header(input)?;
+        let position = context.count_record(&common_header);
+        skip_record_body(input, &common_header)?;
+        if match_record_filters(&common_header, filters) {
+            context.last_record = Some(position);
+            return Ok(common_header);
+        }
+    }
+}
+
+/// Read a common header, with [ParserError::EofExpected] at the end of the input.
+fn read_common_header(input: &mut impl Read) -> Result<CommonHeader, ParserErrorWithBytes> {
+    match parse_common_header(input) {
+        Ok(v) => Ok(v),
+        Err(e) => {
+            if let ParserError::EofError(e) = &e {
+                if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return Err(ParserErrorWithBytes::from(ParserError::EofExpected));
+                }
+            }
+            Err(ParserErrorWithBytes {
+                error: e,
+                bytes: None,
+            })
+        }
+    }
+}
+
+/// Skip the body of the record of the header.
+fn skip_record_body(
+    input: &mut impl Read,
+    common_header: &CommonHeader,
+) -> Result<(), ParserErrorWithBytes> {
+    let length = common_header.length as u64;
+    match std::io::copy(&mut input.by_ref().take(length), &mut std::io::sink()) {
+        Ok(n) if n == length => Ok(()),
+        Ok(_) => Err(ParserErrorWithBytes {
+            error: ParserError::IoError(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)),
+            bytes: None,
+        }),
+        Err(e) => Err(ParserErrorWithBytes {
+            error: ParserError::IoError(e),
+            bytes: None,
+        }),
+    }
+}
+
+/// Source of MRT records for a [BgpkitParser](crate::BgpkitParser).
+///
+/// Implemented for every [Read]er, and for [MrtBytes] holding a whole file in memory.
+pub trait MrtRecordSource {
+    /// Parse the next record, reusing the buffers of the given [ParseContext] where possible.
+    fn next_mrt_record(
+        &mut self,
+        context: &mut ParseContext,
+    ) -> Result<MrtRecord, ParserErrorWithBytes> {
+        self.next_matching_mrt_record(context, &[])
+    }
+
+    /// Parse the next record whose common header matches all the [RecordFilter]s, skipping the
+    /// bodies of other records without parsing them.
+    fn next_matching_mrt_record(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<MrtRecord, ParserErrorWithBytes>;
+
+    /// Read the common header of the next record matching all the [RecordFilter]s, skipping the
+    /// record bodies without parsing them.
+    ///
+    /// The default implementation parses the matching records and drops their bodies.
+    fn next_matching_mrt_header(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<CommonHeader, ParserErrorWithBytes> {
+        self.next_matching_mrt_record(context, filters)
+            .map(|record| record.common_header)
+    }
+}
+
+impl<R: Read> MrtRecordSource for R {
+    fn next_matching_mrt_record(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<MrtRecord, ParserErrorWithBytes> {
+        parse_mrt_record_filtered(self, context, filters)
+    }
+
+    fn next_matching_mrt_header(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<CommonHeader, ParserErrorWithBytes> {
+        read_mrt_header_filtered(self, context, filters)
+    }
+}
+
+/// Uncompressed MRT data held in memory.
+///
+/// Record bodies are parsed from slices of the underlying [Bytes] instead of being copied into an
+/// intermediate buffer. See [BgpkitParser::from_bytes](crate::BgpkitParser::from_bytes).
+///
+/// The data is held until the source is dropped, but the parsed records do not reference it (see
+/// [ParseContext]), so records can outlive the source without keeping the data alive.
+#[derive(Debug, Clone, Default)]
+pub struct MrtBytes {
+    data: Bytes,
+}
+
+impl MrtBytes {
+    pub fn new(data: Bytes) -> Self {
+        MrtBytes { data }
+    }
+
+    /// Number of bytes not parsed yet.
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Split the header and the unparsed body of the next record matching all the
+    /// [RecordFilter]s off the data.
+    fn split_record_filtered(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<(CommonHeader, Bytes), ParserErrorWithBytes> {
+        loop {
+            if self.data.is_empty() {
+                return Err(ParserErrorWithBytes::from(ParserError::EofExpected));
+            }
+
+            let mut header_input = self.data.as_ref();
+            let common_header = read_common_header(&mut header_input)?;
+            let header_len = self.data.len() - header_input.len();
+
+            let length = common_header.length as usize;
+            if self.data.len() - header_len < length {
+                // consume the rest, as the reader-based parser does with a truncated record
+                self.data.clear();
+                return Err(ParserErrorWithBytes {
+                    error: ParserError::IoError(std::io::Error::from(
+                        std::io::ErrorKind::UnexpectedEof,
+                    )),
+                    bytes: None,
+                });
+            }
+            self.data.advance(header_len);
+            let position = context.count_record(&common_header);
+            if !match_record_filters(&common_header, filters) {
+                self.data.advance(length);
+                continue;
+            }
+            context.last_record = Some(position);
+            return Ok((common_header, self.data.split_to(length)));
+        }
+    }
+}
+
+impl MrtRecordSource for MrtBytes {
+    fn next_matching_mrt_record(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<MrtRecord, ParserErrorWithBytes> {
+        let (common_header, body) = self.split_record_filtered(context, filters)?;
+
+        #[cfg(feature = "alloc-stats")]
+        let _parse_stage = crate::parser::alloc_stats::enter_stage(AllocStage::Parse);
+
+        match parse_mrt_body_limited(&common_header, body, context) {
+            Ok(message) => Ok(MrtRecord {
+                common_header,
+                message,
+            }),
+            Err(e) => Err(ParserErrorWithBytes {
+                error: e,
+                bytes: None,
+            }),
+        }
+    }
+
+    fn next_matching_mrt_header(
+        &mut self,
+        context: &mut ParseContext,
+        filters: &[RecordFilter],
+    ) -> Result<CommonHeader, ParserErrorWithBytes> {
+        self.split_record_filtered(context, filters)
+            .map(|(common_header, _body)| common_header)
+    }
+}
+
+/// Parse the body of the record of the header within the [RecordLimits] of the context.
+pub(crate) fn parse_mrt_body_limited(
+    common_header: &CommonHeader,
+    body: Bytes,
+    context: &mut ParseContext,
+) -> Result<MrtMessage, ParserError> {
+    context.start_record();
+    let result = parse_mrt_body_with_context(
+        common_header.entry_type as u16,
+        common_header.entry_subtype,
+        body,
+        context,
+    );
+    match context.finish_record() {
+        Some(e) => Err(e),
+        None => result,
+    }
+}
+
 /// Parse MRT message body with given entry type and subtype.
 ///
 /// The entry type and subtype are parsed from the common header. The message body is parsed
@@ -84,12 +503,22 @@ pub fn parse_mrt_body(
     entry_type: u16,
     entry_subtype: u16,
     data: Bytes,
+) -> Result<MrtMessage, ParserError> {
+    parse_mrt_body_with_context(entry_type, entry_subtype, data, &mut ParseContext::new())
+}
+
+/// Parse MRT message body with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_mrt_body_with_context(
+    entry_type: u16,
+    entry_subtype: u16,
+    data: Bytes,
+    context: &mut ParseContext,
 ) -> Result<MrtMessage, ParserError> {
     let etype = EntryType::try_from(entry_type)?;
 
     let message: MrtMessage = match &etype {
         EntryType::TABLE_DUMP => {
-            let msg = parse_table_dump_message(entry_subtype, data);
+            let msg = parse_table_dump_message_with_context(entry_subtype, data, context);
             match msg {
                 Ok(msg) => MrtMessage::TableDumpMessage(msg),
                 Err(e) => {
@@ -98,7 +527,7 @@ pub fn parse_mrt_body(
             }
         }
         EntryType::TABLE_DUMP_V2 => {
-            let msg = parse_table_dump_v2_message(entry_subtype, data);
+            let msg = parse_table_dump_v2_message_with_context(entry_subtype, data, context);
             match msg {
                 Ok(msg) => MrtMessage::TableDumpV2Message(msg),
                 Err(e) => {
@@ -107,7 +536,7 @@ pub fn parse_mrt_body(
             }
         }
         EntryType::BGP4MP | EntryType::BGP4MP_ET => {
-            let msg = parse_bgp4mp(entry_subtype, data);
+            let msg = parse_bgp4mp_with_context(entry_subtype, data, context);
             match msg {
                 Ok(msg) => MrtMessage::Bgp4Mp(msg),
                 Err(e) => {
@@ -137,20 +566,116 @@ impl MrtRecord {
         }
         let header_bytes = new_header.encode();
 
-        // // debug begins
-        // let parsed_body = parse_mrt_body(
-        //     self.common_header.entry_type as u16,
-        //     self.common_header.entry_subtype,
-        //     message_bytes.clone(),
-        // )
-        // .unwrap();
-        // assert!(self.message == parsed_body);
-        // // debug ends
-
         bytes.put_slice(&header_bytes);
         bytes.put_slice(&message_bytes);
         bytes.freeze()
     }
+
+    /// Return the record with its common header entry type, subtype, and length derived from the
+    /// message content.
+    ///
+    /// For BGP4MP messages, the AS4 subtype is chosen when the message contains 4-byte ASNs, and
+    /// the `msg_type` field of the message is updated accordingly. Subtypes are only upgraded: an
+    /// AS4 subtype is kept even if all ASNs fit in 2 bytes. The ADD-PATH subtypes are kept as
+    /// they are, since path ID 0 is a valid path identifier and ADD-PATH cannot be told from the
+    /// path IDs of the prefixes. The timestamps are kept as is.
+    pub fn with_recomputed_header(mut self) -> MrtRecord {
+        let subtype = match &mut self.message {
+            MrtMessage::TableDumpMessage(m) => {
+                self.common_header.entry_type = EntryType::TABLE_DUMP;
+                match m.prefix.prefix {
+                    IpNet::V4(_) => Afi::Ipv4 as u16,
+                    IpNet::V6(_) => Afi::Ipv6 as u16,
+                }
+            }
+            MrtMessage::TableDumpV2Message(m) => {
+                self.common_header.entry_type = EntryType::TABLE_DUMP_V2;
+                match m {
+                    TableDumpV2Message::PeerIndexTable(_) => TableDumpV2Type::PeerIndexTable as u16,
+                    TableDumpV2Message::RibAfi(r) => r.rib_type as u16,
+                    TableDumpV2Message::RibGeneric(_) => TableDumpV2Type::RibGeneric as u16,
+                    TableDumpV2Message::GeoPeerTable(_) => TableDumpV2Type::GeoPeerTable as u16,
+                }
+            }
+            MrtMessage::Bgp4Mp(m) => {
+                self.common_header.entry_type = match self.common_header.microsecond_timestamp {
+                    Some(_) => EntryType::BGP4MP_ET,
+                    None => EntryType::BGP4MP,
+                };
+                match m {
+                    Bgp4MpEnum::StateChange(msg) => {
+                        if is_four_byte(&msg.peer_asn) || is_four_byte(&msg.local_asn) {
+                            msg.msg_type = Bgp4MpType::StateChangeAs4;
+                        }
+                        msg.msg_type as u16
+                    }
+                    Bgp4MpEnum::Message(msg) => {
+                        msg.msg_type = required_message_type(msg);
+                        msg.msg_type as u16
+                    }
+                }
+            }
+        };
+        self.common_header.entry_subtype = subtype;
+
+        // encoding RIB_GENERIC messages is not supported, keep the original length
+        if !matches!(
+            self.message,
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibGeneric(_))
+        ) {
+            self.common_header.length = self.message.encode(subtype).len() as u32;
+        }
+        self
+    }
+}
+
+fn is_four_byte(asn: &Asn) -> bool {
+    asn.to_u32() > u16::MAX as u32
+}
+
+/// Get the BGP4MP message subtype that can represent the content of the message.
+fn required_message_type(msg: &Bgp4MpMessage) -> Bgp4MpType {
+    let (local, mut as4, add_path) = match msg.msg_type {
+        Bgp4MpType::Message | Bgp4MpType::StateChange => (false, false, false),
+        Bgp4MpType::MessageAs4 | Bgp4MpType::StateChangeAs4 => (false, true, false),
+        Bgp4MpType::MessageLocal => (true, false, false),
+        Bgp4MpType::MessageAs4Local => (true, true, false),
+        Bgp4MpType::MessageAddpath => (false, false, true),
+        Bgp4MpType::MessageAs4Addpath => (false, true, true),
+        Bgp4MpType::MessageLocalAddpath => (true, false, true),
+        Bgp4MpType::MessageLocalAs4Addpath => (true, true, true),
+    };
+
+    as4 |= is_four_byte(&msg.peer_asn) || is_four_byte(&msg.local_asn);
+    if let BgpMessage::Update(update) = &msg.bgp_message {
+        for attr in update.attributes.iter() {
+            match attr {
+                AttributeValue::AsPath {
+                    path,
+                    is_as4: false,
+                } => {
+                    as4 |= path
+                        .iter_segments()
+                        .any(|segment| segment.iter().any(is_four_byte));
+                }
+                AttributeValue::Aggregator {
+                    asn, is_as4: false, ..
+                } => as4 |= is_four_byte(asn),
+                _ => {}
+            }
+        }
+    }
+
+    match (local, as4, add_path) {
+        (false, false, false) => Bgp4MpType::Message,
+        (false, true, false) => Bgp4MpType::MessageAs4,
+        (true, false, false) => Bgp4MpType::MessageLocal,
+        (true, true, false) => Bgp4MpType::MessageAs4Local,
+        (false, false, true) => Bgp4MpType::MessageAddpath,
+        (false, true, true) => Bgp4MpType::MessageAs4Addpath,
+        (true, false, true) => Bgp4MpType::MessageLocalAddpath,
+        (true, true, true) => Bgp4MpType::MessageLocalAs4Addpath,
+    }
 }
 
 impl TryFrom<&BmpMessage> for MrtRecord {
@@ -210,7 +735,6 @@ mod tests {
     use super::*;
     use crate::bmp::messages::headers::{BmpPeerType, PeerFlags, PerPeerFlags};
     use crate::bmp::messages::{BmpCommonHeader, BmpMsgType, BmpPerPeerHeader, RouteMonitoring};
-    use std::net::Ipv4Addr;
 
     #[test]
     fn test_try_from_bmp_message() {
@@ -223,7 +747,7 @@ mod tests {
             per_peer_header: Some(BmpPerPeerHeader {
                 peer_asn: Asn::new_32bit(0),
                 peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
-                peer_bgp_id: Ipv4Addr::from_str("10.0.0.2").unwrap(),
+                peer_bgp_id: BgpIdentifier::from_str("10.0.0.2").unwrap(),
                 timestamp: 0.0,
                 peer_type: BmpPeerType::Global,
                 peer_flags: PerPeerFlags::PeerFlags(PeerFlags::empty()),
@@ -231,6 +755,7 @@ mod tests {
             }),
             message_body: BmpMessageBody::RouteMonitoring(RouteMonitoring {
                 bgp_message: BgpMessage::KeepAlive,
+                tlvs: vec![],
             }),
         };
 
@@ -238,6 +763,265 @@ mod tests {
         assert_eq!(mrt_record.common_header.entry_type, EntryType::BGP4MP_ET);
     }
 
+    #[test]
+    fn test_with_recomputed_header() {
+        // path ID 0 is a valid ADD-PATH path identifier
+        let update = BgpUpdateMessage {
+            withdrawn_prefixes: vec![],
+            attributes: Attributes::default(),
+            announced_prefixes: vec![NetworkPrefix::from_str("10.0.0.0/24").unwrap()],
+        };
+        let record = MrtRecord {
+            common_header: CommonHeader {
+                timestamp: 0,
+                microsecond_timestamp: None,
+                entry_type: EntryType::BGP4MP,
+                entry_subtype: Bgp4MpType::MessageAddpath as u16,
+                length: 0,
+            },
+            message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                msg_type: Bgp4MpType::MessageAddpath,
+                peer_asn: Asn::new_32bit(400000),
+                local_asn: Asn::new_16bit(65000),
+                interface_index: 0,
+                peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
+                local_ip: IpAddr::from_str("10.0.0.2").unwrap(),
+                bgp_message: BgpMessage::Update(update),
+            })),
+        };
+
+        let record = record.with_recomputed_header();
+        let subtype = Bgp4MpType::MessageAs4Addpath as u16;
+        assert_eq!(record.common_header.entry_subtype, subtype);
+        assert_eq!(
+            record.common_header.length as usize,
+            record.message.encode(subtype).len()
+        );
+        match &record.message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => {
+                assert_eq!(msg.msg_type, Bgp4MpType::MessageAs4Addpath)
+            }
+            _ => unreachable!(),
+        }
+
+        let parsed = parse_mrt_record(&mut std::io::Cursor::new(record.encode())).unwrap();
+        assert_eq!(parsed, record);
+    }
+
+    #[test]
+    fn test_parse_mrt_record_with_context() {
+        let record = MrtRecord {
+            common_header: CommonHeader {
+                timestamp: 0,
+                microsecond_timestamp: None,
+                entry_type: EntryType::BGP4MP,
+                entry_subtype: Bgp4MpType::MessageAs4 as u16,
+                length: 0,
+            },
+            message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                msg_type: Bgp4MpType::MessageAs4,
+                peer_asn: Asn::new_32bit(65000),
+                local_asn: Asn::new_32bit(65001),
+                interface_index: 0,
+                peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
+                local_ip: IpAddr::from_str("10.0.0.2").unwrap(),
+                bgp_message: BgpMessage::KeepAlive,
+            })),
+        }
+        .with_recomputed_header();
+        let bytes = [record.encode(), record.encode()].concat();
+
+        let mut input = std::io::Cursor::new(bytes);
+        let mut context = ParseContext::with_capacity(1024);
+        for _ in 0..2 {
+            let parsed = parse_mrt_record_with_context(&mut input, &mut context).unwrap();
+            assert_eq!(parsed, record);
+        }
+        assert!(parse_mrt_record_with_context(&mut input, &mut context).is_err());
+    }
+
+    #[test]
+    fn test_mrt_bytes() {
+        let record = MrtRecord {
+            common_header: CommonHeader {
+                timestamp: 0,
+                microsecond_timestamp: None,
+                entry_type: EntryType::BGP4MP,
+                entry_subtype: Bgp4MpType::MessageAs4 as u16,
+                length: 0,
+            },
+            message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                msg_type: Bgp4MpType::MessageAs4,
+                peer_asn: Asn::new_32bit(65000),
+                local_asn: Asn::new_32bit(65001),
+                interface_index: 0,
+                peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
+                local_ip: IpAddr::from_str("10.0.0.2").unwrap(),
+                bgp_message: BgpMessage::KeepAlive,
+            })),
+        }
+        .with_recomputed_header();
+        let encoded = record.encode();
+
+        let mut context = ParseContext::new();
+        let mut source = MrtBytes::new(Bytes::from([encoded.clone(), encoded.clone()].concat()));
+        for _ in 0..2 {
+            assert_eq!(source.next_mrt_record(&mut context).unwrap(), record);
+        }
+        assert_eq!(source.remaining(), 0);
+        assert!(matches!(
+            source.next_mrt_record(&mut context).unwrap_err().error,
+            ParserError::EofExpected
+        ));
+
+        // truncated body
+        let mut source = MrtBytes::new(encoded.slice(..encoded.len() - 1));
+        assert!(matches!(
+            source.next_mrt_record(&mut context).unwrap_err().error,
+            ParserError::IoError(_)
+        ));
+        assert_eq!(source.remaining(), 0);
+    }
+
+    #[test]
+    fn test_records_do_not_retain_input() {
+        use crate::encoder::MrtUpdatesEncoder;
+
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(&BgpElem {
+            prefix: NetworkPrefix::from_str("10.0.0.0/8").unwrap(),
+            as_path: Some(AsPath::from_sequence([65000, 65001])),
+            communities: Some(vec![MetaCommunity::Plain(Community::Custom(
+                Asn::new_32bit(65000),
+                100,
+            ))]),
+            unknown: Some(vec![AttrRaw {
+                attr_type: AttrType::Unknown(200),
+                bytes: vec![1, 2, 3],
+            }]),
+            ..Default::default()
+        });
+        let data = Bytes::from(encoder.export_bytes().to_vec());
+
+        let mut source = MrtBytes::new(data.clone());
+        let record = source.next_mrt_record(&mut ParseContext::new()).unwrap();
+        drop(source);
+        assert!(data.is_unique());
+        drop(record);
+    }
+
+    #[test]
+    fn test_copy_values_up_to() {
+        use crate::encoder::MrtUpdatesEncoder;
+
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(&BgpElem {
+            prefix: NetworkPrefix::from_str("10.0.0.0/8").unwrap(),
+            as_path: Some(AsPath::from_sequence([65000, 65001])),
+            unknown: Some(vec![AttrRaw {
+                attr_type: AttrType::Unknown(200),
+                bytes: vec![1, 2, 3],
+            }]),
+            ..Default::default()
+        });
+        let data = Bytes::from(encoder.export_bytes().to_vec());
+
+        let sliced = MrtBytes::new(data.clone())
+            .next_mrt_record(&mut ParseContext::new())
+            .unwrap();
+        let mut context = ParseContext::new().with_copy_values_up_to(4);
+        let copied = MrtBytes::new(data).next_mrt_record(&mut context).unwrap();
+        assert_eq!(sliced, copied);
+    }
+
+    #[test]
+    fn test_recycle_records() {
+        use crate::encoder::MrtUpdatesEncoder;
+
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(&BgpElem {
+            prefix: NetworkPrefix::from_str("10.0.0.0/8").unwrap(),
+            as_path: Some(AsPath::from_sequence([65000, 65001])),
+            ..Default::default()
+        });
+        let data = Bytes::from(encoder.export_bytes().to_vec());
+        let attributes_ptr = |record: &MrtRecord| match &record.message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => match &msg.bgp_message {
+                BgpMessage::Update(update) => update.attributes.inner.as_ptr(),
+                _ => panic!("not an update"),
+            },
+            _ => panic!("not a BGP4MP message"),
+        };
+
+        let mut context = ParseContext::new();
+        let first = MrtBytes::new(data.clone())
+            .next_mrt_record(&mut context)
+            .unwrap();
+        let expected = first.clone();
+        let ptr = attributes_ptr(&first);
+        context.recycle(first);
+
+        let second = MrtBytes::new(data).next_mrt_record(&mut context).unwrap();
+        assert_eq!(attributes_ptr(&second), ptr);
+        assert_eq!(second, expected);
+    }
+
+    #[test]
+    fn test_record_filters_skip_bodies() {
+        let record = |timestamp: u32| {
+            MrtRecord {
+                common_header: CommonHeader {
+                    timestamp,
+                    microsecond_timestamp: None,
+                    entry_type: EntryType::BGP4MP,
+                    entry_subtype: Bgp4MpType::MessageAs4 as u16,
+                    length: 0,
+                },
+                message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                    msg_type: Bgp4MpType::MessageAs4,
+                    peer_asn: Asn::new_32bit(65000),
+                    local_asn: Asn::new_32bit(65001),
+                    interface_index: 0,
+                    peer_ip: IpAddr::from_str("10.0.0.1").unwrap(),
+                    local_ip: IpAddr::from_str("10.0.0.2").unwrap(),
+                    bgp_message: BgpMessage::KeepAlive,
+                })),
+            }
+            .with_recomputed_header()
+        };
+        // the body of the skipped record is not valid, so it must not be parsed
+        let mut invalid = record(1).encode().to_vec();
+        invalid.truncate(12);
+        invalid[11] = 2;
+        invalid.extend([0xff, 0xff]);
+        let data = Bytes::from([invalid, record(2).encode().to_vec()].concat());
+        let filters = [RecordFilter::TsStart(2.0)];
+
+        let mut context = ParseContext::new();
+        let mut source = MrtBytes::new(data.clone());
+        assert_eq!(
+            source
+                .next_matching_mrt_record(&mut context, &filters)
+                .unwrap(),
+            record(2)
+        );
+        assert!(source
+            .next_matching_mrt_record(&mut context, &filters)
+            .is_err());
+
+        let mut reader = data.as_ref();
+        assert_eq!(
+            parse_mrt_record_filtered(&mut reader, &mut context, &filters).unwrap(),
+            record(2)
+        );
+        assert!(matches!(
+            parse_mrt_record_filtered(&mut reader, &mut context, &filters)
+                .unwrap_err()
+                .error,
+            ParserError::EofExpected
+        ));
+    }
+
     #[test]
     fn test_parse_mrt_body() {
         let mut data = BytesMut::new();
@@ -0,0 +1,361 @@
+/*!
+Fast prefix scanning of MRT files.
+
+[scan_prefixes] decodes only the fields needed to locate the prefixes of each record: the prefix
+of RIB dump records, and the withdrawn and announced NLRI of BGP4MP UPDATE messages, including the
+MP_REACH_NLRI and MP_UNREACH_NLRI attributes. All other attributes are skipped by their length, and
+peer index tables are not resolved, which makes the scan much faster than a full parse when
+building prefix indexes or checking whether a file mentions a prefix at all.
+
+```no_run
+use bgpkit_parser::scan_prefixes;
+
+let reader = oneio::get_reader("rib.20240101.0000.bz2").unwrap();
+let target = "1.1.1.0/24".parse().unwrap();
+let found = scan_prefixes(reader).any(|(prefix, _)| prefix.prefix == target);
+```
+*/
+use crate::error::ParserError;
+use crate::models::*;
+use crate::parser::mrt::RecordPosition;
+use crate::parser::{parse_nlri_list, ReadUtils};
+use bytes::{Buf, Bytes};
+use log::debug;
+use std::convert::TryFrom;
+use std::io::Read;
+use std::net::IpAddr;
+
+const ATTR_TYPE_MP_REACH_NLRI: u8 = 14;
+const ATTR_TYPE_MP_UNREACH_NLRI: u8 = 15;
+const BGP_MESSAGE_TYPE_UPDATE: u8 = 2;
+
+/// Position of an MRT record in the input, e.g. of the record a scanned prefix was found in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordOffset {
+    /// zero-based index of the record
+    pub index: u64,
+    /// byte offset of the record's common header from the start of the input
+    pub offset: u64,
+}
+
+/// The record can be extracted with [extract_records](crate::extract_records).
+impl From<RecordOffset> for RecordPosition {
+    fn from(value: RecordOffset) -> Self {
+        RecordPosition::Offset(value.offset)
+    }
+}
+
+/// Iterator over the prefixes of an MRT input, created by [scan_prefixes].
+pub struct PrefixScanner<R> {
+    reader: R,
+    next_record: RecordOffset,
+    current_record: RecordOffset,
+    pending: std::vec::IntoIter<NetworkPrefix>,
+    done: bool,
+}
+
+/// Scan the prefixes of all records of an MRT input, without parsing the records.
+///
+/// Each prefix is returned with the position of its record. Prefixes of BGP4MP updates include
+/// both withdrawn and announced prefixes. Records of other types, and records that cannot be
+/// decoded, are skipped; the scan ends at the end of the input or at the first read error.
+pub fn scan_prefixes<R: Read>(reader: R) -> PrefixScanner<R> {
+    PrefixScanner {
+        reader,
+        next_record: RecordOffset {
+            index: 0,
+            offset: 0,
+        },
+        current_record: RecordOffset {
+            index: 0,
+            offset: 0,
+        },
+        pending: vec![].into_iter(),
+        done: false,
+    }
+}
+
+impl<R: Read> PrefixScanner<R> {
+    /// Read the next record and return its entry type, subtype and body, or `None` at the end of
+    /// the input.
+    fn read_record(&mut self) -> Option<(u16, u16, Bytes)> {
+        let mut header = [0u8; 12];
+        if let Err(e) = self.reader.read_exact(&mut header) {
+            if e.kind() != std::io::ErrorKind::UnexpectedEof {
+                debug!("prefix scan stopped: {}", e);
+            }
+            return None;
+        }
+        let entry_type = u16::from_be_bytes([header[4], header[5]]);
+        let entry_subtype = u16::from_be_bytes([header[6], header[7]]);
+        // the length field does not include the common header
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as usize;
+        let mut body = vec![0u8; length];
+        if let Err(e) = self.reader.read_exact(&mut body) {
+            debug!("prefix scan stopped: {}", e);
+            return None;
+        }
+
+        self.current_record = self.next_record;
+        self.next_record.index += 1;
+        self.next_record.offset += (header.len() + length) as u64;
+        Some((entry_type, entry_subtype, Bytes::from(body)))
+    }
+}
+
+impl<R: Read> Iterator for PrefixScanner<R> {
+    type Item = (NetworkPrefix, RecordOffset);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(prefix) = self.pending.next() {
+                return Some((prefix, self.current_record));
+            }
+            if self.done {
+                return None;
+            }
+            let (entry_type, entry_subtype, body) = match self.read_record() {
+                Some(record) => record,
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            };
+            match record_prefixes(entry_type, entry_subtype, body) {
+                Ok(prefixes) => self.pending = prefixes.into_iter(),
+                Err(e) => debug!(
+                    "skipping record #{} in prefix scan: {}",
+                    self.current_record.index, e
+                ),
+            }
+        }
+    }
+}
+
+/// Prefixes of a record body, empty for records without prefixes.
+fn record_prefixes(
+    entry_type: u16,
+    entry_subtype: u16,
+    mut body: Bytes,
+) -> Result<Vec<NetworkPrefix>, ParserError> {
+    let entry_type = match EntryType::try_from(entry_type) {
+        Ok(t) => t,
+        Err(_) => return Ok(vec![]),
+    };
+    match entry_type {
+        EntryType::TABLE_DUMP => {
+            let afi = match entry_subtype {
+                1 => Afi::Ipv4,
+                2 => Afi::Ipv6,
+                _ => return Ok(vec![]),
+            };
+            // view number and sequence number
+            body.has_n_remaining(4)?;
+            body.advance(4);
+            let addr = body.read_address(&afi)?;
+            let prefix_len = body.read_u8()?;
+            Ok(vec![NetworkPrefix::new(ipnet_from(addr, prefix_len)?, 0)])
+        }
+        EntryType::TABLE_DUMP_V2 => {
+            let rib_type = match TableDumpV2Type::try_from(entry_subtype) {
+                Ok(t) => t,
+                Err(_) => return Ok(vec![]),
+            };
+            let afi = match rib_type {
+                TableDumpV2Type::RibIpv4Unicast
+                | TableDumpV2Type::RibIpv4Multicast
+                | TableDumpV2Type::RibIpv4UnicastAddPath
+                | TableDumpV2Type::RibIpv4MulticastAddPath => Afi::Ipv4,
+                TableDumpV2Type::RibIpv6Unicast
+                | TableDumpV2Type::RibIpv6Multicast
+                | TableDumpV2Type::RibIpv6UnicastAddPath
+                | TableDumpV2Type::RibIpv6MulticastAddPath => Afi::Ipv6,
+                TableDumpV2Type::RibGeneric | TableDumpV2Type::RibGenericAddPath => {
+                    body.read_u32()?;
+                    let afi = body.read_afi()?;
+                    body.read_safi()?;
+                    return Ok(vec![body.read_nlri_prefix(&afi, false)?]);
+                }
+                _ => return Ok(vec![]),
+            };
+            // sequence number, then the prefix without path ID, see RFC 8050
+            body.read_u32()?;
+            Ok(vec![body.read_nlri_prefix(&afi, false)?])
+        }
+        EntryType::BGP4MP | EntryType::BGP4MP_ET => {
+            if entry_type == EntryType::BGP4MP_ET {
+                // microsecond timestamp
+                body.read_u32()?;
+            }
+            let msg_type = match Bgp4MpType::try_from(entry_subtype) {
+                Ok(t) => t,
+                Err(_) => return Ok(vec![]),
+            };
+            let (asn_len, add_path) = match msg_type {
+                Bgp4MpType::Message | Bgp4MpType::MessageLocal => (2, false),
+                Bgp4MpType::MessageAs4 | Bgp4MpType::MessageAs4Local => (4, false),
+                Bgp4MpType::MessageAddpath | Bgp4MpType::MessageLocalAddpath => (2, true),
+                Bgp4MpType::MessageAs4Addpath | Bgp4MpType::MessageLocalAs4Addpath => (4, true),
+                Bgp4MpType::StateChange | Bgp4MpType::StateChangeAs4 => return Ok(vec![]),
+            };
+            // peer and local ASNs, interface index
+            body.has_n_remaining(asn_len * 2 + 2)?;
+            body.advance(asn_len * 2 + 2);
+            let afi = body.read_afi()?;
+            // peer and local IP addresses
+            body.read_address(&afi)?;
+            body.read_address(&afi)?;
+            update_prefixes(body, add_path)
+        }
+        _ => Ok(vec![]),
+    }
+}
+
+/// Prefixes of a BGP message, empty if it is not an UPDATE message.
+fn update_prefixes(mut data: Bytes, add_path: bool) -> Result<Vec<NetworkPrefix>, ParserError> {
+    // marker and length
+    data.has_n_remaining(19)?;
+    data.advance(18);
+    if data.get_u8() != BGP_MESSAGE_TYPE_UPDATE {
+        return Ok(vec![]);
+    }
+
+    let withdrawn_length = data.read_u16()? as usize;
+    data.has_n_remaining(withdrawn_length)?;
+    let mut prefixes = parse_nlri_list(data.split_to(withdrawn_length), add_path, &Afi::Ipv4)?;
+
+    let attributes_length = data.read_u16()? as usize;
+    data.has_n_remaining(attributes_length)?;
+    let mut attributes = data.split_to(attributes_length);
+    while attributes.remaining() > 0 {
+        let flags = attributes.read_u8()?;
+        let attr_type = attributes.read_u8()?;
+        let length = match flags & AttrFlags::EXTENDED.bits() {
+            0 => attributes.read_u8()? as usize,
+            _ => attributes.read_u16()? as usize,
+        };
+        attributes.has_n_remaining(length)?;
+        let mut value = attributes.split_to(length);
+        if attr_type != ATTR_TYPE_MP_REACH_NLRI && attr_type != ATTR_TYPE_MP_UNREACH_NLRI {
+            continue;
+        }
+        let afi = value.read_afi()?;
+        match value.read_safi()? {
+            Safi::Unicast | Safi::Multicast => {}
+            // other SAFIs do not carry plain prefixes
+            _ => continue,
+        }
+        if attr_type == ATTR_TYPE_MP_REACH_NLRI {
+            // next hop and reserved byte
+            let next_hop_length = value.read_u8()? as usize;
+            value.has_n_remaining(next_hop_length + 1)?;
+            value.advance(next_hop_length + 1);
+        }
+        prefixes.extend(parse_nlri_list(value, add_path, &afi)?);
+    }
+
+    // the remaining bytes are the announced IPv4 prefixes
+    prefixes.extend(parse_nlri_list(data, add_path, &Afi::Ipv4)?);
+    Ok(prefixes)
+}
+
+fn ipnet_from(addr: IpAddr, prefix_len: u8) -> Result<ipnet::IpNet, ParserError> {
+    ipnet::IpNet::new(addr, prefix_len).map_err(|_| {
+        ParserError::ParseError(format!("Invalid network prefix length: {}", prefix_len))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::{MrtRibEncoder, MrtUpdatesEncoder};
+    use crate::parse_mrt_record;
+    use crate::parser::mrt::extract_records;
+    use crate::parser::test_elems::{announce, withdraw};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_scan_updates() {
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(
+            &announce("192.0.2.1", "10.0.0.0/8", &[64496, 64497]).with_next_hop("192.0.2.1"),
+        );
+        encoder.process_elem(
+            &announce("192.0.2.1", "2001:db8::/32", &[64496, 64497]).with_next_hop("192.0.2.1"),
+        );
+        encoder.process_elem(&withdraw("192.0.2.1", "10.1.0.0/16"));
+        encoder.process_elem(&withdraw("192.0.2.1", "2001:db8:1::/48"));
+        let bytes = encoder.export_bytes();
+
+        let scanned: Vec<_> = scan_prefixes(Cursor::new(bytes.clone())).collect();
+        let prefixes: Vec<String> = scanned.iter().map(|(p, _)| p.to_string()).collect();
+        assert_eq!(
+            prefixes,
+            [
+                "10.0.0.0/8",
+                "2001:db8::/32",
+                "10.1.0.0/16",
+                "2001:db8:1::/48"
+            ]
+        );
+        let indexes: Vec<u64> = scanned.iter().map(|(_, o)| o.index).collect();
+        assert_eq!(indexes, [0, 1, 2, 3]);
+
+        // the offset locates the record of the prefix
+        let (_, offset) = scanned[1];
+        let mut record = vec![];
+        extract_records(&mut Cursor::new(bytes), &[offset.into()], &mut record).unwrap();
+        let record = parse_mrt_record(&mut Cursor::new(record)).unwrap();
+        let elems = crate::Elementor::new().record_to_elems(record);
+        assert_eq!(elems[0].prefix.to_string(), "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_scan_rib() {
+        let mut encoder = MrtRibEncoder::new();
+        encoder.process_elem(
+            &announce("192.0.2.1", "10.0.0.0/8", &[64496, 64497]).with_next_hop("192.0.2.1"),
+        );
+        encoder.process_elem(
+            &announce("192.0.2.1", "2001:db8::/32", &[64496, 64497]).with_next_hop("192.0.2.1"),
+        );
+        let bytes = encoder.export_bytes();
+
+        let mut prefixes: Vec<String> = scan_prefixes(Cursor::new(bytes))
+            .map(|(p, offset)| {
+                // the peer index table comes first
+                assert!(offset.index > 0);
+                p.to_string()
+            })
+            .collect();
+        prefixes.sort();
+        assert_eq!(prefixes, ["10.0.0.0/8", "2001:db8::/32"]);
+    }
+
+    #[test]
+    fn test_scan_skips_bad_records() {
+        let mut encoder = MrtUpdatesEncoder::new();
+        encoder.process_elem(
+            &announce("192.0.2.1", "10.0.0.0/8", &[64496, 64497]).with_next_hop("192.0.2.1"),
+        );
+        let record = encoder.export_bytes().to_vec();
+
+        // a BGP4MP record with a body too short to decode
+        let mut input = vec![0, 0, 0, 0, 0, 16, 0, 4, 0, 0, 0, 2, 0, 0];
+        input.extend(&record);
+        // truncated at the end
+        input.extend(&record[..20]);
+
+        let scanned: Vec<_> = scan_prefixes(Cursor::new(input)).collect();
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].0.to_string(), "10.0.0.0/8");
+        assert_eq!(
+            scanned[0].1,
+            RecordOffset {
+                index: 1,
+                offset: 14
+            }
+        );
+    }
+}
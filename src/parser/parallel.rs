@@ -0,0 +1,516 @@
+/*!
+Parallel parsing of MRT records into elems.
+
+Parsing the bodies of records and turning them into elems is CPU-bound, e.g. for RIB dumps with
+millions of entries, while reading and decompressing the file is comparatively cheap. With
+[BgpkitParser::into_parallel_elem_iter], the raw records are read on a dedicated thread, and
+batches of records are parsed into elems on the rayon thread pool.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, ParallelConfig};
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/rib-example.bz2").unwrap();
+let mut iter = parser.into_parallel_elem_iter(ParallelConfig::default());
+let count = iter.by_ref().count();
+println!("{} elems, {} errors", count, iter.error_summary().total());
+```
+
+By default the elems are returned in the order of the records in the file, like with
+[ElemIterator](crate::ElemIterator). Set [ParallelConfig::ordered] to `false` to return the
+elems of each batch as soon as it is parsed instead; the elems of a record stay together and in
+order.
+
+The peer index table of table dump v2 files is read on the reading thread, and each record is
+sent with a shared snapshot of the table preceding it, so the RIB records of a file with several
+peer index tables are resolved against the right table.
+
+Record filters, [RecordLimits](crate::RecordLimits), provenance, the enricher, elem filters and
+cancellation of the parser apply as with the sequential iterators. The enricher and the elem
+filters run on the thread consuming the iterator.
+*/
+use crate::error::ParserError;
+use crate::models::*;
+use crate::parser::iters::{ElemContext, ElemEnricher, ErrorSummary};
+use crate::parser::mrt::mrt_record::{parse_mrt_body_limited, read_mrt_record_filtered};
+use crate::parser::{
+    BgpkitParser, CancellationToken, Filter, ParseContext, ParserOptions, RecordFilter,
+    RecordLimits, RecordOffset,
+};
+use crate::{Elementor, Filterable};
+use bytes::Bytes;
+use log::{error, warn};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Read;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+
+/// Configuration of [BgpkitParser::into_parallel_elem_iter].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParallelConfig {
+    /// return the elems in the order of the records in the input, defaults to true
+    pub ordered: bool,
+    /// number of records parsed together by a worker, defaults to 1000
+    pub batch_size: usize,
+    /// maximum number of batches read but not yet consumed, bounding the memory used when the
+    /// consumer is slower than the workers. Defaults to four times the number of rayon threads.
+    pub max_batches: usize,
+}
+
+impl Default for ParallelConfig {
+    fn default() -> Self {
+        ParallelConfig {
+            ordered: true,
+            batch_size: 1000,
+            max_batches: 4 * rayon::current_num_threads(),
+        }
+    }
+}
+
+impl<R: Read + Send + 'static> BgpkitParser<R> {
+    /// Parse the records of this parser into elems on the rayon thread pool, see
+    /// [parallel](crate::parser::parallel).
+    pub fn into_parallel_elem_iter(self, config: ParallelConfig) -> ParallelElemIterator {
+        let BgpkitParser {
+            reader,
+            core_dump,
+            filters,
+            options,
+        } = self;
+        let ParserOptions {
+            show_warnings,
+            cancellation,
+            record_filters,
+            confed,
+            enricher,
+            record_limits,
+            record_provenance,
+        } = options;
+
+        let max_batches = config.max_batches.max(1);
+        let (batch_sender, batches) = channel();
+        let (permits, permit_receiver) = sync_channel(max_batches);
+        for _ in 0..max_batches {
+            // cannot block, as the channel has room for all permits
+            let _ = permits.send(());
+        }
+
+        let mut record_reader = RecordReader {
+            reader,
+            context: ParseContext::new().with_limits(record_limits),
+            record_filters,
+            batch_size: config.batch_size.max(1),
+            cancellation: cancellation.clone(),
+            elementor: Elementor::new(),
+            peer_table: None,
+            worker: BatchParser {
+                confed,
+                limits: record_limits,
+                provenance: record_provenance,
+            },
+        };
+        std::thread::spawn(move || record_reader.run(batch_sender, permit_receiver));
+
+        ParallelElemIterator {
+            batches,
+            permits,
+            ordered: config.ordered,
+            next_seq: 0,
+            pending: BTreeMap::new(),
+            records: VecDeque::new(),
+            cache_elems: vec![],
+            filters,
+            enricher,
+            confed,
+            cancellation,
+            show_warnings,
+            core_dump,
+            errors: ErrorSummary::default(),
+            finished: false,
+        }
+    }
+}
+
+/// Record read from the input, with its body not parsed yet.
+struct RawRecord {
+    header: CommonHeader,
+    body: Bytes,
+    position: RecordOffset,
+    /// the peer index table preceding the record
+    peer_table: Option<Arc<PeerIndexTable>>,
+}
+
+/// Record parsed into elems by a worker.
+struct ParsedRecord {
+    header: CommonHeader,
+    peer_table: Option<Arc<PeerIndexTable>>,
+    elems: Vec<BgpElem>,
+}
+
+/// Batch of records parsed by a worker, with its sequence number in the input.
+struct ParsedBatch {
+    seq: u64,
+    records: Vec<Result<ParsedRecord, ParserError>>,
+}
+
+/// Reader of raw records, running on its own thread.
+struct RecordReader<R> {
+    reader: R,
+    context: ParseContext,
+    record_filters: Vec<RecordFilter>,
+    batch_size: usize,
+    cancellation: CancellationToken,
+    /// elementor keeping track of the peer index table
+    elementor: Elementor,
+    peer_table: Option<Arc<PeerIndexTable>>,
+    worker: BatchParser,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// Read batches of records and dispatch them to the rayon thread pool until the end of the
+    /// input, the first IO error, or cancellation.
+    fn run(&mut self, batches: Sender<ParsedBatch>, permits: Receiver<()>) {
+        let mut seq = 0;
+        loop {
+            // wait for a batch to be consumed before reading the next one. fails once the
+            // iterator is dropped.
+            if permits.recv().is_err() {
+                return;
+            }
+            let (records, end) = self.read_batch();
+            if !records.is_empty() {
+                let worker = self.worker;
+                let batches = batches.clone();
+                rayon::spawn(move || {
+                    let records = worker.parse_batch(records);
+                    // the iterator may have been dropped already
+                    let _ = batches.send(ParsedBatch { seq, records });
+                });
+                seq += 1;
+            }
+            if end {
+                return;
+            }
+        }
+    }
+
+    /// Read the next batch of records, returning whether the reading ended.
+    fn read_batch(&mut self) -> (Vec<Result<RawRecord, ParserError>>, bool) {
+        let mut records = Vec::with_capacity(self.batch_size);
+        while records.len() < self.batch_size {
+            if self.cancellation.is_cancelled() {
+                return (records, true);
+            }
+            let (header, body) = match read_mrt_record_filtered(
+                &mut self.reader,
+                &mut self.context,
+                &self.record_filters,
+            ) {
+                Ok(v) => v,
+                Err(e) => {
+                    // only IO errors are returned before parsing the body
+                    if !matches!(e.error, ParserError::EofExpected) {
+                        records.push(Err(e.error));
+                    }
+                    return (records, true);
+                }
+            };
+            if is_peer_table(&header) {
+                // later records depend on the table, so it is parsed here
+                match parse_mrt_body_limited(&header, body, &mut self.context) {
+                    Ok(message) => {
+                        self.elementor.record_to_elems(MrtRecord {
+                            common_header: header,
+                            message,
+                        });
+                        self.peer_table = self.elementor.peer_table().cloned().map(Arc::new);
+                    }
+                    Err(e) => records.push(Err(e)),
+                }
+                continue;
+            }
+            records.push(Ok(RawRecord {
+                header,
+                body,
+                position: self.context.last_record_position().unwrap_or_default(),
+                peer_table: self.peer_table.clone(),
+            }));
+        }
+        (records, false)
+    }
+}
+
+/// Returns true for the table dump v2 peer index tables and geo-location peer tables.
+fn is_peer_table(header: &CommonHeader) -> bool {
+    header.entry_type == EntryType::TABLE_DUMP_V2
+        && (header.entry_subtype == TableDumpV2Type::PeerIndexTable as u16
+            || header.entry_subtype == TableDumpV2Type::GeoPeerTable as u16)
+}
+
+/// Options for parsing batches of records on the workers.
+#[derive(Debug, Clone, Copy)]
+struct BatchParser {
+    confed: Option<ConfedSegments>,
+    limits: RecordLimits,
+    provenance: bool,
+}
+
+impl BatchParser {
+    fn parse_batch(
+        &self,
+        records: Vec<Result<RawRecord, ParserError>>,
+    ) -> Vec<Result<ParsedRecord, ParserError>> {
+        let mut elementor = match self.confed {
+            Some(confed) => Elementor::new().with_confed_segments(confed),
+            None => Elementor::new(),
+        };
+        let mut context = ParseContext::new().with_limits(self.limits);
+        // the table set on the elementor, only copied when it changes within the batch
+        let mut peer_table: Option<Arc<PeerIndexTable>> = None;
+        records
+            .into_iter()
+            .map(|record| {
+                let record = record?;
+                let same_table = match (&peer_table, &record.peer_table) {
+                    (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if !same_table {
+                    elementor.set_peer_table(record.peer_table.as_deref().cloned());
+                    peer_table = record.peer_table.clone();
+                }
+                let message = parse_mrt_body_limited(&record.header, record.body, &mut context)?;
+                let mut elems = elementor.record_to_elems(MrtRecord {
+                    common_header: record.header,
+                    message,
+                });
+                if self.provenance {
+                    for elem in elems.iter_mut() {
+                        elem.record_index = Some(record.position.index);
+                        elem.record_offset = Some(record.position.offset);
+                    }
+                }
+                Ok(ParsedRecord {
+                    header: record.header,
+                    peer_table: record.peer_table,
+                    elems,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Iterator over the [BgpElem]s of a parser parsed in parallel, see
+/// [BgpkitParser::into_parallel_elem_iter].
+///
+/// Dropping the iterator stops the reading thread once the batches already dispatched are parsed.
+pub struct ParallelElemIterator {
+    batches: Receiver<ParsedBatch>,
+    /// returns a permit to the reading thread for each batch consumed
+    permits: SyncSender<()>,
+    ordered: bool,
+    /// sequence number of the next batch when ordered
+    next_seq: u64,
+    /// batches received ahead of the next one when ordered
+    pending: BTreeMap<u64, Vec<Result<ParsedRecord, ParserError>>>,
+    /// remaining records of the current batch
+    records: VecDeque<Result<ParsedRecord, ParserError>>,
+    /// remaining elems of the current record, reversed
+    cache_elems: Vec<BgpElem>,
+    filters: Vec<Filter>,
+    enricher: Option<ElemEnricher>,
+    confed: Option<ConfedSegments>,
+    cancellation: CancellationToken,
+    show_warnings: bool,
+    core_dump: bool,
+    errors: ErrorSummary,
+    finished: bool,
+}
+
+impl ParallelElemIterator {
+    /// Returns the summary of errors encountered so far.
+    ///
+    /// Call this after the iterator is exhausted to check whether any records were skipped.
+    pub fn error_summary(&self) -> &ErrorSummary {
+        &self.errors
+    }
+
+    /// Take the next batch of records, returning false once all batches are consumed.
+    fn next_batch(&mut self) -> bool {
+        loop {
+            if let Some(records) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                self.records = records.into();
+                let _ = self.permits.try_send(());
+                return true;
+            }
+            match self.batches.recv() {
+                Ok(batch) => match self.ordered {
+                    true => {
+                        self.pending.insert(batch.seq, batch.records);
+                    }
+                    false => {
+                        self.records = batch.records.into();
+                        let _ = self.permits.try_send(());
+                        return true;
+                    }
+                },
+                // the reading thread and all workers are done
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Enrich the elems of a record and cache them.
+    fn cache_record(&mut self, record: ParsedRecord) {
+        let mut elems = record.elems;
+        if let Some(enricher) = &mut self.enricher {
+            let context = ElemContext {
+                header: &record.header,
+                peer_table: record.peer_table.as_deref(),
+                confed: self.confed,
+            };
+            for elem in elems.iter_mut() {
+                enricher(elem, &context);
+            }
+        }
+        elems.reverse();
+        self.cache_elems = elems;
+    }
+
+    /// Count the error of a record, returning false if it stops the iteration.
+    fn count_error(&mut self, error: ParserError) -> bool {
+        match &error {
+            ParserError::TruncatedMsg(_) => self.errors.truncated += 1,
+            ParserError::Unsupported(_) => self.errors.unsupported += 1,
+            _ => {}
+        }
+        match error {
+            ParserError::TruncatedMsg(err_str) | ParserError::Unsupported(err_str) => {
+                if self.show_warnings {
+                    warn!("parser warn: {}", err_str);
+                }
+                self.errors.last_error = Some(err_str);
+                true
+            }
+            ParserError::ParseError(err_str) => {
+                error!("parser error: {}", err_str);
+                self.errors.parse_errors += 1;
+                self.errors.last_error = Some(err_str);
+                !self.core_dump
+            }
+            ParserError::LimitExceeded(err_str) => {
+                if self.show_warnings {
+                    warn!("parser warn: {}", err_str);
+                }
+                self.errors.limit_exceeded += 1;
+                self.errors.last_error = Some(err_str);
+                true
+            }
+            ParserError::EofExpected | ParserError::FilterError(_) => false,
+            ParserError::IoError(err) | ParserError::EofError(err) => {
+                error!("{:?}", err);
+                self.errors.io_errors += 1;
+                self.errors.last_error = Some(err.to_string());
+                false
+            }
+            #[cfg(feature = "oneio")]
+            ParserError::OneIoError(err) => {
+                self.errors.io_errors += 1;
+                self.errors.last_error = Some(err.to_string());
+                false
+            }
+        }
+    }
+}
+
+impl Iterator for ParallelElemIterator {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            if self.cancellation.is_cancelled() {
+                // drop the remaining elems of the current record as well
+                self.cache_elems.clear();
+                self.errors.cancelled = true;
+                return None;
+            }
+            if let Some(elem) = self.cache_elems.pop() {
+                match elem.match_filters(&self.filters) {
+                    true => return Some(elem),
+                    false => continue,
+                }
+            }
+            match self.records.pop_front() {
+                Some(Ok(record)) => self.cache_record(record),
+                Some(Err(e)) => {
+                    if !self.count_error(e) {
+                        self.finished = true;
+                    }
+                }
+                None => {
+                    if !self.next_batch() {
+                        self.finished = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors;
+
+    fn input() -> Vec<u8> {
+        test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+            .flat_map(|v| v.bytes)
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_elems() {
+        let expected: Vec<BgpElem> = BgpkitParser::from_bytes(input())
+            .with_record_provenance()
+            .into_elem_iter()
+            .collect();
+        assert_eq!(expected.len(), 5);
+
+        let config = ParallelConfig {
+            ordered: true,
+            batch_size: 1,
+            max_batches: 2,
+        };
+        let mut iter = BgpkitParser::from_reader(std::io::Cursor::new(input()))
+            .with_record_provenance()
+            .into_parallel_elem_iter(config);
+        let elems: Vec<BgpElem> = iter.by_ref().collect();
+        assert_eq!(elems, expected);
+        assert!(!iter.error_summary().has_errors());
+
+        let config = ParallelConfig {
+            ordered: false,
+            ..config
+        };
+        let mut elems: Vec<BgpElem> = BgpkitParser::from_reader(std::io::Cursor::new(input()))
+            .with_record_provenance()
+            .into_parallel_elem_iter(config)
+            .collect();
+        elems.sort_by_key(|e| e.record_index);
+        assert_eq!(elems, expected);
+
+        // elem filters apply to the elems of all batches
+        let elems: Vec<BgpElem> = BgpkitParser::from_reader(std::io::Cursor::new(input()))
+            .add_filter("type", "a")
+            .unwrap()
+            .into_parallel_elem_iter(ParallelConfig::default())
+            .collect();
+        assert_eq!(elems.len(), 4);
+    }
+}
@@ -0,0 +1,720 @@
+/*!
+BGP messages from packet captures of BGP sessions.
+
+[PcapBgpReader] reads a pcap file, e.g. captured with `tcpdump -w bgp.pcap tcp port 179`,
+reassembles the TCP byte streams of the BGP sessions in it, and yields the contained BGP messages
+as BGP4MP [MrtRecord]s, as a collector would have archived them, so that captured sessions can be
+analyzed with the same tooling as MRT files. [BgpStreamReader] reads the BGP messages of one
+direction of a session from its raw TCP payload, e.g. as exported by Wireshark's "Follow TCP
+Stream".
+
+```no_run
+use bgpkit_parser::pcap::PcapBgpReader;
+
+let reader = PcapBgpReader::new(std::fs::File::open("bgp.pcap").unwrap()).unwrap();
+for elem in reader.into_elem_iter() {
+    println!("{}", elem);
+}
+```
+
+Each message is recorded with its sender as the peer and its receiver as the local side, so the
+messages of both directions of a session are included. The ASNs of the speakers, and the use of
+4-octet AS numbers and ADD-PATH, are learned from the OPEN messages of a session. For sessions
+whose OPEN messages are not in the capture, the ASNs are 0, and 4-octet AS numbers without
+ADD-PATH are assumed unless set otherwise with [PcapBgpReader::with_asn_len] and
+[PcapBgpReader::with_add_path].
+
+Only the classic pcap format is supported; pcapng files can be converted with
+`editcap -F pcap in.pcapng out.pcap`. Packets are matched by TCP port, 179 by default, and
+fragmented IP packets are skipped.
+*/
+use crate::models::capabilities::BgpCapabilityType;
+use crate::models::*;
+use crate::parser::bgp::parse_bgp_message;
+use crate::{Elementor, ParserError};
+use bytes::{Buf, Bytes, BytesMut};
+use log::warn;
+use std::collections::{HashMap, VecDeque};
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Largest packet accepted in a capture, to not allocate arbitrary amounts on corrupted files.
+const MAX_PACKET_LEN: usize = 256 * 1024;
+
+/// Number of out-of-order segments buffered per direction before the missing data is considered
+/// lost and skipped.
+const MAX_PENDING_SEGMENTS: usize = 64;
+
+const BGP_MARKER: [u8; 16] = [0xff; 16];
+
+/// Endpoint of a TCP connection.
+type Endpoint = (IpAddr, u16);
+
+/// One direction of a TCP connection, as (sender, receiver).
+type FlowKey = (Endpoint, Endpoint);
+
+/// Reassembled byte stream of one direction of a TCP connection.
+#[derive(Debug, Default)]
+struct TcpFlow {
+    next_seq: Option<u32>,
+    /// segments received ahead of `next_seq`
+    pending: Vec<(u32, Vec<u8>)>,
+    /// reassembled bytes not forming a complete BGP message yet
+    buffer: BytesMut,
+}
+
+impl TcpFlow {
+    /// Add a segment to the stream, in or out of order.
+    fn add_segment(&mut self, seq: u32, payload: &[u8]) {
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        // offsets are compared in sequence number space, which wraps around
+        match seq.wrapping_sub(next_seq) as i32 {
+            offset if offset > 0 => {
+                self.pending.push((seq, payload.to_vec()));
+                if self.pending.len() > MAX_PENDING_SEGMENTS {
+                    // the missing data was not captured, continue after the gap
+                    let first = self
+                        .pending
+                        .iter()
+                        .map(|(seq, _)| *seq)
+                        .min_by_key(|seq| seq.wrapping_sub(next_seq))
+                        .unwrap();
+                    warn!("TCP segments missing from the capture, skipping to the next message");
+                    self.next_seq = Some(first);
+                    self.buffer.clear();
+                    self.drain_pending();
+                }
+            }
+            offset => {
+                self.append(seq, payload, offset);
+                self.drain_pending();
+            }
+        }
+    }
+
+    /// Append the part of a segment starting at or before `next_seq` that was not seen yet.
+    fn append(&mut self, seq: u32, payload: &[u8], offset: i32) {
+        let skip = offset.unsigned_abs() as usize;
+        if skip < payload.len() {
+            self.buffer.extend_from_slice(&payload[skip..]);
+            self.next_seq = Some(seq.wrapping_add(payload.len() as u32));
+        }
+    }
+
+    /// Append the buffered segments that became in order.
+    fn drain_pending(&mut self) {
+        while let Some(next_seq) = self.next_seq {
+            let index = self
+                .pending
+                .iter()
+                .position(|(seq, _)| seq.wrapping_sub(next_seq) as i32 <= 0);
+            match index {
+                Some(index) => {
+                    let (seq, payload) = self.pending.swap_remove(index);
+                    let offset = seq.wrapping_sub(next_seq) as i32;
+                    self.append(seq, &payload, offset);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Take the next complete BGP message out of the stream, skipping bytes before the next
+    /// marker, e.g. when the capture starts in the middle of a message.
+    fn next_message(&mut self) -> Option<Bytes> {
+        loop {
+            match find_marker(&self.buffer) {
+                Some(0) => {}
+                Some(start) => self.buffer.advance(start),
+                None => {
+                    // keep a possible partial marker at the end
+                    let keep = self.buffer.len().min(BGP_MARKER.len() - 1);
+                    self.buffer.advance(self.buffer.len() - keep);
+                    return None;
+                }
+            }
+            if self.buffer.len() < 19 {
+                return None;
+            }
+            let length = u16::from_be_bytes([self.buffer[16], self.buffer[17]]) as usize;
+            if !(19..=4096).contains(&length) {
+                // not a message header, look for the next marker
+                self.buffer.advance(1);
+                continue;
+            }
+            if self.buffer.len() < length {
+                return None;
+            }
+            return Some(self.buffer.split_to(length).freeze());
+        }
+    }
+}
+
+/// Position of the first complete BGP marker in the data.
+fn find_marker(data: &[u8]) -> Option<usize> {
+    data.windows(BGP_MARKER.len())
+        .position(|window| window == BGP_MARKER)
+}
+
+/// BGP session parameters announced by a speaker in its OPEN message.
+#[derive(Debug, Clone, Copy)]
+struct SpeakerInfo {
+    asn: Asn,
+    as4: bool,
+    add_path_send: bool,
+    add_path_receive: bool,
+}
+
+impl From<&BgpOpenMessage> for SpeakerInfo {
+    fn from(open: &BgpOpenMessage) -> Self {
+        let mut info = SpeakerInfo {
+            asn: open.asn,
+            as4: false,
+            add_path_send: false,
+            add_path_receive: false,
+        };
+        for param in &open.opt_params {
+            let cap = match &param.param_value {
+                ParamValue::Capability(cap) => cap,
+                ParamValue::Raw(_) => continue,
+            };
+            match cap.ty {
+                BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY
+                    if cap.value.len() == 4 =>
+                {
+                    let asn = [cap.value[0], cap.value[1], cap.value[2], cap.value[3]];
+                    info.asn = Asn::new_32bit(u32::from_be_bytes(asn));
+                    info.as4 = true;
+                }
+                BgpCapabilityType::ADD_PATH_CAPABILITY => {
+                    for entry in cap.value.chunks_exact(4) {
+                        info.add_path_receive |= entry[3] & 1 != 0;
+                        info.add_path_send |= entry[3] & 2 != 0;
+                    }
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// TCP segment of a captured packet.
+struct TcpSegment<'a> {
+    src: Endpoint,
+    dst: Endpoint,
+    seq: u32,
+    syn: bool,
+    payload: &'a [u8],
+}
+
+/// Reader of the BGP messages of the sessions in a pcap capture, yielding them as BGP4MP records.
+pub struct PcapBgpReader<R> {
+    reader: R,
+    big_endian: bool,
+    nanosecond: bool,
+    link_type: u32,
+    port: u16,
+    asn_len: AsnLength,
+    add_path: bool,
+    flows: HashMap<FlowKey, TcpFlow>,
+    speakers: HashMap<FlowKey, SpeakerInfo>,
+    records: VecDeque<MrtRecord>,
+}
+
+#[cfg(feature = "oneio")]
+impl PcapBgpReader<Box<dyn Read + Send>> {
+    /// Read a capture from a local or remote file, decompressed according to its extension.
+    pub fn from_path(path: &str) -> Result<Self, ParserError> {
+        PcapBgpReader::new(oneio::get_reader(path)?)
+    }
+}
+
+impl<R: Read> PcapBgpReader<R> {
+    /// Read the pcap file header. Fails for pcapng and other formats.
+    pub fn new(mut reader: R) -> Result<Self, ParserError> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+        let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let (big_endian, nanosecond) = match magic {
+            0xa1b2c3d4 => (false, false),
+            0xd4c3b2a1 => (true, false),
+            0xa1b23c4d => (false, true),
+            0x4d3cb2a1 => (true, true),
+            0x0a0d0d0a => {
+                return Err(ParserError::Unsupported(
+                    "pcapng captures are not supported, convert them to pcap first".to_string(),
+                ))
+            }
+            _ => {
+                return Err(ParserError::ParseError(format!(
+                    "not a pcap file, magic number {:#010x}",
+                    magic
+                )))
+            }
+        };
+        let link_type = read_u32(&header[20..24], big_endian);
+        if link_type_header_len(link_type).is_none() {
+            return Err(ParserError::Unsupported(format!(
+                "unsupported pcap link type {}",
+                link_type
+            )));
+        }
+        Ok(PcapBgpReader {
+            reader,
+            big_endian,
+            nanosecond,
+            link_type,
+            port: 179,
+            asn_len: AsnLength::Bits32,
+            add_path: false,
+            flows: HashMap::new(),
+            speakers: HashMap::new(),
+            records: VecDeque::new(),
+        })
+    }
+
+    /// Only read packets from or to this TCP port, 179 by default.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// AS number length of the sessions whose OPEN messages are not in the capture.
+    pub fn with_asn_len(mut self, asn_len: AsnLength) -> Self {
+        self.asn_len = asn_len;
+        self
+    }
+
+    /// Whether path IDs are used in the sessions whose OPEN messages are not in the capture.
+    pub fn with_add_path(mut self, add_path: bool) -> Self {
+        self.add_path = add_path;
+        self
+    }
+
+    /// Iterate over the elems of the captured UPDATE messages.
+    pub fn into_elem_iter(self) -> impl Iterator<Item = BgpElem> {
+        let mut elementor = Elementor::new();
+        self.flat_map(move |record| elementor.record_to_elems(record))
+    }
+
+    /// Read the next packet and queue the BGP messages it completes. Returns `Ok(false)` at the
+    /// end of the capture.
+    fn read_packet(&mut self) -> Result<bool, ParserError> {
+        let mut header = [0u8; 16];
+        match self.reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let seconds = read_u32(&header[0..4], self.big_endian);
+        let fraction = read_u32(&header[4..8], self.big_endian);
+        let captured_len = read_u32(&header[8..12], self.big_endian) as usize;
+        if captured_len > MAX_PACKET_LEN {
+            return Err(ParserError::ParseError(format!(
+                "invalid pcap packet length {}",
+                captured_len
+            )));
+        }
+        let mut data = vec![0u8; captured_len];
+        self.reader.read_exact(&mut data)?;
+
+        let microseconds = match self.nanosecond {
+            true => fraction / 1000,
+            false => fraction,
+        };
+        if let Some(segment) = parse_packet(&data, self.link_type) {
+            if segment.src.1 == self.port || segment.dst.1 == self.port {
+                self.process_segment(segment, seconds, microseconds);
+            }
+        }
+        Ok(true)
+    }
+
+    fn process_segment(&mut self, segment: TcpSegment, seconds: u32, microseconds: u32) {
+        let key = (segment.src, segment.dst);
+        if segment.syn {
+            // new connection, forget the state of a previous one between the same endpoints
+            self.flows.insert(
+                key,
+                TcpFlow {
+                    next_seq: Some(segment.seq.wrapping_add(1)),
+                    ..Default::default()
+                },
+            );
+            self.speakers.remove(&key);
+            self.speakers.remove(&(segment.dst, segment.src));
+        }
+        if segment.payload.is_empty() {
+            return;
+        }
+
+        let flow = self.flows.entry(key).or_default();
+        flow.add_segment(segment.seq, segment.payload);
+        let mut messages = vec![];
+        while let Some(message) = flow.next_message() {
+            messages.push(message);
+        }
+
+        for mut data in messages {
+            let sender = self.speakers.get(&key).copied();
+            let receiver = self.speakers.get(&(segment.dst, segment.src)).copied();
+            let (asn_len, add_path) = match (sender, receiver) {
+                (Some(s), Some(r)) => (
+                    match s.as4 && r.as4 {
+                        true => AsnLength::Bits32,
+                        false => AsnLength::Bits16,
+                    },
+                    s.add_path_send && r.add_path_receive,
+                ),
+                _ => (self.asn_len, self.add_path),
+            };
+            let bgp_message = match parse_bgp_message(&mut data, add_path, &asn_len) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    warn!("failed to parse BGP message from {}: {}", segment.src.0, e);
+                    continue;
+                }
+            };
+            if let BgpMessage::Open(open) = &bgp_message {
+                self.speakers.insert(key, SpeakerInfo::from(open));
+            }
+            let sender = self.speakers.get(&key).copied();
+
+            let msg_type = match (asn_len, add_path) {
+                (AsnLength::Bits32, false) => Bgp4MpType::MessageAs4,
+                (AsnLength::Bits32, true) => Bgp4MpType::MessageAs4Addpath,
+                (AsnLength::Bits16, false) => Bgp4MpType::Message,
+                (AsnLength::Bits16, true) => Bgp4MpType::MessageAddpath,
+            };
+            let message = Bgp4MpMessage {
+                msg_type,
+                peer_asn: sender.map(|s| s.asn).unwrap_or_default(),
+                local_asn: receiver.map(|s| s.asn).unwrap_or_default(),
+                interface_index: 0,
+                peer_ip: segment.src.0,
+                local_ip: segment.dst.0,
+                bgp_message,
+            };
+            let record = MrtRecord {
+                common_header: CommonHeader {
+                    timestamp: seconds,
+                    microsecond_timestamp: Some(microseconds),
+                    entry_type: EntryType::BGP4MP_ET,
+                    entry_subtype: msg_type as u16,
+                    length: 0,
+                },
+                message: MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(message)),
+            };
+            self.records.push_back(record.with_recomputed_header());
+        }
+    }
+}
+
+impl<R: Read> Iterator for PcapBgpReader<R> {
+    type Item = MrtRecord;
+
+    fn next(&mut self) -> Option<MrtRecord> {
+        loop {
+            if let Some(record) = self.records.pop_front() {
+                return Some(record);
+            }
+            match self.read_packet() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    warn!("failed to read pcap packet: {}", e);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+fn read_u32(data: &[u8], big_endian: bool) -> u32 {
+    let bytes = [data[0], data[1], data[2], data[3]];
+    match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    }
+}
+
+/// Length of the link-layer header of the supported link types.
+fn link_type_header_len(link_type: u32) -> Option<usize> {
+    match link_type {
+        // BSD loopback
+        0 => Some(4),
+        // Ethernet, without VLAN tags
+        1 => Some(14),
+        // raw IP, IPv4 and IPv6
+        12 | 101 | 228 | 229 => Some(0),
+        // Linux cooked capture v1 and v2
+        113 => Some(16),
+        276 => Some(20),
+        _ => None,
+    }
+}
+
+/// The TCP segment of a captured packet, if it is an unfragmented TCP packet over IPv4 or IPv6.
+fn parse_packet(data: &[u8], link_type: u32) -> Option<TcpSegment<'_>> {
+    let mut offset = link_type_header_len(link_type)?;
+    if link_type == 1 {
+        // skip 802.1Q and 802.1ad VLAN tags
+        let mut ether_type = u16::from_be_bytes([*data.get(12)?, *data.get(13)?]);
+        while matches!(ether_type, 0x8100 | 0x88a8) {
+            ether_type = u16::from_be_bytes([*data.get(offset + 2)?, *data.get(offset + 3)?]);
+            offset += 4;
+        }
+    }
+    let ip = data.get(offset..)?;
+    let (src, dst, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            if ip.len() < 20 || header_len < 20 {
+                return None;
+            }
+            // more fragments flag or a fragment offset
+            let fragment = u16::from_be_bytes([ip[6], ip[7]]);
+            if ip[9] != 6 || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+            let dst = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+            // the total length excludes link-layer padding
+            let total_len = u16::from_be_bytes([ip[2], ip[3]]) as usize;
+            let end = total_len.min(ip.len());
+            (IpAddr::V4(src), IpAddr::V4(dst), ip.get(header_len..end)?)
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            // extension headers are not supported
+            if *ip.get(6)? != 6 || ip.len() < 40 {
+                return None;
+            }
+            let src: [u8; 16] = ip[8..24].try_into().ok()?;
+            let dst: [u8; 16] = ip[24..40].try_into().ok()?;
+            let end = (40 + payload_len).min(ip.len());
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                ip.get(40..end)?,
+            )
+        }
+        _ => return None,
+    };
+
+    let header_len = ((*tcp.get(12)? >> 4) as usize) * 4;
+    Some(TcpSegment {
+        src: (src, u16::from_be_bytes([tcp[0], tcp[1]])),
+        dst: (dst, u16::from_be_bytes([tcp[2], tcp[3]])),
+        seq: u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]),
+        syn: *tcp.get(13)? & 0x02 != 0,
+        payload: tcp.get(header_len..)?,
+    })
+}
+
+/// Reader of the BGP messages in the TCP payload of one direction of a BGP session.
+pub struct BgpStreamReader<R> {
+    reader: R,
+    asn_len: AsnLength,
+    add_path: bool,
+}
+
+impl<R: Read> BgpStreamReader<R> {
+    /// Read messages with 4-octet AS numbers and without ADD-PATH.
+    pub fn new(reader: R) -> Self {
+        BgpStreamReader {
+            reader,
+            asn_len: AsnLength::Bits32,
+            add_path: false,
+        }
+    }
+
+    pub fn with_asn_len(mut self, asn_len: AsnLength) -> Self {
+        self.asn_len = asn_len;
+        self
+    }
+
+    pub fn with_add_path(mut self, add_path: bool) -> Self {
+        self.add_path = add_path;
+        self
+    }
+
+    /// Read the next message. Returns `Ok(None)` once the stream ends at a message boundary.
+    pub fn read_message(&mut self) -> Result<Option<BgpMessage>, ParserError> {
+        let mut header = [0u8; 19];
+        match self.reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        if header[..16] != BGP_MARKER {
+            return Err(ParserError::ParseError(
+                "BGP message marker not found".to_string(),
+            ));
+        }
+        let length = u16::from_be_bytes([header[16], header[17]]) as usize;
+        if !(19..=4096).contains(&length) {
+            return Err(ParserError::ParseError(format!(
+                "invalid BGP message length {}",
+                length
+            )));
+        }
+        let mut data = BytesMut::zeroed(length);
+        data[..19].copy_from_slice(&header);
+        self.reader.read_exact(&mut data[19..])?;
+        parse_bgp_message(&mut data.freeze(), self.add_path, &self.asn_len).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for BgpStreamReader<R> {
+    type Item = Result<BgpMessage, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    /// Encode a message with the all-ones marker sent by BGP speakers; the encoder zeroes it.
+    fn encode(msg: BgpMessage) -> Vec<u8> {
+        let mut bytes = msg.encode(false, AsnLength::Bits32).to_vec();
+        bytes[..16].copy_from_slice(&BGP_MARKER);
+        bytes
+    }
+
+    fn open_message() -> Vec<u8> {
+        let open = BgpOpenMessage {
+            version: 4,
+            asn: Asn::new_16bit(64496),
+            hold_time: 180,
+            sender_ip: BgpIdentifier::new(1),
+            extended_length: false,
+            opt_params: vec![OptParam {
+                param_type: 2,
+                param_len: 6,
+                param_value: ParamValue::Capability(Capability {
+                    ty: BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY,
+                    value: 4200000000u32.to_be_bytes().to_vec(),
+                }),
+            }],
+        };
+        encode(BgpMessage::Open(open))
+    }
+
+    fn update_message() -> Vec<u8> {
+        let elem = BgpElem {
+            prefix: NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+            as_path: Some(AsPath::from_sequence([4200000000u32, 64497])),
+            origin: Some(Origin::IGP),
+            next_hop: Some(IpAddr::from_str("192.0.2.1").unwrap()),
+            ..Default::default()
+        };
+        encode(BgpMessage::Update(BgpUpdateMessage::from(&elem)))
+    }
+
+    /// Ethernet frame of a TCP segment from 192.0.2.1:179 to 192.0.2.2:50000.
+    fn packet(seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 12];
+        data.extend([0x08, 0x00]);
+        data.extend([0x45, 0]);
+        data.extend((40 + payload.len() as u16).to_be_bytes());
+        data.extend([0, 0, 0x40, 0, 64, 6, 0, 0]);
+        data.extend([192, 0, 2, 1, 192, 0, 2, 2]);
+        data.extend(179u16.to_be_bytes());
+        data.extend(50000u16.to_be_bytes());
+        data.extend(seq.to_be_bytes());
+        data.extend([0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        data.extend(payload);
+        // Ethernet padding
+        data.extend([0, 0]);
+        data
+    }
+
+    fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = 0xa1b2c3d4u32.to_le_bytes().to_vec();
+        data.extend([2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend(65535u32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        for (index, packet) in packets.iter().enumerate() {
+            data.extend((1700000000 + index as u32).to_le_bytes());
+            data.extend(500u32.to_le_bytes());
+            data.extend((packet.len() as u32).to_le_bytes());
+            data.extend((packet.len() as u32).to_le_bytes());
+            data.extend(packet);
+        }
+        data
+    }
+
+    #[test]
+    fn test_pcap_reader() {
+        let open = open_message();
+        let update = update_message();
+        let seq = 1001 + open.len() as u32;
+        let data = pcap(&[
+            // SYN
+            packet(1000, 0x02, &[]),
+            packet(1001, 0x18, &open),
+            // the update split in two segments, received out of order and retransmitted
+            packet(seq + 10, 0x18, &update[10..]),
+            packet(seq, 0x18, &update[..10]),
+            packet(seq, 0x18, &update[..10]),
+        ]);
+
+        let records: Vec<MrtRecord> = PcapBgpReader::new(Cursor::new(data.clone()))
+            .unwrap()
+            .collect();
+        assert_eq!(records.len(), 2);
+        let message = match &records[1].message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(m)) => m,
+            m => panic!("unexpected message {:?}", m),
+        };
+        assert_eq!(message.peer_ip, IpAddr::from_str("192.0.2.1").unwrap());
+        assert_eq!(message.peer_asn, Asn::new_32bit(4200000000));
+        assert_eq!(records[1].common_header.timestamp, 1700000003);
+        assert_eq!(records[1].common_header.microsecond_timestamp, Some(500));
+
+        let elems: Vec<BgpElem> = PcapBgpReader::new(Cursor::new(data))
+            .unwrap()
+            .into_elem_iter()
+            .collect();
+        assert_eq!(elems.len(), 1);
+        assert_eq!(elems[0].prefix.to_string(), "192.0.2.0/24");
+        assert_eq!(elems[0].peer_asn, Asn::new_32bit(4200000000));
+
+        // pcapng section header block
+        let pcapng = [0x0a, 0x0d, 0x0d, 0x0a].repeat(6);
+        assert!(PcapBgpReader::new(Cursor::new(pcapng)).is_err());
+    }
+
+    #[test]
+    fn test_flow_resync() {
+        let update = update_message();
+        let mut flow = TcpFlow::default();
+        // capture starting in the middle of a message
+        flow.add_segment(5000, &update[20..]);
+        flow.add_segment(5000 + update.len() as u32 - 20, &update);
+        assert_eq!(flow.next_message().unwrap().to_vec(), update);
+        assert!(flow.next_message().is_none());
+    }
+
+    #[test]
+    fn test_stream_reader() {
+        let mut data = open_message();
+        data.extend(update_message());
+        let messages: Vec<BgpMessage> = BgpStreamReader::new(Cursor::new(data))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[0], BgpMessage::Open(_)));
+        assert!(matches!(messages[1], BgpMessage::Update(_)));
+
+        let mut reader = BgpStreamReader::new(Cursor::new(vec![0u8; 19]));
+        assert!(reader.read_message().is_err());
+    }
+}
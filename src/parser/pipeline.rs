@@ -0,0 +1,367 @@
+/*!
+Reusable elem processing pipelines.
+
+A [Pipeline] is an ordered list of stages, such as built-in [Filter]s, de-duplication,
+[Enricher]s adding data from external sources, and custom closures that drop, transform, or enrich
+elems. It is defined once and applied to any source of
+elems, like a [BgpkitParser](crate::BgpkitParser) or a live stream.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, Pipeline};
+
+let pipeline = Pipeline::new()
+    .add_filter("type", "a")
+    .unwrap()
+    .dedup()
+    .enrich(|elem| elem.communities = None);
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+for elem in pipeline.apply(parser) {
+    println!("{}", elem);
+}
+```
+
+Pipelines of built-in stages can also be loaded from a [PipelineConfig], e.g. in YAML:
+
+```yaml
+stages:
+  - type: filter
+    filter_type: peer_asn
+    value: "13335"
+  - type: dedup
+  - type: suppress_duplicates
+  # requires the `rpki` feature
+  - type: enrich
+    enricher: rpki
+    path: vrps.json
+```
+
+The built-in enrichers are:
+- `rpki`: tags announcements with their RPKI origin validation state, using the ROAs of a VRP JSON
+  file, see [RoaTable](crate::rpki::RoaTable)
+*/
+use crate::models::BgpElem;
+use crate::parser::duplicates::DuplicateTracker;
+use crate::parser::filter::{Filter, Filterable};
+use crate::ParserError;
+
+type MapFn = Box<dyn FnMut(BgpElem) -> Option<BgpElem> + Send>;
+
+enum Stage {
+    Filter(Filter),
+    Dedup(Option<Box<BgpElem>>),
+    SuppressDuplicates(DuplicateTracker),
+    Map(MapFn),
+}
+
+impl Stage {
+    fn process(&mut self, elem: BgpElem) -> Option<BgpElem> {
+        match self {
+            Stage::Filter(filter) => elem.match_filter(filter).then_some(elem),
+            Stage::Dedup(last) => {
+                if last.as_deref() == Some(&elem) {
+                    return None;
+                }
+                *last = Some(Box::new(elem.clone()));
+                Some(elem)
+            }
+            Stage::SuppressDuplicates(tracker) => (!tracker.observe(&elem)).then_some(elem),
+            Stage::Map(f) => f(elem),
+        }
+    }
+}
+
+/// Ordered stages applied to each elem.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a pipeline from its configuration.
+    pub fn from_config(config: &PipelineConfig) -> Result<Self, ParserError> {
+        let mut pipeline = Pipeline::new();
+        for stage in &config.stages {
+            pipeline = match stage {
+                StageConfig::Filter { filter_type, value } => {
+                    pipeline.add_filter(filter_type, value)?
+                }
+                StageConfig::Dedup => pipeline.dedup(),
+                StageConfig::SuppressDuplicates => pipeline.suppress_duplicates(),
+                #[cfg(feature = "rpki")]
+                StageConfig::Enrich(EnricherConfig::Rpki { path }) => {
+                    let file = std::fs::File::open(path)?;
+                    let roas = crate::rpki::RoaTable::from_json(std::io::BufReader::new(file))?;
+                    pipeline.enricher(roas)
+                }
+            };
+        }
+        Ok(pipeline)
+    }
+
+    /// Add a stage keeping only elems matching the filter.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.stages.push(Stage::Filter(filter));
+        self
+    }
+
+    /// Add a filter stage by filter type and value, as accepted by [Filter::new].
+    pub fn add_filter(self, filter_type: &str, value: &str) -> Result<Self, ParserError> {
+        Ok(self.filter(Filter::new(filter_type, value)?))
+    }
+
+    /// Add a stage dropping elems equal to the elem before them.
+    pub fn dedup(mut self) -> Self {
+        self.stages.push(Stage::Dedup(None));
+        self
+    }
+
+    /// Add a stage dropping announcements identical to the last announcement of the same peer for
+    /// the same prefix, see [duplicates](crate::parser::duplicates).
+    pub fn suppress_duplicates(mut self) -> Self {
+        self.stages
+            .push(Stage::SuppressDuplicates(DuplicateTracker::new()));
+        self
+    }
+
+    /// Add a stage keeping only elems for which the predicate returns true.
+    pub fn filter_fn<F>(self, mut predicate: F) -> Self
+    where
+        F: FnMut(&BgpElem) -> bool + Send + 'static,
+    {
+        self.filter_map(move |elem| predicate(&elem).then_some(elem))
+    }
+
+    /// Add a stage transforming elems.
+    pub fn map<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(BgpElem) -> BgpElem + Send + 'static,
+    {
+        self.filter_map(move |elem| Some(f(elem)))
+    }
+
+    /// Add a stage modifying elems in place, e.g. to fill in data from an external source.
+    pub fn enrich<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(&mut BgpElem) + Send + 'static,
+    {
+        self.map(move |mut elem| {
+            f(&mut elem);
+            elem
+        })
+    }
+
+    /// Add a stage adding data from an [Enricher] to elems.
+    pub fn enricher<E: Enricher + 'static>(self, mut enricher: E) -> Self {
+        self.enrich(move |elem| enricher.enrich(elem))
+    }
+
+    /// Add a stage transforming elems, dropping those for which `f` returns `None`.
+    pub fn filter_map<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(BgpElem) -> Option<BgpElem> + Send + 'static,
+    {
+        self.stages.push(Stage::Map(Box::new(f)));
+        self
+    }
+
+    /// Number of stages.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Run an elem through all stages. Returns `None` if a stage dropped it.
+    pub fn process(&mut self, elem: BgpElem) -> Option<BgpElem> {
+        self.stages
+            .iter_mut()
+            .try_fold(elem, |elem, stage| stage.process(elem))
+    }
+
+    /// Apply the pipeline to a source of elems.
+    pub fn apply<I: IntoIterator<Item = BgpElem>>(self, elems: I) -> PipelineIter<I::IntoIter> {
+        PipelineIter {
+            pipeline: self,
+            elems: elems.into_iter(),
+        }
+    }
+}
+
+/// Source of data added to elems by a [Pipeline] stage, e.g. an RPKI
+/// [RoaTable](crate::rpki::RoaTable).
+pub trait Enricher: Send {
+    /// Add data to the elem in place.
+    fn enrich(&mut self, elem: &mut BgpElem);
+}
+
+/// Iterator over the elems passing a [Pipeline].
+pub struct PipelineIter<I> {
+    pipeline: Pipeline,
+    elems: I,
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for PipelineIter<I> {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        for elem in self.elems.by_ref() {
+            if let Some(elem) = self.pipeline.process(elem) {
+                return Some(elem);
+            }
+        }
+        None
+    }
+}
+
+/// Serializable configuration of a [Pipeline] of built-in stages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipelineConfig {
+    pub stages: Vec<StageConfig>,
+}
+
+/// Configuration of a built-in pipeline stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum StageConfig {
+    /// filter stage, see [Filter::new]
+    Filter { filter_type: String, value: String },
+    /// drop elems equal to the elem before them
+    Dedup,
+    /// drop duplicate announcements of the same peer for the same prefix
+    SuppressDuplicates,
+    /// built-in enricher
+    #[cfg(feature = "rpki")]
+    Enrich(EnricherConfig),
+}
+
+/// Configuration of a built-in [Enricher].
+#[cfg(feature = "rpki")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "enricher", rename_all = "snake_case"))]
+pub enum EnricherConfig {
+    /// RPKI origin validation state from the ROAs of a VRP JSON file, see
+    /// [RoaTable::from_json](crate::rpki::RoaTable::from_json)
+    Rpki { path: std::path::PathBuf },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Asn;
+    use crate::parser::test_elems::{announce, withdraw};
+
+    #[test]
+    fn test_pipeline() {
+        let elems = vec![
+            announce("192.0.2.1", "10.0.0.0/8", &[1]).with_peer_asn(1),
+            announce("192.0.2.1", "10.0.0.0/8", &[1]).with_peer_asn(1),
+            announce("192.0.2.1", "10.0.0.0/8", &[2]).with_peer_asn(2),
+            withdraw("192.0.2.1", "10.0.0.0/8").with_peer_asn(3),
+            announce("192.0.2.1", "10.0.0.0/8", &[4]).with_peer_asn(4),
+        ];
+        let pipeline = Pipeline::new()
+            .add_filter("type", "a")
+            .unwrap()
+            .dedup()
+            .filter_fn(|e| e.peer_asn != Asn::new_32bit(4))
+            .enrich(|e| e.local_pref = Some(100));
+        assert_eq!(pipeline.len(), 4);
+
+        let result: Vec<_> = pipeline.apply(elems).collect();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].peer_asn, Asn::new_32bit(1));
+        assert_eq!(result[1].peer_asn, Asn::new_32bit(2));
+        assert!(result.iter().all(|e| e.local_pref == Some(100)));
+    }
+
+    #[test]
+    fn test_pipeline_from_config() {
+        let config = PipelineConfig {
+            stages: vec![
+                StageConfig::Filter {
+                    filter_type: "peer_asn".to_string(),
+                    value: "2".to_string(),
+                },
+                StageConfig::Dedup,
+            ],
+        };
+        let mut pipeline = Pipeline::from_config(&config).unwrap();
+        assert!(pipeline
+            .process(announce("192.0.2.1", "10.0.0.0/8", &[1]).with_peer_asn(1))
+            .is_none());
+        assert!(pipeline
+            .process(announce("192.0.2.1", "10.0.0.0/8", &[2]).with_peer_asn(2))
+            .is_some());
+        assert!(pipeline
+            .process(announce("192.0.2.1", "10.0.0.0/8", &[2]).with_peer_asn(2))
+            .is_none());
+
+        let config = PipelineConfig {
+            stages: vec![StageConfig::Filter {
+                filter_type: "peer_asn".to_string(),
+                value: "x".to_string(),
+            }],
+        };
+        assert!(Pipeline::from_config(&config).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_pipeline_config_serde() {
+        let json = r#"{"stages": [{"type": "filter", "filter_type": "type", "value": "w"}, {"type": "dedup"}]}"#;
+        let config: PipelineConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.stages.len(), 2);
+        assert_eq!(config.stages[1], StageConfig::Dedup);
+    }
+
+    #[test]
+    #[cfg(feature = "rpki")]
+    fn test_pipeline_rpki_enricher() {
+        use std::io::Write;
+
+        let mut vrps = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            vrps,
+            r#"{{"roas": [{{"asn": "AS2", "prefix": "192.0.2.0/24", "maxLength": 24}}]}}"#
+        )
+        .unwrap();
+        let json = format!(
+            r#"{{"stages": [{{"type": "enrich", "enricher": "rpki", "path": {:?}}}]}}"#,
+            vrps.path()
+        );
+        let config: PipelineConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            config.stages[0],
+            StageConfig::Enrich(EnricherConfig::Rpki {
+                path: vrps.path().to_path_buf()
+            })
+        );
+
+        let mut pipeline = Pipeline::from_config(&config).unwrap();
+        let announcement = announce("192.0.2.1", "192.0.2.0/24", &[1, 2]).with_peer_asn(1);
+        let enriched = pipeline.process(announcement).unwrap();
+        assert_eq!(
+            enriched.communities,
+            Some(vec![crate::models::MetaCommunity::Extended(
+                crate::rpki::RpkiValidation::Valid.to_community()
+            )])
+        );
+
+        let config = PipelineConfig {
+            stages: vec![StageConfig::Enrich(EnricherConfig::Rpki {
+                path: "missing-vrps.json".into(),
+            })],
+        };
+        assert!(Pipeline::from_config(&config).is_err());
+    }
+}
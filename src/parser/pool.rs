@@ -0,0 +1,338 @@
+/*!
+Parsing many MRT files concurrently.
+
+[BgpkitParserPool] parses a list of local or remote files on a fixed number of threads, and
+returns the elems of all files through a single iterator, each with the file it comes from. The
+elems of a file are returned in order, while the elems of different files are interleaved. Files
+failing to open or to parse do not stop the pool: their errors are reported in the
+[FileSummary] of each file.
+
+```no_run
+use bgpkit_parser::BgpkitParserPool;
+
+let files = [
+    "http://archive.routeviews.org/bgpdata/2021.10/UPDATES/updates.20211001.0000.bz2",
+    "http://archive.routeviews.org/route-views.ny/bgpdata/2021.10/UPDATES/updates.20211001.0000.bz2",
+];
+let mut iter = BgpkitParserPool::new(files)
+    .with_threads(2)
+    .add_filter("peer_asn", "13335")
+    .unwrap()
+    .into_elem_iter();
+for (file, elem) in iter.by_ref() {
+    println!("{}: {}", file, elem);
+}
+for summary in iter.file_summaries() {
+    println!("{}: {} elems, {} errors", summary.file, summary.elems, summary.errors.total());
+}
+```
+*/
+use crate::models::BgpElem;
+use crate::parser::iters::ErrorSummary;
+use crate::parser::{BgpkitParser, Filter, ParserErrorWithBytes};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+
+/// Number of elems of a file handed over together from a worker thread.
+const BATCH_SIZE: usize = 1000;
+
+/// Outcome of parsing one file of a [BgpkitParserPool].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+    /// the file, as given to the pool
+    pub file: String,
+    /// number of elems returned from the file
+    pub elems: u64,
+    /// errors encountered parsing the file; failing to open it counts as an IO error
+    pub errors: ErrorSummary,
+}
+
+/// Parser of many MRT files on a pool of threads, see [pool](crate::parser::pool).
+#[derive(Debug, Clone)]
+pub struct BgpkitParserPool {
+    files: Vec<String>,
+    threads: usize,
+    filters: Vec<Filter>,
+    show_warnings: bool,
+}
+
+impl BgpkitParserPool {
+    /// Create a pool for the given files, parsed on as many threads as there are CPUs.
+    pub fn new<I, S>(files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        BgpkitParserPool {
+            files: files.into_iter().map(|f| f.into()).collect(),
+            threads: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            filters: vec![],
+            show_warnings: true,
+        }
+    }
+
+    /// Set the number of files parsed at the same time.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Add a filter applied to the elems of every file, see [BgpkitParser::add_filter].
+    pub fn add_filter(
+        mut self,
+        filter_type: &str,
+        filter_value: &str,
+    ) -> Result<Self, ParserErrorWithBytes> {
+        self.filters.push(Filter::new(filter_type, filter_value)?);
+        Ok(self)
+    }
+
+    /// Do not log warnings of the parsers, see [BgpkitParser::disable_warnings].
+    pub fn disable_warnings(mut self) -> Self {
+        self.show_warnings = false;
+        self
+    }
+
+    /// Parse the files on the threads of the pool, returning their elems through a single
+    /// iterator.
+    ///
+    /// The threads stop once the iterator is dropped.
+    pub fn into_elem_iter(self) -> PoolElemIterator {
+        let (sender, receiver) = sync_channel(4 * self.threads);
+        let pool = Arc::new(self);
+        let next_file = Arc::new(AtomicUsize::new(0));
+        for _ in 0..pool.threads.min(pool.files.len()) {
+            let pool = pool.clone();
+            let next_file = next_file.clone();
+            let sender: SyncSender<PoolMessage> = sender.clone();
+            std::thread::spawn(move || {
+                pool.run_worker(&next_file, |message| sender.send(message).is_ok())
+            });
+        }
+        PoolElemIterator {
+            receiver,
+            file: Arc::from(""),
+            batch: vec![].into_iter(),
+            summaries: vec![],
+        }
+    }
+
+    /// Parse the files on the threads of the pool, calling `f` with every elem and the file it
+    /// comes from on the thread parsing the file. Returns the summaries of all files, in the order
+    /// the files were given.
+    pub fn for_each<F>(self, f: F) -> Vec<FileSummary>
+    where
+        F: Fn(&str, BgpElem) + Sync,
+    {
+        let next_file = AtomicUsize::new(0);
+        let mut summaries: Vec<FileSummary> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads.min(self.files.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut summaries = vec![];
+                        self.run_worker(&next_file, |message| {
+                            match message {
+                                PoolMessage::Elems(file, elems) => {
+                                    elems.into_iter().for_each(|elem| f(file.as_ref(), elem))
+                                }
+                                PoolMessage::Done(summary) => summaries.push(summary),
+                            }
+                            true
+                        });
+                        summaries
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        summaries.sort_by_key(|s| self.files.iter().position(|file| file == &s.file));
+        summaries
+    }
+
+    /// Parse files until all are taken, passing their elems and summaries to `emit` until it
+    /// returns false.
+    fn run_worker<E>(&self, next_file: &AtomicUsize, mut emit: E)
+    where
+        E: FnMut(PoolMessage) -> bool,
+    {
+        loop {
+            let file = match self.files.get(next_file.fetch_add(1, Ordering::Relaxed)) {
+                Some(file) => file,
+                None => return,
+            };
+            let summary = match self.parse_file(file, &mut emit) {
+                Some(summary) => summary,
+                None => return,
+            };
+            if !emit(PoolMessage::Done(summary)) {
+                return;
+            }
+        }
+    }
+
+    /// Parse one file, returning its summary, or None if `emit` asked to stop.
+    fn parse_file<E>(&self, file: &str, emit: &mut E) -> Option<FileSummary>
+    where
+        E: FnMut(PoolMessage) -> bool,
+    {
+        let mut summary = FileSummary {
+            file: file.to_string(),
+            elems: 0,
+            errors: ErrorSummary::default(),
+        };
+        let mut parser = match BgpkitParser::new(file) {
+            Ok(parser) => parser,
+            Err(e) => {
+                summary.errors.io_errors += 1;
+                summary.errors.last_error = Some(e.to_string());
+                return Some(summary);
+            }
+        };
+        parser.filters = self.filters.clone();
+        if !self.show_warnings {
+            parser = parser.disable_warnings();
+        }
+
+        let file: Arc<str> = Arc::from(file);
+        let mut iter = parser.into_elem_iter();
+        loop {
+            let batch = iter.next_batch(BATCH_SIZE);
+            if batch.is_empty() {
+                break;
+            }
+            summary.elems += batch.len() as u64;
+            if !emit(PoolMessage::Elems(file.clone(), batch)) {
+                return None;
+            }
+        }
+        summary.errors = iter.error_summary().clone();
+        Some(summary)
+    }
+}
+
+enum PoolMessage {
+    Elems(Arc<str>, Vec<BgpElem>),
+    Done(FileSummary),
+}
+
+/// Iterator over the elems of the files of a [BgpkitParserPool], with the file of each elem.
+pub struct PoolElemIterator {
+    receiver: Receiver<PoolMessage>,
+    file: Arc<str>,
+    batch: std::vec::IntoIter<BgpElem>,
+    summaries: Vec<FileSummary>,
+}
+
+impl PoolElemIterator {
+    /// Returns the summaries of the files parsed completely so far, in the order they were
+    /// completed.
+    ///
+    /// Call this after the iterator is exhausted to check the errors of all files.
+    pub fn file_summaries(&self) -> &[FileSummary] {
+        &self.summaries
+    }
+}
+
+impl Iterator for PoolElemIterator {
+    type Item = (Arc<str>, BgpElem);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(elem) = self.batch.next() {
+                return Some((self.file.clone(), elem));
+            }
+            match self.receiver.recv() {
+                Ok(PoolMessage::Elems(file, elems)) => {
+                    self.file = file;
+                    self.batch = elems.into_iter();
+                }
+                Ok(PoolMessage::Done(summary)) => self.summaries.push(summary),
+                // all workers are done
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Write the MRT test vectors to files, returning their paths and expected elems.
+    fn write_vectors(dir: &std::path::Path) -> Vec<(String, Vec<BgpElem>)> {
+        test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+            .map(|vector| {
+                let path = dir.join(format!("{}.mrt", vector.name));
+                std::fs::write(&path, &vector.bytes).unwrap();
+                (path.to_str().unwrap().to_string(), vector.expected)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pool_elem_iter() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors = write_vectors(dir.path());
+        let missing = dir.path().join("missing.mrt").to_str().unwrap().to_string();
+        let files = vectors
+            .iter()
+            .map(|(file, _)| file.clone())
+            .chain([missing.clone()]);
+
+        let mut iter = BgpkitParserPool::new(files)
+            .with_threads(3)
+            .into_elem_iter();
+        let mut elems: HashMap<String, Vec<BgpElem>> = HashMap::new();
+        for (file, elem) in iter.by_ref() {
+            elems.entry(file.to_string()).or_default().push(elem);
+        }
+        for (file, expected) in &vectors {
+            assert_eq!(elems.get(file), Some(expected), "{}", file);
+        }
+
+        let summaries = iter.file_summaries();
+        assert_eq!(summaries.len(), vectors.len() + 1);
+        let missing = summaries.iter().find(|s| s.file == missing).unwrap();
+        assert_eq!(missing.errors.io_errors, 1);
+        assert_eq!(missing.elems, 0);
+    }
+
+    #[test]
+    fn test_pool_for_each() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors = write_vectors(dir.path());
+        let files: Vec<String> = vectors.iter().map(|(file, _)| file.clone()).collect();
+
+        let elems = Mutex::new(vec![]);
+        let summaries = BgpkitParserPool::new(files.clone())
+            .with_threads(2)
+            .add_filter("prefix", "192.0.2.0/24")
+            .unwrap()
+            .for_each(|_file, elem| elems.lock().unwrap().push(elem));
+        assert_eq!(
+            summaries.iter().map(|s| s.file.clone()).collect::<Vec<_>>(),
+            files
+        );
+        let expected: usize = vectors
+            .iter()
+            .flat_map(|(_, elems)| elems)
+            .filter(|e| e.prefix.prefix == "192.0.2.0/24".parse().unwrap())
+            .count();
+        assert_eq!(elems.into_inner().unwrap().len(), expected);
+        assert_eq!(
+            summaries.iter().map(|s| s.elems).sum::<u64>(),
+            expected as u64
+        );
+    }
+}
@@ -0,0 +1,186 @@
+/*!
+Reading the input ahead of the parser on a background thread.
+
+The reader of a parser downloads and decompresses the input on demand, so for remote files the
+network, the decompression and the parsing take turns on the same thread. With
+[BgpkitParser::with_prefetch], a dedicated thread reads the decompressed input ahead of the parser
+into a bounded queue of chunks, so that downloading and decompressing overlap with parsing.
+
+```no_run
+use bgpkit_parser::BgpkitParser;
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/rib-example.bz2")
+    .unwrap()
+    .with_prefetch();
+for elem in parser {
+    println!("{}", elem);
+}
+```
+*/
+use crate::parser::BgpkitParser;
+use std::io::{ErrorKind, Read};
+use std::sync::mpsc::{sync_channel, Receiver};
+
+/// Size of the chunks read by the prefetch thread of [BgpkitParser::with_prefetch].
+pub const DEFAULT_PREFETCH_CHUNK_SIZE: usize = 1024 * 1024;
+/// Number of chunks read ahead by the prefetch thread of [BgpkitParser::with_prefetch].
+pub const DEFAULT_PREFETCH_CHUNKS: usize = 8;
+
+impl<R: Read + Send + 'static> BgpkitParser<R> {
+    /// Read the input ahead of the parser on a background thread, see
+    /// [prefetch](crate::parser::prefetch).
+    ///
+    /// Up to [DEFAULT_PREFETCH_CHUNKS] chunks of [DEFAULT_PREFETCH_CHUNK_SIZE] bytes are read ahead.
+    pub fn with_prefetch(self) -> BgpkitParser<PrefetchReader> {
+        self.with_prefetch_capacity(DEFAULT_PREFETCH_CHUNK_SIZE, DEFAULT_PREFETCH_CHUNKS)
+    }
+
+    /// Read the input ahead of the parser on a background thread, with up to `chunks` chunks of
+    /// `chunk_size` bytes read ahead.
+    pub fn with_prefetch_capacity(
+        self,
+        chunk_size: usize,
+        chunks: usize,
+    ) -> BgpkitParser<PrefetchReader> {
+        BgpkitParser {
+            reader: PrefetchReader::with_capacity(self.reader, chunk_size, chunks),
+            core_dump: self.core_dump,
+            filters: self.filters,
+            options: self.options,
+        }
+    }
+}
+
+/// Reader returning the data read ahead from another reader by a background thread.
+///
+/// The thread stops at the end of the input, after an error, or once the reader is dropped.
+pub struct PrefetchReader {
+    chunks: Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl PrefetchReader {
+    /// Start reading ahead from the reader on a new thread, with up to `chunks` chunks of
+    /// `chunk_size` bytes read ahead.
+    pub fn with_capacity<R: Read + Send + 'static>(
+        mut reader: R,
+        chunk_size: usize,
+        chunks: usize,
+    ) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let (sender, receiver) = sync_channel(chunks.max(1));
+        std::thread::spawn(move || loop {
+            let mut chunk = vec![0; chunk_size];
+            let mut filled = 0;
+            // fill the whole chunk, or stop at the end of the input or at an error
+            let result = loop {
+                match reader.read(&mut chunk[filled..]) {
+                    Ok(0) => break Ok(()),
+                    Ok(n) => {
+                        filled += n;
+                        if filled == chunk_size {
+                            break Ok(());
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => break Err(e),
+                }
+            };
+            let end = filled < chunk_size;
+            if filled > 0 {
+                chunk.truncate(filled);
+                if sender.send(Ok(chunk)).is_err() {
+                    // the reader has been dropped
+                    return;
+                }
+            }
+            if let Err(e) = result {
+                let _ = sender.send(Err(e));
+                return;
+            }
+            if end {
+                return;
+            }
+        });
+        PrefetchReader {
+            chunks: receiver,
+            chunk: vec![],
+            position: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for PrefetchReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                Err(_) => {
+                    // the thread stopped at the end of the input
+                    self.done = true;
+                    return Ok(0);
+                }
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BgpElem;
+    use crate::test_vectors;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_prefetch() {
+        for vector in test_vectors::mrt_updates()
+            .into_iter()
+            .chain(test_vectors::mrt_ribs())
+        {
+            for chunk_size in [1, 7, DEFAULT_PREFETCH_CHUNK_SIZE] {
+                let elems: Vec<BgpElem> =
+                    BgpkitParser::from_reader(Cursor::new(vector.bytes.clone()))
+                        .with_prefetch_capacity(chunk_size, 2)
+                        .into_elem_iter()
+                        .collect();
+                assert_eq!(elems, vector.expected, "{} {}", vector.name, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefetch_error() {
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("connection reset"))
+            }
+        }
+
+        let mut reader =
+            PrefetchReader::with_capacity(Cursor::new(vec![1, 2, 3]).chain(FailingReader), 2, 1);
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert!(reader.read(&mut buf).is_err());
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+}
@@ -0,0 +1,699 @@
+/*!
+Replay of routes to another BGP speaker over a live BGP session.
+
+[BgpReplay] connects to a router or collector, establishes a BGP session with it (OPEN with
+negotiated capabilities, then KEEPALIVEs to keep the session up), and sends [BgpElem]s as UPDATE
+messages, either as fast as possible or paced by the elems' timestamps in real or accelerated
+time. This is useful to feed archived routes into a test router, or into another collector.
+
+```no_run
+use bgpkit_parser::models::Asn;
+use bgpkit_parser::replay::BgpReplay;
+use bgpkit_parser::BgpkitParser;
+
+let parser = BgpkitParser::new("updates.20240101.0000.bz2").unwrap()
+    .add_filter("peer_ip", "192.0.2.1").unwrap();
+
+let mut replay = BgpReplay::connect("198.51.100.1:179", Asn::new_32bit(64496), "192.0.2.254".parse().unwrap())
+    .unwrap()
+    .with_speed(10.0);
+replay.establish().unwrap();
+let sent = replay.replay(parser).unwrap();
+replay.close().unwrap();
+println!("replayed {} routes", sent);
+```
+
+Each elem is sent as its own UPDATE message, with its prefix in the MP_REACH_NLRI or
+MP_UNREACH_NLRI attribute, and with its attributes unchanged. The remote speaker therefore
+usually needs to be configured to accept them, e.g. to not enforce the replayer's AS as the first
+AS of the AS path, and to accept the original next hops. Replaying the elems of several peers into
+one session makes their routes overwrite each other, so the elems are typically filtered to a
+single peer first.
+
+UPDATE messages received from the remote speaker are discarded.
+*/
+use crate::models::capabilities::BgpCapabilityType;
+use crate::models::*;
+use crate::parser::bgp::parse_bgp_message;
+use bytes::{Bytes, BytesMut};
+use log::{debug, info};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Time to wait for the OPEN message of the remote speaker, as the hold timer of the OpenSent
+/// state suggested by [RFC4271](https://datatracker.ietf.org/doc/html/rfc4271#section-8).
+const OPEN_TIMEOUT: Duration = Duration::from_secs(240);
+
+/// Interval at which received messages are checked while sending routes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Parameters of an established session, negotiated with the remote speaker's OPEN message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedSession {
+    pub remote_asn: Asn,
+    pub remote_id: BgpIdentifier,
+    /// the smaller of the two hold times, `0` if KEEPALIVEs are not used
+    pub hold_time: u16,
+    /// `Bits32` if both speakers support 4-octet AS numbers
+    pub asn_len: AsnLength,
+    /// whether path IDs are sent, see [BgpReplay::with_add_path]
+    pub add_path: bool,
+}
+
+/// BGP session replaying routes to a remote speaker.
+pub struct BgpReplay {
+    stream: TcpStream,
+    local_asn: Asn,
+    bgp_id: BgpIdentifier,
+    remote_asn: Option<Asn>,
+    hold_time: u16,
+    families: Vec<(Afi, Safi)>,
+    add_path: bool,
+    speed: Option<f64>,
+    session: Option<NegotiatedSession>,
+    /// received bytes not forming a complete message yet
+    buffer: BytesMut,
+    last_sent: Instant,
+    last_received: Instant,
+    last_polled: Instant,
+}
+
+impl BgpReplay {
+    /// Connect to a remote speaker, e.g. `198.51.100.1:179`. The session is established with
+    /// [BgpReplay::establish].
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        local_asn: Asn,
+        bgp_id: BgpIdentifier,
+    ) -> std::io::Result<Self> {
+        Ok(Self::from_stream(
+            TcpStream::connect(addr)?,
+            local_asn,
+            bgp_id,
+        ))
+    }
+
+    /// Replay over an already connected stream, e.g. one accepted from a speaker connecting to
+    /// the replayer.
+    pub fn from_stream(stream: TcpStream, local_asn: Asn, bgp_id: BgpIdentifier) -> Self {
+        let now = Instant::now();
+        BgpReplay {
+            stream,
+            local_asn,
+            bgp_id,
+            remote_asn: None,
+            hold_time: 180,
+            families: vec![(Afi::Ipv4, Safi::Unicast), (Afi::Ipv6, Safi::Unicast)],
+            add_path: false,
+            speed: None,
+            session: None,
+            buffer: BytesMut::new(),
+            last_sent: now,
+            last_received: now,
+            last_polled: now,
+        }
+    }
+
+    /// Only establish the session with a speaker of the given AS.
+    pub fn with_remote_asn(mut self, remote_asn: Asn) -> Self {
+        self.remote_asn = Some(remote_asn);
+        self
+    }
+
+    /// Hold time proposed in the OPEN message, 180 seconds by default. KEEPALIVEs are sent every
+    /// third of the negotiated hold time, and not at all if it is `0`.
+    pub fn with_hold_time(mut self, hold_time: u16) -> Self {
+        self.hold_time = hold_time;
+        self
+    }
+
+    /// Address families advertised with the multiprotocol capability, IPv4 and IPv6 unicast by
+    /// default.
+    pub fn with_families(mut self, families: &[(Afi, Safi)]) -> Self {
+        self.families = families.to_vec();
+        self
+    }
+
+    /// Advertise sending multiple paths per prefix ([RFC7911]) for all families, so that the
+    /// path IDs of the elems are kept. Path IDs are only sent if the remote speaker can receive
+    /// multiple paths for every family.
+    ///
+    /// [RFC7911]: https://datatracker.ietf.org/doc/html/rfc7911
+    pub fn with_add_path(mut self) -> Self {
+        self.add_path = true;
+        self
+    }
+
+    /// Pace the routes by their timestamps, with `speed` the replay speed relative to the
+    /// original time, e.g. `1.0` for real time or `60.0` to replay an hour in a minute. By
+    /// default, routes are sent as fast as possible.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// The negotiated parameters, once the session is established.
+    pub fn session(&self) -> Option<&NegotiatedSession> {
+        self.session.as_ref()
+    }
+
+    /// The OPEN message sent to the remote speaker.
+    pub fn open_message(&self) -> BgpOpenMessage {
+        let mut capabilities: Vec<Capability> = self
+            .families
+            .iter()
+            .map(|(afi, safi)| {
+                let mut value = (*afi as u16).to_be_bytes().to_vec();
+                value.extend([0, *safi as u8]);
+                Capability {
+                    ty: BgpCapabilityType::MULTIPROTOCOL_EXTENSIONS_FOR_BGP_4,
+                    value,
+                }
+            })
+            .collect();
+        capabilities.push(Capability {
+            ty: BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY,
+            value: self.local_asn.to_u32().to_be_bytes().to_vec(),
+        });
+        if self.add_path {
+            let mut value = vec![];
+            for (afi, safi) in &self.families {
+                value.extend((*afi as u16).to_be_bytes());
+                // send
+                value.extend([*safi as u8, 2]);
+            }
+            capabilities.push(Capability {
+                ty: BgpCapabilityType::ADD_PATH_CAPABILITY,
+                value,
+            });
+        }
+
+        BgpOpenMessage {
+            version: 4,
+            asn: match u16::try_from(self.local_asn.to_u32()) {
+                Ok(asn) => Asn::new_16bit(asn),
+                Err(_) => Asn::TRANSITION,
+            },
+            hold_time: self.hold_time,
+            sender_ip: self.bgp_id,
+            extended_length: false,
+            opt_params: capabilities
+                .into_iter()
+                .map(|cap| OptParam {
+                    param_type: 2,
+                    param_len: cap.value.len() as u16 + 2,
+                    param_value: ParamValue::Capability(cap),
+                })
+                .collect(),
+        }
+    }
+
+    /// Exchange OPEN and KEEPALIVE messages with the remote speaker.
+    ///
+    /// A NOTIFICATION received from the remote speaker, or an OPEN message that is not
+    /// acceptable, fails the session with an error.
+    pub fn establish(&mut self) -> std::io::Result<&NegotiatedSession> {
+        self.send(&BgpMessage::Open(self.open_message()))?;
+
+        let open = match self.wait_for_message(OPEN_TIMEOUT)? {
+            BgpMessage::Open(open) => open,
+            msg => return Err(unexpected_message(&msg, "OPEN")),
+        };
+        let session = match self.negotiate(&open) {
+            Ok(session) => session,
+            Err((code, subcode, reason)) => {
+                self.send_notification(code, subcode)?;
+                return Err(std::io::Error::new(ErrorKind::InvalidData, reason));
+            }
+        };
+        self.session = Some(session);
+        self.send(&BgpMessage::KeepAlive)?;
+
+        let timeout = match self.hold_time {
+            0 => OPEN_TIMEOUT,
+            hold_time => Duration::from_secs(hold_time as u64),
+        };
+        match self.wait_for_message(timeout)? {
+            BgpMessage::KeepAlive => {}
+            msg => return Err(unexpected_message(&msg, "KEEPALIVE")),
+        }
+
+        let session = self.session.as_ref().unwrap();
+        info!(
+            "BGP session with AS{} ({}) established",
+            session.remote_asn, session.remote_id
+        );
+        Ok(session)
+    }
+
+    /// Negotiate the session parameters, or return the NOTIFICATION error code and subcode for an
+    /// unacceptable OPEN message.
+    fn negotiate(&self, open: &BgpOpenMessage) -> Result<NegotiatedSession, (u8, u8, String)> {
+        if open.version != 4 {
+            let reason = format!("unsupported BGP version {}", open.version);
+            return Err((2, 1, reason));
+        }
+        if matches!(open.hold_time, 1 | 2) {
+            let reason = format!("unacceptable hold time {}", open.hold_time);
+            return Err((2, 6, reason));
+        }
+
+        let capabilities: Vec<&Capability> = open
+            .opt_params
+            .iter()
+            .filter_map(|param| match &param.param_value {
+                ParamValue::Capability(cap) => Some(cap),
+                ParamValue::Raw(_) => None,
+            })
+            .collect();
+        let find = |ty: BgpCapabilityType| capabilities.iter().find(|cap| cap.ty == ty);
+
+        let (remote_asn, asn_len) =
+            match find(BgpCapabilityType::SUPPORT_FOR_4_OCTET_AS_NUMBER_CAPABILITY) {
+                Some(cap) if cap.value.len() == 4 => {
+                    let asn = u32::from_be_bytes([
+                        cap.value[0],
+                        cap.value[1],
+                        cap.value[2],
+                        cap.value[3],
+                    ]);
+                    (Asn::new_32bit(asn), AsnLength::Bits32)
+                }
+                _ => (open.asn, AsnLength::Bits16),
+            };
+        if let Some(expected) = self.remote_asn {
+            if expected != remote_asn {
+                let reason = format!("bad peer AS {}, expected {}", remote_asn, expected);
+                return Err((2, 2, reason));
+            }
+        }
+
+        // the remote speaker must be able to receive multiple paths for every family
+        let add_path = self.add_path
+            && match find(BgpCapabilityType::ADD_PATH_CAPABILITY) {
+                Some(cap) => self.families.iter().all(|(afi, safi)| {
+                    cap.value.chunks_exact(4).any(|entry| {
+                        u16::from_be_bytes([entry[0], entry[1]]) == *afi as u16
+                            && entry[2] == *safi as u8
+                            && entry[3] & 1 != 0
+                    })
+                }),
+                None => false,
+            };
+
+        Ok(NegotiatedSession {
+            remote_asn,
+            remote_id: open.sender_ip,
+            hold_time: self.hold_time.min(open.hold_time),
+            asn_len,
+            add_path,
+        })
+    }
+
+    /// Send an elem as an UPDATE message.
+    pub fn send_elem(&mut self, elem: &BgpElem) -> std::io::Result<()> {
+        self.check_established()?;
+        if self.last_polled.elapsed() >= POLL_INTERVAL {
+            self.maintain()?;
+        }
+        let mut msg = BgpUpdateMessage::from(elem);
+        // the next hop is carried in MP_REACH_NLRI, the NEXT_HOP attribute is only for IPv4
+        // NLRI outside of it (RFC4760)
+        msg.attributes
+            .inner
+            .retain(|attr| !matches!(attr.value, AttributeValue::NextHop(_)));
+        self.send(&BgpMessage::Update(msg))
+    }
+
+    /// Send End-of-RIB markers ([RFC4724]) for all families, e.g. after replaying a RIB dump.
+    ///
+    /// [RFC4724]: https://datatracker.ietf.org/doc/html/rfc4724#section-2
+    pub fn send_end_of_rib(&mut self) -> std::io::Result<()> {
+        self.check_established()?;
+        for (afi, safi) in self.families.clone() {
+            let attributes = match (afi, safi) {
+                (Afi::Ipv4, Safi::Unicast) => Attributes::default(),
+                _ => Attributes::from_iter([AttributeValue::MpUnreachNlri(Nlri {
+                    afi,
+                    safi,
+                    next_hop: None,
+                    prefixes: vec![],
+                })]),
+            };
+            self.send(&BgpMessage::Update(BgpUpdateMessage {
+                withdrawn_prefixes: vec![],
+                attributes,
+                announced_prefixes: vec![],
+            }))?;
+        }
+        Ok(())
+    }
+
+    /// Send the elems, paced by their timestamps if a replay speed is set with
+    /// [BgpReplay::with_speed], and return the number of elems sent.
+    ///
+    /// The session is kept alive while waiting for the time of the next elem.
+    pub fn replay<I>(&mut self, elems: I) -> std::io::Result<u64>
+    where
+        I: IntoIterator<Item = BgpElem>,
+    {
+        let start = Instant::now();
+        let mut first_timestamp = None;
+        let mut count = 0;
+        for elem in elems {
+            if let Some(speed) = self.speed {
+                let first = *first_timestamp.get_or_insert(elem.timestamp);
+                let offset = ((elem.timestamp - first) / speed).max(0.0);
+                self.wait_until(start + Duration::from_secs_f64(offset))?;
+            }
+            self.send_elem(&elem)?;
+            count += 1;
+        }
+        debug!("replayed {} elems", count);
+        Ok(count)
+    }
+
+    /// Keep the session alive until `deadline`.
+    pub fn wait_until(&mut self, deadline: Instant) -> std::io::Result<()> {
+        loop {
+            self.maintain()?;
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            let next_poll = (now + self.keepalive_interval()).min(deadline);
+            self.read_into_buffer(Some(next_poll - now))?;
+        }
+    }
+
+    /// Close the session with a NOTIFICATION (Cease, administrative shutdown).
+    pub fn close(mut self) -> std::io::Result<()> {
+        self.send_notification(6, 2)?;
+        self.stream.flush()?;
+        self.stream.shutdown(std::net::Shutdown::Both)
+    }
+
+    /// Send a KEEPALIVE if due, check the hold timer, and handle the messages received so far.
+    fn maintain(&mut self) -> std::io::Result<()> {
+        self.last_polled = Instant::now();
+        let hold_time = self.session.as_ref().map(|s| s.hold_time).unwrap_or(0);
+        if hold_time > 0 {
+            if self.last_sent.elapsed() >= self.keepalive_interval() {
+                self.send(&BgpMessage::KeepAlive)?;
+            }
+            if self.last_received.elapsed() > Duration::from_secs(hold_time as u64) {
+                self.send_notification(4, 0)?;
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "hold timer of the BGP session expired",
+                ));
+            }
+        }
+
+        self.read_into_buffer(None)?;
+        while let Some(msg) = self.next_buffered_message()? {
+            match msg {
+                BgpMessage::KeepAlive => {}
+                msg => return Err(unexpected_message(&msg, "KEEPALIVE")),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_established(&self) -> std::io::Result<()> {
+        match self.session {
+            Some(_) => Ok(()),
+            None => Err(std::io::Error::new(
+                ErrorKind::NotConnected,
+                "BGP session not established",
+            )),
+        }
+    }
+
+    fn keepalive_interval(&self) -> Duration {
+        match self.session.as_ref().map(|s| s.hold_time).unwrap_or(0) {
+            0 => POLL_INTERVAL,
+            hold_time => Duration::from_secs(hold_time as u64) / 3,
+        }
+    }
+
+    fn send(&mut self, msg: &BgpMessage) -> std::io::Result<()> {
+        let (add_path, asn_len) = match &self.session {
+            Some(s) => (s.add_path, s.asn_len),
+            None => (false, AsnLength::Bits16),
+        };
+        let mut bytes = BytesMut::from(msg.encode(add_path, asn_len));
+        // RFC 4271 section 4.1: the marker must be all ones, peers close the session otherwise
+        bytes[..16].fill(0xff);
+        self.stream.write_all(&bytes)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    fn send_notification(&mut self, code: u8, subcode: u8) -> std::io::Result<()> {
+        self.send(&BgpMessage::Notification(BgpNotificationMessage {
+            error: BgpError::new(code, subcode),
+            data: vec![],
+        }))
+    }
+
+    /// Wait for the next message other than an UPDATE.
+    fn wait_for_message(&mut self, timeout: Duration) -> std::io::Result<BgpMessage> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(msg) = self.next_buffered_message()? {
+                return Ok(msg);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    "timed out waiting for a message from the remote speaker",
+                ));
+            }
+            self.read_into_buffer(Some(deadline - now))?;
+        }
+    }
+
+    /// Read received bytes into the buffer, waiting up to `timeout`, or without waiting if `None`.
+    fn read_into_buffer(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+        let mut data = [0u8; 4096];
+        let result = match timeout {
+            Some(timeout) => {
+                // a zero timeout is rejected by `set_read_timeout`
+                let timeout = timeout.max(Duration::from_millis(1));
+                self.stream.set_read_timeout(Some(timeout))?;
+                self.stream.read(&mut data)
+            }
+            None => {
+                self.stream.set_nonblocking(true)?;
+                let result = self.stream.read(&mut data);
+                self.stream.set_nonblocking(false)?;
+                result
+            }
+        };
+        match result {
+            Ok(0) => Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "BGP session closed by the remote speaker",
+            )),
+            Ok(n) => {
+                self.buffer.extend_from_slice(&data[..n]);
+                self.last_received = Instant::now();
+                Ok(())
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Take the next complete message other than an UPDATE out of the buffer. A received
+    /// NOTIFICATION is returned as an error.
+    fn next_buffered_message(&mut self) -> std::io::Result<Option<BgpMessage>> {
+        loop {
+            if self.buffer.len() < 19 {
+                return Ok(None);
+            }
+            let length = u16::from_be_bytes([self.buffer[16], self.buffer[17]]) as usize;
+            if !(19..=4096).contains(&length) {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid BGP message length {}", length),
+                ));
+            }
+            if self.buffer.len() < length {
+                return Ok(None);
+            }
+            let mut data: Bytes = self.buffer.split_to(length).freeze();
+            if data[18] == BgpMessageType::UPDATE as u8 {
+                continue;
+            }
+            let msg = parse_bgp_message(&mut data, false, &AsnLength::Bits32)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+            return match msg {
+                BgpMessage::Notification(n) => Err(std::io::Error::new(
+                    ErrorKind::ConnectionAborted,
+                    format!(
+                        "NOTIFICATION received from the remote speaker: {:?}",
+                        n.error
+                    ),
+                )),
+                msg => Ok(Some(msg)),
+            };
+        }
+    }
+}
+
+fn unexpected_message(msg: &BgpMessage, expected: &str) -> std::io::Error {
+    let received = match msg {
+        BgpMessage::Open(_) => "OPEN",
+        BgpMessage::Update(_) => "UPDATE",
+        BgpMessage::Notification(_) => "NOTIFICATION",
+        BgpMessage::KeepAlive => "KEEPALIVE",
+    };
+    std::io::Error::new(
+        ErrorKind::InvalidData,
+        format!("expected {} message, received {}", expected, received),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Elementor;
+    use std::net::{IpAddr, TcpListener};
+    use std::str::FromStr;
+
+    /// Read the next message from the replayer, as the remote speaker.
+    fn read_message(stream: &mut TcpStream, add_path: bool) -> BgpMessage {
+        let mut data = vec![0u8; 19];
+        stream.read_exact(&mut data).unwrap();
+        assert_eq!(data[..16], [0xff; 16]);
+        let length = u16::from_be_bytes([data[16], data[17]]) as usize;
+        data.resize(length, 0);
+        stream.read_exact(&mut data[19..]).unwrap();
+        parse_bgp_message(&mut Bytes::from(data), add_path, &AsnLength::Bits32).unwrap()
+    }
+
+    /// Remote speaker of AS64500 accepting one session, returning the elems received.
+    fn remote_speaker(listener: TcpListener) -> std::thread::JoinHandle<Vec<BgpElem>> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let open = match read_message(&mut stream, false) {
+                BgpMessage::Open(open) => open,
+                msg => panic!("unexpected message {:?}", msg),
+            };
+            assert_eq!(open.asn, Asn::TRANSITION);
+
+            let replay = BgpReplay::from_stream(
+                stream.try_clone().unwrap(),
+                Asn::new_32bit(64500),
+                BgpIdentifier::new(1),
+            )
+            .with_hold_time(90)
+            .with_add_path();
+            let mut response = replay.open_message();
+            // receive multiple paths instead of sending them
+            for param in response.opt_params.iter_mut() {
+                if let ParamValue::Capability(cap) = &mut param.param_value {
+                    if cap.ty == BgpCapabilityType::ADD_PATH_CAPABILITY {
+                        cap.value
+                            .iter_mut()
+                            .skip(3)
+                            .step_by(4)
+                            .for_each(|mode| *mode = 1);
+                    }
+                }
+            }
+            stream
+                .write_all(&BgpMessage::Open(response).encode(false, AsnLength::Bits16))
+                .unwrap();
+            stream
+                .write_all(&BgpMessage::KeepAlive.encode(false, AsnLength::Bits16))
+                .unwrap();
+            assert_eq!(read_message(&mut stream, false), BgpMessage::KeepAlive);
+
+            let peer_ip = IpAddr::from_str("192.0.2.254").unwrap();
+            let mut elems = vec![];
+            loop {
+                match read_message(&mut stream, true) {
+                    BgpMessage::Update(msg) => elems.extend(Elementor::bgp_update_to_elems(
+                        msg,
+                        0.0,
+                        &peer_ip,
+                        &Asn::new_32bit(4200000000),
+                    )),
+                    BgpMessage::Notification(_) => return elems,
+                    msg => panic!("unexpected message {:?}", msg),
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_replay() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let remote = remote_speaker(listener);
+
+        let mut replay =
+            BgpReplay::connect(addr, Asn::new_32bit(4200000000), BgpIdentifier::new(2))
+                .unwrap()
+                .with_remote_asn(Asn::new_32bit(64500))
+                .with_add_path()
+                .with_speed(100.0);
+        let session = replay.establish().unwrap().clone();
+        assert_eq!(session.remote_asn, Asn::new_32bit(64500));
+        assert_eq!(session.hold_time, 90);
+        assert_eq!(session.asn_len, AsnLength::Bits32);
+        assert!(session.add_path);
+
+        let elem = |prefix: &str, next_hop: &str, path_id: u32, timestamp: f64| BgpElem {
+            timestamp,
+            prefix: NetworkPrefix::new(prefix.parse().unwrap(), path_id),
+            as_path: Some(AsPath::from_sequence([4200000000u32, 64496])),
+            origin: Some(Origin::IGP),
+            next_hop: Some(IpAddr::from_str(next_hop).unwrap()),
+            ..Default::default()
+        };
+        let elems = vec![
+            elem("192.0.2.0/24", "192.0.2.254", 1, 1700000000.0),
+            elem("2001:db8::/32", "2001:db8::1", 2, 1700000001.0),
+        ];
+        let start = Instant::now();
+        assert_eq!(replay.replay(elems.clone()).unwrap(), 2);
+        // one second of routes at 100x speed
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        replay.close().unwrap();
+
+        let received = remote.join().unwrap();
+        assert_eq!(received.len(), 2);
+        for (received, sent) in received.iter().zip(&elems) {
+            assert_eq!(received.prefix, sent.prefix);
+            assert_eq!(received.as_path, sent.as_path);
+            assert_eq!(received.next_hop, sent.next_hop);
+        }
+    }
+
+    #[test]
+    fn test_negotiate_rejects_bad_peer_as() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let replay = BgpReplay::from_stream(stream, Asn::new_32bit(64496), BgpIdentifier::new(2))
+            .with_remote_asn(Asn::new_32bit(64501));
+
+        let open = BgpReplay::from_stream(
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+            Asn::new_32bit(64500),
+            BgpIdentifier::new(1),
+        )
+        .open_message();
+        let (code, subcode, _) = replay.negotiate(&open).unwrap_err();
+        assert_eq!((code, subcode), (2, 2));
+
+        let mut open = open;
+        open.version = 3;
+        assert_eq!(replay.negotiate(&open).unwrap_err().0, 2);
+    }
+}
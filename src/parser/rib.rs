@@ -0,0 +1,300 @@
+/*!
+Reconstruct the routing table at any time from a RIB dump and the updates following it.
+
+[RibBuilder] loads the elems of a table dump v2 RIB snapshot into a [RibTable], the route of
+every peer for every prefix, then applies the elems of BGP4MP updates in timestamp order. Each
+update changing the table produces a [RouteChange], and [RibBuilder::advance_to] brings the table
+to its state at a given time.
+
+```no_run
+use bgpkit_parser::rib::RibBuilder;
+use bgpkit_parser::{merge_elems_stable, BgpkitParser};
+
+let rib = BgpkitParser::new("rib.20240101.0000.bz2").unwrap();
+let updates = merge_elems_stable(vec![
+    BgpkitParser::new("updates.20240101.0000.bz2").unwrap(),
+    BgpkitParser::new("updates.20240101.0015.bz2").unwrap(),
+]);
+let mut builder = RibBuilder::new(rib, updates);
+for change in builder.advance_to(1704068400.0) {
+    println!("{} {:?} -> {:?}", change.prefix, change.old.map(|e| e.as_path), change.new.map(|e| e.as_path));
+}
+println!("{} routes at {}", builder.table().len(), builder.table().timestamp());
+```
+
+RIB dumps take a while to write, so the records of a dump have different timestamps. Updates
+older than the first record of the dump, and updates older than the route of their peer for their
+prefix, are therefore ignored as already reflected in the table.
+*/
+use crate::models::{BgpElem, ElemType, NetworkPrefix};
+use crate::parser::demux::PeerKey;
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+/// Change of the route of a peer for a prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteChange {
+    pub peer: PeerKey,
+    pub prefix: NetworkPrefix,
+    /// timestamp of the update causing the change
+    pub timestamp: f64,
+    /// route before the change, None for a new route
+    pub old: Option<BgpElem>,
+    /// route after the change, None for a withdrawn route
+    pub new: Option<BgpElem>,
+}
+
+impl RouteChange {
+    /// Returns true if the route was withdrawn.
+    pub fn is_withdrawal(&self) -> bool {
+        self.new.is_none()
+    }
+}
+
+/// Routing table: the route of every peer for every prefix, as the announcing elem.
+#[derive(Debug, Clone, Default)]
+pub struct RibTable {
+    routes: HashMap<(PeerKey, NetworkPrefix), BgpElem>,
+    timestamp: f64,
+}
+
+impl RibTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time of the state of the table: the timestamp of the last elem applied, or the time the
+    /// table was advanced to.
+    pub fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+
+    /// Number of routes.
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// The route of a peer for a prefix.
+    pub fn get(&self, peer: &PeerKey, prefix: &NetworkPrefix) -> Option<&BgpElem> {
+        self.routes.get(&(*peer, *prefix))
+    }
+
+    /// All routes, in no particular order.
+    pub fn routes(&self) -> impl Iterator<Item = &BgpElem> {
+        self.routes.values()
+    }
+
+    /// The routes of all peers for a prefix, in no particular order.
+    pub fn routes_for_prefix<'a>(
+        &'a self,
+        prefix: &'a NetworkPrefix,
+    ) -> impl Iterator<Item = &'a BgpElem> + 'a {
+        self.routes
+            .iter()
+            .filter(move |((_, p), _)| p == prefix)
+            .map(|(_, elem)| elem)
+    }
+
+    /// Apply an announcement or a withdrawal, returning the change of the table if any.
+    ///
+    /// Elems older than the current route of their peer for their prefix are ignored.
+    /// Withdrawals of prefixes without a route do not change the table.
+    pub fn apply(&mut self, elem: BgpElem) -> Option<RouteChange> {
+        let peer = PeerKey {
+            peer_ip: elem.peer_ip,
+            peer_asn: elem.peer_asn,
+        };
+        let key = (peer, elem.prefix);
+        let timestamp = elem.timestamp;
+        if let Some(current) = self.routes.get(&key) {
+            if timestamp < current.timestamp {
+                return None;
+            }
+        }
+        self.timestamp = self.timestamp.max(timestamp);
+        let (old, new) = match elem.elem_type {
+            ElemType::ANNOUNCE => (self.routes.insert(key, elem.clone()), Some(elem)),
+            ElemType::WITHDRAW => (self.routes.remove(&key), None),
+        };
+        if old.is_none() && new.is_none() {
+            return None;
+        }
+        Some(RouteChange {
+            peer,
+            prefix: key.1,
+            timestamp,
+            old,
+            new,
+        })
+    }
+}
+
+impl Extend<BgpElem> for RibTable {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, elems: T) {
+        for elem in elems {
+            self.apply(elem);
+        }
+    }
+}
+
+/// Routing table built from a RIB dump and the updates following it, see
+/// [rib](crate::parser::rib).
+///
+/// Iterating over the builder applies the updates one by one and returns the changes of the
+/// table.
+pub struct RibBuilder<I: Iterator<Item = BgpElem>> {
+    table: RibTable,
+    updates: Peekable<I>,
+    /// timestamp of the first record of the dump
+    rib_start: f64,
+}
+
+impl<I: Iterator<Item = BgpElem>> RibBuilder<I> {
+    /// Load the elems of a RIB dump, to apply the `updates` after it.
+    ///
+    /// The updates must be ordered by timestamp, e.g. with
+    /// [merge_elems_stable](crate::merge_elems_stable) for several update files.
+    pub fn new<R, U>(rib: R, updates: U) -> Self
+    where
+        R: IntoIterator<Item = BgpElem>,
+        U: IntoIterator<Item = BgpElem, IntoIter = I>,
+    {
+        let mut table = RibTable::new();
+        let mut rib_start: Option<f64> = None;
+        for elem in rib {
+            rib_start = Some(rib_start.map_or(elem.timestamp, |t| t.min(elem.timestamp)));
+            table.apply(elem);
+        }
+        RibBuilder {
+            table,
+            updates: updates.into_iter().peekable(),
+            rib_start: rib_start.unwrap_or(f64::MIN),
+        }
+    }
+
+    /// The current state of the table.
+    pub fn table(&self) -> &RibTable {
+        &self.table
+    }
+
+    pub fn into_table(self) -> RibTable {
+        self.table
+    }
+
+    /// Apply the updates up to the timestamp, inclusive, returning the changes of the table.
+    pub fn advance_to(&mut self, timestamp: f64) -> Vec<RouteChange> {
+        let mut changes = vec![];
+        while let Some(elem) = self.updates.next_if(|e| e.timestamp <= timestamp) {
+            changes.extend(self.apply_update(elem));
+        }
+        self.table.timestamp = self.table.timestamp.max(timestamp);
+        changes
+    }
+
+    fn apply_update(&mut self, elem: BgpElem) -> Option<RouteChange> {
+        match elem.timestamp < self.rib_start {
+            true => None,
+            false => self.table.apply(elem),
+        }
+    }
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for RibBuilder<I> {
+    type Item = RouteChange;
+
+    fn next(&mut self) -> Option<RouteChange> {
+        loop {
+            let elem = self.updates.next()?;
+            if let Some(change) = self.apply_update(elem) {
+                return Some(change);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AsPath, Asn};
+    use crate::parser::test_elems::{announce, withdraw};
+    use std::str::FromStr;
+
+    fn peer(ip: &str) -> PeerKey {
+        PeerKey {
+            peer_ip: ip.parse().unwrap(),
+            peer_asn: Asn::new_32bit(64496),
+        }
+    }
+
+    #[test]
+    fn test_rib_table() {
+        let mut table = RibTable::new();
+        let prefix = NetworkPrefix::from_str("10.0.0.0/8").unwrap();
+        let change = table
+            .apply(announce("192.0.2.1", "10.0.0.0/8", &[1, 2]).at(10.0))
+            .unwrap();
+        assert!(change.old.is_none());
+        table.apply(announce("192.0.2.2", "10.0.0.0/8", &[3]).at(10.0));
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.routes_for_prefix(&prefix).count(), 2);
+
+        let change = table
+            .apply(announce("192.0.2.1", "10.0.0.0/8", &[1, 4]).at(20.0))
+            .unwrap();
+        assert_eq!(
+            change.old.unwrap().as_path,
+            Some(AsPath::from_sequence([1, 2]))
+        );
+        // older than the current route
+        assert!(table
+            .apply(withdraw("192.0.2.1", "10.0.0.0/8").at(15.0))
+            .is_none());
+        // no route to withdraw
+        assert!(table
+            .apply(withdraw("192.0.2.3", "10.0.0.0/8").at(20.0))
+            .is_none());
+
+        let change = table
+            .apply(withdraw("192.0.2.2", "10.0.0.0/8").at(30.0))
+            .unwrap();
+        assert!(change.is_withdrawal());
+        assert!(table.get(&peer("192.0.2.2"), &prefix).is_none());
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.timestamp(), 30.0);
+    }
+
+    #[test]
+    fn test_rib_builder() {
+        let rib = vec![
+            announce("192.0.2.1", "10.0.0.0/8", &[1]).at(100.0),
+            announce("192.0.2.1", "192.0.2.0/24", &[1]).at(101.0),
+        ];
+        let updates = vec![
+            // before the dump
+            withdraw("192.0.2.1", "10.0.0.0/8").at(90.0),
+            // during the dump, before the route of the dump
+            withdraw("192.0.2.1", "192.0.2.0/24").at(100.5),
+            announce("192.0.2.1", "10.0.0.0/8", &[2]).at(110.0),
+            withdraw("192.0.2.1", "192.0.2.0/24").at(120.0),
+            announce("192.0.2.2", "10.0.0.0/8", &[3]).at(130.0),
+        ];
+        let mut builder = RibBuilder::new(rib, updates);
+        assert_eq!(builder.table().len(), 2);
+
+        let changes = builder.advance_to(120.0);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].timestamp, 110.0);
+        assert!(changes[1].is_withdrawal());
+        assert_eq!(builder.table().len(), 1);
+        assert_eq!(builder.table().timestamp(), 120.0);
+
+        let changes: Vec<RouteChange> = builder.by_ref().collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].peer, peer("192.0.2.2"));
+        assert_eq!(builder.into_table().len(), 2);
+    }
+}
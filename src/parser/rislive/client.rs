@@ -0,0 +1,287 @@
+/*!
+Async [RIS-Live](https://ris-live.ripe.net/manual/) client returning a stream of [BgpElem]s.
+
+[RisLiveClient] connects to the RIS-Live websocket, sends the subscriptions, and pings the server
+periodically to keep the connection open. When the connection drops, it reconnects with an
+exponential backoff and sends the subscriptions again, so the stream only ends once the maximum
+number of retries is reached, if one is set.
+
+```no_run
+use bgpkit_parser::rislive::client::RisLiveClient;
+use bgpkit_parser::rislive::messages::RisSubscribe;
+use futures_util::StreamExt;
+
+#[tokio::main]
+async fn main() {
+    let client = RisLiveClient::new().subscribe(RisSubscribe::new().host("rrc21"));
+    let mut elems = Box::pin(client.elem_stream());
+    while let Some(elem) = elems.next().await {
+        println!("{}", elem);
+    }
+}
+```
+*/
+use crate::models::BgpElem;
+use crate::parser::rislive::messages::{Ping, RisLiveClientMessage, RisSubscribe};
+use crate::parser::rislive::parse_ris_live_message;
+use futures_util::stream::{self, Stream};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::{interval, sleep, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+/// RIS-Live websocket endpoint used by [RisLiveClient::new].
+pub const RIS_LIVE_URL: &str = "ws://ris-live.ripe.net/v1/ws/?client=rust-bgpkit-parser";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Client of the RIS-Live websocket, see [client](crate::parser::rislive::client).
+#[derive(Debug, Clone)]
+pub struct RisLiveClient {
+    url: String,
+    subscriptions: Vec<String>,
+    ping_interval: Duration,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    max_retries: Option<u32>,
+}
+
+impl Default for RisLiveClient {
+    fn default() -> Self {
+        RisLiveClient {
+            url: RIS_LIVE_URL.to_string(),
+            subscriptions: vec![],
+            ping_interval: Duration::from_secs(30),
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_retries: None,
+        }
+    }
+}
+
+impl RisLiveClient {
+    /// Create a client of [RIS_LIVE_URL], without subscriptions.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Connect to another websocket URL, e.g. to set the `client` parameter identifying the
+    /// application to RIS-Live.
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Add a subscription, sent again on every reconnection.
+    pub fn subscribe(mut self, subscription: RisSubscribe) -> Self {
+        self.subscriptions.push(subscription.to_json_string());
+        self
+    }
+
+    /// Set the interval between pings sent to the server, 30 seconds by default.
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    /// Set the delays between reconnection attempts, doubling from `min` up to `max`, 1 and 60
+    /// seconds by default.
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self
+    }
+
+    /// End the stream once the connection still fails after the given number of consecutive
+    /// retries, instead of reconnecting forever.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Delay before the given reconnection attempt, starting from 1.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.min_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Connect and send the subscriptions.
+    async fn connect(&self) -> Result<Socket, tokio_tungstenite::tungstenite::Error> {
+        let (mut socket, _response) = connect_async(self.url.as_str()).await?;
+        for subscription in &self.subscriptions {
+            socket.send(Message::Text(subscription.clone())).await?;
+        }
+        Ok(socket)
+    }
+
+    /// Stream of the elems of the messages of the subscriptions.
+    ///
+    /// Messages failing to parse are skipped with a warning.
+    pub fn elem_stream(self) -> impl Stream<Item = BgpElem> {
+        let mut ping = interval(self.ping_interval);
+        ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let state = ClientState {
+            client: self,
+            socket: None,
+            failures: 0,
+            ping,
+            elems: VecDeque::new(),
+        };
+        stream::unfold(state, |mut state| async move {
+            let elem = state.next_elem().await?;
+            Some((elem, state))
+        })
+    }
+}
+
+struct ClientState {
+    client: RisLiveClient,
+    socket: Option<Socket>,
+    /// consecutive failed connection attempts
+    failures: u32,
+    ping: Interval,
+    elems: VecDeque<BgpElem>,
+}
+
+impl ClientState {
+    async fn next_elem(&mut self) -> Option<BgpElem> {
+        loop {
+            if let Some(elem) = self.elems.pop_front() {
+                return Some(elem);
+            }
+            let socket = match &mut self.socket {
+                Some(socket) => socket,
+                None => {
+                    self.reconnect().await?;
+                    continue;
+                }
+            };
+            let message = tokio::select! {
+                message = socket.next() => message,
+                _ = self.ping.tick() => {
+                    match socket.send(Message::Text(Ping {}.to_json_string())).await {
+                        Ok(()) => continue,
+                        Err(e) => Some(Err(e)),
+                    }
+                }
+            };
+            match message {
+                Some(Ok(Message::Text(text))) => match parse_ris_live_message(&text) {
+                    Ok(elems) => self.elems.extend(elems),
+                    Err(e) => warn!("ris live: {}", e),
+                },
+                Some(Ok(Message::Close(_))) | None => {
+                    debug!("ris live: connection closed");
+                    self.socket = None;
+                }
+                // websocket pings are answered by the socket itself
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    warn!("ris live: {}", e);
+                    self.socket = None;
+                }
+            }
+        }
+    }
+
+    /// Connect after the backoff delay, or return None once the maximum number of attempts is
+    /// reached.
+    async fn reconnect(&mut self) -> Option<()> {
+        if let Some(max_retries) = self.client.max_retries {
+            if self.failures > max_retries {
+                return None;
+            }
+        }
+        if self.failures > 0 {
+            sleep(self.client.backoff(self.failures)).await;
+        }
+        match self.client.connect().await {
+            Ok(socket) => {
+                self.socket = Some(socket);
+                self.failures = 0;
+                self.ping.reset();
+            }
+            Err(e) => {
+                warn!("ris live: failed to connect to {}: {}", self.client.url, e);
+                self.failures += 1;
+            }
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const MESSAGE: &str = r#"{"type": "ris_message","data":{"timestamp":1636342486.17,"peer":"37.49.237.175","peer_asn":"199524","id":"21-587-22045871","host":"rrc21","type":"UPDATE","path":[199524,1299,3356,13904],"origin":"igp","announcements":[{"next_hop":"37.49.237.175","prefixes":["64.68.236.0/22"]}]}}"#;
+
+    #[test]
+    fn test_backoff() {
+        let client =
+            RisLiveClient::new().with_backoff(Duration::from_secs(1), Duration::from_secs(10));
+        let delays: Vec<u64> = (1..=6).map(|i| client.backoff(i).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(client.backoff(100).as_secs(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_and_resubscribe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut subscriptions = vec![];
+            // send one message per connection, then close it
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                match socket.next().await {
+                    Some(Ok(Message::Text(text))) => subscriptions.push(text),
+                    other => panic!("unexpected message {:?}", other),
+                }
+                socket
+                    .send(Message::Text(MESSAGE.to_string()))
+                    .await
+                    .unwrap();
+                // the client may be gone after the last message
+                let _ = socket.close(None).await;
+            }
+            subscriptions
+        });
+
+        let client = RisLiveClient::new()
+            .with_url(&url)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .with_max_retries(3)
+            .subscribe(RisSubscribe::new().host("rrc21"));
+        let elems: Vec<BgpElem> = client.elem_stream().take(2).collect().await;
+        assert_eq!(elems.len(), 2);
+        assert_eq!(elems[0].prefix.to_string(), "64.68.236.0/22");
+
+        let subscriptions = server.await.unwrap();
+        let expected = RisSubscribe::new().host("rrc21").to_json_string();
+        assert_eq!(subscriptions, vec![expected.clone(), expected]);
+    }
+
+    #[tokio::test]
+    async fn test_max_retries() {
+        // nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = RisLiveClient::new()
+            .with_url(&url)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .with_max_retries(2);
+        let elems: Vec<BgpElem> = client.elem_stream().collect().await;
+        assert!(elems.is_empty());
+    }
+}
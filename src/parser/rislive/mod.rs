@@ -5,6 +5,9 @@ BGP message stream JSON data.
 The main parsing function, [parse_ris_live_message] converts a JSON-formatted message string into a
 vector of [BgpElem]s.
 
+With the `rislive-client` feature, `client::RisLiveClient` manages the websocket connection,
+including the subscriptions and reconnections, and returns a stream of [BgpElem]s.
+
 Here is an example parsing stream data from one collector:
 ```no_run
 use bgpkit_parser::parse_ris_live_message;
@@ -42,8 +45,9 @@ use crate::parser::rislive::messages::{RisLiveMessage, RisMessageEnum};
 
 use crate::models::*;
 use ipnet::IpNet;
-use std::net::Ipv4Addr;
 
+#[cfg(feature = "rislive-client")]
+pub mod client;
 pub mod error;
 pub mod messages;
 
@@ -127,7 +131,7 @@ pub fn parse_ris_live_message(msg_str: &str) -> Result<Vec<BgpElem>, ParserRisli
                             };
 
                             let asn = unwrap_or_return!(asn_str.parse::<Asn>(), msg_string);
-                            let ip = unwrap_or_return!(ip_str.parse::<Ipv4Addr>(), msg_string);
+                            let ip = unwrap_or_return!(ip_str.parse::<BgpIdentifier>(), msg_string);
                             (Some(asn), Some(ip))
                         }
                     };
@@ -156,6 +160,7 @@ pub fn parse_ris_live_message(msg_str: &str) -> Result<Vec<BgpElem>, ParserRisli
                                         prefix: p,
                                         path_id: 0,
                                     },
+                                    safi: Safi::Unicast,
                                     next_hop: Some(announcement.next_hop),
                                     as_path: path.clone(),
                                     origin_asns: None,
@@ -169,6 +174,10 @@ pub fn parse_ris_live_message(msg_str: &str) -> Result<Vec<BgpElem>, ParserRisli
                                     only_to_customer: None,
                                     unknown: None,
                                     deprecated: None,
+                                    record_index: None,
+                                    record_offset: None,
+                                    rib_side: None,
+                                    policy: None,
                                 });
                             }
                         }
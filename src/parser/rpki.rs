@@ -0,0 +1,287 @@
+/*!
+RPKI route origin validation of announced prefixes.
+
+A [RoaTable] is loaded from the validated ROA payloads (VRPs) exported by a relying party, e.g.
+the JSON output of Routinator or rpki-client. Announcements are then classified into an
+[RpkiValidation] state following [RFC 6811](https://datatracker.ietf.org/doc/html/rfc6811).
+
+```no_run
+use bgpkit_parser::rpki::RoaTable;
+use bgpkit_parser::BgpkitParser;
+
+let roas = RoaTable::from_json(std::fs::File::open("vrps.json").unwrap()).unwrap();
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+for elem in parser.into_elem_iter() {
+    println!("{} {:?}", elem.prefix, roas.validate_elem(&elem));
+}
+```
+
+As an [Enricher](crate::parser::pipeline::Enricher) of a [Pipeline](crate::Pipeline), the table
+adds the validation state to the communities of each announcement, as the origin validation state
+extended community of [RFC 8097](https://datatracker.ietf.org/doc/html/rfc8097).
+*/
+use crate::models::{
+    Asn, BgpElem, ExtendedCommunity, MetaCommunity, NetworkPrefix, OpaqueExtCommunity,
+};
+use crate::parser::pipeline::Enricher;
+use crate::ParserError;
+use ipnet::IpNet;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+/// Validated ROA payload: `asn` may originate `prefix` and its more-specifics up to `max_length`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Roa {
+    pub prefix: IpNet,
+    pub max_length: u8,
+    pub asn: Asn,
+}
+
+/// Route origin validation state, see RFC 6811 section 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum RpkiValidation {
+    /// a ROA covering the prefix authorizes the origin
+    Valid,
+    /// ROAs cover the prefix, but none authorizes the origin for this prefix length
+    Invalid,
+    /// no ROA covers the prefix
+    NotFound,
+}
+
+impl RpkiValidation {
+    /// Origin validation state extended community of RFC 8097: non-transitive opaque type
+    /// `0x43`, subtype `0x00`, and the state as the last value octet.
+    pub fn to_community(self) -> ExtendedCommunity {
+        let state = match self {
+            RpkiValidation::Valid => 0,
+            RpkiValidation::NotFound => 1,
+            RpkiValidation::Invalid => 2,
+        };
+        ExtendedCommunity::NonTransitiveOpaque(OpaqueExtCommunity {
+            subtype: 0,
+            value: [0, 0, 0, 0, 0, state],
+        })
+    }
+}
+
+/// ROAs indexed by prefix.
+#[derive(Debug, Clone, Default)]
+pub struct RoaTable {
+    roas: HashMap<IpNet, Vec<Roa>>,
+}
+
+impl RoaTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load ROAs from the JSON export of a relying party, e.g.
+    /// `{"roas": [{"asn": "AS64496", "prefix": "192.0.2.0/24", "maxLength": 24, "ta": "arin"}]}`.
+    ///
+    /// ASNs are accepted as numbers or `AS`-prefixed strings, and other fields are ignored.
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, ParserError> {
+        let export: VrpExport = serde_json::from_reader(reader)
+            .map_err(|e| ParserError::ParseError(format!("invalid VRP JSON: {}", e)))?;
+        let mut table = RoaTable::new();
+        for vrp in export.roas {
+            let asn = match vrp.asn {
+                VrpAsn::Number(asn) => Asn::new_32bit(asn),
+                VrpAsn::String(asn) => Asn::from_str(&asn.to_ascii_uppercase())
+                    .map_err(|_| ParserError::ParseError(format!("invalid VRP ASN {}", asn)))?,
+            };
+            table.insert(Roa {
+                prefix: vrp.prefix,
+                max_length: vrp.max_length.unwrap_or(vrp.prefix.prefix_len()),
+                asn,
+            });
+        }
+        Ok(table)
+    }
+
+    /// Add a ROA. Identical ROAs are only kept once.
+    pub fn insert(&mut self, mut roa: Roa) {
+        roa.prefix = roa.prefix.trunc();
+        let roas = self.roas.entry(roa.prefix).or_default();
+        if !roas.contains(&roa) {
+            roas.push(roa);
+        }
+    }
+
+    /// Number of distinct ROA prefixes.
+    pub fn len(&self) -> usize {
+        self.roas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roas.is_empty()
+    }
+
+    /// Validate an announcement of `prefix` by `origin`.
+    ///
+    /// A `None` origin, e.g. of an AS path ending with an AS set, is not authorized by any ROA.
+    /// ROAs for AS 0 never authorize an origin.
+    pub fn validate(&self, prefix: &NetworkPrefix, origin: Option<Asn>) -> RpkiValidation {
+        let prefix = prefix.prefix.trunc();
+        let mut covered = false;
+        for len in 0..=prefix.prefix_len() {
+            let covering = match IpNet::new(prefix.addr(), len) {
+                Ok(covering) => covering.trunc(),
+                Err(_) => continue,
+            };
+            let Some(roas) = self.roas.get(&covering) else {
+                continue;
+            };
+            covered = true;
+            if roas.iter().any(|roa| {
+                Some(roa.asn) == origin
+                    && roa.asn != Asn::new_32bit(0)
+                    && prefix.prefix_len() <= roa.max_length
+            }) {
+                return RpkiValidation::Valid;
+            }
+        }
+        match covered {
+            true => RpkiValidation::Invalid,
+            false => RpkiValidation::NotFound,
+        }
+    }
+
+    /// Validate the origin of an elem.
+    ///
+    /// Returns `None` for elems without origin, i.e. withdrawals.
+    pub fn validate_elem(&self, elem: &BgpElem) -> Option<RpkiValidation> {
+        let origins = elem.origin_asns.as_ref().filter(|o| !o.is_empty())?;
+        let origin = match origins.as_slice() {
+            [origin] => Some(*origin),
+            _ => None,
+        };
+        Some(self.validate(&elem.prefix, origin))
+    }
+}
+
+impl FromIterator<Roa> for RoaTable {
+    fn from_iter<T: IntoIterator<Item = Roa>>(iter: T) -> Self {
+        let mut table = RoaTable::new();
+        for roa in iter {
+            table.insert(roa);
+        }
+        table
+    }
+}
+
+/// Replaces any origin validation state community of announcements with the state computed from
+/// the table.
+impl Enricher for RoaTable {
+    fn enrich(&mut self, elem: &mut BgpElem) {
+        let Some(state) = self.validate_elem(elem) else {
+            return;
+        };
+        let communities = elem.communities.get_or_insert_with(Vec::new);
+        communities.retain(|c| !is_origin_validation_community(c));
+        communities.push(MetaCommunity::Extended(state.to_community()));
+    }
+}
+
+fn is_origin_validation_community(community: &MetaCommunity) -> bool {
+    matches!(
+        community,
+        MetaCommunity::Extended(ExtendedCommunity::NonTransitiveOpaque(OpaqueExtCommunity {
+            subtype: 0,
+            ..
+        }))
+    )
+}
+
+/// Top-level object of a VRP JSON export.
+#[derive(serde::Deserialize)]
+struct VrpExport {
+    roas: Vec<Vrp>,
+}
+
+#[derive(serde::Deserialize)]
+struct Vrp {
+    asn: VrpAsn,
+    prefix: IpNet,
+    #[serde(rename = "maxLength")]
+    max_length: Option<u8>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum VrpAsn {
+    Number(u32),
+    String(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VRPS: &str = r#"{"roas": [
+        {"asn": "AS64496", "prefix": "192.0.2.0/23", "maxLength": 24, "ta": "arin"},
+        {"asn": 64497, "prefix": "2001:db8::/32", "maxLength": 32, "ta": "ripe"},
+        {"asn": "AS0", "prefix": "198.51.100.0/24", "maxLength": 24, "ta": "apnic"}
+    ]}"#;
+
+    #[test]
+    fn test_validate() {
+        let roas = RoaTable::from_json(VRPS.as_bytes()).unwrap();
+        assert_eq!(roas.len(), 3);
+
+        let origin = Some(Asn::new_32bit(64496));
+        let prefix = NetworkPrefix::from_str("192.0.2.0/24").unwrap();
+        assert_eq!(roas.validate(&prefix, origin), RpkiValidation::Valid);
+        assert_eq!(
+            roas.validate(&prefix, Some(Asn::new_32bit(64500))),
+            RpkiValidation::Invalid
+        );
+        assert_eq!(roas.validate(&prefix, None), RpkiValidation::Invalid);
+        // longer than the max length
+        let prefix = NetworkPrefix::from_str("192.0.2.0/25").unwrap();
+        assert_eq!(roas.validate(&prefix, origin), RpkiValidation::Invalid);
+        let prefix = NetworkPrefix::from_str("203.0.113.0/24").unwrap();
+        assert_eq!(roas.validate(&prefix, origin), RpkiValidation::NotFound);
+
+        let prefix = NetworkPrefix::from_str("2001:db8::/32").unwrap();
+        assert_eq!(
+            roas.validate(&prefix, Some(Asn::new_32bit(64497))),
+            RpkiValidation::Valid
+        );
+        let prefix = NetworkPrefix::from_str("198.51.100.0/24").unwrap();
+        assert_eq!(
+            roas.validate(&prefix, Some(Asn::new_32bit(0))),
+            RpkiValidation::Invalid
+        );
+
+        assert!(RoaTable::from_json("[]".as_bytes()).is_err());
+        assert!(RoaTable::from_json(
+            r#"{"roas": [{"asn": "X", "prefix": "192.0.2.0/24"}]}"#.as_bytes()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_enrich() {
+        let mut roas = RoaTable::from_json(VRPS.as_bytes()).unwrap();
+        let mut elem = BgpElem {
+            prefix: NetworkPrefix::from_str("192.0.2.0/24").unwrap(),
+            origin_asns: Some(vec![Asn::new_32bit(64496)].into()),
+            communities: Some(vec![MetaCommunity::Extended(
+                RpkiValidation::Invalid.to_community(),
+            )]),
+            ..Default::default()
+        };
+        roas.enrich(&mut elem);
+        assert_eq!(
+            elem.communities,
+            Some(vec![MetaCommunity::Extended(
+                RpkiValidation::Valid.to_community()
+            )])
+        );
+
+        let mut withdrawal = BgpElem::default();
+        roas.enrich(&mut withdrawal);
+        assert_eq!(withdrawal.communities, None);
+    }
+}
@@ -0,0 +1,98 @@
+/*!
+Vectors of parsed records kept for reuse by the parser.
+
+[ParseContext::recycle](crate::ParseContext::recycle) takes back the attribute, prefix and RIB
+entry vectors of records that are no longer needed. The parser takes the vectors of the records
+parsed with the context from these instead of allocating new ones.
+*/
+use crate::models::{Attribute, AttributeValue, Attributes, NetworkPrefix, RibEntry};
+
+/// Maximum number of vectors of each kind kept for reuse.
+const MAX_POOLED_VECS: usize = 4096;
+
+/// Cleared vectors ready to be reused.
+#[derive(Debug, Default)]
+pub(crate) struct VecPool {
+    attributes: Vec<Vec<Attribute>>,
+    prefixes: Vec<Vec<NetworkPrefix>>,
+    rib_entries: Vec<Vec<RibEntry>>,
+}
+
+impl VecPool {
+    pub(crate) fn put_attributes(&mut self, attributes: Attributes) {
+        let mut inner = attributes.inner;
+        for attr in inner.drain(..) {
+            if let AttributeValue::MpReachNlri(nlri) | AttributeValue::MpUnreachNlri(nlri) =
+                attr.value
+            {
+                self.put_prefixes(nlri.prefixes);
+            }
+        }
+        put(&mut self.attributes, inner);
+    }
+
+    pub(crate) fn put_prefixes(&mut self, prefixes: Vec<NetworkPrefix>) {
+        put(&mut self.prefixes, prefixes);
+    }
+
+    pub(crate) fn put_rib_entries(&mut self, mut entries: Vec<RibEntry>) {
+        for entry in entries.drain(..) {
+            self.put_attributes(entry.attributes);
+        }
+        put(&mut self.rib_entries, entries);
+    }
+
+    pub(crate) fn take_attributes(&mut self, capacity: usize) -> Vec<Attribute> {
+        take(&mut self.attributes, capacity)
+    }
+
+    pub(crate) fn take_prefixes(&mut self, capacity: usize) -> Vec<NetworkPrefix> {
+        take(&mut self.prefixes, capacity)
+    }
+
+    pub(crate) fn take_rib_entries(&mut self, capacity: usize) -> Vec<RibEntry> {
+        take(&mut self.rib_entries, capacity)
+    }
+}
+
+fn put<T>(pool: &mut Vec<Vec<T>>, mut vec: Vec<T>) {
+    if vec.capacity() > 0 && pool.len() < MAX_POOLED_VECS {
+        vec.clear();
+        pool.push(vec);
+    }
+}
+
+/// An empty vector with at least `capacity`, reused from the pool if possible.
+fn take<T>(pool: &mut Vec<Vec<T>>, capacity: usize) -> Vec<T> {
+    match pool.pop() {
+        Some(mut vec) => {
+            vec.reserve(capacity);
+            vec
+        }
+        None => Vec::with_capacity(capacity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_vec_pool() {
+        let mut pool = VecPool::default();
+        let mut prefixes = Vec::with_capacity(16);
+        prefixes.push(NetworkPrefix::from_str("10.0.0.0/8").unwrap());
+        let ptr = prefixes.as_ptr();
+        pool.put_prefixes(prefixes);
+        // vectors without allocation are not kept
+        pool.put_prefixes(vec![]);
+        assert_eq!(pool.prefixes.len(), 1);
+
+        let reused = pool.take_prefixes(4);
+        assert_eq!(reused.as_ptr(), ptr);
+        assert!(reused.is_empty());
+        assert!(pool.prefixes.is_empty());
+        assert!(pool.take_prefixes(4).capacity() >= 4);
+    }
+}
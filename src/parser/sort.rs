@@ -0,0 +1,292 @@
+/*!
+Time ordering of elems from sources with different timestamp precision.
+
+`BGP4MP_ET` records carry microsecond timestamps, while `BGP4MP` and table dump records only carry
+whole seconds. An elem at `100.0` from a file without extended timestamps may have been received
+at any time during second 100, so ordering it against `100.3` from an extended timestamp file by
+the floating-point value is wrong half of the time, and reorders events that convergence
+measurements rely on.
+
+The utilities here order elems by whole seconds only and keep the input order within a second:
+the file order within one file, and the order of the sources when merging files. Elems of one
+file keep their sub-second order, as files are written in the order the messages are received.
+
+```
+use bgpkit_parser::{merge_elems_stable, sort_elems_stable, BgpElem};
+
+let elem = |timestamp: f64| BgpElem {
+    timestamp,
+    ..Default::default()
+};
+let sorted = sort_elems_stable(vec![elem(101.0), elem(100.7), elem(100.0)]);
+let timestamps: Vec<f64> = sorted.iter().map(|e| e.timestamp).collect();
+assert_eq!(timestamps, vec![100.7, 100.0, 101.0]);
+
+let extended = vec![elem(100.3), elem(100.5), elem(101.2)];
+let plain = vec![elem(100.0), elem(101.0)];
+let merged: Vec<f64> = merge_elems_stable(vec![extended, plain])
+    .map(|e| e.timestamp)
+    .collect();
+assert_eq!(merged, vec![100.3, 100.5, 100.0, 101.2, 101.0]);
+```
+
+[merge_records_stable] applies the same ordering to MRT records, and [merge_mrt_files] merges
+update files, e.g. of RouteViews and RIS collectors, into a single timestamp-ordered MRT file.
+Records are written back unchanged, keeping the microsecond timestamps of `BGP4MP_ET` records.
+*/
+#[cfg(feature = "oneio")]
+use crate::error::ParserError;
+use crate::models::{BgpElem, MrtRecord};
+#[cfg(feature = "oneio")]
+use crate::parser::{BgpkitParser, ParserErrorWithBytes};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+#[cfg(feature = "oneio")]
+use std::io::Write;
+
+/// The whole second of the elem's timestamp.
+fn second(elem: &BgpElem) -> i64 {
+    elem.timestamp.floor() as i64
+}
+
+/// Sort elems by the whole second of their timestamps, keeping the input order of elems within
+/// the same second.
+pub fn sort_elems_stable(mut elems: Vec<BgpElem>) -> Vec<BgpElem> {
+    // `sort_by_key` is stable
+    elems.sort_by_key(second);
+    elems
+}
+
+/// Merge sources of elems, each ordered by time, into one iterator ordered by the whole second of
+/// the timestamps.
+///
+/// Within the same second, all elems of an earlier source come before the elems of later sources,
+/// and the elems of each source keep their order.
+pub fn merge_elems_stable<I: IntoIterator<Item = BgpElem>>(
+    sources: Vec<I>,
+) -> MergedElems<I::IntoIter> {
+    let mut sources: Vec<I::IntoIter> = sources.into_iter().map(|s| s.into_iter()).collect();
+    let mut heads = Vec::with_capacity(sources.len());
+    let mut queue = BinaryHeap::with_capacity(sources.len());
+    for (index, source) in sources.iter_mut().enumerate() {
+        let head = source.next();
+        if let Some(elem) = &head {
+            queue.push(Reverse((second(elem), index)));
+        }
+        heads.push(head);
+    }
+    MergedElems {
+        sources,
+        heads,
+        queue,
+    }
+}
+
+/// Iterator returned by [merge_elems_stable].
+pub struct MergedElems<I> {
+    sources: Vec<I>,
+    /// the next elem of each source
+    heads: Vec<Option<BgpElem>>,
+    /// second and source index of the sources with a next elem
+    queue: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl<I: Iterator<Item = BgpElem>> Iterator for MergedElems<I> {
+    type Item = BgpElem;
+
+    fn next(&mut self) -> Option<BgpElem> {
+        let Reverse((_, index)) = self.queue.pop()?;
+        let next = self.sources[index].next();
+        if let Some(elem) = &next {
+            self.queue.push(Reverse((second(elem), index)));
+        }
+        std::mem::replace(&mut self.heads[index], next)
+    }
+}
+
+/// The whole second of the record's timestamp, without the microseconds of `BGP4MP_ET` records.
+fn record_second(record: &MrtRecord) -> i64 {
+    record.common_header.timestamp as i64
+}
+
+/// Merge sources of MRT records, each ordered by time, into one iterator ordered by the whole
+/// second of the timestamps, see [merge_elems_stable].
+pub fn merge_records_stable<I: IntoIterator<Item = MrtRecord>>(
+    sources: Vec<I>,
+) -> MergedRecords<I::IntoIter> {
+    let mut sources: Vec<I::IntoIter> = sources.into_iter().map(|s| s.into_iter()).collect();
+    let mut heads = Vec::with_capacity(sources.len());
+    let mut queue = BinaryHeap::with_capacity(sources.len());
+    for (index, source) in sources.iter_mut().enumerate() {
+        let head = source.next();
+        if let Some(record) = &head {
+            queue.push(Reverse((record_second(record), index)));
+        }
+        heads.push(head);
+    }
+    MergedRecords {
+        sources,
+        heads,
+        queue,
+    }
+}
+
+/// Iterator returned by [merge_records_stable].
+pub struct MergedRecords<I> {
+    sources: Vec<I>,
+    /// the next record of each source
+    heads: Vec<Option<MrtRecord>>,
+    /// second and source index of the sources with a next record
+    queue: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl<I: Iterator<Item = MrtRecord>> Iterator for MergedRecords<I> {
+    type Item = MrtRecord;
+
+    fn next(&mut self) -> Option<MrtRecord> {
+        let Reverse((_, index)) = self.queue.pop()?;
+        let next = self.sources[index].next();
+        if let Some(record) = &next {
+            self.queue.push(Reverse((record_second(record), index)));
+        }
+        std::mem::replace(&mut self.heads[index], next)
+    }
+}
+
+/// Merge MRT update files into one timestamp-ordered MRT file, compressed according to the
+/// extension of `output`. Returns the number of records written.
+///
+/// Records of the same second are ordered as [merge_records_stable] does: the records of earlier
+/// inputs come first. Records failing to parse are skipped. Merging RIB dumps is not supported, as
+/// their records refer to the peer index table of their own file.
+#[cfg(feature = "oneio")]
+pub fn merge_mrt_files(inputs: &[&str], output: &str) -> Result<u64, ParserErrorWithBytes> {
+    let sources = inputs
+        .iter()
+        .map(|path| Ok(BgpkitParser::new(path)?.into_record_iter()))
+        .collect::<Result<Vec<_>, ParserErrorWithBytes>>()?;
+    let mut writer = oneio::get_writer(output)?;
+    let mut records = 0;
+    for record in merge_records_stable(sources) {
+        writer
+            .write_all(&record.encode())
+            .map_err(ParserError::IoError)?;
+        records += 1;
+    }
+    writer.flush().map_err(ParserError::IoError)?;
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EntryType;
+
+    fn asns(elems: impl IntoIterator<Item = BgpElem>) -> Vec<u32> {
+        elems.into_iter().map(|e| e.peer_asn.to_u32()).collect()
+    }
+
+    #[test]
+    fn test_sort_elems_stable() {
+        let elems = vec![
+            BgpElem::default().at(100.9).with_peer_asn(1),
+            BgpElem::default().at(100.0).with_peer_asn(2),
+            BgpElem::default().at(99.5).with_peer_asn(3),
+            BgpElem::default().at(100.2).with_peer_asn(4),
+            BgpElem::default().at(f64::NAN).with_peer_asn(5),
+        ];
+        assert_eq!(asns(sort_elems_stable(elems)), vec![5, 3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn test_merge_elems_stable() {
+        let a = vec![
+            BgpElem::default().at(100.1).with_peer_asn(1),
+            BgpElem::default().at(100.8).with_peer_asn(2),
+            BgpElem::default().at(102.0).with_peer_asn(3),
+        ];
+        let b = vec![
+            BgpElem::default().at(99.0).with_peer_asn(4),
+            BgpElem::default().at(100.0).with_peer_asn(5),
+            BgpElem::default().at(101.0).with_peer_asn(6),
+        ];
+        let c: Vec<BgpElem> = vec![];
+        let merged = merge_elems_stable(vec![a, b, c]);
+        assert_eq!(asns(merged), vec![4, 1, 2, 5, 6, 3]);
+    }
+
+    fn records(timestamps: &[(u32, Option<u32>)]) -> Vec<MrtRecord> {
+        let vector = crate::test_vectors::mrt_updates().remove(0);
+        let record = crate::parse_mrt_record(&mut vector.bytes.as_slice()).unwrap();
+        timestamps
+            .iter()
+            .map(|(timestamp, microseconds)| {
+                let mut record = record.clone();
+                record.common_header.timestamp = *timestamp;
+                record.common_header.microsecond_timestamp = *microseconds;
+                record.common_header.entry_type = match microseconds {
+                    Some(_) => EntryType::BGP4MP_ET,
+                    None => EntryType::BGP4MP,
+                };
+                record
+            })
+            .collect()
+    }
+
+    fn timestamps(records: impl IntoIterator<Item = MrtRecord>) -> Vec<(u32, Option<u32>)> {
+        records
+            .into_iter()
+            .map(|r| {
+                (
+                    r.common_header.timestamp,
+                    r.common_header.microsecond_timestamp,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_records_stable() {
+        let extended = records(&[(100, Some(300000)), (100, Some(800000)), (102, Some(0))]);
+        let plain = records(&[(99, None), (100, None), (101, None)]);
+        let merged = merge_records_stable(vec![extended, plain]);
+        assert_eq!(
+            timestamps(merged),
+            vec![
+                (99, None),
+                (100, Some(300000)),
+                (100, Some(800000)),
+                (100, None),
+                (101, None),
+                (102, Some(0)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "oneio")]
+    #[test]
+    fn test_merge_mrt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let write = |name: &str, records: Vec<MrtRecord>| {
+            let bytes: Vec<u8> = records.iter().flat_map(|r| r.encode()).collect();
+            std::fs::write(path(name), bytes).unwrap();
+        };
+        write("a.mrt", records(&[(100, Some(500000)), (102, Some(0))]));
+        write("b.mrt", records(&[(100, None), (101, None)]));
+
+        let output = path("merged.mrt.gz");
+        let count = merge_mrt_files(&[&path("a.mrt"), &path("b.mrt")], &output).unwrap();
+        assert_eq!(count, 4);
+        let merged = BgpkitParser::new(&output).unwrap().into_record_iter();
+        assert_eq!(
+            timestamps(merged),
+            vec![
+                (100, Some(500000)),
+                (100, None),
+                (101, None),
+                (102, Some(0))
+            ]
+        );
+    }
+}
@@ -0,0 +1,477 @@
+/*!
+Approximate distinct counting and heavy hitters for streams of BGP elems.
+
+Exact distinct counting of prefixes, origins, or AS paths across many large MRT files requires
+keeping every distinct value in memory. [HyperLogLog] estimates the number of distinct values
+with a fixed amount of memory (`2^precision` bytes) and a typical relative error of
+`1.04 / sqrt(2^precision)`. [ApproxElemStats] wraps a set of counters for BGP elems.
+
+```no_run
+use bgpkit_parser::{ApproxElemStats, BgpkitParser};
+
+let mut stats = ApproxElemStats::new();
+for file in ["https://spaces.bgpkit.org/parser/update-example.gz"] {
+    stats.extend(BgpkitParser::new(file).unwrap());
+}
+println!("~{} unique prefixes", stats.prefixes());
+```
+
+[TopK] finds the most frequent values, such as the peers or origins with the most elems, with at
+most `k` counters using the space-saving algorithm.
+
+```no_run
+use bgpkit_parser::{BgpkitParser, TopK};
+
+let parser = BgpkitParser::new("https://spaces.bgpkit.org/parser/update-example.gz").unwrap();
+let top = TopK::new(10).consume(parser.into_elem_iter().map(|elem| elem.peer_ip));
+for (peer_ip, count) in top.top() {
+    println!("{} ~{} elems", peer_ip, count.count);
+}
+```
+*/
+use crate::models::BgpElem;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+
+/// Default precision for [HyperLogLog], using 16 KiB of registers with about 0.8% standard error.
+pub const DEFAULT_HLL_PRECISION: u8 = 14;
+
+/// HyperLogLog distinct value estimator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_HLL_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    /// Create a new estimator with `2^precision` registers.
+    ///
+    /// The precision is clamped to the range `4..=18`.
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(4, 18);
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Precision of the estimator.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// Add a value to the estimator.
+    pub fn insert<T: Hash + ?Sized>(&mut self, value: &T) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        self.insert_hash(hasher.finish());
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // set a guard bit so that the rank never exceeds 64 - p + 1
+        let remaining = (hash << p) | (1 << (p - 1));
+        let rank = remaining.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Merge another estimator into this one.
+    ///
+    /// Both estimators must have the same precision, otherwise `false` is returned and this
+    /// estimator is unchanged.
+    pub fn merge(&mut self, other: &HyperLogLog) -> bool {
+        if self.precision != other.precision {
+            return false;
+        }
+        for (a, b) in self.registers.iter_mut().zip(other.registers.iter()) {
+            if *b > *a {
+                *a = *b;
+            }
+        }
+        true
+    }
+
+    /// Estimated number of distinct values inserted.
+    pub fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for &r in &self.registers {
+            sum += 1.0 / (1u64 << r) as f64;
+            if r == 0 {
+                zeros += 1;
+            }
+        }
+        let estimate = alpha * m * m / sum;
+        if estimate <= 2.5 * m && zeros > 0 {
+            // small range correction with linear counting
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    /// Returns true if no value has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|r| *r == 0)
+    }
+}
+
+/// Bounded-memory statistics over a stream of [BgpElem]s.
+///
+/// The number of elems is counted exactly, while the numbers of unique prefixes, origin ASNs,
+/// and AS paths are estimated with [HyperLogLog].
+#[derive(Debug, Clone, Default)]
+pub struct ApproxElemStats {
+    elems: u64,
+    prefixes: HyperLogLog,
+    origins: HyperLogLog,
+    as_paths: HyperLogLog,
+}
+
+impl ApproxElemStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the statistics with the given [HyperLogLog] precision.
+    pub fn with_precision(precision: u8) -> Self {
+        ApproxElemStats {
+            elems: 0,
+            prefixes: HyperLogLog::new(precision),
+            origins: HyperLogLog::new(precision),
+            as_paths: HyperLogLog::new(precision),
+        }
+    }
+
+    /// Update the statistics with a BGP elem.
+    pub fn observe(&mut self, elem: &BgpElem) {
+        self.elems += 1;
+        self.prefixes.insert(&elem.prefix.prefix);
+        if let Some(origins) = &elem.origin_asns {
+            for origin in origins {
+                self.origins.insert(origin);
+            }
+        }
+        if let Some(path) = &elem.as_path {
+            self.as_paths.insert(path);
+        }
+    }
+
+    /// Merge statistics collected separately, e.g. by different threads.
+    ///
+    /// Returns `false` if the precisions differ, in which case nothing is merged.
+    pub fn merge(&mut self, other: &ApproxElemStats) -> bool {
+        if self.prefixes.precision() != other.prefixes.precision() {
+            return false;
+        }
+        self.elems += other.elems;
+        self.prefixes.merge(&other.prefixes);
+        self.origins.merge(&other.origins);
+        self.as_paths.merge(&other.as_paths);
+        true
+    }
+
+    /// Exact number of elems observed.
+    pub fn elems(&self) -> u64 {
+        self.elems
+    }
+
+    /// Estimated number of unique prefixes.
+    pub fn prefixes(&self) -> u64 {
+        self.prefixes.count()
+    }
+
+    /// Estimated number of unique origin ASNs.
+    pub fn origins(&self) -> u64 {
+        self.origins.count()
+    }
+
+    /// Estimated number of unique AS paths.
+    pub fn as_paths(&self) -> u64 {
+        self.as_paths.count()
+    }
+}
+
+impl Extend<BgpElem> for ApproxElemStats {
+    fn extend<T: IntoIterator<Item = BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.observe(&elem);
+        }
+    }
+}
+
+impl<'a> Extend<&'a BgpElem> for ApproxElemStats {
+    fn extend<T: IntoIterator<Item = &'a BgpElem>>(&mut self, iter: T) {
+        for elem in iter {
+            self.observe(elem);
+        }
+    }
+}
+
+impl FromIterator<BgpElem> for ApproxElemStats {
+    fn from_iter<T: IntoIterator<Item = BgpElem>>(iter: T) -> Self {
+        let mut stats = ApproxElemStats::new();
+        stats.extend(iter);
+        stats
+    }
+}
+
+/// Estimated count of a value tracked by [TopK].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopKCount {
+    /// estimated number of occurrences, never lower than the true number
+    pub count: u64,
+    /// maximum overestimation of `count`
+    pub error: u64,
+}
+
+/// Streaming top-k heavy hitters with the space-saving algorithm.
+///
+/// At most `k` values are tracked. When a new value arrives and all counters are in use, the value
+/// with the lowest count is replaced and the new value inherits its count as error. Any value
+/// occurring more than `n / k` times in a stream of `n` values is guaranteed to be tracked.
+///
+/// The value to replace is found with a min-heap keyed on `(count, value)`, so that among values
+/// with the same lowest count the smallest value is replaced and results do not depend on hashing.
+#[derive(Debug, Clone)]
+pub struct TopK<T> {
+    k: usize,
+    total: u64,
+    counters: HashMap<T, TopKCount>,
+    /// `(count, value)` of every counter update; entries whose count is no longer the count of
+    /// the value are outdated and skipped
+    heap: BinaryHeap<Reverse<(u64, T)>>,
+}
+
+impl<T: Hash + Ord + Clone> TopK<T> {
+    /// Create a tracker with `k` counters. `k` is at least 1.
+    pub fn new(k: usize) -> Self {
+        let k = k.max(1);
+        TopK {
+            k,
+            total: 0,
+            counters: HashMap::with_capacity(k),
+            heap: BinaryHeap::with_capacity(2 * k),
+        }
+    }
+
+    /// Add a value.
+    pub fn insert(&mut self, value: T) {
+        self.total += 1;
+        let counter = if let Some(counter) = self.counters.get_mut(&value) {
+            counter.count += 1;
+            *counter
+        } else {
+            let counter = if self.counters.len() < self.k {
+                TopKCount { count: 1, error: 0 }
+            } else {
+                // replace the value with the lowest count
+                let min_count = self.pop_min();
+                TopKCount {
+                    count: min_count + 1,
+                    error: min_count,
+                }
+            };
+            self.counters.insert(value.clone(), counter);
+            counter
+        };
+        self.heap.push(Reverse((counter.count, value)));
+
+        // drop outdated entries once they outnumber the counters
+        if self.heap.len() > 2 * self.k {
+            self.heap = self
+                .counters
+                .iter()
+                .map(|(v, c)| Reverse((c.count, v.clone())))
+                .collect();
+        }
+    }
+
+    /// Remove the tracked value with the lowest `(count, value)` and return its count.
+    fn pop_min(&mut self) -> u64 {
+        loop {
+            let Reverse((count, value)) = self
+                .heap
+                .pop()
+                .expect("every counter has an entry in the heap");
+            if self.counters.get(&value).map(|c| c.count) == Some(count) {
+                self.counters.remove(&value);
+                return count;
+            }
+        }
+    }
+
+    /// Add all values of an iterator and return the tracker.
+    pub fn consume<I: IntoIterator<Item = T>>(mut self, values: I) -> Self {
+        self.extend(values);
+        self
+    }
+
+    /// Total number of values added.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Tracked values sorted by descending estimated count, then by value.
+    pub fn top(&self) -> Vec<(T, TopKCount)> {
+        let mut top: Vec<_> = self.counters.iter().map(|(v, c)| (v.clone(), *c)).collect();
+        top.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+        top
+    }
+}
+
+impl<T: Hash + Ord + Clone> Extend<T> for TopK<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AsPath, Asn};
+
+    fn assert_close(estimate: u64, actual: u64, tolerance: f64) {
+        let error = (estimate as f64 - actual as f64).abs() / actual as f64;
+        assert!(
+            error < tolerance,
+            "estimate {} too far from {} ({:.4})",
+            estimate,
+            actual,
+            error
+        );
+    }
+
+    #[test]
+    fn test_hll_count() {
+        let mut hll = HyperLogLog::default();
+        assert!(hll.is_empty());
+        assert_eq!(hll.count(), 0);
+
+        for i in 0..100u32 {
+            hll.insert(&i);
+            // duplicates do not change the estimate
+            hll.insert(&i);
+        }
+        assert_close(hll.count(), 100, 0.05);
+
+        for i in 0..200_000u32 {
+            hll.insert(&i);
+        }
+        assert_close(hll.count(), 200_000, 0.03);
+    }
+
+    #[test]
+    fn test_hll_merge() {
+        let mut a = HyperLogLog::new(12);
+        let mut b = HyperLogLog::new(12);
+        for i in 0..50_000u32 {
+            a.insert(&i);
+        }
+        for i in 25_000..75_000u32 {
+            b.insert(&i);
+        }
+        assert!(a.merge(&b));
+        assert_close(a.count(), 75_000, 0.05);
+
+        assert!(!a.merge(&HyperLogLog::new(10)));
+    }
+
+    #[test]
+    fn test_approx_elem_stats() {
+        let mut elems = vec![];
+        for i in 0..1000u32 {
+            let mut elem = BgpElem {
+                origin_asns: Some(vec![Asn::new_32bit(i % 10)].into()),
+                as_path: Some(AsPath::from_sequence([65000, i % 100])),
+                ..Default::default()
+            };
+            elem.prefix.prefix = format!("10.{}.{}.0/24", i / 256, i % 256).parse().unwrap();
+            elems.push(elem);
+        }
+
+        let mut stats = ApproxElemStats::new();
+        stats.extend(&elems);
+        assert_eq!(stats.elems(), 1000);
+        assert_close(stats.prefixes(), 1000, 0.05);
+        assert_close(stats.origins(), 10, 0.2);
+        assert_close(stats.as_paths(), 100, 0.05);
+
+        let other: ApproxElemStats = elems.into_iter().collect();
+        assert!(stats.merge(&other));
+        assert_eq!(stats.elems(), 2000);
+        assert_close(stats.prefixes(), 1000, 0.05);
+    }
+
+    #[test]
+    fn test_top_k() {
+        // exact while there are at most k distinct values
+        let top = TopK::new(3).consume([1, 2, 2, 3, 3, 3]);
+        assert_eq!(top.total(), 6);
+        assert_eq!(
+            top.top(),
+            vec![
+                (3, TopKCount { count: 3, error: 0 }),
+                (2, TopKCount { count: 2, error: 0 }),
+                (1, TopKCount { count: 1, error: 0 }),
+            ]
+        );
+
+        // heavy hitters survive a long tail of distinct values
+        let mut top = TopK::new(10);
+        for i in 0..10_000u32 {
+            top.insert(i % 3);
+            top.insert(1000 + i);
+        }
+        let top = top.top();
+        assert_eq!(top.len(), 10);
+        let mut heavy: Vec<u32> = top[..3].iter().map(|(v, _)| *v).collect();
+        heavy.sort();
+        assert_eq!(heavy, vec![0, 1, 2]);
+        for (_, count) in &top[..3] {
+            assert!(count.count >= 3333 && count.count - count.error <= 3334);
+        }
+    }
+
+    #[test]
+    fn test_top_k_ties() {
+        // among the values with the lowest count, the smallest is replaced
+        let top = TopK::new(2).consume([3, 1, 2]);
+        assert_eq!(
+            top.top(),
+            vec![
+                (2, TopKCount { count: 2, error: 1 }),
+                (3, TopKCount { count: 1, error: 0 }),
+            ]
+        );
+
+        let top = TopK::new(2).consume([5, 4, 4, 5]);
+        assert_eq!(
+            top.top(),
+            vec![
+                (4, TopKCount { count: 2, error: 0 }),
+                (5, TopKCount { count: 2, error: 0 }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Elems for the tests of the modules processing elem streams.
+use crate::models::*;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// ASN of the peers of the test elems.
+pub(crate) const PEER_ASN: u32 = 64496;
+
+/// Announcement of `prefix` by `peer` with the AS path `path` and the origins of the path.
+pub(crate) fn announce(peer: &str, prefix: &str, path: &[u32]) -> BgpElem {
+    let as_path = AsPath::from_sequence(path);
+    let origins: AsnSet = as_path.iter_origins().collect();
+    BgpElem {
+        elem_type: ElemType::ANNOUNCE,
+        peer_ip: IpAddr::from_str(peer).unwrap(),
+        peer_asn: Asn::new_32bit(PEER_ASN),
+        prefix: NetworkPrefix::from_str(prefix).unwrap(),
+        origin_asns: (!origins.is_empty()).then_some(origins),
+        as_path: Some(as_path),
+        ..Default::default()
+    }
+}
+
+/// Withdrawal of `prefix` by `peer`.
+pub(crate) fn withdraw(peer: &str, prefix: &str) -> BgpElem {
+    BgpElem {
+        elem_type: ElemType::WITHDRAW,
+        peer_ip: IpAddr::from_str(peer).unwrap(),
+        peer_asn: Asn::new_32bit(PEER_ASN),
+        prefix: NetworkPrefix::from_str(prefix).unwrap(),
+        ..Default::default()
+    }
+}
+
+impl BgpElem {
+    pub(crate) fn at(mut self, timestamp: f64) -> BgpElem {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub(crate) fn with_peer_asn(mut self, peer_asn: u32) -> BgpElem {
+        self.peer_asn = Asn::new_32bit(peer_asn);
+        self
+    }
+
+    pub(crate) fn with_path_id(mut self, path_id: u32) -> BgpElem {
+        self.prefix.path_id = path_id;
+        self
+    }
+
+    pub(crate) fn with_next_hop(mut self, next_hop: &str) -> BgpElem {
+        self.next_hop = Some(IpAddr::from_str(next_hop).unwrap());
+        self
+    }
+
+    pub(crate) fn with_med(mut self, med: u32) -> BgpElem {
+        self.med = Some(med);
+        self
+    }
+
+    pub(crate) fn with_origins(mut self, origins: &[u32]) -> BgpElem {
+        self.origin_asns = Some(origins.iter().map(|asn| Asn::new_32bit(*asn)).collect());
+        self
+    }
+}
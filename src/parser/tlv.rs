@@ -0,0 +1,268 @@
+/*!
+Shared reader for type-length-value encoded fields.
+
+[TlvReader] splits a buffer into [Tlv]s with bounds checking, leaving the interpretation of each
+value to the caller. TLVs of unknown types can be retained verbatim by keeping the raw [Tlv].
+
+The value of a [Tlv] is a slice of the buffer given to the reader, and keeps the whole buffer
+alive as long as it exists. Callers retaining small values long after the message was parsed can
+have them copied with [TlvReader::copy_values_up_to].
+
+```
+use bgpkit_parser::parser::TlvReader;
+use bytes::Bytes;
+
+let data = Bytes::from_static(&[0, 1, 0, 2, b'h', b'i', 0, 9, 0, 0]);
+let tlvs: Vec<_> = TlvReader::new(data).collect::<Result<_, _>>().unwrap();
+assert_eq!(tlvs.len(), 2);
+assert_eq!(tlvs[0].tlv_type, 1);
+assert_eq!(tlvs[0].value.as_ref(), b"hi");
+assert!(tlvs[1].value.is_empty());
+```
+*/
+use crate::error::ParserError;
+use crate::parser::ReadUtils;
+use bytes::{Buf, Bytes};
+
+/// A raw TLV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    pub tlv_type: u16,
+    pub value: Bytes,
+}
+
+/// Width of the type and length fields of a TLV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlvFieldWidth {
+    /// 1-octet fields, e.g. BGP capabilities
+    U8,
+    /// 2-octet fields, e.g. BMP information TLVs and BGP-LS TLVs
+    U16,
+}
+
+impl TlvFieldWidth {
+    const fn len(&self) -> usize {
+        match self {
+            TlvFieldWidth::U8 => 1,
+            TlvFieldWidth::U16 => 2,
+        }
+    }
+}
+
+/// Iterator over the TLVs of a buffer.
+///
+/// Each item is either a complete TLV, or an error if the remaining bytes are too short for the
+/// TLV header or the declared value length. The iterator ends after the first error, and the
+/// unread bytes remain available through [TlvReader::remaining].
+pub struct TlvReader {
+    data: Bytes,
+    type_width: TlvFieldWidth,
+    length_width: TlvFieldWidth,
+    failed: bool,
+    copy_max_len: usize,
+    extra_header_len: usize,
+    allow_partial_header: bool,
+}
+
+impl TlvReader {
+    /// Create a reader for TLVs with 2-octet type and 2-octet length fields.
+    pub fn new(data: Bytes) -> Self {
+        Self::with_widths(data, TlvFieldWidth::U16, TlvFieldWidth::U16)
+    }
+
+    /// Create a reader for TLVs with the given type and length field widths.
+    pub fn with_widths(
+        data: Bytes,
+        type_width: TlvFieldWidth,
+        length_width: TlvFieldWidth,
+    ) -> Self {
+        TlvReader {
+            data,
+            type_width,
+            length_width,
+            failed: false,
+            copy_max_len: 0,
+            extra_header_len: 0,
+            allow_partial_header: false,
+        }
+    }
+
+    /// Read `len` bytes following the length field that are not counted by it, such as the index
+    /// of BMP route monitoring TLVs. They are returned at the start of the value.
+    pub fn with_extra_header(mut self, len: usize) -> Self {
+        self.extra_header_len = len;
+        self
+    }
+
+    /// End without an error if the remaining bytes are too short for a TLV header, e.g. padding at
+    /// the end of a message. A TLV whose value is truncated is still an error, unlike with
+    /// [TlvReader::lenient].
+    pub fn allow_partial_header(mut self) -> Self {
+        self.allow_partial_header = true;
+        self
+    }
+
+    /// Copy the values of up to `max_len` bytes into their own allocations instead of slicing the
+    /// buffer, so that retaining them does not keep the whole buffer alive. Values are not copied
+    /// by default.
+    pub fn copy_values_up_to(mut self, max_len: usize) -> Self {
+        self.copy_max_len = max_len;
+        self
+    }
+
+    /// Bytes that have not been read yet.
+    pub fn remaining(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Iterate over the complete TLVs, stopping silently at the first truncated TLV. This is
+    /// useful for best-effort parsing where trailing garbage should not fail the whole message.
+    pub fn lenient(self) -> impl Iterator<Item = Tlv> {
+        self.map_while(Result::ok)
+    }
+
+    fn read_field(&mut self, width: TlvFieldWidth) -> Result<u16, ParserError> {
+        match width {
+            TlvFieldWidth::U8 => self.data.read_u8().map(u16::from),
+            TlvFieldWidth::U16 => self.data.read_u16(),
+        }
+    }
+
+    fn header_len(&self) -> usize {
+        self.type_width.len() + self.length_width.len() + self.extra_header_len
+    }
+
+    fn read_tlv(&mut self) -> Result<Tlv, ParserError> {
+        self.data.has_n_remaining(self.header_len())?;
+        let tlv_type = self.read_field(self.type_width)?;
+        let length = self.read_field(self.length_width)? as usize + self.extra_header_len;
+        self.data.has_n_remaining(length)?;
+        let value = self.data.split_to(length);
+        let value = match length <= self.copy_max_len {
+            true => Bytes::copy_from_slice(&value),
+            false => value,
+        };
+        Ok(Tlv { tlv_type, value })
+    }
+}
+
+impl Iterator for TlvReader {
+    type Item = Result<Tlv, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || !self.data.has_remaining() {
+            return None;
+        }
+        if self.allow_partial_header && self.data.remaining() < self.header_len() {
+            return None;
+        }
+
+        // do not consume any bytes of a truncated TLV
+        let checkpoint = self.data.clone();
+        match self.read_tlv() {
+            Ok(tlv) => Some(Ok(tlv)),
+            Err(e) => {
+                self.data = checkpoint;
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tlv_reader() {
+        let data = Bytes::from_static(&[0, 1, 0, 1, 7, 0, 2, 0, 0, 0, 3, 0, 5, 1]);
+        let mut reader = TlvReader::new(data);
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            Tlv {
+                tlv_type: 1,
+                value: Bytes::from_static(&[7]),
+            }
+        );
+        assert_eq!(reader.next().unwrap().unwrap().tlv_type, 2);
+        // declared length exceeds the remaining bytes
+        assert!(matches!(
+            reader.next().unwrap(),
+            Err(ParserError::TruncatedMsg(_))
+        ));
+        assert!(reader.next().is_none());
+        assert_eq!(reader.remaining().as_ref(), &[0, 3, 0, 5, 1]);
+    }
+
+    #[test]
+    fn test_tlv_reader_lenient() {
+        // incomplete header after the first TLV
+        let data = Bytes::from_static(&[2, 1, 9, 3]);
+        let tlvs: Vec<Tlv> = TlvReader::with_widths(data, TlvFieldWidth::U8, TlvFieldWidth::U8)
+            .lenient()
+            .collect();
+        assert_eq!(
+            tlvs,
+            vec![Tlv {
+                tlv_type: 2,
+                value: Bytes::from_static(&[9]),
+            }]
+        );
+
+        assert_eq!(TlvReader::new(Bytes::new()).count(), 0);
+    }
+
+    #[test]
+    fn test_tlv_reader_partial_header() {
+        // incomplete header after the first TLV
+        let data = Bytes::from_static(&[0, 1, 0, 1, 7, 0, 2, 0]);
+        let tlvs: Vec<_> = TlvReader::new(data)
+            .allow_partial_header()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tlvs.len(), 1);
+
+        // truncated value
+        let data = Bytes::from_static(&[0, 1, 0, 1, 7, 0, 2, 0, 2, 1]);
+        let mut reader = TlvReader::new(data).allow_partial_header();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_tlv_reader_extra_header() {
+        // 2-octet index between the length and the value
+        let data = Bytes::from_static(&[0, 1, 0, 1, 0, 3, 7, 0, 2, 0, 0, 0, 0]);
+        let tlvs: Vec<_> = TlvReader::new(data)
+            .with_extra_header(2)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tlvs[0].value.as_ref(), &[0, 3, 7]);
+        assert_eq!(tlvs[1].value.as_ref(), &[0, 0]);
+
+        let data = Bytes::from_static(&[0, 1, 0, 1, 0]);
+        assert!(TlvReader::new(data)
+            .with_extra_header(2)
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn test_tlv_reader_copy_values() {
+        let data = Bytes::from(vec![0, 1, 0, 1, 7, 0, 2, 0, 3, 1, 2, 3]);
+        let tlvs: Vec<Tlv> = TlvReader::new(data.clone())
+            .copy_values_up_to(1)
+            .lenient()
+            .collect();
+        assert_eq!(tlvs[0].value.as_ref(), &[7]);
+        assert_eq!(tlvs[1].value.as_ref(), &[1, 2, 3]);
+
+        // only the second value is a slice of the buffer
+        let large = tlvs.into_iter().nth(1).unwrap();
+        assert!(!data.is_unique());
+        drop(large);
+        assert!(data.is_unique());
+    }
+}
@@ -10,6 +10,7 @@ use std::{
 
 use crate::error::ParserError;
 use crate::models::*;
+use crate::parser::ParseContext;
 use crate::ParserError::TruncatedMsg;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use log::debug;
@@ -33,6 +34,15 @@ pub trait ReadUtils: Buf {
         }
     }
 
+    /// Skip `n` bytes. Unlike [Buf::advance], this returns an error instead of panicking if fewer
+    /// than `n` bytes remain.
+    #[inline]
+    fn skip_n_bytes(&mut self, n: usize) -> Result<(), ParserError> {
+        self.has_n_remaining(n)?;
+        self.advance(n);
+        Ok(())
+    }
+
     #[inline]
     fn read_u8(&mut self) -> Result<u8, ParserError> {
         self.has_n_remaining(1)?;
@@ -153,61 +163,22 @@ pub trait ReadUtils: Buf {
     /// The length in bits is 1 byte, and then based on the IP version it reads different number of bytes.
     /// If the `add_path` is true, it will also first read a 4-byte path id first; otherwise, a path-id of 0
     /// is automatically set.
+    ///
+    /// The prefix is decoded in place from the first chunk of the buffer, which for [Bytes] is all
+    /// of its data. If the first chunk is shorter than the prefix could be and more data follows,
+    /// the prefix is copied out of the chunks field by field instead.
     fn read_nlri_prefix(
         &mut self,
         afi: &Afi,
         add_path: bool,
     ) -> Result<NetworkPrefix, ParserError> {
-        let path_id = if add_path { self.read_u32()? } else { 0 };
-
-        // Length in bits
-        let bit_len = self.read_u8()?;
-
-        // Convert to bytes
-        let byte_len: usize = (bit_len as usize + 7) / 8;
-        let addr: IpAddr = match afi {
-            Afi::Ipv4 => {
-                // 4 bytes -- u32
-                if byte_len > 4 {
-                    return Err(ParserError::ParseError(format!(
-                        "Invalid byte length for IPv4 prefix. byte_len: {}, bit_len: {}",
-                        byte_len, bit_len
-                    )));
-                }
-                let mut buff = [0; 4];
-                self.has_n_remaining(byte_len)?;
-                for i in 0..byte_len {
-                    buff[i] = self.get_u8();
-                }
-                IpAddr::V4(Ipv4Addr::from(buff))
-            }
-            Afi::Ipv6 => {
-                // 16 bytes
-                if byte_len > 16 {
-                    return Err(ParserError::ParseError(format!(
-                        "Invalid byte length for IPv6 prefix. byte_len: {}, bit_len: {}",
-                        byte_len, bit_len
-                    )));
-                }
-                self.has_n_remaining(byte_len)?;
-                let mut buff = [0; 16];
-                for i in 0..byte_len {
-                    buff[i] = self.get_u8();
-                }
-                IpAddr::V6(Ipv6Addr::from(buff))
-            }
-        };
-        let prefix = match IpNet::new(addr, bit_len) {
-            Ok(p) => p,
-            Err(_) => {
-                return Err(ParserError::ParseError(format!(
-                    "Invalid network prefix length: {}",
-                    bit_len
-                )))
-            }
-        };
-
-        Ok(NetworkPrefix::new(prefix, path_id))
+        let chunk_len = self.chunk().len();
+        if chunk_len < MAX_NLRI_PREFIX_LEN && chunk_len < self.remaining() {
+            return read_nlri_prefix_copied(self, afi, add_path);
+        }
+        let (prefix, len) = decode_nlri_prefix(self.chunk(), afi, add_path)?;
+        self.advance(len);
+        Ok(prefix)
     }
 
     fn read_n_bytes(&mut self, n_bytes: usize) -> Result<Vec<u8>, ParserError> {
@@ -224,50 +195,170 @@ pub trait ReadUtils: Buf {
     }
 }
 
-pub fn parse_nlri_list(
-    mut input: Bytes,
+/// Number of address bytes of an NLRI prefix, by prefix length in bits.
+const PREFIX_BYTES: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut bits = 0;
+    while bits < 256 {
+        table[bits] = bits.div_ceil(8) as u8;
+        bits += 1;
+    }
+    table
+};
+
+/// Maximum number of bytes of an NLRI prefix: path ID, length and IPv6 address.
+const MAX_NLRI_PREFIX_LEN: usize = 4 + 1 + 16;
+
+/// Read an NLRI prefix that may span multiple chunks of the buffer by copying its fields.
+fn read_nlri_prefix_copied<B: ReadUtils + ?Sized>(
+    input: &mut B,
+    afi: &Afi,
     add_path: bool,
+) -> Result<NetworkPrefix, ParserError> {
+    let mut data = [0; MAX_NLRI_PREFIX_LEN];
+    let mut len = 0;
+    if add_path {
+        input.has_n_remaining(4)?;
+        input.copy_to_slice(&mut data[..4]);
+        len = 4;
+    }
+    input.has_n_remaining(1)?;
+    let bit_len = input.get_u8();
+    data[len] = bit_len;
+    len += 1;
+    // invalid lengths are reported by the decoding below without reading the address
+    let byte_len = PREFIX_BYTES[bit_len as usize] as usize;
+    let max_byte_len = match afi {
+        Afi::Ipv4 => 4,
+        Afi::Ipv6 => 16,
+    };
+    if byte_len <= max_byte_len {
+        input.has_n_remaining(byte_len)?;
+        input.copy_to_slice(&mut data[len..len + byte_len]);
+        len += byte_len;
+    }
+    decode_nlri_prefix(&data[..len], afi, add_path).map(|(prefix, _)| prefix)
+}
+
+/// Decode an NLRI prefix from the start of the slice, returning it with the number of bytes it
+/// takes.
+///
+/// See [ReadUtils::read_nlri_prefix] for the encoding.
+#[inline]
+fn decode_nlri_prefix(
+    data: &[u8],
     afi: &Afi,
-) -> Result<Vec<NetworkPrefix>, ParserError> {
-    let mut is_add_path = add_path;
-    let mut prefixes = vec![];
+    add_path: bool,
+) -> Result<(NetworkPrefix, usize), ParserError> {
+    let (path_id, pos) = match add_path {
+        true => match data.get(..4) {
+            Some(b) => (u32::from_be_bytes([b[0], b[1], b[2], b[3]]), 4),
+            None => return Err(truncated(data.len(), 4)),
+        },
+        false => (0, 0),
+    };
+    let bit_len = match data.get(pos) {
+        Some(b) => *b,
+        None => return Err(truncated(data.len(), pos + 1)),
+    };
+    let byte_len = PREFIX_BYTES[bit_len as usize] as usize;
+    let (max_byte_len, version) = match afi {
+        Afi::Ipv4 => (4, "IPv4"),
+        Afi::Ipv6 => (16, "IPv6"),
+    };
+    if byte_len > max_byte_len {
+        return Err(ParserError::ParseError(format!(
+            "Invalid byte length for {} prefix. byte_len: {}, bit_len: {}",
+            version, byte_len, bit_len
+        )));
+    }
+    let addr_bytes = match data.get(pos + 1..pos + 1 + byte_len) {
+        Some(b) => b,
+        None => return Err(truncated(data.len() - pos - 1, byte_len)),
+    };
+    let prefix = match afi {
+        Afi::Ipv4 => {
+            let mut buff = [0; 4];
+            buff[..byte_len].copy_from_slice(addr_bytes);
+            Ipv4Net::new(Ipv4Addr::from(buff), bit_len).map(IpNet::V4)
+        }
+        Afi::Ipv6 => {
+            let mut buff = [0; 16];
+            buff[..byte_len].copy_from_slice(addr_bytes);
+            Ipv6Net::new(Ipv6Addr::from(buff), bit_len).map(IpNet::V6)
+        }
+    };
+    match prefix {
+        Ok(prefix) => Ok((NetworkPrefix::new(prefix, path_id), pos + 1 + byte_len)),
+        Err(_) => Err(ParserError::ParseError(format!(
+            "Invalid network prefix length: {}",
+            bit_len
+        ))),
+    }
+}
 
-    let mut retry = false;
-    let mut guessed = false;
+fn truncated(remaining: usize, required: usize) -> ParserError {
+    TruncatedMsg(format!(
+        "not enough bytes to read. remaining: {}, required: {}",
+        remaining, required
+    ))
+}
 
-    let mut input_copy = None;
+/// Parse a list of NLRI prefixes.
+///
+/// The prefixes are decoded in one pass over the bytes, without splitting them per prefix.
+pub fn parse_nlri_list(
+    input: Bytes,
+    add_path: bool,
+    afi: &Afi,
+) -> Result<Vec<NetworkPrefix>, ParserError> {
+    parse_nlri_list_with_context(input, add_path, afi, &mut ParseContext::new())
+}
 
-    while input.remaining() > 0 {
-        if !is_add_path && input[0] == 0 {
+/// Parse a list of NLRI prefixes with the buffers and settings of the given [ParseContext].
+pub(crate) fn parse_nlri_list_with_context(
+    input: Bytes,
+    add_path: bool,
+    afi: &Afi,
+    context: &mut ParseContext,
+) -> Result<Vec<NetworkPrefix>, ParserError> {
+    let data = input.as_ref();
+    // most prefixes take at least 4 bytes, e.g. /24s
+    let mut prefixes = context.vecs.take_prefixes(data.len() / 4);
+    let mut is_add_path = add_path;
+    // position and number of prefixes read when add-path was guessed, to retry from there without
+    // add-path if it was wrong
+    let mut guessed_at = None;
+
+    let mut pos = 0;
+    while pos < data.len() {
+        context.record_step()?;
+        if !is_add_path && data[pos] == 0 {
             // it's likely that this is a add-path wrongfully wrapped in non-add-path msg
             debug!("not add-path but with NLRI size to be 0, likely add-path msg in wrong msg type, treat as add-path now");
-            // cloning the data bytes
             is_add_path = true;
-            guessed = true;
-            input_copy = Some(input.clone());
+            guessed_at = Some((pos, prefixes.len()));
         }
-        let prefix = match input.read_nlri_prefix(afi, is_add_path) {
-            Ok(p) => p,
-            Err(e) => {
-                if guessed {
-                    retry = true;
+        match decode_nlri_prefix(&data[pos..], afi, is_add_path) {
+            Ok((prefix, len)) => {
+                prefixes.push(prefix);
+                pos += len;
+            }
+            Err(e) => match guessed_at {
+                Some((start, count)) => {
+                    // try again without attempt to guess add-path
+                    prefixes.truncate(count);
+                    pos = start;
+                    while pos < data.len() {
+                        context.record_step()?;
+                        let (prefix, len) = decode_nlri_prefix(&data[pos..], afi, add_path)?;
+                        prefixes.push(prefix);
+                        pos += len;
+                    }
                     break;
-                } else {
-                    return Err(e);
                 }
-            }
-        };
-        prefixes.push(prefix);
-    }
-
-    if retry {
-        prefixes.clear();
-        // try again without attempt to guess add-path
-        // if we reach here (retry==true), input_copy must be Some
-        let mut input_2 = input_copy.unwrap();
-        while input_2.remaining() > 0 {
-            let prefix = input_2.read_nlri_prefix(afi, add_path)?;
-            prefixes.push(prefix);
+                None => return Err(e),
+            },
         }
     }
 
@@ -389,12 +480,18 @@ impl ComparableRegex {
     pub fn is_match<S: AsRef<str>>(&self, text: S) -> bool {
         self.regex.is_match(text.as_ref())
     }
+
+    /// The pattern the regex was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use bytes::Bytes;
+    use std::str::FromStr;
 
     #[test]
     fn test_read_u8() {
@@ -570,6 +667,81 @@ mod tests {
         assert_eq!(buf.read_nlri_prefix(&Afi::Ipv4, true).unwrap(), expected);
     }
 
+    impl ReadUtils for bytes::buf::Chain<Bytes, Bytes> {}
+
+    #[test]
+    fn test_read_nlri_prefix_across_chunks() {
+        let expected = NetworkPrefix::new(IpNet::from_str("192.168.1.0/24").unwrap(), 1);
+        let mut buf = Bytes::from_static(&[0x00, 0x00, 0x00, 0x01, 0x18, 0xC0])
+            .chain(Bytes::from_static(&[0xA8, 0x01, 0x10]));
+        assert_eq!(buf.read_nlri_prefix(&Afi::Ipv4, true).unwrap(), expected);
+        assert_eq!(buf.remaining(), 1);
+
+        let mut buf = Bytes::from_static(&[0x18, 0xC0]).chain(Bytes::from_static(&[0xA8]));
+        assert!(matches!(
+            buf.read_nlri_prefix(&Afi::Ipv4, false),
+            Err(TruncatedMsg(_))
+        ));
+        let mut buf = Bytes::from_static(&[0x21]).chain(Bytes::from_static(&[0xC0, 0xA8]));
+        assert!(matches!(
+            buf.read_nlri_prefix(&Afi::Ipv4, false),
+            Err(ParserError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_nlri_list() {
+        let v4 = |s: &str, path_id: u32| NetworkPrefix::new(IpNet::from_str(s).unwrap(), path_id);
+        let data = Bytes::from_static(&[8, 10, 24, 192, 0, 2, 32, 192, 0, 2, 1]);
+        assert_eq!(
+            parse_nlri_list(data, false, &Afi::Ipv4).unwrap(),
+            vec![
+                v4("10.0.0.0/8", 0),
+                v4("192.0.2.0/24", 0),
+                v4("192.0.2.1/32", 0)
+            ]
+        );
+
+        let data = Bytes::from_static(&[0, 0, 0, 1, 24, 192, 0, 2, 0, 0, 0, 2, 8, 10]);
+        let expected = vec![v4("192.0.2.0/24", 1), v4("10.0.0.0/8", 2)];
+        assert_eq!(
+            parse_nlri_list(data.clone(), true, &Afi::Ipv4).unwrap(),
+            expected
+        );
+        // add-path guessed from the zero length of the first prefix
+        assert_eq!(parse_nlri_list(data, false, &Afi::Ipv4).unwrap(), expected);
+
+        // wrongly guessed add-path from a default route after another prefix
+        let data = Bytes::from_static(&[1, 0, 0]);
+        assert_eq!(
+            parse_nlri_list(data, false, &Afi::Ipv4).unwrap(),
+            vec![v4("0.0.0.0/1", 0), v4("0.0.0.0/0", 0)]
+        );
+
+        let data = Bytes::from_static(&[
+            48, 0x20, 0x01, 0x0d, 0xb8, 0, 1, 64, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1,
+        ]);
+        assert_eq!(
+            parse_nlri_list(data, false, &Afi::Ipv6).unwrap(),
+            vec![
+                NetworkPrefix::new(IpNet::from_str("2001:db8:1::/48").unwrap(), 0),
+                NetworkPrefix::new(IpNet::from_str("2001:db8:0:1::/64").unwrap(), 0),
+            ]
+        );
+
+        // truncated address and invalid length
+        let data = Bytes::from_static(&[24, 192, 0]);
+        assert!(matches!(
+            parse_nlri_list(data, false, &Afi::Ipv4),
+            Err(ParserError::TruncatedMsg(_))
+        ));
+        let data = Bytes::from_static(&[33, 192, 0, 2, 1, 0]);
+        assert!(matches!(
+            parse_nlri_list(data, false, &Afi::Ipv4),
+            Err(ParserError::ParseError(_))
+        ));
+    }
+
     #[test]
     fn test_encode_asn() {
         let asn = Asn::new_32bit(1);
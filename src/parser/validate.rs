@@ -0,0 +1,576 @@
+/*!
+MRT file integrity checks.
+
+[check_file] and [check_reader] scan MRT data record by record and report the problems found,
+each with the byte offset of the record it was found in:
+
+- records truncated by the end of the file, and record lengths impossible for their MRT type;
+- records failing to parse, and records of unknown or unsupported MRT types;
+- timestamps going backwards;
+- table dump v2 RIB entries before any peer index table, or referencing peers missing from it;
+- path attributes that are repeated, have flags not matching their type, or mandatory attributes
+  missing from announcements.
+
+Unlike the parser iterators, the scan does not stop at the first broken record. It only stops
+when the framing of the following records can no longer be trusted, e.g. at a truncated record.
+
+```no_run
+use bgpkit_parser::validate::check_file;
+
+let report = check_file("updates.20240101.0000.bz2").unwrap();
+for issue in &report.issues {
+    println!("{}", issue);
+}
+println!("{}", report);
+```
+*/
+use crate::error::ParserError;
+use crate::models::*;
+use crate::parser::mrt::mrt_record::parse_mrt_body;
+use bytes::Bytes;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// Maximum length of a BGP4MP record: the largest BGP message (RFC 8654) after the longest
+/// BGP4MP header, i.e. with 4-byte AS numbers and IPv6 addresses.
+const MAX_BGP4MP_LENGTH: u32 = 65535 + 44;
+
+/// Records longer than this are considered corrupted, and the scan stops at them.
+const MAX_RECORD_LENGTH: u32 = 64 * 1024 * 1024;
+
+/// Severity of a [ValidationIssue].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// the data is readable, but unusual or not standard-compliant
+    Warning,
+    /// the data is corrupted or can not be parsed
+    Error,
+}
+
+/// Kind of a [ValidationIssue].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IssueKind {
+    /// the data ends within a record header or body
+    TruncatedRecord,
+    /// the record length is impossible for its MRT type
+    InvalidLength,
+    /// the MRT type is unknown, or its subtype not supported by the parser
+    UnsupportedRecord,
+    /// the record body fails to parse
+    ParseError,
+    /// the record timestamp is earlier than the one of the previous record
+    TimestampOutOfOrder,
+    /// table dump v2 RIB entries come before any peer index table
+    MissingPeerIndexTable,
+    /// a RIB entry references a peer missing from the peer index table
+    PeerIndexMismatch,
+    /// a path attribute is repeated, has wrong flags, or a mandatory one is missing
+    AttributeWarning,
+}
+
+impl IssueKind {
+    pub const fn severity(&self) -> Severity {
+        match self {
+            IssueKind::UnsupportedRecord
+            | IssueKind::TimestampOutOfOrder
+            | IssueKind::AttributeWarning => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A problem found in an MRT record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationIssue {
+    /// byte offset of the record in the uncompressed data
+    pub offset: u64,
+    /// index of the record, starting from 0
+    pub record: u64,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub const fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "offset {} (record {}): {:?} {:?}: {}",
+            self.offset,
+            self.record,
+            self.severity(),
+            self.kind,
+            self.message
+        )
+    }
+}
+
+/// Result of the integrity checks of MRT data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    /// number of records scanned, including the truncated one if any
+    pub records: u64,
+    /// number of bytes scanned
+    pub bytes: u64,
+    /// issues in the order they were found
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Returns true if no errors were found. Warnings are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.errors() == 0
+    }
+
+    /// Number of issues with [Severity::Error].
+    pub fn errors(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.severity() == Severity::Error)
+            .count()
+    }
+
+    /// Number of issues with [Severity::Warning].
+    pub fn warnings(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.severity() == Severity::Warning)
+            .count()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} records, {} bytes: {} errors, {} warnings",
+            self.records,
+            self.bytes,
+            self.errors(),
+            self.warnings()
+        )
+    }
+}
+
+/// Check the integrity of an MRT file, local or remote, compressed or not.
+#[cfg(feature = "oneio")]
+pub fn check_file(path: &str) -> Result<ValidationReport, ParserError> {
+    let mut reader = oneio::get_reader(path)?;
+    Ok(check_reader(&mut reader))
+}
+
+/// Check the integrity of the MRT data of a reader.
+///
+/// I/O errors are reported as a truncated record, at which the scan stops.
+pub fn check_reader<R: Read>(reader: &mut R) -> ValidationReport {
+    let mut validator = Validator::default();
+    while validator.check_record(reader) {}
+    validator.report
+}
+
+#[derive(Default)]
+struct Validator {
+    report: ValidationReport,
+    /// offset of the current record
+    offset: u64,
+    /// timestamp and microseconds of the previous record
+    last_timestamp: Option<(u32, u32)>,
+    /// peer indexes of the last peer index table
+    peer_indexes: Option<HashSet<u16>>,
+}
+
+impl Validator {
+    fn issue(&mut self, kind: IssueKind, message: String) {
+        self.report.issues.push(ValidationIssue {
+            offset: self.offset,
+            record: self.report.records,
+            kind,
+            message,
+        });
+    }
+
+    /// Check the next record. Returns false at the end of the data, or if the following records
+    /// can not be located.
+    fn check_record<R: Read>(&mut self, reader: &mut R) -> bool {
+        let mut header = [0u8; 12];
+        let read = match read_up_to(reader, &mut header) {
+            Ok(0) => return false,
+            Ok(read) => read,
+            Err(e) => {
+                self.issue(IssueKind::TruncatedRecord, e.to_string());
+                return false;
+            }
+        };
+        self.report.bytes += read as u64;
+        if read < header.len() {
+            self.issue(
+                IssueKind::TruncatedRecord,
+                format!("data ends within the record header ({} of 12 bytes)", read),
+            );
+            self.report.records += 1;
+            return false;
+        }
+
+        let timestamp = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let entry_type = u16::from_be_bytes([header[4], header[5]]);
+        let entry_subtype = u16::from_be_bytes([header[6], header[7]]);
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+
+        if length > MAX_RECORD_LENGTH {
+            self.issue(
+                IssueKind::InvalidLength,
+                format!(
+                    "record length {} exceeds {} bytes, following records can not be located",
+                    length, MAX_RECORD_LENGTH
+                ),
+            );
+            self.report.records += 1;
+            return false;
+        }
+
+        let mut body = Vec::new();
+        let read = match reader.by_ref().take(length as u64).read_to_end(&mut body) {
+            Ok(read) => read,
+            Err(e) => {
+                self.issue(IssueKind::TruncatedRecord, e.to_string());
+                self.report.records += 1;
+                return false;
+            }
+        };
+        self.report.bytes += read as u64;
+        if read < length as usize {
+            self.issue(
+                IssueKind::TruncatedRecord,
+                format!(
+                    "data ends within the record body ({} of {} bytes)",
+                    read, length
+                ),
+            );
+            self.report.records += 1;
+            return false;
+        }
+
+        self.check_body(timestamp, entry_type, entry_subtype, Bytes::from(body));
+        self.report.records += 1;
+        self.offset = self.report.bytes;
+        true
+    }
+
+    fn check_body(&mut self, timestamp: u32, entry_type: u16, entry_subtype: u16, mut body: Bytes) {
+        let etype = match EntryType::try_from(entry_type) {
+            Ok(etype) => etype,
+            Err(_) => {
+                self.check_timestamp(timestamp, 0);
+                self.issue(
+                    IssueKind::UnsupportedRecord,
+                    format!("unknown MRT type {}", entry_type),
+                );
+                return;
+            }
+        };
+
+        let mut microseconds = 0;
+        if matches!(
+            etype,
+            EntryType::BGP4MP_ET | EntryType::ISIS_ET | EntryType::OSPFv3_ET
+        ) {
+            if body.len() < 4 {
+                self.issue(
+                    IssueKind::InvalidLength,
+                    format!(
+                        "extended timestamp record length {} is shorter than the microsecond timestamp",
+                        body.len()
+                    ),
+                );
+                return;
+            }
+            let usec = body.split_to(4);
+            microseconds = u32::from_be_bytes([usec[0], usec[1], usec[2], usec[3]]);
+        }
+        self.check_timestamp(timestamp, microseconds);
+
+        if matches!(etype, EntryType::BGP4MP | EntryType::BGP4MP_ET)
+            && body.len() as u32 > MAX_BGP4MP_LENGTH
+        {
+            self.issue(
+                IssueKind::InvalidLength,
+                format!(
+                    "BGP4MP record length {} exceeds {} bytes",
+                    body.len(),
+                    MAX_BGP4MP_LENGTH
+                ),
+            );
+        }
+
+        let message = match parse_mrt_body(entry_type, entry_subtype, body) {
+            Ok(message) => message,
+            Err(ParserError::Unsupported(e)) => {
+                self.issue(IssueKind::UnsupportedRecord, e);
+                return;
+            }
+            Err(e) => {
+                self.issue(
+                    IssueKind::ParseError,
+                    format!("{:?}/{}: {}", etype, entry_subtype, e),
+                );
+                return;
+            }
+        };
+        self.check_message(&message);
+    }
+
+    fn check_timestamp(&mut self, timestamp: u32, microseconds: u32) {
+        if let Some((last, last_microseconds)) = self.last_timestamp {
+            if (timestamp, microseconds) < (last, last_microseconds) {
+                self.issue(
+                    IssueKind::TimestampOutOfOrder,
+                    format!(
+                        "timestamp {}.{:06} is earlier than the previous {}.{:06}",
+                        timestamp, microseconds, last, last_microseconds
+                    ),
+                );
+            }
+        }
+        self.last_timestamp = Some((timestamp, microseconds));
+    }
+
+    fn check_message(&mut self, message: &MrtMessage) {
+        match message {
+            MrtMessage::TableDumpMessage(msg) => {
+                for warning in check_attributes(&msg.attributes, true, false) {
+                    self.issue(IssueKind::AttributeWarning, warning);
+                }
+            }
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::PeerIndexTable(table)) => {
+                self.peer_indexes = Some(table.id_peer_map.keys().copied().collect());
+            }
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibAfi(entries)) => {
+                self.check_rib_entries(&entries.rib_entries);
+            }
+            MrtMessage::TableDumpV2Message(TableDumpV2Message::RibGeneric(entries)) => {
+                self.check_rib_entries(&entries.rib_entries);
+            }
+            MrtMessage::TableDumpV2Message(_) => {}
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(msg)) => {
+                if let BgpMessage::Update(update) = &msg.bgp_message {
+                    let announcement = !update.announced_prefixes.is_empty()
+                        || update.attributes.has_attr(AttrType::MP_REACHABLE_NLRI);
+                    let ipv4_nlri = update
+                        .announced_prefixes
+                        .iter()
+                        .any(|p| p.prefix.addr().is_ipv4());
+                    for warning in check_attributes(&update.attributes, announcement, ipv4_nlri) {
+                        self.issue(IssueKind::AttributeWarning, warning);
+                    }
+                }
+            }
+            MrtMessage::Bgp4Mp(_) => {}
+        }
+    }
+
+    fn check_rib_entries(&mut self, entries: &[RibEntry]) {
+        let peers = match &self.peer_indexes {
+            Some(peers) => peers.len(),
+            None => {
+                self.issue(
+                    IssueKind::MissingPeerIndexTable,
+                    "RIB entries before any peer index table".to_string(),
+                );
+                0
+            }
+        };
+        for (i, entry) in entries.iter().enumerate() {
+            let known = match &self.peer_indexes {
+                Some(peer_indexes) => peer_indexes.contains(&entry.peer_index),
+                None => true,
+            };
+            if !known {
+                self.issue(
+                    IssueKind::PeerIndexMismatch,
+                    format!(
+                        "RIB entry {} references peer index {}, but the peer index table has {} peers",
+                        i, entry.peer_index, peers
+                    ),
+                );
+            }
+            for warning in check_attributes(&entry.attributes, true, false) {
+                self.issue(
+                    IssueKind::AttributeWarning,
+                    format!("RIB entry {}: {}", i, warning),
+                );
+            }
+        }
+    }
+}
+
+/// Read until the buffer is full or the end of the data, returning the number of bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// Check a set of path attributes, returning a warning for each problem found.
+///
+/// Attributes are checked for repeated types and for flags not matching their type. For
+/// announcements, the mandatory `ORIGIN` and `AS_PATH` attributes must be present, and `NEXT_HOP`
+/// as well if IPv4 prefixes are announced outside of `MP_REACH_NLRI`.
+pub fn check_attributes(
+    attributes: &Attributes,
+    announcement: bool,
+    ipv4_nlri: bool,
+) -> Vec<String> {
+    let mut warnings = vec![];
+    let mut seen = HashSet::new();
+    let mut repeated = HashSet::new();
+    for attr in attributes.inner.iter() {
+        let attr_type = attr.value.attr_type();
+        if !seen.insert(attr_type) && repeated.insert(attr_type) {
+            warnings.push(format!("repeated {:?} attribute", attr_type));
+        }
+        let flags = attr.flag.difference(AttrFlags::EXTENDED);
+        if flags != attr.normalized_flags() {
+            warnings.push(format!(
+                "{:?} attribute flags {:#04x} do not match its type",
+                attr_type,
+                attr.flag.bits()
+            ));
+        }
+    }
+
+    if announcement {
+        let mut mandatory = vec![AttrType::ORIGIN, AttrType::AS_PATH];
+        if ipv4_nlri {
+            mandatory.push(AttrType::NEXT_HOP);
+        }
+        for attr_type in mandatory {
+            if !seen.contains(&attr_type) {
+                warnings.push(format!("missing mandatory {:?} attribute", attr_type));
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_vectors;
+    use std::io::Cursor;
+
+    fn updates() -> Vec<Vec<u8>> {
+        test_vectors::mrt_updates()
+            .into_iter()
+            .map(|v| v.bytes)
+            .collect()
+    }
+
+    fn check(bytes: &[u8]) -> ValidationReport {
+        check_reader(&mut Cursor::new(bytes))
+    }
+
+    #[test]
+    fn test_valid_data() {
+        let mut bytes = updates().concat();
+        bytes.extend(test_vectors::mrt_ribs().remove(0).bytes);
+        let report = check(&bytes);
+        assert_eq!(report.records, 5);
+        assert_eq!(report.bytes, bytes.len() as u64);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_truncated_and_invalid_length() {
+        let mut bytes = updates().concat();
+        let truncated_at = bytes.len() - updates()[2].len();
+        bytes.truncate(bytes.len() - 3);
+        let report = check(&bytes);
+        assert_eq!(report.records, 3);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::TruncatedRecord);
+        assert_eq!(report.issues[0].offset, truncated_at as u64);
+        assert!(!report.is_valid());
+
+        // extended timestamp record too short for the microsecond timestamp, then a valid record
+        let mut bytes = vec![0, 0, 0, 0, 0, 17, 0, 4, 0, 0, 0, 2, 0, 0];
+        bytes.extend(&updates()[0]);
+        let report = check(&bytes);
+        assert_eq!(report.records, 2);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::InvalidLength);
+        assert_eq!(report.issues[0].offset, 0);
+    }
+
+    #[test]
+    fn test_timestamp_out_of_order() {
+        let updates = updates();
+        let mut second = updates[1].clone();
+        second[2] -= 1;
+        let bytes = [updates[0].clone(), second, updates[2].clone()].concat();
+        let report = check(&bytes);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::TimestampOutOfOrder);
+        assert_eq!(report.issues[0].offset, updates[0].len() as u64);
+        assert_eq!(report.issues[0].record, 1);
+        // out-of-order timestamps are only warnings
+        assert!(report.is_valid());
+        assert_eq!(report.warnings(), 1);
+    }
+
+    #[test]
+    fn test_peer_index_mismatch() {
+        let rib = test_vectors::mrt_ribs().remove(0).bytes;
+        let rib_offset = 12 + u32::from_be_bytes([rib[8], rib[9], rib[10], rib[11]]) as usize;
+        let mut bytes = rib.clone();
+        // peer index of the first RIB entry, after sequence number, prefix and entry count
+        bytes[rib_offset + 12 + 11] = 5;
+        let report = check(&bytes);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::PeerIndexMismatch);
+        assert_eq!(report.issues[0].offset, rib_offset as u64);
+        assert!(!report.is_valid());
+
+        let report = check(&rib[rib_offset..]);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::MissingPeerIndexTable);
+    }
+
+    #[test]
+    fn test_check_attributes() {
+        let mut attributes = Attributes::default();
+        attributes.add_attr(Attribute::from(AttributeValue::Origin(Origin::IGP)));
+        attributes.add_attr(Attribute::from(AttributeValue::Origin(Origin::EGP)));
+        attributes.add_attr(Attribute {
+            value: AttributeValue::MultiExitDiscriminator(10),
+            flag: AttrFlags::TRANSITIVE,
+        });
+        assert_eq!(
+            check_attributes(&attributes, true, true),
+            vec![
+                "repeated ORIGIN attribute".to_string(),
+                "MULTI_EXIT_DISCRIMINATOR attribute flags 0x40 do not match its type".to_string(),
+                "missing mandatory AS_PATH attribute".to_string(),
+                "missing mandatory NEXT_HOP attribute".to_string(),
+            ]
+        );
+        assert_eq!(check_attributes(&attributes, false, false).len(), 2);
+    }
+}
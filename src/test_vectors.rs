@@ -0,0 +1,278 @@
+/*!
+Canonical MRT and BMP inputs with their expected parsed elems.
+
+The vectors are small hand-written messages covering BGP4MP updates and withdrawals, table dump
+v2 RIB entries, BMP route monitoring messages, and regular and large communities. The parser's
+own tests check every vector, so downstream crates can use them to validate their integration
+against the same fixtures, e.g. after feeding the bytes through their own transport or storage.
+
+```
+use bgpkit_parser::test_vectors;
+use bgpkit_parser::{BgpElem, BgpkitParser};
+
+for vector in test_vectors::mrt_updates() {
+    let elems: Vec<BgpElem> = BgpkitParser::from_bytes(vector.bytes).into_elem_iter().collect();
+    assert_eq!(elems, vector.expected, "{}", vector.name);
+}
+```
+*/
+use crate::models::*;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+
+/// Input bytes and their expected parsed value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestVector<T> {
+    /// Short name to identify the vector in test output.
+    pub name: &'static str,
+    pub bytes: Vec<u8>,
+    pub expected: T,
+}
+
+/// Timestamp of all MRT records.
+const TIMESTAMP: u32 = 1700000000;
+const PEER_ASN: u32 = 64496;
+const PEER_IP: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+const LOCAL_ASN: u32 = 64511;
+const LOCAL_IP: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 254);
+
+/// Path attributes of the announcements, with 4-byte AS numbers.
+#[rustfmt::skip]
+const ATTRIBUTES: &[u8] = &[
+    // ORIGIN: IGP
+    0x40, 1, 1, 0,
+    // AS_PATH: AS_SEQUENCE 64496 64497
+    0x40, 2, 10, 2, 2, 0, 0, 0xFB, 0xF0, 0, 0, 0xFB, 0xF1,
+    // NEXT_HOP: 192.0.2.1
+    0x40, 3, 4, 192, 0, 2, 1,
+    // MULTI_EXIT_DISC: 10
+    0x80, 4, 4, 0, 0, 0, 10,
+    // LOCAL_PREF: 100
+    0x40, 5, 4, 0, 0, 0, 100,
+];
+
+/// COMMUNITIES and LARGE_COMMUNITIES attributes.
+#[rustfmt::skip]
+const COMMUNITY_ATTRIBUTES: &[u8] = &[
+    // COMMUNITIES: 64496:100 NO_EXPORT
+    0xC0, 8, 8, 0xFB, 0xF0, 0, 100, 0xFF, 0xFF, 0xFF, 0x01,
+    // LARGE_COMMUNITIES: 64496:1:2
+    0xC0, 32, 12, 0, 0, 0xFB, 0xF0, 0, 0, 0, 1, 0, 0, 0, 2,
+];
+
+/// NLRI of 10.0.0.0/8 and 192.0.2.0/24.
+const NLRI: &[u8] = &[8, 10, 24, 192, 0, 2];
+
+fn mrt_record(entry_type: u16, subtype: u16, body: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes.extend(TIMESTAMP.to_be_bytes());
+    bytes.extend(entry_type.to_be_bytes());
+    bytes.extend(subtype.to_be_bytes());
+    bytes.extend((body.len() as u32).to_be_bytes());
+    bytes.extend(body);
+    bytes
+}
+
+fn bgp_update(withdrawn: &[u8], attributes: &[u8], nlri: &[u8]) -> Vec<u8> {
+    let length = 19 + 2 + withdrawn.len() + 2 + attributes.len() + nlri.len();
+    let mut bytes = vec![0xFF; 16];
+    bytes.extend((length as u16).to_be_bytes());
+    bytes.push(2);
+    bytes.extend((withdrawn.len() as u16).to_be_bytes());
+    bytes.extend(withdrawn);
+    bytes.extend((attributes.len() as u16).to_be_bytes());
+    bytes.extend(attributes);
+    bytes.extend(nlri);
+    bytes
+}
+
+/// `BGP4MP_MESSAGE_AS4` record of an IPv4 session.
+fn bgp4mp_message_as4(bgp_message: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    body.extend(PEER_ASN.to_be_bytes());
+    body.extend(LOCAL_ASN.to_be_bytes());
+    // interface index and AFI
+    body.extend([0, 0, 0, 1]);
+    body.extend(PEER_IP.octets());
+    body.extend(LOCAL_IP.octets());
+    body.extend(bgp_message);
+    mrt_record(16, 4, &body)
+}
+
+fn prefix(s: &str) -> NetworkPrefix {
+    NetworkPrefix::from_str(s).unwrap()
+}
+
+/// Announcement of the prefix with [ATTRIBUTES].
+fn announcement(timestamp: f64, prefix: NetworkPrefix) -> BgpElem {
+    BgpElem {
+        timestamp,
+        elem_type: ElemType::ANNOUNCE,
+        peer_ip: IpAddr::V4(PEER_IP),
+        peer_asn: PEER_ASN.into(),
+        prefix,
+        next_hop: Some(IpAddr::V4(PEER_IP)),
+        as_path: Some(AsPath::from_sequence([64496, 64497])),
+        origin_asns: Some(AsnSet::from(vec![Asn::new_32bit(64497)])),
+        origin: Some(Origin::IGP),
+        local_pref: Some(100),
+        med: Some(10),
+        ..Default::default()
+    }
+}
+
+fn communities() -> Vec<MetaCommunity> {
+    vec![
+        MetaCommunity::Plain(Community::Custom(Asn::new_16bit(64496), 100)),
+        MetaCommunity::Plain(Community::NoExport),
+        MetaCommunity::Large(LargeCommunity::new(64496, [1, 2])),
+    ]
+}
+
+/// BGP4MP records of BGP UPDATE messages, with the elems of each record.
+pub fn mrt_updates() -> Vec<TestVector<Vec<BgpElem>>> {
+    let timestamp = TIMESTAMP as f64;
+    let withdrawal = BgpElem {
+        timestamp,
+        elem_type: ElemType::WITHDRAW,
+        peer_ip: IpAddr::V4(PEER_IP),
+        peer_asn: PEER_ASN.into(),
+        prefix: prefix("10.0.0.0/8"),
+        next_hop: None,
+        ..Default::default()
+    };
+    let all_attributes = [ATTRIBUTES, COMMUNITY_ATTRIBUTES].concat();
+    vec![
+        TestVector {
+            name: "bgp4mp-update-announce",
+            bytes: bgp4mp_message_as4(&bgp_update(&[], ATTRIBUTES, NLRI)),
+            expected: vec![
+                announcement(timestamp, prefix("10.0.0.0/8")),
+                announcement(timestamp, prefix("192.0.2.0/24")),
+            ],
+        },
+        TestVector {
+            name: "bgp4mp-update-withdraw",
+            bytes: bgp4mp_message_as4(&bgp_update(&[8, 10], &[], &[])),
+            expected: vec![withdrawal],
+        },
+        TestVector {
+            name: "bgp4mp-update-communities",
+            bytes: bgp4mp_message_as4(&bgp_update(&[], &all_attributes, &NLRI[2..])),
+            expected: vec![BgpElem {
+                communities: Some(communities()),
+                ..announcement(timestamp, prefix("192.0.2.0/24"))
+            }],
+        },
+    ]
+}
+
+/// Table dump v2 RIB dumps, i.e. a peer index table followed by RIB records, with the elems of
+/// each dump.
+pub fn mrt_ribs() -> Vec<TestVector<Vec<BgpElem>>> {
+    let mut peer_index_table = vec![];
+    peer_index_table.extend(LOCAL_IP.octets());
+    // empty view name and one peer
+    peer_index_table.extend([0, 0, 0, 1]);
+    // peer type: IPv4 address and 4-byte AS number
+    peer_index_table.push(0x02);
+    peer_index_table.extend(PEER_IP.octets());
+    peer_index_table.extend(PEER_IP.octets());
+    peer_index_table.extend(PEER_ASN.to_be_bytes());
+
+    let mut rib = vec![];
+    // sequence number
+    rib.extend(0u32.to_be_bytes());
+    rib.extend([24, 192, 0, 2]);
+    // one entry, from the first peer
+    rib.extend([0, 1, 0, 0]);
+    rib.extend(TIMESTAMP.to_be_bytes());
+    rib.extend((ATTRIBUTES.len() as u16).to_be_bytes());
+    rib.extend(ATTRIBUTES);
+
+    let bytes = [
+        mrt_record(13, 1, &peer_index_table),
+        mrt_record(13, 2, &rib),
+    ]
+    .concat();
+    vec![TestVector {
+        name: "table-dump-v2-rib-ipv4-unicast",
+        bytes,
+        expected: vec![announcement(TIMESTAMP as f64, prefix("192.0.2.0/24"))],
+    }]
+}
+
+/// BMP route monitoring messages, with the elems of the BGP UPDATE message of each, converted
+/// with [Elementor::bgp_to_elems](crate::Elementor::bgp_to_elems) using the peer of the per-peer
+/// header, and the RIB side and policy of the per-peer header.
+pub fn bmp_route_monitoring() -> Vec<TestVector<Vec<BgpElem>>> {
+    let bgp_message = bgp_update(&[], ATTRIBUTES, NLRI);
+    let mut bytes = vec![3];
+    bytes.extend(((6 + 42 + bgp_message.len()) as u32).to_be_bytes());
+    bytes.push(0);
+    // per-peer header: global instance peer, IPv4 address and 4-byte AS number
+    bytes.extend([0; 2 + 8 + 12]);
+    bytes.extend(PEER_IP.octets());
+    bytes.extend(PEER_ASN.to_be_bytes());
+    bytes.extend(PEER_IP.octets());
+    bytes.extend(TIMESTAMP.to_be_bytes());
+    bytes.extend(500000u32.to_be_bytes());
+    bytes.extend(bgp_message);
+
+    let timestamp = TIMESTAMP as f64 + 0.5;
+    let bmp_announcement = |prefix| BgpElem {
+        rib_side: Some(RibSide::AdjRibIn),
+        policy: Some(RibPolicy::Pre),
+        ..announcement(timestamp, prefix)
+    };
+    vec![TestVector {
+        name: "bmp-route-monitoring",
+        bytes,
+        expected: vec![
+            bmp_announcement(prefix("10.0.0.0/8")),
+            bmp_announcement(prefix("192.0.2.0/24")),
+        ],
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::bmp::messages::BmpMessageBody;
+    use crate::{parse_bmp_msg, BgpkitParser, Elementor};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_mrt_vectors() {
+        for vector in mrt_updates().into_iter().chain(mrt_ribs()) {
+            let elems: Vec<BgpElem> = BgpkitParser::from_bytes(vector.bytes)
+                .into_elem_iter()
+                .collect();
+            assert_eq!(elems, vector.expected, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_bmp_vectors() {
+        for vector in bmp_route_monitoring() {
+            let mut data = Bytes::from(vector.bytes);
+            let msg = parse_bmp_msg(&mut data).unwrap();
+            assert!(data.is_empty(), "{}", vector.name);
+            let peer = msg.per_peer_header.unwrap();
+            let mut elems = match msg.message_body {
+                BmpMessageBody::RouteMonitoring(m) => Elementor::bgp_to_elems(
+                    m.bgp_message,
+                    peer.timestamp,
+                    &peer.peer_ip,
+                    &peer.peer_asn,
+                ),
+                _ => panic!("{}: not a route monitoring message", vector.name),
+            };
+            for elem in &mut elems {
+                elem.rib_side = Some(peer.rib_side());
+                elem.policy = Some(peer.policy());
+            }
+            assert_eq!(elems, vector.expected, "{}", vector.name);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! Property-based round-trip tests.
+//!
+//! Randomly generated BGP UPDATE messages and BGP4MP MRT records are encoded, parsed back, and
+//! compared with the original values. Attribute flags are compared without the extended length
+//! bit, as the encoder sets it whenever an attribute value is longer than 255 bytes.
+//!
+//! Updates carry IPv4 NLRI and optionally IPv6 `MP_REACH_NLRI`/`MP_UNREACH_NLRI`. AS paths hold
+//! both 2-byte and 4-byte ASNs; with 2-byte ASN encoding, 4-byte ASNs are sent as `AS_TRANS` and
+//! the full path as `AS4_PATH`, which the elementor merges back.
+use bgpkit_parser::models::*;
+use bgpkit_parser::parse_mrt_record;
+use bgpkit_parser::parser::bgp::messages::parse_bgp_update_message;
+use bgpkit_parser::Elementor;
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use proptest::prelude::*;
+use std::io::Cursor;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+fn asn_len_strategy() -> impl Strategy<Value = AsnLength> {
+    prop_oneof![Just(AsnLength::Bits16), Just(AsnLength::Bits32)]
+}
+
+fn asn_strategy(asn_len: AsnLength) -> BoxedStrategy<Asn> {
+    match asn_len {
+        AsnLength::Bits16 => any::<u16>().prop_map(Asn::new_16bit).boxed(),
+        AsnLength::Bits32 => any::<u32>().prop_map(Asn::new_32bit).boxed(),
+    }
+}
+
+/// IPv4 prefixes other than the default route, whose zero length the parser takes as a hint of
+/// ADD-PATH NLRI in messages without ADD-PATH.
+fn ipv4_prefix_strategy() -> impl Strategy<Value = NetworkPrefix> {
+    (any::<u32>(), 1u8..=32).prop_map(|(addr, len)| {
+        let net = Ipv4Net::new(Ipv4Addr::from(addr), len).unwrap().trunc();
+        NetworkPrefix::new(IpNet::V4(net), 0)
+    })
+}
+
+/// IPv6 prefixes other than the default route, see [ipv4_prefix_strategy].
+fn ipv6_prefix_strategy() -> impl Strategy<Value = NetworkPrefix> {
+    (any::<u128>(), 1u8..=128).prop_map(|(addr, len)| {
+        let net = Ipv6Net::new(Ipv6Addr::from(addr), len).unwrap().trunc();
+        NetworkPrefix::new(IpNet::V6(net), 0)
+    })
+}
+
+fn ipv6_next_hop_strategy() -> impl Strategy<Value = NextHopAddress> {
+    prop_oneof![
+        any::<u128>().prop_map(|addr| NextHopAddress::Ipv6(Ipv6Addr::from(addr))),
+        (any::<u128>(), any::<u128>()).prop_map(|(global, link_local)| {
+            NextHopAddress::Ipv6LinkLocal(Ipv6Addr::from(global), Ipv6Addr::from(link_local))
+        }),
+    ]
+}
+
+/// AS paths of 2-byte and 4-byte ASNs.
+fn as_path_strategy() -> impl Strategy<Value = AsPath> {
+    prop::collection::vec(
+        (
+            any::<bool>(),
+            prop::collection::vec(
+                prop_oneof![
+                    asn_strategy(AsnLength::Bits16),
+                    asn_strategy(AsnLength::Bits32)
+                ],
+                1..20,
+            ),
+        ),
+        0..5,
+    )
+    .prop_map(|segments| AsPath {
+        segments: segments
+            .into_iter()
+            .map(|(is_set, asns)| match is_set {
+                true => AsPathSegment::AsSet(asns),
+                false => AsPathSegment::AsSequence(asns),
+            })
+            .collect(),
+    })
+}
+
+/// The AS path sent with 2-byte ASNs, with 4-byte ASNs replaced by `AS_TRANS` (RFC 6793).
+fn as_trans_path(path: &AsPath) -> AsPath {
+    let as_trans = |asns: &Vec<Asn>| -> Vec<Asn> {
+        asns.iter()
+            .map(|asn| match asn.to_u32() > u16::MAX as u32 {
+                true => Asn::TRANSITION,
+                false => Asn::new_16bit(asn.to_u32() as u16),
+            })
+            .collect()
+    };
+    AsPath {
+        segments: path
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                AsPathSegment::AsSet(asns) => AsPathSegment::AsSet(as_trans(asns)),
+                AsPathSegment::AsSequence(asns) => AsPathSegment::AsSequence(as_trans(asns)),
+                _ => unreachable!("no confederation segments are generated"),
+            })
+            .collect(),
+    }
+}
+
+/// Map a raw community value the same way the parser does.
+fn community_from_u32(value: u32) -> Community {
+    match value {
+        0xFFFFFF01 => Community::NoExport,
+        0xFFFFFF02 => Community::NoAdvertise,
+        0xFFFFFF03 => Community::NoExportSubConfed,
+        v => Community::Custom(Asn::new_16bit((v >> 16) as u16), v as u16),
+    }
+}
+
+fn attributes_strategy(asn_len: AsnLength) -> impl Strategy<Value = Attributes> {
+    (
+        prop::sample::select(vec![Origin::IGP, Origin::EGP, Origin::INCOMPLETE]),
+        as_path_strategy(),
+        any::<u32>(),
+        prop::option::of(any::<u32>()),
+        prop::option::of(any::<u32>()),
+        any::<bool>(),
+        prop::option::of((asn_strategy(asn_len), any::<u32>())),
+        // up to 100 communities, so that the value may exceed 255 bytes
+        prop::option::of(prop::collection::vec(any::<u32>(), 1..100)),
+        prop::option::of(prop::collection::vec(any::<[u32; 3]>(), 1..20)),
+        prop::option::of((
+            ipv6_next_hop_strategy(),
+            prop::collection::vec(ipv6_prefix_strategy(), 1..20),
+        )),
+        prop::option::of(prop::collection::vec(ipv6_prefix_strategy(), 0..20)),
+    )
+        .prop_map(
+            move |(
+                origin,
+                as_path,
+                next_hop,
+                med,
+                local_pref,
+                atomic,
+                aggregator,
+                communities,
+                large_communities,
+                mp_reach,
+                mp_unreach,
+            )| {
+                // 2-byte ASN speakers receive 4-byte ASNs as AS_TRANS, and the full path in AS4_PATH
+                let (path, as4_path) = match asn_len {
+                    AsnLength::Bits16 => {
+                        let path = as_trans_path(&as_path);
+                        let as4_path = (path != as_path).then_some(as_path);
+                        (path, as4_path)
+                    }
+                    AsnLength::Bits32 => (as_path, None),
+                };
+                let mut values = vec![
+                    AttributeValue::Origin(origin),
+                    AttributeValue::AsPath {
+                        path,
+                        is_as4: false,
+                    },
+                    AttributeValue::NextHop(IpAddr::V4(Ipv4Addr::from(next_hop))),
+                ];
+                if let Some(med) = med {
+                    values.push(AttributeValue::MultiExitDiscriminator(med));
+                }
+                if let Some(local_pref) = local_pref {
+                    values.push(AttributeValue::LocalPreference(local_pref));
+                }
+                if atomic {
+                    values.push(AttributeValue::AtomicAggregate);
+                }
+                if let Some((asn, id)) = aggregator {
+                    values.push(AttributeValue::Aggregator {
+                        asn,
+                        id: BgpIdentifier::from(id),
+                        is_as4: false,
+                    });
+                }
+                if let Some(communities) = communities {
+                    values.push(AttributeValue::Communities(
+                        communities.into_iter().map(community_from_u32).collect(),
+                    ));
+                }
+                if let Some(large_communities) = large_communities {
+                    values.push(AttributeValue::LargeCommunities(
+                        large_communities
+                            .into_iter()
+                            .map(|[global_admin, data1, data2]| LargeCommunity {
+                                global_admin,
+                                local_data: [data1, data2],
+                            })
+                            .collect(),
+                    ));
+                }
+                if let Some((next_hop, prefixes)) = mp_reach {
+                    values.push(AttributeValue::MpReachNlri(Nlri {
+                        afi: Afi::Ipv6,
+                        safi: Safi::Unicast,
+                        next_hop: Some(next_hop),
+                        prefixes,
+                    }));
+                }
+                if let Some(prefixes) = mp_unreach {
+                    values.push(AttributeValue::MpUnreachNlri(Nlri {
+                        afi: Afi::Ipv6,
+                        safi: Safi::Unicast,
+                        next_hop: None,
+                        prefixes,
+                    }));
+                }
+                if let Some(path) = as4_path {
+                    values.push(AttributeValue::AsPath { path, is_as4: true });
+                }
+                Attributes::from(values.into_iter().map(Attribute::from).collect::<Vec<_>>())
+            },
+        )
+}
+
+fn update_strategy(asn_len: AsnLength) -> impl Strategy<Value = BgpUpdateMessage> {
+    (
+        prop::collection::vec(ipv4_prefix_strategy(), 0..20),
+        attributes_strategy(asn_len),
+        prop::collection::vec(ipv4_prefix_strategy(), 0..20),
+    )
+        .prop_map(
+            |(withdrawn_prefixes, attributes, announced_prefixes)| BgpUpdateMessage {
+                withdrawn_prefixes,
+                attributes,
+                announced_prefixes,
+            },
+        )
+}
+
+fn bgp4mp_record_strategy() -> impl Strategy<Value = MrtRecord> {
+    asn_len_strategy()
+        .prop_flat_map(|asn_len| {
+            (
+                Just(asn_len),
+                any::<u32>(),
+                asn_strategy(asn_len),
+                asn_strategy(asn_len),
+                any::<u16>(),
+                any::<u32>(),
+                any::<u32>(),
+                update_strategy(asn_len),
+            )
+        })
+        .prop_map(
+            |(
+                asn_len,
+                timestamp,
+                peer_asn,
+                local_asn,
+                interface_index,
+                peer_ip,
+                local_ip,
+                update,
+            )| {
+                let msg_type = match asn_len {
+                    AsnLength::Bits16 => Bgp4MpType::Message,
+                    AsnLength::Bits32 => Bgp4MpType::MessageAs4,
+                };
+                let message = MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                    msg_type,
+                    peer_asn,
+                    local_asn,
+                    interface_index,
+                    peer_ip: IpAddr::V4(Ipv4Addr::from(peer_ip)),
+                    local_ip: IpAddr::V4(Ipv4Addr::from(local_ip)),
+                    bgp_message: BgpMessage::Update(update),
+                }));
+                let length = message.encode(msg_type as u16).len() as u32;
+                MrtRecord {
+                    common_header: CommonHeader {
+                        timestamp,
+                        microsecond_timestamp: None,
+                        entry_type: EntryType::BGP4MP,
+                        entry_subtype: msg_type as u16,
+                        length,
+                    },
+                    message,
+                }
+            },
+        )
+}
+
+/// Attribute flags and values, ignoring the extended length flag.
+fn normalized_attributes(attributes: &Attributes) -> Vec<(AttrFlags, AttributeValue)> {
+    attributes
+        .clone()
+        .into_attributes_iter()
+        .map(|attr| (attr.flag.difference(AttrFlags::EXTENDED), attr.value))
+        .collect()
+}
+
+fn assert_update_eq(
+    parsed: &BgpUpdateMessage,
+    original: &BgpUpdateMessage,
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!(&parsed.withdrawn_prefixes, &original.withdrawn_prefixes);
+    prop_assert_eq!(&parsed.announced_prefixes, &original.announced_prefixes);
+    prop_assert_eq!(
+        normalized_attributes(&parsed.attributes),
+        normalized_attributes(&original.attributes)
+    );
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_update_roundtrip(
+        (asn_len, update) in asn_len_strategy()
+            .prop_flat_map(|asn_len| (Just(asn_len), update_strategy(asn_len)))
+    ) {
+        let bytes = update.encode(false, asn_len);
+        let parsed = parse_bgp_update_message(bytes, false, &asn_len).unwrap();
+        assert_update_eq(&parsed, &update)?;
+    }
+
+    #[test]
+    fn test_bgp4mp_record_roundtrip(record in bgp4mp_record_strategy()) {
+        let parsed = parse_mrt_record(&mut Cursor::new(record.encode())).unwrap();
+        prop_assert_eq!(parsed.common_header, record.common_header);
+        match (&parsed.message, &record.message) {
+            (
+                MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(parsed)),
+                MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(original)),
+            ) => {
+                prop_assert_eq!(parsed.msg_type, original.msg_type);
+                prop_assert_eq!(parsed.peer_asn, original.peer_asn);
+                prop_assert_eq!(parsed.local_asn, original.local_asn);
+                prop_assert_eq!(parsed.interface_index, original.interface_index);
+                prop_assert_eq!(parsed.peer_ip, original.peer_ip);
+                prop_assert_eq!(parsed.local_ip, original.local_ip);
+                match (&parsed.bgp_message, &original.bgp_message) {
+                    (BgpMessage::Update(parsed), BgpMessage::Update(original)) => {
+                        assert_update_eq(parsed, original)?;
+                    }
+                    _ => prop_assert!(false, "expected a BGP UPDATE message"),
+                }
+            }
+            _ => prop_assert!(false, "expected a BGP4MP message"),
+        }
+    }
+
+    #[test]
+    fn test_bgp4mp_record_elems(record in bgp4mp_record_strategy()) {
+        let update = match &record.message {
+            MrtMessage::Bgp4Mp(Bgp4MpEnum::Message(Bgp4MpMessage {
+                bgp_message: BgpMessage::Update(update),
+                ..
+            })) => update.clone(),
+            _ => unreachable!(),
+        };
+        // the AS4_PATH if present, as generated from the 4-byte ASN path
+        let mut as_path = None;
+        let (mut announced, mut withdrawn) =
+            (update.announced_prefixes.len(), update.withdrawn_prefixes.len());
+        for value in update.attributes.iter() {
+            match value {
+                AttributeValue::AsPath { path, is_as4 } if *is_as4 || as_path.is_none() => {
+                    as_path = Some(path.clone());
+                }
+                AttributeValue::MpReachNlri(nlri) => announced += nlri.prefixes.len(),
+                AttributeValue::MpUnreachNlri(nlri) => withdrawn += nlri.prefixes.len(),
+                _ => {}
+            }
+        }
+
+        let parsed = parse_mrt_record(&mut Cursor::new(record.encode())).unwrap();
+        let elems = Elementor::new().record_to_elems(parsed);
+        prop_assert_eq!(elems.len(), announced + withdrawn);
+        let announcements: Vec<&BgpElem> =
+            elems.iter().filter(|e| e.elem_type == ElemType::ANNOUNCE).collect();
+        prop_assert_eq!(announcements.len(), announced);
+        for elem in announcements {
+            prop_assert_eq!(&elem.as_path, &as_path);
+        }
+    }
+}